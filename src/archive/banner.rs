@@ -0,0 +1,301 @@
+// archive/banner.rs from rustii (c) 2025 NinjaCheetah & Contributors
+// https://github.com/NinjaCheetah/rustii
+//
+// Implements packing and unpacking channel banners (the 00000000.app content of a channel).
+
+use std::cell::RefCell;
+#[cfg(feature = "fs")]
+use std::fs;
+#[cfg(feature = "fs")]
+use std::path::Path;
+use std::rc::Rc;
+use thiserror::Error;
+use crate::archive::{imd5, imet, lz77, u8, wrap};
+
+const BANNER_FILE_NAME: &str = "banner.bin";
+const ICON_FILE_NAME: &str = "icon.bin";
+const SOUND_FILE_NAME: &str = "sound.bin";
+
+#[derive(Debug, Error)]
+pub enum BannerError {
+    #[error("banner is missing required file `{0}`")]
+    MissingFile(String),
+    #[error("IMET header MD5 does not match (expected {expected}, got {actual})")]
+    HashMismatch { expected: String, actual: String },
+    #[error("IMET header lists a size of {expected} bytes for `{file}`, but it is {actual} bytes")]
+    SizeMismatch { file: String, expected: u32, actual: usize },
+    #[error("IMET processing error")]
+    IMET(#[from] imet::IMETError),
+    #[error("IMD5 processing error")]
+    IMD5(#[from] imd5::IMD5Error),
+    #[error("LZ77 processing error")]
+    LZ77(#[from] lz77::LZ77Error),
+    #[error("U8 processing error")]
+    U8(#[from] u8::U8Error),
+    #[error("banner data is not in a valid format")]
+    IO(#[from] std::io::Error),
+}
+
+// Wrapper errors are flattened into the IMD5 and LZ77 errors, since those are the only wrappers
+// used in a banner. Files are only decompressed after their LZ77 magic number has been found, so
+// the only wrapper that can be missing is the IMD5 header.
+impl From<wrap::WrapError> for BannerError {
+    fn from(err: wrap::WrapError) -> Self {
+        match err {
+            wrap::WrapError::IMD5(err) => BannerError::IMD5(err),
+            wrap::WrapError::LZ77(err) => BannerError::LZ77(err),
+            wrap::WrapError::NotWrapped(_) => BannerError::IMD5(imd5::IMD5Error::NotIMD5Data),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Options that control how a banner is packed.
+pub struct BannerPackOptions {
+    /// Whether to LZ77 compress sound.bin. banner.bin and icon.bin are always compressed, but
+    /// sound.bin usually isn't.
+    pub compress_sound: bool,
+    /// The options used to pack the banner and icon archives, and the archive that contains them.
+    pub u8_options: u8::U8PackOptions,
+}
+
+impl Default for BannerPackOptions {
+    fn default() -> Self {
+        BannerPackOptions { compress_sound: false, u8_options: u8::U8PackOptions::nintendo() }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A structure that represents the components of a channel's banner. The banner and icon are
+/// stored as the U8 archives inside banner.bin and icon.bin, and the sound is stored as the data
+/// inside sound.bin, all without their IMD5 headers or compression.
+pub struct Banner {
+    pub imet_header: imet::IMETHeader,
+    pub banner: u8::U8Archive,
+    pub icon: u8::U8Archive,
+    pub sound: Vec<u8>,
+}
+
+// Finds a file in the meta directory of a banner, which is where banner.bin, icon.bin, and
+// sound.bin are stored.
+fn find_meta_file(node_tree: &Rc<RefCell<u8::U8Directory>>, name: &str) -> Result<Vec<u8>, BannerError> {
+    let meta_dir = u8::U8Directory::get_child_dir(node_tree, "meta").ok_or(BannerError::MissingFile(String::from("meta")))?;
+    let meta_dir = meta_dir.borrow();
+    let file = meta_dir.files.iter().find(|file| file.borrow().name == name)
+        .ok_or(BannerError::MissingFile(format!("meta/{}", name)))?;
+    Ok(file.borrow().data.clone())
+}
+
+// Removes the IMD5 header from a file in a banner, and decompresses it if it's compressed.
+fn unwrap_file(data: &[u8]) -> Result<Vec<u8>, BannerError> {
+    let wraps: &[wrap::Wrap] = if data.get(0x20..0x24) == Some(b"LZ77") {
+        &[wrap::Wrap::Lz77, wrap::Wrap::Imd5]
+    } else {
+        &[wrap::Wrap::Imd5]
+    };
+    Ok(wrap::unwrap_chain(data, wraps)?)
+}
+
+// Compresses a file for a banner if requested, and then wraps it in an IMD5 header.
+fn wrap_file(data: &[u8], compress: bool) -> Result<Vec<u8>, BannerError> {
+    let wraps: &[wrap::Wrap] = if compress {
+        &[wrap::Wrap::Lz77, wrap::Wrap::Imd5]
+    } else {
+        &[wrap::Wrap::Imd5]
+    };
+    Ok(wrap::wrap_chain(data, wraps)?)
+}
+
+impl Banner {
+    /// Creates a new Banner instance from its banner, icon, and sound, with empty channel names.
+    pub fn new(banner: u8::U8Archive, icon: u8::U8Archive, sound: Vec<u8>) -> Self {
+        Banner {
+            imet_header: imet::IMETHeader::new([0; 3]),
+            banner,
+            icon,
+            sound,
+        }
+    }
+
+    /// Creates a new Banner instance from the binary data of a channel's banner. The MD5 hash in
+    /// the IMET header and the sizes it lists are checked against the actual files, as are the
+    /// MD5 hashes in the IMD5 header of each file.
+    pub fn unpack(data: &[u8]) -> Result<Self, BannerError> {
+        let imet_header = imet::IMETHeader::from_bytes(data)?;
+        let actual_hash = imet::header_md5(data).ok_or(imet::IMETError::NotIMETData)?;
+        if actual_hash != imet_header.md5_hash {
+            return Err(BannerError::HashMismatch { expected: hex::encode(imet_header.md5_hash), actual: hex::encode(actual_hash) });
+        }
+        let data_offset = imet::imet_data_offset(data).ok_or(imet::IMETError::NotIMETData)?;
+        let archive = u8::U8Archive::from_bytes(data.get(data_offset..).ok_or(imet::IMETError::NotIMETData)?)?;
+        // The sizes in the IMET header are stored in the order icon, banner, sound.
+        let mut files: Vec<Vec<u8>> = Vec::new();
+        for (name, expected) in [ICON_FILE_NAME, BANNER_FILE_NAME, SOUND_FILE_NAME].iter().zip(imet_header.content_sizes) {
+            let file = find_meta_file(&archive.node_tree, name)?;
+            if file.len() != expected as usize {
+                return Err(BannerError::SizeMismatch { file: name.to_string(), expected, actual: file.len() });
+            }
+            files.push(unwrap_file(&file)?);
+        }
+        Ok(Banner {
+            imet_header,
+            banner: u8::U8Archive::from_bytes(&files[1])?,
+            icon: u8::U8Archive::from_bytes(&files[0])?,
+            sound: files.remove(2),
+        })
+    }
+
+    /// Packs a Banner instance into the binary data of a channel's banner. Each file is compressed
+    /// and wrapped in an IMD5 header, and the sizes and MD5 hash in the IMET header are updated to
+    /// match.
+    pub fn pack(&self, options: &BannerPackOptions) -> Result<Vec<u8>, BannerError> {
+        let banner_bin = wrap_file(&self.banner.to_bytes_with_options(&options.u8_options)?, true)?;
+        let icon_bin = wrap_file(&self.icon.to_bytes_with_options(&options.u8_options)?, true)?;
+        let sound_bin = wrap_file(&self.sound, options.compress_sound)?;
+        let mut imet_header = self.imet_header.clone();
+        imet_header.content_sizes = [icon_bin.len() as u32, banner_bin.len() as u32, sound_bin.len() as u32];
+        let node_tree = u8::U8Directory::new(String::new());
+        let meta_dir = u8::U8Directory::new(String::from("meta"));
+        u8::U8Directory::add_dir(&node_tree, Rc::clone(&meta_dir));
+        u8::U8Directory::add_file(&meta_dir, u8::U8File::new(String::from(BANNER_FILE_NAME), banner_bin));
+        u8::U8Directory::add_file(&meta_dir, u8::U8File::new(String::from(ICON_FILE_NAME), icon_bin));
+        u8::U8Directory::add_file(&meta_dir, u8::U8File::new(String::from(SOUND_FILE_NAME), sound_bin));
+        let mut buf = imet_header.to_bytes()?;
+        buf.extend(u8::U8Archive::from_tree(&node_tree)?.to_bytes_with_options(&options.u8_options)?);
+        Ok(buf)
+    }
+
+    #[cfg(feature = "fs")]
+    /// Creates a new Banner instance from a directory, with empty channel names. The directory
+    /// must contain a "banner" and "icon" directory holding the contents of banner.bin and
+    /// icon.bin, and a sound.bin file holding the banner's sound without an IMD5 header.
+    pub fn from_dir(path: &Path) -> Result<Self, BannerError> {
+        let banner_path = path.join("banner");
+        let icon_path = path.join("icon");
+        let sound_path = path.join(SOUND_FILE_NAME);
+        for (dir, name) in [(&banner_path, "banner"), (&icon_path, "icon")] {
+            if !dir.is_dir() {
+                return Err(BannerError::MissingFile(name.to_owned()));
+            }
+        }
+        if !sound_path.is_file() {
+            return Err(BannerError::MissingFile(SOUND_FILE_NAME.to_owned()));
+        }
+        Ok(Banner::new(u8::U8Archive::from_dir(&banner_path)?, u8::U8Archive::from_dir(&icon_path)?, fs::read(sound_path)?))
+    }
+
+    #[cfg(feature = "fs")]
+    /// Writes the components of a Banner out to a directory, using the layout read by
+    /// Banner::from_dir(). The directory will be created if it doesn't already exist.
+    pub fn to_dir(&self, path: &Path) -> Result<(), BannerError> {
+        fs::create_dir_all(path)?;
+        write_dir_recursive(&self.banner.node_tree, &path.join("banner"))?;
+        write_dir_recursive(&self.icon.node_tree, &path.join("icon"))?;
+        fs::write(path.join(SOUND_FILE_NAME), &self.sound)?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "fs")]
+fn write_dir_recursive(dir: &Rc<RefCell<u8::U8Directory>>, path: &Path) -> Result<(), BannerError> {
+    fs::create_dir_all(path)?;
+    for file in &dir.borrow().files {
+        fs::write(path.join(&file.borrow().name), &file.borrow().data)?;
+    }
+    for child in &dir.borrow().dirs {
+        write_dir_recursive(child, &path.join(&child.borrow().name))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_archive(files: &[(&str, &[u8])]) -> u8::U8Archive {
+        let node_tree = u8::U8Directory::new(String::new());
+        let arc = u8::U8Directory::new(String::from("arc"));
+        u8::U8Directory::add_dir(&node_tree, Rc::clone(&arc));
+        for (dir_name, data) in files {
+            let dir = u8::U8Directory::new(dir_name.to_string());
+            u8::U8Directory::add_dir(&arc, Rc::clone(&dir));
+            u8::U8Directory::add_file(&dir, u8::U8File::new(format!("{}.bin", dir_name), data.to_vec()));
+        }
+        u8::U8Archive::from_tree(&node_tree).unwrap()
+    }
+
+    fn build_banner() -> Banner {
+        let banner = build_archive(&[("anim", &[0x01; 64]), ("blyt", &[0x02; 48]), ("timg", &[0x03; 128])]);
+        let icon = build_archive(&[("blyt", &[0x04; 48]), ("timg", &[0x05; 96])]);
+        let mut banner = Banner::new(banner, icon, b"BNS \xFE\xFF\x01\x00".repeat(8));
+        for language in imet::Language::ALL {
+            banner.imet_header.set_channel_name(language, "Test Channel").unwrap();
+        }
+        banner.imet_header.set_channel_name(imet::Language::Japanese, "テストチャンネル").unwrap();
+        banner
+    }
+
+    #[test]
+    fn test_banner_round_trip() {
+        let banner = build_banner();
+        let options = BannerPackOptions::default();
+        let data = banner.pack(&options).unwrap();
+        assert_eq!(&data[0x40..0x44], b"IMET");
+        let unpacked = Banner::unpack(&data).unwrap();
+        assert_eq!(unpacked.imet_header.channel_name(imet::Language::English), "Test Channel");
+        assert_eq!(unpacked.imet_header.channel_name(imet::Language::Japanese), "テストチャンネル");
+        let u8_options = &options.u8_options;
+        assert_eq!(unpacked.banner.to_bytes_with_options(u8_options).unwrap(), banner.banner.to_bytes_with_options(u8_options).unwrap());
+        assert_eq!(unpacked.icon.to_bytes_with_options(u8_options).unwrap(), banner.icon.to_bytes_with_options(u8_options).unwrap());
+        assert_eq!(unpacked.sound, banner.sound);
+        // Packing the unpacked banner again should produce identical data.
+        assert_eq!(unpacked.pack(&options).unwrap(), data);
+        // The banner and icon are compressed, and sound is only compressed when asked.
+        let archive = u8::U8Archive::from_bytes(&data[0x600..]).unwrap();
+        let banner_bin = find_meta_file(&archive.node_tree, BANNER_FILE_NAME).unwrap();
+        assert_eq!(&banner_bin[0x20..0x24], b"LZ77");
+        assert_eq!(&find_meta_file(&archive.node_tree, SOUND_FILE_NAME).unwrap()[0x20..0x24], b"BNS ");
+        let data = banner.pack(&BannerPackOptions { compress_sound: true, ..options }).unwrap();
+        let archive = u8::U8Archive::from_bytes(&data[0x600..]).unwrap();
+        assert_eq!(&find_meta_file(&archive.node_tree, SOUND_FILE_NAME).unwrap()[0x20..0x24], b"LZ77");
+        assert_eq!(Banner::unpack(&data).unwrap().sound, banner.sound);
+    }
+
+    #[test]
+    fn test_banner_validation() {
+        let data = build_banner().pack(&BannerPackOptions::default()).unwrap();
+        // A changed channel name should fail the IMET hash check.
+        let mut bad_data = data.clone();
+        bad_data[0x5C] ^= 0xFF;
+        assert!(matches!(Banner::unpack(&bad_data), Err(BannerError::HashMismatch { .. })));
+        // A size that doesn't match its file should fail even with a correct hash.
+        let mut bad_data = data.clone();
+        bad_data[0x4C..0x50].copy_from_slice(&1u32.to_be_bytes());
+        let md5_hash = imet::header_md5(&bad_data).unwrap();
+        bad_data[0x5F0..0x600].copy_from_slice(&md5_hash);
+        assert!(matches!(Banner::unpack(&bad_data), Err(BannerError::SizeMismatch { expected: 1, .. })));
+        // Corrupted file data should fail the IMD5 hash check.
+        let mut bad_data = data.clone();
+        let sound_offset = bad_data.windows(4).position(|window| window == b"BNS ").unwrap();
+        bad_data[sound_offset] ^= 0xFF;
+        assert!(matches!(Banner::unpack(&bad_data), Err(BannerError::IMD5(imd5::IMD5Error::HashMismatch { .. }))));
+        // Channel names have a maximum length.
+        let mut banner = build_banner();
+        assert!(matches!(banner.imet_header.set_channel_name(imet::Language::English, &"A".repeat(43)), Err(imet::IMETError::NameTooLong(43))));
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_banner_dir_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let banner = build_banner();
+        banner.to_dir(dir.path()).unwrap();
+        assert!(dir.path().join("banner/arc/timg/timg.bin").is_file());
+        let reloaded = Banner::from_dir(dir.path()).unwrap();
+        let options = BannerPackOptions::default();
+        assert_eq!(reloaded.banner.to_bytes_with_options(&options.u8_options).unwrap(), banner.banner.to_bytes_with_options(&options.u8_options).unwrap());
+        assert_eq!(reloaded.sound, banner.sound);
+        fs::remove_file(dir.path().join(SOUND_FILE_NAME)).unwrap();
+        assert!(matches!(Banner::from_dir(dir.path()), Err(BannerError::MissingFile(_))));
+    }
+}
@@ -0,0 +1,75 @@
+// archive/imd5.rs from rustii (c) 2025 NinjaCheetah & Contributors
+// https://github.com/NinjaCheetah/rustii
+//
+// Implements the IMD5 header used to wrap the files inside of a channel's banner.
+
+use std::io::{Cursor, Read};
+use byteorder::{BigEndian, ReadBytesExt};
+use md5::{Digest, Md5};
+use thiserror::Error;
+
+const IMD5_HEADER_SIZE: usize = 0x20;
+
+#[derive(Debug, Error)]
+pub enum IMD5Error {
+    #[error("this does not appear to be IMD5 data (missing magic number)")]
+    NotIMD5Data,
+    #[error("IMD5 header lists a size of {expected} bytes, but the data is {actual} bytes")]
+    SizeMismatch { expected: u32, actual: usize },
+    #[error("IMD5 data MD5 does not match (expected {expected}, got {actual})")]
+    HashMismatch { expected: String, actual: String },
+    #[error("IMD5 data is not in a valid format")]
+    IO(#[from] std::io::Error),
+}
+
+/// Wraps data in an IMD5 header, which stores the size and MD5 hash of the data that follows it.
+pub fn wrap_imd5(data: &[u8]) -> Vec<u8> {
+    let mut buf: Vec<u8> = Vec::with_capacity(IMD5_HEADER_SIZE + data.len());
+    buf.extend_from_slice(b"IMD5");
+    buf.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    buf.extend_from_slice(&[0; 8]);
+    buf.extend_from_slice(&Md5::digest(data));
+    buf.extend_from_slice(data);
+    buf
+}
+
+/// Removes the IMD5 header from the start of data and returns the data that follows it, after
+/// checking it against the size and MD5 hash stored in the header.
+pub fn unwrap_imd5(data: &[u8]) -> Result<Vec<u8>, IMD5Error> {
+    let mut buf = Cursor::new(data);
+    let mut magic = [0u8; 4];
+    buf.read_exact(&mut magic)?;
+    if &magic != b"IMD5" {
+        return Err(IMD5Error::NotIMD5Data);
+    }
+    let size = buf.read_u32::<BigEndian>()?;
+    buf.set_position(0x10);
+    let mut md5_hash = [0u8; 16];
+    buf.read_exact(&mut md5_hash)?;
+    let payload = &data[IMD5_HEADER_SIZE..];
+    if payload.len() != size as usize {
+        return Err(IMD5Error::SizeMismatch { expected: size, actual: payload.len() });
+    }
+    let actual_hash = Md5::digest(payload);
+    if actual_hash.as_slice() != md5_hash {
+        return Err(IMD5Error::HashMismatch { expected: hex::encode(md5_hash), actual: hex::encode(actual_hash) });
+    }
+    Ok(payload.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_imd5() {
+        let wrapped = wrap_imd5(b"banner data");
+        assert_eq!(&wrapped[..8], b"IMD5\x00\x00\x00\x0B");
+        assert_eq!(unwrap_imd5(&wrapped).unwrap(), b"banner data");
+        let mut bad_data = wrapped.clone();
+        bad_data[0x20] ^= 0xFF;
+        assert!(matches!(unwrap_imd5(&bad_data), Err(IMD5Error::HashMismatch { .. })));
+        assert!(matches!(unwrap_imd5(&wrapped[..0x28]), Err(IMD5Error::SizeMismatch { expected: 11, actual: 8 })));
+        assert!(matches!(unwrap_imd5(b"LZ77"), Err(IMD5Error::NotIMD5Data)));
+    }
+}
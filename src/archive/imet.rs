@@ -0,0 +1,201 @@
+// archive/imet.rs from rustii (c) 2025 NinjaCheetah & Contributors
+// https://github.com/NinjaCheetah/rustii
+//
+// Implements the structures and methods required for parsing the IMET headers found on channel
+// banners.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use byteorder::{BigEndian, ReadBytesExt};
+use md5::{Digest, Md5};
+use thiserror::Error;
+
+// The IMET header and the padding before it take up 0x600 bytes, which are all covered by the MD5
+// hash stored at the end of the header.
+const IMET_HASHED_SIZE: usize = 0x600;
+const IMET_MD5_OFFSET: usize = 0x5B0;
+const CHANNEL_NAME_LEN: usize = 42;
+
+#[derive(Debug, Error)]
+pub enum IMETError {
+    #[error("this does not appear to be IMET data (missing magic number)")]
+    NotIMETData,
+    #[error("channel names must not exceed 42 characters (was {0})")]
+    NameTooLong(usize),
+    #[error("IMET data is not in a valid format")]
+    IO(#[from] std::io::Error),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+/// The languages that a channel name can be stored in, in the order they appear in an IMET header.
+pub enum Language {
+    Japanese = 0,
+    English = 1,
+    German = 2,
+    French = 3,
+    Spanish = 4,
+    Italian = 5,
+    Dutch = 6,
+    SimplifiedChinese = 7,
+    TraditionalChinese = 8,
+    Korean = 9,
+}
+
+impl Language {
+    /// All supported languages, in the order they're stored in an IMET header.
+    pub const ALL: [Language; 10] = [
+        Language::Japanese, Language::English, Language::German, Language::French, Language::Spanish,
+        Language::Italian, Language::Dutch, Language::SimplifiedChinese, Language::TraditionalChinese,
+        Language::Korean,
+    ];
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Language::Japanese => write!(f, "Japanese"),
+            Language::English => write!(f, "English"),
+            Language::German => write!(f, "German"),
+            Language::French => write!(f, "French"),
+            Language::Spanish => write!(f, "Spanish"),
+            Language::Italian => write!(f, "Italian"),
+            Language::Dutch => write!(f, "Dutch"),
+            Language::SimplifiedChinese => write!(f, "Simplified Chinese"),
+            Language::TraditionalChinese => write!(f, "Traditional Chinese"),
+            Language::Korean => write!(f, "Korean"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A structure that represents the IMET header found at the start of a channel's banner.
+pub struct IMETHeader {
+    pub header_size: u32,
+    pub imet_version: u32,
+    pub content_sizes: [u32; 3],
+    pub flag1: u32,
+    channel_names: [String; 10],
+    pub md5_hash: [u8; 16],
+}
+
+impl IMETHeader {
+    /// Creates a new IMETHeader instance with empty channel names. The content sizes should be set
+    /// to the sizes of the icon.bin, banner.bin, and sound.bin files in the banner, in that order.
+    pub fn new(content_sizes: [u32; 3]) -> Self {
+        IMETHeader {
+            header_size: IMET_HASHED_SIZE as u32,
+            imet_version: 3,
+            content_sizes,
+            flag1: 0,
+            channel_names: Default::default(),
+            md5_hash: [0; 16],
+        }
+    }
+
+    /// Creates a new IMETHeader instance from the binary data of a channel's banner. The IMET
+    /// header may either be at the start of the data, or after a build tag.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, IMETError> {
+        let imet_offset = find_imet_offset(data).ok_or(IMETError::NotIMETData)?;
+        let mut buf = Cursor::new(data);
+        // Skip the magic number, since we've already checked it.
+        buf.seek(SeekFrom::Start(imet_offset as u64 + 4))?;
+        let header_size = buf.read_u32::<BigEndian>()?;
+        let imet_version = buf.read_u32::<BigEndian>()?;
+        let mut content_sizes = [0u32; 3];
+        for size in content_sizes.iter_mut() {
+            *size = buf.read_u32::<BigEndian>()?;
+        }
+        let flag1 = buf.read_u32::<BigEndian>()?;
+        // Each name is stored as 42 UTF-16 characters, padded with null characters.
+        let mut channel_names: [String; 10] = Default::default();
+        for name in channel_names.iter_mut() {
+            let mut name_buf = [0u16; CHANNEL_NAME_LEN];
+            buf.read_u16_into::<BigEndian>(&mut name_buf)?;
+            let name_len = name_buf.iter().position(|&c| c == 0).unwrap_or(name_buf.len());
+            *name = String::from_utf16_lossy(&name_buf[..name_len]);
+        }
+        // Skip the padding before the MD5 hash at the end of the header.
+        buf.seek(SeekFrom::Start((imet_offset + IMET_MD5_OFFSET) as u64))?;
+        let mut md5_hash = [0u8; 16];
+        buf.read_exact(&mut md5_hash)?;
+        Ok(IMETHeader {
+            header_size,
+            imet_version,
+            content_sizes,
+            flag1,
+            channel_names,
+            md5_hash,
+        })
+    }
+
+    /// Gets the name of the channel in the specified language.
+    pub fn channel_name(&self, language: Language) -> String {
+        self.channel_names[language as usize].clone()
+    }
+
+    /// Gets the names of the channel in all languages.
+    pub fn channel_names(&self) -> HashMap<Language, String> {
+        Language::ALL.iter().map(|&language| (language, self.channel_name(language))).collect()
+    }
+
+    /// Sets the name of the channel in the specified language. Names are limited to 42 UTF-16
+    /// characters.
+    pub fn set_channel_name(&mut self, language: Language, name: &str) -> Result<(), IMETError> {
+        let name_len = name.encode_utf16().count();
+        if name_len > CHANNEL_NAME_LEN {
+            return Err(IMETError::NameTooLong(name_len));
+        }
+        self.channel_names[language as usize] = name.to_owned();
+        Ok(())
+    }
+
+    /// Dumps the data in an IMETHeader instance back into binary data, including the 0x40 bytes
+    /// of padding that come before it. The MD5 hash is recalculated from the new header, rather
+    /// than using the stored one.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, IMETError> {
+        let mut buf: Vec<u8> = vec![0; 0x40];
+        buf.extend_from_slice(b"IMET");
+        buf.extend_from_slice(&self.header_size.to_be_bytes());
+        buf.extend_from_slice(&self.imet_version.to_be_bytes());
+        for size in self.content_sizes {
+            buf.extend_from_slice(&size.to_be_bytes());
+        }
+        buf.extend_from_slice(&self.flag1.to_be_bytes());
+        for name in &self.channel_names {
+            let mut encoded: Vec<u16> = name.encode_utf16().collect();
+            if encoded.len() > CHANNEL_NAME_LEN {
+                return Err(IMETError::NameTooLong(encoded.len()));
+            }
+            encoded.resize(CHANNEL_NAME_LEN, 0);
+            buf.extend(encoded.iter().flat_map(|c| c.to_be_bytes()));
+        }
+        buf.resize(IMET_HASHED_SIZE, 0);
+        let md5_hash = header_md5(&buf).ok_or(IMETError::NotIMETData)?;
+        buf[0x40 + IMET_MD5_OFFSET..0x40 + IMET_MD5_OFFSET + 16].copy_from_slice(&md5_hash);
+        Ok(buf)
+    }
+}
+
+/// Calculates the MD5 hash of the IMET header in a channel's banner, which covers the header and
+/// the 0x40 bytes before it with the stored hash zeroed out. This can be compared against the hash
+/// stored in the header to check it. Returns None if no complete IMET header could be found.
+pub fn header_md5(data: &[u8]) -> Option<[u8; 16]> {
+    let imet_offset = find_imet_offset(data)?;
+    let mut header = data.get(imet_offset - 0x40..imet_offset - 0x40 + IMET_HASHED_SIZE)?.to_vec();
+    header[0x40 + IMET_MD5_OFFSET..0x40 + IMET_MD5_OFFSET + 16].fill(0);
+    Some(Md5::digest(&header).into())
+}
+
+/// Finds the offset of the IMET header in a channel's banner, which is at 0x40 when there's no
+/// build tag, or at 0x80 when there is one. Returns None if no IMET header could be found.
+pub fn find_imet_offset(data: &[u8]) -> Option<usize> {
+    [0x40, 0x80].into_iter().find(|&offset| data.get(offset..offset + 4) == Some(b"IMET"))
+}
+
+/// Gets the offset of the data that follows the IMET header in a channel's banner. Returns None if
+/// no IMET header could be found.
+pub fn imet_data_offset(data: &[u8]) -> Option<usize> {
+    // The IMET header is 0x5C0 bytes long, and the data directly follows it.
+    find_imet_offset(data).map(|offset| offset + 0x5C0)
+}
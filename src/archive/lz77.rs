@@ -4,7 +4,7 @@
 // Implements the compression and decompression routines used for the Wii's LZ77 compression scheme.
 
 use std::cmp::min;
-use std::io::{Cursor, Read, Write, Seek, SeekFrom};
+use std::io::{Cursor, Write, Seek, SeekFrom};
 use byteorder::{BigEndian, LittleEndian, ReadBytesExt, WriteBytesExt};
 use thiserror::Error;
 
@@ -16,6 +16,10 @@ pub enum LZ77Error {
     IO(#[from] std::io::Error),
 }
 
+// The magic number that optionally comes before LZ77-compressed data, and the only compression
+// type supported on the Wii, which always follows it.
+const LZ77_MAGIC: &[u8; 4] = b"LZ77";
+const LZ77_TYPE: u8 = 0x10;
 const LZ_MIN_DISTANCE: usize = 0x01; // Minimum distance for each reference.
 const LZ_MAX_DISTANCE: usize = 0x1000; // Maximum distance for each reference.
 const LZ_MIN_LENGTH: usize = 0x03; // Minimum length for each reference.
@@ -78,8 +82,17 @@ fn compress_get_node_cost(length: usize) -> usize {
     1 + (num_bytes * 8)
 }
 
+/// Gets whether LZ77-compressed data starts with the optional "LZ77" magic number. The
+/// compression type that follows the magic number is also checked, so data that only happens to
+/// start with the same bytes isn't mistaken for having it.
+pub fn has_magic(data: &[u8]) -> bool {
+    data.starts_with(LZ77_MAGIC) && data.get(LZ77_MAGIC.len()) == Some(&LZ77_TYPE)
+}
+
 /// Compresses data using the Wii's LZ77 compression algorithm and returns the compressed result.
-pub fn compress_lz77(data: &[u8]) -> Result<Vec<u8>, LZ77Error> {
+/// The result can optionally start with the "LZ77" magic number, which standalone files usually
+/// include, but files stored inside of other files (like U8 archives) usually don't.
+pub fn compress_lz77(data: &[u8], with_magic: bool) -> Result<Vec<u8>, LZ77Error> {
     // Optimized compressor based around a node graph that finds optimal string matches.
     let mut nodes = vec![LZNode { dist: 0, len: 0, weight: 0 }; data.len()];
     // Iterate over the uncompressed data, starting from the end.
@@ -129,7 +142,10 @@ pub fn compress_lz77(data: &[u8]) -> Result<Vec<u8>, LZ77Error> {
     }
     // Write out compressed data now that we've done our calculations.
     let mut buf = Cursor::new(Vec::new());
-    buf.write_all(b"LZ77\x10")?;
+    if with_magic {
+        buf.write_all(LZ77_MAGIC)?;
+    }
+    buf.write_u8(LZ77_TYPE)?;
     buf.write_u24::<LittleEndian>(data.len() as u32)?;
     let mut src_pos = 0;
     while src_pos < data.len() {
@@ -162,20 +178,19 @@ pub fn compress_lz77(data: &[u8]) -> Result<Vec<u8>, LZ77Error> {
     Ok(buf.into_inner())
 }
 
-/// Decompresses LZ77-compressed data and returns the decompressed result.
+/// Decompresses LZ77-compressed data and returns the decompressed result. The data may or may not
+/// start with the "LZ77" magic number.
 pub fn decompress_lz77(data: &[u8]) -> Result<Vec<u8>, LZ77Error> {
     let mut buf = Cursor::new(data);
     // Check for magic so that we know where to start. If the compressed data was sourced from
     // inside of something, it may not have the magic and instead starts immediately at 0.
-    let mut magic = [0u8; 4];
-    buf.read_exact(&mut magic)?;
-    if &magic != b"LZ77" {
-        buf.seek(SeekFrom::Start(0))?;
+    if has_magic(data) {
+        buf.seek(SeekFrom::Start(LZ77_MAGIC.len() as u64))?;
     }
     // Read one byte to ensure this is compression type 0x10. Nintendo used other types, but only
     // 0x10 was supported on the Wii.
     let compression_type = buf.read_u8()?;
-    if compression_type != 0x10 {
+    if compression_type != LZ77_TYPE {
         return Err(LZ77Error::InvalidCompressionType(compression_type));
     }
     // Read the decompressed size, which is stored as 3 LE bytes for some reason.
@@ -195,11 +210,10 @@ pub fn decompress_lz77(data: &[u8]) -> Result<Vec<u8>, LZ77Error> {
             if flag & (1 << x) != 0 {
                 let reference = buf.read_u16::<BigEndian>()?;
                 let length = 3 + ((reference >> 12) & 0xF);
-                let mut offset = pos - (reference & 0xFFF) as usize - 1;
-                for _ in 0..length {
-                    out_buf[pos] = out_buf[offset];
+                let offset = pos - (reference & 0xFFF) as usize - 1;
+                for i in 0..length as usize {
+                    out_buf[pos] = out_buf[offset + i];
                     pos += 1;
-                    offset += 1;
                     // Avoids a buffer overrun if the copy length would extend past the end of the file.
                     if pos >= decompressed_size {
                         break;
@@ -216,3 +230,41 @@ pub fn decompress_lz77(data: &[u8]) -> Result<Vec<u8>, LZ77Error> {
     }
     Ok(out_buf)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lz77_magic_round_trip() {
+        let data = b"LZ77 compressed data, compressed with LZ77 compression. ".repeat(8);
+        let with_magic = compress_lz77(&data, true).unwrap();
+        let without_magic = compress_lz77(&data, false).unwrap();
+        assert!(has_magic(&with_magic));
+        assert!(!has_magic(&without_magic));
+        assert_eq!(&with_magic[4..], without_magic.as_slice());
+        assert!(without_magic.len() < data.len());
+        // Data decompresses the same way whether or not the magic number is present.
+        assert_eq!(decompress_lz77(&with_magic).unwrap(), data);
+        assert_eq!(decompress_lz77(&without_magic).unwrap(), data);
+        // Adding or removing the magic number afterward works just as well.
+        let mut added = LZ77_MAGIC.to_vec();
+        added.extend_from_slice(&without_magic);
+        assert_eq!(decompress_lz77(&added).unwrap(), data);
+        assert_eq!(decompress_lz77(&with_magic[4..]).unwrap(), data);
+        // Empty data still produces a valid header.
+        assert_eq!(decompress_lz77(&compress_lz77(&[], true).unwrap()).unwrap(), Vec::<u8>::new());
+        assert_eq!(decompress_lz77(&compress_lz77(&[], false).unwrap()).unwrap(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn test_lz77_magic_detection() {
+        // Data that starts with "LZ77" isn't treated as having the magic number unless it's
+        // followed by the compression type, so the first 4 bytes aren't skipped.
+        assert!(!has_magic(b"LZ77 text"));
+        assert!(matches!(decompress_lz77(b"LZ77 text"), Err(LZ77Error::InvalidCompressionType(0x4C))));
+        assert!(matches!(decompress_lz77(b"LZ77\x11\x00\x00\x00"), Err(LZ77Error::InvalidCompressionType(0x4C))));
+        assert!(matches!(decompress_lz77(b"LZ77"), Err(LZ77Error::InvalidCompressionType(0x4C))));
+        assert!(matches!(decompress_lz77(&[]), Err(LZ77Error::IO(_))));
+    }
+}
@@ -4,5 +4,9 @@
 // Root for all archive-related modules.
 
 pub mod ash;
+pub mod banner;
+pub mod imd5;
+pub mod imet;
 pub mod lz77;
 pub mod u8;
+pub mod wrap;
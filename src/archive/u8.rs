@@ -4,7 +4,11 @@
 // Implements the structures and methods required for parsing U8 archives.
 
 use std::cell::RefCell;
+#[cfg(feature = "fs")]
+use std::fs;
 use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+#[cfg(feature = "fs")]
+use std::path::{Path, PathBuf};
 use std::rc::{Rc, Weak};
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use thiserror::Error;
@@ -17,8 +21,19 @@ pub enum U8Error {
     InvalidNodeType(u8, usize),
     #[error("invalid file name at offset {0}")]
     InvalidFileName(u64),
+    #[error("invalid node structure at node {node_index}: {reason}")]
+    InvalidNodeStructure { node_index: usize, reason: String },
     #[error("this does not appear to be a U8 archive (missing magic number)")]
     NotU8Data,
+    #[error("data alignment must be a power of two (was {0})")]
+    InvalidAlignment(u32),
+    #[error("U8 string table is {0} bytes long, but name offsets can only address 0xFFFFFF bytes")]
+    StringTableTooLarge(u64),
+    #[error("more than one entry named `{0}` exists in the same directory")]
+    DuplicateName(String),
+    #[cfg(feature = "fs")]
+    #[error("U8 archive `{}` could not be read", path.display())]
+    ReadFile { path: PathBuf, source: std::io::Error },
     #[error("U8 data is not in a valid format")]
     IO(#[from] std::io::Error),
 }
@@ -101,6 +116,54 @@ impl U8File {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+/// The order that the entries in each directory are written in when packing a U8 archive.
+pub enum NodeSort {
+    /// Files first and then directories, each in the order they were added.
+    #[default]
+    Insertion,
+    /// Files and directories together, sorted by name without regard to case. This matches the
+    /// order used by Nintendo's tools.
+    CaseInsensitive,
+}
+
+#[derive(Debug, Clone)]
+/// Options that control how a U8 archive is packed.
+pub struct U8PackOptions {
+    /// The alignment of each file's data, which must be a power of two. Banner contents use 32
+    /// bytes, while some other archives use 64.
+    pub data_alignment: u32,
+    pub sort: NodeSort,
+}
+
+impl Default for U8PackOptions {
+    fn default() -> Self {
+        U8PackOptions { data_alignment: 32, sort: NodeSort::Insertion }
+    }
+}
+
+impl U8PackOptions {
+    /// Gets the options that match archives created by Nintendo's tools.
+    pub fn nintendo() -> Self {
+        U8PackOptions { data_alignment: 32, sort: NodeSort::CaseInsensitive }
+    }
+}
+
+// A single entry in a directory, used to order files and directories together when packing.
+enum U8Entry {
+    File(Rc<RefCell<U8File>>),
+    Dir(Rc<RefCell<U8Directory>>),
+}
+
+impl U8Entry {
+    fn name(&self) -> String {
+        match self {
+            U8Entry::File(file) => file.borrow().name.clone(),
+            U8Entry::Dir(dir) => dir.borrow().name.clone(),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct U8Node {
     pub node_type: u8,
@@ -115,8 +178,27 @@ pub struct U8Archive {
 }
 
 impl U8Archive {
+    #[cfg(feature = "fs")]
+    /// Creates a new U8 instance from a U8 file, reading the file from the provided path.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, U8Error> {
+        let path = path.as_ref();
+        let data = fs::read(path).map_err(|source| U8Error::ReadFile { path: path.to_path_buf(), source })?;
+        Self::from_bytes(&data)
+    }
+
     /// Creates a new U8 instance from the binary data of a U8 file.
     pub fn from_bytes(data: &[u8]) -> Result<Self, U8Error> {
+        Self::from_bytes_with_mode(data, false)
+    }
+
+    /// Creates a new U8 instance from the binary data of a U8 file, like from_bytes(), but stops
+    /// at the first inconsistent node instead of returning an error. The tree will only contain
+    /// the nodes before that point, which allows partially corrupt archives to be inspected.
+    pub fn from_bytes_lenient(data: &[u8]) -> Result<Self, U8Error> {
+        Self::from_bytes_with_mode(data, true)
+    }
+
+    fn from_bytes_with_mode(data: &[u8], lenient: bool) -> Result<Self, U8Error> {
         let mut buf = Cursor::new(data);
         let mut magic = [0u8; 4];
         buf.read_exact(&mut magic)?;
@@ -133,7 +215,7 @@ impl U8Archive {
                 if &magic != b"\x55\xAA\x38\x2D" {
                     return Err(U8Error::NotU8Data);
                 }
-                println!("ignoring IMET header at 0x40");
+                log::debug!("ignoring IMET header at 0x40");
             }
             // Check for an IMET header that comes after a built tag.
             else {
@@ -146,7 +228,7 @@ impl U8Archive {
                     if &magic != b"\x55\xAA\x38\x2D" {
                         return Err(U8Error::NotU8Data);
                     }
-                    println!("ignoring IMET header at 0x80");
+                    log::debug!("ignoring IMET header at 0x80");
                 }
             }
         }
@@ -168,87 +250,117 @@ impl U8Archive {
             data_offset: root_node_data_offset,
             size: root_node_size,
         };
+        if root_node.node_type != 1 || root_node.size == 0 {
+            return Err(U8Error::InvalidNodeStructure { node_index: 0, reason: String::from("root node is not a directory") });
+        }
         // Create a vec of nodes, push the root node, and then iterate over the remaining number
-        // of nodes in the file and push them to the vec.
+        // of nodes in the file and push them to the vec. The root node's size is the total number
+        // of nodes, so make sure the data is actually long enough to hold that many.
+        let base_name_offset = buf.position() - 12 + root_node_size as u64 * 12;
         let mut u8_nodes: Vec<U8Node> = Vec::new();
         u8_nodes.push(root_node);
-        for _ in 1..root_node_size {
-            let node_type = buf.read_u8()?;
-            let name_offset = buf.read_u24::<BigEndian>()?;
-            let data_offset = buf.read_u32::<BigEndian>()?;
-            let size = buf.read_u32::<BigEndian>()?;
-            u8_nodes.push(U8Node { node_type, name_offset, data_offset, size })
-        }
-        // Iterate over the loaded nodes and load the file names and data associated with them.
-        let base_name_offset = buf.position();
-        let mut file_names = Vec::<String>::new();
-        let mut file_data = Vec::<Vec<u8>>::new();
-        for node in &u8_nodes {
-            buf.seek(SeekFrom::Start(base_name_offset + node.name_offset as u64))?;
-            let mut name_bin = Vec::<u8>::new();
-            // Read the file name one byte at a time until we find a null byte.
-            loop {
-                let byte = buf.read_u8()?;
-                if byte == b'\0' {
+        for i in 1..root_node_size as usize {
+            match Self::read_node(&mut buf) {
+                Ok(node) => u8_nodes.push(node),
+                Err(_) if lenient => {
+                    log::warn!("U8 node table ends after {} of {} nodes", i, root_node_size);
                     break;
-                }
-                name_bin.push(byte);
-            }
-            file_names.push(String::from_utf8(name_bin).map_err(|_| U8Error::InvalidFileName(base_name_offset + node.name_offset as u64))?.to_owned());
-            // If this is a file node, read the data for the file.
-            if node.node_type == 0 {
-                buf.seek(SeekFrom::Start(node.data_offset as u64))?;
-                let mut data = vec![0u8; node.size as usize];
-                buf.read_exact(&mut data)?;
-                file_data.push(data);
-            } else {
-                file_data.push(Vec::new());
+                },
+                Err(_) => return Err(U8Error::InvalidNodeStructure {
+                    node_index: i,
+                    reason: format!("node table extends past the end of the archive (expected {} nodes)", root_node_size),
+                }),
             }
         }
-        // Now that we have all the data loaded out of the file, assemble the tree of U8Items that
-        // provides an actual map of the archive's data.
+        // Walk the nodes in order to assemble the tree of U8Items that provides an actual map of
+        // the archive's data. Each directory on the stack is stored with its node index and the
+        // index of the first node after it, so that nodes are always added to the directory
+        // whose range actually contains them.
         let node_tree = U8Directory::new(String::new());
-        let mut focused_node = Rc::clone(&node_tree);
-        // This is the order of directory nodes we've traversed down.
-        let mut parent_dirs: Vec<u32> = Vec::from([0]);
-        for i in 0..u8_nodes.len() {
-            match u8_nodes[i].node_type {
-                1 => {
-                    // Code for a directory node.
-                    if u8_nodes[i].name_offset != 0 {
-                        // If we're already at the correct level, push a new empty dir item to the
-                        // item we're currently working on.
-                        if u8_nodes[i].data_offset == *parent_dirs.last().unwrap() {
-                            parent_dirs.push(i as u32);
-                            U8Directory::add_dir(&focused_node, U8Directory::new(file_names[i].clone()));
-                            focused_node = U8Directory::get_child_dir(&focused_node, &file_names[i]).unwrap();
-                        }
-                        // Otherwise, go back up the path until we're at the correct level.
-                        else {
-                            while u8_nodes[i].data_offset != *parent_dirs.last().unwrap() {
-                                parent_dirs.pop();
-                                let parent = focused_node.as_ref().borrow().get_parent().unwrap();
-                                focused_node = parent;
-                            }
-                            parent_dirs.push(i as u32);
-                            // Rebuild current working directory, and make sure all directories in the
-                            // path exist.
-                            U8Directory::add_dir(&focused_node, U8Directory::new(file_names[i].clone()));
-                            focused_node = U8Directory::get_child_dir(&focused_node, &file_names[i]).unwrap()
-                        }
-                    }
-                },
-                0 => {
-                    // Code for a file node.
-                    U8Directory::add_file(&focused_node, U8File::new(file_names[i].clone(), file_data[i].clone()));
-                },
-                x => return Err(U8Error::InvalidNodeType(x, i))
+        let mut dir_stack: Vec<(usize, usize, Rc<RefCell<U8Directory>>)> = vec![(0, u8_nodes.len(), Rc::clone(&node_tree))];
+        for (i, node) in u8_nodes.iter().enumerate().skip(1) {
+            if let Err(err) = Self::add_node(&mut buf, base_name_offset, i, node, &mut dir_stack) {
+                if !lenient {
+                    return Err(err);
+                }
+                log::warn!("truncating U8 archive before node {}: {}", i, err);
+                break;
             }
         }
         Ok(U8Archive {
             node_tree,
         })
     }
+
+    fn read_node(buf: &mut Cursor<&[u8]>) -> Result<U8Node, U8Error> {
+        let node_type = buf.read_u8()?;
+        let name_offset = buf.read_u24::<BigEndian>()?;
+        let data_offset = buf.read_u32::<BigEndian>()?;
+        let size = buf.read_u32::<BigEndian>()?;
+        Ok(U8Node { node_type, name_offset, data_offset, size })
+    }
+
+    // Loads the name and data for a node and adds it to the tree under the directory that
+    // contains it, checking that directory nodes agree with the directories around them.
+    fn add_node(buf: &mut Cursor<&[u8]>, base_name_offset: u64, index: usize, node: &U8Node,
+                dir_stack: &mut Vec<(usize, usize, Rc<RefCell<U8Directory>>)>) -> Result<(), U8Error> {
+        // Leave any directories that ended before this node. The root directory covers every
+        // node, so it's never removed.
+        while index >= dir_stack.last().unwrap().1 {
+            dir_stack.pop();
+        }
+        let (parent_index, parent_end, parent) = dir_stack.last().unwrap();
+        buf.seek(SeekFrom::Start(base_name_offset + node.name_offset as u64))?;
+        let mut name_bin = Vec::<u8>::new();
+        // Read the file name one byte at a time until we find a null byte.
+        loop {
+            let byte = buf.read_u8()?;
+            if byte == b'\0' {
+                break;
+            }
+            name_bin.push(byte);
+        }
+        let name = String::from_utf8(name_bin).map_err(|_| U8Error::InvalidFileName(base_name_offset + node.name_offset as u64))?;
+        match node.node_type {
+            0 => {
+                // Code for a file node. Check the data's bounds before reading it, so that a bad
+                // size can't cause a huge allocation.
+                if node.data_offset as u64 + node.size as u64 > buf.get_ref().len() as u64 {
+                    return Err(U8Error::InvalidNodeStructure {
+                        node_index: index,
+                        reason: String::from("file data extends past the end of the archive"),
+                    });
+                }
+                buf.seek(SeekFrom::Start(node.data_offset as u64))?;
+                let mut data = vec![0u8; node.size as usize];
+                buf.read_exact(&mut data)?;
+                U8Directory::add_file(parent, U8File::new(name, data));
+            },
+            1 => {
+                // Code for a directory node. Its data offset is the index of its parent, and its
+                // size is the index of the first node after it, which has to fall inside of its
+                // parent.
+                let end = node.size as usize;
+                if node.data_offset as usize != *parent_index {
+                    return Err(U8Error::InvalidNodeStructure {
+                        node_index: index,
+                        reason: format!("parent index {} does not match enclosing directory at index {}", node.data_offset, parent_index),
+                    });
+                }
+                if end <= index || end > *parent_end {
+                    return Err(U8Error::InvalidNodeStructure {
+                        node_index: index,
+                        reason: format!("end index {} is outside of the range {}-{}", end, index + 1, parent_end),
+                    });
+                }
+                let dir = U8Directory::new(name);
+                U8Directory::add_dir(parent, Rc::clone(&dir));
+                dir_stack.push((index, end, dir));
+            },
+            x => return Err(U8Error::InvalidNodeType(x, index))
+        }
+        Ok(())
+    }
     
     pub fn from_tree(node_tree: &Rc<RefCell<U8Directory>>) -> Result<Self, U8Error> {
         Ok(U8Archive {
@@ -256,49 +368,107 @@ impl U8Archive {
         })
     }
     
-    fn pack_dir_recursive(file_names: &mut Vec<String>, file_data: &mut Vec<Vec<u8>>, u8_nodes: &mut Vec<U8Node>, current_node: &Rc<RefCell<U8Directory>>) {
-        // For files, read their data into the file data list, add their name into the file name 
-        // list, then calculate the offset for their file name and create a new U8Node() for them. 
-        // 0 values for name/data offsets are temporary and are set later.
+    #[cfg(feature = "fs")]
+    /// Creates a new U8 instance from the files and directories inside a directory. Entries are
+    /// added with files before directories, each sorted by name, and anything that isn't a normal
+    /// file or directory is ignored.
+    pub fn from_dir(path: &Path) -> Result<Self, U8Error> {
+        let node_tree = U8Directory::new(String::new());
+        U8Archive::add_dir_recursive(&node_tree, path)?;
+        U8Archive::from_tree(&node_tree)
+    }
+
+    #[cfg(feature = "fs")]
+    fn add_dir_recursive(dir: &Rc<RefCell<U8Directory>>, path: &Path) -> Result<(), U8Error> {
+        let mut entries: Vec<std::path::PathBuf> = fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<_, _>>()?;
+        entries.sort();
+        let name_of = |entry: &Path| entry.file_name().unwrap().to_string_lossy().into_owned();
+        for entry in entries.iter().filter(|entry| entry.is_file()) {
+            U8Directory::add_file(dir, U8File::new(name_of(entry), fs::read(entry)?));
+        }
+        for entry in entries.iter().filter(|entry| entry.is_dir()) {
+            let child = U8Directory::new(name_of(entry));
+            U8Directory::add_dir(dir, Rc::clone(&child));
+            U8Archive::add_dir_recursive(&child, entry)?;
+        }
+        Ok(())
+    }
+
+    fn pack_dir_recursive(file_names: &mut Vec<String>, file_data: &mut Vec<Vec<u8>>, u8_nodes: &mut Vec<U8Node>, current_node: &Rc<RefCell<U8Directory>>,
+                          path: &str, sort: NodeSort) -> Result<(), U8Error> {
         let parent_node = u8_nodes.len() - 1;
-        for file in &current_node.borrow().files {
-            file_names.push(file.borrow().name.clone());
-            file_data.push(file.borrow().data.clone());
-            u8_nodes.push(U8Node { node_type: 0, name_offset: 0, data_offset: 0, size: file_data[u8_nodes.len()].len() as u32});
+        let current_node = current_node.borrow();
+        let mut entries: Vec<U8Entry> = current_node.files.iter().map(|file| U8Entry::File(Rc::clone(file)))
+            .chain(current_node.dirs.iter().map(|dir| U8Entry::Dir(Rc::clone(dir))))
+            .collect();
+        // Entries with the same name can't be told apart once they're extracted, so they aren't
+        // allowed to be packed together.
+        let mut names: Vec<String> = entries.iter().map(|entry| entry.name()).collect();
+        names.sort();
+        if let Some(name) = names.windows(2).find(|pair| pair[0] == pair[1]).map(|pair| &pair[0]) {
+            return Err(U8Error::DuplicateName(format!("{}{}", path, name)));
+        }
+        if sort == NodeSort::CaseInsensitive {
+            entries.sort_by_cached_key(|entry| entry.name().to_lowercase());
         }
-        // For directories, add their name to the file name list, add empty data to the file data 
-        // list, find the total number of files and directories inside the directory to calculate 
-        // the final node included in it, then recursively call this function again on that 
-        // directory to process it.
-        for dir in &current_node.borrow().dirs {
-            file_names.push(dir.borrow().name.clone());
-            file_data.push(Vec::new());
-            let max_node = u8_nodes.len() + current_node.borrow().count() + 1;
-            u8_nodes.push(U8Node { node_type: 1, name_offset: 0, data_offset: parent_node as u32, size: max_node as u32});
-            U8Archive::pack_dir_recursive(file_names, file_data, u8_nodes, dir)
+        for entry in entries {
+            match entry {
+                // For files, read their data into the file data list, add their name into the file
+                // name list, then create a new U8Node() for them. 0 values for name/data offsets
+                // are temporary and are set later.
+                U8Entry::File(file) => {
+                    file_names.push(file.borrow().name.clone());
+                    file_data.push(file.borrow().data.clone());
+                    u8_nodes.push(U8Node { node_type: 0, name_offset: 0, data_offset: 0, size: file_data[u8_nodes.len()].len() as u32});
+                },
+                // For directories, add their name to the file name list, add empty data to the
+                // file data list, find the total number of files and directories inside the
+                // directory to calculate the index of the first node after it, then recursively
+                // call this function again on that directory to process it.
+                U8Entry::Dir(dir) => {
+                    file_names.push(dir.borrow().name.clone());
+                    file_data.push(Vec::new());
+                    let max_node = u8_nodes.len() + dir.borrow().count();
+                    u8_nodes.push(U8Node { node_type: 1, name_offset: 0, data_offset: parent_node as u32, size: max_node as u32});
+                    let dir_path = format!("{}{}/", path, dir.borrow().name);
+                    U8Archive::pack_dir_recursive(file_names, file_data, u8_nodes, &dir, &dir_path, sort)?
+                },
+            }
         }
+        Ok(())
     }
 
-    /// Dumps the data in a U8Archive instance back into binary data that can be written to a file.
+    /// Dumps the data in a U8Archive instance back into binary data that can be written to a file,
+    /// using the default packing options.
     pub fn to_bytes(&self) -> Result<Vec<u8>, U8Error> {
+        self.to_bytes_with_options(&U8PackOptions::default())
+    }
+
+    /// Dumps the data in a U8Archive instance back into binary data that can be written to a file,
+    /// using the provided options to control how file data is aligned and how nodes are ordered.
+    pub fn to_bytes_with_options(&self, options: &U8PackOptions) -> Result<Vec<u8>, U8Error> {
+        let alignment = options.data_alignment;
+        if !alignment.is_power_of_two() {
+            return Err(U8Error::InvalidAlignment(alignment));
+        }
         // We need to start by rebuilding a flat list of the nodes from the directory tree.
         let mut file_names: Vec<String> = vec![String::new()];
         let mut file_data: Vec<Vec<u8>> = vec![Vec::new()];
         let mut u8_nodes: Vec<U8Node> = Vec::new();
         u8_nodes.push(U8Node { node_type: 1, name_offset: 0, data_offset: 0, size: self.node_tree.borrow().count() as u32 });
         let root_node = Rc::clone(&self.node_tree);
-        U8Archive::pack_dir_recursive(&mut file_names, &mut file_data, &mut u8_nodes, &root_node);
-        // Header size starts at 0 because the header size starts with the nodes and does not
-        // include the actual file header.
-        let mut header_size: u32 = 0;
-        // Add 12 bytes for each node, since that's how many bytes each one is made up of.
-        for _ in 0..u8_nodes.len() {
-            header_size += 12;
-        }
-        // Add the number of bytes used for each file/folder name in the string table.
-        for file_name in &file_names {
-            header_size += file_name.len() as u32 + 1
+        U8Archive::pack_dir_recursive(&mut file_names, &mut file_data, &mut u8_nodes, &root_node, "", options.sort)?;
+        // Name offsets are only 24 bits, so the string table (including the NULL byte after each
+        // name) has to fit in that range.
+        let string_table_size: u64 = file_names.iter().map(|file_name| file_name.len() as u64 + 1).sum();
+        if string_table_size > 0xFFFFFF {
+            return Err(U8Error::StringTableTooLarge(string_table_size));
         }
+        // The header size starts with the nodes and does not include the actual file header. Each
+        // node is 12 bytes, followed by the string table.
+        let header_size: u32 = u8_nodes.len() as u32 * 12 + string_table_size as u32;
         // The initial data offset is equal to the file header (32 bytes) + node data aligned to
         // 64 bytes.
         let data_offset: u32 = (header_size + 32 + 63) & !63;
@@ -308,8 +478,8 @@ impl U8Archive {
         let mut current_name_offset: u32 = 0;
         for i in 0..u8_nodes.len() {
             if u8_nodes[i].node_type == 0 {
-                u8_nodes[i].data_offset = (current_data_offset + 31) & !31;
-                current_data_offset += (u8_nodes[i].size + 31) & !31;
+                u8_nodes[i].data_offset = (current_data_offset + alignment - 1) & !(alignment - 1);
+                current_data_offset = u8_nodes[i].data_offset + u8_nodes[i].size;
             }
             // Calculate the name offsets, including the extra 1 for the NULL byte.
             u8_nodes[i].name_offset = current_name_offset;
@@ -336,12 +506,289 @@ impl U8Archive {
         }
         // Pad to the nearest multiple of 64 bytes.
         buf.resize((buf.len() + 63) & !63, 0);
-        // Iterate over the file data and dump it. The file needs to be aligned to 32 bytes after
-        // each write.
-        for data in &file_data {
-            buf.write_all(data)?;
-            buf.resize((buf.len() + 31) & !31, 0);
+        // Iterate over the file data and dump it at the offsets assigned to each file, then pad
+        // the end of the archive to the data alignment.
+        for (node, data) in u8_nodes.iter().zip(&file_data) {
+            if node.node_type == 0 {
+                buf.resize(node.data_offset as usize, 0);
+                buf.write_all(data)?;
+            }
         }
+        buf.resize((buf.len() + alignment as usize - 1) & !(alignment as usize - 1), 0);
         Ok(buf)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Mutex, OnceLock};
+
+    // A logger that records every message, so that tests can check what library code logs.
+    struct CaptureLogger(Mutex<Vec<String>>);
+
+    impl log::Log for CaptureLogger {
+        fn enabled(&self, _: &log::Metadata) -> bool { true }
+        fn log(&self, record: &log::Record) {
+            self.0.lock().unwrap().push(record.args().to_string());
+        }
+        fn flush(&self) {}
+    }
+
+    fn capture_logger() -> &'static CaptureLogger {
+        static LOGGER: OnceLock<&'static CaptureLogger> = OnceLock::new();
+        LOGGER.get_or_init(|| {
+            let logger: &'static CaptureLogger = Box::leak(Box::new(CaptureLogger(Mutex::new(Vec::new()))));
+            log::set_logger(logger).unwrap();
+            log::set_max_level(log::LevelFilter::Trace);
+            logger
+        })
+    }
+
+    #[test]
+    fn test_imet_header_is_logged() {
+        let logger = capture_logger();
+        let node_tree = U8Directory::new(String::new());
+        U8Directory::add_file(&node_tree, U8File::new(String::from("file.bin"), vec![0xAB; 16]));
+        let archive_data = U8Archive::from_tree(&node_tree).unwrap().to_bytes().unwrap();
+        let mut data = vec![0u8; 0x40];
+        data.extend_from_slice(b"IMET");
+        data.resize(0x600, 0);
+        data.extend_from_slice(&archive_data);
+        let u8_archive = U8Archive::from_bytes(&data).unwrap();
+        assert_eq!(u8_archive.node_tree.borrow().files[0].borrow().name, "file.bin");
+        assert!(logger.0.lock().unwrap().iter().any(|message| message == "ignoring IMET header at 0x40"));
+    }
+
+    // Builds an archive with files at several levels, so that node indices matter:
+    // root.bin, a/a1.bin, a/b/b1.bin, c/c1.bin.
+    fn build_nested_archive() -> Vec<u8> {
+        let node_tree = U8Directory::new(String::new());
+        U8Directory::add_file(&node_tree, U8File::new(String::from("root.bin"), vec![0x01; 16]));
+        let dir_a = U8Directory::new(String::from("a"));
+        U8Directory::add_dir(&node_tree, Rc::clone(&dir_a));
+        U8Directory::add_file(&dir_a, U8File::new(String::from("a1.bin"), vec![0x02; 16]));
+        let dir_b = U8Directory::new(String::from("b"));
+        U8Directory::add_dir(&dir_a, Rc::clone(&dir_b));
+        U8Directory::add_file(&dir_b, U8File::new(String::from("b1.bin"), vec![0x03; 16]));
+        let dir_c = U8Directory::new(String::from("c"));
+        U8Directory::add_dir(&node_tree, Rc::clone(&dir_c));
+        U8Directory::add_file(&dir_c, U8File::new(String::from("c1.bin"), vec![0x04; 16]));
+        U8Archive::from_tree(&node_tree).unwrap().to_bytes().unwrap()
+    }
+
+    // Builds an archive directly from a list of (type, name, data offset, size) nodes, for node
+    // layouts that the packer wouldn't produce. File nodes should use an empty data range.
+    fn build_raw_archive(nodes: &[(u8, &str, u32, u32)]) -> Vec<u8> {
+        let mut buf: Vec<u8> = Vec::new();
+        buf.extend_from_slice(b"\x55\xAA\x38\x2D");
+        buf.extend_from_slice(&0x20u32.to_be_bytes());
+        buf.resize(0x20, 0);
+        let mut name_offset: u32 = 0;
+        for (node_type, name, data_offset, size) in nodes {
+            buf.push(*node_type);
+            buf.extend_from_slice(&name_offset.to_be_bytes()[1..]);
+            buf.extend_from_slice(&data_offset.to_be_bytes());
+            buf.extend_from_slice(&size.to_be_bytes());
+            name_offset += name.len() as u32 + 1;
+        }
+        for (_, name, _, _) in nodes {
+            buf.extend_from_slice(name.as_bytes());
+            buf.push(0);
+        }
+        buf
+    }
+
+    fn names(dir: &Rc<RefCell<U8Directory>>) -> (Vec<String>, Vec<String>) {
+        let dir = dir.borrow();
+        (dir.files.iter().map(|file| file.borrow().name.clone()).collect(),
+         dir.dirs.iter().map(|dir| dir.borrow().name.clone()).collect())
+    }
+
+    #[test]
+    fn test_nested_round_trip() {
+        let data = build_nested_archive();
+        // Directory sizes are the index of the first node after the directory.
+        let node_size = |index: usize| u32::from_be_bytes(data[0x20 + index * 12 + 8..0x20 + index * 12 + 12].try_into().unwrap());
+        assert_eq!((node_size(0), node_size(2), node_size(4), node_size(6)), (8, 6, 6, 8));
+        let u8_archive = U8Archive::from_bytes(&data).unwrap();
+        assert_eq!(names(&u8_archive.node_tree), (vec![String::from("root.bin")], vec![String::from("a"), String::from("c")]));
+        let dir_a = U8Directory::get_child_dir(&u8_archive.node_tree, "a").unwrap();
+        assert_eq!(names(&dir_a), (vec![String::from("a1.bin")], vec![String::from("b")]));
+        let dir_b = U8Directory::get_child_dir(&dir_a, "b").unwrap();
+        assert_eq!(dir_b.borrow().files[0].borrow().data, vec![0x03; 16]);
+        let dir_c = U8Directory::get_child_dir(&u8_archive.node_tree, "c").unwrap();
+        assert_eq!(names(&dir_c), (vec![String::from("c1.bin")], vec![]));
+        assert_eq!(u8_archive.to_bytes().unwrap(), data);
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = dir.path().join("archive.arc");
+        fs::write(&archive_path, build_nested_archive()).unwrap();
+        assert_eq!(U8Archive::open(&archive_path).unwrap().to_bytes().unwrap(), build_nested_archive());
+        let missing = dir.path().join("missing.arc");
+        assert!(matches!(U8Archive::open(&missing), Err(U8Error::ReadFile { path, .. }) if path == missing));
+    }
+
+    #[test]
+    fn test_file_after_subdirectory() {
+        // A file that follows a directory's range belongs to the parent, not the directory.
+        let data = build_raw_archive(&[(1, "", 0, 4), (1, "a", 0, 3), (0, "x", 0, 0), (0, "y", 0, 0)]);
+        let u8_archive = U8Archive::from_bytes(&data).unwrap();
+        assert_eq!(names(&u8_archive.node_tree), (vec![String::from("y")], vec![String::from("a")]));
+        let dir_a = U8Directory::get_child_dir(&u8_archive.node_tree, "a").unwrap();
+        assert_eq!(names(&dir_a), (vec![String::from("x")], vec![]));
+    }
+
+    #[test]
+    fn test_invalid_node_structure() {
+        // A directory that ends past the end of its parent.
+        let data = build_raw_archive(&[(1, "", 0, 3), (1, "a", 0, 5), (0, "x", 0, 0)]);
+        assert!(matches!(U8Archive::from_bytes(&data), Err(U8Error::InvalidNodeStructure { node_index: 1, .. })));
+        let u8_archive = U8Archive::from_bytes_lenient(&data).unwrap();
+        assert_eq!(u8_archive.node_tree.borrow().count(), 1);
+        // A directory that ends before itself.
+        let data = build_raw_archive(&[(1, "", 0, 3), (0, "x", 0, 0), (1, "a", 0, 1)]);
+        assert!(matches!(U8Archive::from_bytes(&data), Err(U8Error::InvalidNodeStructure { node_index: 2, .. })));
+        let u8_archive = U8Archive::from_bytes_lenient(&data).unwrap();
+        assert_eq!(names(&u8_archive.node_tree), (vec![String::from("x")], vec![]));
+        // A directory whose parent isn't the directory that contains it.
+        let data = build_raw_archive(&[(1, "", 0, 4), (1, "a", 0, 4), (1, "b", 0, 4), (0, "x", 0, 0)]);
+        assert!(matches!(U8Archive::from_bytes(&data), Err(U8Error::InvalidNodeStructure { node_index: 2, .. })));
+        // A root node that claims more nodes than the archive holds.
+        let mut data = build_nested_archive();
+        data.truncate(0x20 + 4 * 12);
+        assert!(matches!(U8Archive::from_bytes(&data), Err(U8Error::InvalidNodeStructure { node_index: 4, .. })));
+        assert!(matches!(U8Archive::from_bytes(&[0x55, 0xAA, 0x38, 0x2D, 0, 0, 0, 0x20]), Err(U8Error::IO(_))));
+    }
+
+    // Reads the node table of a packed archive back as (type, name, data offset, size) entries.
+    fn read_node_table(data: &[u8]) -> Vec<(u8, String, u32, u32)> {
+        let node_count = u32::from_be_bytes(data[0x28..0x2C].try_into().unwrap()) as usize;
+        let names_offset = 0x20 + node_count * 12;
+        (0..node_count).map(|i| {
+            let node = &data[0x20 + i * 12..0x20 + i * 12 + 12];
+            let name_offset = names_offset + u32::from_be_bytes([0, node[1], node[2], node[3]]) as usize;
+            let name_len = data[name_offset..].iter().position(|&c| c == 0).unwrap();
+            let name = String::from_utf8(data[name_offset..name_offset + name_len].to_vec()).unwrap();
+            (node[0], name, u32::from_be_bytes(node[4..8].try_into().unwrap()), u32::from_be_bytes(node[8..12].try_into().unwrap()))
+        }).collect()
+    }
+
+    #[test]
+    fn test_pack_nintendo_order() {
+        // A tree laid out like a banner's contents, with entries added out of order.
+        let node_tree = U8Directory::new(String::new());
+        let arc = U8Directory::new(String::from("arc"));
+        U8Directory::add_dir(&node_tree, Rc::clone(&arc));
+        U8Directory::add_file(&arc, U8File::new(String::from("Banner.bin"), vec![0x01; 40]));
+        let timg = U8Directory::new(String::from("timg"));
+        U8Directory::add_dir(&arc, Rc::clone(&timg));
+        U8Directory::add_file(&timg, U8File::new(String::from("logo.tpl"), vec![0x02; 8]));
+        let anim = U8Directory::new(String::from("anim"));
+        U8Directory::add_dir(&arc, Rc::clone(&anim));
+        U8Directory::add_file(&anim, U8File::new(String::from("Loop.brlan"), vec![0x03; 16]));
+        U8Directory::add_file(&anim, U8File::new(String::from("banner_Start.brlan"), vec![0x04; 16]));
+        let data = U8Archive::from_tree(&node_tree).unwrap().to_bytes_with_options(&U8PackOptions::nintendo()).unwrap();
+        // The node table is 8 nodes and 65 bytes of names, so data starts at 0x100.
+        let expected = vec![
+            (1, String::from(""), 0, 8),
+            (1, String::from("arc"), 0, 8),
+            (1, String::from("anim"), 1, 5),
+            (0, String::from("banner_Start.brlan"), 0x100, 16),
+            (0, String::from("Loop.brlan"), 0x120, 16),
+            (0, String::from("Banner.bin"), 0x140, 40),
+            (1, String::from("timg"), 1, 8),
+            (0, String::from("logo.tpl"), 0x180, 8),
+        ];
+        assert_eq!(read_node_table(&data), expected);
+        assert_eq!(u32::from_be_bytes(data[0x0C..0x10].try_into().unwrap()), 0x100);
+        assert_eq!(data.len(), 0x1A0);
+        // The sorted archive should still unpack to the same tree.
+        let u8_archive = U8Archive::from_bytes(&data).unwrap();
+        let arc = U8Directory::get_child_dir(&u8_archive.node_tree, "arc").unwrap();
+        assert_eq!(names(&arc), (vec![String::from("Banner.bin")], vec![String::from("anim"), String::from("timg")]));
+    }
+
+    #[test]
+    fn test_pack_alignment() {
+        let u8_archive = U8Archive::from_bytes(&build_nested_archive()).unwrap();
+        let options = U8PackOptions { data_alignment: 64, sort: NodeSort::Insertion };
+        let data = u8_archive.to_bytes_with_options(&options).unwrap();
+        let offsets: Vec<u32> = read_node_table(&data).iter().filter(|node| node.0 == 0).map(|node| node.2).collect();
+        assert_eq!(offsets.len(), 4);
+        assert!(offsets.windows(2).all(|pair| pair[1] - pair[0] == 64));
+        assert!(offsets.iter().all(|offset| offset % 64 == 0));
+        assert_eq!(data.len() % 64, 0);
+        assert_eq!(U8Archive::from_bytes(&data).unwrap().to_bytes().unwrap(), build_nested_archive());
+        let options = U8PackOptions { data_alignment: 48, sort: NodeSort::Insertion };
+        assert!(matches!(u8_archive.to_bytes_with_options(&options), Err(U8Error::InvalidAlignment(48))));
+    }
+
+    #[test]
+    fn test_pack_duplicate_names() {
+        let node_tree = U8Directory::new(String::new());
+        let arc = U8Directory::new(String::from("arc"));
+        U8Directory::add_dir(&node_tree, Rc::clone(&arc));
+        U8Directory::add_file(&arc, U8File::new(String::from("logo.tpl"), vec![0x01; 8]));
+        U8Directory::add_file(&arc, U8File::new(String::from("logo.tpl"), vec![0x02; 8]));
+        let u8_archive = U8Archive::from_tree(&node_tree).unwrap();
+        assert!(matches!(u8_archive.to_bytes(), Err(U8Error::DuplicateName(path)) if path == "arc/logo.tpl"));
+        // A file and a directory can't share a name either, but the same name in different
+        // directories is fine.
+        arc.borrow_mut().files.pop();
+        U8Directory::add_dir(&node_tree, U8Directory::new(String::from("logo.tpl")));
+        assert!(u8_archive.to_bytes().is_ok());
+        U8Directory::add_file(&node_tree, U8File::new(String::from("arc"), vec![0x03; 8]));
+        assert!(matches!(u8_archive.to_bytes(), Err(U8Error::DuplicateName(path)) if path == "arc"));
+    }
+
+    #[test]
+    fn test_pack_string_table_too_large() {
+        // Each name takes up 0x1000 bytes of the string table with its NULL byte, so 0x1000 of them
+        // is just past what a 24-bit name offset can address.
+        let node_tree = U8Directory::new(String::new());
+        for i in 0..0x1000 {
+            U8Directory::add_file(&node_tree, U8File::new(format!("{:0>4095}", i), Vec::new()));
+        }
+        let u8_archive = U8Archive::from_tree(&node_tree).unwrap();
+        assert!(matches!(u8_archive.to_bytes(), Err(U8Error::StringTableTooLarge(0x1000001))));
+        node_tree.borrow_mut().files.pop();
+        assert!(u8_archive.to_bytes().is_ok());
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_from_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::write(dir.path().join("root.bin"), [0x01; 16]).unwrap();
+        fs::write(dir.path().join("a/a1.bin"), [0x02; 16]).unwrap();
+        fs::write(dir.path().join("a/b/b1.bin"), [0x03; 16]).unwrap();
+        fs::create_dir(dir.path().join("c")).unwrap();
+        fs::write(dir.path().join("c/c1.bin"), [0x04; 16]).unwrap();
+        let u8_archive = U8Archive::from_dir(dir.path()).unwrap();
+        assert_eq!(u8_archive.to_bytes().unwrap(), build_nested_archive());
+    }
+
+    #[test]
+    fn test_mutated_node_tables() {
+        let data = build_nested_archive();
+        // Mutate every byte of the node table to a few interesting values. Neither mode should
+        // ever panic, and lenient parsing should always succeed when strict parsing does.
+        for offset in 0x20..0x20 + 8 * 12 {
+            for value in [0x00, 0x01, 0x03, 0x07, 0x08, 0x80, 0xFF] {
+                let mut mutated = data.clone();
+                mutated[offset] = value;
+                let strict = U8Archive::from_bytes(&mutated);
+                let lenient = U8Archive::from_bytes_lenient(&mutated);
+                if let Ok(strict) = strict {
+                    assert_eq!(lenient.unwrap().node_tree.borrow().count(), strict.node_tree.borrow().count());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,113 @@
+// archive/wrap.rs from rustii (c) 2025 NinjaCheetah & Contributors
+// https://github.com/NinjaCheetah/rustii
+//
+// Implements applying and removing chains of wrappers (LZ77 compression and IMD5 headers) around
+// data, like the archives inside of a channel's banner.
+
+use std::fmt;
+use thiserror::Error;
+use crate::archive::{imd5, lz77};
+
+#[derive(Debug, Error)]
+pub enum WrapError {
+    #[error("data is not wrapped with {0}")]
+    NotWrapped(Wrap),
+    #[error("IMD5 processing error")]
+    IMD5(#[from] imd5::IMD5Error),
+    #[error("LZ77 processing error")]
+    LZ77(#[from] lz77::LZ77Error),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// A wrapper that can be applied around data.
+pub enum Wrap {
+    /// LZ77 compression, with the "LZ77" magic number.
+    Lz77,
+    /// An IMD5 header, which stores the size and MD5 hash of the data.
+    Imd5,
+}
+
+impl fmt::Display for Wrap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Wrap::Lz77 => write!(f, "LZ77"),
+            Wrap::Imd5 => write!(f, "IMD5"),
+        }
+    }
+}
+
+/// Applies each wrapper in the chain to data, in order. For example, [Wrap::Lz77, Wrap::Imd5]
+/// compresses the data and then wraps the compressed data in an IMD5 header, which is how the
+/// banner.bin and icon.bin files in a channel's banner are stored.
+pub fn wrap_chain(data: &[u8], wraps: &[Wrap]) -> Result<Vec<u8>, WrapError> {
+    let mut buf = data.to_vec();
+    for wrap in wraps {
+        buf = match wrap {
+            Wrap::Lz77 => lz77::compress_lz77(&buf, true)?,
+            Wrap::Imd5 => imd5::wrap_imd5(&buf),
+        };
+    }
+    Ok(buf)
+}
+
+/// Removes each wrapper in the chain from data, in reverse order, so that the same chain passed to
+/// wrap_chain() can be used to undo it. Any wrapper that isn't present is treated as an error.
+pub fn unwrap_chain(data: &[u8], wraps: &[Wrap]) -> Result<Vec<u8>, WrapError> {
+    let mut buf = data.to_vec();
+    for wrap in wraps.iter().rev() {
+        buf = match wrap {
+            Wrap::Lz77 => {
+                // The LZ77 decompressor accepts data without a magic number, but wrapped data
+                // always has one, so don't try to decompress anything else.
+                if !lz77::has_magic(&buf) {
+                    return Err(WrapError::NotWrapped(Wrap::Lz77));
+                }
+                lz77::decompress_lz77(&buf)?
+            },
+            Wrap::Imd5 => imd5::unwrap_imd5(&buf)?,
+        };
+    }
+    Ok(buf)
+}
+
+/// Detects and removes any wrappers around data, based on their magic numbers. Returns the
+/// unwrapped data along with the chain of wrappers that were removed, in the order that they'd be
+/// passed to wrap_chain().
+pub fn unwrap_detected(data: &[u8]) -> Result<(Vec<u8>, Vec<Wrap>), WrapError> {
+    let mut buf = data.to_vec();
+    let mut wraps: Vec<Wrap> = Vec::new();
+    loop {
+        let wrap = if buf.starts_with(b"IMD5") {
+            Wrap::Imd5
+        } else if lz77::has_magic(&buf) {
+            Wrap::Lz77
+        } else {
+            break;
+        };
+        buf = unwrap_chain(&buf, &[wrap])?;
+        wraps.insert(0, wrap);
+    }
+    Ok((buf, wraps))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_chain() {
+        let data = b"icon data ".repeat(16);
+        let wrapped = wrap_chain(&data, &[Wrap::Lz77, Wrap::Imd5]).unwrap();
+        assert_eq!(&wrapped[..4], b"IMD5");
+        assert_eq!(&wrapped[0x20..0x24], b"LZ77");
+        assert_eq!(unwrap_chain(&wrapped, &[Wrap::Lz77, Wrap::Imd5]).unwrap(), data);
+        assert_eq!(unwrap_detected(&wrapped).unwrap(), (data.clone(), vec![Wrap::Lz77, Wrap::Imd5]));
+        // An empty chain leaves the data alone in both directions.
+        assert_eq!(wrap_chain(&data, &[]).unwrap(), data);
+        assert_eq!(unwrap_detected(&data).unwrap(), (data.clone(), vec![]));
+        // Removing wrappers in the wrong order fails instead of returning garbage.
+        assert!(matches!(unwrap_chain(&wrapped, &[Wrap::Imd5, Wrap::Lz77]), Err(WrapError::NotWrapped(Wrap::Lz77))));
+        let imd5_only = wrap_chain(&data, &[Wrap::Imd5]).unwrap();
+        assert!(matches!(unwrap_chain(&imd5_only, &[Wrap::Imd5, Wrap::Imd5]), Err(WrapError::IMD5(imd5::IMD5Error::NotIMD5Data))));
+    }
+}
@@ -9,45 +9,62 @@ use std::path::{Path, PathBuf};
 use anyhow::{bail, Context, Result};
 use clap::Subcommand;
 use rustii::archive::ash;
+use crate::archive::naming::decompressed_name;
+use crate::batch;
+use crate::output::{dry_run_suffix, resolve_output, write_output, OutputOptions};
 
 #[derive(Subcommand)]
 #[command(arg_required_else_help = true)]
 pub enum Commands {
     /// Compress a file with ASH compression (NOT IMPLEMENTED)
     Compress {
-        /// The path to the file to compress
-        input: String,
-        /// An optional output name; defaults to <input name>.ash
+        /// The path(s) to one or more files to compress
+        #[arg(num_args = 1.., required = true)]
+        input: Vec<String>,
+        /// An optional output name; defaults to <input name>.ash (single input only)
         #[arg(short, long)]
         output: Option<String>,
     },
     /// Decompress an ASH-compressed file
     Decompress {
-        /// The path to the file to decompress
-        input: String,
-        /// An optional output name; defaults to <input name>.out
+        /// The path(s) to one or more files to decompress
+        #[arg(num_args = 1.., required = true)]
+        input: Vec<String>,
+        /// An optional output name; defaults to <input name> without .ash if present, or
+        /// <input name>.out otherwise (single input only)
         #[arg(short, long)]
         output: Option<String>,
+        #[command(flatten)]
+        output_options: OutputOptions,
     }
 }
 
-pub fn compress_ash(_input: &str, _output: &Option<String>) -> Result<()> {
-    todo!();
+pub fn compress_ash(_inputs: &[String], _output: &Option<String>) -> Result<()> {
+    bail!("ASH compression is not implemented yet.");
 }
 
-pub fn decompress_ash(input: &str, output: &Option<String>) -> Result<()> {
+fn decompress_file(input: &str, output: &Option<String>, output_options: &OutputOptions) -> Result<()> {
     let in_path = Path::new(input);
     if !in_path.exists() {
         bail!("Compressed file \"{}\" could not be found.", in_path.display());
     }
+    let out_path = resolve_output(
+        output.as_ref().map(PathBuf::from),
+        decompressed_name(in_path, "ash"),
+        output_options,
+        Some(in_path),
+    )?;
     let compressed = fs::read(in_path)?;
     let decompressed = ash::decompress_ash(&compressed, None, None).with_context(|| "An unknown error occurred while decompressing the data.")?;
-    let out_path = if output.is_some() {
-        PathBuf::from(output.clone().unwrap())
-    } else {
-        PathBuf::from(in_path.file_name().unwrap()).with_extension(format!("{}.out", in_path.extension().unwrap_or("".as_ref()).to_str().unwrap()))
-    };
-    fs::write(out_path.clone(), decompressed)?;
-    println!("Successfully decompressed ASH file to \"{}\"!", out_path.display());
+    write_output(&out_path, &decompressed, output_options)?;
+    println!("Successfully decompressed ASH file to \"{}\"!{}", out_path.display(), dry_run_suffix(output_options.dry_run));
     Ok(())
 }
+
+pub fn decompress_ash(inputs: &[String], output: &Option<String>, output_options: &OutputOptions) -> Result<()> {
+    let inputs = batch::expand_inputs(inputs);
+    if output.is_some() && inputs.len() > 1 {
+        bail!("An output name can only be specified when decompressing a single file.");
+    }
+    batch::run_batch(&inputs, |input| decompress_file(input, output, output_options))
+}
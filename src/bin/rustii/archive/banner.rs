@@ -0,0 +1,70 @@
+// archive/banner.rs from rustii (c) 2025 NinjaCheetah & Contributors
+// https://github.com/NinjaCheetah/rustii
+//
+// Code for the channel banner packing/unpacking commands in the rustii CLI.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{bail, Context, Result};
+use clap::Subcommand;
+use rustii::archive::{banner, imet};
+
+#[derive(Subcommand)]
+#[command(arg_required_else_help = true)]
+pub enum Commands {
+    /// Pack a directory into a channel banner (00000000.app)
+    Pack {
+        /// The directory to pack, containing "banner" and "icon" directories and a sound.bin file
+        input: String,
+        /// The name of the channel, which is used for every language
+        #[arg(long)]
+        name: String,
+        /// An optional output name; defaults to 00000000.app
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Compress sound.bin, which is usually left uncompressed
+        #[arg(long)]
+        compress_sound: bool,
+    },
+    /// Unpack a channel banner (00000000.app) into a directory
+    Unpack {
+        /// The path to the banner to unpack
+        input: String,
+        /// The directory to unpack the banner to
+        output: String,
+    }
+}
+
+pub fn pack_banner(input: &str, name: &str, output: &Option<String>, compress_sound: &bool) -> Result<()> {
+    let in_path = Path::new(input);
+    if !in_path.exists() {
+        bail!("Source directory \"{}\" could not be found.", in_path.display());
+    }
+    let out_path = PathBuf::from(output.clone().unwrap_or(String::from("00000000.app")));
+    let mut channel_banner = banner::Banner::from_dir(in_path).with_context(|| format!("The directory \"{}\" could not be read as a banner.", in_path.display()))?;
+    for language in imet::Language::ALL {
+        channel_banner.imet_header.set_channel_name(language, name).with_context(|| format!("The channel name \"{}\" is invalid.", name))?;
+    }
+    let options = banner::BannerPackOptions { compress_sound: *compress_sound, ..Default::default() };
+    let banner_data = channel_banner.pack(&options).with_context(|| "The banner could not be packed.")?;
+    fs::write(&out_path, banner_data).with_context(|| format!("Could not open output file \"{}\" for writing.", out_path.display()))?;
+    println!("Successfully packed directory \"{}\" into banner \"{}\"!", in_path.display(), out_path.display());
+    Ok(())
+}
+
+pub fn unpack_banner(input: &str, output: &str) -> Result<()> {
+    let in_path = Path::new(input);
+    if !in_path.exists() {
+        bail!("Source banner \"{}\" could not be found.", in_path.display());
+    }
+    let out_path = PathBuf::from(output);
+    if out_path.exists() && !out_path.is_dir() {
+        bail!("A file already exists with the specified directory name!");
+    }
+    let data = fs::read(in_path).with_context(|| format!("Input file \"{}\" could not be read.", in_path.display()))?;
+    let channel_banner = banner::Banner::unpack(&data).with_context(|| format!("The banner \"{}\" could not be parsed, and is likely invalid.", in_path.display()))?;
+    channel_banner.to_dir(&out_path).with_context(|| format!("The banner could not be unpacked to \"{}\".", out_path.display()))?;
+    println!("Channel Name: {}", channel_banner.imet_header.channel_name(imet::Language::English));
+    println!("Successfully unpacked banner to directory \"{}\"!", out_path.display());
+    Ok(())
+}
@@ -8,58 +8,128 @@ use std::path::{Path, PathBuf};
 use anyhow::{bail, Context, Result};
 use clap::Subcommand;
 use rustii::archive::lz77;
+use crate::archive::naming::{compressed_name, decompressed_name};
+use crate::batch;
+use crate::output::{dry_run_suffix, resolve_output, write_output, OutputOptions};
 
 #[derive(Subcommand)]
 #[command(arg_required_else_help = true)]
 pub enum Commands {
     /// Compress a file with LZ77 compression
     Compress {
-        /// The path to the file to compress
-        input: String,
-        /// An optional output name; defaults to <input name>.lz77
+        /// The path(s) to one or more files to compress
+        #[arg(num_args = 1.., required = true)]
+        input: Vec<String>,
+        /// An optional output name; defaults to <input name>.lz77 (single input only)
         #[arg(short, long)]
         output: Option<String>,
+        /// Leave out the "LZ77" magic number, like files stored inside of U8 archives usually do
+        #[arg(long)]
+        no_magic: bool,
+        #[command(flatten)]
+        output_options: OutputOptions,
     },
     /// Decompress an LZ77-compressed file
     Decompress {
-        /// The path to the file to decompress
-        input: String,
-        /// An optional output name; defaults to <input name>.out
+        /// The path(s) to one or more files to decompress
+        #[arg(num_args = 1.., required = true)]
+        input: Vec<String>,
+        /// An optional output name; defaults to <input name> without .lz77 if present, or
+        /// <input name>.out otherwise (single input only)
         #[arg(short, long)]
         output: Option<String>,
+        #[command(flatten)]
+        output_options: OutputOptions,
     }
 }
 
-pub fn compress_lz77(input: &str, output: &Option<String>) -> Result<()> {
+fn compress_file(input: &str, output: &Option<String>, no_magic: bool, output_options: &OutputOptions) -> Result<()> {
     let in_path = Path::new(input);
     if !in_path.exists() {
         bail!("Input file \"{}\" could not be found.", in_path.display());
     }
+    let out_path = resolve_output(
+        output.as_ref().map(PathBuf::from),
+        compressed_name(in_path, "lz77"),
+        output_options,
+        Some(in_path),
+    )?;
     let decompressed = fs::read(in_path)?;
-    let compressed = lz77::compress_lz77(&decompressed).with_context(|| "An unknown error occurred while compressing the data.")?;
-    let out_path = if output.is_some() {
-        PathBuf::from(output.clone().unwrap())
-    } else {
-        PathBuf::from(in_path).with_extension(format!("{}.lz77", in_path.extension().unwrap_or("".as_ref()).to_str().unwrap()))
-    };
-    fs::write(out_path.clone(), compressed)?;
-    println!("Successfully compressed file to \"{}\"!", out_path.display());
+    let compressed = lz77::compress_lz77(&decompressed, !no_magic).with_context(|| "An unknown error occurred while compressing the data.")?;
+    write_output(&out_path, &compressed, output_options)?;
+    println!("Successfully compressed file to \"{}\"!{}", out_path.display(), dry_run_suffix(output_options.dry_run));
     Ok(())
 }
 
-pub fn decompress_lz77(input: &str, output: &Option<String>) -> Result<()> {
+fn decompress_file(input: &str, output: &Option<String>, output_options: &OutputOptions) -> Result<()> {
     let in_path = Path::new(input);
     if !in_path.exists() {
         bail!("Compressed file \"{}\" could not be found.", in_path.display());
     }
+    let out_path = resolve_output(
+        output.as_ref().map(PathBuf::from),
+        decompressed_name(in_path, "lz77"),
+        output_options,
+        Some(in_path),
+    )?;
     let compressed = fs::read(in_path)?;
     let decompressed = lz77::decompress_lz77(&compressed).with_context(|| "An unknown error occurred while decompressing the data.")?;
-    let out_path = if output.is_some() {
-        PathBuf::from(output.clone().unwrap())
-    } else {
-        PathBuf::from(in_path.file_name().unwrap()).with_extension(format!("{}.out", in_path.extension().unwrap_or("".as_ref()).to_str().unwrap()))
-    };
-    fs::write(out_path.clone(), decompressed)?;
-    println!("Successfully decompressed LZ77 file to \"{}\"!", out_path.display());
+    write_output(&out_path, &decompressed, output_options)?;
+    println!("Successfully decompressed LZ77 file to \"{}\"!{}", out_path.display(), dry_run_suffix(output_options.dry_run));
     Ok(())
 }
+
+pub fn compress_lz77(inputs: &[String], output: &Option<String>, no_magic: &bool, output_options: &OutputOptions) -> Result<()> {
+    let inputs = batch::expand_inputs(inputs);
+    if output.is_some() && inputs.len() > 1 {
+        bail!("An output name can only be specified when compressing a single file.");
+    }
+    batch::run_batch(&inputs, |input| compress_file(input, output, *no_magic, output_options))
+}
+
+pub fn decompress_lz77(inputs: &[String], output: &Option<String>, output_options: &OutputOptions) -> Result<()> {
+    let inputs = batch::expand_inputs(inputs);
+    if output.is_some() && inputs.len() > 1 {
+        bail!("An output name can only be specified when decompressing a single file.");
+    }
+    batch::run_batch(&inputs, |input| decompress_file(input, output, output_options))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn options() -> OutputOptions {
+        OutputOptions { output_dir: None, force: false, dry_run: false }
+    }
+
+    #[test]
+    fn test_round_trip_restores_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let original = dir.path().join("banner.bin");
+        fs::write(&original, b"banner data ".repeat(16)).unwrap();
+        let inputs = vec![original.to_string_lossy().to_string()];
+        compress_lz77(&inputs, &None, &false, &options()).unwrap();
+        let compressed = dir.path().join("banner.bin.lz77");
+        assert!(compressed.exists());
+        // Decompressing next to the original would overwrite it, which needs --force.
+        let inputs = vec![compressed.to_string_lossy().to_string()];
+        assert!(decompress_lz77(&inputs, &None, &options()).is_err());
+        fs::remove_file(&original).unwrap();
+        decompress_lz77(&inputs, &None, &options()).unwrap();
+        assert_eq!(fs::read(&original).unwrap(), b"banner data ".repeat(16));
+    }
+
+    #[test]
+    fn test_batch_inputs() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.bin", "b.bin"] {
+            fs::write(dir.path().join(name), b"archive data ".repeat(8)).unwrap();
+        }
+        let pattern = vec![dir.path().join("*.bin").to_string_lossy().to_string()];
+        // An output name can't be shared between several inputs.
+        assert!(compress_lz77(&pattern, &Some(String::from("out.lz77")), &false, &options()).is_err());
+        compress_lz77(&pattern, &None, &true, &options()).unwrap();
+        assert!(dir.path().join("a.bin.lz77").exists() && dir.path().join("b.bin.lz77").exists());
+    }
+}
@@ -2,5 +2,7 @@
 // https://github.com/NinjaCheetah/rustii
 
 pub mod ash;
+pub mod banner;
 pub mod lz77;
+pub mod naming;
 pub mod u8;
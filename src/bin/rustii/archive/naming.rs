@@ -0,0 +1,62 @@
+// archive/naming.rs from rustii (c) 2025 NinjaCheetah & Contributors
+// https://github.com/NinjaCheetah/rustii
+//
+// Common code for naming the output of the compression and decompression commands, so that
+// decompressing a compressed file restores its original name.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// Gets the default name for a compressed file, which is the full name of the input file with the
+/// compression suffix appended to it (i.e. "banner.bin" becomes "banner.bin.lz77").
+pub fn compressed_name(in_path: &Path, suffix: &str) -> PathBuf {
+    let mut name = in_path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(format!(".{}", suffix));
+    in_path.with_file_name(name)
+}
+
+/// Gets the default name for a decompressed file. If the input file ends with the compression
+/// suffix, then the suffix is stripped to restore the original name. Otherwise, ".out" is appended
+/// to the full name of the input file so that it isn't overwritten.
+pub fn decompressed_name(in_path: &Path, suffix: &str) -> PathBuf {
+    let name = in_path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let suffix = format!(".{}", suffix);
+    // The suffix is matched case-insensitively, since files copied off of FAT-formatted SD cards
+    // often end up with uppercase names. A file named only the suffix has no name to restore.
+    if name.len() > suffix.len() && name.is_char_boundary(name.len() - suffix.len()) {
+        let (stem, ending) = name.split_at(name.len() - suffix.len());
+        if ending.eq_ignore_ascii_case(&suffix) {
+            return in_path.with_file_name(stem);
+        }
+    }
+    in_path.with_file_name(format!("{}.out", name))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_compressed_name() {
+        assert_eq!(compressed_name(Path::new("banner.bin"), "lz77"), PathBuf::from("banner.bin.lz77"));
+        assert_eq!(compressed_name(Path::new("dir/banner"), "ash"), PathBuf::from("dir/banner.ash"));
+        assert_eq!(compressed_name(Path::new("dir/file.out"), "lz77"), PathBuf::from("dir/file.out.lz77"));
+    }
+
+    #[test]
+    fn test_decompressed_name() {
+        // The suffix added by compression is stripped, restoring the original name.
+        assert_eq!(decompressed_name(Path::new("banner.bin.lz77"), "lz77"), PathBuf::from("banner.bin"));
+        assert_eq!(decompressed_name(Path::new("dir/banner.ASH"), "ash"), PathBuf::from("dir/banner"));
+        for name in ["banner.bin", "dir/banner", "dir/file.out"] {
+            assert_eq!(decompressed_name(&compressed_name(Path::new(name), "lz77"), "lz77"), PathBuf::from(name));
+        }
+        // Anything else falls back to appending ".out", including inputs with no extension, inputs
+        // that already end in ".out", and inputs named only the suffix.
+        assert_eq!(decompressed_name(Path::new("dir/banner"), "lz77"), PathBuf::from("dir/banner.out"));
+        assert_eq!(decompressed_name(Path::new("banner.out"), "lz77"), PathBuf::from("banner.out.out"));
+        assert_eq!(decompressed_name(Path::new("banner.ash"), "lz77"), PathBuf::from("banner.ash.out"));
+        assert_eq!(decompressed_name(Path::new("banner.bin.lz7"), "lz77"), PathBuf::from("banner.bin.lz7.out"));
+        assert_eq!(decompressed_name(Path::new(".lz77"), "lz77"), PathBuf::from(".lz77.out"));
+    }
+}
@@ -5,12 +5,12 @@
 
 use std::{str, fs};
 use std::cell::RefCell;
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use anyhow::{bail, Context, Result};
 use clap::Subcommand;
-use glob::glob;
-use rustii::archive::u8;
+use rustii::archive::{u8, wrap};
 
 #[derive(Subcommand)]
 #[command(arg_required_else_help = true)]
@@ -21,68 +21,96 @@ pub enum Commands {
         input: String,
         /// The name of the packed U8 archive
         output: String,
+        /// The alignment of each file's data in bytes, which must be a power of two; defaults to 32
+        #[arg(long)]
+        align: Option<u32>,
+        /// The order to write the entries in each directory in, can be "insertion" (files then
+        /// directories, by name) or "name" (case-insensitive, like Nintendo's tools); defaults to
+        /// "insertion"
+        #[arg(long)]
+        sort: Option<String>,
+        /// Compress the packed U8 archive with LZ77 compression
+        #[arg(long)]
+        lz77: bool,
+        /// Wrap the packed U8 archive in an IMD5 header, after compressing it if --lz77 is also
+        /// set (like the icon.bin and banner.bin files in a channel's banner)
+        #[arg(long)]
+        imd5: bool,
     },
-    /// Unpack a U8 archive into a directory
+    /// Unpack a U8 archive into a directory, removing any IMD5 header or LZ77 compression first
     Unpack {
         /// The path to the U8 archive to unpack
         input: String,
         /// The directory to unpack the U8 archive to
         output: String,
+        /// Unpack as much of a malformed archive as possible, stopping at the first invalid node
+        #[arg(long)]
+        force: bool,
     }
 }
 
-fn pack_dir_recursive(dir: &Rc<RefCell<u8::U8Directory>>, in_path: PathBuf) -> Result<()> {
-    let mut files = Vec::new();
-    let mut dirs = Vec::new();
-    for entry in glob(&format!("{}/*", in_path.display()))?.flatten() {
-        match fs::metadata(&entry) {
-            Ok(meta) if meta.is_file() => files.push(entry),
-            Ok(meta) if meta.is_dir() => dirs.push(entry),
-            _ => {} // Anything that isn't a normal file/directory just gets ignored.
-        }
-    }
-    for file in files {
-        let node = u8::U8File::new(file.file_name().unwrap().to_str().unwrap().to_owned(), fs::read(file)?);
-        u8::U8Directory::add_file(dir, node);
-    }
-    for child_dir in dirs {
-        let node = u8::U8Directory::new(child_dir.file_name().unwrap().to_str().unwrap().to_owned());
-        u8::U8Directory::add_dir(dir, node);
-        let dir = u8::U8Directory::get_child_dir(dir, child_dir.file_name().unwrap().to_str().unwrap()).unwrap();
-        pack_dir_recursive(&dir, child_dir)?;
-    }
-    Ok(())
-}
-
-pub fn pack_u8_archive(input: &str, output: &str) -> Result<()> {
+pub fn pack_u8_archive(input: &str, output: &str, align: &Option<u32>, sort: &Option<String>, lz77: &bool, imd5: &bool) -> Result<()> {
     let in_path = Path::new(input);
     if !in_path.exists() {
         bail!("Source directory \"{}\" could not be found.", in_path.display());
     }
     let out_path = PathBuf::from(output);
-    let node_tree = u8::U8Directory::new(String::new());
-    pack_dir_recursive(&node_tree, in_path.to_path_buf()).with_context(|| "A U8 archive could not be packed.")?;
-    let u8_archive = u8::U8Archive::from_tree(&node_tree).with_context(|| "An unknown error occurred while creating a U8 archive from the data.")?;
-    fs::write(&out_path, &u8_archive.to_bytes()?).with_context(|| format!("Could not open output file \"{}\" for writing.", out_path.display()))?;
+    let sort = match sort.as_deref().map(|sort| sort.to_ascii_lowercase()).as_deref() {
+        None | Some("insertion") => u8::NodeSort::Insertion,
+        Some("name") => u8::NodeSort::CaseInsensitive,
+        Some(_) => bail!("The specified sort order \"{}\" is invalid! Try --help to see valid options.", sort.clone().unwrap()),
+    };
+    let options = u8::U8PackOptions { data_alignment: align.unwrap_or(32), sort };
+    let u8_archive = u8::U8Archive::from_dir(in_path).with_context(|| "A U8 archive could not be packed.")?;
+    let u8_data = u8_archive.to_bytes_with_options(&options).with_context(|| "A U8 archive could not be packed.")?;
+    let mut wraps: Vec<wrap::Wrap> = Vec::new();
+    if *lz77 {
+        wraps.push(wrap::Wrap::Lz77);
+    }
+    if *imd5 {
+        wraps.push(wrap::Wrap::Imd5);
+    }
+    let u8_data = wrap::wrap_chain(&u8_data, &wraps).with_context(|| "The packed U8 archive could not be wrapped.")?;
+    fs::write(&out_path, &u8_data).with_context(|| format!("Could not open output file \"{}\" for writing.", out_path.display()))?;
     println!("Successfully packed directory \"{}\" into U8 archive \"{}\"!", in_path.display(), out_path.display());
     Ok(())
 }
 
-fn unpack_dir_recursive(dir: &Rc<RefCell<u8::U8Directory>>, out_path: PathBuf) -> Result<()> {
-    let out_path = out_path.join(&dir.borrow().name);
+// Picks a name for an entry that hasn't already been used in the directory it's being unpacked to.
+// Archives shouldn't contain two entries with the same name, but if one does, the later entries
+// are renamed to name.1, name.2, etc. rather than overwriting the earlier ones.
+fn unique_name(name: &str, used_names: &mut HashSet<String>, out_path: &Path) -> String {
+    let mut unique = name.to_string();
+    let mut i = 1;
+    while used_names.contains(&unique) {
+        unique = format!("{}.{}", name, i);
+        i += 1;
+    }
+    if unique != name {
+        println!("Warning: The U8 archive contains more than one entry named \"{}\" in the same directory, so it was unpacked as \"{}\".",
+            out_path.join(name).display(), out_path.join(&unique).display());
+    }
+    used_names.insert(unique.clone());
+    unique
+}
+
+fn unpack_dir_recursive(dir: &Rc<RefCell<u8::U8Directory>>, out_path: &Path) -> Result<()> {
+    let mut used_names: HashSet<String> = HashSet::new();
     for file in &dir.borrow().files {
-        fs::write(out_path.join(&file.borrow().name), &file.borrow().data).with_context(|| format!("Failed to write output file \"{}\".", &file.borrow().name))?;
+        let name = unique_name(&file.borrow().name, &mut used_names, out_path);
+        fs::write(out_path.join(&name), &file.borrow().data).with_context(|| format!("Failed to write output file \"{}\".", name))?;
     }
     for dir in &dir.borrow().dirs {
-        if !out_path.join(&dir.borrow().name).exists() {
-            fs::create_dir(out_path.join(&dir.borrow().name)).with_context(|| format!("The output directory \"{}\" could not be created.", out_path.display()))?;
+        let dir_path = out_path.join(unique_name(&dir.borrow().name, &mut used_names, out_path));
+        if !dir_path.exists() {
+            fs::create_dir(&dir_path).with_context(|| format!("The output directory \"{}\" could not be created.", dir_path.display()))?;
         }
-        unpack_dir_recursive(dir, out_path.clone())?;
+        unpack_dir_recursive(dir, &dir_path)?;
     }
     Ok(())
 }
 
-pub fn unpack_u8_archive(input: &str, output: &str) -> Result<()> {
+pub fn unpack_u8_archive(input: &str, output: &str, force: &bool) -> Result<()> {
     let in_path = Path::new(input);
     if !in_path.exists() {
         bail!("Source U8 archive \"{}\" could not be found.", in_path.display());
@@ -97,8 +125,79 @@ pub fn unpack_u8_archive(input: &str, output: &str) -> Result<()> {
     }
     // Extract the files and directories in the root, and then recurse over each directory to
     // extract the files and directories they contain.
-    let u8_archive = u8::U8Archive::from_bytes(&fs::read(in_path).with_context(|| format!("Input file \"{}\" could not be read.", in_path.display()))?)?;
-    unpack_dir_recursive(&u8_archive.node_tree, out_path.clone())?;
+    let data = fs::read(in_path).with_context(|| format!("Input file \"{}\" could not be read.", in_path.display()))?;
+    let (data, wraps) = wrap::unwrap_detected(&data).with_context(|| "The U8 archive's IMD5 header or LZ77 compression could not be removed.")?;
+    for wrap in wraps.iter().rev() {
+        println!("Removed {} wrapper from U8 archive.", wrap);
+    }
+    let u8_archive = if *force {
+        u8::U8Archive::from_bytes_lenient(&data)?
+    } else {
+        u8::U8Archive::from_bytes(&data).with_context(|| "The U8 archive could not be parsed. Use --force to unpack as much of it as possible.")?
+    };
+    unpack_dir_recursive(&u8_archive.node_tree, &out_path)?;
     println!("Successfully unpacked U8 archive to directory \"{}\"!", out_path.display());
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Collects every file under a directory with its path relative to that directory, so that two
+    // trees can be compared.
+    fn read_tree(root: &Path, dir: &Path, files: &mut Vec<(PathBuf, Vec<u8>)>) {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                read_tree(root, &path, files);
+            } else {
+                files.push((path.strip_prefix(root).unwrap().to_path_buf(), fs::read(&path).unwrap()));
+            }
+        }
+        files.sort();
+    }
+
+    #[test]
+    fn test_pack_unpack_wrapped() {
+        let dir = tempfile::tempdir().unwrap();
+        let in_dir = dir.path().join("icon");
+        fs::create_dir_all(in_dir.join("arc/blyt")).unwrap();
+        fs::create_dir_all(in_dir.join("arc/timg")).unwrap();
+        fs::write(in_dir.join("arc/blyt/icon.brlyt"), b"RLYT".repeat(32)).unwrap();
+        fs::write(in_dir.join("arc/timg/icon.tpl"), [0x20; 0x200]).unwrap();
+        let icon_bin = dir.path().join("icon.bin");
+        pack_u8_archive(in_dir.to_str().unwrap(), icon_bin.to_str().unwrap(), &None, &None, &true, &true).unwrap();
+        let data = fs::read(&icon_bin).unwrap();
+        assert_eq!(&data[..4], b"IMD5");
+        assert_eq!(&data[0x20..0x24], b"LZ77");
+        let out_dir = dir.path().join("unpacked");
+        unpack_u8_archive(icon_bin.to_str().unwrap(), out_dir.to_str().unwrap(), &false).unwrap();
+        let (mut original, mut unpacked) = (Vec::new(), Vec::new());
+        read_tree(&in_dir, &in_dir, &mut original);
+        read_tree(&out_dir, &out_dir, &mut unpacked);
+        assert_eq!(unpacked, original);
+    }
+
+    #[test]
+    fn test_unpack_duplicate_names() {
+        // Archives with duplicate names can't be packed, so build the tree directly.
+        let node_tree = u8::U8Directory::new(String::new());
+        u8::U8Directory::add_file(&node_tree, u8::U8File::new(String::from("file.bin"), vec![0x01; 8]));
+        u8::U8Directory::add_file(&node_tree, u8::U8File::new(String::from("file.bin"), vec![0x02; 8]));
+        u8::U8Directory::add_file(&node_tree, u8::U8File::new(String::from("file.bin"), vec![0x03; 8]));
+        let dir_a = u8::U8Directory::new(String::from("dir"));
+        u8::U8Directory::add_dir(&node_tree, Rc::clone(&dir_a));
+        u8::U8Directory::add_file(&dir_a, u8::U8File::new(String::from("file.bin"), vec![0x04; 8]));
+        let dir_b = u8::U8Directory::new(String::from("dir"));
+        u8::U8Directory::add_dir(&node_tree, Rc::clone(&dir_b));
+        u8::U8Directory::add_file(&dir_b, u8::U8File::new(String::from("file.bin"), vec![0x05; 8]));
+        let out_dir = tempfile::tempdir().unwrap();
+        unpack_dir_recursive(&node_tree, out_dir.path()).unwrap();
+        assert_eq!(fs::read(out_dir.path().join("file.bin")).unwrap(), [0x01; 8]);
+        assert_eq!(fs::read(out_dir.path().join("file.bin.1")).unwrap(), [0x02; 8]);
+        assert_eq!(fs::read(out_dir.path().join("file.bin.2")).unwrap(), [0x03; 8]);
+        assert_eq!(fs::read(out_dir.path().join("dir/file.bin")).unwrap(), [0x04; 8]);
+        assert_eq!(fs::read(out_dir.path().join("dir.1/file.bin")).unwrap(), [0x05; 8]);
+    }
+}
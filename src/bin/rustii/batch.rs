@@ -0,0 +1,89 @@
+// batch.rs from rustii (c) 2025 NinjaCheetah & Contributors
+// https://github.com/NinjaCheetah/rustii
+//
+// Common code for commands that can operate on multiple input files at once.
+
+use anyhow::{bail, Result};
+use glob::glob;
+
+/// Expands any glob patterns in the provided list of inputs. This is needed on platforms where the
+/// shell doesn't expand globs itself (i.e. Windows). Inputs that aren't valid patterns or don't
+/// match anything are passed through unchanged so that the command can report them as missing.
+pub fn expand_inputs(inputs: &[String]) -> Vec<String> {
+    let mut expanded: Vec<String> = Vec::new();
+    for input in inputs {
+        let matches: Vec<String> = match glob(input) {
+            Ok(paths) => paths
+                .filter_map(|path| path.ok())
+                .map(|path| path.to_string_lossy().to_string())
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        if matches.is_empty() {
+            expanded.push(input.clone());
+        } else {
+            expanded.extend(matches);
+        }
+    }
+    expanded
+}
+
+/// Runs the provided operation on each input file independently, continuing past any failures.
+/// When more than one file is processed, a summary of successes and failures is printed at the end.
+/// Returns an error if any of the files failed, so that the CLI exits with a nonzero code.
+pub fn run_batch<F>(inputs: &[String], mut operation: F) -> Result<()>
+where
+    F: FnMut(&str) -> Result<()>,
+{
+    if inputs.len() == 1 {
+        return operation(&inputs[0]);
+    }
+    let mut failures: Vec<(&str, anyhow::Error)> = Vec::new();
+    for input in inputs {
+        if let Err(e) = operation(input) {
+            eprintln!("Error processing \"{}\": {:#}", input, e);
+            failures.push((input, e));
+        }
+    }
+    println!();
+    println!("Processed {} files: {} succeeded, {} failed.", inputs.len(), inputs.len() - failures.len(), failures.len());
+    if !failures.is_empty() {
+        for (input, _) in &failures {
+            println!("  Failed: {}", input);
+        }
+        bail!("{} of {} files could not be processed.", failures.len(), inputs.len());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_expand_inputs_passthrough() {
+        let inputs = vec![String::from("this_file_does_not_exist_*.wad")];
+        assert_eq!(expand_inputs(&inputs), inputs);
+    }
+
+    #[test]
+    fn test_run_batch_continues_past_failures() {
+        let inputs = vec![String::from("a"), String::from("b"), String::from("c")];
+        let mut processed: Vec<String> = Vec::new();
+        let result = run_batch(&inputs, |input| {
+            processed.push(input.to_string());
+            if input == "b" {
+                bail!("failed");
+            }
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert_eq!(processed, inputs);
+    }
+
+    #[test]
+    fn test_run_batch_all_succeed() {
+        let inputs = vec![String::from("a"), String::from("b")];
+        assert!(run_batch(&inputs, |_| Ok(())).is_ok());
+    }
+}
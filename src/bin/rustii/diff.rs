@@ -0,0 +1,92 @@
+// diff.rs from rustii (c) 2025 NinjaCheetah & Contributors
+// https://github.com/NinjaCheetah/rustii
+//
+// Code for the diff command in the rustii CLI, which compares two TMDs, Tickets, or WADs.
+
+use std::path::Path;
+use anyhow::{bail, Context, Result};
+use rustii::title::{self, diff, ticket, tmd, versions};
+use crate::filetypes::{WiiFileType, identify_file_type};
+
+// Formats a title version the same way that info does, translating it into the standard version
+// format where possible.
+fn format_version(title_version: u16, title_id: title::TitleId, is_vwii: bool) -> String {
+    if title_id == title::TitleId::BOOT2 {
+        return format!("{} (boot2v{})", title_version, title_version);
+    }
+    match versions::dec_to_standard(title_version, &hex::encode(title_id), Some(is_vwii)) {
+        Some(converted_ver) => format!("{} ({})", title_version, converted_ver),
+        None => title_version.to_string(),
+    }
+}
+
+/// Prints a note describing how the version of a system title changed, since the decimal versions
+/// of system titles are difficult to compare at a glance. Nothing is printed for other titles or
+/// when the versions match.
+pub fn print_version_note(title_id: title::TitleId, first: u16, second: u16, is_vwii: bool) {
+    if first == second || title_id.high() != [0x00, 0x00, 0x00, 0x01] {
+        return;
+    }
+    println!("  Note: Version changed from {} to {}", format_version(first, title_id, is_vwii), format_version(second, title_id, is_vwii));
+}
+
+fn print_field_diffs(section: &str, diffs: &[diff::FieldDiff]) {
+    if !diffs.is_empty() {
+        println!("{} Differences", section);
+        for field_diff in diffs {
+            println!("  {}", field_diff);
+        }
+    }
+}
+
+fn diff_tmd(first_path: &Path, second_path: &Path) -> Result<usize> {
+    let first_tmd = tmd::TMD::open(first_path).with_context(|| format!("The provided TMD file \"{}\" could not be loaded.", first_path.display()))?;
+    let second_tmd = tmd::TMD::open(second_path).with_context(|| format!("The provided TMD file \"{}\" could not be loaded.", second_path.display()))?;
+    let tmd_diff = first_tmd.diff(&second_tmd);
+    print_field_diffs("TMD", &tmd_diff.fields);
+    print_version_note(first_tmd.title_id(), first_tmd.title_version, second_tmd.title_version, first_tmd.is_vwii());
+    if !tmd_diff.contents.is_empty() {
+        println!("Content Record Differences");
+        for content_diff in &tmd_diff.contents {
+            println!("  {}", content_diff);
+        }
+    }
+    Ok(tmd_diff.fields.len() + tmd_diff.contents.len())
+}
+
+fn diff_ticket(first_path: &Path, second_path: &Path) -> Result<usize> {
+    let first_ticket = ticket::Ticket::open(first_path).with_context(|| format!("The provided Ticket file \"{}\" could not be loaded.", first_path.display()))?;
+    let second_ticket = ticket::Ticket::open(second_path).with_context(|| format!("The provided Ticket file \"{}\" could not be loaded.", second_path.display()))?;
+    let ticket_diff = first_ticket.diff(&second_ticket);
+    print_field_diffs("Ticket", &ticket_diff.fields);
+    print_version_note(first_ticket.title_id(), first_ticket.title_version, second_ticket.title_version, first_ticket.common_key_index() == 2);
+    Ok(ticket_diff.fields.len())
+}
+
+pub fn diff(first: &str, second: &str, contents: &bool) -> Result<()> {
+    let first_path = Path::new(first);
+    let second_path = Path::new(second);
+    for path in [first_path, second_path] {
+        if !path.exists() {
+            bail!("Input file \"{}\" does not exist.", path.display());
+        }
+    }
+    let file_type = identify_file_type(first);
+    if file_type != identify_file_type(second) {
+        bail!("The provided files are not the same type! Both files must be TMDs, Tickets, or WADs.");
+    }
+    if *contents && file_type != Some(WiiFileType::Wad) {
+        bail!("Content data can only be compared between two WAD files.");
+    }
+    let num_differences = match file_type {
+        Some(WiiFileType::Tmd) => diff_tmd(first_path, second_path)?,
+        Some(WiiFileType::Ticket) => diff_ticket(first_path, second_path)?,
+        Some(WiiFileType::Wad) => return crate::title::wad::diff_wad(first, second, contents),
+        _ => bail!("Only TMDs, Tickets, and WADs can be compared."),
+    };
+    if num_differences > 0 {
+        bail!("Found {} differences between files \"{}\" and \"{}\".", num_differences, first_path.display(), second_path.display());
+    }
+    println!("Files \"{}\" and \"{}\" are identical!", first_path.display(), second_path.display());
+    Ok(())
+}
@@ -7,6 +7,7 @@ use std::{str, fs::File};
 use std::io::{Read, Seek, SeekFrom};
 use std::path::Path;
 use regex::RegexBuilder;
+use rustii::nand::save::{SAVE_BK_MAGIC, SAVE_BK_OFFSET};
 
 #[derive(Debug)]
 #[derive(PartialEq)]
@@ -15,6 +16,7 @@ pub enum WiiFileType {
     Tmd,
     Ticket,
     U8,
+    Save,
 }
 
 pub fn identify_file_type(input: &str) -> Option<WiiFileType> {
@@ -46,20 +48,25 @@ pub fn identify_file_type(input: &str) -> Option<WiiFileType> {
     // These require reading the magic number of the file, so we only try this after everything
     // else has been tried. These are separated from the other methods of detecting these types so
     // that we only have to open the file for reading once.
-    if input.exists() {
-        let mut f = File::open(input).unwrap();
+    // Files that can't be opened or are too short to have a magic number just aren't matched.
+    if input.exists() && let Ok(mut f) = File::open(input) {
         // We need to read more bytes for WADs since they don't have a proper magic number.
         let mut magic_number = vec![0u8; 8];
-        f.read_exact(&mut magic_number).unwrap();
-        if magic_number == b"\x00\x00\x00\x20\x49\x73\x00\x00" || magic_number == b"\x00\x00\x00\x20\x69\x62\x00\x00" {
+        if f.read_exact(&mut magic_number).is_ok() && 
+            (magic_number == b"\x00\x00\x00\x20\x49\x73\x00\x00" || magic_number == b"\x00\x00\x00\x20\x69\x62\x00\x00") {
             return Some(WiiFileType::Wad);
         }
         let mut magic_number = vec![0u8; 4];
-        f.seek(SeekFrom::Start(0)).unwrap();
-        f.read_exact(&mut magic_number).unwrap();
-        if magic_number == b"\x55\xAA\x38\x2D" {
+        if f.seek(SeekFrom::Start(0)).is_ok() && f.read_exact(&mut magic_number).is_ok() &&
+            magic_number == b"\x55\xAA\x38\x2D" {
             return Some(WiiFileType::U8);
         }
+        // Savegames are encrypted up until the Bk header, so we need to check that instead.
+        let mut magic_number = vec![0u8; 8];
+        if f.seek(SeekFrom::Start(SAVE_BK_OFFSET)).is_ok() && f.read_exact(&mut magic_number).is_ok() &&
+            magic_number == SAVE_BK_MAGIC {
+            return Some(WiiFileType::Save);
+        }
     }
     
     // == No match found! ==
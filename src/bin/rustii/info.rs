@@ -4,47 +4,65 @@
 // Code for the info command in the rustii CLI.
 
 use std::{str, fs};
+use std::io::Read;
 use std::cell::RefCell;
 use std::path::Path;
 use std::rc::Rc;
 use anyhow::{bail, Context, Result};
-use rustii::archive::u8;
-use rustii::{title, title::cert, title::tmd, title::ticket, title::wad, title::versions};
+use rustii::archive::{imet, u8};
+use rustii::nand::save;
+use rustii::{title, title::cert, title::dol, title::tmd, title::ticket, title::wad, title::versions};
+use rustii::title::signed::SignedFile;
+use crate::batch;
 use crate::filetypes::{WiiFileType, identify_file_type};
 
 // Avoids duplicated code, since both TMD and Ticket info print the TID in the same way.
-fn print_tid(title_id: [u8; 8]) -> Result<()> {
-    let ascii = String::from_utf8_lossy(&title_id[4..]).trim_end_matches('\0').trim_start_matches('\0').to_owned();
-    let ascii_tid = if ascii.len() == 4 {
-        Some(ascii)
+fn print_tid(title_id: title::TitleId) -> Result<()> {
+    if let Some(ascii_tid) = title_id.ascii_low() {
+        println!("  Title ID: {} ({})", title_id, ascii_tid);
     } else {
-        None
-    };
-    if ascii_tid.is_some() {
-        println!("  Title ID: {} ({})", hex::encode(title_id).to_uppercase(), ascii_tid.unwrap());
-    } else {
-        println!("  Title ID: {}", hex::encode(title_id).to_uppercase());
+        println!("  Title ID: {}", title_id);
     }
     Ok(())
 }
 
 // Same as above, both the TMD and Ticket info print the title version in the same way.
-fn print_title_version(title_version: u16, title_id: [u8; 8], is_vwii: bool) -> Result<()> {
+fn print_title_version(title_version: u16, title_id: title::TitleId, is_vwii: bool) -> Result<()> {
     let converted_ver = versions::dec_to_standard(title_version, &hex::encode(title_id), Some(is_vwii));
     if hex::encode(title_id).eq("0000000100000001") {
         println!("  Title Version: {} (boot2v{})", title_version, title_version);
-    } else if hex::encode(title_id)[..8].eq("00000001") && converted_ver.is_some() {
-        println!("  Title Version: {} ({})", title_version, converted_ver.unwrap());
+    } else if hex::encode(title_id)[..8].eq("00000001") && let Some(converted_ver) = converted_ver {
+        println!("  Title Version: {} ({})", title_version, converted_ver);
     } else {
         println!("  Title Version: {}", title_version);
     }
+    // Flag IOSes that are older than the latest version available on the NUS.
+    if title_id.high() == [0, 0, 0, 1] && title_id.low()[0..3] == [0, 0, 0]
+        && let Some(latest) = versions::latest_ios_version(title_id.low()[3], is_vwii)
+        && title_version < latest {
+        println!("  Latest Version: {} (Outdated)", latest);
+    }
     Ok(())
 }
 
-fn print_tmd_info(tmd: tmd::TMD, cert: Option<cert::Certificate>) -> Result<()> {
+// Both the TMD and Ticket info print the certificate that they were signed with in the same way.
+fn print_signing_certificate(signature_issuer: &str, identity: cert::CertIdentity) {
+    match signature_issuer.rsplit_once('-') {
+        Some((issuer, name)) if !matches!(identity, cert::CertIdentity::Unknown(_)) => {
+            println!("  Certificate: {} ({})", name, identity.environment());
+            println!("  Certificate Issuer: {} ({})", issuer, identity.environment());
+        },
+        _ => println!("  Certificate Info: {} (Unknown)", signature_issuer),
+    }
+}
+
+fn print_tmd_info(tmd: tmd::TMD, cert: Option<cert::Certificate>, channel_name: Option<String>, show_records: bool) -> Result<()> {
     // Print all important keys from the TMD.
     println!("Title Info");
     print_tid(tmd.title_id())?;
+    if let Some(channel_name) = channel_name {
+        println!("  Channel Name: {}", channel_name);
+    }
     print_title_version(tmd.title_version, tmd.title_id(), tmd.is_vwii())?;
     println!("  TMD Version: {}", tmd.tmd_version);
     if hex::encode(tmd.ios_tid()).eq("0000000000000000") {
@@ -53,28 +71,9 @@ fn print_tmd_info(tmd: tmd::TMD, cert: Option<cert::Certificate>) -> Result<()>
     else if hex::encode(tmd.ios_tid()).ne(&format!("{:016X}", tmd.title_version)) {
         println!("  Required IOS: IOS{} ({})", tmd.ios_tid().last().unwrap(), hex::encode(tmd.ios_tid()).to_uppercase());
     }
-    let signature_issuer = String::from_utf8(Vec::from(tmd.signature_issuer)).unwrap_or_default();
-    if signature_issuer.contains("CP00000004") {
-        println!("  Certificate: CP00000004 (Retail)");
-        println!("  Certificate Issuer: Root-CA00000001 (Retail)");
-    }
-    else if signature_issuer.contains("CP00000007") {
-        println!("  Certificate: CP00000007 (Development)");
-        println!("  Certificate Issuer: Root-CA00000002 (Development)");
-    }
-    else if signature_issuer.contains("CP00000005") {
-        println!("  Certificate: CP00000005 (Development/Unknown)");
-        println!("  Certificate Issuer: Root-CA00000002 (Development)");
-    }
-    else if signature_issuer.contains("CP10000000") {
-        println!("  Certificate: CP10000000 (Arcade)");
-        println!("  Certificate Issuer: Root-CA10000000 (Arcade)");
-    }
-    else {
-        println!("  Certificate Info: {} (Unknown)", signature_issuer);
-    }
+    print_signing_certificate(&tmd.signature_issuer(), tmd.signing_certificate());
     let region = if hex::encode(tmd.title_id()).eq("0000000100000002") {
-        match versions::dec_to_standard(tmd.title_version, &hex::encode(tmd.title_id()), Some(tmd.is_vwii != 0))
+        match versions::dec_to_standard(tmd.title_version, &hex::encode(tmd.title_id()), Some(tmd.is_vwii()))
             .unwrap_or_default().chars().last() {
             Some('U') => "USA",
             Some('E') => "EUR",
@@ -89,21 +88,23 @@ fn print_tmd_info(tmd: tmd::TMD, cert: Option<cert::Certificate>) -> Result<()>
     };
     println!("  Region: {}", region);
     println!("  Title Type: {}", tmd.title_type()?);
-    println!("  vWii Title: {}", tmd.is_vwii != 0);
+    println!("  vWii Title: {}", tmd.is_vwii());
     println!("  DVD Video Access: {}", tmd.check_access_right(tmd::AccessRight::DVDVideo));
     println!("  AHB Access: {}", tmd.check_access_right(tmd::AccessRight::AHB));
-    if cert.is_some() {
-        let signing_str = match cert::verify_tmd(&cert.unwrap(), &tmd) {
-            Ok(result) => match result {
-                true => "Valid (Unmodified TMD)",
-                false => {
-                    if tmd.is_fakesigned() {
-                        "Fakesigned"
-                    } else {
-                        "Invalid (Modified TMD)"
-                    }
-                },
-            },
+    let ratings: Vec<String> = tmd::RatingAgency::ALL.iter()
+        .filter_map(|&agency| tmd.ratings.get(agency).map(|age| format!("{} {}+", agency, age)))
+        .collect();
+    if ratings.is_empty() {
+        println!("  Age Ratings: None");
+    } else {
+        println!("  Age Ratings: {}", ratings.join(", "));
+    }
+    if let Some(cert) = cert {
+        let signing_str = match cert::verify_tmd_detailed(&cert, &tmd) {
+            Ok(cert::SignatureStatus::Valid) => "Valid (Unmodified TMD)",
+            Ok(cert::SignatureStatus::ValidTruchaOnly) => "Trucha Only (Signed TMD with Malformed Padding)",
+            Ok(cert::SignatureStatus::Fakesigned) => "Fakesigned",
+            Ok(cert::SignatureStatus::Invalid) => "Invalid (Modified TMD)",
             Err(_) => {
                 if tmd.is_fakesigned() {
                     "Fakesigned"
@@ -117,14 +118,30 @@ fn print_tmd_info(tmd: tmd::TMD, cert: Option<cert::Certificate>) -> Result<()>
         println!("  Fakesigned: {}", tmd.is_fakesigned());
     }
     println!("\nContent Info");
-    println!("  Total Contents: {}", tmd.content_records.borrow().len());
+    println!("  Total Contents: {}", tmd.num_contents());
     println!("  Boot Content Index: {}", tmd.boot_index);
+    let summary = tmd.content_summary();
+    let content_types = [tmd::ContentType::Normal, tmd::ContentType::Shared, tmd::ContentType::DLC,
+        tmd::ContentType::Development, tmd::ContentType::HashTree];
+    for content_type in content_types {
+        let type_summary = summary.get(&content_type);
+        if type_summary.count > 0 {
+            println!("  {} Contents: {} ({} bytes)", content_type, type_summary.count, type_summary.size);
+        }
+    }
+    if !summary.shared_content_ids.is_empty() {
+        let shared_cids: Vec<String> = summary.shared_content_ids.iter().map(|cid| format!("{:08X}", cid)).collect();
+        println!("  Shared Content IDs: {}", shared_cids.join(", "));
+    }
+    if !show_records {
+        return Ok(());
+    }
     println!("  Content Records:");
-    for content in tmd.content_records.borrow().iter() {
+    for content in tmd.content_records.read().iter() {
         println!("    Content Index: {}", content.index);
         println!("      Content ID: {:08X}", content.content_id);
         println!("      Content Type: {}", content.content_type);
-        println!("      Content Size: {} bytes ({} blocks)", content.content_size, title::bytes_to_blocks(content.content_size as usize));
+        println!("      Content Size: {} bytes ({} blocks)", content.content_size, title::bytes_to_blocks(content.content_size));
         println!("      Content Hash: {}", hex::encode(content.content_hash));
     }
     Ok(())
@@ -134,45 +151,57 @@ fn print_ticket_info(ticket: ticket::Ticket, cert: Option<cert::Certificate>) ->
     // Print all important keys from the Ticket.
     println!("Ticket Info");
     print_tid(ticket.title_id())?;
-    print_title_version(ticket.title_version, ticket.title_id(), ticket.common_key_index == 2)?;
+    print_title_version(ticket.title_version, ticket.title_id(), ticket.common_key_index() == 2)?;
     println!("  Ticket Version: {}", ticket.ticket_version);
-    let signature_issuer = String::from_utf8(Vec::from(ticket.signature_issuer)).unwrap_or_default();
-    if signature_issuer.contains("XS00000003") {
-        println!("  Certificate: XS00000003 (Retail)");
-        println!("  Certificate Issuer: Root-CA00000001 (Retail)");
-    } else if signature_issuer.contains("XS00000006") {
-        println!("  Certificate: XS00000006 (Development)");
-        println!("  Certificate Issuer: Root-CA00000002 (Development)");
-    } else if signature_issuer.contains("XS00000004") {
-        println!("  Certificate: XS00000004 (Development/Unknown)");
-        println!("  Certificate Issuer: Root-CA00000002 (Development)");
-    } else {
-        println!("  Certificate Info: {} (Unknown)", signature_issuer);
-    }
-    let key = match ticket.common_key_index {
-        0 => {
+    print_signing_certificate(&ticket.signature_issuer(), ticket.signing_certificate());
+    // Out of range indices are treated as index 0 by the console, so they use the common key too.
+    let key = match ticket.common_key_index() {
+        1 => "Korean",
+        2 => "vWii",
+        _ => {
             if ticket.is_dev() { "Common (Development)" }
             else { "Common (Retail)" }
         }
-        1 => "Korean",
-        2 => "vWii",
-        _ => "Unknown (Likely Common)"
     };
     println!("  Decryption Key: {}", key);
+    if !ticket.has_valid_common_key_index() {
+        println!("  Warning: Common key index {} is out of range!", ticket.common_key_index());
+    }
     println!("  Title Key (Encrypted): {}", hex::encode(ticket.title_key));
     println!("  Title Key (Decrypted): {}", hex::encode(ticket.dec_title_key()));
-    if cert.is_some() {
-        let signing_str = match cert::verify_ticket(&cert.unwrap(), &ticket) {
-            Ok(result) => match result {
-                true => "Valid (Unmodified Ticket)",
-                false => {
-                    if ticket.is_fakesigned() {
-                        "Fakesigned"
-                    } else {
-                        "Invalid (Modified Ticket)"
-                    }
-                },
-            },
+    println!("  Personalized: {}", ticket.is_personalized());
+    if ticket.is_personalized() {
+        println!("  Console ID: {}", hex::encode_upper(ticket.console_id));
+    }
+    if ticket.all_contents_permitted() {
+        println!("  Content Access: All contents permitted");
+    } else {
+        let permitted: Vec<String> = ticket.permitted_contents().iter().map(|index| index.to_string()).collect();
+        if permitted.is_empty() {
+            println!("  Content Access: No contents permitted");
+        } else {
+            println!("  Content Access: Indices {}", permitted.join(", "));
+        }
+    }
+    let limits: Vec<String> = ticket.limits().iter()
+        .filter(|(limit_type, _)| *limit_type != ticket::LimitType::None)
+        .map(|(limit_type, limit_max)| match limit_type {
+            ticket::LimitType::Time => format!("{} minutes", limit_max),
+            ticket::LimitType::LaunchCount => format!("{} launches", limit_max),
+            _ => format!("{} (max {})", limit_type, limit_max),
+        })
+        .collect();
+    if limits.is_empty() {
+        println!("  Title Limits: None");
+    } else {
+        println!("  Title Limits: {}", limits.join(", "));
+    }
+    if let Some(cert) = cert {
+        let signing_str = match cert::verify_ticket_detailed(&cert, &ticket) {
+            Ok(cert::SignatureStatus::Valid) => "Valid (Unmodified Ticket)",
+            Ok(cert::SignatureStatus::ValidTruchaOnly) => "Trucha Only (Signed Ticket with Malformed Padding)",
+            Ok(cert::SignatureStatus::Fakesigned) => "Fakesigned",
+            Ok(cert::SignatureStatus::Invalid) => "Invalid (Modified Ticket)",
             Err(_) => {
                 if ticket.is_fakesigned() {
                     "Fakesigned"
@@ -188,39 +217,36 @@ fn print_ticket_info(ticket: ticket::Ticket, cert: Option<cert::Certificate>) ->
     Ok(())
 }
 
-fn print_wad_info(wad: wad::WAD) -> Result<()> {
-    println!("WAD Info");
-    match wad.header.wad_type {
-        wad::WADType::ImportBoot => { println!("  WAD Type: boot2") },
-        wad::WADType::Installable => { println!("  WAD Type: Standard Installable") },
-    }
-    // Create a Title for size info, signing info and TMD/Ticket info.
-    let title = title::Title::from_wad(&wad).with_context(|| "The provided WAD file could not be parsed, and is likely invalid.")?;
-    let min_size_blocks = title::bytes_to_blocks(title.title_size(None)?);
-    let max_size_blocks = title::bytes_to_blocks(title.title_size(Some(true))?);
-    if min_size_blocks == max_size_blocks {
-        println!("  Installed Size: {} blocks", min_size_blocks);
-    } else {
-        println!("  Installed Size: {}-{} blocks", min_size_blocks, max_size_blocks);
+/// Determines the signing status of a Title, used by both the full and brief WAD info output and
+/// by commands that modify a WAD's signatures.
+pub fn wad_signing_status(title: &title::Title) -> Result<&'static str> {
+    // Titles without a Ticket (only boot2) can only have their TMD checked.
+    if !title.has_ticket() {
+        let signing_str = match cert::verify_tmd_detailed(&title.cert_chain.tmd_cert(), &title.tmd) {
+            Ok(cert::SignatureStatus::Valid) => "Legitimate (Unmodified TMD, No Ticket)",
+            Ok(cert::SignatureStatus::ValidTruchaOnly) => "Trucha Only (Malformed TMD Signature, No Ticket)",
+            _ if title.tmd.is_fakesigned() => "Fakesigned (TMD, No Ticket)",
+            _ => "Illegitimate (Modified TMD, No Ticket)",
+        };
+        return Ok(signing_str);
     }
-    let min_size = title.title_size(None)? as f64 / 1048576.0;
-    let max_size = title.title_size(Some(true))? as f64 / 1048576.0;
-    if min_size == max_size {
-        println!("  Installed Size (MB): {:.2} MB", min_size);
-    } else {
-        println!("  Installed Size (MB): {:.2}-{:.2} MB", min_size, max_size);
+    // Signatures that were made with a real key but have malformed padding fail verify(), but IOS
+    // still accepts them if it has the trucha bug, so they're identified separately first.
+    let tmd_status = cert::verify_tmd_detailed(&title.cert_chain.tmd_cert(), &title.tmd).unwrap_or(cert::SignatureStatus::Invalid);
+    let ticket_status = cert::verify_ticket_detailed(&title.cert_chain.ticket_cert(), &title.ticket).unwrap_or(cert::SignatureStatus::Invalid);
+    if tmd_status.is_genuine() && ticket_status.is_genuine() &&
+        (tmd_status == cert::SignatureStatus::ValidTruchaOnly || ticket_status == cert::SignatureStatus::ValidTruchaOnly) {
+        return Ok("Trucha Only (Signed TMD + Ticket with Malformed Padding)");
     }
-    println!("  Has Meta/Footer: {}", wad.meta_size() != 0);
-    println!("  Has CRL: {}", wad.crl_size() != 0);
     let signing_str = match title.verify() {
         Ok(result) => match result {
             true => "Legitimate (Unmodified TMD + Ticket)",
             false => {
                 if title.is_fakesigned() {
                     "Fakesigned"
-                } else if cert::verify_tmd(&title.cert_chain.tmd_cert(), &title.tmd)? {
+                } else if matches!(cert::verify_tmd(&title.cert_chain.tmd_cert(), &title.tmd), Ok(true)) {
                     "Piratelegit (Unmodified TMD, Modified Ticket)"
-                } else if  cert::verify_ticket(&title.cert_chain.ticket_cert(), &title.ticket)? {
+                } else if matches!(cert::verify_ticket(&title.cert_chain.ticket_cert(), &title.ticket), Ok(true)) {
                     "Edited (Modified TMD, Unmodified Ticket)"
                 } else {
                     "Illegitimate (Modified TMD + Ticket)"
@@ -235,14 +261,189 @@ fn print_wad_info(wad: wad::WAD) -> Result<()> {
             }
         }
     };
+    Ok(signing_str)
+}
+
+// The signing status shown for WADs whose certificate chain can't be parsed, since nothing can be
+// verified without it.
+const SIGNING_STATUS_UNAVAILABLE: &str = "Unavailable (Invalid Certificate Chain)";
+
+// Parses a WAD into a Title. A WAD with a broken certificate chain can still have its other
+// components displayed, so that case returns None rather than an error.
+fn title_from_wad(wad: &wad::WAD, in_path: &Path) -> Result<Option<title::Title>> {
+    if wad.parsed_cert_chain().is_err() {
+        return Ok(None);
+    }
+    let title = title::Title::from_wad(wad)
+        .with_context(|| format!("The WAD file \"{}\" could not be parsed, and is likely invalid.", in_path.display()))?;
+    Ok(Some(title))
+}
+
+// Prints the offset and size of each section of a WAD as a table, along with the padding after it.
+fn print_wad_layout(layout: &wad::WadLayout) {
+    println!("  Layout:");
+    println!("    {:<18} {:>10} {:>10} {:>10} {:>10}  Padding Status", "Section", "Offset", "Size", "Pad Offset", "Pad Size");
+    for entry in &layout.sections {
+        let status = match entry.padding.non_zero_offset {
+            _ if entry.padding.size == 0 => String::from("-"),
+            Some(offset) => format!("Non-zero at {:#X}", offset),
+            None => String::from("Zero-filled"),
+        };
+        println!("    {:<18} {:>#10X} {:>#10X} {:>#10X} {:>#10X}  {}", entry.section.to_string(), entry.offset, entry.size,
+            entry.padding.offset, entry.padding.size, status);
+    }
+    println!("    Total Size: {:#X} bytes", layout.total_size);
+}
+
+fn print_wad_info(wad: wad::WAD, data: &[u8], in_path: &Path, show_records: bool, show_layout: bool) -> Result<()> {
+    println!("WAD Info");
+    match wad.header.wad_type {
+        wad::WADType::ImportBoot => { println!("  WAD Type: boot2") },
+        wad::WADType::Installable => { println!("  WAD Type: Standard Installable") },
+    }
+    // Create a Title for size info, signing info and TMD/Ticket info.
+    let title = title_from_wad(&wad, in_path)?;
+    if matches!(wad.header.wad_type, wad::WADType::ImportBoot) {
+        // boot2 isn't installed to the NAND's filesystem like other titles, so its installed size
+        // isn't meaningful. Its version and contents are more useful.
+        if let Ok(tmd) = wad.parsed_tmd() {
+            println!("  boot2 Version: boot2v{}", tmd.title_version);
+            println!("  Content Structure:");
+            for record in tmd.content_records.read().iter() {
+                println!("    - Content {:08X} (Index {}): {} bytes, {}", record.content_id, record.index, record.content_size, record.content_type);
+            }
+        }
+    } else if let Some(title) = &title {
+        let min_size_blocks = title.title_size_blocks(title::SharedContentAccounting::Exclude)?;
+        let max_size_blocks = title.title_size_blocks(title::SharedContentAccounting::Include)?;
+        if min_size_blocks == max_size_blocks {
+            println!("  Installed Size: {} blocks", min_size_blocks);
+        } else {
+            println!("  Installed Size: {}-{} blocks", min_size_blocks, max_size_blocks);
+        }
+        let min_size = title.title_size(title::SharedContentAccounting::Exclude)? as f64 / 1048576.0;
+        let max_size = title.title_size(title::SharedContentAccounting::Include)? as f64 / 1048576.0;
+        if min_size == max_size {
+            println!("  Installed Size (MB): {:.2} MB", min_size);
+        } else {
+            println!("  Installed Size (MB): {:.2}-{:.2} MB", min_size, max_size);
+        }
+    }
+    println!("  Has Meta/Footer: {}", wad.meta_size() != 0);
+    if let Some(build_info) = title.as_ref().and_then(|title| title.parsed_meta()).and_then(|meta| meta.build_info) {
+        println!("  Built: {}", build_info);
+    }
+    println!("  Has CRL: {}", wad.crl_size() != 0);
+    let signing_str = match &title {
+        Some(title) => wad_signing_status(title)?,
+        None => SIGNING_STATUS_UNAVAILABLE,
+    };
     println!("  Signing Status: {}", signing_str);
-    println!();
-    print_ticket_info(title.ticket, Some(title.cert_chain.ticket_cert()))?;
-    println!();
-    print_tmd_info(title.tmd, Some(title.cert_chain.tmd_cert()))?;
+    let mut anomalies = wad.validate();
+    anomalies.extend(wad::WAD::validate_layout(data).unwrap_or_default());
+    if anomalies.is_empty() {
+        println!("  Anomalies: None");
+    } else {
+        println!("  Anomalies:");
+        for anomaly in &anomalies {
+            println!("    - {}", anomaly);
+        }
+    }
+    if show_layout {
+        print_wad_layout(&wad.layout());
+    }
+    match title {
+        Some(title) => {
+            // Prefer the English channel name, but fall back on the first non-empty name if there
+            // isn't one.
+            let channel_name = title.channel_names().ok().and_then(|names| {
+                std::iter::once(imet::Language::English).chain(imet::Language::ALL)
+                    .map(|language| names[&language].clone())
+                    .find(|name| !name.is_empty())
+            });
+            println!();
+            if title.has_ticket() {
+                print_ticket_info(title.ticket.clone(), Some(title.cert_chain.ticket_cert()))?;
+            } else {
+                println!("Ticket Info");
+                println!("  Not present in WAD");
+            }
+            println!();
+            print_tmd_info(title.tmd.clone(), Some(title.cert_chain.tmd_cert()), channel_name, show_records)?;
+            // boot2 doesn't have a boot content in the same sense as other titles.
+            if !matches!(wad.header.wad_type, wad::WADType::ImportBoot) {
+                println!();
+                print_boot_content_info(&title);
+            }
+        },
+        None => {
+            println!("  Note: The certificate chain could not be parsed, so signatures cannot be verified.");
+            let tmd = wad.parsed_tmd().with_context(|| format!("The TMD in WAD file \"{}\" could not be parsed, and is likely invalid.", in_path.display()))?;
+            println!();
+            if matches!(wad.header.wad_type, wad::WADType::ImportBoot) && wad.ticket().is_empty() {
+                println!("Ticket Info");
+                println!("  Not present in WAD");
+            } else {
+                let ticket = wad.parsed_ticket().with_context(|| format!("The Ticket in WAD file \"{}\" could not be parsed, and is likely invalid.", in_path.display()))?;
+                print_ticket_info(ticket, None)?;
+            }
+            println!();
+            print_tmd_info(tmd, None, None, show_records)?;
+        }
+    }
     Ok(())
 }
 
+// Guesses what kind of file a boot content that isn't a DOL is from its first few bytes. IOS
+// kernels and modules are stored as ELFs, sometimes behind a small loader header.
+fn guess_content_type(header: &[u8]) -> &'static str {
+    if header.windows(4).any(|window| window == b"\x7FELF") {
+        "ELF"
+    } else if header.starts_with(b"\x55\xAA\x38\x2D") {
+        "U8 Archive"
+    } else if imet::find_imet_offset(header).is_some() {
+        "Banner (IMET)"
+    } else if header.starts_with(b"LZ77") {
+        "LZ77 Compressed Data"
+    } else {
+        "Unknown"
+    }
+}
+
+fn print_boot_content_info(title: &title::Title) {
+    println!("Boot Content");
+    println!("  Boot Index: {}", title.tmd.boot_index);
+    let Ok(position) = title.get_boot_content_index() else {
+        println!("  Not found in content records");
+        return;
+    };
+    let record = title.tmd.content_records.read()[position].clone();
+    println!("  Content ID: {:08X}", record.content_id);
+    println!("  Content Size: {} bytes", record.content_size);
+    let mut header: Vec<u8> = Vec::new();
+    match title.open_boot_content() {
+        Ok(reader) => {
+            // Only the start of the content is needed to identify it, so don't decrypt the rest.
+            if let Err(err) = reader.take(dol::DOL_HEADER_SIZE as u64).read_to_end(&mut header) {
+                println!("  Content Type: Unknown (could not be read: {})", err);
+                return;
+            }
+        },
+        Err(_) => {
+            println!("  Content Type: Unknown (not present in WAD)");
+            return;
+        }
+    }
+    match dol::DolHeader::from_bytes(&header) {
+        Ok(dol_header) => {
+            println!("  Content Type: DOL");
+            println!("  DOL Entry Point: {:#010X}", dol_header.entry_point);
+            println!("  DOL Size: {} bytes", dol_header.file_size());
+        },
+        Err(_) => println!("  Content Type: {} (not a DOL)", guess_content_type(&header)),
+    }
+}
+
 fn print_full_tree(dir: &Rc<RefCell<u8::U8Directory>>, indent: usize) {
     let prefix = "  ".repeat(indent);
     let dir_name = if !dir.borrow().name.is_empty() {
@@ -272,31 +473,283 @@ fn print_u8_info(u8_archive: u8::U8Archive) -> Result<()> {
     Ok(())
 }
 
-pub fn info(input: &str) -> Result<()> {
+fn print_save_info(save_data: save::SaveData) -> Result<()> {
+    println!("Savegame Info");
+    println!("  Title ID: {}", save_data.title_id);
+    if let Some(ascii_tid) = save_data.title_id.ascii_low() {
+        println!("  Title ID (ASCII): {}", ascii_tid);
+    }
+    println!("  Title: {}", save_data.banner.title);
+    if !save_data.banner.subtitle.is_empty() {
+        println!("  Subtitle: {}", save_data.banner.subtitle);
+    }
+    println!("  Permissions: {:#04X}", save_data.permissions);
+    println!("  Console ID: {:08X}", save_data.ng_id);
+    println!("  MAC Address: {}", save_data.mac_address.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(":"));
+    println!("  File Count: {}", save_data.files.len());
+    println!("  Save Data:");
+    for file in &save_data.files {
+        match file.file_type {
+            save::SaveFileType::Directory => println!("    D {}", file.name),
+            save::SaveFileType::File => println!("    F {} ({} bytes)", file.name, file.data.len()),
+        }
+    }
+    Ok(())
+}
+
+// The parsed contents of a file that info can be displayed for.
+enum InfoInput {
+    Signed(Box<SignedFile>),
+    U8(u8::U8Archive),
+    Save(save::SaveData),
+}
+
+// Reads and parses an input file so that its info can be displayed. Signed files are identified by
+// their structure, so that files with misleading names are still displayed correctly, while other
+// files are identified by their name or magic number.
+fn read_input(input: &str) -> Result<(InfoInput, Vec<u8>)> {
     let in_path = Path::new(input);
     if !in_path.exists() {
         bail!("Input file \"{}\" does not exist.", in_path.display());
     }
-    match identify_file_type(input) {
-        Some(WiiFileType::Tmd) => {
-            let tmd = tmd::TMD::from_bytes(&fs::read(in_path)?).with_context(|| "The provided TMD file could not be parsed, and is likely invalid.")?;
-            print_tmd_info(tmd, None)?;
+    let data = fs::read(in_path).with_context(|| format!("Input file \"{}\" could not be read.", in_path.display()))?;
+    if let Some(file_type) = SignedFile::identify(&data) {
+        let file = SignedFile::from_bytes(&data)
+            .with_context(|| format!("The {} file \"{}\" could not be parsed, and is likely invalid.", file_type, in_path.display()))?;
+        return Ok((InfoInput::Signed(Box::new(file)), data));
+    }
+    let parsed = match identify_file_type(input) {
+        Some(WiiFileType::U8) => {
+            InfoInput::U8(u8::U8Archive::from_bytes(&data).with_context(|| format!("The U8 archive \"{}\" could not be parsed, and is likely invalid.", in_path.display()))?)
+        },
+        Some(WiiFileType::Save) => {
+            InfoInput::Save(save::SaveData::from_bytes(&data).with_context(|| format!("The savegame \"{}\" could not be parsed, and is likely invalid.", in_path.display()))?)
+        },
+        // Files named like a signed file that don't have the structure of one are most likely
+        // truncated or corrupted.
+        Some(WiiFileType::Wad) => bail!("The WAD file \"{}\" could not be parsed, and is likely invalid.", in_path.display()),
+        Some(WiiFileType::Tmd) => bail!("The TMD file \"{}\" could not be parsed, and is likely invalid.", in_path.display()),
+        Some(WiiFileType::Ticket) => bail!("The Ticket file \"{}\" could not be parsed, and is likely invalid.", in_path.display()),
+        None => bail!("Information cannot be displayed for this file type."),
+    };
+    Ok((parsed, data))
+}
+
+// Prints a compact, single line summary of a file, used when getting info for many files at once.
+fn print_brief_info(input: &str) -> Result<()> {
+    let in_path = Path::new(input);
+    let (file, _) = read_input(input)?;
+    match file {
+        InfoInput::Signed(file) => match *file {
+            SignedFile::Tmd(tmd) => {
+                let signing_str = if tmd.is_fakesigned() { "Fakesigned" } else { "Not Fakesigned" };
+                println!("{}: TMD | {} | v{} | {} | {}", input, tmd.title_id(), tmd.title_version,
+                    tmd.title_type()?, signing_str);
+            },
+            SignedFile::Ticket(ticket) => {
+                let signing_str = if ticket.is_fakesigned() { "Fakesigned" } else { "Not Fakesigned" };
+                println!("{}: Ticket | {} | v{} | {}", input, ticket.title_id(), ticket.title_version,
+                    signing_str);
+            },
+            SignedFile::Wad(wad) => {
+                match title_from_wad(&wad, in_path)? {
+                    Some(title) => {
+                        println!("{}: WAD | {} | v{} | {} | {}", input, title.tmd.title_id(), title.tmd.title_version,
+                            title.tmd.title_type()?, wad_signing_status(&title)?);
+                    },
+                    None => {
+                        let tmd = wad.parsed_tmd().with_context(|| format!("The TMD in WAD file \"{}\" could not be parsed, and is likely invalid.", in_path.display()))?;
+                        println!("{}: WAD | {} | v{} | {} | {}", input, tmd.title_id(), tmd.title_version,
+                            tmd.title_type()?, SIGNING_STATUS_UNAVAILABLE);
+                    }
+                }
+            },
         },
-        Some(WiiFileType::Ticket) => {
-            let ticket = ticket::Ticket::from_bytes(&fs::read(in_path)?).with_context(|| "The provided Ticket file could not be parsed, and is likely invalid.")?;
-            print_ticket_info(ticket, None)?;
+        InfoInput::U8(u8_archive) => {
+            println!("{}: U8 Archive | {} nodes", input, u8_archive.node_tree.borrow().count());
         },
-        Some(WiiFileType::Wad) => {
-            let wad = wad::WAD::from_bytes(&fs::read(in_path)?).with_context(|| "The provided WAD file could not be parsed, and is likely invalid.")?;
-            print_wad_info(wad)?;
+        InfoInput::Save(save_data) => {
+            println!("{}: Savegame | {} | {} | {} files", input, save_data.title_id, save_data.banner.title, save_data.files.len());
         },
-        Some(WiiFileType::U8) => {
-            let u8_archive = u8::U8Archive::from_bytes(&fs::read(in_path)?).with_context(|| "The provided U8 archive could not be parsed, and is likely invalid.")?;
-            print_u8_info(u8_archive)?;
+    }
+    Ok(())
+}
+
+fn print_info(input: &str, show_records: bool, show_layout: bool) -> Result<()> {
+    let in_path = Path::new(input);
+    let (file, data) = read_input(input)?;
+    match file {
+        InfoInput::Signed(file) => match *file {
+            SignedFile::Tmd(tmd) => print_tmd_info(tmd, None, None, show_records)?,
+            SignedFile::Ticket(ticket) => print_ticket_info(ticket, None)?,
+            SignedFile::Wad(wad) => print_wad_info(*wad, &data, in_path, show_records, show_layout)?,
+        },
+        InfoInput::U8(u8_archive) => print_u8_info(u8_archive)?,
+        InfoInput::Save(save_data) => print_save_info(save_data)?,
+    }
+    Ok(())
+}
+
+pub fn info(inputs: &[String], brief: &bool, no_records: &bool, layout: &bool) -> Result<()> {
+    let inputs = batch::expand_inputs(inputs);
+    let mut first = true;
+    batch::run_batch(&inputs, |input| {
+        if *brief {
+            print_brief_info(input)
+        } else {
+            // Separate the full output for each file when more than one is being displayed.
+            if !first {
+                println!();
+            }
+            first = false;
+            print_info(input, !*no_records, *layout)
         }
-        None => {
-            bail!("Information cannot be displayed for this file type.");
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustii::title::signed::SignedFileType;
+
+    const TITLE_ID: [u8; 8] = [0x00, 0x01, 0x00, 0x01, 0x52, 0x53, 0x54, 0x49];
+
+    fn signing_string(issuer: &str) -> [u8; 64] {
+        let mut buf = [0u8; 64];
+        buf[..issuer.len()].copy_from_slice(issuer.as_bytes());
+        buf
+    }
+
+    fn build_tmd() -> Vec<u8> {
+        let mut buf = vec![0u8; 0x1E4];
+        buf[0..4].copy_from_slice(&0x00010001u32.to_be_bytes());
+        buf[0x140..0x180].copy_from_slice(&signing_string("Root-CA00000001-CP00000004"));
+        buf[0x184..0x18C].copy_from_slice(&[0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x38]);
+        buf[0x18C..0x194].copy_from_slice(&TITLE_ID);
+        buf[0x194..0x198].copy_from_slice(&0x00000001u32.to_be_bytes());
+        buf
+    }
+
+    fn build_ticket(version: u8) -> Vec<u8> {
+        let mut buf = vec![0u8; 0x2A4];
+        buf[0..4].copy_from_slice(&0x00010001u32.to_be_bytes());
+        buf[0x140..0x180].copy_from_slice(&signing_string("Root-CA00000001-XS00000003"));
+        buf[0x1BC] = version;
+        buf[0x1DC..0x1E4].copy_from_slice(&TITLE_ID);
+        buf
+    }
+
+    fn build_wad(cert_chain: &[u8], ticket: &[u8], tmd: &[u8]) -> Vec<u8> {
+        build_wad_with_type(b"Is", cert_chain, ticket, tmd)
+    }
+
+    fn build_wad_with_type(wad_type: &[u8; 2], cert_chain: &[u8], ticket: &[u8], tmd: &[u8]) -> Vec<u8> {
+        let mut buf: Vec<u8> = Vec::new();
+        buf.extend_from_slice(&0x20u32.to_be_bytes());
+        buf.extend_from_slice(wad_type);
+        buf.extend_from_slice(&0u16.to_be_bytes());
+        for size in [cert_chain.len(), 0, ticket.len(), tmd.len(), 0, 0] {
+            buf.extend_from_slice(&(size as u32).to_be_bytes());
+        }
+        for section in [cert_chain, ticket, tmd] {
+            buf.resize((buf.len() + 63) & !63, 0);
+            buf.extend_from_slice(section);
+        }
+        buf.resize((buf.len() + 63) & !63, 0);
+        buf
+    }
+
+    fn write_input(dir: &tempfile::TempDir, name: &str, data: &[u8]) -> String {
+        let path = dir.path().join(name);
+        fs::write(&path, data).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_info_truncated_tmd() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = write_input(&dir, "title.tmd", &build_tmd()[..0x100]);
+        let err = print_info(&input, true, false).unwrap_err();
+        assert!(err.to_string().contains("title.tmd"));
+        assert!(print_brief_info(&input).is_err());
+        let input = write_input(&dir, "complete.tmd", &build_tmd());
+        assert!(print_info(&input, true, false).is_ok());
+    }
+
+    #[test]
+    fn test_info_bad_ticket_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = write_input(&dir, "title.tik", &build_ticket(1));
+        let err = print_info(&input, true, false).unwrap_err();
+        assert!(err.to_string().contains("title.tik"));
+        assert!(print_brief_info(&input).is_err());
+        let input = write_input(&dir, "valid.tik", &build_ticket(0));
+        assert!(print_info(&input, true, false).is_ok());
+    }
+
+    #[test]
+    fn test_info_wad_garbage_cert_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = write_input(&dir, "title.wad", &build_wad(&[0xAB; 0x100], &build_ticket(0), &build_tmd()));
+        assert!(print_info(&input, true, false).is_ok());
+        assert!(print_info(&input, true, true).is_ok());
+        assert!(print_brief_info(&input).is_ok());
+        // A WAD with a broken TMD still can't be displayed, but shouldn't panic.
+        let input = write_input(&dir, "broken.wad", &build_wad(&[0xAB; 0x100], &build_ticket(0), &build_tmd()[..0x100]));
+        assert!(print_info(&input, true, false).is_err());
+    }
+
+    #[test]
+    fn test_info_boot2_wad() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut tmd = build_tmd();
+        tmd[0x18C..0x194].copy_from_slice(&title::TitleId::BOOT2.to_bytes());
+        let input = write_input(&dir, "boot2.wad", &build_wad_with_type(b"ib", &[0xAB; 0x100], &[], &tmd));
+        let (file, _) = read_input(&input).unwrap();
+        let InfoInput::Signed(file) = file else { panic!("expected a WAD") };
+        let SignedFile::Wad(wad) = *file else { panic!("expected a WAD") };
+        assert!(matches!(wad.header.wad_type, wad::WADType::ImportBoot));
+        assert!(print_info(&input, true, false).is_ok());
+        assert!(print_brief_info(&input).is_ok());
+    }
+
+    #[test]
+    fn test_info_short_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = write_input(&dir, "tiny.bin", &[0x55, 0xAA]);
+        assert!(print_info(&input, true, false).is_err());
+    }
+
+    #[test]
+    fn test_guess_content_type() {
+        let mut ios_kernel = vec![0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+        ios_kernel.extend(b"\x7FELF\x01\x02\x01");
+        assert_eq!(guess_content_type(&ios_kernel), "ELF");
+        assert_eq!(guess_content_type(b"\x55\xAA\x38\x2D\x00\x00\x00\x20"), "U8 Archive");
+        assert_eq!(guess_content_type(b"LZ77\x10\x00\x01\x00"), "LZ77 Compressed Data");
+        assert_eq!(guess_content_type(&[0x5A; 0x100]), "Unknown");
+        assert_eq!(guess_content_type(&[]), "Unknown");
+    }
+
+    #[test]
+    fn test_info_misleading_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        // Signed files are identified by their structure, so the name they're given doesn't matter.
+        let wad = build_wad(&[0xAB; 0x100], &build_ticket(0), &build_tmd());
+        for (name, data, expected) in [
+            ("title.tmd", wad.clone(), SignedFileType::Wad),
+            ("title.bin", wad, SignedFileType::Wad),
+            ("title.wad", build_tmd(), SignedFileType::Tmd),
+            ("title.tik", build_tmd(), SignedFileType::Tmd),
+            ("tmd.bin", build_ticket(0), SignedFileType::Ticket),
+            ("title.app", build_ticket(0), SignedFileType::Ticket),
+        ] {
+            let input = write_input(&dir, name, &data);
+            let (file, _) = read_input(&input).unwrap();
+            let InfoInput::Signed(file) = file else { panic!("expected a signed file") };
+            assert_eq!(file.file_type(), expected);
+            assert!(print_info(&input, true, false).is_ok());
+            assert!(print_brief_info(&input).is_ok());
         }
     }
-    Ok(())
 }
@@ -4,19 +4,29 @@
 // Base for the rustii CLI that handles argument parsing and directs execution to the proper module.
 
 mod archive;
+mod batch;
+mod diff;
 mod title;
 mod filetypes;
 mod info;
 mod nand;
+mod output;
 
 use anyhow::Result;
 use clap::{Subcommand, Parser};
+use log::LevelFilter;
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+    /// Show more detailed output, repeat for even more detail (-vv)
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+    /// Only show errors
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
 }
 
 #[derive(Subcommand)]
@@ -27,6 +37,26 @@ enum Commands {
         #[command(subcommand)]
         command: archive::ash::Commands,
     },
+    /// Pack/unpack a channel banner
+    Banner {
+        #[command(subcommand)]
+        command: archive::banner::Commands,
+    },
+    /// Export the certificates in a certificate chain
+    Cert {
+        #[command(subcommand)]
+        command: title::cert::Commands,
+    },
+    /// Compare two TMDs, Tickets, or WADs and report their differences
+    Diff {
+        /// The path to the first file to compare
+        first: String,
+        /// The path to the second file to compare
+        second: String,
+        /// Also compare the decrypted data of contents that exist in both files (WADs only)
+        #[arg(long)]
+        contents: bool,
+    },
     /// Manage Wii EmuNANDs
     Emunand {
         #[command(subcommand)]
@@ -34,32 +64,80 @@ enum Commands {
     },
     /// Fakesign a TMD, Ticket, or WAD (trucha bug)
     Fakesign {
-        /// The path to a TMD, Ticket, or WAD
-        input: String,
-        /// An (optional) output name; defaults to overwriting input file if not provided
+        /// The path(s) to one or more TMDs, Tickets, or WADs
+        #[arg(num_args = 1.., required = true)]
+        input: Vec<String>,
+        /// An (optional) output name; defaults to overwriting input file if not provided (single input only)
         #[arg(short, long)]
         output: Option<String>,
+        #[command(flatten)]
+        output_options: output::OutputOptions,
+    },
+    /// Get the SHA-1 hash of a content file, optionally comparing it against a TMD
+    Hash {
+        /// The path to the file to hash
+        input: String,
+        /// The path to a TMD to compare the hash against
+        #[arg(long)]
+        tmd: Option<String>,
+        /// The index of the content record in the TMD to compare against
+        #[arg(short, long, requires = "tmd", conflicts_with = "cid")]
+        index: Option<u16>,
+        /// The Content ID of the content record in the TMD to compare against
+        #[arg(short, long, requires = "tmd")]
+        cid: Option<String>,
     },
     /// Get information about a TMD, Ticket, or WAD
     Info {
-        /// The path to a TMD, Ticket, or WAD
-        input: String,
+        /// The path(s) to one or more TMDs, Tickets, or WADs
+        #[arg(num_args = 1.., required = true)]
+        input: Vec<String>,
+        /// Print a compact one line summary for each file instead of the full info
+        #[arg(short, long)]
+        brief: bool,
+        /// Don't list every content record, only the summary of each type of content
+        #[arg(long)]
+        no_records: bool,
+        /// Print the offset and size of each section of a WAD, and of the padding after it
+        #[arg(long, conflicts_with = "brief")]
+        layout: bool,
     },
     /// Compress/decompress data using LZ77 compression
     Lz77 {
         #[command(subcommand)]
         command: archive::lz77::Commands
     },
+    /// Manage BootMii NAND backups
+    Nand {
+        #[command(subcommand)]
+        command: nand::backup::Commands
+    },
     /// Download data from the NUS
+    #[cfg(feature = "nus")]
     Nus {
         #[command(subcommand)]
         command: title::nus::Commands
     },
+    /// Unpack Wii savegames (data.bin)
+    Save {
+        #[command(subcommand)]
+        command: nand::save::Commands
+    },
     /// Manage setting.txt
     Setting {
         #[command(subcommand)]
         command: nand::setting::Commands
     },
+    /// Edit a standalone Ticket file
+    Ticket {
+        #[command(subcommand)]
+        command: title::ticket::Commands,
+    },
+    /// Edit a standalone TMD file
+    Tmd {
+        #[command(subcommand)]
+        command: title::tmd::Commands,
+    },
     /// Pack/unpack a U8 archive
     U8 {
         #[command(subcommand)]
@@ -74,63 +152,146 @@ enum Commands {
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
-    
+    // Library diagnostics and progress are only shown when asked for, but RUST_LOG can still be
+    // used to override this.
+    let log_level = if cli.quiet {
+        LevelFilter::Error
+    } else {
+        match cli.verbose {
+            0 => LevelFilter::Warn,
+            1 => LevelFilter::Info,
+            2 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        }
+    };
+    env_logger::Builder::new().filter_level(log_level).format_timestamp(None).parse_default_env().init();
+
     match &cli.command {
         Some(Commands::Ash { command }) => {
             match command {
                 archive::ash::Commands::Compress { input, output } => {
                     archive::ash::compress_ash(input, output)?
                 },
-                archive::ash::Commands::Decompress { input, output } => {
-                    archive::ash::decompress_ash(input, output)?
+                archive::ash::Commands::Decompress { input, output, output_options } => {
+                    archive::ash::decompress_ash(input, output, output_options)?
                 }
             }
         },
+        Some(Commands::Banner { command }) => {
+            match command {
+                archive::banner::Commands::Pack { input, name, output, compress_sound } => {
+                    archive::banner::pack_banner(input, name, output, compress_sound)?
+                },
+                archive::banner::Commands::Unpack { input, output } => {
+                    archive::banner::unpack_banner(input, output)?
+                }
+            }
+        },
+        Some(Commands::Cert { command }) => {
+            match command {
+                title::cert::Commands::Export { input, output, json } => {
+                    title::cert::export_cert(input, output, json)?
+                }
+            }
+        },
+        Some(Commands::Diff { first, second, contents }) => {
+            diff::diff(first, second, contents)?
+        },
         Some(Commands::Emunand { command }) => {
             match command {
+                nand::emunand::Commands::Cleanup { emunand, delete } => {
+                    nand::emunand::cleanup(emunand, delete)?
+                },
                 nand::emunand::Commands::Info { emunand } => {
                     nand::emunand::info(emunand)?
                 },
+                nand::emunand::Commands::Init { emunand, certs } => {
+                    nand::emunand::init(emunand, certs)?
+                },
+                nand::emunand::Commands::InstallDir { dir, emunand, override_meta, ignore_deps, dry_run } => {
+                    nand::emunand::install_dir(dir, emunand, override_meta, ignore_deps, dry_run)?
+                },
+                #[cfg(feature = "nus")]
                 nand::emunand::Commands::InstallMissing { emunand, vwii } => {
                     nand::emunand::install_missing(emunand, vwii)?
                 },
-                nand::emunand::Commands::InstallTitle { wad, emunand, override_meta} => {
-                    nand::emunand::install_title(wad, emunand, override_meta)?
+                nand::emunand::Commands::InstallTitle { wad, emunand, override_meta, ignore_deps, dry_run } => {
+                    nand::emunand::install_title(wad, emunand, override_meta, ignore_deps, dry_run)?
+                },
+                nand::emunand::Commands::Repair { emunand } => {
+                    nand::emunand::repair(emunand)?
+                },
+                nand::emunand::Commands::Setting { emunand, show, set } => {
+                    nand::emunand::setting(emunand, show, set)?
                 },
-                nand::emunand::Commands::UninstallTitle { tid, emunand, remove_ticket } => {
-                    nand::emunand::uninstall_title(tid, emunand, remove_ticket)?
+                nand::emunand::Commands::Ticket { command } => {
+                    match command {
+                        nand::emunand::TicketCommands::Install { ticket, emunand, force } => {
+                            nand::emunand::install_ticket(ticket, emunand, force)?
+                        },
+                        nand::emunand::TicketCommands::Remove { tid, emunand } => {
+                            nand::emunand::remove_ticket(tid, emunand)?
+                        },
+                    }
+                },
+                nand::emunand::Commands::UidList { emunand } => {
+                    nand::emunand::uid_list(emunand)?
+                },
+                nand::emunand::Commands::UninstallTitle { tid, emunand, remove_ticket, dry_run } => {
+                    nand::emunand::uninstall_title(tid, emunand, remove_ticket, dry_run)?
                 }
             }
         }
-        Some(Commands::Fakesign { input, output }) => {
-            title::fakesign::fakesign(input, output)?
+        Some(Commands::Fakesign { input, output, output_options }) => {
+            title::fakesign::fakesign(input, output, output_options)?
         },
-        Some(Commands::Info { input }) => {
-            info::info(input)?
+        Some(Commands::Hash { input, tmd, index, cid }) => {
+            title::hash::hash(input, tmd, index, cid)?
+        },
+        Some(Commands::Info { input, brief, no_records, layout }) => {
+            info::info(input, brief, no_records, layout)?
         },
         Some(Commands::Lz77 { command }) => {
             match command {
-                archive::lz77::Commands::Compress { input, output } => {
-                    archive::lz77::compress_lz77(input, output)?
+                archive::lz77::Commands::Compress { input, output, no_magic, output_options } => {
+                    archive::lz77::compress_lz77(input, output, no_magic, output_options)?
                 },
-                archive::lz77::Commands::Decompress { input, output } => {
-                    archive::lz77::decompress_lz77(input, output)?
+                archive::lz77::Commands::Decompress { input, output, output_options } => {
+                    archive::lz77::decompress_lz77(input, output, output_options)?
                 }
             }
         },
+        Some(Commands::Nand { command }) => {
+            match command {
+                nand::backup::Commands::ToEmunand { nand, keys, output } => {
+                    nand::backup::to_emunand(nand, keys, output)?
+                }
+            }
+        },
+        #[cfg(feature = "nus")]
         Some(Commands::Nus { command }) => {
             match command {
-                title::nus::Commands::Content { tid, cid, version, output, decrypt} => {
-                    title::nus::download_content(tid, cid, version, output, decrypt)?  
+                title::nus::Commands::Content { tid, cid, version, output, output_options, decrypt} => {
+                    title::nus::download_content(tid, cid, version, output, output_options, decrypt)?
+                },
+                title::nus::Commands::SystemTitles { menu, region, output, dry_run } => {
+                    title::nus::download_system_titles(menu, region, output, *dry_run)?
                 },
-                title::nus::Commands::Ticket { tid, output } => {
-                    title::nus::download_ticket(tid, output)?  
+                title::nus::Commands::Ticket { tid, output, output_options, raw } => {
+                    title::nus::download_ticket(tid, output, output_options, raw)?
                 },
                 title::nus::Commands::Title { tid, version, output} => {
                     title::nus::download_title(tid, version, output)?
                 }
-                title::nus::Commands::Tmd { tid, version, output} => {
-                    title::nus::download_tmd(tid, version, output)?
+                title::nus::Commands::Tmd { tid, version, output, output_options, raw, certs } => {
+                    title::nus::download_tmd(tid, version, output, output_options, raw, certs)?
+                }
+            }
+        },
+        Some(Commands::Save { command }) => {
+            match command {
+                nand::save::Commands::Unpack { input, output } => {
+                    nand::save::unpack_save(input, output)?
                 }
             }
         },
@@ -144,38 +305,70 @@ fn main() -> Result<()> {
                 }
             }
         },
+        Some(Commands::Ticket { command }) => {
+            match command {
+                title::ticket::Commands::Edit { input, output, no_fakesign, show_key, edits } => {
+                    title::ticket::edit_ticket(input, output, no_fakesign, show_key, edits)?
+                }
+            }
+        },
+        Some(Commands::Tmd { command }) => {
+            match command {
+                title::tmd::Commands::Edit { input, output, no_fakesign, edits } => {
+                    title::tmd::edit_tmd(input, output, no_fakesign, edits)?
+                }
+            }
+        },
         Some(Commands::U8 { command }) => {
             match command {
-                archive::u8::Commands::Pack { input, output } => {
-                    archive::u8::pack_u8_archive(input, output)?
+                archive::u8::Commands::Pack { input, output, align, sort, lz77, imd5 } => {
+                    archive::u8::pack_u8_archive(input, output, align, sort, lz77, imd5)?
                 },
-                archive::u8::Commands::Unpack { input, output } => {
-                    archive::u8::unpack_u8_archive(input, output)?
+                archive::u8::Commands::Unpack { input, output, force } => {
+                    archive::u8::unpack_u8_archive(input, output, force)?
                 }
             }
         },
         Some(Commands::Wad { command }) => {
             match command {
-                title::wad::Commands::Add { input, content, output, cid, r#type } => {
-                    title::wad::add_wad(input, content, output, cid, r#type)?
+                title::wad::Commands::Add { input, content, output, output_options, cid, r#type } => {
+                    title::wad::add_wad(input, content, output, output_options, cid, r#type)?
+                },
+                title::wad::Commands::ApplyMeta { input, meta, output, output_options } => {
+                    title::wad::apply_meta(input, meta, output, output_options)?
+                },
+                title::wad::Commands::Convert { input, target, output, output_options, title_key, show_key, no_fakesign } => {
+                    title::wad::convert_wad(input, target, output, output_options, title_key, show_key, no_fakesign)?
+                },
+                title::wad::Commands::Diff { first, second, contents } => {
+                    title::wad::diff_wad(first, second, contents)?
+                },
+                title::wad::Commands::DumpMeta { input, output } => {
+                    title::wad::dump_meta(input, output)?
+                },
+                title::wad::Commands::Edit { input, output, output_options, edits } => {
+                    title::wad::edit_wad(input, output, output_options, edits)?
+                },
+                title::wad::Commands::Extract { input, output, identifier, boot, encrypted } => {
+                    title::wad::extract_wad(input, output, identifier, boot, encrypted)?
                 },
-                title::wad::Commands::Convert { input, target, output } => {
-                    title::wad::convert_wad(input, target, output)?
+                title::wad::Commands::Lint { input, warnings_as_errors } => {
+                    title::wad::lint_wad(input, warnings_as_errors)?
                 },
-                title::wad::Commands::Edit { input, output, edits } => {
-                    title::wad::edit_wad(input, output, edits)?
+                title::wad::Commands::Pack { input, output, cert, no_fakesign, allow_missing, encrypted, reproducible } => {
+                    title::wad::pack_wad(input, output, cert, no_fakesign, allow_missing, encrypted, reproducible)?
                 },
-                title::wad::Commands::Pack { input, output} => {
-                    title::wad::pack_wad(input, output)?
+                title::wad::Commands::Remove { input, output, output_options, identifier, r#type } => {
+                    title::wad::remove_wad(input, output, output_options, identifier, r#type)?
                 },
-                title::wad::Commands::Remove { input, output, identifier } => {
-                    title::wad::remove_wad(input, output, identifier)?
+                title::wad::Commands::Set { input, content, output, output_options, identifier, r#type} => {
+                    title::wad::set_wad(input, content, output, output_options, identifier, r#type)?
                 },
-                title::wad::Commands::Set { input, content, output, identifier, r#type} => {
-                    title::wad::set_wad(input, content, output, identifier, r#type)?
+                title::wad::Commands::Unpack { input, output, encrypted, name_by } => {
+                    title::wad::unpack_wad(input, output, encrypted, name_by)?
                 },
-                title::wad::Commands::Unpack { input, output } => {
-                    title::wad::unpack_wad(input, output)?
+                title::wad::Commands::Verify { input, strict } => {
+                    title::wad::verify_wad(input, strict)?
                 },
             }
         },
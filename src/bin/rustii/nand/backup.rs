@@ -0,0 +1,55 @@
+// nand/backup.rs from rustii (c) 2025 NinjaCheetah & Contributors
+// https://github.com/NinjaCheetah/rustii
+//
+// Code for BootMii NAND backup-related commands in the rustii CLI.
+
+use std::fs;
+use std::path::Path;
+use anyhow::{bail, Context, Result};
+use clap::Subcommand;
+use rustii::nand::backup;
+
+#[derive(Subcommand)]
+#[command(arg_required_else_help = true)]
+pub enum Commands {
+    /// Convert a BootMii NAND backup into a directory-style EmuNAND
+    ToEmunand {
+        /// The path to the NAND backup (nand.bin)
+        nand: String,
+        /// The path to the console's keys.bin
+        keys: String,
+        /// The directory to create the EmuNAND in
+        output: String,
+    }
+}
+
+pub fn to_emunand(nand: &str, keys: &str, output: &str) -> Result<()> {
+    let nand_path = Path::new(nand);
+    if !nand_path.exists() {
+        bail!("Source NAND backup \"{}\" could not be found.", nand_path.display());
+    }
+    let keys_path = Path::new(keys);
+    let keys_data = fs::read(keys_path).with_context(|| format!("Could not open keys file \"{}\" for reading.", keys_path.display()))?;
+    let out_path = Path::new(output);
+    if out_path.exists() && !out_path.is_dir() {
+        bail!("A file already exists with the specified directory name!");
+    }
+    let nand_backup = backup::NandBackup::open(nand_path, &keys_data)
+        .with_context(|| format!("The NAND backup \"{}\" could not be opened.", nand_path.display()))?;
+    println!("Extracting NAND backup, please wait...");
+    let report = nand_backup.extract_all(out_path).with_context(|| "The NAND backup could not be extracted.")?;
+    for dir in &report.skipped_dirs {
+        println!("Note: Skipped temporary directory \"{}\".", dir);
+    }
+    if report.corrected_clusters > 0 {
+        println!("Corrected errors in {} clusters using their ECC data.", report.corrected_clusters);
+    }
+    if !report.damaged_files.is_empty() {
+        println!("The following files have uncorrectable errors and were not extracted:");
+        for file in &report.damaged_files {
+            println!("  {}", file);
+        }
+    }
+    println!("Successfully extracted {} files from NAND backup \"{}\" to EmuNAND at \"{}\"!", report.extracted_files, nand_path.display(), out_path.display());
+    Ok(())
+}
@@ -4,25 +4,64 @@
 // Code for EmuNAND-related commands in the rustii CLI.
 
 use std::{str, fs};
-use std::path::{absolute, Path};
+use std::path::{absolute, Path, PathBuf};
 use anyhow::{bail, Context, Result};
-use clap::Subcommand;
+use clap::{Args, Subcommand};
 use walkdir::WalkDir;
-use rustii::nand::{emunand, setting};
-use rustii::title::{nus, tmd};
+use crate::output::dry_run_suffix;
+use rustii::nand::{emunand, setting, sys};
+use rustii::title::{cert, ticket, tmd, TitleId};
+#[cfg(feature = "nus")]
+use rustii::title::nus;
 use rustii::title;
 
 #[derive(Subcommand)]
 #[command(arg_required_else_help = true)]
 pub enum Commands {
+    /// Audit an EmuNAND's shared content for orphaned entries and missing files
+    Cleanup {
+        #[command(flatten)]
+        emunand: EmunandTarget,
+        /// Delete orphaned entries and untracked files instead of only reporting them
+        #[clap(long)]
+        delete: bool,
+    },
     /// Display information about an EmuNAND
     Info {
-        emunand: String,
+        #[command(flatten)]
+        emunand: EmunandTarget,
+    },
+    /// Initialize a new EmuNAND with the standard NAND layout
+    Init {
+        #[command(flatten)]
+        emunand: EmunandTarget,
+        /// A certificate chain to write to /sys/cert.sys; defaults to a cached or downloaded chain
+        #[clap(long)]
+        certs: Option<String>,
+    },
+    /// Install an unpacked title directory to an EmuNAND, such as one downloaded from the NUS
+    InstallDir {
+        /// The path to the directory containing the title's TMD, Ticket, and contents
+        dir: String,
+        #[command(flatten)]
+        emunand: EmunandTarget,
+        /// Install the content at index 0 as title.met; this will override any meta/footer data
+        /// included in the directory
+        #[clap(long)]
+        override_meta: bool,
+        /// Install the title even if the IOS it requires is not installed to the EmuNAND
+        #[clap(long)]
+        ignore_deps: bool,
+        /// Check that the title can be installed and report the files that would be written,
+        /// without changing the EmuNAND
+        #[clap(long)]
+        dry_run: bool,
     },
     /// Automatically install missing IOSes to an EmuNAND
+    #[cfg(feature = "nus")]
     InstallMissing {
-        /// The path to the target EmuNAND
-        emunand: String,
+        #[command(flatten)]
+        emunand: EmunandTarget,
         /// Explicitly install vWii IOSes instead of detecting the EmuNAND type automatically
         #[clap(long)]
         vwii: bool
@@ -31,28 +70,157 @@ pub enum Commands {
     InstallTitle {
         /// The path to the WAD file to install
         wad: String,
-        /// The path to the target EmuNAND
-        emunand: String,
+        #[command(flatten)]
+        emunand: EmunandTarget,
         /// Install the content at index 0 as title.met; this will override any meta/footer data
         /// included in the WAD
         #[clap(long)]
         override_meta: bool,
+        /// Install the title even if the IOS it requires is not installed to the EmuNAND
+        #[clap(long)]
+        ignore_deps: bool,
+        /// Check that the title can be installed and report the files that would be written,
+        /// without changing the EmuNAND
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Repair a damaged content.map and uid.sys, and clean up shared content
+    Repair {
+        #[command(flatten)]
+        emunand: EmunandTarget,
+    },
+    /// View or edit an EmuNAND's setting.txt
+    Setting {
+        #[command(flatten)]
+        emunand: EmunandTarget,
+        /// Print the contents of setting.txt; this is the default if no changes are made
+        #[clap(long)]
+        show: bool,
+        /// Set a setting.txt key to a new value, like AREA=USA (can be used multiple times)
+        #[clap(long, value_name = "KEY=VALUE")]
+        set: Vec<String>,
+    },
+    /// Install or remove Tickets without installing a title
+    Ticket {
+        #[command(subcommand)]
+        command: TicketCommands,
+    },
+    /// List the Title ID/UID pairings in an EmuNAND's uid.sys
+    UidList {
+        #[command(flatten)]
+        emunand: EmunandTarget,
     },
     /// Uninstall a title from an EmuNAND
     UninstallTitle {
         /// The Title ID of the title to uninstall, or the path to a WAD file to read the Title ID
         /// from
         tid: String,
-        /// The path to the target EmuNAND
-        emunand: String,
+        #[command(flatten)]
+        emunand: EmunandTarget,
         /// Remove the Ticket file; default behavior is to leave it intact
         #[clap(long)]
         remove_ticket: bool,
+        /// Report the files that would be removed, without changing the EmuNAND
+        #[clap(long)]
+        dry_run: bool,
     }
 }
 
-pub fn info(emunand: &str) -> Result<()> {
-    let emunand_path = Path::new(emunand);
+#[derive(Subcommand)]
+#[command(arg_required_else_help = true)]
+pub enum TicketCommands {
+    /// Install a Ticket to an EmuNAND without installing the title's contents
+    Install {
+        /// The path to the Ticket to install
+        ticket: String,
+        #[command(flatten)]
+        emunand: EmunandTarget,
+        /// Replace the Ticket if one is already installed for the same title
+        #[clap(long)]
+        force: bool,
+    },
+    /// Remove a Ticket from an EmuNAND, leaving the title itself installed
+    Remove {
+        /// The Title ID of the Ticket to remove
+        tid: String,
+        #[command(flatten)]
+        emunand: EmunandTarget,
+    },
+}
+
+#[derive(Args)]
+pub struct EmunandTarget {
+    /// The path to the target EmuNAND
+    #[arg(required_unless_present = "dolphin")]
+    emunand: Option<String>,
+    /// Use Dolphin's NAND as the target EmuNAND instead of an explicit path
+    #[arg(long, conflicts_with = "emunand")]
+    dolphin: bool,
+}
+
+impl EmunandTarget {
+    // Gets the path to the target EmuNAND, searching for Dolphin's NAND if --dolphin was passed.
+    fn resolve(&self) -> Result<PathBuf> {
+        if self.dolphin {
+            match emunand::locate_dolphin_nand() {
+                Some(path) => {
+                    println!("Using Dolphin NAND at \"{}\".", path.display());
+                    Ok(path)
+                },
+                None => {
+                    let checked: Vec<String> = emunand::dolphin_nand_candidates(&emunand::DolphinSearchPaths::from_env())
+                        .iter().map(|path| format!("  {}", path.display())).collect();
+                    bail!("Dolphin's NAND could not be found! The following locations were checked:\n{}", checked.join("\n"));
+                }
+            }
+        } else {
+            Ok(PathBuf::from(self.emunand.clone().unwrap()))
+        }
+    }
+}
+
+pub fn cleanup(emunand: &EmunandTarget, delete: &bool) -> Result<()> {
+    let emunand_path = emunand.resolve()?;
+    if !emunand_path.exists() {
+        bail!("Target EmuNAND directory \"{}\" could not be found.", emunand_path.display());
+    }
+    let emunand = emunand::EmuNAND::open(emunand_path.to_path_buf())?;
+    let report = emunand.cleanup_shared_content(*delete).with_context(|| "The EmuNAND's shared content could not be audited.")?;
+    if report.orphaned_entries.is_empty() && report.missing_files.is_empty() && report.untracked_files.is_empty() {
+        println!("No problems were found with the EmuNAND's shared content!");
+        return Ok(());
+    }
+    if !report.orphaned_entries.is_empty() {
+        println!("Shared content not used by any installed title:");
+        for (file_name, hash) in &report.orphaned_entries {
+            println!("  {}.app (Hash: {})", file_name, hex::encode(hash));
+        }
+        println!();
+    }
+    if !report.missing_files.is_empty() {
+        println!("Shared content tracked by content.map that is missing:");
+        for file_name in &report.missing_files {
+            println!("  {}.app", file_name);
+        }
+        println!();
+    }
+    if !report.untracked_files.is_empty() {
+        println!("Shared content not tracked by content.map:");
+        for file_name in &report.untracked_files {
+            println!("  {}", file_name);
+        }
+        println!();
+    }
+    if *delete {
+        println!("Removed {} orphaned entries and {} untracked files.", report.orphaned_entries.len(), report.untracked_files.len());
+    } else {
+        println!("Run again with --delete to remove orphaned entries and untracked files.");
+    }
+    Ok(())
+}
+
+pub fn info(emunand: &EmunandTarget) -> Result<()> {
+    let emunand_path = emunand.resolve()?;
     if !emunand_path.exists() {
         bail!("Target EmuNAND directory \"{}\" could not be found.", emunand_path.display());
     }
@@ -61,7 +229,7 @@ pub fn info(emunand: &str) -> Result<()> {
     println!("EmuNAND Info");
     println!("  Path: {}", absolute(emunand_path)?.display());
     let mut is_vwii = false;
-    match emunand.get_title_tmd([0, 0, 0, 1, 0, 0, 0, 2]) {
+    match emunand.get_title_tmd(TitleId::new([0, 0, 0, 1, 0, 0, 0, 2])) {
         Some(tmd) => {
             is_vwii = tmd.is_vwii();
             println!("  System Menu Version: {}", title::versions::dec_to_standard(tmd.title_version, "0000000100000002", Some(is_vwii)).unwrap());
@@ -102,7 +270,7 @@ pub fn info(emunand: &str) -> Result<()> {
         .filter(|entry| entry.file_type().is_file())
         .map(|entry| fs::metadata(entry.path()).map(|m| m.len()).unwrap_or(0))
         .sum();
-    println!("  Space Used: {} blocks ({:.2} MB)", title::bytes_to_blocks(total_size as usize), total_size as f64 / 1048576.0);
+    println!("  Space Used: {} blocks ({:.2} MB)", title::bytes_to_blocks(total_size), total_size as f64 / 1048576.0);
     println!();
     // Build a catalog of all installed titles so that we can display them.
     let mut installed_ioses: Vec<String> = Vec::new();
@@ -141,12 +309,14 @@ pub fn info(emunand: &str) -> Result<()> {
                 } else if ios[8..16].eq("00000201") {
                     println!("  BC-WFS ({})", ios.to_ascii_uppercase());
                 }
-                let tmd = emunand.get_title_tmd(hex::decode(ios)?.try_into().unwrap()).unwrap();
+                let tmd = emunand.get_title_tmd(TitleId::from_hex(ios)?)
+                    .with_context(|| format!("The TMD for installed title {} could not be read.", ios.to_ascii_uppercase()))?;
                 println!("    Version: {}", tmd.title_version);
             }
             else {
                 println!("  IOS{} ({})", u32::from_str_radix(&ios[8..16], 16)?, ios.to_ascii_uppercase());
-                let tmd = emunand.get_title_tmd(hex::decode(ios)?.try_into().unwrap()).unwrap();
+                let tmd = emunand.get_title_tmd(TitleId::from_hex(ios)?)
+                    .with_context(|| format!("The TMD for installed title {} could not be read.", ios.to_ascii_uppercase()))?;
                 println!("    Version: {} ({})", tmd.title_version, title::versions::dec_to_standard(tmd.title_version, ios, None).unwrap());
             }
         }
@@ -156,19 +326,21 @@ pub fn info(emunand: &str) -> Result<()> {
     if !installed_titles.is_empty() {
         println!("Installed Titles:");
         for title in installed_titles {
-            let ascii = String::from_utf8_lossy(&hex::decode(&title[8..16])?).to_string();
-            let ascii_tid = if ascii.len() == 4 {
-                Some(ascii)
-            } else {
-                None
-            };
-            if ascii_tid.is_some() {
-                println!("  {} ({})", title.to_uppercase(), ascii_tid.unwrap());
+            let tid = TitleId::from_hex(&title)?;
+            let is_dlc = matches!(tid.title_type(), Some(tmd::TitleType::DLC));
+            let dlc_marker = if is_dlc { " [DLC]" } else { "" };
+            if let Some(ascii_tid) = tid.ascii_low() {
+                println!("  {} ({}){}", tid, ascii_tid, dlc_marker);
             } else {
-                println!("  {}", title.to_uppercase());
+                println!("  {}{}", tid, dlc_marker);
             }
-            let tmd = emunand.get_title_tmd(hex::decode(&title)?.try_into().unwrap()).unwrap();
+            let tmd = emunand.get_title_tmd(tid)
+                .with_context(|| format!("The TMD for installed title {} could not be read.", tid))?;
             println!("    Version: {}", tmd.title_version);
+            // DLC is often only partially purchased, so show how much of it is actually installed.
+            if is_dlc && let Some((installed, total)) = emunand.installed_content_count(tid) {
+                println!("    Contents Installed: {} of {}", installed, total);
+            }
             let ios_tid = &hex::encode(tmd.ios_tid()).to_ascii_uppercase();
             print!("    Required IOS: IOS{} ({})", u32::from_str_radix(&hex::encode(&tmd.ios_tid()[4..8]), 16)?, ios_tid);
             if !installed_ioses.contains(ios_tid) {
@@ -192,8 +364,8 @@ pub fn info(emunand: &str) -> Result<()> {
             } else {
                 None
             };
-            if ascii_tid.is_some() {
-                println!("  {} ({})", title.to_uppercase(), ascii_tid.unwrap());
+            if let Some(ascii_tid) = ascii_tid {
+                println!("  {} ({})", title.to_uppercase(), ascii_tid);
             } else {
                 println!("  {}", title.to_uppercase());
             }
@@ -214,8 +386,27 @@ pub fn info(emunand: &str) -> Result<()> {
     Ok(())
 }
 
-pub fn install_missing(emunand: &str, vwii: &bool) -> Result<()> {
-    let emunand_path = Path::new(emunand);
+pub fn init(emunand: &EmunandTarget, certs: &Option<String>) -> Result<()> {
+    let emunand_path = emunand.resolve()?;
+    if emunand_path.exists() && !emunand_path.is_dir() {
+        bail!("A file already exists with the specified EmuNAND directory name!");
+    }
+    let cert_chain = if let Some(certs) = certs {
+        let cert_path = Path::new(certs);
+        cert::CertificateChain::open(cert_path)
+            .with_context(|| format!("The provided certificate chain \"{}\" could not be loaded.", cert_path.display()))?
+    } else {
+        crate::title::wad::fallback_cert_chain(crate::title::wad::cert_cache_path().as_deref())?
+    };
+    let options = emunand::InitOptions { cert_chain: Some(cert_chain) };
+    emunand::EmuNAND::init(emunand_path.to_path_buf(), options).with_context(|| format!("The EmuNAND at \"{}\" could not be initialized.", emunand_path.display()))?;
+    println!("Successfully initialized EmuNAND at \"{}\"!", emunand_path.display());
+    Ok(())
+}
+
+#[cfg(feature = "nus")]
+pub fn install_missing(emunand: &EmunandTarget, vwii: &bool) -> Result<()> {
+    let emunand_path = emunand.resolve()?;
     if !emunand_path.exists() {
         bail!("Target EmuNAND directory \"{}\" could not be found.", emunand_path.display());
     }
@@ -224,7 +415,7 @@ pub fn install_missing(emunand: &str, vwii: &bool) -> Result<()> {
     let vwii = if *vwii {
         true
     } else {
-        match emunand.get_title_tmd([0, 0, 0, 1, 0, 0, 0, 2]) {
+        match emunand.get_title_tmd(TitleId::new([0, 0, 0, 1, 0, 0, 0, 2])) {
             Some(tmd) => {
                 tmd.is_vwii()
             },
@@ -255,7 +446,12 @@ pub fn install_missing(emunand: &str, vwii: &bool) -> Result<()> {
             category.titles.iter().for_each(|x| installed_titles.push(format!("{}{}", category.title_type, x)));
         }
     }
-    let title_tmds: Vec<tmd::TMD> = installed_titles.iter().map(|x| emunand.get_title_tmd(hex::decode(x).unwrap().try_into().unwrap()).unwrap()).collect();
+    let title_tmds: Vec<tmd::TMD> = installed_titles.iter()
+        .map(|x| {
+            let tid = TitleId::from_hex(x)?;
+            emunand.get_title_tmd(tid).with_context(|| format!("The TMD for installed title {} could not be read.", tid))
+        })
+        .collect::<Result<Vec<_>>>()?;
     let mut missing_ioses: Vec<u32> = title_tmds.iter()
         .filter(|x| !installed_ioses.contains(&hex::encode(x.ios_tid()).to_ascii_uppercase()))
         .map(|x| u32::from_str_radix(&hex::encode(&x.ios_tid()[4..8]), 16).unwrap()).collect();
@@ -265,72 +461,296 @@ pub fn install_missing(emunand: &str, vwii: &bool) -> Result<()> {
     missing_ioses.sort();
     // Because we don't need to install the same IOS for every single title that requires it.
     missing_ioses.dedup();
-    let missing_tids: Vec<[u8; 8]> = {
-        if vwii {
-            missing_ioses.iter().map(|x| {
-                let mut tid = [0u8; 8];
-                tid[3] = 7;
-                tid[4..8].copy_from_slice(&x.to_be_bytes());
-                tid
-            }).collect()
-        } else {
-            missing_ioses.iter().map(|x| {
-                let mut tid = [0u8; 8];
-                tid[3] = 1;
-                tid[4..8].copy_from_slice(&x.to_be_bytes());
-                tid
-            }).collect()
-        }
-    };
+    let tid_high: [u8; 4] = if vwii { [0, 0, 0, 7] } else { [0, 0, 0, 1] };
+    let missing_tids: Vec<TitleId> = missing_ioses.iter().map(|x| TitleId::from_parts(tid_high, x.to_be_bytes())).collect();
     println!("Missing IOSes:");
     for ios in &missing_tids {
-        println!("  IOS{} ({})", u32::from_str_radix(&hex::encode(&ios[4..8]), 16)?, hex::encode(ios).to_ascii_uppercase());
+        println!("  IOS{} ({})", u32::from_be_bytes(ios.low()), ios);
     }
     println!();
     for ios in missing_tids {
-        println!("Downloading IOS{} ({})...", u32::from_str_radix(&hex::encode(&ios[4..8]), 16)?, hex::encode(ios).to_ascii_uppercase());
+        println!("Downloading IOS{} ({})...", u32::from_be_bytes(ios.low()), ios);
         let title = nus::download_title(ios, None, true)?;
         let version = title.tmd.title_version;
-        println!("  Installing IOS{} ({}) v{}...", u32::from_str_radix(&hex::encode(&ios[4..8]), 16)?, hex::encode(ios).to_ascii_uppercase(), version);
-        emunand.install_title(title, false)?;
-        println!("  Installed IOS{} ({}) v{}!", u32::from_str_radix(&hex::encode(&ios[4..8]), 16)?, hex::encode(ios).to_ascii_uppercase(), version);
+        println!("  Installing IOS{} ({}) v{}...", u32::from_be_bytes(ios.low()), ios, version);
+        emunand.install_title(title, &emunand::InstallOptions::default())?;
+        println!("  Installed IOS{} ({}) v{}!", u32::from_be_bytes(ios.low()), ios, version);
     }
     println!("\nAll missing IOSes have been installed!");
     Ok(())
 }
 
-pub fn install_title(wad: &str, emunand: &str, override_meta: &bool) -> Result<()> {
+// Opens the target EmuNAND, without creating any of its missing directories for a dry run.
+fn open_emunand(emunand_path: &Path, dry_run: bool) -> Result<emunand::EmuNAND> {
+    if dry_run {
+        Ok(emunand::EmuNAND::open_read_only(emunand_path.to_path_buf())?)
+    } else {
+        Ok(emunand::EmuNAND::open(emunand_path.to_path_buf())?)
+    }
+}
+
+// Installs a title to the EmuNAND, or only reports the files that the installation would write
+// during a dry run.
+fn install_or_plan(emunand: &emunand::EmuNAND, title: title::Title, options: &emunand::InstallOptions, dry_run: bool) -> Result<(), emunand::EmuNANDError> {
+    if dry_run {
+        for file in emunand.plan_install(&title, options)?.files {
+            println!("Would write {} bytes to \"{}\".", file.size, file.path.display());
+        }
+    } else {
+        emunand.install_title(title, options)?;
+    }
+    Ok(())
+}
+
+pub fn install_dir(dir: &str, emunand: &EmunandTarget, override_meta: &bool, ignore_deps: &bool, dry_run: &bool) -> Result<()> {
+    let dir_path = Path::new(dir);
+    if !dir_path.is_dir() {
+        bail!("Source directory \"{}\" could not be found.", dir_path.display());
+    }
+    let emunand_path = emunand.resolve()?;
+    if !emunand_path.exists() {
+        bail!("Target EmuNAND directory \"{}\" could not be found.", emunand_path.display());
+    }
+    let emunand = open_emunand(&emunand_path, *dry_run)?;
+    let options = emunand::InstallOptions {
+        override_meta: *override_meta,
+        check_dependencies: !*ignore_deps,
+        strict_dependencies: true,
+        ..Default::default()
+    };
+    match emunand.load_title_from_directory(dir_path).and_then(|title| install_or_plan(&emunand, title, &options, *dry_run)) {
+        Err(emunand::EmuNANDError::MissingDependency { ios_tid }) => {
+            bail!("This title requires IOS{} ({}), which is not installed to the target EmuNAND! Install it first, or use --ignore-deps to install this title anyway.",
+                u32::from_be_bytes(ios_tid.low()), ios_tid);
+        },
+        Err(emunand::EmuNANDError::MissingTicket(tid)) => {
+            bail!("The title directory does not contain a Ticket, and no Ticket for Title ID \"{}\" is installed to the target EmuNAND! Install one first with \"rustii emunand ticket install\".", tid);
+        },
+        result => { result.with_context(|| format!("The title directory \"{}\" could not be installed.", dir_path.display()))?; },
+    }
+    println!("Successfully installed title directory \"{}\" to EmuNAND at \"{}\"!{}", dir_path.display(), emunand_path.display(), dry_run_suffix(*dry_run));
+    Ok(())
+}
+
+pub fn install_title(wad: &str, emunand: &EmunandTarget, override_meta: &bool, ignore_deps: &bool, dry_run: &bool) -> Result<()> {
     let wad_path = Path::new(wad);
     if !wad_path.exists() {
         bail!("Source WAD \"{}\" could not be found.", wad_path.display());
     }
-    let emunand_path = Path::new(emunand);
+    let emunand_path = emunand.resolve()?;
+    if !emunand_path.exists() {
+        bail!("Target EmuNAND directory \"{}\" could not be found.", emunand_path.display());
+    }
+    let title = title::Title::open(wad_path).with_context(|| format!("The provided WAD file \"{}\" could not be loaded.", wad_path.display()))?;
+    let emunand = open_emunand(&emunand_path, *dry_run)?;
+    let install_size = emunand.install_size(&title).with_context(|| "The installed size of the title could not be calculated.")?;
+    println!("This will use {} blocks, {} already shared.", install_size.blocks, install_size.shared_blocks);
+    let options = emunand::InstallOptions {
+        override_meta: *override_meta,
+        check_dependencies: !*ignore_deps,
+        strict_dependencies: true,
+        ..Default::default()
+    };
+    match install_or_plan(&emunand, title, &options, *dry_run) {
+        Err(emunand::EmuNANDError::MissingDependency { ios_tid }) => {
+            bail!("This title requires IOS{} ({}), which is not installed to the target EmuNAND! Install it first, or use --ignore-deps to install this title anyway.",
+                u32::from_be_bytes(ios_tid.low()), ios_tid);
+        },
+        result => { result?; },
+    }
+    println!("Successfully installed WAD \"{}\" to EmuNAND at \"{}\"!{}", wad_path.display(), emunand_path.display(), dry_run_suffix(*dry_run));
+    Ok(())
+}
+
+pub fn repair(emunand: &EmunandTarget) -> Result<()> {
+    let emunand_path = emunand.resolve()?;
+    if !emunand_path.exists() {
+        bail!("Target EmuNAND directory \"{}\" could not be found.", emunand_path.display());
+    }
+    let emunand = emunand::EmuNAND::open(emunand_path.to_path_buf())?;
+    let report = emunand.repair().with_context(|| "The EmuNAND could not be repaired.")?;
+    let mut fixes: Vec<String> = Vec::new();
+    if report.content_map_discarded_bytes > 0 {
+        fixes.push(format!("Discarded {} bytes of partial entries from content.map", report.content_map_discarded_bytes));
+    }
+    for entry in &report.content_map_removed {
+        fixes.push(format!("Removed duplicate content.map entry {:08x}.app (Hash: {})", entry.shared_id, hex::encode(entry.hash)));
+    }
+    if report.uid_sys_discarded_bytes > 0 {
+        fixes.push(format!("Discarded {} bytes of partial entries from uid.sys", report.uid_sys_discarded_bytes));
+    }
+    for entry in &report.uid_sys_removed {
+        fixes.push(format!("Removed duplicate uid.sys entry for Title ID {} (UID: 0x{:X})", entry.title_id, entry.uid));
+    }
+    for (file_name, hash) in &report.shared_content.orphaned_entries {
+        fixes.push(format!("Removed shared content {}.app not used by any installed title (Hash: {})", file_name, hex::encode(hash)));
+    }
+    for file_name in &report.shared_content.untracked_files {
+        fixes.push(format!("Removed shared content {} not tracked by content.map", file_name));
+    }
+    for file_name in &report.shared_content.missing_files {
+        println!("Warning: Shared content {}.app is tracked by content.map but is missing, and cannot be recovered.", file_name);
+    }
+    if fixes.is_empty() {
+        println!("No problems were found with the EmuNAND at \"{}\"!", emunand_path.display());
+        return Ok(());
+    }
+    println!("Successfully repaired EmuNAND at \"{}\"!\nSummary of fixes:", emunand_path.display());
+    for fix in &fixes {
+        println!(" - {}", fix);
+    }
+    Ok(())
+}
+
+pub fn setting(emunand: &EmunandTarget, show: &bool, set: &[String]) -> Result<()> {
+    let emunand_path = emunand.resolve()?;
+    if !emunand_path.exists() {
+        bail!("Target EmuNAND directory \"{}\" could not be found.", emunand_path.display());
+    }
+    let emunand = emunand::EmuNAND::open(emunand_path.to_path_buf())?;
+    let setting_path = emunand.get_emunand_dir("title").unwrap()
+        .join("00000001")
+        .join("00000002")
+        .join("data")
+        .join("setting.txt");
+    if !setting_path.exists() {
+        bail!("The target EmuNAND does not have a setting.txt file!");
+    }
+    let mut setting_txt = setting::SettingTxt::from_bytes(&fs::read(&setting_path)?).with_context(|| "The EmuNAND's setting.txt could not be parsed, and is likely invalid.")?;
+    for change in set {
+        let (key, value) = change.split_once('=')
+            .with_context(|| format!("The change \"{}\" is invalid! Changes must be in the format KEY=VALUE.", change))?;
+        setting_txt.set(key, value).with_context(|| format!("The change \"{}\" could not be applied.", change))?;
+    }
+    if !set.is_empty() {
+        fs::write(&setting_path, setting_txt.to_bytes()?).with_context(|| format!("Could not open setting.txt \"{}\" for writing.", setting_path.display()))?;
+        println!("Successfully updated setting.txt in EmuNAND at \"{}\"!", emunand_path.display());
+    }
+    if *show || set.is_empty() {
+        println!("setting.txt");
+        for key in setting::SETTING_KEYS {
+            println!("  {}: {}", key, setting_txt.get(key).unwrap());
+        }
+    }
+    Ok(())
+}
+
+pub fn install_ticket(ticket: &str, emunand: &EmunandTarget, force: &bool) -> Result<()> {
+    let ticket_path = Path::new(ticket);
+    if !ticket_path.exists() {
+        bail!("Source Ticket \"{}\" could not be found.", ticket_path.display());
+    }
+    let emunand_path = emunand.resolve()?;
+    if !emunand_path.exists() {
+        bail!("Target EmuNAND directory \"{}\" could not be found.", emunand_path.display());
+    }
+    let tik = ticket::Ticket::open(ticket_path).with_context(|| format!("The provided Ticket \"{}\" could not be loaded.", ticket_path.display()))?;
+    let emunand = emunand::EmuNAND::open(emunand_path.to_path_buf())?;
+    match emunand.install_ticket(&tik, *force) {
+        Err(emunand::EmuNANDError::TicketAlreadyInstalled(tid)) => {
+            bail!("A Ticket for Title ID \"{}\" is already installed to the target EmuNAND! Use --force to replace it.", tid);
+        },
+        result => { result?; },
+    }
+    println!("Successfully installed Ticket for Title ID \"{}\" to EmuNAND at \"{}\"!", tik.title_id(), emunand_path.display());
+    Ok(())
+}
+
+pub fn remove_ticket(tid: &str, emunand: &EmunandTarget) -> Result<()> {
+    let emunand_path = emunand.resolve()?;
+    if !emunand_path.exists() {
+        bail!("Target EmuNAND directory \"{}\" could not be found.", emunand_path.display());
+    }
+    let tid_bin: TitleId = tid.parse().with_context(|| "The specified Title ID is not valid!")?;
+    let emunand = emunand::EmuNAND::open(emunand_path.to_path_buf())?;
+    match emunand.remove_ticket(tid_bin) {
+        Err(emunand::EmuNANDError::TicketNotInstalled) => {
+            bail!("No Ticket for Title ID \"{}\" is installed to the target EmuNAND.", tid_bin);
+        },
+        result => { result?; },
+    }
+    println!("Successfully removed Ticket for Title ID \"{}\" from EmuNAND at \"{}\"!", tid_bin, emunand_path.display());
+    Ok(())
+}
+
+pub fn uid_list(emunand: &EmunandTarget) -> Result<()> {
+    let emunand_path = emunand.resolve()?;
     if !emunand_path.exists() {
         bail!("Target EmuNAND directory \"{}\" could not be found.", emunand_path.display());
     }
-    let wad_file = fs::read(wad_path).with_context(|| format!("Failed to open WAD file \"{}\" for reading.", wad_path.display()))?;
-    let title = title::Title::from_bytes(&wad_file).with_context(|| format!("The provided WAD file \"{}\" appears to be invalid.", wad_path.display()))?;
     let emunand = emunand::EmuNAND::open(emunand_path.to_path_buf())?;
-    emunand.install_title(title, *override_meta)?;
-    println!("Successfully installed WAD \"{}\" to EmuNAND at \"{}\"!", wad_path.display(), emunand_path.display());
+    let uid_sys_path = emunand.get_emunand_dir("sys").unwrap().join("uid.sys");
+    if !uid_sys_path.exists() {
+        bail!("The target EmuNAND does not have a uid.sys file!");
+    }
+    let uid_sys = sys::UidSys::from_bytes(&fs::read(&uid_sys_path)?).with_context(|| "The uid.sys file could not be parsed.")?;
+    println!("uid.sys Entries ({}):", uid_sys.entries().len());
+    for entry in uid_sys.entries() {
+        if let Some(ascii_tid) = entry.title_id.ascii_low() {
+            println!("  0x{:04X}: {} ({})", entry.uid, entry.title_id, ascii_tid);
+        } else {
+            println!("  0x{:04X}: {}", entry.uid, entry.title_id);
+        }
+    }
+    let anomalies = uid_sys.anomalies();
+    if !anomalies.is_empty() {
+        println!();
+        println!("The following anomalies were found in uid.sys:");
+        for anomaly in anomalies {
+            match anomaly {
+                sys::UidSysAnomaly::DuplicateTitleId(title_id) => {
+                    println!("  - Title ID {} appears more than once", title_id);
+                },
+                sys::UidSysAnomaly::NonMonotonicUid { title_id, uid, previous_uid } => {
+                    println!("  - Title ID {} has UID 0x{:04X}, which is not higher than the previous UID 0x{:04X}", title_id, uid, previous_uid);
+                },
+            }
+        }
+    }
     Ok(())
 }
 
-pub fn uninstall_title(tid: &str, emunand: &str, remove_ticket: &bool) -> Result<()> {
-    let emunand_path = Path::new(emunand);
+pub fn uninstall_title(tid: &str, emunand: &EmunandTarget, remove_ticket: &bool, dry_run: &bool) -> Result<()> {
+    let emunand_path = emunand.resolve()?;
     if !emunand_path.exists() {
         bail!("Target EmuNAND directory \"{}\" could not be found.", emunand_path.display());
     }
     let tid_as_path = Path::new(&tid);
-    let tid_bin: [u8; 8] = if tid_as_path.exists() {
-        let wad_file = fs::read(tid_as_path).with_context(|| format!("Failed to open WAD file \"{}\" for reading.", tid_as_path.display()))?;
-        let title = title::Title::from_bytes(&wad_file).with_context(|| format!("The provided WAD file \"{}\" appears to be invalid.", tid_as_path.display()))?;
+    let tid_bin: TitleId = if tid_as_path.exists() {
+        let title = title::Title::open(tid_as_path).with_context(|| format!("The provided WAD file \"{}\" could not be loaded.", tid_as_path.display()))?;
         title.tmd.title_id()
     } else {
-        hex::decode(tid).with_context(|| "The specified Title ID is not valid! The Title ID must be in hex format.")?.try_into().unwrap()
+        tid.parse().with_context(|| "The specified Title ID is not valid!")?
     };
-    let emunand = emunand::EmuNAND::open(emunand_path.to_path_buf())?;
-    emunand.uninstall_title(tid_bin, *remove_ticket)?;
-    println!("Successfully uninstalled title with Title ID \"{}\" from EmuNAND at \"{}\"!", hex::encode(tid_bin).to_ascii_uppercase(), emunand_path.display());
+    let emunand = open_emunand(&emunand_path, *dry_run)?;
+    if *dry_run {
+        for path in emunand.plan_uninstall(tid_bin, *remove_ticket)? {
+            println!("Would remove \"{}\".", path.display());
+        }
+    } else {
+        emunand.uninstall_title(tid_bin, *remove_ticket)?;
+    }
+    println!("Successfully uninstalled title with Title ID \"{}\" from EmuNAND at \"{}\"!{}", tid_bin, emunand_path.display(), dry_run_suffix(*dry_run));
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_uninstall_dry_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let title_dir = dir.path().join("title").join("00010001").join("52535449");
+        fs::create_dir_all(title_dir.join("content")).unwrap();
+        let target = EmunandTarget { emunand: Some(dir.path().to_string_lossy().to_string()), dolphin: false };
+        // Nothing is removed, and none of the EmuNAND's missing directories are created.
+        uninstall_title("0001000152535449", &target, &true, &true).unwrap();
+        assert!(title_dir.join("content").is_dir());
+        assert!(!dir.path().join("shared1").exists());
+        // Titles that aren't installed still fail.
+        assert!(uninstall_title("0001000152535450", &target, &false, &true).is_err());
+        uninstall_title("0001000152535449", &target, &false, &false).unwrap();
+        assert!(!title_dir.exists());
+    }
+}
@@ -1,5 +1,7 @@
 // nand/mod.rs from rustii (c) 2025 NinjaCheetah & Contributors
 // https://github.com/NinjaCheetah/rustii
 
+pub mod backup;
 pub mod emunand;
+pub mod save;
 pub mod setting;
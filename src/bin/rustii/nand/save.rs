@@ -0,0 +1,57 @@
+// nand/save.rs from rustii (c) 2025 NinjaCheetah & Contributors
+// https://github.com/NinjaCheetah/rustii
+//
+// Code for the Wii savegame (data.bin) commands in the rustii CLI.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{bail, Context, Result};
+use clap::Subcommand;
+use rustii::nand::save;
+
+#[derive(Subcommand)]
+#[command(arg_required_else_help = true)]
+pub enum Commands {
+    /// Decrypt and unpack a savegame (data.bin) into a directory
+    Unpack {
+        /// The path to the savegame to unpack
+        input: String,
+        /// The directory to unpack the savegame to
+        output: String,
+    }
+}
+
+pub fn unpack_save(input: &str, output: &str) -> Result<()> {
+    let in_path = Path::new(input);
+    if !in_path.exists() {
+        bail!("Source savegame \"{}\" could not be found.", in_path.display());
+    }
+    let out_path = PathBuf::from(output);
+    if out_path.exists() {
+        if !out_path.is_dir() {
+            bail!("A file already exists with the specified directory name!");
+        }
+    } else {
+        fs::create_dir(&out_path).with_context(|| format!("The output directory \"{}\" could not be created.", out_path.display()))?;
+    }
+    let save_data = save::SaveData::from_bytes(&fs::read(in_path).with_context(|| format!("Input file \"{}\" could not be read.", in_path.display()))?)
+        .with_context(|| "The provided savegame could not be parsed, and is likely invalid.")?;
+    // The banner lives alongside the rest of the save's files in the title's data directory.
+    fs::write(out_path.join("banner.bin"), &save_data.banner_data).with_context(|| "Failed to write output file \"banner.bin\".")?;
+    for file in &save_data.files {
+        let file_path = out_path.join(&file.name);
+        match file.file_type {
+            save::SaveFileType::Directory => {
+                fs::create_dir_all(&file_path).with_context(|| format!("The output directory \"{}\" could not be created.", file_path.display()))?;
+            },
+            save::SaveFileType::File => {
+                if let Some(parent) = file_path.parent() {
+                    fs::create_dir_all(parent).with_context(|| format!("The output directory \"{}\" could not be created.", parent.display()))?;
+                }
+                fs::write(&file_path, &file.data).with_context(|| format!("Failed to write output file \"{}\".", file.name))?;
+            }
+        }
+    }
+    println!("Successfully unpacked savegame for title {} to directory \"{}\"!", save_data.title_id, out_path.display());
+    Ok(())
+}
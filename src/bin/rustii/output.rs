@@ -0,0 +1,146 @@
+// output.rs from rustii (c) 2025 NinjaCheetah & Contributors
+// https://github.com/NinjaCheetah/rustii
+//
+// Common code for deciding where commands that write a single file should write it.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{bail, Context, Result};
+use clap::Args;
+
+#[derive(Args)]
+#[clap(next_help_heading = "Output Options")]
+pub struct OutputOptions {
+    /// A directory to write the output file to when no output name is provided, instead of the
+    /// default location
+    #[arg(long, conflicts_with = "output")]
+    pub output_dir: Option<String>,
+    /// Overwrite the output file if it already exists
+    #[arg(long)]
+    pub force: bool,
+    /// Do everything except writing the output file, and report what would have been written
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+/// Gets the suffix to add to the summary lines printed by a command, so that the output of a dry
+/// run can't be mistaken for changes that were actually made.
+pub fn dry_run_suffix(dry_run: bool) -> &'static str {
+    if dry_run { " (dry run)" } else { "" }
+}
+
+/// Resolves the path that a command should write its output to. An explicitly provided output path
+/// is always used as-is, while the default path is redirected into the output directory if one was
+/// provided. Commands are allowed to overwrite their own input file, but any other existing file
+/// will only be overwritten with --force.
+pub fn resolve_output(output: Option<PathBuf>, default: PathBuf, options: &OutputOptions, input: Option<&Path>) -> Result<PathBuf> {
+    let out_path = match (output, &options.output_dir) {
+        (Some(output), _) => output,
+        (None, Some(output_dir)) => {
+            let output_dir = Path::new(output_dir);
+            if output_dir.exists() && !output_dir.is_dir() {
+                bail!("A file already exists with the specified output directory name!");
+            }
+            if !options.dry_run {
+                fs::create_dir_all(output_dir).with_context(|| format!("The output directory \"{}\" could not be created.", output_dir.display()))?;
+            }
+            match default.file_name() {
+                Some(name) => output_dir.join(name),
+                None => bail!("The default output name \"{}\" is not a file name.", default.display()),
+            }
+        },
+        (None, None) => default,
+    };
+    if out_path.exists() && !options.force && !is_same_file(&out_path, input) {
+        bail!("Output file \"{}\" already exists! Use --force to overwrite it.", out_path.display());
+    }
+    Ok(out_path)
+}
+
+/// Writes the data produced by a command to its output path, or only reports the path and size
+/// that would have been written during a dry run.
+pub fn write_output(out_path: &Path, data: &[u8], options: &OutputOptions) -> Result<()> {
+    if options.dry_run {
+        println!("Would write {} bytes to \"{}\".", data.len(), out_path.display());
+        return Ok(());
+    }
+    fs::write(out_path, data).with_context(|| format!("Could not open output file \"{}\" for writing.", out_path.display()))
+}
+
+// Checks whether the output path refers to the same file as the input, which is how commands that
+// edit a file in place are expected to behave.
+fn is_same_file(out_path: &Path, input: Option<&Path>) -> bool {
+    match input {
+        Some(input) => match (fs::canonicalize(out_path), fs::canonicalize(input)) {
+            (Ok(out_path), Ok(input)) => out_path == input,
+            _ => false,
+        },
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn options(output_dir: Option<&Path>, force: bool) -> OutputOptions {
+        OutputOptions { output_dir: output_dir.map(|dir| dir.to_string_lossy().to_string()), force, dry_run: false }
+    }
+
+    #[test]
+    fn test_resolve_output_refuses_existing() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.wad");
+        let existing = dir.path().join("existing.wad");
+        fs::write(&input, b"input").unwrap();
+        fs::write(&existing, b"existing").unwrap();
+        // Overwriting an unrelated file needs --force, whether it was named explicitly or not.
+        let err = resolve_output(Some(existing.clone()), input.clone(), &options(None, false), Some(&input)).unwrap_err();
+        assert!(err.to_string().contains("--force"));
+        assert!(resolve_output(None, existing.clone(), &options(None, false), Some(&input)).is_err());
+        assert!(resolve_output(None, existing.clone(), &options(None, false), None).is_err());
+        assert_eq!(resolve_output(Some(existing.clone()), input.clone(), &options(None, true), Some(&input)).unwrap(), existing);
+        // Files that don't exist yet and the input itself can always be written.
+        let new = dir.path().join("new.wad");
+        assert_eq!(resolve_output(Some(new.clone()), input.clone(), &options(None, false), Some(&input)).unwrap(), new);
+        assert_eq!(resolve_output(None, input.clone(), &options(None, false), Some(&input)).unwrap(), input);
+    }
+
+    #[test]
+    fn test_resolve_output_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.wad");
+        fs::write(&input, b"input").unwrap();
+        let out_dir = dir.path().join("out").join("nested");
+        // The default name is redirected into the output directory, which is created if needed.
+        let out_path = resolve_output(None, input.clone(), &options(Some(&out_dir), false), Some(&input)).unwrap();
+        assert_eq!(out_path, out_dir.join("input.wad"));
+        assert!(out_dir.is_dir());
+        // Once it exists there, it's no longer the input and can't be silently replaced.
+        fs::write(&out_path, b"output").unwrap();
+        assert!(resolve_output(None, input.clone(), &options(Some(&out_dir), false), Some(&input)).is_err());
+        assert!(resolve_output(None, input.clone(), &options(Some(&out_dir), true), Some(&input)).is_ok());
+        // An output directory can't be created over an existing file.
+        assert!(resolve_output(None, input.clone(), &options(Some(&input), false), Some(&input)).is_err());
+    }
+
+    #[test]
+    fn test_dry_run_writes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.wad");
+        fs::write(&input, b"input").unwrap();
+        let out_dir = dir.path().join("out");
+        let dry_run = OutputOptions { dry_run: true, ..options(Some(&out_dir), false) };
+        // The output directory isn't created, and neither is the output file.
+        let out_path = resolve_output(None, input.clone(), &dry_run, Some(&input)).unwrap();
+        write_output(&out_path, b"output", &dry_run).unwrap();
+        assert!(!out_dir.exists());
+        let dry_run = OutputOptions { dry_run: true, ..options(None, false) };
+        write_output(&input, b"output", &dry_run).unwrap();
+        assert_eq!(fs::read(&input).unwrap(), b"input");
+        // Existing files are still refused, so a dry run fails wherever a real run would.
+        let existing = dir.path().join("existing.wad");
+        fs::write(&existing, b"existing").unwrap();
+        assert!(resolve_output(Some(existing), input.clone(), &dry_run, Some(&input)).is_err());
+    }
+}
@@ -0,0 +1,96 @@
+// title/cert.rs from rustii (c) 2025 NinjaCheetah & Contributors
+// https://github.com/NinjaCheetah/rustii
+//
+// Code for certificate chain-related commands in the rustii CLI.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{bail, Context, Result};
+use clap::Subcommand;
+use rustii::title::cert;
+
+#[derive(Subcommand)]
+#[command(arg_required_else_help = true)]
+pub enum Commands {
+    /// Split a certificate chain into its individual certificates
+    Export {
+        /// The path to the certificate chain to export
+        input: String,
+        /// The directory to write each certificate to, named after the certificate's identity
+        #[arg(short, long, required_unless_present = "json")]
+        output: Option<String>,
+        /// Print the issuer, identity, key type, and public key of each certificate as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+pub fn export_cert(input: &str, output: &Option<String>, json: &bool) -> Result<()> {
+    let in_path = Path::new(input);
+    if !in_path.exists() {
+        bail!("Source certificate chain \"{}\" could not be found.", in_path.display());
+    }
+    let cert_chain = cert::CertificateChain::open(in_path)
+        .with_context(|| format!("The provided certificate chain \"{}\" could not be loaded.", in_path.display()))?;
+    if let Some(output) = output {
+        let out_path = PathBuf::from(output);
+        if out_path.exists() {
+            if !out_path.is_dir() {
+                bail!("A file already exists with the specified directory name!");
+            }
+        } else {
+            fs::create_dir(&out_path).with_context(|| format!("The output directory \"{}\" could not be created.", out_path.display()))?;
+        }
+        for cert in cert_chain.iter() {
+            let cert_path = out_path.join(format!("{}.cert", cert.child_cert_identity()));
+            fs::write(&cert_path, cert.to_bytes()?).with_context(|| format!("Failed to write output file \"{}\".", cert_path.display()))?;
+        }
+        // Keep stdout clean when the JSON is being printed, so that it can be piped elsewhere.
+        if !*json {
+            println!("Successfully exported certificate chain to directory \"{}\"!", out_path.display());
+        }
+    }
+    if *json {
+        let certs: Vec<&cert::Certificate> = cert_chain.iter().collect();
+        println!("{}", serde_json::to_string_pretty(&certs)?);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_cert(issuer: &str, identity: &str) -> Vec<u8> {
+        let mut buf: Vec<u8> = Vec::new();
+        buf.extend_from_slice(&0x00010001u32.to_be_bytes());
+        buf.resize(0x140, 0);
+        let mut name = [0u8; 64];
+        name[..issuer.len()].copy_from_slice(issuer.as_bytes());
+        buf.extend_from_slice(&name);
+        buf.extend_from_slice(&0x00000001u32.to_be_bytes());
+        let mut name = [0u8; 64];
+        name[..identity.len()].copy_from_slice(identity.as_bytes());
+        buf.extend_from_slice(&name);
+        buf.extend_from_slice(&[0u8; 4]);
+        buf.extend_from_slice(&[0xFFu8; 256]);
+        buf.extend_from_slice(&0x00010001u32.to_be_bytes());
+        buf.resize((buf.len() + 63) & !63, 0);
+        buf
+    }
+
+    #[test]
+    fn test_export_cert_reassembles() {
+        let dir = tempfile::tempdir().unwrap();
+        let names = [("Root", "CA00000001"), ("Root-CA00000001", "CP00000004"), ("Root-CA00000001", "XS00000003")];
+        let chain: Vec<u8> = names.iter().flat_map(|(issuer, identity)| build_cert(issuer, identity)).collect();
+        let chain_path = dir.path().join("chain.cert");
+        fs::write(&chain_path, &chain).unwrap();
+        let out_path = dir.path().join("certs");
+        export_cert(chain_path.to_str().unwrap(), &Some(out_path.to_str().unwrap().to_owned()), &false).unwrap();
+        let reassembled: Vec<u8> = names.iter()
+            .flat_map(|(_, identity)| fs::read(out_path.join(format!("{}.cert", identity))).unwrap())
+            .collect();
+        assert_eq!(reassembled, chain);
+    }
+}
@@ -6,60 +6,72 @@
 use std::{str, fs};
 use std::path::{Path, PathBuf};
 use anyhow::{bail, Context, Result};
-use rustii::{title, title::tmd, title::ticket};
-use crate::filetypes::{WiiFileType, identify_file_type};
+use rustii::title::signed::{SignedFile, SignedFileType};
+use crate::batch;
+use crate::output::{dry_run_suffix, resolve_output, write_output, OutputOptions};
 
-pub fn fakesign(input: &str, output: &Option<String>) -> Result<()> {
+fn fakesign_file(input: &str, output: &Option<String>, output_options: &OutputOptions) -> Result<()> {
     let in_path = Path::new(input);
     if !in_path.exists() {
         bail!("Input file \"{}\" does not exist.", in_path.display());
     }
-    match identify_file_type(input) {
-        Some(WiiFileType::Wad) => {
-            let out_path = if output.is_some() {
-                PathBuf::from(output.clone().unwrap().as_str()).with_extension("wad")
-            } else {
-                PathBuf::from(input)
-            };
-            // Load WAD into a Title instance, then fakesign it.
-            let mut title = title::Title::from_bytes(fs::read(in_path).with_context(|| "Could not open WAD file for reading.")?.as_slice())
-                .with_context(|| "The provided WAD file could not be parsed, and is likely invalid.")?;
-            title.fakesign().with_context(|| "An unknown error occurred while fakesigning the provided WAD.")?;
-            // Write output file.
-            fs::write(out_path, title.to_wad()?.to_bytes()?).with_context(|| "Could not open output file for writing.")?;
-            println!("WAD fakesigned!");
-        },
-        Some(WiiFileType::Tmd) => {
-            let out_path = if output.is_some() {
-                PathBuf::from(output.clone().unwrap().as_str()).with_extension("tmd")
-            } else {
-                PathBuf::from(input)
-            };
-            // Load TMD into a TMD instance, then fakesign it.
-            let mut tmd = tmd::TMD::from_bytes(fs::read(in_path).with_context(|| "Could not open TMD file for reading.")?.as_slice())
-                .with_context(|| "The provided TMD file could not be parsed, and is likely invalid.")?;
-            tmd.fakesign().with_context(|| "An unknown error occurred while fakesigning the provided TMD.")?;
-            // Write output file.
-            fs::write(out_path, tmd.to_bytes()?).with_context(|| "Could not open output file for writing.")?;
-            println!("TMD fakesigned!");
-        },
-        Some(WiiFileType::Ticket) => {
-            let out_path = if output.is_some() {
-                PathBuf::from(output.clone().unwrap().as_str()).with_extension("tik")
-            } else {
-                PathBuf::from(input)
-            };
-            // Load Ticket into a Ticket instance, then fakesign it.
-            let mut ticket = ticket::Ticket::from_bytes(fs::read(in_path).with_context(|| "Could not open Ticket file for reading.")?.as_slice())
-                .with_context(|| "The provided Ticket file could not be parsed, and is likely invalid.")?;
-            ticket.fakesign().with_context(|| "An unknown error occurred while fakesigning the provided Ticket.")?;
-            // Write output file.
-            fs::write(out_path, ticket.to_bytes()?).with_context(|| "Could not open output file for writing.")?;
-            println!("Ticket fakesigned!");
-        },
-        _ => {
-            bail!("You can only fakesign TMDs, Tickets, and WADs!");
-        }
-    }
+    let data = fs::read(in_path).with_context(|| format!("Input file \"{}\" could not be read.", in_path.display()))?;
+    // The type of file is identified from its data rather than its name, so files with misleading
+    // extensions can still be fakesigned.
+    let Some(file_type) = SignedFile::identify(&data) else {
+        bail!("You can only fakesign TMDs, Tickets, and WADs!");
+    };
+    let mut file = SignedFile::from_bytes(&data)
+        .with_context(|| format!("The provided {} file could not be parsed, and is likely invalid.", file_type))?;
+    let extension = match file_type {
+        SignedFileType::Wad => "wad",
+        SignedFileType::Tmd => "tmd",
+        SignedFileType::Ticket => "tik",
+    };
+    let out_path = resolve_output(output.as_ref().map(|output| PathBuf::from(output).with_extension(extension)), PathBuf::from(input), output_options, Some(in_path))?;
+    file.fakesign().with_context(|| format!("An unknown error occurred while fakesigning the provided {}.", file_type))?;
+    write_output(&out_path, &file.to_bytes()?, output_options)?;
+    println!("{} \"{}\" fakesigned!{}", file_type, input, dry_run_suffix(output_options.dry_run));
     Ok(())
 }
+
+pub fn fakesign(inputs: &[String], output: &Option<String>, output_options: &OutputOptions) -> Result<()> {
+    let inputs = batch::expand_inputs(inputs);
+    if output.is_some() && inputs.len() > 1 {
+        bail!("An output name can only be specified when fakesigning a single file.");
+    }
+    batch::run_batch(&inputs, |input| fakesign_file(input, output, output_options))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn build_tmd() -> Vec<u8> {
+        let mut buf = vec![0u8; 0x1E4];
+        buf[0..4].copy_from_slice(&0x00010001u32.to_be_bytes());
+        buf[0x140..0x15A].copy_from_slice(b"Root-CA00000001-CP00000004");
+        buf
+    }
+
+    #[test]
+    fn test_fakesign_dry_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let tmd_path = dir.path().join("title.tmd");
+        fs::write(&tmd_path, build_tmd()).unwrap();
+        let options = OutputOptions { output_dir: None, force: false, dry_run: true };
+        let input = tmd_path.to_str().unwrap();
+        // Neither the input nor a new output file should be written.
+        fakesign_file(input, &None, &options).unwrap();
+        fakesign_file(input, &Some(dir.path().join("new").to_string_lossy().to_string()), &options).unwrap();
+        assert_eq!(fs::read(&tmd_path).unwrap(), build_tmd());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+        // Files that can't be fakesigned still fail.
+        let bad_path = dir.path().join("bad.tmd");
+        fs::write(&bad_path, [0x5A; 0x100]).unwrap();
+        assert!(fakesign_file(bad_path.to_str().unwrap(), &None, &options).is_err());
+        // Without a dry run, the file is actually fakesigned.
+        fakesign_file(input, &None, &OutputOptions { dry_run: false, ..options }).unwrap();
+        assert_ne!(fs::read(&tmd_path).unwrap(), build_tmd());
+    }
+}
@@ -0,0 +1,48 @@
+// title/hash.rs from rustii (c) 2025 NinjaCheetah & Contributors
+// https://github.com/NinjaCheetah/rustii
+//
+// Code for the content hash command in the rustii CLI.
+
+use std::fs;
+use std::path::Path;
+use anyhow::{bail, Context, Result};
+use sha1::{Digest, Sha1};
+use rustii::title::tmd;
+
+pub fn hash(input: &str, tmd: &Option<String>, index: &Option<u16>, cid: &Option<String>) -> Result<()> {
+    let in_path = Path::new(input);
+    if !in_path.exists() {
+        bail!("Input file \"{}\" does not exist.", in_path.display());
+    }
+    let data = fs::read(in_path).with_context(|| format!("Input file \"{}\" could not be read.", in_path.display()))?;
+    println!("SHA-1: {}", hex::encode(Sha1::digest(&data)));
+    println!("Size: {} bytes", data.len());
+    let Some(tmd) = tmd else {
+        return Ok(());
+    };
+    let tmd_path = Path::new(tmd);
+    let tmd = tmd::TMD::open(tmd_path).with_context(|| format!("The provided TMD file \"{}\" could not be loaded.", tmd_path.display()))?;
+    let record = if let Some(index) = index {
+        match tmd.content_record_by_index(*index) {
+            Some(record) => record,
+            None => bail!("The specified index {} does not exist in the provided TMD!", index),
+        }
+    } else if let Some(cid) = cid {
+        let cid_value = super::parse_cid(cid)?;
+        match tmd.content_record_by_cid(cid_value) {
+            Some(record) => record,
+            None => bail!("The specified Content ID \"{}\" does not exist in the provided TMD!", cid),
+        }
+    } else {
+        bail!("An index or Content ID must be provided to compare against a TMD!");
+    };
+    println!("Expected SHA-1: {}", hex::encode(record.content_hash));
+    println!("Expected Size: {} bytes", record.content_size);
+    if record.matches(&data) {
+        println!("Result: Match (Content ID {:08X}, index {})", record.content_id, record.index);
+        return Ok(());
+    }
+    println!("Result: Mismatch (Content ID {:08X}, index {})", record.content_id, record.index);
+    println!("Size Difference: {:+} bytes", data.len() as i64 - record.content_size as i64);
+    bail!("The hash of \"{}\" does not match the content record in the provided TMD.", in_path.display());
+}
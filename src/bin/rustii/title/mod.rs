@@ -1,6 +1,49 @@
 // title/mod.rs from rustii (c) 2025 NinjaCheetah & Contributors
 // https://github.com/NinjaCheetah/rustii
+//
+// Root for all title-related commands, and helpers shared between them.
 
+pub mod cert;
 pub mod fakesign;
+pub mod hash;
+#[cfg(feature = "nus")]
 pub mod nus;
+pub mod ticket;
+pub mod tmd;
 pub mod wad;
+
+use anyhow::{bail, Result};
+
+/// Parses a Content ID passed on the command line. Content IDs can be 0x-prefixed hex (0x10),
+/// 8-digit hex like rustii displays them (00000010), or decimal (16).
+pub fn parse_cid(cid: &str) -> Result<u32> {
+    let parsed = if let Some(hex) = cid.strip_prefix("0x").or_else(|| cid.strip_prefix("0X")) {
+        u32::from_str_radix(hex, 16)
+    } else if cid.len() == 8 {
+        u32::from_str_radix(cid, 16)
+    } else {
+        cid.parse::<u32>()
+    };
+    match parsed {
+        Ok(cid) => Ok(cid),
+        Err(_) => bail!("The specified Content ID \"{}\" is invalid! Content IDs must be 0x-prefixed hex (0x10), 8-digit hex (00000010), or decimal (16).", cid),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cid() {
+        assert_eq!(parse_cid("0x10").unwrap(), 0x10);
+        assert_eq!(parse_cid("0XfF").unwrap(), 0xFF);
+        assert_eq!(parse_cid("00000010").unwrap(), 0x10);
+        assert_eq!(parse_cid("0000001a").unwrap(), 0x1A);
+        assert_eq!(parse_cid("16").unwrap(), 16);
+        assert_eq!(parse_cid("4294967295").unwrap(), u32::MAX);
+        for invalid in ["1a", "0x", "0x100000000", "-1", ""] {
+            assert!(parse_cid(invalid).unwrap_err().to_string().contains("0x-prefixed hex"));
+        }
+    }
+}
@@ -4,12 +4,13 @@
 // Code for NUS-related commands in the rustii CLI.
 
 use std::{str, fs};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use anyhow::{bail, Context, Result};
 use clap::{Subcommand, Args};
 use sha1::{Sha1, Digest};
-use rustii::title::{cert, content, crypto, nus, ticket, tmd};
+use rustii::title::{cert, content, crypto, manifest, nus, ticket, tmd, versions, TitleId};
 use rustii::title;
+use crate::output::{dry_run_suffix, resolve_output, write_output, OutputOptions};
 
 #[derive(Subcommand)]
 #[command(arg_required_else_help = true)]
@@ -18,33 +19,57 @@ pub enum Commands {
     Content {
         /// The Title ID that the content belongs to
         tid: String,
-        /// The Content ID of the content (in hex format, like 000000xx)
+        /// The Content ID of the content (0x-prefixed or 8-digit hex, or decimal)
         cid: String,
         /// The title version that the content belongs to (only required for decryption)
         #[arg(short, long)]
-        version: Option<u16>,
+        version: Option<String>,
         /// An optional content file name; defaults to <cid>(.app)
         #[arg(short, long)]
         output: Option<String>,
+        #[command(flatten)]
+        output_options: OutputOptions,
         /// Decrypt the content
         #[arg(short, long)]
         decrypt: bool,
     },
+    /// Download every title that makes up a system update from the NUS
+    SystemTitles {
+        /// The Wii Menu version installed by the system update (e.g. 4.3U, or 4.3 with --region)
+        #[arg(short, long)]
+        menu: String,
+        /// The region of the system update (U, E, J, or K), if not included in the menu version
+        #[arg(short, long)]
+        region: Option<String>,
+        /// The directory to download the titles to
+        #[arg(short, long)]
+        output: String,
+        /// Print the titles that would be downloaded without downloading anything
+        #[arg(long)]
+        dry_run: bool,
+    },
     /// Download a Ticket from the NUS
     Ticket {
         /// The Title ID that the Ticket is for
         tid: String,
-        /// An optional Ticket name; defaults to <tid>.tik
+        /// An optional Ticket name; defaults to <tid>.tik, or cetk with --raw
         #[arg(short, long)]
         output: Option<String>,
+        #[command(flatten)]
+        output_options: OutputOptions,
+        /// Save the cetk exactly as it's served by the NUS, including the certificates appended
+        /// to the Ticket
+        #[arg(long)]
+        raw: bool,
     },
     /// Download a title from the NUS
     Title {
         /// The Title ID of the Title to download
         tid: String,
-        /// The version of the Title to download
+        /// The version of the Title to download (either decimal, or a standard version like 4.3U for
+        /// system titles)
         #[arg(short, long)]
-        version: Option<u16>,
+        version: Option<String>,
         #[command(flatten)]
         output: TitleOutputType,
     },
@@ -52,12 +77,22 @@ pub enum Commands {
     Tmd {
         /// The Title ID that the TMD is for
         tid: String,
-        /// The version of the TMD to download
+        /// The version of the TMD to download (either decimal, or a standard version like 4.3U for
+        /// system titles)
         #[arg(short, long)]
-        version: Option<u16>,
+        version: Option<String>,
         /// An optional TMD name; defaults to <tid>.tmd
         #[arg(short, long)]
         output: Option<String>,
+        #[command(flatten)]
+        output_options: OutputOptions,
+        /// Save the TMD exactly as it's served by the NUS, including the certificates appended to
+        /// the TMD
+        #[arg(long)]
+        raw: bool,
+        /// Also save the certificates appended to the TMD to the specified file
+        #[arg(long)]
+        certs: Option<String>,
     }
 }
 
@@ -73,43 +108,55 @@ pub struct TitleOutputType {
     wad: Option<String>,
 }
 
-pub fn download_content(tid: &str, cid: &str, version: &Option<u16>, output: &Option<String>, decrypt: &bool) -> Result<()> {
-    println!("Downloading content with Content ID {cid}...");
-    if tid.len() != 16 {
-        bail!("The specified Title ID is invalid!");
+// Parses a title version provided on the command line, which can either be a decimal version or
+// a standard version string like "4.3U" that can be converted to one.
+fn parse_version(version: &Option<String>, title_id: TitleId) -> Result<Option<u16>> {
+    match version {
+        Some(version) => match version.parse::<u16>() {
+            Ok(version) => Ok(Some(version)),
+            Err(_) => match versions::standard_to_dec(version, &title_id.to_string()) {
+                Some(version) => Ok(Some(version)),
+                None => bail!("The specified version \"{}\" is invalid! Versions must either be decimal or a known standard version like 4.3U.", version),
+            }
+        },
+        None => Ok(None),
     }
-    let cid = u32::from_str_radix(cid, 16).with_context(|| "The specified Content ID is invalid!")?;
-    let tid: [u8; 8] = hex::decode(tid)?.try_into().unwrap();
-    let content = nus::download_content(tid, cid, true).with_context(|| "Content data could not be downloaded.")?;
-    let out_path = if output.is_some() {
-        PathBuf::from(output.clone().unwrap())
-    } else if *decrypt {
-        PathBuf::from(format!("{:08X}.app", cid))
+}
+
+pub fn download_content(tid: &str, cid: &str, version: &Option<String>, output: &Option<String>, output_options: &OutputOptions, decrypt: &bool) -> Result<()> {
+    println!("Downloading content with Content ID {cid}...");
+    let title_id: TitleId = tid.parse().with_context(|| "The specified Title ID is invalid!")?;
+    let version = parse_version(version, title_id)?;
+    let cid = super::parse_cid(cid)?;
+    let default_name = if *decrypt {
+        format!("{:08X}.app", cid)
     } else {
-        PathBuf::from(format!("{:08X}", cid))
+        format!("{:08X}", cid)
     };
+    let out_path = resolve_output(output.as_ref().map(PathBuf::from), PathBuf::from(default_name), output_options, None)?;
+    let content = nus::download_content(title_id, cid, true).with_context(|| "Content data could not be downloaded.")?;
     if *decrypt {
         // We need the version to get the correct TMD because the content's index is the IV for
         // decryption. A Ticket also needs to be available, of course.
-        let version: u16 = if version.is_some() {
-            version.unwrap()
+        let version: u16 = if let Some(version) = version {
+            version
         } else {
             bail!("You must specify the title version that the requested content belongs to for decryption!");
         };
-        let tmd_res = &nus::download_tmd(tid, Some(version), true);
+        let tmd_res = &nus::download_tmd(title_id, Some(version), true);
         println!(" - Downloading TMD...");
         let tmd = match tmd_res {
-            Ok(tmd) => tmd::TMD::from_bytes(tmd)?,
+            Ok(tmd) => tmd::TMD::from_bytes(&tmd.tmd)?,
             Err(_) => bail!("No TMD could be found for the specified version! Check the version and try again.")
         };
         println!(" - Downloading Ticket...");
-        let tik_res = &nus::download_ticket(tid, true);
-        let tik = match tik_res {
-            Ok(tik) => ticket::Ticket::from_bytes(tik)?,
-            Err(_) => bail!("No Ticket is available for this title! The content cannot be decrypted.")
+        let tik = match nus::download_ticket(title_id, true) {
+            Ok(tik) => ticket::Ticket::from_bytes(&tik)?,
+            Err(nus::NUSError::NotFound) => bail!("No Ticket is available for this title! The content cannot be decrypted."),
+            Err(e) => return Err(e).with_context(|| "Ticket data could not be downloaded."),
         };
         println!(" - Decrypting content...");
-        let (content_hash, content_size, content_index) = tmd.content_records.borrow().iter()
+        let (content_hash, content_size, content_index) = tmd.content_records.read().iter()
             .find(|record| record.content_id == cid)
             .map(|record| (record.content_hash, record.content_size, record.index))
             .with_context(|| "No matching content record could be found. Please make sure the requested content is from the specified title version.")?;
@@ -123,34 +170,68 @@ pub fn download_content(tid: &str, cid: &str, version: &Option<u16>, output: &Op
             bail!("The content's hash did not match the expected value. (Hash was {}, but the expected hash is {}.)",
                 hex::encode(result), hex::encode(content_hash));
         }
-        fs::write(&out_path, content_dec).with_context(|| format!("Failed to open content file \"{}\" for writing.", out_path.display()))?;
+        write_output(&out_path, &content_dec, output_options)?;
     } else {
         // If we're not decrypting, just write the file out and call it a day.
-        fs::write(&out_path, content).with_context(|| format!("Failed to open content file \"{}\" for writing.", out_path.display()))?
+        write_output(&out_path, &content, output_options)?;
     }
-    println!("Successfully downloaded content with Content ID {:08X} to file \"{}\"!", cid, out_path.display());
+    println!("Successfully downloaded content with Content ID {:08X} to file \"{}\"!{}", cid, out_path.display(), dry_run_suffix(output_options.dry_run));
     Ok(())
 }
 
-pub fn download_ticket(tid: &str, output: &Option<String>) -> Result<()> {
-    println!("Downloading Ticket for title {tid}...");
-    if tid.len() != 16 {
-        bail!("The specified Title ID is invalid!");
-    }
-    let out_path = if output.is_some() {
-        PathBuf::from(output.clone().unwrap())
-    } else {
-        PathBuf::from(format!("{}.tik", tid))
+// Downloads a single title from a system update into the output directory, packing it into a WAD
+// if it has a common Ticket, and saving its encrypted contents otherwise.
+fn download_system_title(title_id: TitleId, version: u16, cert_chain: &cert::CertificateChain, out_path: &Path) -> Result<()> {
+    println!(" - Downloading and parsing TMD...");
+    let tmd = tmd::TMD::from_bytes(&nus::download_tmd(title_id, Some(version), true).with_context(|| "TMD data could not be downloaded.")?.tmd)?;
+    println!(" - Downloading and parsing Ticket...");
+    let out_name = out_path.join(format!("{}-v{}", title_id, version)).to_string_lossy().into_owned();
+    let tik = match nus::download_ticket(title_id, true) {
+        Ok(tik) => ticket::Ticket::from_bytes(&tik)?,
+        Err(nus::NUSError::NotFound) => {
+            println!("  - No Ticket is available!");
+            return download_title_dir(tmd, None, cert_chain.clone(), out_name);
+        },
+        Err(e) => return Err(e).with_context(|| "Ticket data could not be downloaded."),
     };
-    let tid: [u8; 8] = hex::decode(tid)?.try_into().unwrap();
-    let tik_data = nus::download_ticket(tid, true).with_context(|| "Ticket data could not be downloaded.")?;
-    fs::write(&out_path, tik_data)?;
-    println!("Successfully downloaded Ticket to \"{}\"!", out_path.display());
-    Ok(())
+    let mut contents: Vec<Vec<u8>> = Vec::new();
+    for record in tmd.content_records.read().iter() {
+        println!(" - Downloading content {} of {} (Content ID: {}, Size: {} bytes)...",
+            record.index + 1, tmd.num_contents(), record.content_id, record.content_size);
+        contents.push(nus::download_content(title_id, record.content_id, true).with_context(|| format!("Content with Content ID {} could not be downloaded.", record.content_id))?);
+    }
+    let content_region = content::ContentRegion::from_contents(contents, tmd.content_records.clone())?;
+    let title = title::Title::from_parts(cert_chain.clone(), None, tik, tmd, content_region, None)?;
+    download_title_wad(title, out_name)
 }
 
-fn download_title_dir(title: title::Title, output: String) -> Result<()> {
-    println!(" - Saving downloaded data...");
+pub fn download_system_titles(menu: &str, region: &Option<String>, output: &str, dry_run: bool) -> Result<()> {
+    let menu_version = match region {
+        Some(region) => {
+            let region = region.to_ascii_uppercase();
+            if !["U", "E", "J", "K"].contains(&region.as_str()) {
+                bail!("The specified region \"{}\" is invalid! Valid regions are U, E, J, and K.", region);
+            }
+            if menu.ends_with(|c: char| c.is_ascii_alphabetic()) {
+                if !menu.to_ascii_uppercase().ends_with(&region) {
+                    bail!("The specified menu version \"{}\" does not match the specified region \"{}\"!", menu, region);
+                }
+                menu.to_owned()
+            } else {
+                format!("{}{}", menu, region)
+            }
+        },
+        None => menu.to_owned(),
+    };
+    let titles = versions::system_update_titles(&menu_version)
+        .with_context(|| format!("No system update is known for Wii Menu version \"{}\"! Only the 4.3 system updates are currently supported.", menu_version))?;
+    if dry_run {
+        println!("System update {} contains {} titles:", menu_version.to_ascii_uppercase(), titles.len());
+        for update_title in &titles {
+            println!("  - {} v{}", update_title.title_id, update_title.version);
+        }
+        return Ok(());
+    }
     let out_path = PathBuf::from(output);
     if out_path.exists() {
         if !out_path.is_dir() {
@@ -159,25 +240,71 @@ fn download_title_dir(title: title::Title, output: String) -> Result<()> {
     } else {
         fs::create_dir(&out_path).with_context(|| format!("The output directory \"{}\" could not be created.", out_path.display()))?;
     }
-    let tid = hex::encode(title.tmd.title_id());
-    println!("  - Saving TMD...");
-    fs::write(out_path.join(format!("{}.tmd", &tid)), title.tmd.to_bytes()?).with_context(|| format!("Failed to open TMD file \"{}.tmd\" for writing.", tid))?;
-    println!("  - Saving Ticket...");
-    fs::write(out_path.join(format!("{}.tik", &tid)), title.ticket.to_bytes()?).with_context(|| format!("Failed to open Ticket file \"{}.tmd\" for writing.", tid))?;
-    println!("  - Saving certificate chain...");
-    fs::write(out_path.join(format!("{}.cert", &tid)), title.cert_chain.to_bytes()?).with_context(|| format!("Failed to open certificate chain file \"{}.cert\" for writing.", tid))?;
-    // Iterate over the content files and write them out in encrypted form.
-    for record in title.content.content_records.borrow().iter() {
-        println!("  - Decrypting and saving content with Content ID {}...", record.content_id);
-        fs::write(out_path.join(format!("{:08X}.app", record.content_id)), title.get_content_by_cid(record.content_id)?)
-            .with_context(|| format!("Failed to open content file \"{:08X}.app\" for writing.", record.content_id))?;
+    println!("Downloading system update {}, please wait...", menu_version.to_ascii_uppercase());
+    let cert_chain = cert::CertificateChain::from_bytes(&nus::download_cert_chain(true).with_context(|| "Certificate chain could not be built.")?)?;
+    download_update_titles(&titles, &out_path, |title_id, version| download_system_title(title_id, version, &cert_chain, &out_path))
+        .with_context(|| format!("System update {} could not be fully downloaded.", menu_version.to_ascii_uppercase()))?;
+    println!("Successfully downloaded system update {} to directory \"{}\"!", menu_version.to_ascii_uppercase(), out_path.display());
+    Ok(())
+}
+
+// Downloads every title in a system update with the provided download function, collecting
+// failures so that one bad title doesn't abort the whole set. Only titles that were packed into a
+// WAD are skipped, since a title without a Ticket is saved to a directory that could have been
+// left partially filled, and downloading it again resumes it from its manifest.
+fn download_update_titles<F>(titles: &[versions::SystemUpdateTitle], out_path: &Path, mut download: F) -> Result<()>
+where
+    F: FnMut(TitleId, u16) -> Result<()>,
+{
+    let mut failures: Vec<(TitleId, anyhow::Error)> = Vec::new();
+    for (i, update_title) in titles.iter().enumerate() {
+        let out_name = format!("{}-v{}", update_title.title_id, update_title.version);
+        if out_path.join(format!("{}.wad", out_name)).exists() {
+            println!("Skipping title {} of {} ({} v{}), as it has already been downloaded.",
+                i + 1, titles.len(), update_title.title_id, update_title.version);
+            continue;
+        }
+        println!("Downloading title {} of {} ({} v{})...", i + 1, titles.len(), update_title.title_id, update_title.version);
+        if let Err(err) = download(update_title.title_id, update_title.version) {
+            println!("Failed to download title {}: {:#}", update_title.title_id, err);
+            failures.push((update_title.title_id, err));
+        }
     }
-    println!("Successfully downloaded title with Title ID {} to directory \"{}\"!", tid, out_path.display());
+    if !failures.is_empty() {
+        println!("The following titles could not be downloaded:");
+        for (title_id, err) in &failures {
+            println!("  - {}: {:#}", title_id, err);
+        }
+        bail!("{} of {} titles could not be downloaded!", failures.len(), titles.len());
+    }
+    Ok(())
+}
+
+pub fn download_ticket(tid: &str, output: &Option<String>, output_options: &OutputOptions, raw: &bool) -> Result<()> {
+    let title_id: TitleId = tid.parse().with_context(|| "The specified Title ID is invalid!")?;
+    println!("Downloading Ticket for title {}...", title_id);
+    let default_name = if *raw {
+        String::from("cetk")
+    } else {
+        format!("{}.tik", title_id)
+    };
+    let out_path = resolve_output(output.as_ref().map(PathBuf::from), PathBuf::from(default_name), output_options, None)?;
+    let tik_res = if *raw {
+        nus::download_cetk(title_id, true)
+    } else {
+        nus::download_ticket(title_id, true)
+    };
+    let tik_data = match tik_res {
+        Ok(tik_data) => tik_data,
+        Err(nus::NUSError::NotFound) => bail!("Title {} has no common Ticket on the NUS.", title_id),
+        Err(e) => return Err(e).with_context(|| "Ticket data could not be downloaded."),
+    };
+    write_output(&out_path, &tik_data, output_options)?;
+    println!("Successfully downloaded Ticket to \"{}\"!{}", out_path.display(), dry_run_suffix(output_options.dry_run));
     Ok(())
 }
 
-fn download_title_dir_enc(tmd: tmd::TMD, content_region: content::ContentRegion, cert_chain: cert::CertificateChain, output: String) -> Result<()> {
-    println!(" - Saving downloaded data...");
+fn download_title_dir(tmd: tmd::TMD, tik: Option<ticket::Ticket>, cert_chain: cert::CertificateChain, output: String) -> Result<()> {
     let out_path = PathBuf::from(output);
     if out_path.exists() {
         if !out_path.is_dir() {
@@ -186,16 +313,46 @@ fn download_title_dir_enc(tmd: tmd::TMD, content_region: content::ContentRegion,
     } else {
         fs::create_dir(&out_path).with_context(|| format!("The output directory \"{}\" could not be created.", out_path.display()))?;
     }
-    let tid = hex::encode(tmd.title_id());
-    println!("  - Saving TMD...");
-    fs::write(out_path.join(format!("{}.tmd", &tid)), tmd.to_bytes()?).with_context(|| format!("Failed to open TMD file \"{}.tmd\" for writing.", tid))?;
-    println!("  - Saving certificate chain...");
-    fs::write(out_path.join(format!("{}.cert", &tid)), cert_chain.to_bytes()?).with_context(|| format!("Failed to open certificate chain file \"{}.cert\" for writing.", tid))?;
-    // Iterate over the content files and write them out in encrypted form.
-    for record in content_region.content_records.borrow().iter() {
-        println!("  - Saving content with Content ID {}...", record.content_id);
-        fs::write(out_path.join(format!("{:08X}", record.content_id)), content_region.get_enc_content_by_cid(record.content_id)?)
-            .with_context(|| format!("Failed to open content file \"{:08X}\" for writing.", record.content_id))?;
+    let title_id = tmd.title_id();
+    let tid = hex::encode(title_id);
+    // Contents left behind by an earlier, interrupted download of the same title can be reused,
+    // since the manifest records exactly what they should be.
+    let manifest = manifest::TitleManifest::new(&tmd, tik.as_ref());
+    let previous = manifest::TitleManifest::read(&out_path).ok().flatten()
+        .filter(|previous| manifest.is_same_download(previous));
+    // Write the manifest before any contents, so that the download can be resumed if it doesn't
+    // finish.
+    manifest.write(&out_path).with_context(|| format!("Failed to open manifest file \"{}\" for writing.", manifest::MANIFEST_FILE_NAME))?;
+    println!(" - Saving TMD...");
+    fs::write(out_path.join(&manifest.tmd), tmd.to_bytes()?).with_context(|| format!("Failed to open TMD file \"{}\" for writing.", manifest.tmd))?;
+    if let (Some(tik), Some(tik_name)) = (&tik, &manifest.ticket) {
+        println!(" - Saving Ticket...");
+        fs::write(out_path.join(tik_name), tik.to_bytes()?).with_context(|| format!("Failed to open Ticket file \"{}\" for writing.", tik_name))?;
+    }
+    println!(" - Saving certificate chain...");
+    fs::write(out_path.join(&manifest.cert_chain), cert_chain.to_bytes()?).with_context(|| format!("Failed to open certificate chain file \"{}\" for writing.", manifest.cert_chain))?;
+    let num_contents = manifest.contents.len();
+    for (i, content) in manifest.contents.iter().enumerate() {
+        if previous.is_some() && manifest.is_content_complete(&out_path, content)? {
+            println!(" - Content {} of {} (Content ID: {}) was already downloaded, skipping...", i + 1, num_contents, content.content_id);
+            continue;
+        }
+        println!(" - Downloading content {} of {} (Content ID: {}, Size: {} bytes)...", i + 1, num_contents, content.content_id, content.size);
+        let data = nus::download_content(title_id, content.content_id, true).with_context(|| format!("Content with Content ID {} could not be downloaded.", content.content_id))?;
+        // Contents are saved decrypted when there's a Ticket to decrypt them with.
+        let data = match &tik {
+            Some(tik) => {
+                let mut content_dec = crypto::decrypt_content(&data, tik.dec_title_key(), content.index);
+                content_dec.truncate(content.size as usize);
+                if hex::encode(Sha1::digest(&content_dec)) != content.hash {
+                    bail!("Content with Content ID {} could not be decrypted, and may be corrupted.", content.content_id);
+                }
+                content_dec
+            },
+            None => data,
+        };
+        fs::write(out_path.join(&content.file), data).with_context(|| format!("Failed to open content file \"{}\" for writing.", content.file))?;
+        println!("   - Done!");
     }
     println!("Successfully downloaded title with Title ID {} to directory \"{}\"!", tid, out_path.display());
     Ok(())
@@ -204,78 +361,116 @@ fn download_title_dir_enc(tmd: tmd::TMD, content_region: content::ContentRegion,
 fn download_title_wad(title: title::Title, output: String) -> Result<()> {
     println!(" - Packing WAD...");
     let out_path = PathBuf::from(output).with_extension("wad");
-    fs::write(&out_path, title.to_wad().with_context(|| "A WAD could not be packed.")?.to_bytes()?).with_context(|| format!("Could not open WAD file \"{}\" for writing.", out_path.display()))?;
+    fs::write(&out_path, title.to_wad_bytes().with_context(|| "A WAD could not be packed.")?).with_context(|| format!("Could not open WAD file \"{}\" for writing.", out_path.display()))?;
     println!("Successfully downloaded title with Title ID {} to WAD file \"{}\"!", hex::encode(title.tmd.title_id()), out_path.display());
     Ok(())
 }
 
-pub fn download_title(tid: &str, version: &Option<u16>, output: &TitleOutputType) -> Result<()> {
-    if tid.len() != 16 {
-        bail!("The specified Title ID is invalid!");
-    }
-    if version.is_some() {
-        println!("Downloading title {} v{}, please wait...", tid, version.clone().unwrap());
+pub fn download_title(tid: &str, version: &Option<String>, output: &TitleOutputType) -> Result<()> {
+    let title_id: TitleId = tid.parse().with_context(|| "The specified Title ID is invalid!")?;
+    let version = parse_version(version, title_id)?;
+    if let Some(version) = version {
+        println!("Downloading title {} v{}, please wait...", title_id, version);
     } else {
-        println!("Downloading title {} vLatest, please wait...", tid);
+        println!("Downloading title {} vLatest, please wait...", title_id);
     }
-    let tid: [u8; 8] = hex::decode(tid)?.try_into().unwrap();
     println!(" - Downloading and parsing TMD...");
-    let tmd = tmd::TMD::from_bytes(&nus::download_tmd(tid, *version, true).with_context(|| "TMD data could not be downloaded.")?)?;
+    let tmd = tmd::TMD::from_bytes(&nus::download_tmd(title_id, version, true).with_context(|| "TMD data could not be downloaded.")?.tmd)?;
     println!(" - Downloading and parsing Ticket...");
-    let tik_res = &nus::download_ticket(tid, true);
-    let tik = match tik_res {
-        Ok(tik) => Some(ticket::Ticket::from_bytes(tik)?),
-        Err(_) => {
+    let tik = match nus::download_ticket(title_id, true) {
+        Ok(tik) => Some(ticket::Ticket::from_bytes(&tik)?),
+        Err(nus::NUSError::NotFound) => {
             if output.wad.is_some() {
                 bail!("--wad was specified, but this Title has no common Ticket and cannot be packed into a WAD!");
             } else {
                 println!("  - No Ticket is available!");
                 None
             }
-        }
+        },
+        Err(e) => return Err(e).with_context(|| "Ticket data could not be downloaded."),
     };
+    if output.wad.is_none() {
+        // Directories are written to as contents are downloaded, so that the download can be
+        // resumed if it's interrupted.
+        println!(" - Building certificate chain...");
+        let cert_chain = cert::CertificateChain::from_bytes(&nus::download_cert_chain(true).with_context(|| "Certificate chain could not be built.")?)?;
+        return download_title_dir(tmd, tik, cert_chain, output.output.clone().unwrap());
+    }
     // Build a vec of contents by iterating over the content records and downloading each one.
     let mut contents: Vec<Vec<u8>> = Vec::new();
-    for record in tmd.content_records.borrow().iter() {
+    for record in tmd.content_records.read().iter() {
         println!(" - Downloading content {} of {} (Content ID: {}, Size: {} bytes)...",
-            record.index + 1, &tmd.content_records.borrow().len(), record.content_id, record.content_size);
-        contents.push(nus::download_content(tid, record.content_id, true).with_context(|| format!("Content with Content ID {} could not be downloaded.", record.content_id))?);
+            record.index + 1, tmd.num_contents(), record.content_id, record.content_size);
+        contents.push(nus::download_content(title_id, record.content_id, true).with_context(|| format!("Content with Content ID {} could not be downloaded.", record.content_id))?);
         println!("   - Done!");
     }
     let content_region = content::ContentRegion::from_contents(contents, tmd.content_records.clone())?;
     println!(" - Building certificate chain...");
     let cert_chain = cert::CertificateChain::from_bytes(&nus::download_cert_chain(true).with_context(|| "Certificate chain could not be built.")?)?;
-    if tik.is_some() {
-        // If we have a Ticket, then build a Title and jump to the output method.
-        let title = title::Title::from_parts(cert_chain, None, tik.unwrap(), tmd, content_region, None)?;
-        if output.wad.is_some() {
-            download_title_wad(title, output.wad.clone().unwrap())?;
-        } else {
-            download_title_dir(title, output.output.clone().unwrap())?;
-        }
+    // A Ticket is always available here, since WAD output requires one.
+    let title = title::Title::from_parts(cert_chain, None, tik.unwrap(), tmd, content_region, None)?;
+    download_title_wad(title, output.wad.clone().unwrap())
+}
+
+pub fn download_tmd(tid: &str, version: &Option<String>, output: &Option<String>, output_options: &OutputOptions, raw: &bool, certs: &Option<String>) -> Result<()> {
+    let title_id: TitleId = tid.parse().with_context(|| "The specified Title ID is invalid!")?;
+    println!("Downloading TMD for title {}...", title_id);
+    let version = parse_version(version, title_id)?;
+    let default_name = if let Some(version) = version {
+        format!("{}.tmd.{}", title_id, version)
     } else {
-        // If we're downloading to a directory and have no Ticket, save the TMD and encrypted
-        // contents to the directory only.
-        download_title_dir_enc(tmd, content_region, cert_chain, output.output.clone().unwrap())?;
+        format!("{}.tmd", title_id)
+    };
+    let out_path = resolve_output(output.as_ref().map(PathBuf::from), PathBuf::from(default_name), output_options, None)?;
+    let certs_path = match certs {
+        Some(certs) => Some(resolve_output(Some(PathBuf::from(certs)), PathBuf::from(certs), output_options, None)?),
+        None => None,
+    };
+    let nus_tmd = nus::download_tmd(title_id, version, true).with_context(|| "TMD data could not be downloaded.")?;
+    if certs_path.is_some() && nus_tmd.certs.is_empty() {
+        bail!("The downloaded TMD has no certificates appended to it, so there are no certificates to save!");
+    }
+    let tmd_data = if *raw { nus_tmd.raw() } else { &nus_tmd.tmd };
+    write_output(&out_path, tmd_data, output_options)?;
+    println!("Successfully downloaded TMD to \"{}\"!{}", out_path.display(), dry_run_suffix(output_options.dry_run));
+    if let Some(certs_path) = certs_path {
+        write_output(&certs_path, nus_tmd.cert_data(), output_options)?;
+        println!("Saved {} certificates appended to the TMD to \"{}\"!{}", nus_tmd.certs.len(), certs_path.display(), dry_run_suffix(output_options.dry_run));
     }
     Ok(())
 }
 
-pub fn download_tmd(tid: &str, version: &Option<u16>, output: &Option<String>) -> Result<()> {
-    println!("Downloading TMD for title {tid}...");
-    if tid.len() != 16 {
-        bail!("The specified Title ID is invalid!");
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_download_update_titles() {
+        let dir = tempfile::tempdir().unwrap();
+        let titles: Vec<versions::SystemUpdateTitle> = [(0x0000000100000002, 513), (0x0000000100000009, 1034), (0x000000010000000C, 269)]
+            .into_iter()
+            .map(|(tid, version)| versions::SystemUpdateTitle { title_id: TitleId::new(u64::to_be_bytes(tid)), version })
+            .collect();
+        // A finished WAD is skipped, but a directory left behind by an interrupted download without
+        // a Ticket is downloaded again so that it can be resumed.
+        fs::write(dir.path().join(format!("{}-v513.wad", titles[0].title_id)), b"wad").unwrap();
+        fs::create_dir(dir.path().join(format!("{}-v1034", titles[1].title_id))).unwrap();
+        let mut downloaded: Vec<TitleId> = Vec::new();
+        download_update_titles(&titles, dir.path(), |title_id, _| {
+            downloaded.push(title_id);
+            Ok(())
+        }).unwrap();
+        assert_eq!(downloaded, vec![titles[1].title_id, titles[2].title_id]);
+        // A failed title doesn't stop the rest from being downloaded, but is still reported.
+        let mut downloaded: Vec<TitleId> = Vec::new();
+        let err = download_update_titles(&titles, dir.path(), |title_id, _| {
+            downloaded.push(title_id);
+            if title_id == titles[1].title_id {
+                bail!("failed");
+            }
+            Ok(())
+        }).unwrap_err();
+        assert_eq!(downloaded, vec![titles[1].title_id, titles[2].title_id]);
+        assert_eq!(err.to_string(), "1 of 3 titles could not be downloaded!");
     }
-    let out_path = if output.is_some() {
-        PathBuf::from(output.clone().unwrap())
-    } else if version.is_some() {
-        PathBuf::from(format!("{}.tmd.{}", tid, version.unwrap()))
-    } else {
-        PathBuf::from(format!("{}.tmd", tid))
-    };
-    let tid: [u8; 8] = hex::decode(tid)?.try_into().unwrap();
-    let tmd_data = nus::download_tmd(tid, *version, true).with_context(|| "TMD data could not be downloaded.")?;
-    fs::write(&out_path, tmd_data)?;
-    println!("Successfully downloaded TMD to \"{}\"!", out_path.display());
-    Ok(())
 }
@@ -0,0 +1,132 @@
+// title/ticket.rs from rustii (c) 2025 NinjaCheetah & Contributors
+// https://github.com/NinjaCheetah/rustii
+//
+// Code for Ticket-related commands in the rustii CLI.
+
+use std::{str, fs};
+use std::path::{Path, PathBuf};
+use anyhow::{bail, Context, Result};
+use clap::{Subcommand, Args};
+use rustii::title::{ticket, TitleId};
+
+#[derive(Subcommand)]
+#[command(arg_required_else_help = true)]
+pub enum Commands {
+    /// Edit the properties of a Ticket file
+    Edit {
+        /// The path to the Ticket to modify
+        input: String,
+        /// An optional output path; defaults to overwriting input Ticket file
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Don't fakesign the modified Ticket, leaving it with an invalid signature
+        #[arg(long)]
+        no_fakesign: bool,
+        /// Print the encrypted and decrypted Title Keys of the modified Ticket
+        #[arg(long)]
+        show_key: bool,
+        #[command(flatten)]
+        edits: TicketModifications
+    },
+}
+
+#[derive(Args)]
+#[clap(next_help_heading = "Possible Modifications")]
+#[group(multiple = true, required = true)]
+pub struct TicketModifications {
+    /// A new title version for this Ticket
+    #[arg(long)]
+    version: Option<u16>,
+    /// A new Title ID for this Ticket (formatted as 16 hex characters, e.g. 0001000148414445)
+    #[arg(long)]
+    tid: Option<String>,
+    /// A new common key index for this Ticket (0 = Common, 1 = Korean, 2 = vWii); the Title Key
+    /// is re-encrypted so that it doesn't change
+    #[arg(long)]
+    key_index: Option<u8>,
+    /// Permit access to every content, which unlocks DLC that the Ticket only partially authorizes
+    #[arg(long)]
+    permit_all: bool,
+    /// Remove all play time and launch count limits, such as those on demo Tickets
+    #[arg(long)]
+    clear_limits: bool,
+    /// Remove the console-specific data from a personalized Ticket
+    #[arg(long)]
+    unpersonalize: bool,
+    /// A new decrypted Title Key (in hex), required to unpersonalize a Ticket whose Title Key
+    /// is encrypted with a console-specific key
+    #[arg(long)]
+    title_key: Option<String>,
+}
+
+pub fn edit_ticket(input: &str, output: &Option<String>, no_fakesign: &bool, show_key: &bool, edits: &TicketModifications) -> Result<()> {
+    let in_path = Path::new(input);
+    if !in_path.exists() {
+        bail!("Source Ticket \"{}\" does not exist.", in_path.display());
+    }
+    let out_path = if output.is_some() {
+        PathBuf::from(output.clone().unwrap()).with_extension("tik")
+    } else {
+        in_path.to_path_buf()
+    };
+    // Validate the new Title Key up front, so that a bad key can't leave a half-edited Ticket behind.
+    let title_key = match &edits.title_key {
+        Some(title_key) => Some(ticket::parse_title_key(title_key)
+            .with_context(|| format!("The specified Title Key \"{}\" is not valid! The Title Key must be 32 hex characters long.", title_key))?),
+        None => None,
+    };
+    let mut ticket = ticket::Ticket::open(in_path).with_context(|| format!("The provided Ticket file \"{}\" could not be loaded.", in_path.display()))?;
+    let mut changes_summary: Vec<String> = Vec::new();
+    if let Some(new_version) = edits.version {
+        changes_summary.push(format!("Changed title version from {} to {}", ticket.title_version, new_version));
+        ticket.title_version = new_version;
+    }
+    // The Title Key is set before the Title ID and common key index, since changing those keeps the
+    // decrypted Title Key the same and re-encrypts it.
+    if let Some(title_key) = title_key {
+        ticket.set_dec_title_key(title_key);
+        changes_summary.push(format!("Changed decrypted Title Key to \"{}\"", hex::encode(title_key)));
+    }
+    if let Some(new_tid) = &edits.tid {
+        let new_tid: TitleId = new_tid.parse().with_context(|| "The specified Title ID is not valid!")?;
+        changes_summary.push(format!("Changed Title ID from {} to {}", ticket.title_id(), new_tid));
+        ticket.set_title_id(new_tid)?;
+    }
+    if let Some(new_index) = edits.key_index {
+        let old_index = ticket.common_key_index();
+        ticket.change_common_key(new_index, ticket.is_dev()).with_context(|| "The specified common key index is not valid! The common key index must be between 0 and 2.")?;
+        changes_summary.push(format!("Changed common key index from {} to {}", old_index, new_index));
+    }
+    if edits.permit_all {
+        changes_summary.push(format!("Permitted all contents (previously {} of 512 permitted)", ticket.permitted_contents().len()));
+        ticket.permit_all_contents();
+    }
+    if edits.clear_limits {
+        let active = ticket.limits().iter().filter(|(limit_type, _)| *limit_type != ticket::LimitType::None).count();
+        changes_summary.push(format!("Removed all title limits (previously {} active)", active));
+        ticket.clear_limits();
+    }
+    // This has to happen after the Title Key is set, since a Ticket with a console-specific Title
+    // Key can't be unpersonalized without it.
+    if edits.unpersonalize {
+        if !ticket.is_personalized() {
+            bail!("The provided Ticket is not personalized!");
+        }
+        let console_id = hex::encode_upper(ticket.console_id);
+        ticket.unpersonalize().with_context(|| "The Ticket could not be unpersonalized. Supply the decrypted Title Key with --title-key and try again.")?;
+        changes_summary.push(format!("Unpersonalized Ticket (previously issued to console {})", console_id));
+    }
+    if !*no_fakesign {
+        ticket.fakesign().with_context(|| "An unknown error occurred while fakesigning the modified Ticket.")?;
+    }
+    fs::write(&out_path, ticket.to_bytes()?).with_context(|| format!("Could not open output file \"{}\" for writing.", out_path.display()))?;
+    println!("Successfully edited Ticket \"{}\"!\nSummary of changes:", out_path.display());
+    for change in &changes_summary {
+        println!(" - {}", change);
+    }
+    if *show_key {
+        println!("Title Key (Encrypted): {}", hex::encode(ticket.title_key));
+        println!("Title Key (Decrypted): {}", hex::encode(ticket.dec_title_key()));
+    }
+    Ok(())
+}
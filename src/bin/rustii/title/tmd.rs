@@ -0,0 +1,100 @@
+// title/tmd.rs from rustii (c) 2025 NinjaCheetah & Contributors
+// https://github.com/NinjaCheetah/rustii
+//
+// Code for TMD-related commands in the rustii CLI.
+
+use std::{str, fs};
+use std::path::{Path, PathBuf};
+use anyhow::{bail, Context, Result};
+use clap::{Subcommand, Args};
+use rustii::title::{tmd, TitleId};
+
+#[derive(Subcommand)]
+#[command(arg_required_else_help = true)]
+pub enum Commands {
+    /// Edit the properties of a TMD file
+    Edit {
+        /// The path to the TMD to modify
+        input: String,
+        /// An optional output path; defaults to overwriting input TMD file
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Don't fakesign the modified TMD, leaving it with an invalid signature
+        #[arg(long)]
+        no_fakesign: bool,
+        #[command(flatten)]
+        edits: TmdModifications
+    },
+}
+
+#[derive(Args)]
+#[clap(next_help_heading = "Possible Modifications")]
+#[group(multiple = true, required = true)]
+pub struct TmdModifications {
+    /// A new title version for this TMD
+    #[arg(long)]
+    version: Option<u16>,
+    /// A new Title ID for this TMD (formatted as 16 hex characters, e.g. 0001000148414445)
+    #[arg(long)]
+    tid: Option<String>,
+    /// A new IOS version for this TMD (formatted as the decimal IOS version, e.g. 58, with a valid
+    /// range of 3-255)
+    #[arg(long)]
+    ios: Option<u8>,
+    /// A new region for this TMD (valid options are "JPN", "USA", "EUR", "None", "KOR")
+    #[arg(long)]
+    region: Option<String>,
+    /// A new boot index for this TMD, which must match the index of an existing content
+    #[arg(long)]
+    boot_index: Option<u16>,
+}
+
+pub fn edit_tmd(input: &str, output: &Option<String>, no_fakesign: &bool, edits: &TmdModifications) -> Result<()> {
+    let in_path = Path::new(input);
+    if !in_path.exists() {
+        bail!("Source TMD \"{}\" does not exist.", in_path.display());
+    }
+    let out_path = if output.is_some() {
+        PathBuf::from(output.clone().unwrap()).with_extension("tmd")
+    } else {
+        in_path.to_path_buf()
+    };
+    let mut tmd = tmd::TMD::open(in_path).with_context(|| format!("The provided TMD file \"{}\" could not be loaded.", in_path.display()))?;
+    let mut changes_summary: Vec<String> = Vec::new();
+    if let Some(new_version) = edits.version {
+        changes_summary.push(format!("Changed title version from {} to {}", tmd.title_version, new_version));
+        tmd.title_version = new_version;
+    }
+    if let Some(new_tid) = &edits.tid {
+        let new_tid: TitleId = new_tid.parse().with_context(|| "The specified Title ID is not valid!")?;
+        changes_summary.push(format!("Changed Title ID from {} to {}", tmd.title_id(), new_tid));
+        tmd.set_title_id(new_tid)?;
+    }
+    if let Some(new_ios) = edits.ios {
+        if new_ios < 3 {
+            bail!("The specified IOS version is not valid! The new IOS version must be between 3 and 255.")
+        }
+        let new_ios_tid = TitleId::from_parts([0, 0, 0, 1], (new_ios as u32).to_be_bytes());
+        changes_summary.push(format!("Changed required IOS from IOS{} to IOS{}", tmd.ios_tid().last().unwrap(), new_ios));
+        tmd.set_ios_tid(new_ios_tid.to_bytes())?;
+    }
+    if let Some(new_region) = &edits.region {
+        let old_region = tmd.region().to_owned();
+        tmd.set_region(new_region).with_context(|| format!("The specified region \"{}\" is invalid! Try --help to see valid regions.", new_region))?;
+        changes_summary.push(format!("Changed region from \"{}\" to \"{}\"", old_region, tmd.region()));
+    }
+    if let Some(new_boot_index) = edits.boot_index {
+        let old_boot_index = tmd.boot_index;
+        tmd.set_boot_index(new_boot_index).with_context(|| format!("The specified boot index {} is invalid! No content in this TMD has that index.", new_boot_index))?;
+        changes_summary.push(format!("Changed boot index from {} to {}", old_boot_index, new_boot_index));
+    }
+    if !*no_fakesign {
+        tmd.fakesign().with_context(|| "An unknown error occurred while fakesigning the modified TMD.")?;
+    }
+    fs::write(&out_path, tmd.to_bytes()?).with_context(|| format!("Could not open output file \"{}\" for writing.", out_path.display()))?;
+    println!("Successfully edited TMD \"{}\"!\nSummary of changes:", out_path.display());
+    for change in &changes_summary {
+        println!(" - {}", change);
+    }
+    Ok(())
+}
@@ -3,17 +3,19 @@
 //
 // Code for WAD-related commands in the rustii CLI.
 
-use std::{str, fs, fmt};
+use std::{env, str, fs};
+use std::fs::File;
+use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
-use std::rc::Rc;
 use anyhow::{bail, Context, Result};
 use clap::{Subcommand, Args};
-use glob::glob;
-use hex::FromHex;
-use rand::prelude::*;
 use regex::RegexBuilder;
-use rustii::title::{cert, crypto, tmd, ticket, content, wad};
+use rustii::title::{cert, tmd, content, lint, manifest, meta, metadata, ticket, wad, TitleId};
+#[cfg(feature = "nus")]
+use rustii::title::nus;
 use rustii::title;
+use crate::info::wad_signing_status;
+use crate::output::{dry_run_suffix, resolve_output, write_output, OutputOptions};
 
 #[derive(Subcommand)]
 #[command(arg_required_else_help = true)]
@@ -27,7 +29,10 @@ pub enum Commands {
         /// An optional output path; defaults to overwriting input WAD file
         #[arg(short, long)]
         output: Option<String>,
-        /// An optional Content ID for the new content; defaults to being randomly assigned
+        #[command(flatten)]
+        output_options: OutputOptions,
+        /// An optional Content ID for the new content (0x-prefixed or 8-digit hex, or decimal); defaults
+        /// to one higher than the highest Content ID in use
         #[arg(short, long)]
         cid: Option<String>,
         /// An optional type for the new content, can be "Normal", "Shared", or "DLC"; defaults to
@@ -35,6 +40,18 @@ pub enum Commands {
         #[arg(short, long)]
         r#type: Option<String>,
     },
+    /// Apply an edited metadata document created by dump-meta to a WAD file
+    ApplyMeta {
+        /// The path to the WAD file to modify
+        input: String,
+        /// The path to the JSON metadata document to apply
+        meta: String,
+        /// An optional output path; defaults to overwriting input WAD file
+        #[arg(short, long)]
+        output: Option<String>,
+        #[command(flatten)]
+        output_options: OutputOptions,
+    },
     /// Re-encrypt a WAD file with a different key
     Convert {
         /// The path to the WAD to convert
@@ -43,7 +60,38 @@ pub enum Commands {
         #[arg(short, long)]
         output: Option<String>,
         #[command(flatten)]
+        output_options: OutputOptions,
+        #[command(flatten)]
         target: ConvertTargets,
+        /// A decrypted Title Key (32 hex characters) to use instead of the WAD's existing one; the
+        /// contents are re-encrypted to match
+        #[arg(long)]
+        title_key: Option<String>,
+        /// Print the encrypted and decrypted Title Keys used by the converted WAD
+        #[arg(long)]
+        show_key: bool,
+        /// Don't fakesign the converted WAD, leaving the original signatures in place even though
+        /// they'll no longer be valid
+        #[arg(long, conflicts_with = "title_key")]
+        no_fakesign: bool,
+    },
+    /// Compare two WAD files and report their differences
+    Diff {
+        /// The path to the first WAD to compare
+        first: String,
+        /// The path to the second WAD to compare
+        second: String,
+        /// Also compare the decrypted data of contents that exist in both WADs
+        #[arg(long)]
+        contents: bool,
+    },
+    /// Dump the editable metadata of a WAD file to a JSON document
+    DumpMeta {
+        /// The path to the WAD file to dump metadata from
+        input: String,
+        /// An optional path to write the metadata to; defaults to printing it
+        #[arg(short, long)]
+        output: Option<String>,
     },
     /// Edit the properties of a WAD file
     Edit {
@@ -53,14 +101,55 @@ pub enum Commands {
         #[arg(short, long)]
         output: Option<String>,
         #[command(flatten)]
+        output_options: OutputOptions,
+        #[command(flatten)]
         edits: WadModifications
     },
+    /// Extract a single content from a WAD file
+    Extract {
+        /// The path to the WAD to extract content from
+        input: String,
+        /// The path to write the extracted content to
+        output: String,
+        /// Extract the content without decrypting it
+        #[arg(long)]
+        encrypted: bool,
+        #[command(flatten)]
+        identifier: ContentIdentifier,
+        /// Extract the title's boot content, as set in its TMD
+        #[arg(long, group = "ContentIdentifier")]
+        boot: bool,
+    },
+    /// Check a WAD file for structural problems that would prevent it from installing
+    Lint {
+        /// The path to the WAD to check
+        input: String,
+        /// Fail if any warnings are found, not just errors
+        #[arg(long)]
+        warnings_as_errors: bool,
+    },
     /// Pack a directory into a WAD file
     Pack {
         /// The directory to pack into a WAD
         input: String,
         /// The name of the packed WAD file
-        output: String
+        output: String,
+        /// A certificate chain to use instead of the one in the directory; if neither is present, a
+        /// cached chain is used (from $RUSTII_CERT_CACHE if set), or one is downloaded from the NUS
+        #[arg(long)]
+        cert: Option<String>,
+        /// Don't fakesign the packed WAD, preserving any existing signatures
+        #[arg(long)]
+        no_fakesign: bool,
+        /// Allow content files to be missing, like the unpurchased contents of DLC
+        #[arg(long)]
+        allow_missing: bool,
+        /// Treat every content file as encrypted, rather than detecting whether each one is
+        /// encrypted or decrypted
+        #[arg(long)]
+        encrypted: bool,
+        #[command(flatten)]
+        reproducible: ReproducibleOptions,
     },
     /// Remove content from a WAD file
     Remove {
@@ -70,7 +159,12 @@ pub enum Commands {
         #[arg(short, long)]
         output: Option<String>,
         #[command(flatten)]
+        output_options: OutputOptions,
+        #[command(flatten)]
         identifier: ContentIdentifier,
+        /// Remove all contents of a type, which can be "Normal", "Shared", or "DLC"
+        #[arg(short, long, group = "ContentIdentifier")]
+        r#type: Option<String>,
     },
     /// Replace existing content in a WAD file with new data
     Set {
@@ -81,6 +175,8 @@ pub enum Commands {
         /// An optional output path; defaults to overwriting input WAD file
         #[arg(short, long)]
         output: Option<String>,
+        #[command(flatten)]
+        output_options: OutputOptions,
         /// An optional new type for the content, can be "Normal", "Shared", or "DLC"
         #[arg(short, long)]
         r#type: Option<String>,
@@ -92,7 +188,21 @@ pub enum Commands {
         /// The path to the WAD to unpack
         input: String,
         /// The directory to extract the WAD to
-        output: String
+        output: String,
+        /// Write contents without decrypting them, skipping hash checks
+        #[arg(long)]
+        encrypted: bool,
+        /// How to name content files, can be "index" or "cid"; defaults to "index"
+        #[arg(long)]
+        name_by: Option<String>,
+    },
+    /// Verify the contents and structure of a WAD file
+    Verify {
+        /// The path to the WAD to verify
+        input: String,
+        /// Fail if any structural anomalies are found in the WAD, even if it can be parsed
+        #[arg(long)]
+        strict: bool,
     },
 }
 
@@ -109,6 +219,23 @@ pub struct ConvertTargets {
     /// Use the vWii key, allowing this WAD to theoretically be installed from Wii U mode if a Wii U mode WAD installer is created
     #[arg(long)]
     vwii: bool,
+    /// Use the Korean common key, allowing this WAD to be installed on Korean consoles
+    #[arg(long)]
+    korean: bool,
+}
+
+#[derive(Args)]
+#[clap(next_help_heading = "Reproducible Packing")]
+pub struct ReproducibleOptions {
+    /// Produce a WAD that only depends on the files being packed, so that packing the same
+    /// directory again creates an identical file; a footer's build timestamp is dropped unless
+    /// --timestamp is provided
+    #[arg(long)]
+    reproducible: bool,
+    /// The build timestamp to record in the footer of a reproducible WAD, formatted as
+    /// "YYYY/MM/DD HH:MM:SS"
+    #[arg(long, requires = "reproducible")]
+    timestamp: Option<String>,
 }
 
 #[derive(Args)]
@@ -118,7 +245,7 @@ pub struct ContentIdentifier {
     /// The index of the target content
     #[arg(short, long)]
     index: Option<usize>,
-    /// The Content ID of the target content
+    /// The Content ID of the target content (0x-prefixed or 8-digit hex, or decimal)
     #[arg(short, long)]
     cid: Option<String>,
 }
@@ -131,32 +258,26 @@ pub struct WadModifications {
     /// range of 3-255)
     #[arg(long)]
     ios: Option<u8>,
-    /// A new Title ID for this WAD (formatted as 4 ASCII characters, e.g. HADE)
+    /// A new Title ID for this WAD (formatted as 4 ASCII characters, e.g. HADE, or as a full Title
+    /// ID, e.g. 00010001-HADE or 0001000148414445)
     #[arg(long)]
     tid: Option<String>,
     /// A new type for this WAD (valid options are "System", "Channel", "SystemChannel",
     /// "GameChannel", "DLC", "HiddenChannel")
     #[arg(long)]
     r#type: Option<String>,
+    /// Enable or disable AHB access for this WAD (true or false)
+    #[arg(long)]
+    ahb_access: Option<bool>,
+    /// Enable or disable DVD video access for this WAD (true or false)
+    #[arg(long)]
+    dvd_video: Option<bool>,
+    /// Renumber the content indices to be contiguous, closing any gaps left by removed content
+    #[arg(long)]
+    reindex: bool,
 }
 
-enum Target {
-    Retail,
-    Dev,
-    Vwii,
-}
-
-impl fmt::Display for Target {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Target::Retail => write!(f, "retail"),
-            Target::Dev => write!(f, "development"),
-            Target::Vwii => write!(f, "vWii"),
-        }
-    }
-}
-
-pub fn add_wad(input: &str, content: &str, output: &Option<String>, cid: &Option<String>, ctype: &Option<String>) -> Result<()> {
+pub fn add_wad(input: &str, content: &str, output: &Option<String>, output_options: &OutputOptions, cid: &Option<String>, ctype: &Option<String>) -> Result<()> {
     let in_path = Path::new(input);
     if !in_path.exists() {
         bail!("Source WAD \"{}\" could not be found.", in_path.display());
@@ -165,13 +286,9 @@ pub fn add_wad(input: &str, content: &str, output: &Option<String>, cid: &Option
     if !content_path.exists() {
         bail!("New content \"{}\" could not be found.", content_path.display());
     }
-    let out_path = if output.is_some() {
-        PathBuf::from(output.clone().unwrap()).with_extension("wad")
-    } else {
-        in_path.to_path_buf()
-    };
+    let out_path = resolve_output(output.as_ref().map(|output| PathBuf::from(output).with_extension("wad")), in_path.to_path_buf(), output_options, Some(in_path))?;
     // Load the WAD and parse the target type and Content ID.
-    let mut title = title::Title::from_bytes(&fs::read(in_path)?).with_context(|| "The provided WAD file could not be parsed, and is likely invalid.")?;
+    let mut title = title::Title::open(in_path).with_context(|| format!("The provided WAD file \"{}\" could not be loaded.", in_path.display()))?;
     let new_content = fs::read(content_path)?;
     let target_type = if ctype.is_some() {
         match ctype.clone().unwrap().to_ascii_lowercase().as_str() {
@@ -184,121 +301,185 @@ pub fn add_wad(input: &str, content: &str, output: &Option<String>, cid: &Option
         println!("Using default type \"Normal\" because no content type was specified.");
         tmd::ContentType::Normal
     };
-    let target_cid = if cid.is_some() {
-        let cid = u32::from_str_radix(cid.clone().unwrap().as_str(), 16).with_context(|| "The specified Content ID is invalid!")?;
-        if title.content.content_records.borrow().iter().any(|record| record.content_id == cid) {
+    let target_cid = if let Some(cid) = cid {
+        let cid = super::parse_cid(cid)?;
+        if title.content.content_records.read().iter().any(|record| record.content_id == cid) {
             bail!("The specified Content ID \"{:08X}\" is already being used in this WAD!", cid);
         }
         cid
     } else {
-        // Generate a random CID if one wasn't specified, and ensure that it isn't already in use.
-        let mut rng = rand::rng();
-        let mut cid: u32;
-        loop {
-            cid = rng.random_range(0..=0xFF);
-            if !title.content.content_records.borrow().iter().any(|record| record.content_id == cid) {
-                break;
-            }
-        }
-        println!("Generated new random Content ID \"{:08X}\" ({}) because no Content ID was specified.", cid, cid);
+        let cid = title.content.next_available_cid();
+        println!("Using next available Content ID \"{:08X}\" ({}) because no Content ID was specified.", cid, cid);
         cid
     };
-    title.add_content(&new_content, target_cid, target_type.clone()).with_context(|| "An unknown error occurred while setting the new content.")?;
-    title.fakesign().with_context(|| "An unknown error occurred while fakesigning the modified WAD.")?;
-    fs::write(&out_path, title.to_wad()?.to_bytes()?).with_context(|| "Could not open output file for writing.")?;
-    println!("Successfully added new content with Content ID \"{:08X}\" ({}) and type \"{}\" to WAD file \"{}\"!", target_cid, target_cid, target_type, out_path.display());
+    title.add_content(&new_content, Some(target_cid), target_type.clone()).with_context(|| "An unknown error occurred while setting the new content.")?;
+    let wad_data = title.fakesign().with_context(|| "An unknown error occurred while fakesigning the modified WAD.")?.to_wad_bytes()?;
+    write_output(&out_path, &wad_data, output_options)?;
+    println!("Successfully added new content with Content ID \"{:08X}\" ({}) and type \"{}\" to WAD file \"{}\"!{}",
+        target_cid, target_cid, target_type, out_path.display(), dry_run_suffix(output_options.dry_run));
     Ok(())
 }
 
-pub fn convert_wad(input: &str, target: &ConvertTargets, output: &Option<String>) -> Result<()> {
+pub fn convert_wad(input: &str, target: &ConvertTargets, output: &Option<String>, output_options: &OutputOptions, title_key: &Option<String>, show_key: &bool, no_fakesign: &bool) -> Result<()> {
     let in_path = Path::new(input);
     if !in_path.exists() {
         bail!("Source WAD \"{}\" could not be found.", in_path.display());
     }
+    let title_key = match title_key {
+        Some(title_key) => Some(ticket::parse_title_key(title_key)
+            .with_context(|| format!("The specified Title Key \"{}\" is not valid! The Title Key must be 32 hex characters long.", title_key))?),
+        None => None,
+    };
     // Parse the target passed to identify the encryption target.
     let target = if target.dev {
-        Target::Dev
+        title::EncryptionTarget::Dev
     } else if target.vwii {
-        Target::Vwii
+        title::EncryptionTarget::Vwii
+    } else if target.korean {
+        title::EncryptionTarget::Korean
     } else {
-        Target::Retail
+        title::EncryptionTarget::Retail
     };
     // Get the output name now that we know the target, if one wasn't passed.
-    let out_path = if output.is_some() {
-        PathBuf::from(output.clone().unwrap()).with_extension("wad")
-    } else {
-        match target {
-            Target::Retail => PathBuf::from(format!("{}_retail.wad", in_path.file_stem().unwrap().to_str().unwrap())),
-            Target::Dev => PathBuf::from(format!("{}_dev.wad", in_path.file_stem().unwrap().to_str().unwrap())),
-            Target::Vwii => PathBuf::from(format!("{}_vWii.wad", in_path.file_stem().unwrap().to_str().unwrap())),
-        }
+    let suffix = match target {
+        title::EncryptionTarget::Retail => "retail",
+        title::EncryptionTarget::Dev => "dev",
+        title::EncryptionTarget::Vwii => "vWii",
+        title::EncryptionTarget::Korean => "korean",
     };
-    let mut title = title::Title::from_bytes(&fs::read(in_path)?).with_context(|| "The provided WAD file could not be parsed, and is likely invalid.")?;
-    // Bail if the WAD is already using the selected encryption.
-    if matches!(target, Target::Dev) && title.ticket.is_dev() {
-        bail!("This is already a development WAD!");
-    } else if matches!(target, Target::Retail) && !title.ticket.is_dev() && !title.tmd.is_vwii() {
-        bail!("This is already a retail WAD!");
-    } else if matches!(target, Target::Vwii) && !title.ticket.is_dev() && title.tmd.is_vwii() {
-        bail!("This is already a vWii WAD!");
-    }
-    // Save the current encryption to display at the end.
-    let source = if title.ticket.is_dev() {
-        "development"
-    } else if title.tmd.is_vwii() {
-        "vWii"
-    } else {
-        "retail"
-    };
-    let title_key = title.ticket.dec_title_key();
-    let title_key_new: [u8; 16];
-    match target {
-        Target::Dev => {
-            title.tmd.set_signature_issuer(String::from("Root-CA00000002-CP00000007"))?;
-            title.ticket.set_signature_issuer(String::from("Root-CA00000002-XS00000006"))?;
-            title_key_new = crypto::encrypt_title_key(title_key, 0, title.ticket.title_id(), true);
-            title.ticket.common_key_index = 0;
-            title.tmd.is_vwii = 0;
-        },
-        Target::Retail => {
-            title.tmd.set_signature_issuer(String::from("Root-CA00000001-CP00000004"))?;
-            title.ticket.set_signature_issuer(String::from("Root-CA00000001-XS00000003"))?;
-            title_key_new = crypto::encrypt_title_key(title_key, 0, title.ticket.title_id(), false);
-            title.ticket.common_key_index = 0;
-            title.tmd.is_vwii = 0;
+    let out_path = resolve_output(
+        output.as_ref().map(|output| PathBuf::from(output).with_extension("wad")),
+        PathBuf::from(format!("{}_{}.wad", in_path.file_stem().unwrap().to_str().unwrap(), suffix)),
+        output_options,
+        Some(in_path),
+    )?;
+    let mut title = title::Title::open(in_path).with_context(|| format!("The provided WAD file \"{}\" could not be loaded.", in_path.display()))?;
+    // Save the current encryption to display at the end, and whether the WAD was legitimately
+    // signed, since fakesigning it will replace those signatures.
+    let source = title.encryption();
+    let was_legit = title.verify().unwrap_or(false);
+    // The new Title Key has to be set before converting, since converting re-encrypts whatever the
+    // current decrypted Title Key is.
+    if let Some(title_key) = title_key {
+        title.set_title_key(title_key).with_context(|| "The WAD's contents could not be re-encrypted with the specified Title Key.")?;
+    }
+    match title.convert_encryption(target, !*no_fakesign) {
+        // Only changing the Title Key is a valid use of this command, so don't fail in that case.
+        Err(title::TitleError::AlreadyEncrypted(_)) if title_key.is_some() => (),
+        Err(title::TitleError::AlreadyEncrypted(target)) => bail!("This is already a {} WAD!", target),
+        result => {
+            result.with_context(|| "An unknown error occurred while converting the WAD.")?;
         },
-        Target::Vwii => {
-            title.tmd.set_signature_issuer(String::from("Root-CA00000001-CP00000004"))?;
-            title.ticket.set_signature_issuer(String::from("Root-CA00000001-XS00000003"))?;
-            title_key_new = crypto::encrypt_title_key(title_key, 2, title.ticket.title_id(), false);
-            title.ticket.common_key_index = 2;
-            title.tmd.is_vwii = 1;
+    }
+    write_output(&out_path, &title.to_wad_bytes()?, output_options)?;
+    let suffix = dry_run_suffix(output_options.dry_run);
+    if source == target {
+        println!("Successfully changed the Title Key of {} WAD \"{}\"!{}", target, out_path.file_name().unwrap().to_str().unwrap(), suffix);
+    } else {
+        println!("Successfully converted {} WAD to {} WAD \"{}\"!{}", source, target, out_path.file_name().unwrap().to_str().unwrap(), suffix);
+    }
+    if *no_fakesign {
+        println!("Signing Status: {}", wad_signing_status(&title)?);
+    } else if was_legit {
+        println!("Warning: The input WAD was legitimately signed, but the converted WAD has been fakesigned. Use --no-fakesign to keep the original signatures.");
+    }
+    if *show_key {
+        println!("Title Key (Encrypted): {}", hex::encode(title.ticket.title_key));
+        println!("Title Key (Decrypted): {}", hex::encode(title.ticket.dec_title_key()));
+    }
+    Ok(())
+}
+
+pub fn diff_wad(first: &str, second: &str, contents: &bool) -> Result<()> {
+    let first_path = Path::new(first);
+    let second_path = Path::new(second);
+    for path in [first_path, second_path] {
+        if !path.exists() {
+            bail!("Source WAD \"{}\" could not be found.", path.display());
         }
     }
-    title.ticket.title_key = title_key_new;
-    title.fakesign()?;
-    fs::write(&out_path, title.to_wad()?.to_bytes()?)?;
-    println!("Successfully converted {} WAD to {} WAD \"{}\"!", source, target, out_path.file_name().unwrap().to_str().unwrap());
+    let first_title = title::Title::open(first_path).with_context(|| format!("The provided WAD file \"{}\" could not be loaded.", first_path.display()))?;
+    let second_title = title::Title::open(second_path).with_context(|| format!("The provided WAD file \"{}\" could not be loaded.", second_path.display()))?;
+    let title_diff = first_title.diff(&second_title);
+    let mut num_differences = title_diff.header.len() + title_diff.tmd.len() + title_diff.ticket.len() + title_diff.contents.len();
+    for (section, diffs) in [("Title", &title_diff.header), ("TMD", &title_diff.tmd), ("Ticket", &title_diff.ticket)] {
+        if !diffs.is_empty() {
+            println!("{} Differences", section);
+            for field_diff in diffs {
+                println!("  {}", field_diff);
+            }
+        }
+        if section == "TMD" {
+            crate::diff::print_version_note(first_title.tmd.title_id(), first_title.tmd.title_version, second_title.tmd.title_version, first_title.tmd.is_vwii());
+        }
+    }
+    if !title_diff.contents.is_empty() {
+        println!("Content Record Differences");
+        for content_diff in &title_diff.contents {
+            println!("  {}", content_diff);
+        }
+    }
+    if *contents {
+        let mut content_differences: Vec<String> = Vec::new();
+        for record in first_title.content.content_records.read().iter() {
+            let cid = record.content_id;
+            if !second_title.content.content_records.read().iter().any(|other| other.content_id == cid) {
+                continue;
+            }
+            let first_content = first_title.get_content_by_cid(cid).with_context(|| format!("Content {:08X} in \"{}\" could not be decrypted.", cid, first_path.display()))?;
+            let second_content = second_title.get_content_by_cid(cid).with_context(|| format!("Content {:08X} in \"{}\" could not be decrypted.", cid, second_path.display()))?;
+            let ranges = title::diff::diff_bytes(&first_content, &second_content);
+            if !ranges.is_empty() {
+                let ranges: Vec<String> = ranges.iter().map(|range| format!("0x{:X}-0x{:X}", range.start, range.end - 1)).collect();
+                content_differences.push(format!("  Content {:08X}: differs at {}", cid, ranges.join(", ")));
+            }
+        }
+        if !content_differences.is_empty() {
+            println!("Content Data Differences");
+            for content_difference in &content_differences {
+                println!("{}", content_difference);
+            }
+        }
+        num_differences += content_differences.len();
+    }
+    if num_differences > 0 {
+        bail!("Found {} differences between WAD files \"{}\" and \"{}\".", num_differences, first_path.display(), second_path.display());
+    }
+    println!("WAD files \"{}\" and \"{}\" are identical!", first_path.display(), second_path.display());
     Ok(())
 }
 
-pub fn edit_wad(input: &str, output: &Option<String>, edits: &WadModifications) -> Result<()> {
+pub fn edit_wad(input: &str, output: &Option<String>, output_options: &OutputOptions, edits: &WadModifications) -> Result<()> {
     let in_path = Path::new(input);
     if !in_path.exists() {
         bail!("Source directory \"{}\" does not exist.", in_path.display());
     }
-    let out_path = if output.is_some() {
-        PathBuf::from(output.clone().unwrap()).with_extension("wad")
-    } else {
-        in_path.to_path_buf()
-    };
-    let mut title = title::Title::from_bytes(&fs::read(in_path)?).with_context(|| "The provided WAD file could not be parsed, and is likely invalid.")?;
-    // Parse possible edits and perform each one provided. Unlike WiiPy, I don't need a state bool
+    let out_path = resolve_output(output.as_ref().map(|output| PathBuf::from(output).with_extension("wad")), in_path.to_path_buf(), output_options, Some(in_path))?;
+    let mut title = title::Title::open(in_path).with_context(|| format!("The provided WAD file \"{}\" could not be loaded.", in_path.display()))?;
+    // Parse and validate every possible edit before any of them are made, since the summary of
+    // each change describes the value it's replacing. Unlike WiiPy, I don't need a state bool
     // here! Wow!
     let mut changes_summary: Vec<String> = Vec::new();
+    let mut new_title_id: Option<TitleId> = None;
     // These are joined, because that way if both are selected we only need to set the TID (and by
     // extension, re-encrypt the Title Key) a single time.
     if edits.tid.is_some() || edits.r#type.is_some() {
+        let current_tid = title.tmd.title_id();
+        // A full Title ID brings its own high half with it, while 4 ASCII characters only replace
+        // the low half. Either way, --type takes priority for the high half.
+        let new_tid: Option<(TitleId, bool)> = if let Some(tid) = &edits.tid {
+            let tid = tid.trim().to_ascii_uppercase();
+            let parsed: TitleId = tid.parse().with_context(|| "The specified Title ID is not valid!")?;
+            if let Some(ascii_low) = parsed.ascii_low() {
+                let re = RegexBuilder::new(r"^[a-z0-9!@#$%^&*]{4}$").case_insensitive(true).build()?;
+                if !re.is_match(&ascii_low) {
+                    bail!("The specified Title ID is not valid! The new Title ID must be 4 characters and include only letters, numbers, and the special characters \"!@#$%&*\".");
+                }
+            }
+            Some((parsed, tid.len() != 4))
+        } else {
+            None
+        };
         let tid_high = if edits.r#type.is_some() {
             let new_type = match edits.r#type.clone().unwrap().to_ascii_lowercase().as_str() {
                 "system" => tmd::TitleType::System,
@@ -310,95 +491,247 @@ pub fn edit_wad(input: &str, output: &Option<String>, edits: &WadModifications)
                 _ => bail!("The specified title type \"{}\" is invalid! Try --help to see valid types.", edits.r#type.clone().unwrap()),
             };
             changes_summary.push(format!("Changed title type from \"{}\" to \"{}\"", title.tmd.title_type()?, new_type));
-            Vec::from_hex(format!("{:08X}", new_type as u32))?
+            (new_type as u32).to_be_bytes()
+        } else if let Some((new_tid, true)) = new_tid {
+            new_tid.high()
         } else {
-            title.tmd.title_id()[0..4].to_vec()
+            current_tid.high()
         };
-        let tid_low = if edits.tid.is_some() {
-            let re = RegexBuilder::new(r"^[a-z0-9!@#$%^&*]{4}$").case_insensitive(true).build()?;
-            let new_tid_low = edits.tid.clone().unwrap().to_ascii_uppercase();
-            if !re.is_match(&new_tid_low) {
-                bail!("The specified Title ID is not valid! The new Title ID must be 4 characters and include only letters, numbers, and the special characters \"!@#$%&*\".");
-            }
-            changes_summary.push(format!("Changed Title ID from \"{}\" to \"{}\"", hex::encode(&title.tmd.title_id()[4..8]).to_ascii_uppercase(), hex::encode(&new_tid_low).to_ascii_uppercase()));
-            Vec::from_hex(hex::encode(new_tid_low))?
+        let tid_low = if let Some((new_tid, _)) = new_tid {
+            new_tid.low()
         } else {
-            title.tmd.title_id()[4..8].to_vec()
+            current_tid.low()
         };
-        let new_tid: Vec<u8> = tid_high.iter().chain(&tid_low).copied().collect();
-        title.set_title_id(new_tid.try_into().unwrap())?;
+        let final_tid = TitleId::from_parts(tid_high, tid_low);
+        if new_tid.is_some() && final_tid != current_tid {
+            changes_summary.push(format!("Changed Title ID from {} to {}", current_tid, final_tid));
+        }
+        new_title_id = Some(final_tid);
     }
-    if edits.ios.is_some() {
-        let new_ios = edits.ios.unwrap();
+    if let Some(new_ios) = edits.ios {
         if new_ios < 3 {
             bail!("The specified IOS version is not valid! The new IOS version must be between 3 and 255.")
         }
-        let new_ios_tid = <[u8; 8]>::from_hex(format!("00000001{:08X}", new_ios))?;
         changes_summary.push(format!("Changed required IOS from IOS{} to IOS{}", title.tmd.ios_tid().last().unwrap(), new_ios));
-        title.tmd.set_ios_tid(new_ios_tid)?;
     }
-    title.fakesign()?;
-    fs::write(&out_path, title.to_wad()?.to_bytes()?).with_context(|| format!("Could not open output file \"{}\" for writing.", out_path.display()))?;
-    println!("Successfully edited WAD file \"{}\"!\nSummary of changes:", out_path.display());
+    let mut access_rights: Vec<(tmd::AccessRight, bool)> = Vec::new();
+    for (right, enabled) in [(tmd::AccessRight::AHB, edits.ahb_access), (tmd::AccessRight::DVDVideo, edits.dvd_video)] {
+        if let Some(enabled) = enabled {
+            changes_summary.push(format!("Changed {} from \"{}\" to \"{}\"", right, title.tmd.check_access_right(right), enabled));
+            access_rights.push((right, enabled));
+        }
+    }
+    // Every edit is optional, so they can't be made as a single chain, and each one is only made if
+    // it was requested.
+    if let Some(new_title_id) = new_title_id {
+        title.set_title_id(new_title_id)?;
+    }
+    if let Some(new_ios) = edits.ios {
+        title.set_required_ios(new_ios as u32)?;
+    }
+    for (right, enabled) in access_rights {
+        title.set_access_right(right, enabled)?;
+    }
+    if edits.reindex {
+        let moved = title.content.content_records.read().iter().enumerate()
+            .filter(|(position, record)| record.index != *position as u16)
+            .count();
+        title.reindex_contents().with_context(|| "The contents of the provided WAD could not be reindexed.")?;
+        if moved > 0 {
+            changes_summary.push(format!("Reindexed {} content(s) so that content indices are contiguous", moved));
+        } else {
+            changes_summary.push(String::from("Content indices were already contiguous, so no contents were reindexed"));
+        }
+    }
+    write_output(&out_path, &title.fakesign()?.to_wad_bytes()?, output_options)?;
+    println!("Successfully edited WAD file \"{}\"!{}\nSummary of changes:", out_path.display(), dry_run_suffix(output_options.dry_run));
     for change in &changes_summary {
         println!(" - {}", change);
     }
     Ok(())
 }
 
-pub fn pack_wad(input: &str, output: &str) -> Result<()> {
+pub fn extract_wad(input: &str, output: &str, identifier: &ContentIdentifier, boot: &bool, encrypted: &bool) -> Result<()> {
+    let in_path = Path::new(input);
+    if !in_path.exists() {
+        bail!("Source WAD \"{}\" could not be found.", in_path.display());
+    }
+    let title = title::Title::open(in_path).with_context(|| format!("The provided WAD file \"{}\" could not be loaded.", in_path.display()))?;
+    // Resolve the identifier passed into the position of the target content, since that's what
+    // both the encrypted and decrypted getters use.
+    let (index, description) = if *boot {
+        let index = title.get_boot_content_index().with_context(|| "The boot content of the provided WAD could not be found!")?;
+        (index, format!("boot content (index {})", title.tmd.boot_index))
+    } else if let Some(index) = identifier.index {
+        let num_contents = title.content.len();
+        if index >= num_contents {
+            bail!("The specified index {} does not exist in this WAD! The maximum index is {}.", index, num_contents.saturating_sub(1));
+        }
+        (index, format!("content at index {}", index))
+    } else if let Some(cid_str) = &identifier.cid {
+        let cid = super::parse_cid(cid_str)?;
+        let index = match title.content.content_records.read().iter().position(|record| record.content_id == cid) {
+            Some(index) => index,
+            None => bail!("The specified Content ID \"{}\" ({}) does not exist in this WAD!", cid_str, cid),
+        };
+        (index, format!("content with Content ID \"{}\" ({})", cid_str, cid))
+    } else {
+        bail!("No content to extract was specified! Try --help to see how to select content.");
+    };
+    let content = if *encrypted {
+        title.content.get_enc_content_by_index(index).with_context(|| "An unknown error occurred while reading the content from the WAD.")?
+    } else {
+        title.get_content_by_index(index).with_context(|| "The content could not be decrypted, and may be corrupted.")?
+    };
+    let out_path = Path::new(output);
+    fs::write(out_path, content).with_context(|| format!("Could not open output file \"{}\" for writing.", out_path.display()))?;
+    println!("Successfully extracted {} from WAD file \"{}\" to \"{}\"!", description, in_path.display(), out_path.display());
+    Ok(())
+}
+
+// Gets the path that a downloaded certificate chain should be cached at, which can be set with the
+// RUSTII_CERT_CACHE environment variable. Defaults to the user's cache directory.
+pub fn lint_wad(input: &str, warnings_as_errors: &bool) -> Result<()> {
+    let in_path = Path::new(input);
+    if !in_path.exists() {
+        bail!("Source WAD \"{}\" could not be found.", in_path.display());
+    }
+    let title = title::Title::open(in_path).with_context(|| format!("The provided WAD file \"{}\" could not be loaded.", in_path.display()))?;
+    let warnings = title.lint();
+    for warning in &warnings {
+        println!(" - {}: {}", warning.severity(), warning);
+    }
+    let num_errors = warnings.iter().filter(|warning| warning.severity() == lint::LintSeverity::Error).count();
+    if num_errors > 0 || (*warnings_as_errors && !warnings.is_empty()) {
+        bail!("Found {} error(s) and {} warning(s) in WAD file \"{}\".", num_errors, warnings.len() - num_errors, in_path.display());
+    }
+    if warnings.is_empty() {
+        println!("No problems found in WAD file \"{}\"!", in_path.display());
+    } else {
+        println!("Found {} warning(s) in WAD file \"{}\", but no errors.", warnings.len(), in_path.display());
+    }
+    Ok(())
+}
+
+pub fn cert_cache_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("RUSTII_CERT_CACHE") {
+        return Some(PathBuf::from(path));
+    }
+    let cache_dir = if let Some(dir) = env::var_os("XDG_CACHE_HOME") {
+        PathBuf::from(dir)
+    } else if let Some(dir) = env::var_os("LOCALAPPDATA") {
+        PathBuf::from(dir)
+    } else {
+        PathBuf::from(env::var_os("HOME")?).join(".cache")
+    };
+    Some(cache_dir.join("rustii").join("cert_chain.cert"))
+}
+
+#[cfg(feature = "nus")]
+fn download_cert_chain() -> Result<Vec<u8>> {
+    println!("No certificate chain was found, downloading one from the NUS...");
+    nus::download_cert_chain(false).with_context(|| "The certificate chain could not be downloaded. Use --cert to provide one instead.")
+}
+
+#[cfg(not(feature = "nus"))]
+fn download_cert_chain() -> Result<Vec<u8>> {
+    bail!("No certificate chain was found, and this build of rustii cannot download one from the NUS. Use --cert to provide one instead.")
+}
+
+// Gets the certificate chain to use for a title that doesn't include one, trying the cache first
+// and then falling back on downloading it from the NUS. Downloaded chains are saved to the cache.
+pub fn fallback_cert_chain(cache_path: Option<&Path>) -> Result<cert::CertificateChain> {
+    cached_cert_chain(cache_path, download_cert_chain)
+}
+
+// Loads the certificate chain from the cache at the provided path if there is one, and otherwise
+// gets one with the provided download function and saves it to the cache.
+fn cached_cert_chain<F>(cache_path: Option<&Path>, download: F) -> Result<cert::CertificateChain>
+where
+    F: FnOnce() -> Result<Vec<u8>>,
+{
+    if let Some(cache_path) = cache_path
+        && cache_path.exists() {
+        let cert_chain = cert::CertificateChain::open(cache_path)
+            .with_context(|| format!("The cached certificate chain \"{}\" could not be loaded.", cache_path.display()))?;
+        println!("Using cached certificate chain \"{}\".", cache_path.display());
+        return Ok(cert_chain);
+    }
+    let cert_data = download()?;
+    let cert_chain = cert::CertificateChain::from_bytes(&cert_data).with_context(|| "The downloaded certificate chain appears to be invalid.")?;
+    if let Some(cache_path) = cache_path {
+        // Failing to cache the chain shouldn't stop the WAD from being packed.
+        let cached = cache_path.parent().is_none_or(|dir| fs::create_dir_all(dir).is_ok()) && fs::write(cache_path, &cert_data).is_ok();
+        if !cached {
+            println!("Warning: the downloaded certificate chain could not be cached to \"{}\".", cache_path.display());
+        }
+    }
+    Ok(cert_chain)
+}
+
+// Loads the title in a directory to be packed. A certificate chain provided with --cert is always
+// used, then one in the directory, and the fallback is only called if neither is available.
+fn load_pack_title<F>(in_path: &Path, cert_chain: Option<cert::CertificateChain>, allow_missing: bool, content_form: Option<content::ContentForm>, fallback: F) -> Result<title::Title>
+where
+    F: FnOnce() -> Result<cert::CertificateChain>,
+{
+    let pack_context = || format!("The source directory \"{}\" could not be packed.", in_path.display());
+    let manifest = manifest::TitleManifest::read(in_path)
+        .with_context(|| format!("The manifest in the source directory \"{}\" appears to be invalid.", in_path.display()))?;
+    let title = if let Some(manifest) = manifest {
+        // Downloaded titles come with a manifest listing exactly which files make up the title and
+        // how they're stored, so there's no need to search for them.
+        println!("Using manifest \"{}\" to find title files.", manifest::MANIFEST_FILE_NAME);
+        let cert_chain = match cert_chain {
+            None if !in_path.join(&manifest.cert_chain).exists() => Some(fallback()?),
+            cert_chain => cert_chain,
+        };
+        manifest.load_title(in_path, cert_chain).with_context(pack_context)?
+    } else if cert_chain.is_some() {
+        title::Title::from_directory_with_options(in_path, cert_chain, allow_missing, content_form).with_context(pack_context)?
+    } else {
+        match title::Title::from_directory_with_options(in_path, None, allow_missing, content_form) {
+            Err(title::TitleError::MissingComponent(component)) if component == ".cert" => {
+                let cert_chain = fallback()?;
+                title::Title::from_directory_with_options(in_path, Some(cert_chain), allow_missing, content_form).with_context(pack_context)?
+            },
+            result => result.with_context(pack_context)?,
+        }
+    };
+    Ok(title)
+}
+
+pub fn pack_wad(input: &str, output: &str, cert: &Option<String>, no_fakesign: &bool, allow_missing: &bool, encrypted: &bool, reproducible: &ReproducibleOptions) -> Result<()> {
     let in_path = Path::new(input);
     if !in_path.exists() {
         bail!("Source directory \"{}\" does not exist.", in_path.display());
     }
-    // Read TMD file (only accept one file).
-    let tmd_files: Vec<PathBuf> = glob(&format!("{}/*.tmd", in_path.display()))?
-        .filter_map(|f| f.ok()).collect();
-    if tmd_files.is_empty() {
-        bail!("No TMD file found in the source directory.");
-    } else if tmd_files.len() > 1 {
-        bail!("More than one TMD file found in the source directory.");
-    }
-    let mut tmd = tmd::TMD::from_bytes(&fs::read(&tmd_files[0]).with_context(|| "Could not open TMD file for reading.")?)
-        .with_context(|| "The provided TMD file appears to be invalid.")?;
-    // Read Ticket file (only accept one file).
-    let ticket_files: Vec<PathBuf> = glob(&format!("{}/*.tik", in_path.display()))?
-        .filter_map(|f| f.ok()).collect();
-    if ticket_files.is_empty() {
-        bail!("No Ticket file found in the source directory.");
-    } else if ticket_files.len() > 1 {
-        bail!("More than one Ticket file found in the source directory.");
-    }
-    let tik = ticket::Ticket::from_bytes(&fs::read(&ticket_files[0]).with_context(|| "Could not open Ticket file for reading.")?)
-        .with_context(|| "The provided Ticket file appears to be invalid.")?;
-    // Read cert chain (only accept one file).
-    let cert_files: Vec<PathBuf> = glob(&format!("{}/*.cert", in_path.display()))?
-        .filter_map(|f| f.ok()).collect();
-    if cert_files.is_empty() {
-        bail!("No cert file found in the source directory.");
-    } else if cert_files.len() > 1 {
-        bail!("More than one Cert file found in the source directory.");
-    }
-    let cert_chain = cert::CertificateChain::from_bytes(&fs::read(&cert_files[0]).with_context(|| "Could not open cert chain file for reading.")?)
-        .with_context(|| "The provided certificate chain appears to be invalid.")?;
-    // Read footer, if one exists (only accept one file).
-    let footer_files: Vec<PathBuf> = glob(&format!("{}/*.footer", in_path.display()))?
-        .filter_map(|f| f.ok()).collect();
-    let mut footer: Vec<u8> = Vec::new();
-    if footer_files.len() == 1 {
-        footer = fs::read(&footer_files[0]).with_context(|| "Could not open footer file for reading.")?;
-    }
-    // Iterate over expected content and read it into a content region.
-    let mut content_region = content::ContentRegion::new(Rc::clone(&tmd.content_records))?;
-    let content_indexes: Vec<u16> = tmd.content_records.borrow().iter().map(|record| record.index).collect();
-    for index in content_indexes {
-        let data = fs::read(format!("{}/{:08X}.app", in_path.display(), index)).with_context(|| format!("Could not open content file \"{:08X}.app\" for reading.", index))?;
-        content_region.set_content(&data, index as usize, None, None, tik.dec_title_key())
-            .with_context(|| "Failed to load content into the ContentRegion.")?;
-    }
-    // Ensure that the TMD is modified with our potentially updated content records.
-    tmd.content_records = content_region.content_records.clone();
-    let wad = wad::WAD::from_parts(&cert_chain, &[], &tik, &tmd, &content_region, &footer).with_context(|| "An unknown error occurred while building a WAD from the input files.")?;
+    let timestamp_override = match &reproducible.timestamp {
+        Some(timestamp) => match meta::BuildTimestamp::parse(timestamp) {
+            Some(timestamp) => Some(timestamp),
+            None => bail!("The provided timestamp \"{}\" is not valid! Timestamps must be formatted as \"YYYY/MM/DD HH:MM:SS\".", timestamp),
+        },
+        None => None,
+    };
+    let pack_options = title::PackOptions { deterministic: reproducible.reproducible, timestamp_override };
+    let content_form = if *encrypted { Some(content::ContentForm::Encrypted) } else { None };
+    let cert_chain = match cert {
+        Some(cert) => {
+            let cert_path = Path::new(cert);
+            Some(cert::CertificateChain::open(cert_path)
+                .with_context(|| format!("The provided certificate chain \"{}\" could not be loaded.", cert_path.display()))?)
+        },
+        None => None,
+    };
+    let mut title = load_pack_title(in_path, cert_chain, *allow_missing, content_form, || fallback_cert_chain(cert_cache_path().as_deref()))?;
+    let num_contents = title.content.len();
+    let missing = (0..num_contents).filter(|i| !title.content.is_content_present(*i)).count();
+    if missing > 0 {
+        println!("Note: {} of {} contents were missing and will not be included in the WAD.", missing, num_contents);
+    }
+    if !*no_fakesign {
+        title.fakesign().with_context(|| "An unknown error occurred while fakesigning the packed WAD.")?;
+    }
     // Write out WAD file.
     let mut out_path = PathBuf::from(output);
     match out_path.extension() {
@@ -411,46 +744,58 @@ pub fn pack_wad(input: &str, output: &str) -> Result<()> {
             out_path.set_extension("wad");
         }
     }
-    fs::write(&out_path, wad.to_bytes()?).with_context(|| format!("Could not open output file \"{}\" for writing.", out_path.display()))?;
+    // Write the WAD straight to the file, since it may be too large to comfortably copy in memory.
+    let out_file = File::create(&out_path).with_context(|| format!("Could not open output file \"{}\" for writing.", out_path.display()))?;
+    let mut writer = BufWriter::new(out_file);
+    title.write_wad_with_options(&mut writer, &pack_options).with_context(|| format!("Could not write to output file \"{}\".", out_path.display()))?;
+    writer.flush().with_context(|| format!("Could not write to output file \"{}\".", out_path.display()))?;
     println!("Successfully packed WAD file to \"{}\"!", out_path.display());
     Ok(())
 }
 
-pub fn remove_wad(input: &str, output: &Option<String>, identifier: &ContentIdentifier) ->  Result<()> {
+pub fn remove_wad(input: &str, output: &Option<String>, output_options: &OutputOptions, identifier: &ContentIdentifier, ctype: &Option<String>) ->  Result<()> {
     let in_path = Path::new(input);
     if !in_path.exists() {
         bail!("Source WAD \"{}\" could not be found.", in_path.display());
     }
-    let out_path = if output.is_some() {
-        PathBuf::from(output.clone().unwrap()).with_extension("wad")
-    } else {
-        in_path.to_path_buf()
-    };
-    let mut title = title::Title::from_bytes(&fs::read(in_path)?).with_context(|| "The provided WAD file could not be parsed, and is likely invalid.")?;
+    let out_path = resolve_output(output.as_ref().map(|output| PathBuf::from(output).with_extension("wad")), in_path.to_path_buf(), output_options, Some(in_path))?;
+    let mut title = title::Title::open(in_path).with_context(|| format!("The provided WAD file \"{}\" could not be loaded.", in_path.display()))?;
     // Parse the identifier passed to choose how to find and remove the target.
     // ...maybe don't take the above comment out of context
-    if identifier.index.is_some() {
-        title.content.remove_content(identifier.index.unwrap()).with_context(|| "The specified index does not exist in the provided WAD!")?;
-        println!("{:?}", title.tmd);
-        title.fakesign().with_context(|| "An unknown error occurred while fakesigning the modified WAD.")?;
-        fs::write(&out_path, title.to_wad()?.to_bytes()?).with_context(|| "Could not open output file for writing.")?;
-        println!("Successfully removed content at index {} in WAD file \"{}\".", identifier.index.unwrap(), out_path.display());
+    let summary = if let Some(index) = identifier.index {
+        title.remove_content(index).with_context(|| "The specified index does not exist in the provided WAD!")?;
+        format!("Successfully removed content at index {} in WAD file \"{}\".", index, out_path.display())
     } else if identifier.cid.is_some() {
-        let cid = u32::from_str_radix(identifier.cid.clone().unwrap().as_str(), 16).with_context(|| "The specified Content ID is invalid!")?;
-        let index = match title.content.get_index_from_cid(cid) {
-            Ok(index) => index,
-            Err(_) => bail!("The specified Content ID \"{}\" ({}) does not exist in this WAD!", identifier.cid.clone().unwrap(), cid),
+        let cid = super::parse_cid(identifier.cid.as_deref().unwrap())?;
+        let index = match title.content.content_records.read().iter().position(|record| record.content_id == cid) {
+            Some(index) => index,
+            None => bail!("The specified Content ID \"{}\" ({}) does not exist in this WAD!", identifier.cid.clone().unwrap(), cid),
         };
-        title.content.remove_content(index).with_context(|| "An unknown error occurred while removing content from the WAD.")?;
-        println!("{:?}", title.tmd);
-        title.fakesign().with_context(|| "An unknown error occurred while fakesigning the modified WAD.")?;
-        fs::write(&out_path, title.to_wad()?.to_bytes()?).with_context(|| "Could not open output file for writing.")?;
-        println!("Successfully removed content with Content ID \"{}\" ({}) in WAD file \"{}\".", identifier.cid.clone().unwrap(), cid, out_path.display());
-    }
+        title.remove_content(index).with_context(|| "An unknown error occurred while removing content from the WAD.")?;
+        format!("Successfully removed content with Content ID \"{}\" ({}) in WAD file \"{}\".", identifier.cid.clone().unwrap(), cid, out_path.display())
+    } else if let Some(ctype) = ctype {
+        let target_type = match ctype.to_ascii_lowercase().as_str() {
+            "normal" => tmd::ContentType::Normal,
+            "shared" => tmd::ContentType::Shared,
+            "dlc" => tmd::ContentType::DLC,
+            _ => bail!("The specified content type \"{}\" is invalid! Try --help to see valid types.", ctype),
+        };
+        let removed = title.remove_contents_by_type(target_type.clone());
+        if removed.is_empty() {
+            bail!("There are no contents of type \"{}\" in this WAD!", target_type);
+        }
+        let cids: Vec<String> = removed.iter().map(|cid| format!("{:08X}", cid)).collect();
+        format!("Successfully removed {} content(s) of type \"{}\" ({}) in WAD file \"{}\".", removed.len(), target_type, cids.join(", "), out_path.display())
+    } else {
+        return Ok(());
+    };
+    let wad_data = title.fakesign().with_context(|| "An unknown error occurred while fakesigning the modified WAD.")?.to_wad_bytes()?;
+    write_output(&out_path, &wad_data, output_options)?;
+    println!("{}{}", summary, dry_run_suffix(output_options.dry_run));
     Ok(())
 }
 
-pub fn set_wad(input: &str, content: &str, output: &Option<String>, identifier: &ContentIdentifier, ctype: &Option<String>) -> Result<()> {
+pub fn set_wad(input: &str, content: &str, output: &Option<String>, output_options: &OutputOptions, identifier: &ContentIdentifier, ctype: &Option<String>) -> Result<()> {
     let in_path = Path::new(input);
     if !in_path.exists() {
         bail!("Source WAD \"{}\" could not be found.", in_path.display());
@@ -459,13 +804,9 @@ pub fn set_wad(input: &str, content: &str, output: &Option<String>, identifier:
     if !content_path.exists() {
         bail!("New content \"{}\" could not be found.", content_path.display());
     }
-    let out_path = if output.is_some() {
-        PathBuf::from(output.clone().unwrap()).with_extension("wad")
-    } else {
-        in_path.to_path_buf()
-    };
+    let out_path = resolve_output(output.as_ref().map(|output| PathBuf::from(output).with_extension("wad")), in_path.to_path_buf(), output_options, Some(in_path))?;
     // Load the WAD and parse the new type, if one was specified.
-    let mut title = title::Title::from_bytes(&fs::read(in_path)?).with_context(|| "The provided WAD file could not be parsed, and is likely invalid.")?;
+    let mut title = title::Title::open(in_path).with_context(|| format!("The provided WAD file \"{}\" could not be loaded.", in_path.display()))?;
     let new_content = fs::read(content_path)?;
     let mut target_type: Option<tmd::ContentType> = None;
     if ctype.is_some() {
@@ -477,59 +818,217 @@ pub fn set_wad(input: &str, content: &str, output: &Option<String>, identifier:
         };
     }
     // Parse the identifier passed to choose how to do the find and replace.
-    if identifier.index.is_some() {
-        match title.set_content(&new_content, identifier.index.unwrap(), None, target_type) {
+    let summary = if let Some(index) = identifier.index {
+        match title.set_content(&new_content, index, None, target_type) {
             Err(title::TitleError::Content(content::ContentError::IndexOutOfRange { index, max })) => {
                 bail!("The specified index {} does not exist in this WAD! The maximum index is {}.", index, max)
             },
+            Err(title::TitleError::Content(content::ContentError::Empty(index))) => {
+                bail!("The specified index {} does not exist in this WAD! This WAD does not contain any content.", index)
+            },
             Err(e) => bail!("An unknown error occurred while setting the new content: {e}"),
             Ok(_) => (),
         }
-        title.fakesign().with_context(|| "An unknown error occurred while fakesigning the modified WAD.")?;
-        fs::write(&out_path, title.to_wad()?.to_bytes()?).with_context(|| "Could not open output file for writing.")?;
-        println!("Successfully replaced content at index {} in WAD file \"{}\".", identifier.index.unwrap(), out_path.display());
+        format!("Successfully replaced content at index {} in WAD file \"{}\".", index, out_path.display())
     } else if identifier.cid.is_some() {
-        let cid = u32::from_str_radix(identifier.cid.clone().unwrap().as_str(), 16).with_context(|| "The specified Content ID is invalid!")?;
+        let cid = super::parse_cid(identifier.cid.as_deref().unwrap())?;
         let index = match title.content.get_index_from_cid(cid) {
             Ok(index) => index,
             Err(_) => bail!("The specified Content ID \"{}\" ({}) does not exist in this WAD!", identifier.cid.clone().unwrap(), cid),
         };
         title.set_content(&new_content, index, None, target_type).with_context(|| "An unknown error occurred while setting the new content.")?;
-        title.fakesign().with_context(|| "An unknown error occurred while fakesigning the modified WAD.")?;
-        fs::write(&out_path, title.to_wad()?.to_bytes()?).with_context(|| "Could not open output file for writing.")?;
-        println!("Successfully replaced content with Content ID \"{}\" ({}) in WAD file \"{}\".", identifier.cid.clone().unwrap(), cid, out_path.display());
-    }
+        format!("Successfully replaced content with Content ID \"{}\" ({}) in WAD file \"{}\".", identifier.cid.clone().unwrap(), cid, out_path.display())
+    } else {
+        return Ok(());
+    };
+    let wad_data = title.fakesign().with_context(|| "An unknown error occurred while fakesigning the modified WAD.")?.to_wad_bytes()?;
+    write_output(&out_path, &wad_data, output_options)?;
+    println!("{}{}", summary, dry_run_suffix(output_options.dry_run));
     Ok(())
 }
 
-pub fn unpack_wad(input: &str, output: &str) -> Result<()> {
+pub fn unpack_wad(input: &str, output: &str, encrypted: &bool, name_by: &Option<String>) -> Result<()> {
     let in_path = Path::new(input);
     if !in_path.exists() {
         bail!("Source WAD \"{}\" could not be found.", input);
     }
-    let wad_file = fs::read(in_path).with_context(|| format!("Failed to open WAD file \"{}\" for reading.", in_path.display()))?;
-    let title = title::Title::from_bytes(&wad_file).with_context(|| format!("The provided WAD file \"{}\" appears to be invalid.", in_path.display()))?;
-    let tid = hex::encode(title.tmd.title_id());
-    // Create output directory if it doesn't exist.
+    let naming = match name_by.as_deref().map(|name| name.to_ascii_lowercase()).as_deref() {
+        None | Some("index") => title::ContentNaming::Index,
+        Some("cid") => title::ContentNaming::ContentID,
+        Some(_) => bail!("The specified naming convention \"{}\" is invalid! Try --help to see valid options.", name_by.clone().unwrap()),
+    };
+    let title = title::Title::open(in_path).with_context(|| format!("The provided WAD file \"{}\" could not be loaded.", in_path.display()))?;
     let out_path = Path::new(output);
-    if !out_path.exists() {
-        fs::create_dir(out_path).with_context(|| format!("The output directory \"{}\" could not be created.", out_path.display()))?;
-    }
-    // Write out all WAD components.
-    let tmd_file_name = format!("{}.tmd", tid);
-    fs::write(Path::join(out_path, tmd_file_name.clone()), title.tmd.to_bytes()?).with_context(|| format!("Failed to open TMD file \"{}\" for writing.", tmd_file_name))?;
-    let ticket_file_name = format!("{}.tik", tid);
-    fs::write(Path::join(out_path, ticket_file_name.clone()), title.ticket.to_bytes()?).with_context(|| format!("Failed to open Ticket file \"{}\" for writing.", ticket_file_name))?;
-    let cert_file_name = format!("{}.cert", tid);
-    fs::write(Path::join(out_path, cert_file_name.clone()), title.cert_chain.to_bytes()?).with_context(|| format!("Failed to open certificate chain file \"{}\" for writing.", cert_file_name))?;
-    let meta_file_name = format!("{}.footer", tid);
-    fs::write(Path::join(out_path, meta_file_name.clone()), title.meta()).with_context(|| format!("Failed to open footer file \"{}\" for writing.", meta_file_name))?;
-    // Iterate over contents, decrypt them, and write them out.
-    for i in 0..title.tmd.content_records.borrow().len() {
-        let content_file_name = format!("{:08X}.app", title.content.content_records.borrow()[i].index);
-        let dec_content = title.get_content_by_index(i).with_context(|| format!("Failed to unpack content with Content ID {:08X}.", title.content.content_records.borrow()[i].content_id))?;
-        fs::write(Path::join(out_path, content_file_name), dec_content).with_context(|| format!("Failed to open content file \"{:08X}.app\" for writing.", title.content.content_records.borrow()[i].content_id))?;
+    title.to_directory_with_options(out_path, naming, *encrypted).with_context(|| format!("The WAD file could not be unpacked to \"{}\".", out_path.display()))?;
+    for (i, record) in title.iter_by_position() {
+        if !title.content.is_content_present(i) {
+            println!("Note: Content {:08X} (index {}) is not present in the WAD and was skipped.", record.content_id, record.index);
+        }
     }
     println!("Successfully unpacked WAD file to \"{}\"!", out_path.display());
     Ok(())
 }
+
+pub fn verify_wad(input: &str, strict: &bool) -> Result<()> {
+    let in_path = Path::new(input);
+    if !in_path.exists() {
+        bail!("Source WAD \"{}\" could not be found.", input);
+    }
+    let wad_file = fs::read(in_path).with_context(|| format!("Failed to open WAD file \"{}\" for reading.", in_path.display()))?;
+    let wad = wad::WAD::from_bytes(&wad_file).with_context(|| format!("The provided WAD file \"{}\" appears to be invalid.", in_path.display()))?;
+    let title = title::Title::from_wad(&wad).with_context(|| format!("The provided WAD file \"{}\" appears to be invalid.", in_path.display()))?;
+    // Check the hash of every content against its record.
+    let mut bad_contents = 0;
+    let num_contents = title.content.len();
+    for (i, record) in title.iter_by_position() {
+        if !title.content.is_content_present(i) {
+            println!(" - Content at index {} is not present", record.index);
+            continue;
+        }
+        if let Err(e) = title.get_content_by_index(i) {
+            println!(" - Content at index {} is invalid: {}", record.index, e);
+            bad_contents += 1;
+        }
+    }
+    if title.verify().unwrap_or(false) {
+        println!(" - Signature: Valid");
+    } else if title.is_fakesigned() {
+        println!(" - Signature: Fakesigned");
+    } else {
+        println!(" - Signature: Invalid");
+    }
+    let mut anomalies = wad.validate();
+    anomalies.extend(wad::WAD::validate_layout(&wad_file)?);
+    for anomaly in &anomalies {
+        println!(" - Anomaly: {}", anomaly);
+    }
+    // Re-packing a WAD should reproduce it exactly, but data that isn't part of any section can't
+    // be kept.
+    let exact = !*strict || wad.is_roundtrip_exact(&wad_file);
+    if !exact {
+        println!(" - Warning: Re-packing this WAD will not reproduce the original file exactly, as data past the end of the last section and padding cut off by truncation are not kept");
+    }
+    if bad_contents > 0 {
+        bail!("{} of {} contents in WAD file \"{}\" failed verification.", bad_contents, num_contents, in_path.display());
+    }
+    if *strict && !anomalies.is_empty() {
+        bail!("Found {} structural anomalies in WAD file \"{}\".", anomalies.len(), in_path.display());
+    }
+    if !exact {
+        bail!("WAD file \"{}\" cannot be re-packed exactly.", in_path.display());
+    }
+    println!("Successfully verified WAD file \"{}\"!", in_path.display());
+    Ok(())
+}
+
+pub fn dump_meta(input: &str, output: &Option<String>) -> Result<()> {
+    let in_path = Path::new(input);
+    if !in_path.exists() {
+        bail!("Source WAD \"{}\" does not exist.", in_path.display());
+    }
+    let title = title::Title::open(in_path).with_context(|| format!("The provided WAD file \"{}\" could not be loaded.", in_path.display()))?;
+    let meta = metadata::TitleMetadata::from_title(&title).to_json()?;
+    if let Some(output) = output {
+        let out_path = Path::new(output);
+        fs::write(out_path, meta + "\n").with_context(|| format!("Could not open output file \"{}\" for writing.", out_path.display()))?;
+        println!("Successfully dumped metadata from WAD file \"{}\" to \"{}\"!", in_path.display(), out_path.display());
+    } else {
+        println!("{}", meta);
+    }
+    Ok(())
+}
+
+pub fn apply_meta(input: &str, meta: &str, output: &Option<String>, output_options: &OutputOptions) -> Result<()> {
+    let in_path = Path::new(input);
+    if !in_path.exists() {
+        bail!("Source WAD \"{}\" does not exist.", in_path.display());
+    }
+    let meta_path = Path::new(meta);
+    if !meta_path.exists() {
+        bail!("Metadata file \"{}\" does not exist.", meta_path.display());
+    }
+    let out_path = resolve_output(output.as_ref().map(|output| PathBuf::from(output).with_extension("wad")), in_path.to_path_buf(), output_options, Some(in_path))?;
+    let mut title = title::Title::open(in_path).with_context(|| format!("The provided WAD file \"{}\" could not be loaded.", in_path.display()))?;
+    let meta = metadata::TitleMetadata::from_json(&fs::read_to_string(meta_path)?)
+        .with_context(|| format!("The metadata file \"{}\" could not be parsed.", meta_path.display()))?;
+    let old_tmd = title.tmd.clone();
+    let old_ticket = title.ticket.clone();
+    meta.apply(&mut title).with_context(|| "The provided metadata could not be applied to the WAD.")?;
+    title.fakesign()?;
+    write_output(&out_path, &title.to_wad_bytes()?, output_options)?;
+    let suffix = dry_run_suffix(output_options.dry_run);
+    let tmd_diff = old_tmd.diff(&title.tmd);
+    let ticket_diff = old_ticket.diff(&title.ticket);
+    if tmd_diff.is_empty() && ticket_diff.is_empty() {
+        println!("Successfully applied metadata to WAD file \"{}\"!{} No fields were changed.", out_path.display(), suffix);
+        return Ok(());
+    }
+    println!("Successfully applied metadata to WAD file \"{}\"!{}\nSummary of changes:", out_path.display(), suffix);
+    for field_diff in tmd_diff.fields.iter().chain(ticket_diff.fields.iter()) {
+        println!(" - {}", field_diff);
+    }
+    for content_diff in &tmd_diff.contents {
+        println!(" - {}", content_diff);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn load_fixture() -> title::Title {
+        title::Title::from_bytes(&fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/test_title.wad")).unwrap()).unwrap()
+    }
+
+    fn no_download() -> Result<Vec<u8>> {
+        panic!("the certificate chain shouldn't have been downloaded");
+    }
+
+    #[test]
+    fn test_cached_cert_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join("cache").join("cert_chain.cert");
+        let cert_data = load_fixture().cert_chain_bytes().unwrap();
+        // Without a cached chain, one is downloaded and then saved to the cache.
+        let cert_chain = cached_cert_chain(Some(&cache_path), || Ok(cert_data.clone())).unwrap();
+        assert_eq!(cert_chain.to_bytes().unwrap(), cert_data);
+        assert_eq!(fs::read(&cache_path).unwrap(), cert_data);
+        // Once it's cached, it's used instead of downloading a new one.
+        let cert_chain = cached_cert_chain(Some(&cache_path), no_download).unwrap();
+        assert_eq!(cert_chain.to_bytes().unwrap(), cert_data);
+        // An invalid cached chain is reported rather than silently replaced.
+        fs::write(&cache_path, [0x5A; 0x40]).unwrap();
+        let err = cached_cert_chain(Some(&cache_path), no_download).unwrap_err();
+        assert!(err.to_string().contains("cached certificate chain"));
+        // Failing to download one is an error too.
+        assert!(cached_cert_chain(None, || bail!("offline")).is_err());
+    }
+
+    #[test]
+    fn test_pack_cert_chain_priority() {
+        let title = load_fixture();
+        let cert_data = title.cert_chain_bytes().unwrap();
+        // A chain that can be told apart from the one in the directory.
+        let mut other_data = cert_data.clone();
+        other_data[0x10] ^= 0xFF;
+        let other_chain = cert::CertificateChain::from_bytes(&other_data).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        title.to_directory(dir.path()).unwrap();
+        // --cert wins over the chain in the directory, which wins over the fallback.
+        let packed = load_pack_title(dir.path(), Some(other_chain.clone()), false, None, || panic!("no fallback expected")).unwrap();
+        assert_eq!(packed.cert_chain_bytes().unwrap(), other_data);
+        let packed = load_pack_title(dir.path(), None, false, None, || panic!("no fallback expected")).unwrap();
+        assert_eq!(packed.cert_chain_bytes().unwrap(), cert_data);
+        // Without a chain in the directory, the fallback is used.
+        for entry in fs::read_dir(dir.path()).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|ext| ext == "cert") {
+                fs::remove_file(path).unwrap();
+            }
+        }
+        let packed = load_pack_title(dir.path(), None, false, None, || Ok(other_chain.clone())).unwrap();
+        assert_eq!(packed.cert_chain_bytes().unwrap(), other_data);
+        assert!(load_pack_title(dir.path(), None, false, None, || bail!("offline")).is_err());
+    }
+}
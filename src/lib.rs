@@ -2,7 +2,46 @@
 // https://github.com/NinjaCheetah/rustii
 //
 // Root level module that imports the feature modules.
+//
+// rustii never prints anything itself. Diagnostics are reported through the `log` crate, and the
+// progress of long operations (like downloading or installing titles) is logged under the
+// PROGRESS_LOG_TARGET target, so that applications can choose how to display it.
+//
+// The parsing and crypto core (the archive and title modules) only ever works on in-memory data,
+// and can be built for wasm32-unknown-unknown on its own with:
+// cargo build --lib --no-default-features --target wasm32-unknown-unknown
+
+//! rustii is a library for handling the files and formats used by the Wii. The most commonly used
+//! types are available from the [prelude], and [Title] is the main entry point for working with
+//! digital titles.
+//!
+//! ```no_run
+//! use rustii::prelude::*;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut title = Title::from_bytes(&std::fs::read("title.wad")?)?;
+//! println!("{} v{}", title.tmd.title_id(), title.tmd.title_version);
+//! for (index, record) in title.content.iter_by_position() {
+//!     println!("Content {:08X}: {} bytes", record.content_id, title.get_content_by_index(index)?.len());
+//! }
+//! title.fakesign()?;
+//! let wad: WAD = title.to_wad()?;
+//! std::fs::write("title_fakesigned.wad", wad.to_bytes()?)?;
+//! # Ok(())
+//! # }
+//! ```
 
 pub mod archive;
+#[cfg(feature = "nand")]
 pub mod nand;
+pub mod prelude;
 pub mod title;
+
+pub use title::Title;
+
+// The NUS feature uses a blocking HTTP client, which isn't available on wasm.
+#[cfg(all(target_arch = "wasm32", feature = "nus"))]
+compile_error!("the nus feature is not supported on wasm targets, build with --no-default-features instead");
+
+/// The log target used for progress events during long operations.
+pub const PROGRESS_LOG_TARGET: &str = "rustii::progress";
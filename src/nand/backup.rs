@@ -0,0 +1,484 @@
+// nand/backup.rs from rustii (c) 2025 NinjaCheetah & Contributors
+// https://github.com/NinjaCheetah/rustii
+//
+// Implements the structures and methods required for reading BootMii NAND backups (nand.bin) and
+// extracting their filesystem.
+
+use std::fs;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use aes::cipher::{BlockDecryptMut, KeyIvInit};
+use aes::cipher::block_padding::NoPadding;
+use thiserror::Error;
+
+const PAGE_SIZE: usize = 0x800;
+const SPARE_SIZE: usize = 0x40;
+const PAGES_PER_CLUSTER: usize = 8;
+const CLUSTER_SIZE: usize = PAGE_SIZE * PAGES_PER_CLUSTER;
+const CLUSTER_COUNT: usize = 0x8000;
+const SUPERBLOCK_START: usize = 0x7F00;
+const SUPERBLOCK_CLUSTERS: usize = 16;
+const SUPERBLOCK_COUNT: usize = 16;
+const FAT_OFFSET: usize = 0xC;
+const FST_OFFSET: usize = FAT_OFFSET + CLUSTER_COUNT * 2;
+const FST_ENTRY_COUNT: usize = 0x17FF;
+const FST_ENTRY_SIZE: usize = 0x20;
+const FAT_LAST_CLUSTER: u16 = 0xFFFB;
+const FST_NONE: u16 = 0xFFFF;
+const NAND_SIZE_NO_ECC: u64 = 0x20000000;
+const NAND_SIZE_ECC: u64 = 0x21000000;
+const NAND_SIZE_ECC_KEYS: u64 = 0x21000400;
+const KEYS_NAND_KEY_OFFSET: usize = 0x158;
+// The top-level directories that only hold temporary data during installs, and aren't extracted.
+const SKIPPED_DIRS: [&str; 2] = ["import", "tmp"];
+
+#[derive(Debug, Error)]
+pub enum NandBackupError {
+    #[error("NAND backup is an invalid size ({0} bytes), and is not a supported BootMii backup")]
+    InvalidSize(u64),
+    #[error("keys.bin is too short to contain the NAND key")]
+    InvalidKeys,
+    #[error("no valid filesystem superblock could be found in the NAND backup")]
+    NoSuperblock,
+    #[error("NAND backup data is not in a valid format")]
+    IO(#[from] std::io::Error),
+}
+
+#[derive(Debug, Default)]
+/// A structure that represents the results of extracting a NAND backup.
+pub struct ExtractReport {
+    /// The number of files that were extracted.
+    pub extracted_files: usize,
+    /// The number of clusters that had errors which were corrected using their ECC data.
+    pub corrected_clusters: usize,
+    /// Paths of files that were not extracted because their data is damaged, either because their
+    /// ECC data indicates uncorrectable errors, or because their cluster chain is broken.
+    pub damaged_files: Vec<String>,
+    /// Paths of the top-level directories that were skipped, since they only hold temporary data.
+    pub skipped_dirs: Vec<String>,
+}
+
+// The result of checking a page against its ECC data.
+#[derive(Debug, PartialEq)]
+enum EccStatus {
+    Ok,
+    Corrected,
+    Uncorrectable,
+}
+
+#[derive(Debug, Clone)]
+// A file or directory in the NAND's filesystem table.
+struct FstEntry {
+    name: String,
+    mode: u8,
+    sub: u16,
+    sib: u16,
+    size: u32,
+}
+
+impl FstEntry {
+    fn from_bytes(data: &[u8]) -> Self {
+        let name_end = data[0..12].iter().position(|byte| *byte == 0).unwrap_or(12);
+        FstEntry {
+            name: String::from_utf8_lossy(&data[0..name_end]).to_string(),
+            mode: data[12],
+            sub: u16::from_be_bytes([data[14], data[15]]),
+            sib: u16::from_be_bytes([data[16], data[17]]),
+            size: u32::from_be_bytes([data[18], data[19], data[20], data[21]]),
+        }
+    }
+
+    fn is_file(&self) -> bool {
+        self.mode & 3 == 1
+    }
+
+    fn is_dir(&self) -> bool {
+        self.mode & 3 == 2
+    }
+}
+
+/// Calculates the ECC data for a 512-byte subpage of NAND data, in the format stored in a page's
+/// spare area.
+pub fn calc_ecc(data: &[u8]) -> [u8; 4] {
+    let mut a = [[0u8; 2]; 12];
+    for (i, byte) in data.iter().take(512).enumerate() {
+        for j in 0..9 {
+            a[3 + j][(i >> j) & 1] ^= byte;
+        }
+    }
+    let x = a[3][0] ^ a[3][1];
+    a[0] = [x & 0x55, x & 0xAA];
+    a[1] = [x & 0x33, x & 0xCC];
+    a[2] = [x & 0x0F, x & 0xF0];
+    let mut a0: u16 = 0;
+    let mut a1: u16 = 0;
+    for (j, pair) in a.iter().enumerate() {
+        a0 |= ((pair[0].count_ones() & 1) as u16) << j;
+        a1 |= ((pair[1].count_ones() & 1) as u16) << j;
+    }
+    let a0 = a0.to_le_bytes();
+    let a1 = a1.to_le_bytes();
+    [a0[0], a0[1], a1[0], a1[1]]
+}
+
+// Checks a page against the ECC data in its spare area, correcting a single flipped bit in each
+// subpage if needed. Erased pages have no ECC data, and are always considered valid.
+fn check_page_ecc(page: &mut [u8], spare: &[u8]) -> EccStatus {
+    if spare.iter().all(|byte| *byte == 0xFF) {
+        return EccStatus::Ok;
+    }
+    let mut status = EccStatus::Ok;
+    for (i, subpage) in page.chunks_mut(512).enumerate() {
+        let stored = &spare[0x30 + i * 4..0x34 + i * 4];
+        let calculated = calc_ecc(subpage);
+        let s0 = u16::from_le_bytes([stored[0] ^ calculated[0], stored[1] ^ calculated[1]]) & 0xFFF;
+        let s1 = u16::from_le_bytes([stored[2] ^ calculated[2], stored[3] ^ calculated[3]]) & 0xFFF;
+        if s0 == 0 && s1 == 0 {
+            continue;
+        }
+        if s0 ^ s1 == 0xFFF {
+            // A single flipped data bit, whose location is given by the syndrome.
+            subpage[(s1 >> 3) as usize] ^= 1 << (s1 & 7);
+            status = EccStatus::Corrected;
+        } else if s0.count_ones() + s1.count_ones() == 1 {
+            // A single flipped bit in the ECC data itself, so the data is fine.
+            status = EccStatus::Corrected;
+        } else {
+            return EccStatus::Uncorrectable;
+        }
+    }
+    status
+}
+
+/// A BootMii NAND backup (nand.bin) that can be read and extracted.
+pub struct NandBackup {
+    nand_path: PathBuf,
+    nand_key: [u8; 16],
+    has_ecc: bool,
+    fat: Vec<u16>,
+    fst: Vec<FstEntry>,
+}
+
+impl NandBackup {
+    /// Opens a BootMii NAND backup, using the NAND key from the console's keys.bin to decrypt it.
+    /// Backups with and without ECC data are supported, as are backups with keys.bin appended.
+    pub fn open(nand_path: &Path, keys: &[u8]) -> Result<Self, NandBackupError> {
+        if keys.len() < KEYS_NAND_KEY_OFFSET + 16 {
+            return Err(NandBackupError::InvalidKeys);
+        }
+        let nand_key: [u8; 16] = keys[KEYS_NAND_KEY_OFFSET..KEYS_NAND_KEY_OFFSET + 16].try_into().unwrap();
+        let has_ecc = match fs::metadata(nand_path)?.len() {
+            NAND_SIZE_ECC | NAND_SIZE_ECC_KEYS => true,
+            NAND_SIZE_NO_ECC => false,
+            size => return Err(NandBackupError::InvalidSize(size)),
+        };
+        let mut backup = NandBackup {
+            nand_path: nand_path.to_path_buf(),
+            nand_key,
+            has_ecc,
+            fat: Vec::new(),
+            fst: Vec::new(),
+        };
+        backup.load_superblock()?;
+        Ok(backup)
+    }
+
+    // Reads the raw data of a cluster, checking each page against its ECC data if the backup has
+    // any. Returns the status of the worst page.
+    fn read_raw_cluster(&self, file: &mut File, cluster: usize) -> Result<(Vec<u8>, EccStatus), NandBackupError> {
+        let page_stride = if self.has_ecc { PAGE_SIZE + SPARE_SIZE } else { PAGE_SIZE };
+        let mut data = vec![0u8; CLUSTER_SIZE];
+        let mut status = EccStatus::Ok;
+        for (i, page) in data.chunks_mut(PAGE_SIZE).enumerate() {
+            file.seek(SeekFrom::Start(((cluster * PAGES_PER_CLUSTER + i) * page_stride) as u64))?;
+            file.read_exact(page)?;
+            if self.has_ecc {
+                let mut spare = [0u8; SPARE_SIZE];
+                file.read_exact(&mut spare)?;
+                match check_page_ecc(page, &spare) {
+                    EccStatus::Ok => (),
+                    EccStatus::Corrected => status = EccStatus::Corrected,
+                    EccStatus::Uncorrectable => return Ok((data, EccStatus::Uncorrectable)),
+                }
+            }
+        }
+        Ok((data, status))
+    }
+
+    // Finds the newest valid superblock and loads the FAT and FST from it. Superblocks aren't
+    // encrypted, and each one spans 16 clusters.
+    fn load_superblock(&mut self) -> Result<(), NandBackupError> {
+        let mut file = File::open(&self.nand_path)?;
+        let mut candidates: Vec<(u32, usize)> = Vec::new();
+        for i in 0..SUPERBLOCK_COUNT {
+            let cluster = SUPERBLOCK_START + i * SUPERBLOCK_CLUSTERS;
+            let (data, _) = self.read_raw_cluster(&mut file, cluster)?;
+            if &data[0..4] == b"SFFS" {
+                candidates.push((u32::from_be_bytes(data[4..8].try_into().unwrap()), cluster));
+            }
+        }
+        // Try the newest superblock first, and fall back to older ones if it's damaged.
+        candidates.sort_by_key(|(generation, _)| std::cmp::Reverse(*generation));
+        for (_, start) in candidates {
+            let mut superblock: Vec<u8> = Vec::with_capacity(CLUSTER_SIZE * SUPERBLOCK_CLUSTERS);
+            let mut damaged = false;
+            for cluster in start..start + SUPERBLOCK_CLUSTERS {
+                let (data, status) = self.read_raw_cluster(&mut file, cluster)?;
+                if status == EccStatus::Uncorrectable {
+                    damaged = true;
+                    break;
+                }
+                superblock.extend(data);
+            }
+            if damaged {
+                continue;
+            }
+            self.fat = superblock[FAT_OFFSET..FST_OFFSET].chunks(2)
+                .map(|entry| u16::from_be_bytes([entry[0], entry[1]]))
+                .collect();
+            self.fst = superblock[FST_OFFSET..FST_OFFSET + FST_ENTRY_COUNT * FST_ENTRY_SIZE].chunks(FST_ENTRY_SIZE)
+                .map(FstEntry::from_bytes)
+                .collect();
+            return Ok(());
+        }
+        Err(NandBackupError::NoSuperblock)
+    }
+
+    // Reads and decrypts the data of a file by following its cluster chain. Returns None if the
+    // file is damaged.
+    fn read_file(&self, file: &mut File, entry: &FstEntry, report: &mut ExtractReport) -> Result<Option<Vec<u8>>, NandBackupError> {
+        type Aes128CbcDec = cbc::Decryptor<aes::Aes128>;
+        let mut data: Vec<u8> = Vec::with_capacity(entry.size as usize);
+        let mut cluster = entry.sub;
+        while data.len() < entry.size as usize {
+            if cluster as usize >= CLUSTER_COUNT {
+                return Ok(None);
+            }
+            let (mut cluster_data, status) = self.read_raw_cluster(file, cluster as usize)?;
+            match status {
+                EccStatus::Ok => (),
+                EccStatus::Corrected => report.corrected_clusters += 1,
+                EccStatus::Uncorrectable => return Ok(None),
+            }
+            // Every cluster is encrypted on its own, with an IV of zero.
+            let decryptor = Aes128CbcDec::new(&self.nand_key.into(), &[0u8; 16].into());
+            decryptor.decrypt_padded_mut::<NoPadding>(&mut cluster_data).unwrap();
+            data.extend(cluster_data);
+            cluster = self.fat[cluster as usize];
+            if cluster == FAT_LAST_CLUSTER && data.len() < entry.size as usize {
+                return Ok(None);
+            }
+        }
+        data.truncate(entry.size as usize);
+        Ok(Some(data))
+    }
+
+    // Recursively extracts the children of a directory in the FST.
+    fn extract_dir(&self, file: &mut File, index: u16, dest: &Path, nand_path: &str, visited: &mut Vec<bool>,
+                   report: &mut ExtractReport) -> Result<(), NandBackupError> {
+        let mut child = self.fst[index as usize].sub;
+        while child != FST_NONE && (child as usize) < self.fst.len() && !visited[child as usize] {
+            visited[child as usize] = true;
+            let entry = &self.fst[child as usize];
+            let path = format!("{}/{}", nand_path, entry.name);
+            // Names that could escape the output directory can't have come from a valid NAND.
+            if entry.name.is_empty() || entry.name == "." || entry.name == ".." || entry.name.contains(['/', '\\']) {
+                report.damaged_files.push(path);
+            } else if entry.is_dir() {
+                if nand_path.is_empty() && SKIPPED_DIRS.contains(&entry.name.as_str()) {
+                    report.skipped_dirs.push(path);
+                } else {
+                    let dir = dest.join(&entry.name);
+                    if !dir.exists() {
+                        fs::create_dir(&dir)?;
+                    }
+                    self.extract_dir(file, child, &dir, &path, visited, report)?;
+                }
+            } else if entry.is_file() {
+                match self.read_file(file, entry, report)? {
+                    Some(data) => {
+                        fs::write(dest.join(&entry.name), data)?;
+                        report.extracted_files += 1;
+                    },
+                    None => report.damaged_files.push(path),
+                }
+            }
+            child = entry.sib;
+        }
+        Ok(())
+    }
+
+    /// Extracts the entire filesystem of the NAND backup to the destination directory, producing
+    /// the directory layout used by EmuNANDs. The /import/ and /tmp/ directories are skipped, as
+    /// they only hold temporary data. Files with uncorrectable errors are reported and not written,
+    /// rather than being written with corrupt data.
+    pub fn extract_all(&self, dest: &Path) -> Result<ExtractReport, NandBackupError> {
+        let mut report = ExtractReport::default();
+        if !dest.exists() {
+            fs::create_dir_all(dest)?;
+        }
+        let mut file = File::open(&self.nand_path)?;
+        let mut visited = vec![false; self.fst.len()];
+        visited[0] = true;
+        self.extract_dir(&mut file, 0, dest, "", &mut visited, &mut report)?;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use aes::cipher::BlockEncryptMut;
+
+    const TEST_NAND_KEY: [u8; 16] = [0x42; 16];
+
+    struct TestNand {
+        file: File,
+        fst: Vec<u8>,
+        fat: Vec<u16>,
+        next_cluster: u16,
+    }
+
+    impl TestNand {
+        fn new(path: &Path) -> Self {
+            let file = fs::OpenOptions::new().read(true).write(true).create(true).truncate(true).open(path).unwrap();
+            file.set_len(NAND_SIZE_ECC).unwrap();
+            let mut nand = TestNand { file, fst: vec![0u8; FST_ENTRY_COUNT * FST_ENTRY_SIZE], fat: vec![0xFFFE; CLUSTER_COUNT], next_cluster: 0x40 };
+            nand.set_entry(0, "/", 2, FST_NONE, FST_NONE, 0);
+            nand
+        }
+
+        fn set_entry(&mut self, index: usize, name: &str, mode: u8, sub: u16, sib: u16, size: u32) {
+            let entry = &mut self.fst[index * FST_ENTRY_SIZE..(index + 1) * FST_ENTRY_SIZE];
+            entry.fill(0);
+            entry[..name.len()].copy_from_slice(name.as_bytes());
+            entry[12] = mode;
+            entry[14..16].copy_from_slice(&sub.to_be_bytes());
+            entry[16..18].copy_from_slice(&sib.to_be_bytes());
+            entry[18..22].copy_from_slice(&size.to_be_bytes());
+        }
+
+        fn write_raw_cluster(&mut self, cluster: usize, data: &[u8]) {
+            for (i, page) in data.chunks(PAGE_SIZE).enumerate() {
+                let mut spare = [0u8; SPARE_SIZE];
+                spare[0] = 0xFF;
+                for (j, subpage) in page.chunks(512).enumerate() {
+                    spare[0x30 + j * 4..0x34 + j * 4].copy_from_slice(&calc_ecc(subpage));
+                }
+                self.file.seek(SeekFrom::Start(((cluster * PAGES_PER_CLUSTER + i) * (PAGE_SIZE + SPARE_SIZE)) as u64)).unwrap();
+                self.file.write_all(page).unwrap();
+                self.file.write_all(&spare).unwrap();
+            }
+        }
+
+        // Writes encrypted file data to new clusters, and returns the first cluster.
+        fn write_file_data(&mut self, data: &[u8]) -> u16 {
+            type Aes128CbcEnc = cbc::Encryptor<aes::Aes128>;
+            let first = self.next_cluster;
+            let chunks: Vec<&[u8]> = data.chunks(CLUSTER_SIZE).collect();
+            for (i, chunk) in chunks.iter().enumerate() {
+                let cluster = self.next_cluster;
+                let mut buf = chunk.to_vec();
+                buf.resize(CLUSTER_SIZE, 0);
+                let encryptor = Aes128CbcEnc::new(&TEST_NAND_KEY.into(), &[0u8; 16].into());
+                encryptor.encrypt_padded_mut::<NoPadding>(&mut buf, CLUSTER_SIZE).unwrap();
+                self.write_raw_cluster(cluster as usize, &buf);
+                self.next_cluster += 1;
+                self.fat[cluster as usize] = if i == chunks.len() - 1 { FAT_LAST_CLUSTER } else { self.next_cluster };
+            }
+            first
+        }
+
+        fn write_superblock(&mut self, index: usize, generation: u32) {
+            let mut superblock = vec![0u8; CLUSTER_SIZE * SUPERBLOCK_CLUSTERS];
+            superblock[0..4].copy_from_slice(b"SFFS");
+            superblock[4..8].copy_from_slice(&generation.to_be_bytes());
+            for (i, entry) in self.fat.iter().enumerate() {
+                superblock[FAT_OFFSET + i * 2..FAT_OFFSET + i * 2 + 2].copy_from_slice(&entry.to_be_bytes());
+            }
+            superblock[FST_OFFSET..FST_OFFSET + self.fst.len()].copy_from_slice(&self.fst);
+            let start = SUPERBLOCK_START + index * SUPERBLOCK_CLUSTERS;
+            for (i, cluster) in superblock.chunks(CLUSTER_SIZE).enumerate() {
+                self.write_raw_cluster(start + i, cluster);
+            }
+        }
+
+        // Flips bits in the stored data of a cluster without updating its ECC data.
+        fn flip_bits(&mut self, cluster: u16, offsets: &[usize]) {
+            for offset in offsets {
+                let page = offset / PAGE_SIZE;
+                let pos = ((cluster as usize * PAGES_PER_CLUSTER + page) * (PAGE_SIZE + SPARE_SIZE) + offset % PAGE_SIZE) as u64;
+                let mut byte = [0u8; 1];
+                self.file.seek(SeekFrom::Start(pos)).unwrap();
+                self.file.read_exact(&mut byte).unwrap();
+                byte[0] ^= 0x04;
+                self.file.seek(SeekFrom::Start(pos)).unwrap();
+                self.file.write_all(&byte).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_ecc() {
+        let mut data: Vec<u8> = (0..512).map(|i| (i * 7 % 256) as u8).collect();
+        let mut spare = [0xAAu8; SPARE_SIZE];
+        spare[0x30..0x34].copy_from_slice(&calc_ecc(&data));
+        let original = data.clone();
+        assert_eq!(check_page_ecc(&mut data, &spare), EccStatus::Ok);
+        data[300] ^= 0x20;
+        assert_eq!(check_page_ecc(&mut data, &spare), EccStatus::Corrected);
+        assert_eq!(data, original);
+        data[10] ^= 0x01;
+        data[20] ^= 0x01;
+        assert_eq!(check_page_ecc(&mut data, &spare), EccStatus::Uncorrectable);
+        assert_eq!(check_page_ecc(&mut data, &[0xFF; SPARE_SIZE]), EccStatus::Ok);
+    }
+
+    #[test]
+    fn test_extract_all() {
+        let dir = tempfile::tempdir().unwrap();
+        let nand_path = dir.path().join("nand.bin");
+        let mut nand = TestNand::new(&nand_path);
+        let big_file: Vec<u8> = (0..CLUSTER_SIZE + 100).map(|i| (i % 251) as u8).collect();
+        let big_cluster = nand.write_file_data(&big_file);
+        let corrected_cluster = nand.write_file_data(b"corrected");
+        let damaged_cluster = nand.write_file_data(b"damaged");
+        let tmp_cluster = nand.write_file_data(b"temporary");
+        // Root: /title, /sys, /tmp
+        nand.set_entry(0, "/", 2, 1, FST_NONE, 0);
+        nand.set_entry(1, "title", 2, 4, 2, 0);
+        nand.set_entry(2, "sys", 2, 6, 3, 0);
+        nand.set_entry(3, "tmp", 2, 8, FST_NONE, 0);
+        nand.set_entry(4, "00000001", 2, 5, FST_NONE, 0);
+        nand.set_entry(5, "big.bin", 1, big_cluster, FST_NONE, big_file.len() as u32);
+        nand.set_entry(6, "uid.sys", 1, corrected_cluster, 7, 9);
+        nand.set_entry(7, "bad.bin", 1, damaged_cluster, FST_NONE, 7);
+        nand.set_entry(8, "temp.bin", 1, tmp_cluster, FST_NONE, 9);
+        nand.write_superblock(3, 5);
+        // An older superblock with a different layout should be ignored.
+        nand.set_entry(0, "/", 2, FST_NONE, FST_NONE, 0);
+        nand.write_superblock(7, 4);
+        nand.flip_bits(corrected_cluster, &[100]);
+        nand.flip_bits(damaged_cluster, &[100, 200]);
+
+        let mut keys = vec![0u8; 0x400];
+        keys[KEYS_NAND_KEY_OFFSET..KEYS_NAND_KEY_OFFSET + 16].copy_from_slice(&TEST_NAND_KEY);
+        let backup = NandBackup::open(&nand_path, &keys).unwrap();
+        let out = dir.path().join("out");
+        let report = backup.extract_all(&out).unwrap();
+        assert_eq!(report.extracted_files, 2);
+        assert_eq!(report.corrected_clusters, 1);
+        assert_eq!(report.damaged_files, vec![String::from("/sys/bad.bin")]);
+        assert_eq!(report.skipped_dirs, vec![String::from("/tmp")]);
+        assert_eq!(fs::read(out.join("title").join("00000001").join("big.bin")).unwrap(), big_file);
+        assert_eq!(fs::read(out.join("sys").join("uid.sys")).unwrap(), b"corrected");
+        assert!(!out.join("sys").join("bad.bin").exists());
+        assert!(!out.join("tmp").exists());
+
+        assert!(matches!(NandBackup::open(&nand_path, &keys[..0x100]), Err(NandBackupError::InvalidKeys)));
+        fs::write(dir.path().join("small.bin"), [0u8; 16]).unwrap();
+        assert!(matches!(NandBackup::open(&dir.path().join("small.bin"), &keys), Err(NandBackupError::InvalidSize(16))));
+    }
+}
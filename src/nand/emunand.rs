@@ -5,17 +5,28 @@
 
 use std::fs;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use glob::glob;
 use thiserror::Error;
 use crate::nand::sys;
 use crate::title;
-use crate::title::{cert, content, ticket, tmd};
+use crate::title::{cert, content, ticket, tmd, TitleId};
+#[cfg(feature = "serde")]
+use crate::title::manifest;
+use crate::PROGRESS_LOG_TARGET;
 
 #[derive(Debug, Error)]
 pub enum EmuNANDError {
     #[error("the specified title is not installed to the EmuNAND")]
     TitleNotInstalled,
+    #[error("no Ticket for the specified title is installed to the EmuNAND")]
+    TicketNotInstalled,
+    #[error("a Ticket for {0} is already installed to the EmuNAND")]
+    TicketAlreadyInstalled(TitleId),
+    #[error("the title directory does not contain a Ticket, and no Ticket for {0} is installed to the EmuNAND")]
+    MissingTicket(TitleId),
+    #[error("the title directory does not contain a certificate chain, and the EmuNAND does not have a valid cert.sys")]
+    MissingCertChain,
     #[error("EmuNAND requires the directory `{0}`, but a file with that name already exists")]
     DirectoryNameConflict(String),
     #[error("specified EmuNAND root does not exist")]
@@ -30,6 +41,15 @@ pub enum EmuNANDError {
     Ticket(#[from] ticket::TicketError),
     #[error("content processing error")]
     Content(#[from] content::ContentError),
+    #[error("title processing error")]
+    Title(#[from] title::TitleError),
+    #[cfg(feature = "serde")]
+    #[error("title manifest processing error")]
+    Manifest(#[from] manifest::ManifestError),
+    #[error("the title requires {ios_tid}, which is not installed to the EmuNAND")]
+    MissingDependency { ios_tid: TitleId },
+    #[error("staged installation could not be moved into place at `{0}`")]
+    StageCommitFailed(String, #[source] std::io::Error),
     #[error("io error occurred during EmuNAND operation")]
     IO(#[from] std::io::Error),
 }
@@ -43,6 +63,116 @@ pub struct InstalledTitles {
     pub titles: Vec<String>,
 }
 
+#[derive(Debug, Default, Clone)]
+/// Options that control how a title is installed to an EmuNAND.
+pub struct InstallOptions {
+    /// Install the content at index 0 as title.met, instead of any meta/footer data included in
+    /// the title.
+    pub override_meta: bool,
+    /// Check that the IOS required by the title is installed before installing it.
+    pub check_dependencies: bool,
+    /// Fail the installation if a dependency is missing, instead of returning a warning.
+    pub strict_dependencies: bool,
+    /// The minimum version of the required IOS that counts as installed, if any.
+    pub min_ios_version: Option<u16>,
+}
+
+#[derive(Debug, Default, Clone)]
+/// Options that control how a new EmuNAND is initialized.
+pub struct InitOptions {
+    /// The certificate chain to write to /sys/cert.sys. No cert.sys will be written if this is
+    /// None.
+    pub cert_chain: Option<cert::CertificateChain>,
+}
+
+// The directories, relative to the EmuNAND root, that make up the standard layout of a NAND.
+const INIT_DIRS: &[&str] = &[
+    "import",
+    "meta",
+    "shared1",
+    "shared2",
+    "shared2/sys",
+    "sys",
+    "ticket",
+    "ticket/00000001",
+    "ticket/00010001",
+    "ticket/00010002",
+    "title",
+    "title/00000001",
+    "title/00010001",
+    "title/00010002",
+    "tmp",
+    "wfs",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A problem found while installing a title to an EmuNAND that didn't stop the installation.
+pub enum InstallWarning {
+    /// The IOS required by the title isn't installed, or is older than the required minimum.
+    MissingDependency { ios_tid: TitleId },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A file that installing a title to an EmuNAND will write.
+pub struct PlannedFile {
+    /// The final path of the file in the EmuNAND.
+    pub path: PathBuf,
+    /// The size of the file in bytes.
+    pub size: u64,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+/// Everything that installing a title to an EmuNAND will do, worked out without writing anything.
+/// See EmuNAND::plan_install().
+pub struct InstallPlan {
+    /// Every file that the installation will create or replace, in the order they're written.
+    pub files: Vec<PlannedFile>,
+    /// Any warnings that the installation will produce.
+    pub warnings: Vec<InstallWarning>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+/// The amount of space that installing a title to an EmuNAND will use.
+pub struct InstallSize {
+    /// The number of bytes that the installation will add, including the TMD, Ticket, and footer.
+    pub bytes: u64,
+    /// The number of blocks that the installation will add. Like the System Menu, each content is
+    /// rounded up to a whole number of blocks individually.
+    pub blocks: u64,
+    /// The number of bytes of shared content that the title uses but is already installed, and so
+    /// won't be installed again.
+    pub shared_bytes: u64,
+    /// The number of blocks of shared content that's already installed.
+    pub shared_blocks: u64,
+}
+
+#[derive(Debug, Default)]
+/// A structure that represents the results of auditing an EmuNAND's shared content.
+pub struct SharedContentReport {
+    /// Entries in content.map whose content isn't used by any installed title, as pairs of file
+    /// names (without the .app extension) and content hashes.
+    pub orphaned_entries: Vec<(String, [u8; 20])>,
+    /// File names (without the .app extension) of entries in content.map whose files are missing.
+    pub missing_files: Vec<String>,
+    /// File names of .app files in /shared1/ that aren't tracked by content.map.
+    pub untracked_files: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+/// A structure that represents the results of repairing an EmuNAND's content.map and uid.sys.
+pub struct RepairReport {
+    /// The number of bytes of partial entries discarded from the end of content.map.
+    pub content_map_discarded_bytes: usize,
+    /// Entries removed from content.map because they repeated an earlier entry's hash or name.
+    pub content_map_removed: Vec<content::ContentMapEntry>,
+    /// The number of bytes of partial entries discarded from the end of uid.sys.
+    pub uid_sys_discarded_bytes: usize,
+    /// Entries removed from uid.sys because they repeated an earlier entry's Title ID.
+    pub uid_sys_removed: Vec<sys::UidSysEntry>,
+    /// The results of cleaning up shared content after content.map was repaired.
+    pub shared_content: SharedContentReport,
+}
+
 fn safe_create_dir(dir: &PathBuf) -> Result<(), EmuNANDError> {
     if !dir.exists() {
         fs::create_dir(dir)?;
@@ -52,6 +182,199 @@ fn safe_create_dir(dir: &PathBuf) -> Result<(), EmuNANDError> {
     Ok(())
 }
 
+#[derive(Debug, Clone, Default)]
+/// The directories used when searching for Dolphin's NAND. These are normally read from the
+/// environment with from_env(), but can be set manually to search somewhere else.
+pub struct DolphinSearchPaths {
+    /// The operating system to search the standard locations of, as reported by
+    /// std::env::consts::OS.
+    pub os: String,
+    /// A Dolphin user directory set explicitly with DOLPHIN_EMU_USERPATH, which is how portable
+    /// setups are usually found.
+    pub user_path: Option<PathBuf>,
+    /// The user's home directory.
+    pub home: Option<PathBuf>,
+    /// The roaming AppData directory on Windows.
+    pub app_data: Option<PathBuf>,
+    /// The XDG data directory on Linux, if it's been changed from the default.
+    pub xdg_data_home: Option<PathBuf>,
+    /// The XDG config directory on Linux, if it's been changed from the default.
+    pub xdg_config_home: Option<PathBuf>,
+}
+
+impl DolphinSearchPaths {
+    /// Creates a new DolphinSearchPaths instance from the current environment.
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+        DolphinSearchPaths {
+            os: std::env::consts::OS.to_owned(),
+            user_path: var("DOLPHIN_EMU_USERPATH"),
+            home: var("HOME").or_else(|| var("USERPROFILE")),
+            app_data: var("APPDATA"),
+            xdg_data_home: var("XDG_DATA_HOME"),
+            xdg_config_home: var("XDG_CONFIG_HOME"),
+        }
+    }
+}
+
+// Gets every Dolphin user directory that could exist, in the order that Dolphin itself would pick
+// them, paired with the path of the Dolphin.ini file that belongs to it.
+fn dolphin_user_dirs(paths: &DolphinSearchPaths) -> Vec<(PathBuf, PathBuf)> {
+    let mut user_dirs: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let with_config = |dir: PathBuf| {
+        let config = dir.join("Config").join("Dolphin.ini");
+        (dir, config)
+    };
+    if let Some(user_path) = &paths.user_path {
+        user_dirs.push(with_config(user_path.clone()));
+    }
+    match paths.os.as_str() {
+        "windows" => {
+            if let Some(app_data) = &paths.app_data {
+                user_dirs.push(with_config(app_data.join("Dolphin Emulator")));
+            }
+            if let Some(home) = &paths.home {
+                user_dirs.push(with_config(home.join("Documents").join("Dolphin Emulator")));
+            }
+        },
+        "macos" => {
+            if let Some(home) = &paths.home {
+                user_dirs.push(with_config(home.join("Library").join("Application Support").join("Dolphin")));
+            }
+        },
+        _ => {
+            if let Some(home) = &paths.home {
+                user_dirs.push(with_config(home.join(".dolphin-emu")));
+            }
+            let data_home = paths.xdg_data_home.clone().or_else(|| paths.home.as_ref().map(|home| home.join(".local").join("share")));
+            let config_home = paths.xdg_config_home.clone().or_else(|| paths.home.as_ref().map(|home| home.join(".config")));
+            if let (Some(data_home), Some(config_home)) = (data_home, config_home) {
+                user_dirs.push((data_home.join("dolphin-emu"), config_home.join("dolphin-emu").join("Dolphin.ini")));
+            }
+            if let Some(home) = &paths.home {
+                let flatpak = home.join(".var").join("app").join("org.DolphinEmu.dolphin-emu");
+                user_dirs.push((flatpak.join("data").join("dolphin-emu"), flatpak.join("config").join("dolphin-emu").join("Dolphin.ini")));
+            }
+        },
+    }
+    user_dirs
+}
+
+// Reads a custom NAND root out of a Dolphin.ini file, if the file exists and sets one.
+fn read_dolphin_nand_root(config_path: &Path) -> Option<PathBuf> {
+    let config = fs::read_to_string(config_path).ok()?;
+    config.lines()
+        .filter_map(|line| line.split_once('='))
+        .find(|(key, _)| key.trim() == "NANDRootPath")
+        .map(|(_, value)| value.trim())
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Gets every location that Dolphin's NAND could be found at, in the order that they should be
+/// checked. Custom NAND roots set in Dolphin's config take priority over the default Wii directory
+/// of the same user directory.
+pub fn dolphin_nand_candidates(paths: &DolphinSearchPaths) -> Vec<PathBuf> {
+    let mut candidates: Vec<PathBuf> = Vec::new();
+    for (user_dir, config_path) in dolphin_user_dirs(paths) {
+        if let Some(nand_root) = read_dolphin_nand_root(&config_path) {
+            candidates.push(nand_root);
+        }
+        candidates.push(user_dir.join("Wii"));
+    }
+    candidates.dedup();
+    candidates
+}
+
+/// Searches for Dolphin's NAND using the provided search paths. Returns the first candidate
+/// location that exists, or None if Dolphin's NAND couldn't be found.
+pub fn locate_dolphin_nand_in(paths: &DolphinSearchPaths) -> Option<PathBuf> {
+    dolphin_nand_candidates(paths).into_iter().find(|candidate| candidate.is_dir())
+}
+
+/// Searches for Dolphin's NAND in the standard locations for the current OS, including any custom
+/// NAND root set in Dolphin's config. Returns None if Dolphin's NAND couldn't be found.
+pub fn locate_dolphin_nand() -> Option<PathBuf> {
+    locate_dolphin_nand_in(&DolphinSearchPaths::from_env())
+}
+
+// Moves a file or directory from the staging area into its final location.
+fn commit_staged_file(staged: &Path, target: &Path) -> Result<(), EmuNANDError> {
+    fs::rename(staged, target).map_err(|err| EmuNANDError::StageCommitFailed(target.display().to_string(), err))
+}
+
+// Tracks the files moved into place while committing a staged title, so that they can all be put
+// back if a later step fails. Anything that a staged file replaces is moved into the backup
+// directory first rather than being overwritten.
+struct StagedCommit {
+    backup_dir: PathBuf,
+    committed: Vec<(PathBuf, Option<PathBuf>)>,
+}
+
+impl StagedCommit {
+    fn new(backup_dir: PathBuf) -> Result<Self, EmuNANDError> {
+        fs::create_dir(&backup_dir)?;
+        Ok(StagedCommit { backup_dir, committed: Vec::new() })
+    }
+
+    // Moves a staged file or directory into place, backing up whatever it replaces.
+    fn commit(&mut self, staged: &Path, target: &Path) -> Result<(), EmuNANDError> {
+        let backup = if target.exists() {
+            let backup = self.backup_dir.join(self.committed.len().to_string());
+            commit_staged_file(target, &backup)?;
+            Some(backup)
+        } else {
+            None
+        };
+        if let Err(err) = commit_staged_file(staged, target) {
+            if let Some(backup) = backup {
+                restore_backup(&backup, target);
+            }
+            return Err(err);
+        }
+        self.committed.push((target.to_path_buf(), backup));
+        Ok(())
+    }
+
+    // Undoes every move made so far, newest first, removing new files and restoring the ones that
+    // they replaced.
+    fn roll_back(self) {
+        for (target, backup) in self.committed.into_iter().rev() {
+            let removed = if target.is_dir() { fs::remove_dir_all(&target) } else { fs::remove_file(&target) };
+            if let Err(err) = removed {
+                log::warn!("could not remove \"{}\" while rolling back a failed install: {}", target.display(), err);
+            }
+            if let Some(backup) = backup {
+                restore_backup(&backup, &target);
+            }
+        }
+    }
+}
+
+// Moves a backed up file or directory back to where it came from. Failing to do so can't be
+// recovered from, but it shouldn't hide the error that caused the rollback either, so it's only
+// logged.
+fn restore_backup(backup: &Path, target: &Path) {
+    if let Err(err) = fs::rename(backup, target) {
+        log::warn!("could not restore \"{}\" while rolling back a failed install: {}", target.display(), err);
+    }
+}
+
+// Gets whether a Title ID belongs to DLC, which ES installs a little differently from other titles.
+fn is_dlc(tid: TitleId) -> bool {
+    matches!(tid.title_type(), Some(tmd::TitleType::DLC))
+}
+
+// Gets the content records of a title that installing it will actually install, paired with their
+// positions. Contents that aren't present are always skipped, and DLC also skips contents that its
+// Ticket doesn't permit, since DLC is often only partially purchased.
+fn installable_records(title: &title::Title) -> Vec<(usize, tmd::ContentRecord)> {
+    let is_dlc = is_dlc(title.tmd.title_id());
+    title.iter_by_position()
+        .filter(|(i, record)| title.content.is_content_present(*i) && (!is_dlc || title.ticket.is_content_permitted(record.index)))
+        .collect()
+}
+
 /// An EmuNAND object that allows for creating and modifying Wii EmuNANDs.
 pub struct EmuNAND {
     emunand_dirs: HashMap<String, PathBuf>,
@@ -62,6 +385,19 @@ impl EmuNAND {
     /// will initialize the basic directory structure if it doesn't already exist, but will not do
     /// anything beyond that.
     pub fn open(emunand_root: PathBuf) -> Result<Self, EmuNANDError> {
+        let emunand = EmuNAND::open_read_only(emunand_root)?;
+        for dir in emunand.emunand_dirs.values() {
+            if !dir.exists() {
+                fs::create_dir(dir)?;
+            }
+        }
+        Ok(emunand)
+    }
+
+    /// Open an existing EmuNAND like EmuNAND::open(), but without creating any missing directories.
+    /// This is used to plan changes to an EmuNAND without modifying it, and methods that write to
+    /// the EmuNAND may fail if the directories they need don't exist.
+    pub fn open_read_only(emunand_root: PathBuf) -> Result<Self, EmuNANDError> {
         if !emunand_root.exists() {
             return Err(EmuNANDError::RootNotFound);
         }
@@ -76,11 +412,9 @@ impl EmuNAND {
         emunand_dirs.insert(String::from("title"), emunand_root.join("title"));
         emunand_dirs.insert(String::from("tmp"), emunand_root.join("tmp"));
         emunand_dirs.insert(String::from("wfs"), emunand_root.join("wfs"));
-        for dir in emunand_dirs.keys() {
-            if !emunand_dirs[dir].exists() {
-                fs::create_dir(&emunand_dirs[dir])?;
-            } else if !emunand_dirs[dir].is_dir() {
-                return Err(EmuNANDError::DirectoryNameConflict(emunand_dirs[dir].to_str().unwrap().to_string()));
+        for dir in emunand_dirs.values() {
+            if dir.exists() && !dir.is_dir() {
+                return Err(EmuNANDError::DirectoryNameConflict(dir.to_str().unwrap().to_string()));
             }
         }
         Ok(EmuNAND {
@@ -88,6 +422,32 @@ impl EmuNAND {
         })
     }
     
+    /// Initialize a new EmuNAND with the full standard NAND layout, creating the root directory if
+    /// it doesn't already exist. This writes an empty uid.sys and content.map, and writes cert.sys
+    /// if a certificate chain was provided. Existing files are never overwritten, so initializing
+    /// an existing EmuNAND will only fill in whatever is missing.
+    pub fn init(root: PathBuf, options: InitOptions) -> Result<Self, EmuNANDError> {
+        if !root.exists() {
+            fs::create_dir_all(&root)?;
+        }
+        for dir in INIT_DIRS {
+            safe_create_dir(&root.join(dir))?;
+        }
+        let uid_sys_path = root.join("sys").join("uid.sys");
+        if !uid_sys_path.exists() {
+            fs::write(&uid_sys_path, [])?;
+        }
+        let content_map_path = root.join("shared1").join("content.map");
+        if !content_map_path.exists() {
+            fs::write(&content_map_path, [])?;
+        }
+        let cert_sys_path = root.join("sys").join("cert.sys");
+        if let Some(cert_chain) = options.cert_chain && !cert_sys_path.exists() {
+            fs::write(&cert_sys_path, cert_chain.to_bytes()?)?;
+        }
+        EmuNAND::open(root)
+    }
+
     /// Gets the path to a directory in the root of an EmuNAND, if it's a valid directory.
     pub fn get_emunand_dir(&self, dir: &str) -> Option<&PathBuf> {
         self.emunand_dirs.get(dir)
@@ -120,30 +480,65 @@ impl EmuNAND {
         installed_titles
     }
     
+    // Gets the path that the Ticket for a title is installed to, /ticket/<tid_high>/<tid_low>.tik.
+    fn ticket_path(&self, tid: TitleId) -> PathBuf {
+        self.emunand_dirs["ticket"]
+            .join(hex::encode(tid.high()))
+            .join(format!("{}.tik", hex::encode(tid.low())))
+    }
+
+    // Creates the directory that the Ticket for a title is installed to if it doesn't already
+    // exist, and returns the path the Ticket should be written to.
+    fn create_ticket_dir(&self, tid: TitleId) -> Result<PathBuf, EmuNANDError> {
+        let ticket_path = self.ticket_path(tid);
+        safe_create_dir(&ticket_path.parent().unwrap().to_path_buf())?;
+        Ok(ticket_path)
+    }
+
     /// Get the Ticket for a title installed to an EmuNAND. Returns a Ticket instance if a Ticket
     /// with the specified Title ID can be found, or None if not.
-    pub fn get_title_ticket(&self, tid: [u8; 8]) -> Option<ticket::Ticket> {
-        let ticket_path = self.emunand_dirs["title"]
-            .join(hex::encode(&tid[0..4]))
-            .join(format!("{}.tik", hex::encode(&tid[4..8])));
-        if ticket_path.exists() {
-            match fs::read(&ticket_path) {
-                Ok(content) => {
-                    ticket::Ticket::from_bytes(&content).ok()
-                },
-                Err(_) => None,
-            }
-        } else {
-            None
+    pub fn get_ticket(&self, tid: TitleId) -> Option<ticket::Ticket> {
+        let content = fs::read(self.ticket_path(tid)).ok()?;
+        ticket::Ticket::from_bytes(&content).ok()
+    }
+
+    /// Get the Ticket for a title installed to an EmuNAND. This is the same as
+    /// EmuNAND::get_ticket().
+    pub fn get_title_ticket(&self, tid: TitleId) -> Option<ticket::Ticket> {
+        self.get_ticket(tid)
+    }
+
+    /// Install only a Ticket to an EmuNAND, without installing a TMD or any content. This is
+    /// useful for titles whose content is installed separately, like disc-based DLC. If a Ticket
+    /// for the same title is already installed, it will only be replaced if overwrite is true.
+    pub fn install_ticket(&self, ticket: &ticket::Ticket, overwrite: bool) -> Result<(), EmuNANDError> {
+        let tid = ticket.title_id();
+        if !overwrite && self.ticket_path(tid).exists() {
+            return Err(EmuNANDError::TicketAlreadyInstalled(tid));
+        }
+        let ticket_path = self.create_ticket_dir(tid)?;
+        log::info!(target: PROGRESS_LOG_TARGET, "installing Ticket for title {}", tid);
+        fs::write(ticket_path, ticket.to_bytes()?)?;
+        Ok(())
+    }
+
+    /// Remove the Ticket for a title from an EmuNAND. The title itself is left installed, if it
+    /// is.
+    pub fn remove_ticket(&self, tid: TitleId) -> Result<(), EmuNANDError> {
+        let ticket_path = self.ticket_path(tid);
+        if !ticket_path.exists() {
+            return Err(EmuNANDError::TicketNotInstalled);
         }
+        fs::remove_file(ticket_path)?;
+        Ok(())
     }
 
     /// Get the TMD for a title installed to an EmuNAND. Returns a Ticket instance if a TMD with the
     /// specified Title ID can be found, or None if not.
-    pub fn get_title_tmd(&self, tid: [u8; 8]) -> Option<tmd::TMD> {
+    pub fn get_title_tmd(&self, tid: TitleId) -> Option<tmd::TMD> {
         let tmd_path = self.emunand_dirs["title"]
-            .join(hex::encode(&tid[0..4]))
-            .join(hex::encode(&tid[4..8]).to_ascii_lowercase())
+            .join(hex::encode(tid.high()))
+            .join(hex::encode(tid.low()).to_ascii_lowercase())
             .join("content")
             .join("title.tmd");
         if tmd_path.exists() {
@@ -158,36 +553,252 @@ impl EmuNAND {
         }
     }
     
-    /// Install the provided title to an EmuNAND, mimicking a WAD installation performed by ES. The 
-    /// "override meta" option will install the content at index 0 as title.met, instead of any 
-    /// actual meta/footer data contained in the title.
-    pub fn install_title(&self, title: title::Title, override_meta: bool) -> Result<(), EmuNANDError> {
-        // Save the two halves of the TID, since those are part of the installation path.
-        let tid_high = hex::encode(&title.tmd.title_id()[0..4]);
-        let tid_low = hex::encode(&title.tmd.title_id()[4..8]);
-        // Tickets are installed to /ticket/<tid_high>/<tid_low>.tik.
-        let ticket_dir = self.emunand_dirs["ticket"].join(&tid_high);
-        safe_create_dir(&ticket_dir)?;
-        fs::write(ticket_dir.join(format!("{}.tik", &tid_low)), title.ticket.to_bytes()?)?;
-        // TMDs and normal content (non-shared) are installed to 
-        // /title/<tid_high>/<tid_low>/content/, as title.tmd and <cid>.app.
-        let mut title_dir = self.emunand_dirs["title"].join(&tid_high);
-        safe_create_dir(&title_dir)?;
-        title_dir = title_dir.join(&tid_low);
-        safe_create_dir(&title_dir)?;
-        // Create an empty "data" dir if it doesn't exist.
-        safe_create_dir(&title_dir.join("data"))?;
-        title_dir = title_dir.join("content");
-        // Delete any existing installed content/the current TMD.
-        if title_dir.exists() {
-            fs::remove_dir_all(&title_dir)?;
-        }
-        fs::create_dir(&title_dir)?;
-        fs::write(title_dir.join("title.tmd"), title.tmd.to_bytes()?)?;
-        for i in 0..title.content.content_records.borrow().len() {
-            if matches!(title.content.content_records.borrow()[i].content_type, tmd::ContentType::Normal) {
-                let content_path = title_dir.join(format!("{:08X}.app", title.content.content_records.borrow()[i].content_id).to_ascii_lowercase());
-                fs::write(content_path, title.get_content_by_index(i)?)?;
+    /// Counts how many of the contents listed in the TMD of a title installed to an EmuNAND are
+    /// actually installed, returning the number installed and the total number of contents. Normal
+    /// content is installed if its file exists in the title's content directory, and shared content
+    /// is installed if it's listed in /shared1/content.map. This is mostly useful for DLC, which is
+    /// often only partially installed. Returns None if the title isn't installed.
+    pub fn installed_content_count(&self, tid: TitleId) -> Option<(usize, usize)> {
+        let tmd = self.get_title_tmd(tid)?;
+        let content_dir = self.emunand_dirs["title"]
+            .join(hex::encode(tid.high()))
+            .join(hex::encode(tid.low()).to_ascii_lowercase())
+            .join("content");
+        let content_map = fs::read(self.emunand_dirs["shared1"].join("content.map")).ok()
+            .and_then(|data| content::SharedContentMap::from_bytes(&data).ok())
+            .unwrap_or_default();
+        let records = tmd.content_records.read();
+        let installed = records.iter().filter(|record| match record.content_type {
+            tmd::ContentType::Shared => content_map.find_by_hash(&record.content_hash).is_some(),
+            _ => content_dir.join(format!("{:08x}.app", record.content_id)).exists(),
+        }).count();
+        Some((installed, records.len()))
+    }
+
+    /// Writes the provided certificate chain to /sys/cert.sys, replacing any existing cert.sys.
+    /// cert.sys stores the CA, TMD (CP), and Ticket (XS) certificates in the same order as a
+    /// certificate chain does.
+    pub fn write_cert_sys(&self, cert_chain: &cert::CertificateChain) -> Result<(), EmuNANDError> {
+        fs::write(self.emunand_dirs["sys"].join("cert.sys"), cert_chain.to_bytes()?)?;
+        Ok(())
+    }
+
+    // Checks whether /sys/cert.sys exists and contains a valid certificate chain.
+    fn has_valid_cert_sys(&self) -> bool {
+        match fs::read(self.emunand_dirs["sys"].join("cert.sys")) {
+            Ok(data) => cert::CertificateChain::from_bytes(&data).is_ok(),
+            Err(_) => false,
+        }
+    }
+
+    /// Install the provided title to an EmuNAND, mimicking a WAD installation performed by ES. See
+    /// InstallOptions for the available options. Returns any warnings produced by the
+    /// installation, which are only possible when dependencies are checked non-strictly.
+    ///
+    /// Like ES, everything is first written to a staging area in /import/, and only moved into
+    /// place once every file has been written successfully, so a failed installation leaves any
+    /// existing installation of the title untouched. The staging area is always cleaned up.
+    pub fn install_title(&self, title: title::Title, options: &InstallOptions) -> Result<Vec<InstallWarning>, EmuNANDError> {
+        let warnings = self.check_dependencies(&title.tmd, options)?;
+        let stage_dir = self.emunand_dirs["import"].join(title.tmd.title_id().to_string().to_ascii_lowercase());
+        if stage_dir.exists() {
+            fs::remove_dir_all(&stage_dir)?;
+        }
+        fs::create_dir(&stage_dir)?;
+        log::info!(target: PROGRESS_LOG_TARGET, "installing title {}", title.tmd.title_id());
+        let result = self.stage_title(&title, options.override_meta, &stage_dir)
+            .and_then(|shared_files| {
+                log::info!(target: PROGRESS_LOG_TARGET, "moving staged files for title {} into place", title.tmd.title_id());
+                self.commit_staged_title(&title, &shared_files, &stage_dir)
+            });
+        // Clean up the staging area regardless of whether the installation succeeded, but make sure
+        // that an installation error takes priority over a cleanup error.
+        let cleanup = fs::remove_dir_all(&stage_dir);
+        result?;
+        cleanup?;
+        Ok(warnings)
+    }
+
+    /// Install a title from a directory containing its unpacked components to an EmuNAND, like
+    /// EmuNAND::install_title(). The directory can either be one written by Title::to_directory(),
+    /// or a title downloaded from the NUS with a manifest, and contents can be either encrypted or
+    /// decrypted.
+    ///
+    /// If the directory doesn't contain a certificate chain, the EmuNAND's cert.sys is used
+    /// instead, since it isn't needed for each title. If it doesn't contain a Ticket, a Ticket
+    /// already installed to the EmuNAND for the same title (such as one installed with
+    /// EmuNAND::install_ticket()) is used instead, and the installation fails if there isn't one.
+    pub fn install_from_directory(&self, path: &Path, options: &InstallOptions) -> Result<Vec<InstallWarning>, EmuNANDError> {
+        let title = self.load_title_from_directory(path)?;
+        self.install_title(title, options)
+    }
+
+    /// Loads a title from a directory containing its unpacked components, filling in a missing
+    /// certificate chain or Ticket from the EmuNAND in the same way as
+    /// EmuNAND::install_from_directory(), but without installing it.
+    pub fn load_title_from_directory(&self, path: &Path) -> Result<title::Title, EmuNANDError> {
+        #[cfg(feature = "serde")]
+        if let Some(manifest) = manifest::TitleManifest::read(path)? {
+            let tid = manifest.title_id()?;
+            let cert_chain = if path.join(&manifest.cert_chain).exists() { None } else { Some(self.cert_sys()?) };
+            let fallback_ticket = if manifest.ticket.is_some() { None } else { Some(self.get_ticket(tid).ok_or(EmuNANDError::MissingTicket(tid))?) };
+            return Ok(manifest.load_title_with_ticket(path, cert_chain, fallback_ticket)?);
+        }
+        let tmd_path = title::find_component(path, "tmd", true)?.unwrap();
+        let tid = tmd::TMD::from_bytes(&fs::read(tmd_path)?)?.title_id();
+        let cert_chain = if title::find_component(path, "cert", false)?.is_some() { None } else { Some(self.cert_sys()?) };
+        let fallback_ticket = if title::find_component(path, "tik", false)?.is_some() || tid == TitleId::BOOT2 {
+            None
+        } else {
+            Some(self.get_ticket(tid).ok_or(EmuNANDError::MissingTicket(tid))?)
+        };
+        Ok(title::Title::load_directory(path, cert_chain, fallback_ticket, false, None)?)
+    }
+
+    // Checks the dependencies of a title before it's installed, as requested by the install
+    // options. Returns the warnings for any missing dependencies, or an error if they're strict.
+    fn check_dependencies(&self, tmd: &tmd::TMD, options: &InstallOptions) -> Result<Vec<InstallWarning>, EmuNANDError> {
+        let mut warnings: Vec<InstallWarning> = Vec::new();
+        if options.check_dependencies && let Some(ios_tid) = self.missing_dependency(tmd, options.min_ios_version) {
+            if options.strict_dependencies {
+                return Err(EmuNANDError::MissingDependency { ios_tid });
+            }
+            warnings.push(InstallWarning::MissingDependency { ios_tid });
+        }
+        Ok(warnings)
+    }
+
+    /// Works out everything that installing a title with EmuNAND::install_title() would do, without
+    /// writing anything to the EmuNAND. Dependencies are checked in the same way, so a plan is only
+    /// returned for an installation that would be attempted.
+    pub fn plan_install(&self, title: &title::Title, options: &InstallOptions) -> Result<InstallPlan, EmuNANDError> {
+        let warnings = self.check_dependencies(&title.tmd, options)?;
+        let tid = title.tmd.title_id();
+        let title_dir = self.emunand_dirs["title"].join(hex::encode(tid.high())).join(hex::encode(tid.low()));
+        let mut files: Vec<PlannedFile> = Vec::new();
+        let mut plan_file = |path: PathBuf, size: u64| files.push(PlannedFile { path, size });
+        plan_file(self.ticket_path(tid), title.ticket.to_bytes()?.len() as u64);
+        if !self.has_valid_cert_sys() {
+            plan_file(self.emunand_dirs["sys"].join("cert.sys"), title.cert_chain.to_bytes()?.len() as u64);
+        }
+        plan_file(title_dir.join("content").join("title.tmd"), title.tmd.to_bytes()?.len() as u64);
+        let records = installable_records(title);
+        for (_, record) in &records {
+            if matches!(record.content_type, tmd::ContentType::Normal | tmd::ContentType::DLC) {
+                let file_name = format!("{:08X}.app", record.content_id).to_ascii_lowercase();
+                plan_file(title_dir.join("content").join(file_name), record.content_size);
+            }
+        }
+        let content_map_path = self.emunand_dirs["shared1"].join("content.map");
+        let mut content_map = if content_map_path.exists() {
+            content::SharedContentMap::from_bytes(&fs::read(&content_map_path)?)?
+        } else {
+            content::SharedContentMap::new()
+        };
+        for (_, record) in &records {
+            if matches!(record.content_type, tmd::ContentType::Shared)
+                && let Some(file_name) = content_map.add(&record.content_hash)? {
+                plan_file(self.emunand_dirs["shared1"].join(format!("{}.app", file_name.to_ascii_lowercase())), record.content_size);
+            }
+        }
+        plan_file(content_map_path, content_map.to_bytes()?.len() as u64);
+        let meta_size = if options.override_meta {
+            title.content.content_records.read()[title.get_position_for_index(0)?].content_size
+        } else {
+            title.meta().len() as u64
+        };
+        if meta_size > 0 {
+            plan_file(self.emunand_dirs["meta"].join(hex::encode(tid.high())).join(hex::encode(tid.low())).join("title.met"), meta_size);
+        }
+        if !is_dlc(tid) {
+            let uid_sys_path = self.emunand_dirs["sys"].join("uid.sys");
+            let mut uid_sys = if uid_sys_path.exists() {
+                sys::UidSys::from_bytes(&fs::read(&uid_sys_path)?)?
+            } else {
+                sys::UidSys::new()
+            };
+            uid_sys.add(tid)?;
+            plan_file(uid_sys_path, uid_sys.to_bytes()?.len() as u64);
+        }
+        Ok(InstallPlan { files, warnings })
+    }
+
+    // Reads the certificate chain stored in /sys/cert.sys.
+    fn cert_sys(&self) -> Result<cert::CertificateChain, EmuNANDError> {
+        let data = fs::read(self.emunand_dirs["sys"].join("cert.sys")).map_err(|_| EmuNANDError::MissingCertChain)?;
+        cert::CertificateChain::from_bytes(&data).map_err(|_| EmuNANDError::MissingCertChain)
+    }
+
+    /// Calculates how much space installing a title will use on the EmuNAND. Shared content that's
+    /// already listed in /shared1/content.map isn't counted, since it won't be installed again.
+    pub fn install_size(&self, title: &title::Title) -> Result<InstallSize, EmuNANDError> {
+        let content_map_path = self.emunand_dirs["shared1"].join("content.map");
+        let content_map = if content_map_path.exists() {
+            content::SharedContentMap::from_bytes(&fs::read(&content_map_path)?)?
+        } else {
+            content::SharedContentMap::new()
+        };
+        let metadata_size = (title.tmd.to_bytes()?.len() + title.ticket.to_bytes()?.len() + title.meta().len()) as u64;
+        let mut size = InstallSize {
+            bytes: metadata_size,
+            blocks: title::bytes_to_blocks(metadata_size),
+            ..Default::default()
+        };
+        let mut new_shared_hashes: Vec<[u8; 20]> = Vec::new();
+        for (_, record) in installable_records(title) {
+            let is_shared = matches!(record.content_type, tmd::ContentType::Shared);
+            if is_shared && content_map.find_by_hash(&record.content_hash).is_some() {
+                size.shared_bytes += record.content_size;
+                size.shared_blocks += title::bytes_to_blocks(record.content_size);
+            } else if !is_shared || !new_shared_hashes.contains(&record.content_hash) {
+                // Shared content used more than once by the same title is still only installed once.
+                if is_shared {
+                    new_shared_hashes.push(record.content_hash);
+                }
+                size.bytes += record.content_size;
+                size.blocks += title::bytes_to_blocks(record.content_size);
+            }
+        }
+        Ok(size)
+    }
+
+    // Checks whether the IOS required by a TMD is installed, and is at least the minimum version if
+    // one was provided. Returns the Title ID of the IOS if it's missing. Titles that don't require
+    // an IOS, like IOSes themselves, never have a missing dependency.
+    fn missing_dependency(&self, tmd: &tmd::TMD, min_ios_version: Option<u16>) -> Option<TitleId> {
+        let ios_tid = TitleId::new(tmd.ios_tid());
+        if ios_tid.high() != [0, 0, 0, 1] || ios_tid.low() == [0, 0, 0, 0] {
+            return None;
+        }
+        match self.get_title_tmd(ios_tid) {
+            Some(ios_tmd) if min_ios_version.is_none_or(|min| ios_tmd.title_version >= min) => None,
+            _ => Some(ios_tid),
+        }
+    }
+
+    // Writes every file that installing a title will create or replace to the staging directory.
+    // Returns the names of the new shared content files that were staged.
+    fn stage_title(&self, title: &title::Title, override_meta: bool, stage_dir: &Path) -> Result<Vec<String>, EmuNANDError> {
+        fs::write(stage_dir.join("ticket.tik"), title.ticket.to_bytes()?)?;
+        // Like ES, write the title's certificate chain to cert.sys if there isn't already a valid
+        // one, since some homebrew and Dolphin expect it to be there. A valid cert.sys is left
+        // alone.
+        if !self.has_valid_cert_sys() {
+            fs::write(stage_dir.join("cert.sys"), title.cert_chain.to_bytes()?)?;
+        }
+        // The records are only copied once, and are shared by the normal and shared content loops.
+        let records = installable_records(title);
+        // Normal content (non-shared), including DLC content, is staged alongside the TMD, since
+        // the whole content directory is moved into place at once. Contents are decrypted
+        // straight into their files, so that large titles don't need a second copy of each content
+        // in memory.
+        let content_dir = stage_dir.join("content");
+        fs::create_dir(&content_dir)?;
+        fs::write(content_dir.join("title.tmd"), title.tmd.to_bytes()?)?;
+        for (i, record) in &records {
+            if matches!(record.content_type, tmd::ContentType::Normal | tmd::ContentType::DLC) {
+                let content_path = content_dir.join(format!("{:08X}.app", record.content_id).to_ascii_lowercase());
+                title.open_content_by_index(*i)?.copy_to(&mut fs::File::create(content_path)?)?;
             }
         }
         // Shared content needs to be installed to /shared1/, with incremental names decided by
@@ -200,63 +811,687 @@ impl EmuNAND {
         } else {
             content::SharedContentMap::new()
         };
-        for i in 0..title.content.content_records.borrow().len() {
-            if matches!(title.content.content_records.borrow()[i].content_type, tmd::ContentType::Shared) {
-                if let Some(file_name) = content_map.add(&title.content.content_records.borrow()[i].content_hash)? {
-                    let content_path = self.emunand_dirs["shared1"].join(format!("{}.app", file_name.to_ascii_lowercase()));
-                    fs::write(content_path, title.get_content_by_index(i)?)?;
-                }
-            }
+        let shared_dir = stage_dir.join("shared1");
+        fs::create_dir(&shared_dir)?;
+        let mut shared_files: Vec<String> = Vec::new();
+        for (i, record) in records.iter().filter(|(_, record)| matches!(record.content_type, tmd::ContentType::Shared)) {
+            // The map only returns a new name for content that isn't already installed, and the
+            // content is only decrypted once it's known to be needed, since installing a batch of
+            // titles that share the same content would otherwise decrypt it over and over again.
+            let Some(file_name) = content_map.add(&record.content_hash)? else {
+                log::debug!("shared content {:08X} is already installed, skipping it", record.content_id);
+                continue;
+            };
+            let file_name = format!("{}.app", file_name.to_ascii_lowercase());
+            title.open_content_by_index(*i)?.copy_to(&mut fs::File::create(shared_dir.join(&file_name))?)?;
+            shared_files.push(file_name);
         }
-        fs::write(&content_map_path, content_map.to_bytes()?)?;
+        fs::write(stage_dir.join("content.map"), content_map.to_bytes()?)?;
         // The "footer" (officially "meta") is installed to /meta/<tid_high>/<tid_low>/title.met.
         // The "override meta" option installs the content at index 0 to title.met instead, as that
         // content contains the banner, and that's what title.met is meant to hold.
         let meta_data = if override_meta {
-            title.get_content_by_index(0)?
+            title.get_content_by_index(title.get_position_for_index(0)?)?
         } else {
-            title.meta()
+            title.meta().to_vec()
         };
         if !meta_data.is_empty() {
+            fs::write(stage_dir.join("title.met"), meta_data)?;
+        }
+        // Finally, we need to update uid.sys (or create it if it doesn't exist) so that the newly
+        // installed title will actually show up (at least for channels). ES never adds DLC to
+        // uid.sys, since it isn't launched on its own.
+        if !is_dlc(title.tmd.title_id()) {
+            let uid_sys_path = self.emunand_dirs["sys"].join("uid.sys");
+            let mut uid_sys = if uid_sys_path.exists() {
+                sys::UidSys::from_bytes(&fs::read(&uid_sys_path)?)?
+            } else {
+                sys::UidSys::new()
+            };
+            uid_sys.add(title.tmd.title_id())?;
+            fs::write(stage_dir.join("uid.sys"), uid_sys.to_bytes()?)?;
+        }
+        Ok(shared_files)
+    }
+
+    // Moves the files staged by stage_title() into their final locations. Each file is moved with
+    // a rename, and the content directory is swapped as a whole, so no file is ever left partially
+    // written. If any step fails, everything that was already moved is put back, so the existing
+    // installation of the title (if any) is left exactly as it was.
+    fn commit_staged_title(&self, title: &title::Title, shared_files: &[String], stage_dir: &Path) -> Result<(), EmuNANDError> {
+        let mut commit = StagedCommit::new(stage_dir.join("backup"))?;
+        let result = self.commit_staged_files(title, shared_files, stage_dir, &mut commit);
+        if result.is_err() {
+            commit.roll_back();
+        }
+        result
+    }
+
+    fn commit_staged_files(&self, title: &title::Title, shared_files: &[String], stage_dir: &Path, commit: &mut StagedCommit) -> Result<(), EmuNANDError> {
+        // Save the two halves of the TID, since those are part of the installation path.
+        let tid_high = hex::encode(title.tmd.title_id().high());
+        let tid_low = hex::encode(title.tmd.title_id().low());
+        // Tickets are installed to /ticket/<tid_high>/<tid_low>.tik.
+        let ticket_path = self.create_ticket_dir(title.tmd.title_id())?;
+        commit.commit(&stage_dir.join("ticket.tik"), &ticket_path)?;
+        // Shared content is committed before content.map, so that the map never refers to content
+        // that doesn't exist yet.
+        for file_name in shared_files {
+            commit.commit(&stage_dir.join("shared1").join(file_name), &self.emunand_dirs["shared1"].join(file_name))?;
+        }
+        commit.commit(&stage_dir.join("content.map"), &self.emunand_dirs["shared1"].join("content.map"))?;
+        // TMDs and normal content (non-shared) are installed to 
+        // /title/<tid_high>/<tid_low>/content/, as title.tmd and <cid>.app.
+        let mut title_dir = self.emunand_dirs["title"].join(&tid_high);
+        safe_create_dir(&title_dir)?;
+        title_dir = title_dir.join(&tid_low);
+        safe_create_dir(&title_dir)?;
+        // Create an empty "data" dir if it doesn't exist.
+        safe_create_dir(&title_dir.join("data"))?;
+        // Any existing installed content and the current TMD are swapped out as a whole.
+        commit.commit(&stage_dir.join("content"), &title_dir.join("content"))?;
+        if stage_dir.join("title.met").exists() {
             let mut meta_dir = self.emunand_dirs["meta"].join(&tid_high);
             safe_create_dir(&meta_dir)?;
             meta_dir = meta_dir.join(&tid_low);
             safe_create_dir(&meta_dir)?;
-            fs::write(meta_dir.join("title.met"), meta_data)?;
+            commit.commit(&stage_dir.join("title.met"), &meta_dir.join("title.met"))?;
+        }
+        if stage_dir.join("cert.sys").exists() {
+            commit.commit(&stage_dir.join("cert.sys"), &self.emunand_dirs["sys"].join("cert.sys"))?;
+        }
+        if stage_dir.join("uid.sys").exists() {
+            commit.commit(&stage_dir.join("uid.sys"), &self.emunand_dirs["sys"].join("uid.sys"))?;
         }
-        // Finally, we need to update uid.sys (or create it if it doesn't exist) so that the newly
-        // installed title will actually show up (at least for channels).
-        let uid_sys_path = self.emunand_dirs["sys"].join("uid.sys");
-        let mut uid_sys = if uid_sys_path.exists() {
-            sys::UidSys::from_bytes(&fs::read(&uid_sys_path)?)?
-        } else {
-            sys::UidSys::new()
-        };
-        uid_sys.add(&title.tmd.title_id())?;
-        fs::write(&uid_sys_path, &uid_sys.to_bytes()?)?;
         Ok(())
     }
     
+    /// Audits the shared content in /shared1/ by cross-referencing content.map against the shared
+    /// content used by every installed title and the .app files that actually exist. If delete is
+    /// true, orphaned entries are removed from content.map along with their files, and untracked
+    /// files are deleted. Missing files are only reported, as they can't be recovered.
+    pub fn cleanup_shared_content(&self, delete: bool) -> Result<SharedContentReport, EmuNANDError> {
+        let shared_dir = &self.emunand_dirs["shared1"];
+        let content_map_path = shared_dir.join("content.map");
+        let mut content_map = if content_map_path.exists() {
+            content::SharedContentMap::from_bytes(&fs::read(&content_map_path)?)?
+        } else {
+            content::SharedContentMap::new()
+        };
+        // Collect the hash of every shared content used by an installed title.
+        let mut used_hashes: Vec<[u8; 20]> = Vec::new();
+        for category in self.get_installed_titles() {
+            for low in category.titles {
+                let tid = match TitleId::from_hex(&format!("{}{}", category.title_type, low)) {
+                    Ok(tid) => tid,
+                    Err(_) => continue,
+                };
+                if let Some(tmd) = self.get_title_tmd(tid) {
+                    used_hashes.extend(tmd.content_records.read().iter()
+                        .filter(|record| matches!(record.content_type, tmd::ContentType::Shared))
+                        .map(|record| record.content_hash));
+                }
+            }
+        }
+        let mut report = SharedContentReport::default();
+        let entries = content_map.entries();
+        for (file_name, hash) in &entries {
+            if !used_hashes.contains(hash) {
+                report.orphaned_entries.push((file_name.clone(), *hash));
+            }
+            if !shared_dir.join(format!("{}.app", file_name)).exists() {
+                report.missing_files.push(file_name.clone());
+            }
+        }
+        // Consoles write shared content file names in lowercase, but compare them without regard
+        // to case in case something else wrote them in uppercase.
+        for entry in fs::read_dir(shared_dir)? {
+            let path = entry?.path();
+            let is_app = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("app"));
+            if !path.is_file() || !is_app {
+                continue;
+            }
+            let stem = path.file_stem().unwrap().to_string_lossy().to_ascii_lowercase();
+            if !entries.iter().any(|(file_name, _)| *file_name == stem) {
+                report.untracked_files.push(path.file_name().unwrap().to_string_lossy().into_owned());
+            }
+        }
+        report.untracked_files.sort();
+        if delete {
+            for (file_name, hash) in &report.orphaned_entries {
+                content_map.remove(hash);
+                let content_path = shared_dir.join(format!("{}.app", file_name));
+                if content_path.exists() {
+                    fs::remove_file(content_path)?;
+                }
+            }
+            for file_name in &report.untracked_files {
+                fs::remove_file(shared_dir.join(file_name))?;
+            }
+            if !report.orphaned_entries.is_empty() {
+                fs::write(&content_map_path, content_map.to_bytes()?)?;
+            }
+        }
+        Ok(report)
+    }
+
+    /// Repairs /shared1/content.map and /sys/uid.sys, which can be left with partial or duplicated
+    /// entries by interrupted writes or other tools. Partial entries at the end of each file are
+    /// discarded and duplicated entries are removed, and each file is rewritten only if anything
+    /// changed. Shared content is then cleaned up with EmuNAND::cleanup_shared_content(), which
+    /// removes the files of any entries that were dropped.
+    pub fn repair(&self) -> Result<RepairReport, EmuNANDError> {
+        let mut report = RepairReport::default();
+        let content_map_path = self.emunand_dirs["shared1"].join("content.map");
+        if content_map_path.exists() {
+            let mut content_map = content::SharedContentMap::from_bytes(&fs::read(&content_map_path)?)?;
+            report.content_map_discarded_bytes = content_map.discarded_bytes();
+            report.content_map_removed = content_map.repair();
+            if report.content_map_discarded_bytes > 0 || !report.content_map_removed.is_empty() {
+                fs::write(&content_map_path, content_map.to_bytes()?)?;
+            }
+        }
+        let uid_sys_path = self.emunand_dirs["sys"].join("uid.sys");
+        if uid_sys_path.exists() {
+            let mut uid_sys = sys::UidSys::from_bytes(&fs::read(&uid_sys_path)?)?;
+            report.uid_sys_discarded_bytes = uid_sys.discarded_bytes();
+            report.uid_sys_removed = uid_sys.repair();
+            if report.uid_sys_discarded_bytes > 0 || !report.uid_sys_removed.is_empty() {
+                fs::write(&uid_sys_path, uid_sys.to_bytes()?)?;
+            }
+        }
+        report.shared_content = self.cleanup_shared_content(true)?;
+        Ok(report)
+    }
+
     /// Uninstall a title with the provided Title ID from an EmuNAND. By default, the Ticket will be
     /// left intact unlesss "remove ticket" is set to true.
-    pub fn uninstall_title(&self, tid: [u8; 8], remove_ticket: bool) -> Result<(), EmuNANDError> {
+    pub fn uninstall_title(&self, tid: TitleId, remove_ticket: bool) -> Result<(), EmuNANDError> {
+        for path in self.plan_uninstall(tid, remove_ticket)? {
+            if path.is_dir() {
+                fs::remove_dir_all(&path)?;
+            } else {
+                fs::remove_file(&path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the files and directories that EmuNAND::uninstall_title() would delete, without
+    /// deleting them. Returns an error if the title isn't installed.
+    pub fn plan_uninstall(&self, tid: TitleId, remove_ticket: bool) -> Result<Vec<PathBuf>, EmuNANDError> {
         // Save the two halves of the TID, since those are part of the installation path.
-        let tid_high = hex::encode(&tid[0..4]);
-        let tid_low = hex::encode(&tid[4..8]);
-        // Ensure that a title directory actually exists for the specified title. If it does, then
-        // delete it.
+        let tid_high = hex::encode(tid.high());
+        let tid_low = hex::encode(tid.low());
+        // Ensure that a title directory actually exists for the specified title.
         let title_dir = self.emunand_dirs["title"].join(&tid_high).join(&tid_low);
         if !title_dir.exists() {
             return Err(EmuNANDError::TitleNotInstalled);
         }
-        fs::remove_dir_all(&title_dir)?;
-        // If we've been told to delete the Ticket, check if it exists and then do so.
+        let mut paths = vec![title_dir];
+        // If we've been told to delete the Ticket, only include it if it exists.
         if remove_ticket {
-            let ticket_path = self.emunand_dirs["ticket"].join(&tid_high).join(format!("{}.tik", &tid_low));
+            let ticket_path = self.ticket_path(tid);
             if ticket_path.exists() {
-                fs::remove_file(&ticket_path)?;
+                paths.push(ticket_path);
             }
         }
-        Ok(())
+        Ok(paths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::title::test_utils;
+
+    #[test]
+    fn test_cleanup_shared_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let emunand = EmuNAND::open(dir.path().to_path_buf()).unwrap();
+        let title = test_utils::build_title(&[vec![0x01; 100], vec![0x02; 200]]);
+        title.content.content_records.write()[1].content_type = tmd::ContentType::Shared;
+        let used_hash = title.content.content_records.read()[1].content_hash;
+        emunand.install_title(title, &InstallOptions::default()).unwrap();
+        let shared_dir = dir.path().join("shared1");
+        assert!(shared_dir.join("00000000.app").exists());
+        // Add an orphaned entry, an entry with no file, and an untracked file with an uppercase name.
+        let mut content_map = content::SharedContentMap::from_bytes(&fs::read(shared_dir.join("content.map")).unwrap()).unwrap();
+        content_map.add(&[0xAA; 20]).unwrap();
+        fs::write(shared_dir.join("00000001.app"), [0u8; 16]).unwrap();
+        content_map.add(&[0xBB; 20]).unwrap();
+        fs::write(shared_dir.join("content.map"), content_map.to_bytes().unwrap()).unwrap();
+        fs::write(shared_dir.join("0000000A.APP"), [0u8; 16]).unwrap();
+
+        let report = emunand.cleanup_shared_content(false).unwrap();
+        assert_eq!(report.orphaned_entries, vec![(String::from("00000001"), [0xAA; 20]), (String::from("00000002"), [0xBB; 20])]);
+        assert_eq!(report.missing_files, vec![String::from("00000002")]);
+        assert_eq!(report.untracked_files, vec![String::from("0000000A.APP")]);
+        assert!(shared_dir.join("00000001.app").exists());
+
+        emunand.cleanup_shared_content(true).unwrap();
+        assert!(!shared_dir.join("00000001.app").exists());
+        assert!(!shared_dir.join("0000000A.APP").exists());
+        let content_map = content::SharedContentMap::from_bytes(&fs::read(shared_dir.join("content.map")).unwrap()).unwrap();
+        assert_eq!(content_map.entries(), vec![(String::from("00000000"), used_hash)]);
+        let report = emunand.cleanup_shared_content(false).unwrap();
+        assert!(report.orphaned_entries.is_empty() && report.missing_files.is_empty() && report.untracked_files.is_empty());
+    }
+
+    #[test]
+    fn test_installed_shared_content_not_decrypted() {
+        let dir = tempfile::tempdir().unwrap();
+        let emunand = EmuNAND::open(dir.path().to_path_buf()).unwrap();
+        let build = |contents: &[Vec<u8>]| {
+            let mut title = test_utils::build_title(contents);
+            title.set_title_id(TitleId::from_parts([0x00, 0x01, 0x00, 0x01], *b"RSTJ")).unwrap();
+            for record in title.content.content_records.write().iter_mut().skip(1) {
+                record.content_type = tmd::ContentType::Shared;
+            }
+            title
+        };
+        let mut installed = build(&[vec![0x01; 64], vec![0x02; 64]]);
+        installed.set_title_id(TitleId::new(test_utils::TEST_TITLE_ID)).unwrap();
+        emunand.install_title(installed, &InstallOptions::default()).unwrap();
+        // Shared content that would fail its hash check if it were decrypted shows that it was.
+        let corrupt = |title: &mut title::Title, index: usize| title.content.load_enc_content(&[0xFF; 64], index).unwrap();
+        let mut title = build(&[vec![0x03; 64], vec![0x02; 64], vec![0x04; 64]]);
+        corrupt(&mut title, 2);
+        assert!(matches!(emunand.install_title(title, &InstallOptions::default()), Err(EmuNANDError::Content(_))));
+        // Shared content that's already installed is skipped without being decrypted, so its data
+        // doesn't matter.
+        let mut title = build(&[vec![0x03; 64], vec![0x02; 64], vec![0x04; 64]]);
+        corrupt(&mut title, 1);
+        emunand.install_title(title, &InstallOptions::default()).unwrap();
+        let shared_dir = dir.path().join("shared1");
+        assert_eq!(fs::read(shared_dir.join("00000000.app")).unwrap(), vec![0x02; 64]);
+        assert_eq!(fs::read(shared_dir.join("00000001.app")).unwrap(), vec![0x04; 64]);
+        assert!(!shared_dir.join("00000002.app").exists());
+    }
+
+    #[test]
+    fn test_install_partial_dlc() {
+        let dir = tempfile::tempdir().unwrap();
+        let emunand = EmuNAND::open(dir.path().to_path_buf()).unwrap();
+        let contents = vec![vec![0x01; 100], vec![0x02; 300], vec![0x03; 50], vec![0x04; 80]];
+        let mut title = test_utils::build_dlc_title(&contents);
+        for record in title.content.content_records.write().iter_mut().skip(1) {
+            record.content_type = tmd::ContentType::DLC;
+        }
+        // Content that's present but not permitted by the Ticket isn't installed either.
+        title.ticket.set_content_permitted(3, false).unwrap();
+        let tid = title.tmd.title_id();
+        let plan = emunand.plan_install(&title, &InstallOptions::default()).unwrap();
+        let size = emunand.install_size(&title).unwrap();
+        let overhead = (title.tmd.to_bytes().unwrap().len() + title.ticket.to_bytes().unwrap().len()) as u64;
+        assert_eq!(size.bytes, overhead + 150);
+        emunand.install_title(title, &InstallOptions::default()).unwrap();
+        let content_dir = dir.path().join("title").join("00010005").join(hex::encode(tid.low())).join("content");
+        assert_eq!(fs::read(content_dir.join("00000010.app")).unwrap(), contents[0]);
+        assert_eq!(fs::read(content_dir.join("00000012.app")).unwrap(), contents[2]);
+        assert!(!content_dir.join("00000011.app").exists());
+        assert!(!content_dir.join("00000013.app").exists());
+        for file in &plan.files {
+            assert!(file.path.exists(), "{}", file.path.display());
+        }
+        // ES doesn't add DLC to uid.sys.
+        assert!(!plan.files.iter().any(|file| file.path.ends_with("uid.sys")));
+        assert!(!dir.path().join("sys").join("uid.sys").exists());
+        assert_eq!(emunand.installed_content_count(tid), Some((2, 4)));
+        assert_eq!(emunand.installed_content_count(TitleId::new(test_utils::TEST_TITLE_ID)), None);
+    }
+
+    #[test]
+    fn test_install_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let emunand = EmuNAND::open(dir.path().to_path_buf()).unwrap();
+        let title = test_utils::build_title(&[vec![0x01; 0x30000], vec![0x02; 0x20000], vec![0x03; 0x10000]]);
+        title.content.content_records.write()[1].content_type = tmd::ContentType::Shared;
+        title.content.content_records.write()[2].content_type = tmd::ContentType::Shared;
+        let shared_hashes: Vec<[u8; 20]> = title.content.content_records.read()[1..].iter().map(|r| r.content_hash).collect();
+        let overhead = (title.tmd.to_bytes().unwrap().len() + title.ticket.to_bytes().unwrap().len() + title.meta().len()) as u64;
+        // No shared content present.
+        let size = emunand.install_size(&title).unwrap();
+        assert_eq!(size.bytes, overhead + 0x60000);
+        assert_eq!(size.blocks, 5);
+        assert_eq!((size.shared_bytes, size.shared_blocks), (0, 0));
+        // Partially populated content.map.
+        let mut content_map = content::SharedContentMap::new();
+        content_map.add(&[0xAA; 20]).unwrap();
+        content_map.add(&shared_hashes[0]).unwrap();
+        fs::write(dir.path().join("shared1").join("content.map"), content_map.to_bytes().unwrap()).unwrap();
+        let size = emunand.install_size(&title).unwrap();
+        assert_eq!(size.bytes, overhead + 0x40000);
+        assert_eq!(size.blocks, 4);
+        assert_eq!((size.shared_bytes, size.shared_blocks), (0x20000, 1));
+        // All shared content present.
+        content_map.add(&shared_hashes[1]).unwrap();
+        fs::write(dir.path().join("shared1").join("content.map"), content_map.to_bytes().unwrap()).unwrap();
+        let size = emunand.install_size(&title).unwrap();
+        assert_eq!(size.bytes, overhead + 0x30000);
+        assert_eq!(size.blocks, 3);
+        assert_eq!((size.shared_bytes, size.shared_blocks), (0x30000, 2));
+    }
+
+    #[test]
+    fn test_plan_install_and_uninstall() {
+        let dir = tempfile::tempdir().unwrap();
+        let title = test_utils::build_title(&[vec![0x01; 0x300], vec![0x02; 0x200]]);
+        title.content.content_records.write()[1].content_type = tmd::ContentType::Shared;
+        // Planning against a read-only EmuNAND doesn't create anything, not even its directories.
+        let emunand = EmuNAND::open_read_only(dir.path().to_path_buf()).unwrap();
+        let plan = emunand.plan_install(&title, &InstallOptions::default()).unwrap();
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+        assert!(plan.warnings.is_empty());
+        // Every planned file should be written by a real install, with the planned size.
+        let emunand = EmuNAND::open(dir.path().to_path_buf()).unwrap();
+        emunand.install_title(title, &InstallOptions::default()).unwrap();
+        for file in &plan.files {
+            assert_eq!(fs::metadata(&file.path).unwrap().len(), file.size, "{}", file.path.display());
+        }
+        assert!(plan.files.iter().any(|file| file.path.ends_with("shared1/00000000.app")));
+        // Planning an uninstall lists what would be removed without removing it.
+        let tid = TitleId::new(test_utils::TEST_TITLE_ID);
+        let paths = emunand.plan_uninstall(tid, true).unwrap();
+        assert_eq!(paths, vec![dir.path().join("title").join("00010001").join("52535449"), emunand.ticket_path(tid)]);
+        assert!(paths.iter().all(|path| path.exists()));
+        emunand.uninstall_title(tid, true).unwrap();
+        assert!(paths.iter().all(|path| !path.exists()));
+        assert!(matches!(emunand.plan_uninstall(tid, true), Err(EmuNANDError::TitleNotInstalled)));
+    }
+
+    #[test]
+    fn test_failed_install_leaves_existing_title() {
+        let dir = tempfile::tempdir().unwrap();
+        let emunand = EmuNAND::open(dir.path().to_path_buf()).unwrap();
+        emunand.install_title(test_utils::build_title(&[vec![0x01; 100]]), &InstallOptions::default()).unwrap();
+        let content_dir = dir.path().join("title").join("00010001").join("52535449").join("content");
+        let tmd_data = fs::read(content_dir.join("title.tmd")).unwrap();
+        let content_data = fs::read(content_dir.join("00000010.app")).unwrap();
+        let uid_sys_data = fs::read(dir.path().join("sys").join("uid.sys")).unwrap();
+        // A title with a second content that doesn't match its hash can't be decrypted, which
+        // should abort the install after the first content has already been staged.
+        let title = test_utils::build_title(&[vec![0x01; 100], vec![0x02; 200]]);
+        title.content.content_records.write()[1].content_hash = [0u8; 20];
+        title.content.content_records.write()[1].content_type = tmd::ContentType::Shared;
+        assert!(matches!(emunand.install_title(title, &InstallOptions::default()), Err(EmuNANDError::Content(_))));
+        assert_eq!(fs::read(content_dir.join("title.tmd")).unwrap(), tmd_data);
+        assert_eq!(fs::read(content_dir.join("00000010.app")).unwrap(), content_data);
+        assert!(!content_dir.join("00000011.app").exists());
+        assert_eq!(fs::read(dir.path().join("sys").join("uid.sys")).unwrap(), uid_sys_data);
+        assert!(!dir.path().join("shared1").join("content.map").exists()
+            || fs::read(dir.path().join("shared1").join("content.map")).unwrap().is_empty());
+        assert_eq!(fs::read_dir(dir.path().join("import")).unwrap().count(), 0);
+        // A failure while moving the staged files into place should put back everything that was
+        // already moved. Here, the title's meta can't be installed because a file is in the way of
+        // its directory, which is only found after the Ticket, content.map, and content are moved.
+        let ticket_data = fs::read(dir.path().join("ticket").join("00010001").join("52535449.tik")).unwrap();
+        let content_map_data = fs::read(dir.path().join("shared1").join("content.map")).unwrap();
+        fs::write(dir.path().join("meta").join("00010001"), b"not a directory").unwrap();
+        let mut title = test_utils::build_title(&[vec![0x05; 100], vec![0x06; 200]]);
+        title.content.content_records.write()[1].content_type = tmd::ContentType::Shared;
+        title.ticket.title_version = 0x100;
+        title.set_meta(&[0x4D; 0x40]);
+        assert!(matches!(emunand.install_title(title, &InstallOptions::default()), Err(EmuNANDError::DirectoryNameConflict(_))));
+        assert_eq!(fs::read(dir.path().join("ticket").join("00010001").join("52535449.tik")).unwrap(), ticket_data);
+        assert_eq!(fs::read(dir.path().join("shared1").join("content.map")).unwrap(), content_map_data);
+        assert!(!dir.path().join("shared1").join("00000000.app").exists());
+        assert_eq!(fs::read(content_dir.join("title.tmd")).unwrap(), tmd_data);
+        assert_eq!(fs::read(content_dir.join("00000010.app")).unwrap(), content_data);
+        assert!(!content_dir.join("00000011.app").exists());
+        assert_eq!(fs::read(dir.path().join("sys").join("uid.sys")).unwrap(), uid_sys_data);
+        assert_eq!(fs::read_dir(dir.path().join("import")).unwrap().count(), 0);
+        fs::remove_file(dir.path().join("meta").join("00010001")).unwrap();
+        // A successful install should replace the existing content and clean up after itself.
+        emunand.install_title(test_utils::build_title(&[vec![0x03; 100], vec![0x04; 200]]), &InstallOptions::default()).unwrap();
+        assert_eq!(fs::read(content_dir.join("00000011.app")).unwrap(), vec![0x04; 200]);
+        assert_eq!(emunand.get_title_tmd(TitleId::new(test_utils::TEST_TITLE_ID)).unwrap().content_records.read().len(), 2);
+        assert_eq!(fs::read_dir(dir.path().join("import")).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_check_dependencies() {
+        let dir = tempfile::tempdir().unwrap();
+        let emunand = EmuNAND::open(dir.path().to_path_buf()).unwrap();
+        let ios_tid = TitleId::from_hex("000000010000003A").unwrap();
+        let build_channel = || {
+            let mut channel = test_utils::build_title(&[vec![0x01; 100]]);
+            channel.tmd.set_ios_tid(ios_tid.to_bytes()).unwrap();
+            channel
+        };
+        let strict = InstallOptions { check_dependencies: true, strict_dependencies: true, ..Default::default() };
+        let lenient = InstallOptions { check_dependencies: true, ..Default::default() };
+        // Without the IOS installed, a strict check should fail without installing anything, and a
+        // lenient check should install the title with a warning.
+        assert!(matches!(emunand.install_title(build_channel(), &strict), Err(EmuNANDError::MissingDependency { ios_tid: tid }) if tid == ios_tid));
+        assert!(emunand.get_title_tmd(TitleId::new(test_utils::TEST_TITLE_ID)).is_none());
+        assert_eq!(emunand.install_title(build_channel(), &lenient).unwrap(), vec![InstallWarning::MissingDependency { ios_tid }]);
+        assert!(emunand.get_title_tmd(TitleId::new(test_utils::TEST_TITLE_ID)).is_some());
+        assert!(emunand.install_title(build_channel(), &InstallOptions::default()).unwrap().is_empty());
+        // Install a stub IOS58, which should satisfy the dependency unless a newer version is needed.
+        let mut ios = test_utils::build_title(&[vec![0x02; 100]]);
+        ios.tmd.set_title_id(ios_tid).unwrap();
+        ios.tmd.title_version = 0x1F00;
+        emunand.install_title(ios, &InstallOptions::default()).unwrap();
+        assert!(emunand.install_title(build_channel(), &strict).unwrap().is_empty());
+        let outdated = InstallOptions { min_ios_version: Some(0x2000), ..strict.clone() };
+        assert!(matches!(emunand.install_title(build_channel(), &outdated), Err(EmuNANDError::MissingDependency { .. })));
+        let current = InstallOptions { min_ios_version: Some(0x1F00), ..strict };
+        assert!(emunand.install_title(build_channel(), &current).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_init() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("emunand");
+        let cert_chain = cert::CertificateChain::from_bytes(&test_utils::build_cert_chain()).unwrap();
+        EmuNAND::init(root.clone(), InitOptions { cert_chain: Some(cert_chain) }).unwrap();
+        let mut paths: Vec<String> = walkdir::WalkDir::new(&root).min_depth(1).into_iter()
+            .map(|entry| entry.unwrap().path().strip_prefix(&root).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect();
+        paths.sort();
+        let mut expected: Vec<String> = INIT_DIRS.iter()
+            .chain(&["shared1/content.map", "sys/cert.sys", "sys/uid.sys"])
+            .map(|path| path.to_string())
+            .collect();
+        expected.sort();
+        assert_eq!(paths, expected);
+        assert_eq!(fs::read(root.join("sys").join("cert.sys")).unwrap(), test_utils::build_cert_chain());
+        assert!(fs::read(root.join("sys").join("uid.sys")).unwrap().is_empty());
+        // Initializing or opening an existing EmuNAND shouldn't touch anything already in it.
+        let emunand = EmuNAND::open(root.clone()).unwrap();
+        emunand.install_title(test_utils::build_title(&[vec![0x01; 100]]), &InstallOptions::default()).unwrap();
+        let uid_sys = fs::read(root.join("sys").join("uid.sys")).unwrap();
+        EmuNAND::init(root.clone(), InitOptions::default()).unwrap();
+        EmuNAND::open(root.clone()).unwrap();
+        assert_eq!(fs::read(root.join("sys").join("uid.sys")).unwrap(), uid_sys);
+        assert!(root.join("sys").join("cert.sys").exists());
+        assert!(emunand.get_title_tmd(TitleId::new(test_utils::TEST_TITLE_ID)).is_some());
+    }
+
+    #[test]
+    fn test_install_shuffled_indices() {
+        let dir = tempfile::tempdir().unwrap();
+        let emunand = EmuNAND::open(dir.path().to_path_buf()).unwrap();
+        let title = test_utils::build_title_with_indices(&[(1, vec![0x01; 100]), (0, vec![0x02; 200])]);
+        emunand.install_title(title, &InstallOptions { override_meta: true, ..Default::default() }).unwrap();
+        let content_dir = dir.path().join("title").join("00010001").join("52535449").join("content");
+        assert_eq!(fs::read(content_dir.join("00000010.app")).unwrap(), vec![0x01; 100]);
+        assert_eq!(fs::read(content_dir.join("00000011.app")).unwrap(), vec![0x02; 200]);
+        // The overridden meta is the content with index 0, not the first content stored.
+        let meta_path = dir.path().join("meta").join("00010001").join("52535449").join("title.met");
+        assert_eq!(fs::read(meta_path).unwrap(), vec![0x02; 200]);
+    }
+
+    #[test]
+    fn test_install_ticket() {
+        let dir = tempfile::tempdir().unwrap();
+        let emunand = EmuNAND::open(dir.path().to_path_buf()).unwrap();
+        let tid = TitleId::new(test_utils::TEST_TITLE_ID);
+        let ticket = ticket::Ticket::from_bytes(&test_utils::build_ticket(test_utils::TEST_TITLE_ID, [0x11; 16])).unwrap();
+        assert!(emunand.get_ticket(tid).is_none());
+        emunand.install_ticket(&ticket, false).unwrap();
+        let ticket_path = dir.path().join("ticket").join("00010001").join("52535449.tik");
+        assert_eq!(fs::read(&ticket_path).unwrap(), ticket.to_bytes().unwrap());
+        assert_eq!(emunand.get_ticket(tid).unwrap().dec_title_key(), [0x11; 16]);
+        assert!(emunand.get_title_tmd(tid).is_none());
+        // An existing Ticket is only replaced when asked to.
+        let new_ticket = ticket::Ticket::from_bytes(&test_utils::build_ticket(test_utils::TEST_TITLE_ID, [0x22; 16])).unwrap();
+        assert!(matches!(emunand.install_ticket(&new_ticket, false), Err(EmuNANDError::TicketAlreadyInstalled(t)) if t == tid));
+        assert_eq!(emunand.get_ticket(tid).unwrap().dec_title_key(), [0x11; 16]);
+        emunand.install_ticket(&new_ticket, true).unwrap();
+        assert_eq!(emunand.get_ticket(tid).unwrap().dec_title_key(), [0x22; 16]);
+        // A later full install of the same title replaces the Ticket and installs alongside it.
+        let title = test_utils::build_title(&[vec![0x01; 100]]);
+        let title_ticket = title.ticket.to_bytes().unwrap();
+        emunand.install_title(title, &InstallOptions::default()).unwrap();
+        assert_eq!(fs::read(&ticket_path).unwrap(), title_ticket);
+        assert!(emunand.get_title_tmd(tid).is_some());
+        // Removing the Ticket leaves the title installed.
+        emunand.remove_ticket(tid).unwrap();
+        assert!(!ticket_path.exists());
+        assert!(emunand.get_title_tmd(tid).is_some());
+        assert!(matches!(emunand.remove_ticket(tid), Err(EmuNANDError::TicketNotInstalled)));
+        emunand.uninstall_title(tid, true).unwrap();
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_install_from_nus_download() {
+        let dir = tempfile::tempdir().unwrap();
+        let emunand = EmuNAND::open(dir.path().to_path_buf()).unwrap();
+        let tid = TitleId::new(test_utils::TEST_TITLE_ID);
+        let title = test_utils::build_title(&[vec![0x01; 100], vec![0x02; 300]]);
+        let content_dir = dir.path().join("title").join("00010001").join("52535449").join("content");
+        // A decrypted download includes its Ticket.
+        let download = tempfile::tempdir().unwrap();
+        let manifest = test_utils::write_nus_download(&title, download.path(), false);
+        fs::write(download.path().join(manifest.ticket.as_ref().unwrap()), title.ticket.to_bytes().unwrap()).unwrap();
+        emunand.install_from_directory(download.path(), &InstallOptions::default()).unwrap();
+        assert_eq!(fs::read(content_dir.join("00000011.app")).unwrap(), vec![0x02; 300]);
+        assert!(emunand.get_ticket(tid).is_some());
+        emunand.uninstall_title(tid, true).unwrap();
+        // An encrypted download has no Ticket, so it can only be installed once one is.
+        let download = tempfile::tempdir().unwrap();
+        test_utils::write_nus_download(&title, download.path(), true);
+        assert!(matches!(emunand.install_from_directory(download.path(), &InstallOptions::default()), Err(EmuNANDError::MissingTicket(t)) if t == tid));
+        assert!(emunand.get_title_tmd(tid).is_none());
+        emunand.install_ticket(&title.ticket, false).unwrap();
+        emunand.install_from_directory(download.path(), &InstallOptions::default()).unwrap();
+        assert_eq!(fs::read(content_dir.join("00000010.app")).unwrap(), vec![0x01; 100]);
+        assert_eq!(fs::read(content_dir.join("title.tmd")).unwrap(), title.tmd.to_bytes().unwrap());
+    }
+
+    #[test]
+    fn test_install_from_directory_without_cert() {
+        let dir = tempfile::tempdir().unwrap();
+        let emunand = EmuNAND::open(dir.path().to_path_buf()).unwrap();
+        let tid = TitleId::new(test_utils::TEST_TITLE_ID);
+        let title = test_utils::build_title(&[vec![0x03; 100]]);
+        let unpacked = tempfile::tempdir().unwrap();
+        title.to_directory_with_options(unpacked.path(), title::ContentNaming::Index, true).unwrap();
+        fs::remove_file(unpacked.path().join("0001000152535449.cert")).unwrap();
+        // Without a certificate chain in the directory, cert.sys is used instead.
+        assert!(matches!(emunand.install_from_directory(unpacked.path(), &InstallOptions::default()), Err(EmuNANDError::MissingCertChain)));
+        emunand.write_cert_sys(&title.cert_chain).unwrap();
+        emunand.install_from_directory(unpacked.path(), &InstallOptions::default()).unwrap();
+        let content_dir = dir.path().join("title").join("00010001").join("52535449").join("content");
+        assert_eq!(fs::read(content_dir.join("00000010.app")).unwrap(), vec![0x03; 100]);
+        assert!(emunand.get_ticket(tid).is_some());
+    }
+
+    #[test]
+    fn test_repair() {
+        let dir = tempfile::tempdir().unwrap();
+        let emunand = EmuNAND::open(dir.path().to_path_buf()).unwrap();
+        let title = test_utils::build_title(&[vec![0x01; 100], vec![0x02; 200]]);
+        title.content.content_records.write()[1].content_type = tmd::ContentType::Shared;
+        let used_hash = title.content.content_records.read()[1].content_hash;
+        emunand.install_title(title, &InstallOptions::default()).unwrap();
+        // A healthy EmuNAND doesn't need anything repaired.
+        let report = emunand.repair().unwrap();
+        assert_eq!((report.content_map_discarded_bytes, report.uid_sys_discarded_bytes), (0, 0));
+        assert!(report.content_map_removed.is_empty() && report.uid_sys_removed.is_empty());
+        // Duplicate the shared content under a second name, and leave partial entries behind.
+        let shared_dir = dir.path().join("shared1");
+        let mut content_map = fs::read(shared_dir.join("content.map")).unwrap();
+        content_map.extend_from_slice(b"00000001");
+        content_map.extend_from_slice(&used_hash);
+        content_map.extend_from_slice(b"0000");
+        fs::write(shared_dir.join("content.map"), &content_map).unwrap();
+        fs::copy(shared_dir.join("00000000.app"), shared_dir.join("00000001.app")).unwrap();
+        let uid_sys_path = dir.path().join("sys").join("uid.sys");
+        let uid_sys = fs::read(&uid_sys_path).unwrap();
+        let mut corrupted = uid_sys.clone();
+        corrupted.extend_from_slice(&uid_sys[12..24]);
+        corrupted.extend_from_slice(&[0x00; 7]);
+        fs::write(&uid_sys_path, &corrupted).unwrap();
+        let report = emunand.repair().unwrap();
+        assert_eq!(report.content_map_discarded_bytes, 4);
+        assert_eq!(report.content_map_removed, vec![content::ContentMapEntry { shared_id: 1, hash: used_hash }]);
+        assert_eq!(report.uid_sys_discarded_bytes, 7);
+        assert_eq!(report.uid_sys_removed.len(), 1);
+        assert_eq!(report.shared_content.untracked_files, vec![String::from("00000001.app")]);
+        assert!(!shared_dir.join("00000001.app").exists());
+        assert!(shared_dir.join("00000000.app").exists());
+        assert_eq!(fs::read(&uid_sys_path).unwrap(), uid_sys);
+        let content_map = content::SharedContentMap::from_bytes(&fs::read(shared_dir.join("content.map")).unwrap()).unwrap();
+        assert_eq!(content_map.entries(), vec![(String::from("00000000"), used_hash)]);
+        // Installing still works after the repair.
+        emunand.install_title(test_utils::build_title(&[vec![0x03; 100]]), &InstallOptions::default()).unwrap();
+    }
+
+    #[test]
+    fn test_install_writes_cert_sys() {
+        let dir = tempfile::tempdir().unwrap();
+        let emunand = EmuNAND::open(dir.path().to_path_buf()).unwrap();
+        let cert_sys_path = dir.path().join("sys").join("cert.sys");
+        assert!(!cert_sys_path.exists());
+        emunand.install_title(test_utils::build_title(&[vec![0x01; 100]]), &InstallOptions::default()).unwrap();
+        let cert_chain = cert::CertificateChain::from_bytes(&fs::read(&cert_sys_path).unwrap()).unwrap();
+        assert_eq!(cert_chain.to_bytes().unwrap(), test_utils::build_cert_chain());
+        // An invalid cert.sys should be replaced, but a valid one should be left alone.
+        fs::write(&cert_sys_path, [0u8; 16]).unwrap();
+        emunand.install_title(test_utils::build_title(&[vec![0x01; 100]]), &InstallOptions::default()).unwrap();
+        assert_eq!(fs::read(&cert_sys_path).unwrap(), test_utils::build_cert_chain());
+        let mut modified = test_utils::build_cert_chain();
+        modified[0x100] ^= 0xFF;
+        fs::write(&cert_sys_path, &modified).unwrap();
+        emunand.install_title(test_utils::build_title(&[vec![0x01; 100]]), &InstallOptions::default()).unwrap();
+        assert_eq!(fs::read(&cert_sys_path).unwrap(), modified);
+        emunand.write_cert_sys(&cert_chain).unwrap();
+        assert_eq!(fs::read(&cert_sys_path).unwrap(), test_utils::build_cert_chain());
+    }
+
+    #[test]
+    fn test_locate_dolphin_nand() {
+        let dir = tempfile::tempdir().unwrap();
+        let home = dir.path().join("home");
+        let mut paths = DolphinSearchPaths { os: String::from("linux"), home: Some(home.clone()), ..Default::default() };
+        assert!(locate_dolphin_nand_in(&paths).is_none());
+        assert!(dolphin_nand_candidates(&paths).contains(&home.join(".local/share/dolphin-emu/Wii")));
+        let default_nand = home.join(".local/share/dolphin-emu/Wii");
+        fs::create_dir_all(&default_nand).unwrap();
+        assert_eq!(locate_dolphin_nand_in(&paths), Some(default_nand.clone()));
+        // A custom NAND root in Dolphin's config should be preferred once it exists.
+        let custom_nand = dir.path().join("custom");
+        fs::create_dir_all(home.join(".config/dolphin-emu")).unwrap();
+        fs::write(home.join(".config/dolphin-emu/Dolphin.ini"), format!("[General]\nNANDRootPath = {}\n", custom_nand.display())).unwrap();
+        assert_eq!(locate_dolphin_nand_in(&paths), Some(default_nand));
+        fs::create_dir(&custom_nand).unwrap();
+        assert_eq!(locate_dolphin_nand_in(&paths), Some(custom_nand));
+        // A user path set explicitly should take priority over everything else.
+        let portable = dir.path().join("portable");
+        fs::create_dir_all(portable.join("Wii")).unwrap();
+        paths.user_path = Some(portable.clone());
+        assert_eq!(locate_dolphin_nand_in(&paths), Some(portable.join("Wii")));
+        // Other OSes should search their own standard locations.
+        let app_data = dir.path().join("AppData");
+        let windows = DolphinSearchPaths { os: String::from("windows"), home: Some(home.clone()), app_data: Some(app_data.clone()), ..Default::default() };
+        assert_eq!(dolphin_nand_candidates(&windows)[0], app_data.join("Dolphin Emulator").join("Wii"));
+        fs::create_dir_all(app_data.join("Dolphin Emulator").join("Wii")).unwrap();
+        assert_eq!(locate_dolphin_nand_in(&windows), Some(app_data.join("Dolphin Emulator").join("Wii")));
+        let macos = DolphinSearchPaths { os: String::from("macos"), home: Some(home), ..Default::default() };
+        assert!(locate_dolphin_nand_in(&macos).is_none());
     }
 }
@@ -3,6 +3,8 @@
 //
 // Root for all NAND-related modules.
 
+pub mod backup;
 pub mod emunand;
+pub mod save;
 pub mod setting;
 pub mod sys;
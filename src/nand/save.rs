@@ -0,0 +1,350 @@
+// nand/save.rs from rustii (c) 2025 NinjaCheetah & Contributors
+// https://github.com/NinjaCheetah/rustii
+//
+// Implements the structures and methods required for decrypting and parsing Wii savegames exported
+// to the SD card (data.bin).
+
+use std::io::{Cursor, Read};
+use std::path::{Component, Path};
+use aes::cipher::{BlockDecryptMut, KeyIvInit};
+use aes::cipher::block_padding::NoPadding;
+use byteorder::{BigEndian, ReadBytesExt};
+use md5::{Digest, Md5};
+use thiserror::Error;
+use crate::title::TitleId;
+
+const SD_KEY: [u8; 16] = [0xAB, 0x01, 0xB9, 0xD8, 0xE1, 0x62, 0x2B, 0x08, 0xAF, 0xBA, 0xD8, 0x4D, 0xBF, 0xC2, 0xA5, 0x5D];
+const SD_IV: [u8; 16] = [0x21, 0x67, 0x12, 0xE6, 0xAA, 0x1F, 0x68, 0x9F, 0x95, 0xC5, 0xA2, 0x23, 0x24, 0xDC, 0x6A, 0x98];
+// The header's MD5 is calculated with this value in place of the MD5 itself.
+const MD5_BLANKER: [u8; 16] = [0x0E, 0x65, 0x37, 0x81, 0x99, 0xBE, 0x45, 0x17, 0xAB, 0x06, 0xEC, 0x22, 0x45, 0x1A, 0x57, 0x93];
+const HEADER_SIZE: usize = 0xF0C0;
+const HEADER_MD5_OFFSET: usize = 0x0E;
+const HEADER_BANNER_OFFSET: usize = 0x20;
+const BK_HEADER_SIZE: usize = 0x80;
+const BK_HEADER_MAGIC: u32 = 0x426B0001;
+const FILE_HEADER_SIZE: usize = 0x80;
+const FILE_HEADER_MAGIC: u32 = 0x03ADF17E;
+const FILE_NAME_SIZE: usize = 0x45;
+const BANNER_MAGIC: &[u8; 4] = b"WIBN";
+const BANNER_TEXT_SIZE: usize = 0x40;
+
+/// The magic number and size found at the start of the Bk header, used to identify data.bin files.
+pub const SAVE_BK_MAGIC: [u8; 8] = [0x00, 0x00, 0x00, 0x70, 0x42, 0x6B, 0x00, 0x01];
+/// The offset of the Bk header in a data.bin file.
+pub const SAVE_BK_OFFSET: u64 = HEADER_SIZE as u64;
+
+#[derive(Debug, Error)]
+pub enum SaveError {
+    #[error("save data is too short to contain a valid header")]
+    InvalidLength,
+    #[error("save data header MD5 does not match (expected {expected}, got {actual}), the save is corrupted or not a valid data.bin")]
+    HeaderHashMismatch { expected: String, actual: String },
+    #[error("save banner is not valid (expected magic `WIBN`)")]
+    InvalidBanner,
+    #[error("save data Bk header is not valid (expected magic 0x426B0001, got {0:#010X})")]
+    InvalidBkHeader(u32),
+    #[error("save file {0} has an invalid header (expected magic 0x03ADF17E, got {1:#010X})")]
+    InvalidFileHeader(usize, u32),
+    #[error("save file {0} has an unknown type {1}")]
+    UnknownFileType(usize, u8),
+    #[error("save file `{0}` has a name that isn't a safe relative path")]
+    InvalidFileName(String),
+    #[error("save file `{0}` extends past the end of the save data")]
+    TruncatedFile(String),
+    #[error("save data is not in a valid format")]
+    IO(#[from] std::io::Error),
+}
+
+// Decrypts data encrypted with the SD key. The length of the data must be a multiple of 16 bytes.
+fn decrypt_sd(data: &[u8], iv: [u8; 16]) -> Vec<u8> {
+    type Aes128CbcDec = cbc::Decryptor<aes::Aes128>;
+    let decryptor = Aes128CbcDec::new(&SD_KEY.into(), &iv.into());
+    let mut buf = data.to_owned();
+    decryptor.decrypt_padded_mut::<NoPadding>(&mut buf).unwrap();
+    buf
+}
+
+// Calculates the MD5 of a decrypted header, with the stored MD5 replaced by the blanker.
+fn header_md5(header: &[u8]) -> [u8; 16] {
+    let mut header = header.to_owned();
+    header[HEADER_MD5_OFFSET..HEADER_MD5_OFFSET + 16].copy_from_slice(&MD5_BLANKER);
+    Md5::digest(&header).into()
+}
+
+// Decodes a null-terminated UTF-16BE string, as used by the banner's title and subtitle.
+fn read_utf16_string(data: &[u8]) -> String {
+    let chars: Vec<u16> = data.chunks_exact(2)
+        .map(|c| u16::from_be_bytes([c[0], c[1]]))
+        .take_while(|c| *c != 0)
+        .collect();
+    String::from_utf16_lossy(&chars)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// The type of an entry in a save.
+pub enum SaveFileType {
+    File,
+    Directory,
+}
+
+#[derive(Debug, Clone)]
+/// A file or directory contained in a save.
+pub struct SaveFile {
+    /// The path of the entry, relative to the title's data directory.
+    pub name: String,
+    pub permissions: u8,
+    pub attributes: u8,
+    pub file_type: SaveFileType,
+    /// The decrypted data of the file. Always empty for directories.
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+/// The parsed contents of a save's banner (banner.bin).
+pub struct SaveBanner {
+    pub flags: u32,
+    pub animation_speed: u16,
+    pub title: String,
+    pub subtitle: String,
+}
+
+#[derive(Debug)]
+/// A structure that represents a decrypted Wii savegame, as exported to the SD card (data.bin).
+pub struct SaveData {
+    pub title_id: TitleId,
+    pub permissions: u8,
+    /// The raw data of the save's banner.bin.
+    pub banner_data: Vec<u8>,
+    pub banner: SaveBanner,
+    /// The ID of the console that exported the save.
+    pub ng_id: u32,
+    pub mac_address: [u8; 6],
+    pub files: Vec<SaveFile>,
+}
+
+impl SaveData {
+    /// Creates a new SaveData instance from the binary data of a data.bin file. The header is
+    /// decrypted and verified against its MD5, and all contained files are decrypted.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, SaveError> {
+        if data.len() < HEADER_SIZE + BK_HEADER_SIZE {
+            return Err(SaveError::InvalidLength);
+        }
+        // The main header is the only encrypted header, so decrypt and verify it first.
+        let header = decrypt_sd(&data[..HEADER_SIZE], SD_IV);
+        let stored_md5 = &header[HEADER_MD5_OFFSET..HEADER_MD5_OFFSET + 16];
+        let actual_md5 = header_md5(&header);
+        if stored_md5 != actual_md5 {
+            return Err(SaveError::HeaderHashMismatch {
+                expected: hex::encode(stored_md5),
+                actual: hex::encode(actual_md5),
+            });
+        }
+        let mut buf = Cursor::new(&header);
+        let mut title_id = [0u8; 8];
+        buf.read_exact(&mut title_id)?;
+        let banner_size = buf.read_u32::<BigEndian>()? as usize;
+        let permissions = buf.read_u8()?;
+        if banner_size > HEADER_SIZE - HEADER_BANNER_OFFSET {
+            return Err(SaveError::InvalidBanner);
+        }
+        let banner_data = header[HEADER_BANNER_OFFSET..HEADER_BANNER_OFFSET + banner_size].to_vec();
+        let banner = SaveData::parse_banner(&banner_data)?;
+        // The Bk header follows, and isn't encrypted.
+        let mut buf = Cursor::new(&data[HEADER_SIZE..HEADER_SIZE + BK_HEADER_SIZE]);
+        let _bk_header_size = buf.read_u32::<BigEndian>()?;
+        let bk_magic = buf.read_u32::<BigEndian>()?;
+        if bk_magic != BK_HEADER_MAGIC {
+            return Err(SaveError::InvalidBkHeader(bk_magic));
+        }
+        let ng_id = buf.read_u32::<BigEndian>()?;
+        let file_count = buf.read_u32::<BigEndian>()?;
+        buf.set_position(0x68);
+        let mut mac_address = [0u8; 6];
+        buf.read_exact(&mut mac_address)?;
+        // Then the files, each of which has its own plain header followed by its encrypted data.
+        let mut files: Vec<SaveFile> = Vec::new();
+        let mut offset = HEADER_SIZE + BK_HEADER_SIZE;
+        for i in 0..file_count as usize {
+            if data.len() < offset + FILE_HEADER_SIZE {
+                return Err(SaveError::InvalidLength);
+            }
+            let mut buf = Cursor::new(&data[offset..offset + FILE_HEADER_SIZE]);
+            let magic = buf.read_u32::<BigEndian>()?;
+            if magic != FILE_HEADER_MAGIC {
+                return Err(SaveError::InvalidFileHeader(i, magic));
+            }
+            let size = buf.read_u32::<BigEndian>()? as usize;
+            let permissions = buf.read_u8()?;
+            let attributes = buf.read_u8()?;
+            let file_type = match buf.read_u8()? {
+                1 => SaveFileType::File,
+                2 => SaveFileType::Directory,
+                other => return Err(SaveError::UnknownFileType(i, other)),
+            };
+            let mut name = [0u8; FILE_NAME_SIZE];
+            buf.read_exact(&mut name)?;
+            let name = String::from_utf8_lossy(&name).trim_end_matches('\0').to_string();
+            let is_safe = !name.is_empty() && Path::new(&name).components().all(|c| matches!(c, Component::Normal(_)));
+            if !is_safe {
+                return Err(SaveError::InvalidFileName(name));
+            }
+            let mut iv = [0u8; 16];
+            buf.read_exact(&mut iv)?;
+            offset += FILE_HEADER_SIZE;
+            let data = if file_type == SaveFileType::File {
+                let padded_size = (size + 0x3F) & !0x3F;
+                if data.len() < offset + padded_size {
+                    return Err(SaveError::TruncatedFile(name));
+                }
+                let mut file_data = decrypt_sd(&data[offset..offset + padded_size], iv);
+                file_data.truncate(size);
+                offset += padded_size;
+                file_data
+            } else {
+                Vec::new()
+            };
+            files.push(SaveFile { name, permissions, attributes, file_type, data });
+        }
+        Ok(SaveData {
+            title_id: TitleId::new(title_id),
+            permissions,
+            banner_data,
+            banner,
+            ng_id,
+            mac_address,
+            files,
+        })
+    }
+
+    fn parse_banner(data: &[u8]) -> Result<SaveBanner, SaveError> {
+        if data.len() < 0x20 + BANNER_TEXT_SIZE * 2 || &data[..4] != BANNER_MAGIC {
+            return Err(SaveError::InvalidBanner);
+        }
+        let mut buf = Cursor::new(data);
+        buf.set_position(4);
+        let flags = buf.read_u32::<BigEndian>()?;
+        let animation_speed = buf.read_u16::<BigEndian>()?;
+        Ok(SaveBanner {
+            flags,
+            animation_speed,
+            title: read_utf16_string(&data[0x20..0x20 + BANNER_TEXT_SIZE]),
+            subtitle: read_utf16_string(&data[0x20 + BANNER_TEXT_SIZE..0x20 + BANNER_TEXT_SIZE * 2]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aes::cipher::BlockEncryptMut;
+    use byteorder::WriteBytesExt;
+    use std::io::Write;
+
+    fn encrypt_sd(data: &[u8], iv: [u8; 16]) -> Vec<u8> {
+        type Aes128CbcEnc = cbc::Encryptor<aes::Aes128>;
+        let encryptor = Aes128CbcEnc::new(&SD_KEY.into(), &iv.into());
+        let mut buf = data.to_owned();
+        let len = buf.len();
+        encryptor.encrypt_padded_mut::<NoPadding>(&mut buf, len).unwrap();
+        buf
+    }
+
+    fn build_banner(title: &str, subtitle: &str) -> Vec<u8> {
+        let mut banner = Vec::new();
+        banner.extend_from_slice(BANNER_MAGIC);
+        banner.write_u32::<BigEndian>(0x10).unwrap();
+        banner.write_u16::<BigEndian>(3).unwrap();
+        banner.resize(0x20, 0);
+        for text in [title, subtitle] {
+            let mut text_data: Vec<u8> = text.encode_utf16().flat_map(|c| c.to_be_bytes()).collect();
+            text_data.resize(BANNER_TEXT_SIZE, 0);
+            banner.extend_from_slice(&text_data);
+        }
+        // Banner image and a single icon.
+        banner.resize(0x72A0, 0xAB);
+        banner
+    }
+
+    // Builds a synthetic data.bin from the provided entries, where None marks a directory.
+    fn build_save(entries: &[(&str, Option<&[u8]>)]) -> Vec<u8> {
+        let banner = build_banner("Test Save", "rustii");
+        let mut header = Vec::new();
+        header.write_u64::<BigEndian>(0x0001000152535449).unwrap();
+        header.write_u32::<BigEndian>(banner.len() as u32).unwrap();
+        header.write_u8(0x3C).unwrap();
+        header.write_u8(0).unwrap();
+        header.extend_from_slice(&[0u8; 16]);
+        header.resize(HEADER_BANNER_OFFSET, 0);
+        header.extend_from_slice(&banner);
+        header.resize(HEADER_SIZE, 0);
+        let md5 = header_md5(&header);
+        header[HEADER_MD5_OFFSET..HEADER_MD5_OFFSET + 16].copy_from_slice(&md5);
+        let mut save = encrypt_sd(&header, SD_IV);
+        let mut files = Vec::new();
+        for (i, (name, data)) in entries.iter().enumerate() {
+            let iv = [i as u8 + 1; 16];
+            files.write_u32::<BigEndian>(FILE_HEADER_MAGIC).unwrap();
+            files.write_u32::<BigEndian>(data.map_or(0, |d| d.len() as u32)).unwrap();
+            files.write_u8(0x3C).unwrap();
+            files.write_u8(0).unwrap();
+            files.write_u8(if data.is_some() { 1 } else { 2 }).unwrap();
+            let mut name_data = name.as_bytes().to_vec();
+            name_data.resize(FILE_NAME_SIZE, 0);
+            files.write_all(&name_data).unwrap();
+            files.write_all(&iv).unwrap();
+            files.resize(files.len() + 0x20, 0);
+            if let Some(data) = data {
+                let mut data = data.to_vec();
+                data.resize((data.len() + 0x3F) & !0x3F, 0);
+                files.write_all(&encrypt_sd(&data, iv)).unwrap();
+            }
+        }
+        save.write_u32::<BigEndian>(0x70).unwrap();
+        save.write_u32::<BigEndian>(BK_HEADER_MAGIC).unwrap();
+        save.write_u32::<BigEndian>(0x0403AC68).unwrap();
+        save.write_u32::<BigEndian>(entries.len() as u32).unwrap();
+        save.write_u32::<BigEndian>(files.len() as u32).unwrap();
+        save.resize(HEADER_SIZE + 0x60, 0);
+        save.write_u64::<BigEndian>(0x0001000152535449).unwrap();
+        save.write_all(&[0x00, 0x17, 0xAB, 0x12, 0x34, 0x56]).unwrap();
+        save.resize(HEADER_SIZE + BK_HEADER_SIZE, 0);
+        save.extend_from_slice(&files);
+        save
+    }
+
+    #[test]
+    fn test_parse_save() {
+        let file_data: Vec<u8> = (0..0x95).map(|i| i as u8).collect();
+        let save_data = build_save(&[("save.dat", Some(&file_data)), ("replays", None), ("replays/01.bin", Some(b"replay"))]);
+        assert_eq!(&save_data[SAVE_BK_OFFSET as usize..SAVE_BK_OFFSET as usize + 8], SAVE_BK_MAGIC);
+        let save = SaveData::from_bytes(&save_data).unwrap();
+        assert_eq!(save.title_id, TitleId::from_hex("0001000152535449").unwrap());
+        assert_eq!(save.permissions, 0x3C);
+        assert_eq!(save.banner_data.len(), 0x72A0);
+        assert_eq!(save.banner.title, "Test Save");
+        assert_eq!(save.banner.subtitle, "rustii");
+        assert_eq!(save.banner.animation_speed, 3);
+        assert_eq!(save.ng_id, 0x0403AC68);
+        assert_eq!(save.mac_address, [0x00, 0x17, 0xAB, 0x12, 0x34, 0x56]);
+        assert_eq!(save.files.len(), 3);
+        assert_eq!(save.files[0].name, "save.dat");
+        assert_eq!(save.files[0].data, file_data);
+        assert_eq!(save.files[1].file_type, SaveFileType::Directory);
+        assert!(save.files[1].data.is_empty());
+        assert_eq!(save.files[2].name, "replays/01.bin");
+        assert_eq!(save.files[2].data, b"replay");
+    }
+
+    #[test]
+    fn test_invalid_save() {
+        let mut save_data = build_save(&[("save.dat", Some(b"data"))]);
+        assert!(matches!(SaveData::from_bytes(&save_data[..0x100]), Err(SaveError::InvalidLength)));
+        assert!(matches!(SaveData::from_bytes(&save_data[..save_data.len() - 0x10]), Err(SaveError::TruncatedFile(_))));
+        let unsafe_save = build_save(&[("../save.dat", Some(b"data"))]);
+        assert!(matches!(SaveData::from_bytes(&unsafe_save), Err(SaveError::InvalidFileName(_))));
+        save_data[HEADER_SIZE + 4] = 0;
+        assert!(matches!(SaveData::from_bytes(&save_data), Err(SaveError::InvalidBkHeader(_))));
+        save_data[0x40] ^= 0xFF;
+        assert!(matches!(SaveData::from_bytes(&save_data), Err(SaveError::HeaderHashMismatch { .. })));
+    }
+}
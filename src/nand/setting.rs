@@ -5,10 +5,41 @@
 // Menu's data.
 
 use std::collections::HashMap;
-use std::io::Cursor;
-use byteorder::ReadBytesExt;
+use thiserror::Error;
 
 const SETTINGS_KEY: u32 = 0x73B5DBFA;
+const SETTINGS_SIZE: usize = 256;
+
+#[derive(Debug, Error)]
+pub enum SettingError {
+    #[error("setting.txt must be {SETTINGS_SIZE} bytes long, but only {0} bytes were provided")]
+    InvalidLength(usize),
+    #[error("setting.txt contains an invalid line `{0}`, lines must be in the format KEY=VALUE")]
+    InvalidLine(String),
+    #[error("setting.txt is missing the required key `{0}`")]
+    MissingKey(String),
+    #[error("`{0}` is not a known setting.txt key")]
+    UnknownKey(String),
+    #[error("`{0}` is not a valid value for the DVD key, it must be a number from 0-255")]
+    InvalidDvd(String),
+    #[error("setting.txt contents are {0} bytes long, but cannot be longer than {SETTINGS_SIZE} bytes")]
+    TooLong(usize),
+}
+
+/// The keys stored in setting.txt, in the order that they're written.
+pub const SETTING_KEYS: [&str; 8] = ["AREA", "MODEL", "DVD", "MPCH", "CODE", "SERNO", "VIDEO", "GAME"];
+
+// Encrypts or decrypts setting.txt data. The encryption is a simple rolling XOR, so the same
+// operation works in both directions.
+fn xor_setting(data: &[u8]) -> Vec<u8> {
+    let mut key: u32 = SETTINGS_KEY;
+    let mut out: Vec<u8> = Vec::with_capacity(data.len());
+    for byte in data {
+        out.push(byte ^ (key & 0xFF) as u8);
+        key = key.rotate_left(1); // Automatic bit rotation!? Thanks for the tip clippy!
+    }
+    out
+}
 
 /// A structure that allows for encrypting, decrypting, parsing, and editing a setting.txt file.
 pub struct SettingTxt {
@@ -24,77 +55,142 @@ pub struct SettingTxt {
 
 impl SettingTxt {
     /// Creates a new SettingTxt instance from the binary data of an encrypted setting.txt file.
-    pub fn from_bytes(data: &[u8]) -> Result<Self, std::io::Error> {
+    pub fn from_bytes(data: &[u8]) -> Result<Self, SettingError> {
         // Unlike most files we have to deal with, setting.txt is encrypted. This means we need to
         // decrypt it first, and *then* we can parse it.
-        let mut buf = Cursor::new(data);
-        let mut key: u32 = SETTINGS_KEY;
-        let mut dec_data: Vec<u8> = Vec::new();
-        for _ in 0..256 {
-            dec_data.push(buf.read_u8()? ^ (key & 0xFF) as u8);
-            key = key.rotate_left(1); // Automatic bit rotation!? Thanks for the tip clippy!
+        if data.len() < SETTINGS_SIZE {
+            return Err(SettingError::InvalidLength(data.len()));
         }
+        let dec_data = xor_setting(&data[..SETTINGS_SIZE]);
+        // Everything after the last line is padding that was encrypted along with the text, so
+        // decrypts to garbage that needs to be dropped.
         let setting_str = String::from_utf8_lossy(&dec_data);
-        let setting_str = setting_str[0..setting_str.clone().rfind('\n').unwrap_or(setting_str.len() - 2) + 1].to_string();
-        let setting_txt = SettingTxt::from_string(setting_str)?;
-        Ok(setting_txt)
+        let setting_str = match setting_str.rfind('\n') {
+            Some(end) => setting_str[..end + 1].to_string(),
+            None => setting_str.to_string(),
+        };
+        SettingTxt::from_string(setting_str)
     }
 
     /// Creates a new SettingTxt instance from the decrypted text of a setting.txt file.
-    pub fn from_string(data: String) -> Result<Self, std::io::Error> {
+    pub fn from_string(data: String) -> Result<Self, SettingError> {
         let mut setting_keys: HashMap<String, String> = HashMap::new();
-        for line in data.lines() {
-            let (key, value) = line.split_once("=").unwrap();
+        for line in data.lines().filter(|line| !line.is_empty()) {
+            let (key, value) = line.split_once("=").ok_or_else(|| SettingError::InvalidLine(line.to_owned()))?;
             setting_keys.insert(key.to_owned(), value.to_owned());
         }
-        let area = setting_keys["AREA"].to_string();
-        let model = setting_keys["MODEL"].to_string();
-        let dvd = setting_keys["DVD"].as_str().parse::<u8>().unwrap();
-        let mpch = setting_keys["MPCH"].to_string();
-        let code = setting_keys["CODE"].to_string();
-        let serial_number = setting_keys["SERNO"].to_string();
-        let video = setting_keys["VIDEO"].to_string();
-        let game = setting_keys["GAME"].to_string();
+        let get_key = |key: &str| setting_keys.get(key).cloned().ok_or_else(|| SettingError::MissingKey(key.to_owned()));
+        let dvd = get_key("DVD")?;
         Ok(SettingTxt {
-            area,
-            model,
-            dvd,
-            mpch,
-            code,
-            serial_number,
-            video,
-            game,
+            area: get_key("AREA")?,
+            model: get_key("MODEL")?,
+            dvd: dvd.parse::<u8>().map_err(|_| SettingError::InvalidDvd(dvd))?,
+            mpch: get_key("MPCH")?,
+            code: get_key("CODE")?,
+            serial_number: get_key("SERNO")?,
+            video: get_key("VIDEO")?,
+            game: get_key("GAME")?,
         })
     }
-    
+
+    /// Gets the value of a setting.txt key by its name (e.g. "AREA"). Returns None if the key
+    /// isn't a known setting.txt key.
+    pub fn get(&self, key: &str) -> Option<String> {
+        match key.to_ascii_uppercase().as_str() {
+            "AREA" => Some(self.area.clone()),
+            "MODEL" => Some(self.model.clone()),
+            "DVD" => Some(self.dvd.to_string()),
+            "MPCH" => Some(self.mpch.clone()),
+            "CODE" => Some(self.code.clone()),
+            "SERNO" => Some(self.serial_number.clone()),
+            "VIDEO" => Some(self.video.clone()),
+            "GAME" => Some(self.game.clone()),
+            _ => None,
+        }
+    }
+
+    /// Sets the value of a setting.txt key by its name (e.g. "AREA").
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), SettingError> {
+        match key.to_ascii_uppercase().as_str() {
+            "AREA" => self.area = value.to_owned(),
+            "MODEL" => self.model = value.to_owned(),
+            "DVD" => self.dvd = value.parse::<u8>().map_err(|_| SettingError::InvalidDvd(value.to_owned()))?,
+            "MPCH" => self.mpch = value.to_owned(),
+            "CODE" => self.code = value.to_owned(),
+            "SERNO" => self.serial_number = value.to_owned(),
+            "VIDEO" => self.video = value.to_owned(),
+            "GAME" => self.game = value.to_owned(),
+            _ => return Err(SettingError::UnknownKey(key.to_owned())),
+        }
+        Ok(())
+    }
+
     /// Encrypts and then dumps the data in a SettingTxt instance back into binary data that can be
     /// written to a file.
-    pub fn to_bytes(&self) -> Result<Vec<u8>, std::io::Error> {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SettingError> {
         let setting_str = self.to_string()?;
-        let setting_bytes = setting_str.as_bytes();
-        let mut buf = Cursor::new(setting_bytes);
-        let mut key: u32 = SETTINGS_KEY;
-        let mut enc_data: Vec<u8> = Vec::new();
-        for _ in 0..setting_str.len() {
-            enc_data.push(buf.read_u8()? ^ (key & 0xFF) as u8);
-            key = key.rotate_left(1);
+        if setting_str.len() > SETTINGS_SIZE {
+            return Err(SettingError::TooLong(setting_str.len()));
         }
-        enc_data.resize(256, 0);
+        let mut enc_data = xor_setting(setting_str.as_bytes());
+        enc_data.resize(SETTINGS_SIZE, 0);
         Ok(enc_data)
     }
-    
+
     /// Dumps the decrypted data in a SettingTxt instance into a string that can be written to a
     /// file.
-    pub fn to_string(&self) -> Result<String, std::io::Error> {
+    pub fn to_string(&self) -> Result<String, SettingError> {
         let mut setting_str = String::new();
-        setting_str += &format!("AREA={}\r\n", self.area);
-        setting_str += &format!("MODEL={}\r\n", self.model);
-        setting_str += &format!("DVD={}\r\n", self.dvd);
-        setting_str += &format!("MPCH={}\r\n", self.mpch);
-        setting_str += &format!("CODE={}\r\n", self.code);
-        setting_str += &format!("SERNO={}\r\n", self.serial_number);
-        setting_str += &format!("VIDEO={}\r\n", self.video);
-        setting_str += &format!("GAME={}\r\n", self.game);
+        for key in SETTING_KEYS {
+            setting_str += &format!("{}={}\r\n", key, self.get(key).unwrap());
+        }
         Ok(setting_str)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_SETTING: &str = "AREA=USA\r\nMODEL=RVL-001(USA)\r\nDVD=0\r\nMPCH=0x7FFE\r\nCODE=LU\r\nSERNO=123456789\r\nVIDEO=NTSC\r\nGAME=US\r\n";
+
+    #[test]
+    fn test_xor_known_pairs() {
+        // The first bytes of the key stream are 0xFA, 0xF4, 0xE9, 0xD3, as the key rotates left.
+        assert_eq!(xor_setting(b"AREA=USA\r\n"), [0xBB, 0xA6, 0xAC, 0x92, 0x9A, 0x1B, 0xCF, 0x78, 0x7E, 0xED]);
+        assert_eq!(xor_setting(&[0xBB, 0xA6, 0xAC, 0x92]), b"AREA");
+        assert_eq!(xor_setting(&[0u8; 4]), [0xFA, 0xF4, 0xE9, 0xD3]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let setting = SettingTxt::from_string(TEST_SETTING.to_owned()).unwrap();
+        assert_eq!(setting.to_string().unwrap(), TEST_SETTING);
+        let data = setting.to_bytes().unwrap();
+        assert_eq!(data.len(), SETTINGS_SIZE);
+        assert_eq!(&data[..4], [0xBB, 0xA6, 0xAC, 0x92]);
+        assert!(data[TEST_SETTING.len()..].iter().all(|byte| *byte == 0));
+        let mut reparsed = SettingTxt::from_bytes(&data).unwrap();
+        assert_eq!(reparsed.to_string().unwrap(), TEST_SETTING);
+        reparsed.set("area", "EUR").unwrap();
+        reparsed.set("DVD", "1").unwrap();
+        assert_eq!(reparsed.get("AREA").as_deref(), Some("EUR"));
+        assert_eq!(reparsed.dvd, 1);
+        let reparsed = SettingTxt::from_bytes(&reparsed.to_bytes().unwrap()).unwrap();
+        assert_eq!(reparsed.area, "EUR");
+        assert_eq!(reparsed.serial_number, "123456789");
+    }
+
+    #[test]
+    fn test_invalid_settings() {
+        let mut setting = SettingTxt::from_string(TEST_SETTING.to_owned()).unwrap();
+        assert!(matches!(setting.set("REGION", "USA"), Err(SettingError::UnknownKey(_))));
+        assert!(matches!(setting.set("DVD", "x"), Err(SettingError::InvalidDvd(_))));
+        assert!(setting.get("REGION").is_none());
+        setting.set("GAME", &"A".repeat(300)).unwrap();
+        assert!(matches!(setting.to_bytes(), Err(SettingError::TooLong(_))));
+        assert!(matches!(SettingTxt::from_string(TEST_SETTING.replace("GAME=US\r\n", "")), Err(SettingError::MissingKey(key)) if key == "GAME"));
+        assert!(matches!(SettingTxt::from_string(String::from("AREA\r\n")), Err(SettingError::InvalidLine(_))));
+        assert!(matches!(SettingTxt::from_bytes(&[0u8; 16]), Err(SettingError::InvalidLength(16))));
+    }
+}
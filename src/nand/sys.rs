@@ -7,51 +7,68 @@
 use std::io::{Cursor, Read, Write};
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use thiserror::Error;
+use crate::title::TitleId;
 
 #[derive(Debug, Error)]
 pub enum UidSysError {
-    #[error("uid.sys is an invalid length and cannot be parsed")]
-    InvalidUidSysLength,
     #[error("uid.sys data is not in a valid format")]
     IO(#[from] std::io::Error),
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 /// A structure that represents a Title ID/UID pairing in a uid.sys file.
 pub struct UidSysEntry {
-    pub title_id: [u8; 8],
+    pub title_id: TitleId,
     pub uid: u32,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// A problem found in a uid.sys file that the Wii would not have created itself.
+pub enum UidSysAnomaly {
+    /// A Title ID appears in more than one entry.
+    DuplicateTitleId(TitleId),
+    /// An entry's UID is not higher than the UID of the entry before it.
+    NonMonotonicUid { title_id: TitleId, uid: u32, previous_uid: u32 },
+}
+
 /// A structure that allows for creating, parsing, and editing a /sys/uid.sys file.
 pub struct UidSys {
     entries: Vec<UidSysEntry>,
+    discarded_bytes: usize,
+}
+
+impl Default for UidSys {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl UidSys {
-    /// Creates a new UidSys instance from the binary data of a uid.sys file.
+    /// Creates a new UidSys instance from the binary data of a uid.sys file. Each entry is 12
+    /// bytes long, and a partial entry at the end of the file is discarded rather than causing an
+    /// error. See UidSys::discarded_bytes().
     pub fn from_bytes(data: &[u8]) -> Result<Self, UidSysError> {
-        // The uid.sys file must be divisible by a multiple of 12, or something is wrong, since each
-        // entry is 12 bytes long.
-        if (data.len() % 12) != 0 {
-            return Err(UidSysError::InvalidUidSysLength);
-        }
         let entry_count = data.len() / 12;
+        let discarded_bytes = data.len() % 12;
+        if discarded_bytes > 0 {
+            log::warn!("discarding {} trailing bytes from uid.sys, which don't make up a whole entry", discarded_bytes);
+        }
         let mut buf = Cursor::new(data);
         let mut entries: Vec<UidSysEntry> = Vec::new();
         for _ in 0..entry_count {
             let mut title_id = [0u8; 8];
             buf.read_exact(&mut title_id)?;
             let uid = buf.read_u32::<BigEndian>()?;
-            entries.push(UidSysEntry { title_id, uid });
+            entries.push(UidSysEntry { title_id: TitleId::new(title_id), uid });
         }
-        Ok(UidSys { entries })
+        Ok(UidSys { entries, discarded_bytes })
     }
     
     /// Creates a new UidSys instance and initializes it with the default entry of the Wii Menu
     /// (0000000100000002) with UID 0x1000.
     pub fn new() -> Self {
-        let mut uid_sys = UidSys { entries: Vec::new() };
-        uid_sys.add(&[0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x2]).unwrap();
+        let mut uid_sys = UidSys { entries: Vec::new(), discarded_bytes: 0 };
+        uid_sys.add(TitleId::new([0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x2])).unwrap();
         uid_sys
     }
     
@@ -59,19 +76,58 @@ impl UidSys {
     pub fn to_bytes(&self) -> Result<Vec<u8>, UidSysError> {
         let mut buf: Vec<u8> = Vec::new();
         for entry in self.entries.iter() {
-            buf.write_all(&entry.title_id)?;
+            buf.write_all(entry.title_id.as_ref())?;
             buf.write_u32::<BigEndian>(entry.uid)?;
         }
         Ok(buf)
     }
     
+    /// Gets the number of bytes at the end of the uid.sys data that were discarded when it was
+    /// parsed, because they didn't make up a whole entry. These bytes are not written back out.
+    pub fn discarded_bytes(&self) -> usize {
+        self.discarded_bytes
+    }
+
+    /// Removes entries for Title IDs that already appeared in an earlier entry, keeping the first
+    /// entry for each Title ID so that existing titles keep their UIDs. Returns the entries that
+    /// were removed.
+    pub fn repair(&mut self) -> Vec<UidSysEntry> {
+        let mut kept: Vec<UidSysEntry> = Vec::new();
+        let mut removed: Vec<UidSysEntry> = Vec::new();
+        for entry in self.entries.drain(..) {
+            if kept.iter().any(|prev| prev.title_id == entry.title_id) {
+                removed.push(entry);
+            } else {
+                kept.push(entry);
+            }
+        }
+        self.entries = kept;
+        self.discarded_bytes = 0;
+        removed
+    }
+
+    /// Gets all the Title ID/UID pairings in uid.sys, in the order that they appear in the file.
+    pub fn entries(&self) -> &[UidSysEntry] {
+        &self.entries
+    }
+
+    /// Gets the UID assigned to a Title ID, if the Title ID is present in uid.sys.
+    pub fn get_uid(&self, title_id: TitleId) -> Option<u32> {
+        self.entries.iter().find(|entry| entry.title_id == title_id).map(|entry| entry.uid)
+    }
+
+    /// Checks whether a Title ID is present in uid.sys.
+    pub fn contains(&self, title_id: TitleId) -> bool {
+        self.get_uid(title_id).is_some()
+    }
+
     /// Adds a new Title ID to uid.sys, and assigns it a new UID. The new Title ID will only be 
-    /// added if it is not already present in the file. Returns None if the Title ID was already
-    /// present, or the newly assigned UID if the Title ID was just added.
-    pub fn add(&mut self, title_id: &[u8; 8]) -> Result<Option<u32>, UidSysError> {
-        // Return None if the Title ID is already accounted for.
-        if self.entries.iter().any(|entry| entry.title_id == *title_id) {
-            return Ok(None);
+    /// added if it is not already present in the file. Returns the UID assigned to the Title ID,
+    /// which will be its existing UID if it was already present.
+    pub fn add(&mut self, title_id: TitleId) -> Result<u32, UidSysError> {
+        // Return the existing UID if the Title ID is already accounted for.
+        if let Some(uid) = self.get_uid(title_id) {
+            return Ok(uid);
         }
         // Find the highest UID and increment it to choose the UID for the new Title ID.
         let max_uid = self.entries.iter()
@@ -79,9 +135,109 @@ impl UidSys {
             .map(|entry| entry.uid)
             .unwrap_or(4095);
         self.entries.push(UidSysEntry {
-            title_id: *title_id,
+            title_id,
             uid: max_uid + 1,
         });
-        Ok(Some(max_uid + 1))
+        Ok(max_uid + 1)
+    }
+
+    /// Checks uid.sys for entries that the Wii would not have created itself, which are Title IDs
+    /// that appear more than once and UIDs that don't increase from one entry to the next.
+    pub fn anomalies(&self) -> Vec<UidSysAnomaly> {
+        let mut anomalies: Vec<UidSysAnomaly> = Vec::new();
+        for (i, entry) in self.entries.iter().enumerate() {
+            if self.entries[..i].iter().any(|prev| prev.title_id == entry.title_id)
+                && !anomalies.contains(&UidSysAnomaly::DuplicateTitleId(entry.title_id)) {
+                anomalies.push(UidSysAnomaly::DuplicateTitleId(entry.title_id));
+            }
+            if i > 0 && entry.uid <= self.entries[i - 1].uid {
+                anomalies.push(UidSysAnomaly::NonMonotonicUid {
+                    title_id: entry.title_id,
+                    uid: entry.uid,
+                    previous_uid: self.entries[i - 1].uid,
+                });
+            }
+        }
+        anomalies
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_uid_sys(entries: &[(&str, u32)]) -> Vec<u8> {
+        let mut data: Vec<u8> = Vec::new();
+        for (tid, uid) in entries {
+            data.extend_from_slice(&hex::decode(tid).unwrap());
+            data.extend_from_slice(&uid.to_be_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let data = build_uid_sys(&[("0000000100000002", 0x1000), ("0001000148414445", 0x1001)]);
+        let mut uid_sys = UidSys::from_bytes(&data).unwrap();
+        assert_eq!(uid_sys.entries().len(), 2);
+        assert_eq!(uid_sys.to_bytes().unwrap(), data);
+        let channel = TitleId::from_hex("0001000148414445").unwrap();
+        assert!(uid_sys.contains(channel));
+        assert_eq!(uid_sys.get_uid(channel), Some(0x1001));
+        assert_eq!(uid_sys.add(channel).unwrap(), 0x1001);
+        let new_title = TitleId::from_hex("0001000152535449").unwrap();
+        assert!(!uid_sys.contains(new_title));
+        assert_eq!(uid_sys.add(new_title).unwrap(), 0x1002);
+        assert!(uid_sys.anomalies().is_empty());
+        let reparsed = UidSys::from_bytes(&uid_sys.to_bytes().unwrap()).unwrap();
+        assert_eq!(reparsed.entries(), uid_sys.entries());
+        assert_eq!(reparsed.get_uid(new_title), Some(0x1002));
+        // A partial final entry is discarded instead of causing an error.
+        let truncated = UidSys::from_bytes(&data[..20]).unwrap();
+        assert_eq!(truncated.entries().len(), 1);
+        assert_eq!(truncated.discarded_bytes(), 8);
+        assert_eq!(truncated.to_bytes().unwrap(), data[..12]);
+    }
+
+    #[test]
+    fn test_anomalies() {
+        let data = build_uid_sys(&[
+            ("0000000100000002", 0x1000),
+            ("0001000148414445", 0x1002),
+            ("0001000148414445", 0x1001),
+            ("0001000148414445", 0x1003),
+        ]);
+        let uid_sys = UidSys::from_bytes(&data).unwrap();
+        let channel = TitleId::from_hex("0001000148414445").unwrap();
+        assert_eq!(uid_sys.anomalies(), vec![
+            UidSysAnomaly::DuplicateTitleId(channel),
+            UidSysAnomaly::NonMonotonicUid { title_id: channel, uid: 0x1001, previous_uid: 0x1002 },
+        ]);
+        assert_eq!(UidSys::new().entries()[0].uid, 0x1000);
+    }
+
+    #[test]
+    fn test_repair() {
+        let mut data = build_uid_sys(&[
+            ("0000000100000002", 0x1000),
+            ("0001000148414445", 0x1001),
+            ("0000000100000002", 0x1002),
+            ("0001000148414445", 0x1003),
+            ("0001000152535449", 0x1004),
+        ]);
+        data.extend_from_slice(&[0x00, 0x01, 0x00]);
+        let mut uid_sys = UidSys::from_bytes(&data).unwrap();
+        assert_eq!(uid_sys.discarded_bytes(), 3);
+        let removed = uid_sys.repair();
+        assert_eq!(removed.iter().map(|entry| entry.uid).collect::<Vec<u32>>(), vec![0x1002, 0x1003]);
+        assert_eq!(uid_sys.discarded_bytes(), 0);
+        assert!(uid_sys.anomalies().is_empty());
+        assert_eq!(uid_sys.to_bytes().unwrap(), build_uid_sys(&[
+            ("0000000100000002", 0x1000),
+            ("0001000148414445", 0x1001),
+            ("0001000152535449", 0x1004),
+        ]));
+        // New Title IDs still get a UID higher than any that was in use.
+        assert_eq!(uid_sys.add(TitleId::from_hex("0001000148414446").unwrap()).unwrap(), 0x1005);
     }
 }
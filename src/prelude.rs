@@ -0,0 +1,33 @@
+// prelude.rs from rustii (c) 2025 NinjaCheetah & Contributors
+// https://github.com/NinjaCheetah/rustii
+//
+// Re-exports the types needed for the most common workflows, so that they can be imported at once.
+
+//! The types used by most programs that work with Wii titles and archives, along with their error
+//! types, so that they can all be imported with a single `use`.
+//!
+//! ```
+//! use rustii::prelude::*;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! // A blank Ticket can be created for any title, and survives being written out and read back.
+//! let title_id: TitleId = "0001000148414241".parse()?;
+//! let ticket = Ticket::from_bytes(&Ticket::blank(title_id).to_bytes()?)?;
+//! assert_eq!(ticket.title_id(), title_id);
+//! assert_eq!(ticket.title_id().ascii_low().as_deref(), Some("HABA"));
+//!
+//! // Signed files can be identified from their data alone.
+//! assert!(matches!(SignedFile::from_bytes(&ticket.to_bytes()?)?, SignedFile::Ticket(_)));
+//! # Ok(())
+//! # }
+//! ```
+
+pub use crate::archive::u8::{U8Archive, U8Error};
+pub use crate::title::{SignedFile, Title, TitleError, TitleId};
+pub use crate::title::cert::{CertIdentity, Certificate, CertificateChain, CertificateError};
+pub use crate::title::content::{ContentError, ContentRegion};
+pub use crate::title::signed::SignedFileError;
+pub use crate::title::ticket::{Ticket, TicketError};
+pub use crate::title::title_id::TitleIdError;
+pub use crate::title::tmd::{ContentRecord, ContentType, TMD, TMDError};
+pub use crate::title::wad::{WAD, WADError, WADType};
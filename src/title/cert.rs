@@ -3,7 +3,12 @@
 //
 // Implements the structures and methods required for validating the signatures of Wii titles.
 
+use std::fmt;
+#[cfg(feature = "fs")]
+use std::fs;
 use std::io::{Cursor, Read, Write, SeekFrom, Seek};
+#[cfg(feature = "fs")]
+use std::path::{Path, PathBuf};
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use rsa::pkcs8::DecodePublicKey;
 use rsa::pkcs1v15::Pkcs1v15Sign;
@@ -12,6 +17,14 @@ use sha1::{Digest, Sha1};
 use thiserror::Error;
 use crate::title::{tmd, ticket};
 
+/// Converts the raw bytes of a signature issuer or certificate identity field into a string. The
+/// name ends at the first byte that isn't a printable ASCII character, so padding made of null
+/// bytes, 0xFF, or other garbage is never included in the result.
+pub fn issuer_to_string(issuer: &[u8]) -> String {
+    let end = issuer.iter().position(|byte| !byte.is_ascii_graphic()).unwrap_or(issuer.len());
+    String::from_utf8_lossy(&issuer[..end]).into_owned()
+}
+
 #[derive(Debug, Error)]
 pub enum CertificateError {
     #[error("certificate appears to be signed with invalid key type `{0}`")]
@@ -26,17 +39,216 @@ pub enum CertificateError {
     IncorrectCertificate(String),
     #[error("the data you are attempting to verify was not signed with the provided certificate")]
     NonMatchingCertificates,
+    #[error("TMD processing error")]
+    TMD(#[from] tmd::TMDError),
+    #[error("Ticket processing error")]
+    Ticket(#[from] ticket::TicketError),
+    #[cfg(feature = "fs")]
+    #[error("certificate chain file `{}` could not be read", path.display())]
+    ReadFile { path: PathBuf, source: std::io::Error },
     #[error("certificate data is not in a valid format")]
     IO(#[from] std::io::Error),
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The detailed result of verifying a signature, which separates signatures that are genuinely
+/// valid from ones that IOS only accepts because of the trucha bug.
+pub enum SignatureStatus {
+    /// The signature was made with the certificate's key and is correctly padded.
+    Valid,
+    /// The signature was made with the certificate's key and contains a hash that IOS's buggy
+    /// strncmp() comparison accepts, but its PKCS#1 padding is malformed. This will only install
+    /// on a console with the trucha bug.
+    ValidTruchaOnly,
+    /// The signature is null and the hash of the signed data starts with 0x00.
+    Fakesigned,
+    /// The signature is not valid.
+    Invalid,
+}
+
+impl SignatureStatus {
+    /// Gets whether the signature was made with the certificate's key, regardless of whether its
+    /// padding is correct.
+    pub fn is_genuine(&self) -> bool {
+        matches!(self, SignatureStatus::Valid | SignatureStatus::ValidTruchaOnly)
+    }
+}
+
+impl fmt::Display for SignatureStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SignatureStatus::Valid => write!(f, "Valid"),
+            SignatureStatus::ValidTruchaOnly => write!(f, "Valid (Trucha Only)"),
+            SignatureStatus::Fakesigned => write!(f, "Fakesigned"),
+            SignatureStatus::Invalid => write!(f, "Invalid"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The known certificates that Wii files are signed with, identified by their full issuer path
+/// (like "Root-CA00000001-CP00000004").
+pub enum CertIdentity {
+    /// Root-CA00000001, the retail CA certificate.
+    RetailCA,
+    /// Root-CA00000002, the development CA certificate.
+    DevCA,
+    /// Root-CA00000001-CP00000004, the retail TMD certificate.
+    RetailCP,
+    /// Root-CA00000002-CP00000007, the development TMD certificate. The older CP00000005 is also
+    /// treated as this certificate.
+    DevCP,
+    /// Root-CA00000001-XS00000003, the retail Ticket certificate.
+    RetailXS,
+    /// Root-CA00000002-XS00000006, the development Ticket certificate. The older XS00000004 is also
+    /// treated as this certificate.
+    DevXS,
+    /// Root-CA10000000, the CA certificate used by Wii-based arcade boards.
+    ArcadeCA,
+    /// Root-CA10000000-CP10000000, the TMD certificate used by Wii-based arcade boards.
+    ArcadeCP,
+    /// Any other certificate, holding its full issuer path.
+    Unknown(String),
+}
+
+impl CertIdentity {
+    /// Identifies a certificate from its full issuer path, like the signature issuer of a TMD or
+    /// Ticket.
+    pub fn from_issuer(issuer: &str) -> CertIdentity {
+        match issuer {
+            "Root-CA00000001" => CertIdentity::RetailCA,
+            "Root-CA00000002" => CertIdentity::DevCA,
+            "Root-CA00000001-CP00000004" => CertIdentity::RetailCP,
+            "Root-CA00000002-CP00000005" | "Root-CA00000002-CP00000007" => CertIdentity::DevCP,
+            "Root-CA00000001-XS00000003" => CertIdentity::RetailXS,
+            "Root-CA00000002-XS00000004" | "Root-CA00000002-XS00000006" => CertIdentity::DevXS,
+            "Root-CA10000000" => CertIdentity::ArcadeCA,
+            "Root-CA10000000-CP10000000" => CertIdentity::ArcadeCP,
+            _ => CertIdentity::Unknown(issuer.to_owned()),
+        }
+    }
+
+    /// Gets whether a certificate is one of the retail certificates.
+    pub fn is_retail(&self) -> bool {
+        matches!(self, CertIdentity::RetailCA | CertIdentity::RetailCP | CertIdentity::RetailXS)
+    }
+
+    /// Gets whether a certificate is one of the development certificates.
+    pub fn is_dev(&self) -> bool {
+        matches!(self, CertIdentity::DevCA | CertIdentity::DevCP | CertIdentity::DevXS)
+    }
+
+    /// Gets whether a certificate is one of the arcade certificates.
+    pub fn is_arcade(&self) -> bool {
+        matches!(self, CertIdentity::ArcadeCA | CertIdentity::ArcadeCP)
+    }
+
+    /// Gets the name of the environment that a certificate belongs to, which is "Retail",
+    /// "Development", "Arcade", or "Unknown".
+    pub fn environment(&self) -> &'static str {
+        if self.is_retail() {
+            "Retail"
+        } else if self.is_dev() {
+            "Development"
+        } else if self.is_arcade() {
+            "Arcade"
+        } else {
+            "Unknown"
+        }
+    }
+}
+
+// The DER-encoded DigestInfo that precedes a SHA-1 hash in a PKCS#1 v1.5 signature.
+const SHA1_DIGEST_INFO: [u8; 15] = [0x30, 0x21, 0x30, 0x09, 0x06, 0x05, 0x2B, 0x0E, 0x03, 0x02, 0x1A, 0x05, 0x00, 0x04, 0x14];
+
+// Compares two hashes the same way IOS does, using strncmp(). The comparison stops at the first
+// null byte, so any hashes that match up to and including a null byte are considered equal.
+fn strncmp_hashes(a: &[u8], b: &[u8]) -> bool {
+    for (byte_a, byte_b) in a.iter().zip(b.iter()) {
+        if byte_a != byte_b {
+            return false;
+        }
+        if *byte_a == 0 {
+            return true;
+        }
+    }
+    true
+}
+
+// Classifies a signature over data with the provided SHA-1 hash, using the public key from the
+// certificate that the data claims to be signed by.
+fn classify_signature(cert: &Certificate, hash: &[u8], signature: &[u8]) -> SignatureStatus {
+    if signature.iter().all(|byte| *byte == 0) {
+        // A null signature "decrypts" to all zeroes, which only passes the strncmp() check when
+        // the hash also starts with a null byte.
+        return if hash.first() == Some(&0) { SignatureStatus::Fakesigned } else { SignatureStatus::Invalid };
+    }
+    if matches!(cert.pub_key_type, CertificateKeyType::ECC) {
+        return SignatureStatus::Invalid;
+    }
+    let modulus = BigUint::from_bytes_be(&cert.pub_key_modulus);
+    let signature_int = BigUint::from_bytes_be(signature);
+    if signature_int >= modulus {
+        return SignatureStatus::Invalid;
+    }
+    let decrypted = signature_int.modpow(&BigUint::from(cert.pub_key_exponent), &modulus).to_bytes_be();
+    let key_len = cert.pub_key_modulus.len();
+    let mut block = vec![0u8; key_len - decrypted.len()];
+    block.extend_from_slice(&decrypted);
+    // A correctly padded block is 00 01 FF ... FF 00, followed by the DigestInfo and the hash.
+    let mut expected = vec![0x00, 0x01];
+    expected.resize(key_len - SHA1_DIGEST_INFO.len() - hash.len() - 1, 0xFF);
+    expected.push(0x00);
+    expected.extend_from_slice(&SHA1_DIGEST_INFO);
+    expected.extend_from_slice(hash);
+    if block == expected {
+        SignatureStatus::Valid
+    } else if strncmp_hashes(&block[key_len - hash.len()..], hash) {
+        SignatureStatus::ValidTruchaOnly
+    } else {
+        SignatureStatus::Invalid
+    }
+}
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CertificateKeyType {
     Rsa4096,
     Rsa2048,
     ECC
 }
 
+impl CertificateKeyType {
+    /// Gets the type of key used to create a signature from the signature type stored at the start
+    /// of a TMD, Ticket, or certificate. Returns None if the signature type isn't known.
+    pub fn from_signature_type(signature_type: u32) -> Option<Self> {
+        match signature_type {
+            0x00010000 => Some(CertificateKeyType::Rsa4096),
+            0x00010001 => Some(CertificateKeyType::Rsa2048),
+            0x00010002 => Some(CertificateKeyType::ECC),
+            _ => None,
+        }
+    }
+
+    /// Gets the length of a signature created with this type of key.
+    pub fn signature_len(&self) -> usize {
+        match self {
+            CertificateKeyType::Rsa4096 => 512,
+            CertificateKeyType::Rsa2048 => 256,
+            CertificateKeyType::ECC => 60,
+        }
+    }
+
+    /// Gets the length of the padding that follows a signature created with this type of key in
+    /// a TMD or Ticket, which aligns the signed data that follows it.
+    pub fn signature_padding_len(&self) -> usize {
+        match self {
+            CertificateKeyType::Rsa4096 | CertificateKeyType::Rsa2048 => 60,
+            CertificateKeyType::ECC => 64,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 /// A structure that represents the components of a Wii signing certificate.
 pub struct Certificate {
@@ -144,14 +356,22 @@ impl Certificate {
         Ok(buf)
     }
     
+    /// Gets the signed data of a certificate, which is everything after the signature and the
+    /// padding that aligns it to 64 bytes.
+    pub fn signed_body(&self) -> Result<Vec<u8>, CertificateError> {
+        let mut body = self.to_bytes()?;
+        body.drain(..0x40 + self.signature.len());
+        Ok(body)
+    }
+
     /// Gets the name of the certificate used to sign a certificate as a string.
     pub fn signature_issuer(&self) -> String {
-        String::from_utf8_lossy(&self.signature_issuer).trim_end_matches('\0').to_owned()
+        issuer_to_string(&self.signature_issuer)
     }
     
     /// Gets the name of a certificate's child certificate as a string.
     pub fn child_cert_identity(&self) -> String {
-        String::from_utf8_lossy(&self.child_cert_identity).trim_end_matches('\0').to_owned()
+        issuer_to_string(&self.child_cert_identity)
     }
     
     /// Gets the modulus of the public key contained in a certificate.
@@ -163,9 +383,55 @@ impl Certificate {
     pub fn pub_key_exponent(&self) -> u32 {
         self.pub_key_exponent
     }
+
+    /// Gets the type of the public key contained in a certificate.
+    pub fn pub_key_type(&self) -> CertificateKeyType {
+        self.pub_key_type.clone()
+    }
+
+    /// Gets the ID of the public key contained in a certificate.
+    pub fn pub_key_id(&self) -> u32 {
+        self.pub_key_id
+    }
+
+    /// Identifies a certificate as an issuer, which is the name that anything signed with it
+    /// (like a TMD, Ticket, or child certificate) uses as its signature issuer.
+    pub fn issuer_identity(&self) -> CertIdentity {
+        CertIdentity::from_issuer(&format!("{}-{}", self.signature_issuer(), self.child_cert_identity()))
+    }
+
+    /// Gets whether a certificate is one of the retail certificates.
+    pub fn is_retail(&self) -> bool {
+        self.issuer_identity().is_retail()
+    }
+
+    /// Gets whether a certificate is one of the development certificates.
+    pub fn is_dev(&self) -> bool {
+        self.issuer_identity().is_dev()
+    }
+}
+
+// Certificates are serialized by hand rather than derived, since the raw name fields are padded
+// byte arrays that are far more useful as strings, and the key data is easier to read as hex.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Certificate {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("Certificate", 8)?;
+        state.serialize_field("issuer", &self.signature_issuer())?;
+        state.serialize_field("identity", &self.child_cert_identity())?;
+        state.serialize_field("signer_key_type", &self.signer_key_type)?;
+        state.serialize_field("signature", &hex::encode(&self.signature))?;
+        state.serialize_field("key_type", &self.pub_key_type)?;
+        state.serialize_field("key_id", &self.pub_key_id)?;
+        state.serialize_field("exponent", &self.pub_key_exponent)?;
+        state.serialize_field("modulus", &hex::encode(&self.pub_key_modulus))?;
+        state.end()
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 /// A structure that represents the components of the Wii's signing certificate chain.
 pub struct CertificateChain {
     ca_cert: Certificate,
@@ -174,6 +440,15 @@ pub struct CertificateChain {
 }
 
 impl CertificateChain {
+    #[cfg(feature = "fs")]
+    /// Creates a new CertificateChain instance from a certificate chain file, reading the file
+    /// from the provided path.
+    pub fn open(path: impl AsRef<Path>) -> Result<CertificateChain, CertificateError> {
+        let path = path.as_ref();
+        let data = fs::read(path).map_err(|source| CertificateError::ReadFile { path: path.to_path_buf(), source })?;
+        CertificateChain::from_bytes(&data)
+    }
+
     /// Creates a new CertificateChain instance from the binary data of an entire certificate chain.
     /// This chain must contain a CA certificate, a TMD certificate, and a Ticket certificate or
     /// else this method will return an error.
@@ -277,6 +552,22 @@ impl CertificateChain {
     pub fn ticket_cert(&self) -> Certificate {
         self.ticket_cert.clone()
     }
+
+    /// Iterates over the certificates in the chain in the order that they're stored, which is the
+    /// CA certificate, then the TMD certificate, then the Ticket certificate.
+    pub fn iter(&self) -> impl Iterator<Item = &Certificate> {
+        [&self.ca_cert, &self.tmd_cert, &self.ticket_cert].into_iter()
+    }
+
+    /// Gets whether every certificate in the chain is one of the retail certificates.
+    pub fn is_retail(&self) -> bool {
+        self.iter().all(|cert| cert.is_retail())
+    }
+
+    /// Gets whether every certificate in the chain is one of the development certificates.
+    pub fn is_dev(&self) -> bool {
+        self.iter().all(|cert| cert.is_dev())
+    }
 }
 
 /// Verifies a Wii CA certificate (either CA00000001 for retail or CA00000002 for development) using
@@ -319,7 +610,7 @@ pub fn verify_child_cert(ca_cert: &Certificate, child_cert: &Certificate) -> Res
         return Err(CertificateError::NonMatchingCertificates)
     }
     let mut hasher = Sha1::new();
-    hasher.update(&child_cert.to_bytes().map_err(CertificateError::IO)?[320..]);
+    hasher.update(&child_cert.signed_body()?);
     let cert_hash = hasher.finalize().as_slice().to_owned();
     let public_key_modulus = BigUint::from_bytes_be(&ca_cert.pub_key_modulus());
     let public_key_exponent = BigUint::from(ca_cert.pub_key_exponent());
@@ -341,7 +632,7 @@ pub fn verify_tmd(tmd_cert: &Certificate, tmd: &tmd::TMD) -> Result<bool, Certif
         return Err(CertificateError::NonMatchingCertificates)
     }
     let mut hasher = Sha1::new();
-    hasher.update(&tmd.to_bytes().map_err(CertificateError::IO)?[320..]);
+    hasher.update(&tmd.signed_body()?);
     let tmd_hash = hasher.finalize().as_slice().to_owned();
     let public_key_modulus = BigUint::from_bytes_be(&tmd_cert.pub_key_modulus());
     let public_key_exponent = BigUint::from(tmd_cert.pub_key_exponent());
@@ -363,7 +654,7 @@ pub fn verify_ticket(ticket_cert: &Certificate, ticket: &ticket::Ticket) -> Resu
         return Err(CertificateError::NonMatchingCertificates)
     }
     let mut hasher = Sha1::new();
-    hasher.update(&ticket.to_bytes().map_err(CertificateError::IO)?[320..]);
+    hasher.update(&ticket.signed_body()?);
     let ticket_hash = hasher.finalize().as_slice().to_owned();
     let public_key_modulus = BigUint::from_bytes_be(&ticket_cert.pub_key_modulus());
     let public_key_exponent = BigUint::from(ticket_cert.pub_key_exponent());
@@ -373,3 +664,189 @@ pub fn verify_ticket(ticket_cert: &Certificate, ticket: &ticket::Ticket) -> Resu
         Err(_) => Ok(false),
     }
 }
+
+/// Verifies the signature of a TMD using a TMD signing certificate, like verify_tmd(), but also
+/// identifies signatures that only pass because of the trucha bug and fakesigned TMDs.
+pub fn verify_tmd_detailed(tmd_cert: &Certificate, tmd: &tmd::TMD) -> Result<SignatureStatus, CertificateError> {
+    if !tmd_cert.signature_issuer().contains("Root-CA") || !tmd_cert.child_cert_identity().contains("CP") {
+        return Err(CertificateError::IncorrectCertificate("TMD".to_owned()));
+    }
+    if format!("{}-{}", tmd_cert.signature_issuer(), tmd_cert.child_cert_identity()).ne(&tmd.signature_issuer()) {
+        return Err(CertificateError::NonMatchingCertificates)
+    }
+    let tmd_hash = Sha1::digest(&tmd.signed_body()?);
+    Ok(classify_signature(tmd_cert, &tmd_hash, &tmd.signature))
+}
+
+/// Verifies the signature of a Ticket using a Ticket signing certificate, like verify_ticket(), but
+/// also identifies signatures that only pass because of the trucha bug and fakesigned Tickets.
+pub fn verify_ticket_detailed(ticket_cert: &Certificate, ticket: &ticket::Ticket) -> Result<SignatureStatus, CertificateError> {
+    if !ticket_cert.signature_issuer().contains("Root-CA") || !ticket_cert.child_cert_identity().contains("XS") {
+        return Err(CertificateError::IncorrectCertificate("Ticket".to_owned()));
+    }
+    if format!("{}-{}", ticket_cert.signature_issuer(), ticket_cert.child_cert_identity()).ne(&ticket.signature_issuer()) {
+        return Err(CertificateError::NonMatchingCertificates)
+    }
+    let ticket_hash = Sha1::digest(&ticket.signed_body()?);
+    Ok(classify_signature(ticket_cert, &ticket_hash, &ticket.signature))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::title::test_utils;
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let cert_path = dir.path().join("title.cert");
+        fs::write(&cert_path, test_utils::build_cert_chain()).unwrap();
+        assert_eq!(CertificateChain::open(&cert_path).unwrap().to_bytes().unwrap(), test_utils::build_cert_chain());
+        let missing = dir.path().join("missing.cert");
+        assert!(matches!(CertificateChain::open(&missing), Err(CertificateError::ReadFile { path, .. }) if path == missing));
+    }
+
+    #[test]
+    fn test_split_and_reassemble_chain() {
+        let data = test_utils::build_cert_chain();
+        let chain = CertificateChain::from_bytes(&data).unwrap();
+        let identities: Vec<String> = chain.iter().map(|cert| cert.child_cert_identity()).collect();
+        assert_eq!(identities, vec!["CA00000001", "CP00000004", "XS00000003"]);
+        let pieces: Vec<Vec<u8>> = chain.iter().map(|cert| cert.to_bytes().unwrap()).collect();
+        assert_eq!(pieces.concat(), data);
+        let certs: Vec<Certificate> = pieces.iter().map(|piece| Certificate::from_bytes(piece).unwrap()).collect();
+        let reassembled = CertificateChain::from_certs(certs[0].clone(), certs[1].clone(), certs[2].clone()).unwrap();
+        assert_eq!(reassembled.to_bytes().unwrap(), data);
+    }
+
+    #[test]
+    fn test_cert_identity() {
+        let known = [
+            ("Root-CA00000001", CertIdentity::RetailCA),
+            ("Root-CA00000002", CertIdentity::DevCA),
+            ("Root-CA00000001-CP00000004", CertIdentity::RetailCP),
+            ("Root-CA00000002-CP00000005", CertIdentity::DevCP),
+            ("Root-CA00000002-CP00000007", CertIdentity::DevCP),
+            ("Root-CA00000001-XS00000003", CertIdentity::RetailXS),
+            ("Root-CA00000002-XS00000004", CertIdentity::DevXS),
+            ("Root-CA00000002-XS00000006", CertIdentity::DevXS),
+            ("Root-CA10000000", CertIdentity::ArcadeCA),
+            ("Root-CA10000000-CP10000000", CertIdentity::ArcadeCP),
+        ];
+        for (issuer, identity) in known {
+            assert_eq!(CertIdentity::from_issuer(issuer), identity);
+        }
+        assert!(CertIdentity::RetailCP.is_retail() && !CertIdentity::RetailCP.is_dev());
+        assert!(CertIdentity::DevXS.is_dev() && !CertIdentity::DevXS.is_retail());
+        assert_eq!(CertIdentity::ArcadeCP.environment(), "Arcade");
+        // A known certificate under the wrong CA isn't a real certificate.
+        for issuer in ["Root-CA00000002-CP00000004", "Root-CA00000001-XS00000006", "Root", ""] {
+            let identity = CertIdentity::from_issuer(issuer);
+            assert_eq!(identity, CertIdentity::Unknown(issuer.to_owned()));
+            assert!(!identity.is_retail() && !identity.is_dev() && !identity.is_arcade());
+            assert_eq!(identity.environment(), "Unknown");
+        }
+    }
+
+    #[test]
+    fn test_chain_identity() {
+        let chain = CertificateChain::from_bytes(&test_utils::build_cert_chain()).unwrap();
+        let identities: Vec<CertIdentity> = chain.iter().map(|cert| cert.issuer_identity()).collect();
+        assert_eq!(identities, vec![CertIdentity::RetailCA, CertIdentity::RetailCP, CertIdentity::RetailXS]);
+        assert!(chain.is_retail());
+        assert!(!chain.is_dev());
+        // A single development certificate makes the chain neither retail nor development.
+        let dev_cp = Certificate::from_bytes(&test_utils::build_cert("Root-CA00000002", "CP00000007")).unwrap();
+        assert!(dev_cp.is_dev());
+        let mixed = CertificateChain::from_certs(chain.ca_cert(), dev_cp, chain.ticket_cert()).unwrap();
+        assert!(!mixed.is_retail());
+        assert!(!mixed.is_dev());
+    }
+
+    #[test]
+    fn test_signing_certificate() {
+        let mut tmd = tmd::TMD::from_bytes(&test_utils::build_tmd(test_utils::TEST_TITLE_ID)).unwrap();
+        assert_eq!(tmd.signing_certificate(), CertIdentity::RetailCP);
+        tmd.set_signature_issuer(String::from("Root-CA10000000-CP10000000")).unwrap();
+        assert_eq!(tmd.signing_certificate(), CertIdentity::ArcadeCP);
+        let mut ticket = ticket::Ticket::from_bytes(&test_utils::build_ticket(test_utils::TEST_TITLE_ID, test_utils::TEST_TITLE_KEY)).unwrap();
+        assert_eq!(ticket.signing_certificate(), CertIdentity::RetailXS);
+        ticket.set_signature_issuer(String::from("Root-CA00000002-XS00000006")).unwrap();
+        assert_eq!(ticket.signing_certificate(), CertIdentity::DevXS);
+        ticket.set_signature_issuer(String::from("Root-CA00000003-XS00000009")).unwrap();
+        assert_eq!(ticket.signing_certificate(), CertIdentity::Unknown(String::from("Root-CA00000003-XS00000009")));
+    }
+
+    #[test]
+    fn test_cert_signed_body() {
+        let data = test_utils::build_cert("Root-CA00000001", "CP00000004");
+        let cert = Certificate::from_bytes(&data).unwrap();
+        assert_eq!(cert.signed_body().unwrap(), data[320..]);
+    }
+
+    // Builds a "signing" certificate with an exponent of 1, so that a signature decrypts to
+    // itself and the test can craft the exact decrypted block it wants.
+    fn identity_key_cert(issuer: &str, identity: &str) -> Certificate {
+        let mut cert = Certificate::from_bytes(&test_utils::build_cert(issuer, identity)).unwrap();
+        cert.pub_key_exponent = 1;
+        cert
+    }
+
+    fn signature_block(hash: &[u8], correct_padding: bool) -> Vec<u8> {
+        let mut block = vec![0x00, if correct_padding { 0x01 } else { 0x02 }];
+        block.resize(256 - SHA1_DIGEST_INFO.len() - hash.len() - 1, 0xFF);
+        block.push(0x00);
+        block.extend_from_slice(&SHA1_DIGEST_INFO);
+        block.extend_from_slice(hash);
+        block
+    }
+
+    fn tmd_hash(tmd: &tmd::TMD) -> Vec<u8> {
+        Sha1::digest(tmd.signed_body().unwrap()).to_vec()
+    }
+
+    #[test]
+    fn test_verify_tmd_detailed() {
+        let cert = identity_key_cert("Root-CA00000001", "CP00000004");
+        let mut tmd = tmd::TMD::from_bytes(&test_utils::build_tmd(test_utils::TEST_TITLE_ID)).unwrap();
+        tmd.signature = signature_block(&tmd_hash(&tmd), true);
+        assert_eq!(verify_tmd_detailed(&cert, &tmd).unwrap(), SignatureStatus::Valid);
+        tmd.signature = signature_block(&tmd_hash(&tmd), false);
+        assert_eq!(verify_tmd_detailed(&cert, &tmd).unwrap(), SignatureStatus::ValidTruchaOnly);
+        // Modifying the TMD after signing invalidates the signature.
+        tmd.title_version += 1;
+        assert_eq!(verify_tmd_detailed(&cert, &tmd).unwrap(), SignatureStatus::Invalid);
+        tmd.fakesign().unwrap();
+        assert_eq!(verify_tmd_detailed(&cert, &tmd).unwrap(), SignatureStatus::Fakesigned);
+        // Once the hash starts with a null byte, only that byte has to match for strncmp().
+        let mut partial_hash = tmd_hash(&tmd);
+        partial_hash[1..].fill(0xAA);
+        tmd.signature = signature_block(&partial_hash, false);
+        assert_eq!(verify_tmd_detailed(&cert, &tmd).unwrap(), SignatureStatus::ValidTruchaOnly);
+        // With correct padding but a partially matching hash, the signature still isn't Valid.
+        tmd.signature = signature_block(&partial_hash, true);
+        assert_eq!(verify_tmd_detailed(&cert, &tmd).unwrap(), SignatureStatus::ValidTruchaOnly);
+        let wrong_cert = identity_key_cert("Root-CA00000001", "XS00000003");
+        assert!(matches!(verify_tmd_detailed(&wrong_cert, &tmd), Err(CertificateError::IncorrectCertificate(_))));
+    }
+
+    #[test]
+    fn test_verify_ticket_detailed() {
+        let cert = identity_key_cert("Root-CA00000001", "XS00000003");
+        let mut ticket = ticket::Ticket::from_bytes(&test_utils::build_ticket(test_utils::TEST_TITLE_ID, test_utils::TEST_TITLE_KEY)).unwrap();
+        let hash = Sha1::digest(ticket.signed_body().unwrap()).to_vec();
+        ticket.signature = signature_block(&hash, true);
+        assert_eq!(verify_ticket_detailed(&cert, &ticket).unwrap(), SignatureStatus::Valid);
+        let mut wrong_hash = hash.clone();
+        wrong_hash[0] ^= 0xFF;
+        ticket.signature = signature_block(&wrong_hash, false);
+        assert_eq!(verify_ticket_detailed(&cert, &ticket).unwrap(), SignatureStatus::Invalid);
+        // A null signature isn't fakesigned unless the hash also starts with a null byte.
+        ticket.signature = vec![0; 256];
+        let expected = if ticket.is_fakesigned() { SignatureStatus::Fakesigned } else { SignatureStatus::Invalid };
+        assert_eq!(verify_ticket_detailed(&cert, &ticket).unwrap(), expected);
+        ticket.fakesign().unwrap();
+        assert_eq!(verify_ticket_detailed(&cert, &ticket).unwrap(), SignatureStatus::Fakesigned);
+    }
+}
@@ -3,162 +3,402 @@
 //
 // Implements content parsing and editing.
 
-use std::cell::RefCell;
-use std::io::{Cursor, Read, Seek, SeekFrom, Write};
-use std::rc::Rc;
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
 use sha1::{Sha1, Digest};
 use thiserror::Error;
-use crate::title::tmd::{ContentRecord, ContentType};
+use crate::title::tmd::{ContentRecord, ContentRecords, ContentType};
 use crate::title::crypto;
 use crate::title::crypto::encrypt_content;
+use crate::PROGRESS_LOG_TARGET;
 
 #[derive(Debug, Error)]
 pub enum ContentError {
     #[error("requested index {index} is out of range (must not exceed {max})")]
     IndexOutOfRange { index: usize, max: usize },
+    #[error("requested index {0} is out of range (there are no content records)")]
+    Empty(usize),
     #[error("expected {required} contents based on content records but found {found}")]
     MissingContents { required: usize, found: usize },
+    #[error("content {index} (Content ID {cid:08X}) ends at offset {required} but the content region is only {found} bytes long")]
+    TruncatedContent { index: usize, cid: u32, required: u64, found: u64 },
+    #[error("content at position {0} is not present in the content region")]
+    ContentNotPresent(usize),
     #[error("content with requested Content ID {0} could not be found")]
     CIDNotFound(u32),
+    #[error("content with requested record index {0} could not be found")]
+    RecordIndexNotFound(u16),
     #[error("the specified index {0} already exists in the content records")]
     IndexAlreadyExists(u16),
     #[error("the specified Content ID {0} already exists in the content records")]
     CIDAlreadyExists(u32),
     #[error("content's hash did not match the expected value (was {hash}, expected {expected})")]
     BadHash { hash: String, expected: String },
-    #[error("content.map is an invalid length and cannot be parsed")]
-    InvalidSharedContentMapLength,
+    #[error("content at index {index} is not valid encrypted content ({reason})")]
+    BadEncryptedContent { index: usize, reason: String },
+    #[error("content at index {index} is neither decrypted content (hash was {hash}, expected {expected}) nor encrypted content ({reason})")]
+    UnrecognizedContent { index: usize, hash: String, expected: String, reason: String },
+    #[error("content alignment must be a power of two that's at least 16, but {0} was provided")]
+    InvalidAlignment(u64),
     #[error("found invalid shared content name `{0}`")]
     InvalidSharedContentName(String),
     #[error("content data is not in a valid format")]
     IO(#[from] std::io::Error),
 }
 
+/// The alignment that contents are padded to in a standard WAD.
+pub const DEFAULT_CONTENT_ALIGNMENT: u64 = 64;
+
+// The alignments that are checked for when parsing a content region, from most to least common.
+pub(crate) const CONTENT_ALIGNMENTS: [u64; 3] = [64, 32, 16];
+
+// Rounds a value up to the nearest multiple of the provided alignment, which must be a power of two.
+pub(crate) fn align_to(value: u64, alignment: u64) -> u64 {
+    (value + alignment - 1) & !(alignment - 1)
+}
+
+// Finds the alignment that a content region was most likely built with, based on the sizes of the
+// contents stored in it. A region that exactly matches the padded size of its contents is
+// preferred over one that's only padded to 16 bytes at the end, and regions that don't match any
+// alignment (such as ones with trailing data) are assumed to use the default.
+fn detect_alignment(data_len: u64, sizes: &[u64]) -> u64 {
+    let total = |alignment: u64| -> u64 { sizes.iter().map(|size| align_to(*size, alignment)).sum() };
+    let total_min = |alignment: u64| -> u64 {
+        match sizes.last() {
+            Some(last) => total(alignment) - align_to(*last, alignment) + align_to(*last, 16),
+            None => 0,
+        }
+    };
+    CONTENT_ALIGNMENTS.iter().find(|alignment| total(**alignment) == data_len)
+        .or_else(|| CONTENT_ALIGNMENTS.iter().find(|alignment| total_min(**alignment) == data_len))
+        .copied()
+        .unwrap_or(DEFAULT_CONTENT_ALIGNMENT)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The forms that content data can be stored in outside of a content region.
+pub enum ContentForm {
+    /// The content is decrypted, and matches the size and hash in its content record.
+    Decrypted,
+    /// The content is encrypted with the Title Key, and is padded to a multiple of 16 bytes.
+    Encrypted,
+}
+
 #[derive(Debug)]
 /// A structure that represents the block of data containing the content of a digital Wii title.
+/// Contents that aren't present, like unpurchased DLC, are stored as None and are skipped when
+/// the region is written back out.
 pub struct ContentRegion {
-    pub content_records: Rc<RefCell<Vec<ContentRecord>>>,
-    pub content_region_size: u32,
-    pub content_start_offsets: Vec<u64>,
-    pub contents: Vec<Vec<u8>>,
+    pub content_records: ContentRecords,
+    pub contents: Vec<Option<Vec<u8>>>,
+    alignment: u64,
+    trailing_data: Vec<u8>,
 }
 
 impl ContentRegion {
     /// Creates a ContentRegion instance that can be used to parse and edit content stored in a 
-    /// digital Wii title from the content area of a WAD and the ContentRecords from a TMD.
-    pub fn from_bytes(data: &[u8], content_records: Rc<RefCell<Vec<ContentRecord>>>) -> Result<Self, ContentError> {
-        let content_region_size = data.len() as u32;
-        let num_contents = content_records.borrow().len() as u16;
-        // Calculate the starting offsets of each content.
-        let content_start_offsets: Vec<u64> = std::iter::once(0)
-            .chain(content_records.borrow().iter().scan(0, |offset, record| {
-                *offset += record.content_size;
-                if record.content_size % 64 != 0 {
-                    *offset += 64 - (record.content_size % 64);
-                }
-                Some(*offset)
-            })).take(content_records.borrow().len()).collect(); // Trims the extra final entry.
+    /// digital Wii title from the content area of a WAD and the ContentRecords from a TMD. The
+    /// alignment of the contents is detected from the size of the content area.
+    pub fn from_bytes(data: &[u8], content_records: ContentRecords) -> Result<Self, ContentError> {
+        let present = vec![true; content_records.read().len()];
+        ContentRegion::from_bytes_with_presence(data, content_records, &present)
+    }
+
+    /// Creates a ContentRegion instance from the content area of a WAD and the ContentRecords from
+    /// a TMD, like ContentRegion::from_bytes(), but where only the contents marked as present are
+    /// stored in the content area. This is used for DLC titles, which often don't include every
+    /// content listed in their TMD.
+    pub fn from_bytes_with_presence(data: &[u8], content_records: ContentRecords, present: &[bool]) -> Result<Self, ContentError> {
+        if present.len() != content_records.read().len() {
+            return Err(ContentError::MissingContents { required: content_records.read().len(), found: present.len() });
+        }
+        let present_sizes: Vec<u64> = content_records.read().iter().zip(present)
+            .filter(|(_, present)| **present)
+            .map(|(record, _)| record.content_size)
+            .collect();
+        let alignment = detect_alignment(data.len() as u64, &present_sizes);
+        if alignment != DEFAULT_CONTENT_ALIGNMENT {
+            log::debug!("content region uses {}-byte content alignment", alignment);
+        }
+        // Calculate the starting offsets of each content. Absent contents take up no space, so
+        // they start where the next present content does.
+        let content_start_offsets: Vec<u64> = content_records.read().iter().zip(present).scan(0, |offset, (record, present)| {
+            let start = *offset;
+            if *present {
+                *offset += align_to(record.content_size, alignment);
+            }
+            Some(start)
+        }).collect();
+        // Make sure that every content can be fully read before trying to parse any of them, so
+        // that a short content region reports which content is missing rather than an IO error.
+        for (i, record) in content_records.read().iter().enumerate().filter(|(i, _)| present[*i]) {
+            let required = content_start_offsets[i] + ((record.content_size + 15) & !15);
+            if required > data.len() as u64 {
+                return Err(ContentError::TruncatedContent { index: i, cid: record.content_id, required, found: data.len() as u64 });
+            }
+        }
         // Parse the content blob and create a vector of vectors from it.
-        let mut contents: Vec<Vec<u8>> = Vec::with_capacity(num_contents as usize);
+        let mut contents: Vec<Option<Vec<u8>>> = Vec::with_capacity(present.len());
         let mut buf = Cursor::new(data);
-        for i in 0..num_contents {
-            buf.seek(SeekFrom::Start(content_start_offsets[i as usize]))?;
-            let size = (content_records.borrow()[i as usize].content_size + 15) & !15;
+        for (i, record) in content_records.read().iter().enumerate() {
+            if !present[i] {
+                contents.push(None);
+                continue;
+            }
+            buf.seek(SeekFrom::Start(content_start_offsets[i]))?;
+            let size = (record.content_size + 15) & !15;
             let mut content = vec![0u8; size as usize];
             buf.read_exact(&mut content)?;
-            contents.push(content);
+            contents.push(Some(content));
+        }
+        // Anything past the padded end of the last content isn't described by the records, but
+        // gets kept so that it can be written back out unchanged.
+        let padded_size: u64 = present_sizes.iter().map(|size| align_to(*size, alignment)).sum();
+        let trailing_data = data.get(padded_size as usize..).unwrap_or_default().to_vec();
+        if !trailing_data.is_empty() {
+            log::debug!("content region has {} bytes of trailing data", trailing_data.len());
         }
         Ok(ContentRegion {
-            content_records: Rc::clone(&content_records),
-            content_region_size,
-            content_start_offsets,
+            content_records: content_records.clone(),
             contents,
+            alignment,
+            trailing_data,
         })
     }
 
     /// Creates a ContentRegion instance that can be used to parse and edit content stored in a 
     /// digital Wii title from a vector of contents and the ContentRecords from a TMD.
-    pub fn from_contents(contents: Vec<Vec<u8>>, content_records: Rc<RefCell<Vec<ContentRecord>>>) -> Result<Self, ContentError> {
-        if contents.len() != content_records.borrow().len() {
-            return Err(ContentError::MissingContents { required: content_records.borrow().len(), found: contents.len()});
+    pub fn from_contents(contents: Vec<Vec<u8>>, content_records: ContentRecords) -> Result<Self, ContentError> {
+        if contents.len() != content_records.read().len() {
+            return Err(ContentError::MissingContents { required: content_records.read().len(), found: contents.len()});
+        }
+        let mut content_region = Self::new(content_records.clone())?;
+        // Contents are provided in the same order as the records, so load them by position rather
+        // than by their record index, which may be out of order.
+        for (i, content) in contents.iter().enumerate() {
+            content_region.load_enc_content(content, i)?;
+        }
+        Ok(content_region)
+    }
+
+    /// Creates a ContentRegion instance from a vector of contents and the ContentRecords from a
+    /// TMD, like ContentRegion::from_contents(), but where contents that aren't present are None.
+    pub fn from_optional_contents(contents: Vec<Option<Vec<u8>>>, content_records: ContentRecords) -> Result<Self, ContentError> {
+        if contents.len() != content_records.read().len() {
+            return Err(ContentError::MissingContents { required: content_records.read().len(), found: contents.len()});
         }
-        let mut content_region = Self::new(Rc::clone(&content_records))?;
-        for i in 0..contents.len() {
-            let target_index = content_region.content_records.borrow()[i].index;
-            content_region.load_enc_content(&contents[i], target_index as usize)?;
+        let mut content_region = Self::new(content_records.clone())?;
+        for (i, content) in contents.iter().enumerate() {
+            if let Some(content) = content {
+                content_region.load_enc_content(content, i)?;
+            }
         }
         Ok(content_region)
     }
     
     /// Creates a ContentRegion instance from the ContentRecords of a TMD that contains no actual
-    /// content. This can be used to load existing content from files.
-    pub fn new(content_records: Rc<RefCell<Vec<ContentRecord>>>) -> Result<Self, ContentError> {
-        let content_region_size: u64 = content_records.borrow().iter().map(|x| (x.content_size + 63) & !63).sum();
-        let content_region_size = content_region_size as u32;
-        let num_contents = content_records.borrow().len() as u16;
-        let content_start_offsets: Vec<u64> = vec![0; num_contents as usize];
-        let contents: Vec<Vec<u8>> = vec![Vec::new(); num_contents as usize];
+    /// content. This can be used to load existing content from files. Every content starts out as
+    /// not present until it's loaded.
+    pub fn new(content_records: ContentRecords) -> Result<Self, ContentError> {
+        let contents: Vec<Option<Vec<u8>>> = vec![None; content_records.read().len()];
         Ok(ContentRegion {
-            content_records:  Rc::clone(&content_records),
-            content_region_size,
-            content_start_offsets,
+            content_records:  content_records.clone(),
             contents,
+            alignment: DEFAULT_CONTENT_ALIGNMENT,
+            trailing_data: Vec::new(),
         })
     }
     
-    /// Dumps the entire ContentRegion back into binary data that can be written to a file.
+    /// Dumps the entire ContentRegion back into binary data that can be written to a file. Each
+    /// content is padded to the region's alignment, and contents that aren't present are skipped.
+    /// Any trailing data found after the contents when the region was parsed is written back out
+    /// after them.
     pub fn to_bytes(&self) -> Result<Vec<u8>, std::io::Error> {
-        let mut buf: Vec<u8> = Vec::new();
-        for i in 0..self.content_records.borrow().len() {
-            let mut content = self.contents[i].clone();
-            // Round up size to nearest 64 to add appropriate padding.
-            content.resize((content.len() + 63) & !63, 0);
-            buf.write_all(&content)?;
-        }
+        let mut buf: Vec<u8> = Vec::with_capacity(self.data_size() as usize);
+        self.write_to(&mut buf)?;
         Ok(buf)
     }
+
+    /// Writes the entire ContentRegion to the provided writer, in the same format as to_bytes().
+    /// Contents are written directly from the region, so no extra copy of the data is made.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), std::io::Error> {
+        let mut written: u64 = 0;
+        for content in self.present_contents() {
+            writer.write_all(content)?;
+            let padded = align_to(written + content.len() as u64, self.alignment);
+            io::copy(&mut io::repeat(0).take(padded - written - content.len() as u64), writer)?;
+            written = padded;
+        }
+        writer.write_all(&self.trailing_data)?;
+        Ok(())
+    }
+
+    /// Gets the size of the binary data of the ContentRegion, as returned by to_bytes().
+    pub fn data_size(&self) -> u64 {
+        let contents_size: u64 = self.present_contents().map(|content| align_to(content.len() as u64, self.alignment)).sum();
+        contents_size + self.trailing_data.len() as u64
+    }
+
+    /// Gets the number of contents in the ContentRegion, including any that aren't present. This
+    /// is always derived from the content records, so it can't fall out of sync with the TMD.
+    pub fn len(&self) -> usize {
+        self.content_records.read().len()
+    }
+
+    /// Gets whether the ContentRegion has no content records at all.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Iterates over the contents that are present in the region and have a matching record.
+    fn present_contents(&self) -> impl Iterator<Item = &Vec<u8>> {
+        self.contents.iter().take(self.len()).flatten()
+    }
+
+    /// Gets the alignment that each content is padded to in the ContentRegion. This is 64 bytes for
+    /// most WADs, but some tools (and boot2 WADs) use 16 or 32 bytes instead.
+    pub fn alignment(&self) -> u64 {
+        self.alignment
+    }
+
+    /// Sets the alignment that each content will be padded to when the ContentRegion is written
+    /// out. The alignment must be a power of two that's at least 16, since contents are encrypted
+    /// in 16-byte blocks.
+    pub fn set_alignment(&mut self, alignment: u64) -> Result<(), ContentError> {
+        if alignment < 16 || !alignment.is_power_of_two() {
+            return Err(ContentError::InvalidAlignment(alignment));
+        }
+        self.alignment = alignment;
+        Ok(())
+    }
+
+    /// Gets any data that was found after the padded end of the last content when the
+    /// ContentRegion was parsed. This is usually empty, but some WADs built with nonstandard
+    /// alignment have extra data here.
+    pub fn trailing_data(&self) -> &[u8] {
+        &self.trailing_data
+    }
     
-    /// Gets the index of content using its Content ID.
+    /// Gets whether the content at the specified position in the ContentRegion is present. Contents
+    /// that are missing, like unpurchased DLC, are listed in the content records but have no data.
+    pub fn is_content_present(&self, index: usize) -> bool {
+        matches!(self.contents.get(index), Some(Some(_)))
+    }
+
+    /// Gets a Content ID that isn't used by any existing content, for assigning to new content. This
+    /// is one higher than the highest Content ID in use, or the lowest unused Content ID if the
+    /// highest possible one is already taken.
+    pub fn next_available_cid(&self) -> u32 {
+        let records = self.content_records.read();
+        match records.iter().map(|record| record.content_id).max() {
+            None => 0,
+            Some(max) if max < u32::MAX => max + 1,
+            // There can't be anywhere close to 2^32 contents, so an unused Content ID will always
+            // be found.
+            Some(_) => (0..u32::MAX).find(|cid| !records.iter().any(|record| record.content_id == *cid)).unwrap_or(0),
+        }
+    }
+
+    /// Gets the position of content in the ContentRegion using its Content ID. This is the
+    /// position used by the other methods of a ContentRegion, which won't match the index stored in
+    /// the content's record if the records are out of order.
     pub fn get_index_from_cid(&self, cid: u32) -> Result<usize, ContentError> {
         // Use fancy Rust find and map methods to find the index matching the provided CID. Take
         // that libWiiPy!
-        let content_index = self.content_records.borrow().iter()
-            .find(|record| record.content_id == cid)
-            .map(|record| record.index);
+        let content_index = self.content_records.read().iter()
+            .position(|record| record.content_id == cid);
         if let Some(index) = content_index {
-            Ok(index as usize)
+            Ok(index)
         } else {
             Err(ContentError::CIDNotFound(cid))
         }
     }
 
+    /// Gets the position of content in the ContentRegion using the index stored in its record.
+    /// Record indices don't have to match positions, since records can be stored out of order.
+    pub fn get_position_for_index(&self, index: u16) -> Result<usize, ContentError> {
+        self.content_records.read().iter()
+            .position(|record| record.index == index)
+            .ok_or(ContentError::RecordIndexNotFound(index))
+    }
+
+    /// Iterates over the content records in the order they're stored, yielding each record
+    /// alongside its position in the ContentRegion. The records are copied when this is called, so
+    /// the ContentRegion can be accessed or modified while iterating.
+    pub fn iter_by_position(&self) -> impl Iterator<Item = (usize, ContentRecord)> + use<> {
+        self.content_records.read().clone().into_iter().enumerate()
+    }
+
+    /// Iterates over the content records sorted by the index stored in each record, yielding each
+    /// record alongside its position in the ContentRegion. Like ContentRegion::iter_by_position(),
+    /// the records are copied when this is called.
+    pub fn iter_by_record_index(&self) -> impl Iterator<Item = (usize, ContentRecord)> + use<> {
+        let mut records: Vec<(usize, ContentRecord)> = self.iter_by_position().collect();
+        records.sort_by_key(|(_, record)| record.index);
+        records.into_iter()
+    }
+
     /// Gets the encrypted content file from the ContentRegion at the specified index.
     pub fn get_enc_content_by_index(&self, index: usize) -> Result<Vec<u8>, ContentError> {
-        let content = self.contents.get(index).ok_or(ContentError::IndexOutOfRange { index, max: self.content_records.borrow().len() - 1 })?;
-        Ok(content.clone())
+        self.record_at(index)?;
+        self.contents.get(index).cloned().flatten().ok_or(ContentError::ContentNotPresent(index))
     }
 
     /// Gets the decrypted content file from the ContentRegion at the specified index.
     pub fn get_content_by_index(&self, index: usize, title_key: [u8; 16]) -> Result<Vec<u8>, ContentError> {
+        let record = self.record_at(index)?;
         let content = self.get_enc_content_by_index(index)?;
+        log::debug!(target: PROGRESS_LOG_TARGET, "decrypting content {} of {}", index + 1, self.len());
         // Verify the hash of the decrypted content against its record.
-        let mut content_dec = crypto::decrypt_content(&content, title_key, self.content_records.borrow()[index].index);
-        content_dec.resize(self.content_records.borrow()[index].content_size as usize, 0);
+        let mut content_dec = crypto::decrypt_content(&content, title_key, record.index);
+        content_dec.resize(record.content_size as usize, 0);
         let mut hasher = Sha1::new();
         hasher.update(content_dec.clone());
         let result = hasher.finalize();
-        if result[..] != self.content_records.borrow()[index].content_hash {
-            return Err(ContentError::BadHash { hash: hex::encode(result), expected: hex::encode(self.content_records.borrow()[index].content_hash) });
+        if result[..] != record.content_hash {
+            return Err(ContentError::BadHash { hash: hex::encode(result), expected: hex::encode(record.content_hash) });
         }
         Ok(content_dec)
     }
 
+    /// Opens the content at the specified index for reading, decrypting it as it's read instead of
+    /// all at once. See ContentReader for how the content's hash is checked.
+    pub fn open_content_by_index(&self, index: usize, title_key: [u8; 16]) -> Result<ContentReader<'_>, ContentError> {
+        let record = self.record_at(index)?;
+        let content = self.contents.get(index).and_then(|content| content.as_deref()).ok_or(ContentError::ContentNotPresent(index))?;
+        log::debug!(target: PROGRESS_LOG_TARGET, "opening content {} of {}", index + 1, self.len());
+        Ok(ContentReader::new(content, &record, title_key))
+    }
+
+    // Gets a copy of the content record at the specified position, or an error if there isn't one.
+    // Records can be added to or removed from the TMD after the region was created, so this should
+    // be used instead of indexing the records directly.
+    fn record_at(&self, index: usize) -> Result<ContentRecord, ContentError> {
+        self.content_records.read().get(index).cloned().ok_or_else(|| self.index_error(index))
+    }
+
+    // Builds the error for an index that doesn't have a content record. A region with no records
+    // at all gets its own error, since there's no maximum index to report.
+    fn index_error(&self, index: usize) -> ContentError {
+        match self.len() {
+            0 => ContentError::Empty(index),
+            len => ContentError::IndexOutOfRange { index, max: len - 1 },
+        }
+    }
+
+    // Stores content at the specified position, growing the list of contents first if records have
+    // been added to the TMD since the region was created.
+    fn store_content(&mut self, index: usize, content: Option<Vec<u8>>) {
+        if self.contents.len() <= index {
+            self.contents.resize(index + 1, None);
+        }
+        self.contents[index] = content;
+    }
+
     /// Gets the encrypted content file from the ContentRegion with the specified Content ID.
     pub fn get_enc_content_by_cid(&self, cid: u32) -> Result<Vec<u8>, ContentError> {
-        let index = self.content_records.borrow().iter().position(|x| x.content_id == cid);
+        let index = self.content_records.read().iter().position(|x| x.content_id == cid);
         if let Some(index) = index {
-            let content = self.get_enc_content_by_index(index).map_err(|_| ContentError::CIDNotFound(cid))?;
-            Ok(content)
+            self.get_enc_content_by_index(index)
         } else {
             Err(ContentError::CIDNotFound(cid))
         }
@@ -166,7 +406,7 @@ impl ContentRegion {
 
     /// Gets the decrypted content file from the ContentRegion with the specified Content ID.
     pub fn get_content_by_cid(&self, cid: u32, title_key: [u8; 16]) -> Result<Vec<u8>, ContentError> {
-        let index = self.content_records.borrow().iter().position(|x| x.content_id == cid);
+        let index = self.content_records.read().iter().position(|x| x.content_id == cid);
         if let Some(index) = index {
             let content_dec = self.get_content_by_index(index, title_key)?;
             Ok(content_dec)
@@ -178,10 +418,8 @@ impl ContentRegion {
     /// Loads existing content into the specified index of a ContentRegion instance. This content 
     /// must be encrypted.
     pub fn load_enc_content(&mut self, content: &[u8], index: usize) -> Result<(), ContentError> {
-        if index >= self.content_records.borrow().len() {
-            return Err(ContentError::IndexOutOfRange { index, max: self.content_records.borrow().len() - 1 });
-        }
-        self.contents[index] = content.to_vec();
+        self.record_at(index)?;
+        self.store_content(index, Some(content.to_vec()));
         Ok(())
     }
     
@@ -190,22 +428,22 @@ impl ContentRegion {
     /// values can be set in the corresponding content record. Optionally, a new Content ID or
     /// content type can be provided, with the existing values being preserved by default.
     pub fn set_enc_content(&mut self, content: &[u8], index: usize, content_size: u64, content_hash: [u8; 20], cid: Option<u32>, content_type: Option<ContentType>) -> Result<(), ContentError> {
-        if index >= self.content_records.borrow().len() {
-            return Err(ContentError::IndexOutOfRange { index, max: self.content_records.borrow().len() - 1 });
-        }
-        self.content_records.borrow_mut()[index].content_size = content_size;
-        self.content_records.borrow_mut()[index].content_hash = content_hash;
-        if cid.is_some() {
-            // Make sure that the new CID isn't already in use.
-            if self.content_records.borrow().iter().any(|record| record.content_id == cid.unwrap()) {
-                return Err(ContentError::CIDAlreadyExists(cid.unwrap()));
+        let mut record = self.record_at(index)?;
+        // Make sure that the new CID isn't already in use before anything is changed, so that a
+        // conflict doesn't leave the record describing content that was never stored.
+        if let Some(cid) = cid {
+            if self.content_records.read().iter().any(|record| record.content_id == cid) {
+                return Err(ContentError::CIDAlreadyExists(cid));
             }
-            self.content_records.borrow_mut()[index].content_id = cid.unwrap();
+            record.content_id = cid;
         }
-        if content_type.is_some() {
-            self.content_records.borrow_mut()[index].content_type = content_type.unwrap();
+        if let Some(content_type) = content_type {
+            record.content_type = content_type;
         }
-        self.contents[index] = content.to_vec();
+        record.content_size = content_size;
+        record.content_hash = content_hash;
+        self.content_records.write()[index] = record;
+        self.store_content(index, Some(content.to_vec()));
         Ok(())
     }
     
@@ -213,45 +451,121 @@ impl ContentRegion {
     /// must be decrypted and needs to match the size and hash listed in the content record at that
     /// index.
     pub fn load_content(&mut self, content: &[u8], index: usize, title_key: [u8; 16]) -> Result<(), ContentError> {
-        if index >= self.content_records.borrow().len() {
-            return Err(ContentError::IndexOutOfRange { index, max: self.content_records.borrow().len() - 1 });
-        }
+        let record = self.record_at(index)?;
         // Hash the content we're trying to load to ensure it matches the hash expected in the
         // matching record.
-        let mut hasher = Sha1::new();
-        hasher.update(content);
-        let result = hasher.finalize();
-        if result[..] != self.content_records.borrow()[index].content_hash {
-            return Err(ContentError::BadHash { hash: hex::encode(result), expected: hex::encode(self.content_records.borrow()[index].content_hash) });
+        if !record.matches(content) {
+            return Err(ContentError::BadHash { hash: hex::encode(Sha1::digest(content)), expected: hex::encode(record.content_hash) });
         }
-        let content_enc = encrypt_content(content, title_key, self.content_records.borrow()[index].index, self.content_records.borrow()[index].content_size);
-        self.contents[index] = content_enc;
+        let content_enc = encrypt_content(content, title_key, record.index, record.content_size);
+        self.store_content(index, Some(content_enc));
         Ok(())
     }
 
+    // Checks whether the provided data is the encrypted form of the content at the specified index,
+    // returning a description of the failed check if it isn't.
+    fn check_enc_content(&self, content: &[u8], index: usize, title_key: [u8; 16]) -> Result<(), String> {
+        let record = self.record_at(index).map_err(|e| e.to_string())?;
+        let padded_size = align_to(record.content_size, 16);
+        if content.len() as u64 != padded_size {
+            return Err(format!("size was {} bytes, expected {} bytes", content.len(), padded_size));
+        }
+        let mut content_dec = crypto::decrypt_content(content, title_key, record.index);
+        content_dec.truncate(record.content_size as usize);
+        if !record.matches(&content_dec) {
+            return Err(format!("decrypted hash was {}", hex::encode(Sha1::digest(&content_dec))));
+        }
+        Ok(())
+    }
+
+    /// Loads existing content of an unknown form into the specified index of a ContentRegion
+    /// instance, returning the form that it was found to be in. Content is treated as decrypted if
+    /// its hash matches the content record at that index, and as encrypted if it's the padded size
+    /// of the record and decrypts to content with a matching hash. A form can optionally be
+    /// provided to skip detection, in which case only that form will be checked for.
+    pub fn probe_content(&mut self, content: &[u8], index: usize, title_key: [u8; 16], form: Option<ContentForm>) -> Result<ContentForm, ContentError> {
+        let record = self.record_at(index)?;
+        match form {
+            Some(ContentForm::Decrypted) => {
+                self.load_content(content, index, title_key)?;
+                Ok(ContentForm::Decrypted)
+            },
+            Some(ContentForm::Encrypted) => {
+                self.check_enc_content(content, index, title_key)
+                    .map_err(|reason| ContentError::BadEncryptedContent { index, reason })?;
+                self.load_enc_content(content, index)?;
+                Ok(ContentForm::Encrypted)
+            },
+            None => {
+                if record.matches(content) {
+                    self.load_content(content, index, title_key)?;
+                    return Ok(ContentForm::Decrypted);
+                }
+                if let Err(reason) = self.check_enc_content(content, index, title_key) {
+                    return Err(ContentError::UnrecognizedContent {
+                        index,
+                        hash: hex::encode(Sha1::digest(content)),
+                        expected: hex::encode(record.content_hash),
+                        reason,
+                    });
+                }
+                self.load_enc_content(content, index)?;
+                Ok(ContentForm::Encrypted)
+            }
+        }
+    }
+
     /// Sets the content at the specified index to the provided decrypted content. This content will
     /// have its size and hash saved into the matching record. Optionally, a new Content ID or
     /// content type can be provided, with the existing values being preserved by default. The
     /// Title Key will be used to encrypt this content before it is stored.
     pub fn set_content(&mut self, content: &[u8], index: usize, cid: Option<u32>, content_type: Option<ContentType>, title_key: [u8; 16]) -> Result<(), ContentError> {
+        let record = self.record_at(index)?;
         let content_size = content.len() as u64;
         let mut hasher = Sha1::new();
         hasher.update(content);
         let content_hash: [u8; 20] = hasher.finalize().into();
-        let content_enc = encrypt_content(content, title_key, index as u16, content_size);
+        let content_enc = encrypt_content(content, title_key, record.index, content_size);
         self.set_enc_content(&content_enc, index, content_size, content_hash, cid, content_type)?;
         Ok(())
     }
     
     /// Removes the content at the specified index from the content list and content records. This
     /// may leave a gap in the indexes recorded in the content records, but this should not cause
-    /// issues on the Wii or with correctly implemented WAD parsers.
+    /// issues on the Wii or with correctly implemented WAD parsers. Use reindex() to close any gaps
+    /// for tools that don't handle them.
     pub fn remove_content(&mut self, index: usize) -> Result<(), ContentError> {
-        if self.contents.get(index).is_none() || self.content_records.borrow().get(index).is_none() {
-            return Err(ContentError::IndexOutOfRange { index, max: self.content_records.borrow().len() - 1 });
+        if self.contents.get(index).is_none() || self.content_records.read().get(index).is_none() {
+            return Err(self.index_error(index));
         }
         self.contents.remove(index);
-        self.content_records.borrow_mut().remove(index);
+        self.content_records.write().remove(index);
+        Ok(())
+    }
+
+    /// Renumbers the indices in the content records so that they run from 0 to n-1, preserving the
+    /// current order of the records. Content is encrypted using its index as the IV, so any present
+    /// content that receives a new index is re-encrypted to match.
+    pub fn reindex(&mut self, title_key: [u8; 16]) -> Result<(), ContentError> {
+        // Decrypt everything that needs to move up front, so that a bad hash leaves the region
+        // untouched rather than half-renumbered.
+        let mut reencrypted: Vec<(usize, Option<Vec<u8>>)> = Vec::new();
+        for (position, record) in self.content_records.read().iter().enumerate() {
+            if record.index == position as u16 {
+                continue;
+            }
+            let content = if self.is_content_present(position) {
+                let content_dec = self.get_content_by_index(position, title_key)?;
+                Some(encrypt_content(&content_dec, title_key, position as u16, record.content_size))
+            } else {
+                None
+            };
+            reencrypted.push((position, content));
+        }
+        for (position, content) in reencrypted {
+            self.content_records.write()[position].index = position as u16;
+            self.store_content(position, content);
+        }
         Ok(())
     }
 
@@ -259,27 +573,27 @@ impl ContentRegion {
     /// Content ID, type, index, and decrypted hash will be added to the record.
     pub fn add_enc_content(&mut self, content: &[u8], index: u16, cid: u32, content_type: ContentType, content_size: u64, content_hash: [u8; 20]) -> Result<(), ContentError> {
         // Return an error if the specified index or CID already exist in the records.
-        if self.content_records.borrow().iter().any(|record| record.index == index) {
+        if self.content_records.read().iter().any(|record| record.index == index) {
             return Err(ContentError::IndexAlreadyExists(index));
         }
-        if self.content_records.borrow().iter().any(|record| record.content_id == cid) {
+        if self.content_records.read().iter().any(|record| record.content_id == cid) {
             return Err(ContentError::CIDAlreadyExists(cid));
         }
-        self.contents.push(content.to_vec());
-        self.content_records.borrow_mut().push(ContentRecord { content_id: cid, index, content_type, content_size, content_hash });
+        self.contents.push(Some(content.to_vec()));
+        self.content_records.write().push(ContentRecord { content_id: cid, index, content_type, content_size, content_hash });
         Ok(())
     }
     
     /// Adds new decrypted content to the end of the content list and content records. The provided
     /// Content ID and type will be added to the record alongside a hash of the decrypted data. An
-    /// index will be automatically assigned based on the highest index currently recorded in the
-    /// content records.
+    /// index will be automatically assigned that's one higher than the highest index currently
+    /// recorded in the content records, or 0 if there are no records yet.
     pub fn add_content(&mut self, content: &[u8], cid: u32, content_type: ContentType, title_key: [u8; 16]) -> Result<(), ContentError> {
-        let max_index = self.content_records.borrow().iter()
-            .max_by_key(|record| record.index)
-            .map(|record| record.index)
-            .unwrap_or(0); // This should be impossible, but I guess 0 is a safe value just in case?
-        let new_index = max_index + 1;
+        let max_index = self.content_records.read().iter().map(|record| record.index).max();
+        let new_index = match max_index {
+            None => 0,
+            Some(max_index) => max_index.checked_add(1).ok_or(ContentError::IndexAlreadyExists(max_index))?,
+        };
         let content_size = content.len() as u64;
         let mut hasher = Sha1::new();
         hasher.update(content);
@@ -290,7 +604,104 @@ impl ContentRegion {
     }
 }
 
-#[derive(Debug)]
+// The amount of encrypted content decrypted at once by a ContentReader.
+const READ_CHUNK_SIZE: usize = 0x10000;
+
+/// A reader that decrypts content from a ContentRegion as it's read, so that the decrypted content
+/// never has to be held in memory all at once. The SHA-1 hash of the content is computed as it's
+/// decrypted, and the read that would return the end of the content fails with an error wrapping
+/// ContentError::BadHash if it doesn't match the content's record.
+pub struct ContentReader<'a> {
+    content: &'a [u8],
+    content_pos: usize,
+    remaining: u64,
+    decryptor: crypto::ContentDecryptor,
+    hasher: Sha1,
+    expected_hash: [u8; 20],
+    bad_hash: Option<String>,
+    finished: bool,
+    buf: Vec<u8>,
+    buf_pos: usize,
+}
+
+impl<'a> ContentReader<'a> {
+    fn new(content: &'a [u8], record: &ContentRecord, title_key: [u8; 16]) -> Self {
+        ContentReader {
+            content,
+            content_pos: 0,
+            remaining: record.content_size,
+            decryptor: crypto::ContentDecryptor::new(title_key, record.index),
+            hasher: Sha1::new(),
+            expected_hash: record.content_hash,
+            bad_hash: None,
+            finished: false,
+            buf: Vec::new(),
+            buf_pos: 0,
+        }
+    }
+
+    /// Reads the rest of the content into a writer, returning the number of bytes written. Unlike
+    /// std::io::copy(), a hash mismatch is returned as ContentError::BadHash rather than as an
+    /// IO error.
+    pub fn copy_to<W: Write>(mut self, writer: &mut W) -> Result<u64, ContentError> {
+        io::copy(&mut self, writer).map_err(|err| {
+            if err.get_ref().is_some_and(|inner| inner.is::<ContentError>()) {
+                *err.into_inner().unwrap().downcast::<ContentError>().unwrap()
+            } else {
+                ContentError::IO(err)
+            }
+        })
+    }
+
+    fn bad_hash_error(&self, hash: &str) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, ContentError::BadHash { hash: hash.to_string(), expected: hex::encode(self.expected_hash) })
+    }
+
+    // Decrypts the next chunk of content into the buffer, and checks the hash once the end of the
+    // content has been reached. The final chunk is never made available if the hash is wrong.
+    fn fill_buf(&mut self) -> io::Result<()> {
+        let chunk_len = (align_to(self.remaining, 16) as usize).min(READ_CHUNK_SIZE);
+        let chunk = self.content.get(self.content_pos..self.content_pos + chunk_len).ok_or(io::Error::from(io::ErrorKind::UnexpectedEof))?;
+        self.content_pos += chunk_len;
+        self.buf.clear();
+        self.buf.extend_from_slice(chunk);
+        self.decryptor.decrypt(&mut self.buf);
+        self.buf.truncate((chunk_len as u64).min(self.remaining) as usize);
+        self.buf_pos = 0;
+        self.remaining -= self.buf.len() as u64;
+        self.hasher.update(&self.buf);
+        if self.remaining == 0 {
+            self.finished = true;
+            let result = self.hasher.finalize_reset();
+            if result[..] != self.expected_hash {
+                self.buf.clear();
+                let hash = hex::encode(result);
+                let err = self.bad_hash_error(&hash);
+                self.bad_hash = Some(hash);
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Read for ContentReader<'_> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if let Some(hash) = &self.bad_hash {
+            return Err(self.bad_hash_error(hash));
+        }
+        // Empty content still needs its hash checked, so the buffer is filled at least once.
+        if self.buf_pos == self.buf.len() && !self.finished {
+            self.fill_buf()?;
+        }
+        let len = out.len().min(self.buf.len() - self.buf_pos);
+        out[..len].copy_from_slice(&self.buf[self.buf_pos..self.buf_pos + len]);
+        self.buf_pos += len;
+        Ok(len)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 /// A structure that represents a shared Content ID/content hash pairing in a content.map file.
 pub struct ContentMapEntry {
     pub shared_id: u32,
@@ -300,17 +711,26 @@ pub struct ContentMapEntry {
 /// A structure that allows for parsing and editing a /shared1/content.map file.
 pub struct SharedContentMap {
     pub records: Vec<ContentMapEntry>,
+    discarded_bytes: usize,
+}
+
+impl Default for SharedContentMap {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SharedContentMap {
-    /// Creates a new SharedContentMap instance from the binary data of a content.map file.
+    /// Creates a new SharedContentMap instance from the binary data of a content.map file. Each
+    /// entry is 28 bytes long, and a partial entry at the end of the file (usually left behind by
+    /// an interrupted write) is discarded rather than causing an error. See
+    /// SharedContentMap::discarded_bytes().
     pub fn from_bytes(data: &[u8]) -> Result<SharedContentMap, ContentError> {
-        // The uid.sys file must be divisible by a multiple of 28, or something is wrong, since each
-        // entry is 28 bytes long.
-        if (data.len() % 28) != 0 {
-            return Err(ContentError::InvalidSharedContentMapLength);
-        }
         let record_count = data.len() / 28;
+        let discarded_bytes = data.len() % 28;
+        if discarded_bytes > 0 {
+            log::warn!("discarding {} trailing bytes from content.map, which don't make up a whole entry", discarded_bytes);
+        }
         let mut buf = Cursor::new(data);
         let mut records: Vec<ContentMapEntry> = Vec::new();
         for _ in 0..record_count {
@@ -329,12 +749,35 @@ impl SharedContentMap {
             buf.read_exact(&mut hash)?;
             records.push(ContentMapEntry { shared_id, hash });
         }
-        Ok(SharedContentMap { records })
+        Ok(SharedContentMap { records, discarded_bytes })
     }
     
     /// Creates a new, empty SharedContentMap instance that can then be populated.
     pub fn new() -> Self {
-        SharedContentMap { records: Vec::new() }
+        SharedContentMap { records: Vec::new(), discarded_bytes: 0 }
+    }
+
+    /// Gets the number of bytes at the end of the content.map data that were discarded when it was
+    /// parsed, because they didn't make up a whole entry. These bytes are not written back out.
+    pub fn discarded_bytes(&self) -> usize {
+        self.discarded_bytes
+    }
+
+    /// Removes entries that repeat the hash or file name of an earlier entry, which a console
+    /// would never create, keeping the first of each. Returns the entries that were removed.
+    pub fn repair(&mut self) -> Vec<ContentMapEntry> {
+        let mut kept: Vec<ContentMapEntry> = Vec::new();
+        let mut removed: Vec<ContentMapEntry> = Vec::new();
+        for record in self.records.drain(..) {
+            if kept.iter().any(|entry| entry.hash == record.hash || entry.shared_id == record.shared_id) {
+                removed.push(record);
+            } else {
+                kept.push(record);
+            }
+        }
+        self.records = kept;
+        self.discarded_bytes = 0;
+        removed
     }
 
     /// Dumps the data in a SharedContentMap back into binary data that can be written to a file.
@@ -348,6 +791,29 @@ impl SharedContentMap {
         Ok(buf)
     }
 
+    /// Gets the file name (without the .app extension) and content hash of every record in
+    /// content.map. File names are lowercase, matching how they're written on a console.
+    pub fn entries(&self) -> Vec<(String, [u8; 20])> {
+        self.records.iter().map(|record| (format!("{:08x}", record.shared_id), record.hash)).collect()
+    }
+
+    /// Finds the file name (without the .app extension) of the shared content with the provided
+    /// hash, if it's present in content.map.
+    pub fn find_by_hash(&self, hash: &[u8; 20]) -> Option<String> {
+        self.records.iter()
+            .find(|record| record.hash == *hash)
+            .map(|record| format!("{:08x}", record.shared_id))
+    }
+
+    /// Removes the shared content with the provided hash from content.map. Returns the file name
+    /// (without the .app extension) that the content was stored under, or None if the hash wasn't
+    /// present. Other records keep their file names, so the removed name is left unused.
+    pub fn remove(&mut self, hash: &[u8; 20]) -> Option<String> {
+        let index = self.records.iter().position(|record| record.hash == *hash)?;
+        let record = self.records.remove(index);
+        Some(format!("{:08x}", record.shared_id))
+    }
+
     /// Adds new shared content to content.map, and assigns it a new file name. The new content 
     /// will only be added if its hash is not already present in the file. Returns None if the 
     /// content hash was already present, or the assigned file name if the hash was just added.
@@ -369,3 +835,290 @@ impl SharedContentMap {
         Ok(Some(format!("{:08X}", max_index)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::title::test_utils;
+
+    #[test]
+    fn test_out_of_order_records() {
+        let contents = [vec![0x01; 100], vec![0x02; 200], vec![0x03; 300]];
+        let title = test_utils::build_title_with_indices(&[(2, contents[0].clone()), (0, contents[1].clone()), (1, contents[2].clone())]);
+        let region = &title.content;
+        assert_eq!(region.get_index_from_cid(0x10).unwrap(), 0);
+        assert_eq!(region.get_index_from_cid(0x12).unwrap(), 2);
+        // Rebuilding the region from its encrypted contents should keep each content in place.
+        let enc_contents: Vec<Vec<u8>> = (0..3).map(|i| region.get_enc_content_by_index(i).unwrap()).collect();
+        let rebuilt = ContentRegion::from_contents(enc_contents, ContentRecords::new(region.content_records.read().clone())).unwrap();
+        for (i, content) in contents.iter().enumerate() {
+            assert_eq!(&rebuilt.get_content_by_index(i, test_utils::TEST_TITLE_KEY).unwrap(), content);
+        }
+        // The same should be true after a round trip through a WAD.
+        let reloaded = crate::title::Title::from_bytes(&title.to_wad().unwrap().to_bytes().unwrap()).unwrap();
+        assert_eq!(reloaded.tmd.content_record_by_index(0).unwrap().content_id, 0x11);
+        for (i, content) in contents.iter().enumerate() {
+            assert_eq!(&reloaded.get_content_by_index(i).unwrap(), content);
+        }
+        assert_eq!(reloaded.get_content_by_cid(0x12).unwrap(), contents[2]);
+    }
+
+    #[test]
+    fn test_set_content_out_of_order() {
+        let mut title = test_utils::build_title_with_indices(&[(2, vec![0x01; 100]), (0, vec![0x02; 200]), (1, vec![0x03; 300])]);
+        // New content has to be encrypted with the index in its record, not its position.
+        title.set_content(&[0x09; 40], 0, None, None).unwrap();
+        assert_eq!(title.get_content_by_index(0).unwrap(), [0x09; 40]);
+        assert_eq!(title.get_content_by_index(1).unwrap(), [0x02; 200]);
+        let reloaded = crate::title::Title::from_bytes(&title.to_wad_bytes().unwrap()).unwrap();
+        assert_eq!(reloaded.get_content_by_index(0).unwrap(), [0x09; 40]);
+        assert_eq!(reloaded.tmd.content_record_by_index(2).unwrap().content_size, 40);
+    }
+
+    #[test]
+    fn test_from_bytes_sizes() {
+        let contents = [vec![0x01; 100], vec![0x02; 200]];
+        let title = test_utils::build_title(&contents);
+        let records = || ContentRecords::new(title.content.content_records.read().clone());
+        // The second content ends at 128 + 208 bytes, and is padded to 128 + 256 bytes.
+        let padded = title.content.to_bytes().unwrap();
+        assert_eq!(padded.len(), 384);
+        let exact = &padded[..336];
+        for data in [exact, &padded[..]] {
+            let region = ContentRegion::from_bytes(data, records()).unwrap();
+            assert!(region.trailing_data().is_empty());
+            assert_eq!(region.to_bytes().unwrap(), padded);
+            assert_eq!(region.get_content_by_index(1, test_utils::TEST_TITLE_KEY).unwrap(), contents[1]);
+        }
+        // Short content regions should name the first content that couldn't be read.
+        assert!(matches!(ContentRegion::from_bytes(&padded[..335], records()),
+            Err(ContentError::TruncatedContent { index: 1, cid: 0x11, required: 336, found: 335 })));
+        assert!(matches!(ContentRegion::from_bytes(&padded[..100], records()),
+            Err(ContentError::TruncatedContent { index: 0, cid: 0x10, required: 112, found: 100 })));
+        // Extra data after the padded contents should survive a round trip.
+        let mut extended = padded.clone();
+        extended.extend_from_slice(&[0xAA; 64]);
+        let region = ContentRegion::from_bytes(&extended, records()).unwrap();
+        assert_eq!(region.trailing_data(), [0xAA; 64]);
+        assert_eq!(region.to_bytes().unwrap(), extended);
+    }
+
+    #[test]
+    fn test_probe_content() {
+        let contents = [vec![0x01; 100], vec![0x02; 32]];
+        let title = test_utils::build_title(&contents);
+        let key = test_utils::TEST_TITLE_KEY;
+        let mut region = ContentRegion::new(ContentRecords::new(title.content.content_records.read().clone())).unwrap();
+        let enc_content = title.content.get_enc_content_by_index(0).unwrap();
+        assert_eq!(region.probe_content(&contents[0], 0, key, None).unwrap(), ContentForm::Decrypted);
+        assert_eq!(region.get_enc_content_by_index(0).unwrap(), enc_content);
+        assert_eq!(region.probe_content(&enc_content, 0, key, None).unwrap(), ContentForm::Encrypted);
+        assert_eq!(region.get_content_by_index(0, key).unwrap(), contents[0]);
+        // Content that's already a multiple of 16 bytes must still be detected correctly.
+        assert_eq!(region.probe_content(&contents[1], 1, key, None).unwrap(), ContentForm::Decrypted);
+        assert_eq!(region.probe_content(&title.content.get_enc_content_by_index(1).unwrap(), 1, key, None).unwrap(), ContentForm::Encrypted);
+        assert!(matches!(region.probe_content(&contents[0], 0, key, Some(ContentForm::Encrypted)),
+            Err(ContentError::BadEncryptedContent { index: 0, .. })));
+        assert!(matches!(region.probe_content(&enc_content, 0, key, Some(ContentForm::Decrypted)), Err(ContentError::BadHash { .. })));
+        assert!(matches!(region.probe_content(&[0xFF; 112], 0, key, None), Err(ContentError::UnrecognizedContent { index: 0, .. })));
+    }
+
+    #[test]
+    fn test_alignment() {
+        let contents = [vec![0x01; 100], vec![0x02; 200], vec![0x03; 40]];
+        let mut title = test_utils::build_title(&contents);
+        let records = title.content.content_records.read().clone();
+        assert_eq!(title.content.alignment(), DEFAULT_CONTENT_ALIGNMENT);
+        assert!(matches!(title.content.set_alignment(24), Err(ContentError::InvalidAlignment(24))));
+        assert!(matches!(title.content.set_alignment(8), Err(ContentError::InvalidAlignment(8))));
+        for (alignment, expected_len) in [(16, 112 + 208 + 48), (32, 128 + 224 + 64), (64, 128 + 256 + 64)] {
+            title.content.set_alignment(alignment).unwrap();
+            let data = title.content.to_bytes().unwrap();
+            assert_eq!(data.len(), expected_len);
+            let region = ContentRegion::from_bytes(&data, ContentRecords::new(records.clone())).unwrap();
+            assert_eq!(region.alignment(), alignment);
+            assert!(region.trailing_data().is_empty());
+            assert_eq!(region.to_bytes().unwrap(), data);
+            assert_eq!(region.get_content_by_index(2, test_utils::TEST_TITLE_KEY).unwrap(), contents[2]);
+        }
+    }
+
+    #[test]
+    fn test_next_available_cid() {
+        let mut region = ContentRegion::new(ContentRecords::new(Vec::new())).unwrap();
+        assert_eq!(region.next_available_cid(), 0);
+        for i in 0..400u16 {
+            let cid = region.next_available_cid();
+            assert!(!region.content_records.read().iter().any(|record| record.content_id == cid));
+            region.add_enc_content(&[0; 16], i, cid, ContentType::Normal, 16, [0; 20]).unwrap();
+        }
+        assert_eq!(region.next_available_cid(), 400);
+        // Once the highest Content ID is taken, the lowest free one should be used instead.
+        region.add_enc_content(&[0; 16], 400, u32::MAX, ContentType::Normal, 16, [0; 20]).unwrap();
+        region.remove_content(17).unwrap();
+        assert_eq!(region.next_available_cid(), 17);
+    }
+
+    #[test]
+    fn test_empty_region() {
+        let key = test_utils::TEST_TITLE_KEY;
+        let mut region = ContentRegion::new(ContentRecords::new(Vec::new())).unwrap();
+        assert!(ContentRegion::from_bytes(&[], ContentRecords::new(Vec::new())).is_ok());
+        assert!(ContentRegion::from_contents(Vec::new(), ContentRecords::new(Vec::new())).is_ok());
+        assert!(ContentRegion::from_optional_contents(Vec::new(), ContentRecords::new(Vec::new())).is_ok());
+        assert!(region.to_bytes().unwrap().is_empty());
+        assert_eq!(region.data_size(), 0);
+        assert!(!region.is_content_present(0));
+        assert_eq!(region.next_available_cid(), 0);
+        assert_eq!(region.iter_by_position().count(), 0);
+        assert_eq!(region.iter_by_record_index().count(), 0);
+        // Anything that looks up content by position should report that there are no records,
+        // rather than underflowing while working out the maximum index.
+        assert!(matches!(region.get_content_by_index(0, key), Err(ContentError::Empty(0))));
+        assert!(matches!(region.get_enc_content_by_index(0), Err(ContentError::Empty(0))));
+        assert!(matches!(region.open_content_by_index(0, key), Err(ContentError::Empty(0))));
+        assert!(matches!(region.load_enc_content(&[0; 16], 0), Err(ContentError::Empty(0))));
+        assert!(matches!(region.load_content(&[0; 16], 0, key), Err(ContentError::Empty(0))));
+        assert!(matches!(region.set_enc_content(&[0; 16], 0, 16, [0; 20], None, None), Err(ContentError::Empty(0))));
+        assert!(matches!(region.set_content(&[0; 16], 0, None, None, key), Err(ContentError::Empty(0))));
+        assert!(matches!(region.probe_content(&[0; 16], 0, key, None), Err(ContentError::Empty(0))));
+        assert!(matches!(region.remove_content(0), Err(ContentError::Empty(0))));
+        assert!(matches!(region.get_index_from_cid(0), Err(ContentError::CIDNotFound(0))));
+        assert!(matches!(region.get_content_by_cid(0, key), Err(ContentError::CIDNotFound(0))));
+        assert!(matches!(region.get_enc_content_by_cid(0), Err(ContentError::CIDNotFound(0))));
+        assert!(matches!(region.get_position_for_index(0), Err(ContentError::RecordIndexNotFound(0))));
+        region.reindex(key).unwrap();
+        // The first content added to an empty region gets index 0.
+        region.add_content(&[0x01; 32], 0x10, ContentType::Normal, key).unwrap();
+        assert_eq!(region.content_records.read()[0].index, 0);
+        assert_eq!(region.get_content_by_index(0, key).unwrap(), [0x01; 32]);
+    }
+
+    #[test]
+    fn test_single_record_region() {
+        let key = test_utils::TEST_TITLE_KEY;
+        let title = test_utils::build_title(&[vec![0x01; 32]]);
+        let mut region = ContentRegion::from_bytes(&title.content.to_bytes().unwrap(), ContentRecords::new(title.content.content_records.read().clone())).unwrap();
+        assert!(region.is_content_present(0));
+        assert!(!region.is_content_present(1));
+        assert_eq!(region.get_content_by_index(0, key).unwrap(), [0x01; 32]);
+        assert_eq!(region.get_content_by_cid(0x10, key).unwrap(), [0x01; 32]);
+        assert_eq!(region.get_index_from_cid(0x10).unwrap(), 0);
+        assert_eq!(region.get_position_for_index(0).unwrap(), 0);
+        assert_eq!(region.next_available_cid(), 0x11);
+        assert!(matches!(region.get_content_by_index(1, key), Err(ContentError::IndexOutOfRange { index: 1, max: 0 })));
+        assert!(matches!(region.get_enc_content_by_index(1), Err(ContentError::IndexOutOfRange { index: 1, max: 0 })));
+        assert!(matches!(region.open_content_by_index(1, key), Err(ContentError::IndexOutOfRange { index: 1, max: 0 })));
+        assert!(matches!(region.load_enc_content(&[0; 16], 1), Err(ContentError::IndexOutOfRange { index: 1, max: 0 })));
+        assert!(matches!(region.load_content(&[0; 16], 1, key), Err(ContentError::IndexOutOfRange { index: 1, max: 0 })));
+        assert!(matches!(region.set_content(&[0; 16], 1, None, None, key), Err(ContentError::IndexOutOfRange { index: 1, max: 0 })));
+        assert!(matches!(region.probe_content(&[0; 16], 1, key, None), Err(ContentError::IndexOutOfRange { index: 1, max: 0 })));
+        assert!(matches!(region.remove_content(1), Err(ContentError::IndexOutOfRange { index: 1, max: 0 })));
+        region.set_content(&[0x02; 48], 0, None, None, key).unwrap();
+        assert_eq!(region.get_content_by_index(0, key).unwrap(), [0x02; 48]);
+        region.add_content(&[0x03; 16], 0x11, ContentType::Normal, key).unwrap();
+        assert_eq!(region.content_records.read()[1].index, 1);
+        // Removing every record leaves an empty region behind, which should behave like one.
+        region.remove_content(1).unwrap();
+        region.remove_content(0).unwrap();
+        assert!(matches!(region.get_content_by_index(0, key), Err(ContentError::Empty(0))));
+        assert!(region.to_bytes().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_missing_contents() {
+        let contents = [vec![0x01; 100], vec![0x02; 200], vec![0x03; 300]];
+        let title = test_utils::build_title(&contents);
+        let records = || ContentRecords::new(title.content.content_records.read().clone());
+        let enc_contents: Vec<Option<Vec<u8>>> = (0..3)
+            .map(|i| (i != 1).then(|| title.content.get_enc_content_by_index(i).unwrap()))
+            .collect();
+        let region = ContentRegion::from_optional_contents(enc_contents, records()).unwrap();
+        assert!(!region.is_content_present(1));
+        assert!(!region.is_content_present(3));
+        assert!(matches!(region.get_enc_content_by_cid(0x11), Err(ContentError::ContentNotPresent(1))));
+        // The absent content shouldn't take up any space in the content region.
+        let data = region.to_bytes().unwrap();
+        assert_eq!(data.len(), 128 + 320);
+        let present = [true, false, true];
+        let reparsed = ContentRegion::from_bytes_with_presence(&data, records(), &present).unwrap();
+        assert_eq!(reparsed.get_content_by_index(2, test_utils::TEST_TITLE_KEY).unwrap(), contents[2]);
+        assert!(!reparsed.is_content_present(1));
+        assert_eq!(reparsed.to_bytes().unwrap(), data);
+        assert!(matches!(ContentRegion::from_bytes(&data, records()), Err(ContentError::TruncatedContent { index: 2, .. })));
+        assert!(matches!(ContentRegion::from_bytes_with_presence(&data, records(), &present[..2]), Err(ContentError::MissingContents { required: 3, found: 2 })));
+    }
+
+    #[test]
+    fn test_records_changed_after_creation() {
+        let contents = [vec![0x01; 100], vec![0x02; 200]];
+        let mut title = test_utils::build_title(&contents);
+        // Removing a record through the TMD leaves the region with more contents than records,
+        // which should be reported rather than causing a panic.
+        let record = title.tmd.content_records.write().pop().unwrap();
+        assert!(matches!(title.content.get_content_by_index(1, test_utils::TEST_TITLE_KEY), Err(ContentError::IndexOutOfRange { index: 1, max: 0 })));
+        assert!(matches!(title.content.get_enc_content_by_index(1), Err(ContentError::IndexOutOfRange { index: 1, max: 0 })));
+        assert_eq!(title.content.get_content_by_index(0, test_utils::TEST_TITLE_KEY).unwrap(), contents[0]);
+        // Adding records through the TMD should allow content to be loaded for them.
+        title.tmd.content_records.write().push(record.clone());
+        let mut extra = record;
+        extra.content_id = 0x20;
+        extra.index = 2;
+        title.tmd.content_records.write().push(extra);
+        title.content.contents.truncate(1);
+        assert!(matches!(title.content.get_enc_content_by_index(2), Err(ContentError::ContentNotPresent(2))));
+        title.content.load_content(&contents[1], 2, test_utils::TEST_TITLE_KEY).unwrap();
+        assert!(title.content.is_content_present(2));
+        assert!(!title.content.is_content_present(1));
+    }
+
+    #[test]
+    fn test_shared_content_map() {
+        let mut map = SharedContentMap::new();
+        assert_eq!(map.add(&[0x11; 20]).unwrap().as_deref(), Some("00000000"));
+        assert_eq!(map.add(&[0x22; 20]).unwrap().as_deref(), Some("00000001"));
+        assert_eq!(map.add(&[0x11; 20]).unwrap(), None);
+        for _ in 0..9 {
+            map.add(&[map.records.len() as u8; 20]).unwrap();
+        }
+        assert_eq!(map.find_by_hash(&[0x0A; 20]).as_deref(), Some("0000000a"));
+        assert_eq!(map.entries()[1], (String::from("00000001"), [0x22; 20]));
+        let reparsed = SharedContentMap::from_bytes(&map.to_bytes().unwrap()).unwrap();
+        assert_eq!(reparsed.entries(), map.entries());
+        assert_eq!(map.remove(&[0x22; 20]).as_deref(), Some("00000001"));
+        assert_eq!(map.remove(&[0x22; 20]), None);
+        assert_eq!(map.find_by_hash(&[0x22; 20]), None);
+        assert_eq!(map.entries().len(), 10);
+        // Removing a record shouldn't cause a name to be reused.
+        assert_eq!(map.add(&[0x33; 20]).unwrap().as_deref(), Some("0000000B"));
+    }
+
+    fn build_content_map(entries: &[(&str, u8)]) -> Vec<u8> {
+        let mut data: Vec<u8> = Vec::new();
+        for (name, hash_byte) in entries {
+            data.extend_from_slice(name.as_bytes());
+            data.extend_from_slice(&[*hash_byte; 20]);
+        }
+        data
+    }
+
+    #[test]
+    fn test_shared_content_map_recovery() {
+        // A truncated final entry is discarded.
+        let data = build_content_map(&[("00000000", 0x11), ("00000001", 0x22), ("00000002", 0x33)]);
+        let map = SharedContentMap::from_bytes(&data[..data.len() - 9]).unwrap();
+        assert_eq!(map.entries().len(), 2);
+        assert_eq!(map.discarded_bytes(), 19);
+        assert_eq!(map.to_bytes().unwrap(), data[..56]);
+        assert_eq!(SharedContentMap::from_bytes(&data[..5]).unwrap().discarded_bytes(), 5);
+        // Entries that repeat a hash or a name are removed by a repair.
+        let data = build_content_map(&[("00000000", 0x11), ("00000001", 0x22), ("00000002", 0x11), ("00000001", 0x44), ("00000003", 0x55)]);
+        let mut map = SharedContentMap::from_bytes(&data).unwrap();
+        assert_eq!(map.discarded_bytes(), 0);
+        let removed = map.repair();
+        assert_eq!(removed, vec![ContentMapEntry { shared_id: 2, hash: [0x11; 20] }, ContentMapEntry { shared_id: 1, hash: [0x44; 20] }]);
+        let names: Vec<String> = map.entries().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["00000000", "00000001", "00000003"]);
+        assert!(map.repair().is_empty());
+    }
+}
@@ -5,6 +5,7 @@
 
 use aes::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
 use aes::cipher::block_padding::ZeroPadding;
+use sha1::{Sha1, Digest};
 use crate::title::commonkeys::get_common_key;
 
 // Convert a Title ID into the format required for use as the Title Key decryption IV.
@@ -45,6 +46,30 @@ pub fn decrypt_content(data: &[u8], title_key: [u8; 16], index: u16) -> Vec<u8>
     buf
 }
 
+/// A streaming content decryptor, which decrypts content in pieces as it's read rather than all at
+/// once. The CBC chaining state is carried between calls, so decrypting content in several pieces
+/// produces the same result as decrypt_content().
+pub struct ContentDecryptor {
+    decryptor: cbc::Decryptor<aes::Aes128>,
+}
+
+impl ContentDecryptor {
+    /// Creates a new ContentDecryptor for the content with the corresponding Title Key and index.
+    pub fn new(title_key: [u8; 16], index: u16) -> Self {
+        let mut iv = [0u8; 16];
+        iv[..2].copy_from_slice(&index.to_be_bytes());
+        ContentDecryptor { decryptor: cbc::Decryptor::new(&title_key.into(), &iv.into()) }
+    }
+
+    /// Decrypts the next piece of content in place. The length of the data must be a multiple of
+    /// 16 bytes, and any partial block at the end is left as-is.
+    pub fn decrypt(&mut self, data: &mut [u8]) {
+        for block in data.chunks_exact_mut(16) {
+            self.decryptor.decrypt_block_mut(block.into());
+        }
+    }
+}
+
 /// Encrypt content using the corresponding Title Key and content index.
 pub fn encrypt_content(data: &[u8], title_key: [u8; 16], index: u16, size: u64) -> Vec<u8> {
     let mut iv = Vec::from(index.to_be_bytes());
@@ -58,3 +83,23 @@ pub fn encrypt_content(data: &[u8], title_key: [u8; 16], index: u16, size: u64)
     buf.resize(size as usize, 0);
     buf
 }
+
+/// Brute-forces a signed body (a TMD or Ticket) into a fakesigned state by changing the unused
+/// 16-bit field at the provided offset until the SHA-1 hash of the signed data (which starts at
+/// body_offset, after the signature and its padding) starts with 0x00. The body is only serialized
+/// once by the caller and the hash state up to the field is computed once, so each attempt only
+/// rehashes the data following the field. Returns the value that was written, or None if no value
+/// produced a valid hash.
+pub fn fakesign_body(body: &mut [u8], body_offset: usize, field_offset: usize) -> Option<u16> {
+    let mut prefix_hasher = Sha1::new();
+    prefix_hasher.update(&body[body_offset..field_offset]);
+    for current_int in 1..u16::MAX {
+        body[field_offset..field_offset + 2].copy_from_slice(&current_int.to_be_bytes());
+        let mut hasher = prefix_hasher.clone();
+        hasher.update(&body[field_offset..]);
+        if hasher.finalize()[0] == 0 {
+            return Some(current_int);
+        }
+    }
+    None
+}
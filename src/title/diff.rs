@@ -0,0 +1,308 @@
+// title/diff.rs from rustii (c) 2025 NinjaCheetah & Contributors
+// https://github.com/NinjaCheetah/rustii
+//
+// Implements structured comparisons between two digital Wii titles, or their TMDs and Tickets.
+
+use std::fmt;
+use std::ops::Range;
+use crate::title::{ticket, tmd, Title};
+
+#[derive(Debug, Clone, PartialEq)]
+/// A single field that differs between two titles, with the value of the field in each title.
+pub struct FieldDiff {
+    pub field: String,
+    pub left: String,
+    pub right: String,
+}
+
+impl fmt::Display for FieldDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} -> {}", self.field, self.left, self.right)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A difference between the content records of two titles, keyed by Content ID.
+pub enum ContentDiff {
+    /// A content that only exists in the second title.
+    Added { cid: u32 },
+    /// A content that only exists in the first title.
+    Removed { cid: u32 },
+    /// A content that exists in both titles, but with different records.
+    Changed { cid: u32, changes: Vec<FieldDiff> },
+}
+
+impl fmt::Display for ContentDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ContentDiff::Added { cid } => write!(f, "Content {:08X}: added", cid),
+            ContentDiff::Removed { cid } => write!(f, "Content {:08X}: removed", cid),
+            ContentDiff::Changed { cid, changes } => {
+                let changes: Vec<String> = changes.iter().map(|change| change.to_string()).collect();
+                write!(f, "Content {:08X}: {}", cid, changes.join(", "))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// A structure that represents all of the differences found between two TMDs.
+pub struct TmdDiff {
+    pub fields: Vec<FieldDiff>,
+    pub contents: Vec<ContentDiff>,
+}
+
+impl TmdDiff {
+    /// Gets whether no differences were found between the two TMDs.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty() && self.contents.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// A structure that represents all of the differences found between two Tickets.
+pub struct TicketDiff {
+    pub fields: Vec<FieldDiff>,
+}
+
+impl TicketDiff {
+    /// Gets whether no differences were found between the two Tickets.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// A structure that represents all of the differences found between two titles.
+pub struct TitleDiff {
+    pub header: Vec<FieldDiff>,
+    pub tmd: Vec<FieldDiff>,
+    pub ticket: Vec<FieldDiff>,
+    pub contents: Vec<ContentDiff>,
+}
+
+impl TitleDiff {
+    /// Gets whether no differences were found between the two titles.
+    pub fn is_empty(&self) -> bool {
+        self.header.is_empty() && self.tmd.is_empty() && self.ticket.is_empty() && self.contents.is_empty()
+    }
+}
+
+// Records a FieldDiff if the two values don't match.
+fn compare<T: PartialEq + fmt::Display>(diffs: &mut Vec<FieldDiff>, field: &str, left: T, right: T) {
+    if left != right {
+        diffs.push(FieldDiff { field: field.to_owned(), left: left.to_string(), right: right.to_string() });
+    }
+}
+
+/// Compares two TMDs, returning a TmdDiff describing every difference in their fields and content
+/// records. Content records are matched by Content ID.
+pub fn diff_tmds(left: &tmd::TMD, right: &tmd::TMD) -> TmdDiff {
+    let mut diff = TmdDiff::default();
+    compare(&mut diff.fields, "Title ID", left.title_id(), right.title_id());
+    compare(&mut diff.fields, "Signature Issuer", left.signature_issuer(), right.signature_issuer());
+    compare(&mut diff.fields, "Title Version", left.title_version, right.title_version);
+    compare(&mut diff.fields, "Required IOS", hex::encode(left.ios_tid()), hex::encode(right.ios_tid()));
+    compare(&mut diff.fields, "Region", left.region(), right.region());
+    compare(&mut diff.fields, "Group ID", format!("0x{:04X}", left.group_id), format!("0x{:04X}", right.group_id));
+    compare(&mut diff.fields, "Access Rights", format!("0x{:08X}", left.access_rights.bits()), format!("0x{:08X}", right.access_rights.bits()));
+    compare(&mut diff.fields, "Age Ratings", hex::encode(left.ratings.to_bytes()), hex::encode(right.ratings.to_bytes()));
+    compare(&mut diff.fields, "Boot Index", left.boot_index, right.boot_index);
+    compare(&mut diff.fields, "vWii Title", left.is_vwii(), right.is_vwii());
+    let left_records = left.content_records.read();
+    let right_records = right.content_records.read();
+    for record in left_records.iter() {
+        match right_records.iter().find(|other| other.content_id == record.content_id) {
+            Some(other) => {
+                let mut changes: Vec<FieldDiff> = Vec::new();
+                compare(&mut changes, "Index", record.index, other.index);
+                compare(&mut changes, "Type", record.content_type.to_string(), other.content_type.to_string());
+                compare(&mut changes, "Size", record.content_size, other.content_size);
+                compare(&mut changes, "Hash", hex::encode(record.content_hash), hex::encode(other.content_hash));
+                if !changes.is_empty() {
+                    diff.contents.push(ContentDiff::Changed { cid: record.content_id, changes });
+                }
+            },
+            None => diff.contents.push(ContentDiff::Removed { cid: record.content_id }),
+        }
+    }
+    for record in right_records.iter() {
+        if !left_records.iter().any(|other| other.content_id == record.content_id) {
+            diff.contents.push(ContentDiff::Added { cid: record.content_id });
+        }
+    }
+    diff
+}
+
+/// Compares two Tickets, returning a TicketDiff describing every difference in their fields. The
+/// decrypted Title Key is compared, since the encrypted key also changes when only the common key
+/// does.
+pub fn diff_tickets(left: &ticket::Ticket, right: &ticket::Ticket) -> TicketDiff {
+    let mut diff = TicketDiff::default();
+    compare(&mut diff.fields, "Title ID", left.title_id(), right.title_id());
+    compare(&mut diff.fields, "Signature Issuer", left.signature_issuer(), right.signature_issuer());
+    compare(&mut diff.fields, "Title Key", hex::encode(left.dec_title_key()), hex::encode(right.dec_title_key()));
+    compare(&mut diff.fields, "Common Key Index", left.common_key_index(), right.common_key_index());
+    compare(&mut diff.fields, "Title Version", left.title_version, right.title_version);
+    compare(&mut diff.fields, "Ticket ID", hex::encode_upper(left.ticket_id), hex::encode_upper(right.ticket_id));
+    compare(&mut diff.fields, "Console ID", hex::encode_upper(left.console_id), hex::encode_upper(right.console_id));
+    let format_access = |ticket: &ticket::Ticket| {
+        if ticket.all_contents_permitted() {
+            return String::from("All");
+        }
+        let permitted: Vec<String> = ticket.permitted_contents().iter().map(|index| index.to_string()).collect();
+        format!("[{}]", permitted.join(", "))
+    };
+    compare(&mut diff.fields, "Content Access", format_access(left), format_access(right));
+    let format_limits = |ticket: &ticket::Ticket| {
+        let limits: Vec<String> = ticket.limits().iter()
+            .filter(|(limit_type, _)| *limit_type != ticket::LimitType::None)
+            .map(|(limit_type, limit_max)| format!("{} (max {})", limit_type, limit_max))
+            .collect();
+        if limits.is_empty() { String::from("None") } else { limits.join(", ") }
+    };
+    compare(&mut diff.fields, "Title Limits", format_limits(left), format_limits(right));
+    diff
+}
+
+/// Compares two titles, returning a TitleDiff describing every difference in their identifying
+/// fields, TMDs, Tickets, and content records. Content records are matched by Content ID.
+pub fn diff_titles(left: &Title, right: &Title) -> TitleDiff {
+    let mut diff = TitleDiff::default();
+    // Title-wide fields.
+    compare(&mut diff.header, "Title ID", left.tmd.title_id(), right.tmd.title_id());
+    compare(&mut diff.header, "Title Type",
+            left.tmd.title_type().map(|t| t.to_string()).unwrap_or_else(|_| String::from("Unknown")),
+            right.tmd.title_type().map(|t| t.to_string()).unwrap_or_else(|_| String::from("Unknown")));
+    compare(&mut diff.header, "Certificate Chain",
+            hex::encode(left.cert_chain.to_bytes().unwrap_or_default()),
+            hex::encode(right.cert_chain.to_bytes().unwrap_or_default()));
+    compare(&mut diff.header, "Footer Size", left.meta().len(), right.meta().len());
+    // The Title ID is already covered by the title-wide fields, so it's left out of the TMD and
+    // Ticket sections to avoid reporting it three times.
+    let tmd_diff = diff_tmds(&left.tmd, &right.tmd);
+    diff.tmd = tmd_diff.fields.into_iter().filter(|field| field.field != "Title ID").collect();
+    diff.contents = tmd_diff.contents;
+    diff.ticket = diff_tickets(&left.ticket, &right.ticket).fields.into_iter().filter(|field| field.field != "Title ID").collect();
+    diff
+}
+
+/// Compares two blocks of binary data, returning the ranges of offsets where they differ. If one
+/// block is longer than the other, the extra data is included as a differing range.
+pub fn diff_bytes(left: &[u8], right: &[u8]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    let mut start: Option<usize> = None;
+    let common_len = left.len().min(right.len());
+    for i in 0..common_len {
+        match (left[i] != right[i], start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                ranges.push(s..i);
+                start = None;
+            },
+            _ => (),
+        }
+    }
+    let end = left.len().max(right.len());
+    if let Some(s) = start {
+        ranges.push(s..end);
+    } else if common_len < end {
+        ranges.push(common_len..end);
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::title::{test_utils, TitleId};
+
+    #[test]
+    fn test_diff_identical_titles() {
+        let left = test_utils::build_title(&[vec![0x01; 32], vec![0x02; 64]]);
+        let right = test_utils::build_title(&[vec![0x01; 32], vec![0x02; 64]]);
+        assert!(diff_titles(&left, &right).is_empty());
+    }
+
+    #[test]
+    fn test_diff_titles() {
+        let left = test_utils::build_title(&[vec![0x01; 32], vec![0x02; 64]]);
+        let mut right = test_utils::build_title(&[vec![0x01; 32], vec![0x03; 64]]);
+        right.tmd.title_version = 2;
+        right.tmd.boot_index = 1;
+        right.ticket.set_common_key_index(1).unwrap();
+        right.add_content(&[0x04; 16], Some(0x40), tmd::ContentType::Normal).unwrap();
+        right.remove_content(0).unwrap();
+        let diff = diff_titles(&left, &right);
+        assert!(diff.header.is_empty());
+        let tmd_fields: Vec<&str> = diff.tmd.iter().map(|d| d.field.as_str()).collect();
+        assert_eq!(tmd_fields, vec!["Title Version", "Boot Index"]);
+        assert!(diff.ticket.iter().any(|d| d.field == "Common Key Index" && d.left == "0" && d.right == "1"));
+        assert_eq!(diff.contents[0], ContentDiff::Removed { cid: 0x10 });
+        assert!(matches!(&diff.contents[1], ContentDiff::Changed { cid: 0x11, changes } if changes.len() == 1 && changes[0].field == "Hash"));
+        assert_eq!(diff.contents[2], ContentDiff::Added { cid: 0x40 });
+    }
+
+    #[test]
+    fn test_diff_tmds() {
+        let left = test_utils::build_title(&[vec![0x01; 32], vec![0x02; 64], vec![0x03; 16]]);
+        let mut right = test_utils::build_title(&[vec![0x01; 32], vec![0x02; 64], vec![0x03; 16]]);
+        // Change every scalar field that gets compared.
+        right.tmd.set_title_id(TitleId::from_hex("0000000100000050").unwrap()).unwrap();
+        right.tmd.set_signature_issuer(String::from("Root-CA00000002-CP00000007")).unwrap();
+        right.tmd.title_version = 0x1F00;
+        right.tmd.set_ios_tid([0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x3A]).unwrap();
+        right.tmd.set_region("EUR").unwrap();
+        right.tmd.group_id = 0x3031;
+        right.tmd.set_access_right(tmd::AccessRight::AHB, true);
+        right.tmd.ratings.set(tmd::RatingAgency::ALL[0], Some(12)).unwrap();
+        right.tmd.boot_index = 1;
+        right.tmd.set_is_vwii(true);
+        // Change a content's hash and size, change another's type, remove one, and add one.
+        right.set_content(&[0x04; 48], 0, None, None).unwrap();
+        right.tmd.content_records.write()[1].content_type = tmd::ContentType::Shared;
+        right.remove_content(2).unwrap();
+        right.add_content(&[0x05; 16], Some(0x40), tmd::ContentType::Normal).unwrap();
+        let diff = left.tmd.diff(&right.tmd);
+        let fields: Vec<&str> = diff.fields.iter().map(|d| d.field.as_str()).collect();
+        assert_eq!(fields, vec!["Title ID", "Signature Issuer", "Title Version", "Required IOS", "Region", "Group ID",
+                                "Access Rights", "Age Ratings", "Boot Index", "vWii Title"]);
+        assert!(diff.fields.iter().any(|d| d.field == "Required IOS" && d.left == "0000000100000038" && d.right == "000000010000003a"));
+        assert!(matches!(&diff.contents[0], ContentDiff::Changed { cid: 0x10, changes }
+            if changes.iter().map(|c| c.field.as_str()).collect::<Vec<_>>() == vec!["Size", "Hash"]));
+        assert!(matches!(&diff.contents[1], ContentDiff::Changed { cid: 0x11, changes } if changes.len() == 1 && changes[0].field == "Type"));
+        assert_eq!(diff.contents[2], ContentDiff::Removed { cid: 0x12 });
+        assert_eq!(diff.contents[3], ContentDiff::Added { cid: 0x40 });
+        assert!(left.tmd.diff(&left.tmd.clone()).is_empty());
+    }
+
+    #[test]
+    fn test_diff_tickets() {
+        let left = ticket::Ticket::from_bytes(&test_utils::build_ticket(test_utils::TEST_TITLE_ID, test_utils::TEST_TITLE_KEY)).unwrap();
+        let mut right = ticket::Ticket::from_bytes(&test_utils::build_ticket(test_utils::TEST_TITLE_ID, test_utils::TEST_TITLE_KEY)).unwrap();
+        assert!(left.diff(&right).is_empty());
+        right.set_title_id(TitleId::from_hex("0001000148414445").unwrap()).unwrap();
+        right.set_signature_issuer(String::from("Root-CA00000002-XS00000006")).unwrap();
+        right.set_dec_title_key([0xAB; 16]);
+        right.set_common_key_index(1).unwrap();
+        right.title_version = 3;
+        right.ticket_id = [0x01; 8];
+        right.console_id = [0x02; 4];
+        right.set_content_permitted(1, true).unwrap();
+        right.set_limit(0, ticket::LimitType::Time, 30).unwrap();
+        let diff = left.diff(&right);
+        let fields: Vec<&str> = diff.fields.iter().map(|d| d.field.as_str()).collect();
+        assert_eq!(fields, vec!["Title ID", "Signature Issuer", "Title Key", "Common Key Index", "Title Version", "Ticket ID",
+                                "Console ID", "Content Access", "Title Limits"]);
+        assert!(diff.fields.iter().any(|d| d.field == "Content Access" && d.left == "[]" && d.right == "[1]"));
+        assert!(diff.fields.iter().any(|d| d.field == "Title Limits" && d.left == "None" && d.right == "Time (max 30)"));
+    }
+
+    #[test]
+    fn test_diff_bytes() {
+        assert!(diff_bytes(&[1, 2, 3], &[1, 2, 3]).is_empty());
+        assert_eq!(diff_bytes(&[1, 2, 3, 4, 5], &[1, 0, 0, 4, 0]), vec![1..3, 4..5]);
+        assert_eq!(diff_bytes(&[1, 2], &[1, 2, 3, 4]), vec![2..4]);
+        assert_eq!(diff_bytes(&[1, 2, 3], &[1, 0]), vec![1..3]);
+    }
+}
@@ -0,0 +1,191 @@
+// title/dol.rs from rustii (c) 2025 NinjaCheetah & Contributors
+// https://github.com/NinjaCheetah/rustii
+//
+// Implements parsing the header of DOL executables, which are used as the boot content of channels.
+
+use std::io::{Cursor, Read};
+use byteorder::{BigEndian, ReadBytesExt};
+use thiserror::Error;
+
+/// The size of a DOL header, which is also the offset of the earliest possible section data.
+pub const DOL_HEADER_SIZE: usize = 0x100;
+const NUM_TEXT_SECTIONS: usize = 7;
+const NUM_DATA_SECTIONS: usize = 11;
+// The ranges of memory that a DOL's sections can be loaded to, which are MEM1 and MEM2 through
+// their cached addresses.
+const MEMORY_RANGES: [(u32, u32); 2] = [(0x80000000, 0x81800000), (0x90000000, 0x94000000)];
+
+#[derive(Debug, Error)]
+pub enum DOLError {
+    #[error("DOL has no text sections")]
+    NoTextSections,
+    #[error("DOL section at offset {offset:#X} overlaps the header")]
+    SectionOverlapsHeader { offset: u32 },
+    #[error("DOL section is loaded to address {address:#010X}, which is outside of memory")]
+    InvalidLoadAddress { address: u32 },
+    #[error("DOL entry point {0:#010X} is not inside of a text section")]
+    InvalidEntryPoint(u32),
+    #[error("DOL data is not in a valid format")]
+    IO(#[from] std::io::Error),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// A section of a DOL executable, and where it's loaded to in memory.
+pub struct DolSection {
+    pub offset: u32,
+    pub address: u32,
+    pub size: u32,
+}
+
+impl DolSection {
+    // Gets whether an address falls inside of the memory that the section is loaded to.
+    fn contains(&self, address: u32) -> bool {
+        address >= self.address && (address as u64) < self.address as u64 + self.size as u64
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The header of a DOL executable. Only sections with a non-zero size are included.
+pub struct DolHeader {
+    pub text_sections: Vec<DolSection>,
+    pub data_sections: Vec<DolSection>,
+    pub bss_address: u32,
+    pub bss_size: u32,
+    pub entry_point: u32,
+}
+
+impl DolHeader {
+    /// Creates a new DolHeader instance from the start of a DOL executable. Only the first
+    /// DOL_HEADER_SIZE bytes are needed, and anything after them is ignored. The header is checked
+    /// to make sure that it actually describes a DOL, so this can be used to identify DOLs.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, DOLError> {
+        let mut buf = Cursor::new(data);
+        let mut read_fields = |count: usize| -> Result<Vec<u32>, DOLError> {
+            (0..count).map(|_| Ok(buf.read_u32::<BigEndian>()?)).collect()
+        };
+        let text_offsets = read_fields(NUM_TEXT_SECTIONS)?;
+        let data_offsets = read_fields(NUM_DATA_SECTIONS)?;
+        let text_addresses = read_fields(NUM_TEXT_SECTIONS)?;
+        let data_addresses = read_fields(NUM_DATA_SECTIONS)?;
+        let text_sizes = read_fields(NUM_TEXT_SECTIONS)?;
+        let data_sizes = read_fields(NUM_DATA_SECTIONS)?;
+        let bss_address = buf.read_u32::<BigEndian>()?;
+        let bss_size = buf.read_u32::<BigEndian>()?;
+        let entry_point = buf.read_u32::<BigEndian>()?;
+        let sections = |offsets: &[u32], addresses: &[u32], sizes: &[u32]| -> Vec<DolSection> {
+            offsets.iter().zip(addresses).zip(sizes)
+                .filter(|(_, size)| **size != 0)
+                .map(|((&offset, &address), &size)| DolSection { offset, address, size })
+                .collect()
+        };
+        let header = DolHeader {
+            text_sections: sections(&text_offsets, &text_addresses, &text_sizes),
+            data_sections: sections(&data_offsets, &data_addresses, &data_sizes),
+            bss_address,
+            bss_size,
+            entry_point,
+        };
+        header.validate()?;
+        Ok(header)
+    }
+
+    /// Creates a new DolHeader instance by reading only the header from the start of a DOL
+    /// executable, leaving the rest of the data unread.
+    pub fn from_reader<R: Read>(reader: &mut R) -> Result<Self, DOLError> {
+        let mut header = [0u8; DOL_HEADER_SIZE];
+        reader.read_exact(&mut header)?;
+        DolHeader::from_bytes(&header)
+    }
+
+    /// Gets the size of the DOL executable described by the header, which ends with whichever of
+    /// its sections is stored last.
+    pub fn file_size(&self) -> u64 {
+        self.sections().map(|section| section.offset as u64 + section.size as u64).max().unwrap_or(DOL_HEADER_SIZE as u64)
+    }
+
+    /// Iterates over all of the sections in the DOL executable, starting with the text sections.
+    pub fn sections(&self) -> impl Iterator<Item = &DolSection> {
+        self.text_sections.iter().chain(&self.data_sections)
+    }
+
+    // Checks that the header describes a DOL that could actually be loaded, since there's no
+    // magic number to go on.
+    fn validate(&self) -> Result<(), DOLError> {
+        if self.text_sections.is_empty() {
+            return Err(DOLError::NoTextSections);
+        }
+        for section in self.sections() {
+            if (section.offset as usize) < DOL_HEADER_SIZE {
+                return Err(DOLError::SectionOverlapsHeader { offset: section.offset });
+            }
+            let end = section.address as u64 + section.size as u64;
+            if !MEMORY_RANGES.iter().any(|(start, limit)| section.address >= *start && end <= *limit as u64) {
+                return Err(DOLError::InvalidLoadAddress { address: section.address });
+            }
+        }
+        if !self.text_sections.iter().any(|section| section.contains(self.entry_point)) {
+            return Err(DOLError::InvalidEntryPoint(self.entry_point));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Builds a DOL with one text section and one data section, with the entry point at the start
+    // of the text section.
+    fn build_dol() -> Vec<u8> {
+        let mut buf = vec![0u8; DOL_HEADER_SIZE];
+        let mut write_field = |offset: usize, value: u32| buf[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+        write_field(0x00, 0x100);
+        write_field(0x1C, 0x200);
+        write_field(0x48, 0x80003100);
+        write_field(0x64, 0x80004000);
+        write_field(0x90, 0x100);
+        write_field(0xAC, 0x80);
+        write_field(0xD8, 0x80005000);
+        write_field(0xDC, 0x400);
+        write_field(0xE0, 0x80003100);
+        buf.resize(0x280, 0x60);
+        buf
+    }
+
+    #[test]
+    fn test_parse_dol_header() {
+        let data = build_dol();
+        let header = DolHeader::from_bytes(&data).unwrap();
+        assert_eq!(header.text_sections, vec![DolSection { offset: 0x100, address: 0x80003100, size: 0x100 }]);
+        assert_eq!(header.data_sections, vec![DolSection { offset: 0x200, address: 0x80004000, size: 0x80 }]);
+        assert_eq!((header.bss_address, header.bss_size), (0x80005000, 0x400));
+        assert_eq!(header.entry_point, 0x80003100);
+        assert_eq!(header.file_size(), data.len() as u64);
+        // Reading from a stream only consumes the header.
+        let mut reader = Cursor::new(&data);
+        assert_eq!(DolHeader::from_reader(&mut reader).unwrap(), header);
+        assert_eq!(reader.position(), DOL_HEADER_SIZE as u64);
+    }
+
+    #[test]
+    fn test_reject_non_dol() {
+        assert!(matches!(DolHeader::from_bytes(&[0u8; DOL_HEADER_SIZE]), Err(DOLError::NoTextSections)));
+        assert!(matches!(DolHeader::from_bytes(&build_dol()[..0x80]), Err(DOLError::IO(_))));
+        let mut data = build_dol();
+        data[0xE0..0xE4].copy_from_slice(&0x80004000u32.to_be_bytes());
+        assert!(matches!(DolHeader::from_bytes(&data), Err(DOLError::InvalidEntryPoint(0x80004000))));
+        let mut data = build_dol();
+        data[0x64..0x68].copy_from_slice(&0x00004000u32.to_be_bytes());
+        assert!(matches!(DolHeader::from_bytes(&data), Err(DOLError::InvalidLoadAddress { address: 0x00004000 })));
+        let mut data = build_dol();
+        data[0x1C..0x20].copy_from_slice(&0x80u32.to_be_bytes());
+        assert!(matches!(DolHeader::from_bytes(&data), Err(DOLError::SectionOverlapsHeader { offset: 0x80 })));
+        // Other common boot contents, like an ELF or a U8 archive, aren't mistaken for DOLs.
+        let mut elf = vec![0u8; DOL_HEADER_SIZE];
+        elf[..4].copy_from_slice(b"\x7FELF");
+        assert!(DolHeader::from_bytes(&elf).is_err());
+        let mut u8_archive = vec![0u8; DOL_HEADER_SIZE];
+        u8_archive[..4].copy_from_slice(b"\x55\xAA\x38\x2D");
+        assert!(DolHeader::from_bytes(&u8_archive).is_err());
+    }
+}
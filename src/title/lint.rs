@@ -0,0 +1,256 @@
+// title/lint.rs from rustii (c) 2025 NinjaCheetah & Contributors
+// https://github.com/NinjaCheetah/rustii
+//
+// Implements structural sanity checks for digital Wii titles, which catch problems that would
+// make a title uninstallable regardless of how it's signed.
+
+use std::collections::HashSet;
+use std::fmt;
+use crate::title::{Title, TitleId};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+/// How serious a problem found by linting a title is.
+pub enum LintSeverity {
+    /// The title is unusual, but may still install and work correctly.
+    Warning,
+    /// The title will not install or will not work correctly.
+    Error,
+}
+
+impl fmt::Display for LintSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LintSeverity::Warning => write!(f, "Warning"),
+            LintSeverity::Error => write!(f, "Error"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A structural problem found by linting a title.
+pub enum TitleLintWarning {
+    /// The TMD's boot index doesn't match the index of any content record.
+    BootIndexNotFound(u16),
+    /// The Title IDs in the TMD and Ticket don't match.
+    TitleIdMismatch { tmd: TitleId, ticket: TitleId },
+    /// A content record has a size of zero.
+    EmptyContent { cid: u32, index: u16 },
+    /// The IOS required by the TMD isn't a valid IOS Title ID.
+    InvalidRequiredIos(TitleId),
+    /// The number of content records in the TMD doesn't match the number of contents.
+    NumContentsMismatch { records: usize, contents: usize },
+    /// The number of contents declared by the TMD's data doesn't match its content records.
+    DeclaredNumContentsMismatch { declared: u16, records: usize },
+    /// More than one content record uses the same Content ID.
+    DuplicateContentId(u32),
+    /// More than one content record uses the same index.
+    DuplicateIndex(u16),
+    /// The TMD's vWii flag doesn't match the common key index used by the Ticket.
+    VwiiKeyMismatch { is_vwii: bool, common_key_index: u8 },
+    /// The Ticket's common key index doesn't refer to a known common key.
+    InvalidCommonKeyIndex(u8),
+}
+
+impl TitleLintWarning {
+    /// Gets how serious the problem is.
+    pub fn severity(&self) -> LintSeverity {
+        match self {
+            TitleLintWarning::EmptyContent { .. } | TitleLintWarning::DeclaredNumContentsMismatch { .. } |
+                TitleLintWarning::InvalidCommonKeyIndex(_) => LintSeverity::Warning,
+            _ => LintSeverity::Error,
+        }
+    }
+}
+
+impl fmt::Display for TitleLintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TitleLintWarning::BootIndexNotFound(index) =>
+                write!(f, "boot index {} does not match any content record", index),
+            TitleLintWarning::TitleIdMismatch { tmd, ticket } =>
+                write!(f, "TMD Title ID {} does not match Ticket Title ID {}", tmd, ticket),
+            TitleLintWarning::EmptyContent { cid, index } =>
+                write!(f, "content {:08X} (index {}) has a size of 0 bytes", cid, index),
+            TitleLintWarning::InvalidRequiredIos(ios_tid) =>
+                write!(f, "required IOS {} is not a valid IOS Title ID", ios_tid),
+            TitleLintWarning::NumContentsMismatch { records, contents } =>
+                write!(f, "TMD has {} content records, but the title has {} contents", records, contents),
+            TitleLintWarning::DeclaredNumContentsMismatch { declared, records } =>
+                write!(f, "TMD declares {} contents, but only has {} content records", declared, records),
+            TitleLintWarning::DuplicateContentId(cid) =>
+                write!(f, "Content ID {:08X} is used by more than one content record", cid),
+            TitleLintWarning::DuplicateIndex(index) =>
+                write!(f, "index {} is used by more than one content record", index),
+            TitleLintWarning::VwiiKeyMismatch { is_vwii, common_key_index } => {
+                if *is_vwii {
+                    write!(f, "TMD is marked as vWii, but the Ticket uses common key index {} instead of the vWii key", common_key_index)
+                } else {
+                    write!(f, "Ticket uses the vWii common key, but the TMD is not marked as vWii")
+                }
+            }
+            TitleLintWarning::InvalidCommonKeyIndex(common_key_index) =>
+                write!(f, "Ticket uses unknown common key index {}, so the common key will be used instead", common_key_index),
+        }
+    }
+}
+
+// Gets whether the IOS required by a title is valid. Titles can only require IOS3-IOS255, but
+// system titles that don't run under an IOS (like IOSes themselves) leave the field empty.
+fn is_valid_required_ios(title: &Title) -> bool {
+    let ios_tid = title.tmd.ios_tid();
+    if ios_tid == [0; 8] {
+        return title.tmd.title_id().high() == [0x00, 0x00, 0x00, 0x01];
+    }
+    let ios_version = u32::from_be_bytes(ios_tid[4..8].try_into().unwrap());
+    ios_tid[0..4] == [0x00, 0x00, 0x00, 0x01] && (3..=255).contains(&ios_version)
+}
+
+/// Checks a title for structural problems that would prevent it from installing or working
+/// correctly, returning every problem found.
+pub fn lint_title(title: &Title) -> Vec<TitleLintWarning> {
+    let mut warnings: Vec<TitleLintWarning> = Vec::new();
+    let records = title.tmd.content_records.read();
+    if !records.iter().any(|record| record.index == title.tmd.boot_index) {
+        warnings.push(TitleLintWarning::BootIndexNotFound(title.tmd.boot_index));
+    }
+    if title.has_ticket() && title.tmd.title_id() != title.ticket.title_id() {
+        warnings.push(TitleLintWarning::TitleIdMismatch { tmd: title.tmd.title_id(), ticket: title.ticket.title_id() });
+    }
+    if !is_valid_required_ios(title) {
+        warnings.push(TitleLintWarning::InvalidRequiredIos(TitleId::new(title.tmd.ios_tid())));
+    }
+    if records.len() != title.content.contents.len() {
+        warnings.push(TitleLintWarning::NumContentsMismatch { records: records.len(), contents: title.content.contents.len() });
+    }
+    if let Some(declared) = title.tmd.declared_num_contents() {
+        warnings.push(TitleLintWarning::DeclaredNumContentsMismatch { declared, records: records.len() });
+    }
+    let mut seen_cids: HashSet<u32> = HashSet::new();
+    let mut seen_indices: HashSet<u16> = HashSet::new();
+    for record in records.iter() {
+        if record.content_size == 0 {
+            warnings.push(TitleLintWarning::EmptyContent { cid: record.content_id, index: record.index });
+        }
+        if !seen_cids.insert(record.content_id) {
+            warnings.push(TitleLintWarning::DuplicateContentId(record.content_id));
+        }
+        if !seen_indices.insert(record.index) {
+            warnings.push(TitleLintWarning::DuplicateIndex(record.index));
+        }
+    }
+    let uses_vwii_key = title.ticket.common_key_index() == 2;
+    if title.has_ticket() && title.tmd.is_vwii() != uses_vwii_key {
+        warnings.push(TitleLintWarning::VwiiKeyMismatch { is_vwii: title.tmd.is_vwii(), common_key_index: title.ticket.common_key_index() });
+    }
+    if title.has_ticket() && !title.ticket.has_valid_common_key_index() {
+        warnings.push(TitleLintWarning::InvalidCommonKeyIndex(title.ticket.common_key_index()));
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::title::{test_utils, ticket::Ticket, tmd, EncryptionTarget};
+
+    fn build_title() -> Title {
+        test_utils::build_title(&[vec![0x01; 16], vec![0x02; 16]])
+    }
+
+    #[test]
+    fn test_lint_clean_title() {
+        assert!(build_title().lint().is_empty());
+    }
+
+    #[test]
+    fn test_lint_boot_index() {
+        let mut title = build_title();
+        title.tmd.boot_index = 5;
+        assert_eq!(title.lint(), vec![TitleLintWarning::BootIndexNotFound(5)]);
+        assert_eq!(title.lint()[0].severity(), LintSeverity::Error);
+    }
+
+    #[test]
+    fn test_lint_title_id_mismatch() {
+        let mut title = build_title();
+        let other = TitleId::from_hex("0001000148414445").unwrap();
+        title.ticket.set_title_id(other).unwrap();
+        assert_eq!(title.lint(), vec![TitleLintWarning::TitleIdMismatch { tmd: title.tmd.title_id(), ticket: other }]);
+    }
+
+    #[test]
+    fn test_lint_empty_content() {
+        let mut title = build_title();
+        title.add_content(&[], Some(0x20), tmd::ContentType::Normal).unwrap();
+        let warnings = title.lint();
+        assert_eq!(warnings, vec![TitleLintWarning::EmptyContent { cid: 0x20, index: 2 }]);
+        assert_eq!(warnings[0].severity(), LintSeverity::Warning);
+    }
+
+    #[test]
+    fn test_lint_required_ios() {
+        let mut title = build_title();
+        // The required IOS can't be set to an invalid value through the TMD, so patch the raw data.
+        let mut tmd_data = title.tmd.to_bytes().unwrap();
+        tmd_data[0x184..0x18C].copy_from_slice(&[0x00, 0x01, 0x00, 0x01, 0x48, 0x41, 0x44, 0x45]);
+        title.set_tmd(tmd::TMD::from_bytes(&tmd_data).unwrap());
+        assert_eq!(title.lint(), vec![TitleLintWarning::InvalidRequiredIos(TitleId::from_hex("0001000148414445").unwrap())]);
+        tmd_data[0x184..0x18C].copy_from_slice(&[0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02]);
+        title.set_tmd(tmd::TMD::from_bytes(&tmd_data).unwrap());
+        assert_eq!(title.lint().len(), 1);
+    }
+
+    #[test]
+    fn test_lint_num_contents_mismatch() {
+        let mut title = build_title();
+        title.content.contents.pop();
+        assert_eq!(title.lint(), vec![TitleLintWarning::NumContentsMismatch { records: 2, contents: 1 }]);
+    }
+
+    #[test]
+    fn test_lint_declared_num_contents_mismatch() {
+        let mut title = build_title();
+        let mut tmd_data = title.tmd.to_bytes().unwrap();
+        tmd_data[0x1DE..0x1E0].copy_from_slice(&3u16.to_be_bytes());
+        title.set_tmd(tmd::TMD::from_bytes(&tmd_data).unwrap());
+        let warnings = title.lint();
+        assert_eq!(warnings, vec![TitleLintWarning::DeclaredNumContentsMismatch { declared: 3, records: 2 }]);
+        assert_eq!(warnings[0].severity(), LintSeverity::Warning);
+    }
+
+    #[test]
+    fn test_lint_duplicates() {
+        let title = build_title();
+        {
+            let mut records = title.tmd.content_records.write();
+            records[1].content_id = records[0].content_id;
+            records[1].index = records[0].index;
+        }
+        assert_eq!(title.lint(), vec![TitleLintWarning::DuplicateContentId(0x10), TitleLintWarning::DuplicateIndex(0)]);
+    }
+
+    #[test]
+    fn test_lint_vwii_key_mismatch() {
+        let mut title = build_title();
+        title.tmd.set_is_vwii(true);
+        assert_eq!(title.lint(), vec![TitleLintWarning::VwiiKeyMismatch { is_vwii: true, common_key_index: 0 }]);
+        // Converting properly sets both, which is consistent.
+        title.tmd.set_is_vwii(false);
+        title.convert_encryption(EncryptionTarget::Vwii, true).unwrap();
+        assert!(title.lint().is_empty());
+        title.tmd.set_is_vwii(false);
+        assert_eq!(title.lint(), vec![TitleLintWarning::VwiiKeyMismatch { is_vwii: false, common_key_index: 2 }]);
+    }
+
+    #[test]
+    fn test_lint_invalid_common_key_index() {
+        let mut title = build_title();
+        let ticket = title.ticket.to_bytes().unwrap();
+        title.ticket = Ticket::from_bytes(&test_utils::with_common_key_index(&ticket, 3)).unwrap();
+        let warnings = title.lint();
+        assert_eq!(warnings, vec![TitleLintWarning::InvalidCommonKeyIndex(3)]);
+        assert_eq!(warnings[0].severity(), LintSeverity::Warning);
+        title.ticket = Ticket::from_bytes(&test_utils::with_common_key_index(&ticket, 0xFF)).unwrap();
+        assert_eq!(title.lint(), vec![TitleLintWarning::InvalidCommonKeyIndex(0xFF)]);
+    }
+}
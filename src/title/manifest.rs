@@ -0,0 +1,277 @@
+// title/manifest.rs from rustii (c) 2025 NinjaCheetah & Contributors
+// https://github.com/NinjaCheetah/rustii
+//
+// Implements the manifest written alongside titles downloaded to a directory, which records what
+// was downloaded so that the title can be repacked or the download resumed later.
+
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use sha1::{Digest, Sha1};
+use thiserror::Error;
+use crate::title::{cert, content, crypto, ticket, tmd, Title, TitleId};
+
+/// The name of the manifest file written to a title's directory.
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+#[derive(Debug, Error)]
+pub enum ManifestError {
+    #[error("manifest field `{0}` is not valid")]
+    InvalidField(String),
+    #[error("the manifest is for title {manifest}, but the TMD is for title {tmd}")]
+    TitleIdMismatch { manifest: TitleId, tmd: TitleId },
+    #[error("the manifest does not list a file for content {0:08X}")]
+    MissingContent(u32),
+    #[error("the manifest does not list a Ticket, so the title cannot be packed")]
+    MissingTicket,
+    #[error("manifest data is not valid JSON")]
+    Json(#[from] serde_json::Error),
+    #[error("certificate processing error")]
+    Certificate(#[from] cert::CertificateError),
+    #[error("TMD processing error")]
+    TMD(#[from] tmd::TMDError),
+    #[error("Ticket processing error")]
+    Ticket(#[from] ticket::TicketError),
+    #[error("content processing error")]
+    Content(#[from] content::ContentError),
+    #[error("an error occurred while assembling a Title from the manifest")]
+    Title(#[from] crate::title::TitleError),
+    #[error("manifest data could not be read or written")]
+    IO(#[from] std::io::Error),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+/// A content recorded in a TitleManifest.
+pub struct ManifestContent {
+    pub content_id: u32,
+    pub index: u16,
+    /// The size of the decrypted content.
+    pub size: u64,
+    /// The SHA-1 hash of the decrypted content, as a hex string.
+    pub hash: String,
+    /// The name of the content's file in the title's directory.
+    pub file: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+/// A structure that represents the manifest written alongside a title downloaded to a directory.
+/// It records which title and version were downloaded, which files make up the title, and whether
+/// the contents were saved encrypted, so that the directory can be repacked without guessing.
+pub struct TitleManifest {
+    /// The Title ID of the title, as a hex string.
+    pub title_id: String,
+    pub title_version: u16,
+    /// Whether the content files are encrypted.
+    pub encrypted: bool,
+    /// The decrypted Title Key, as a hex string, if a Ticket was available.
+    pub title_key: Option<String>,
+    /// When the manifest was created, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// The name of the TMD's file in the title's directory.
+    pub tmd: String,
+    /// The name of the Ticket's file in the title's directory, if a Ticket was available.
+    pub ticket: Option<String>,
+    /// The name of the certificate chain's file in the title's directory.
+    pub cert_chain: String,
+    pub contents: Vec<ManifestContent>,
+}
+
+impl TitleManifest {
+    /// Creates a new TitleManifest describing a title with the provided TMD and optional Ticket.
+    /// Files are named the same way that the NUS names them, with decrypted contents having an
+    /// additional .app extension. Contents are recorded as encrypted when no Ticket is provided,
+    /// since they can't be decrypted.
+    pub fn new(tmd: &tmd::TMD, ticket: Option<&ticket::Ticket>) -> TitleManifest {
+        let tid = hex::encode(tmd.title_id());
+        let encrypted = ticket.is_none();
+        let contents = tmd.content_records.read().iter().map(|record| ManifestContent {
+            content_id: record.content_id,
+            index: record.index,
+            size: record.content_size,
+            hash: hex::encode(record.content_hash),
+            file: if encrypted { format!("{:08X}", record.content_id) } else { format!("{:08X}.app", record.content_id) },
+        }).collect();
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or(0);
+        TitleManifest {
+            title_id: tid.clone(),
+            title_version: tmd.title_version,
+            encrypted,
+            title_key: ticket.map(|ticket| hex::encode(ticket.dec_title_key())),
+            timestamp,
+            tmd: format!("{}.tmd", tid),
+            ticket: ticket.map(|_| format!("{}.tik", tid)),
+            cert_chain: format!("{}.cert", tid),
+            contents,
+        }
+    }
+
+    /// Creates a new TitleManifest instance from its JSON data.
+    pub fn from_json(data: &str) -> Result<TitleManifest, ManifestError> {
+        Ok(serde_json::from_str(data)?)
+    }
+
+    /// Dumps the TitleManifest to JSON data that can be written to a file.
+    pub fn to_json(&self) -> Result<String, ManifestError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Reads the manifest from a title's directory. Returns None if the directory doesn't contain
+    /// a manifest.
+    pub fn read(path: &Path) -> Result<Option<TitleManifest>, ManifestError> {
+        let manifest_path = path.join(MANIFEST_FILE_NAME);
+        if !manifest_path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(TitleManifest::from_json(&fs::read_to_string(manifest_path)?)?))
+    }
+
+    /// Writes the manifest to a title's directory.
+    pub fn write(&self, path: &Path) -> Result<(), ManifestError> {
+        fs::write(path.join(MANIFEST_FILE_NAME), self.to_json()?)?;
+        Ok(())
+    }
+
+    /// Gets the Title ID of the title described by the manifest.
+    pub fn title_id(&self) -> Result<TitleId, ManifestError> {
+        TitleId::from_hex(&self.title_id).map_err(|_| ManifestError::InvalidField(String::from("title_id")))
+    }
+
+    /// Gets the decrypted Title Key recorded in the manifest, if there is one.
+    pub fn title_key(&self) -> Result<Option<[u8; 16]>, ManifestError> {
+        self.title_key.as_ref()
+            .map(|key| hex::decode(key).ok()
+                .and_then(|key| <[u8; 16]>::try_from(key).ok())
+                .ok_or(ManifestError::InvalidField(String::from("title_key"))))
+            .transpose()
+    }
+
+    /// Gets whether a manifest describes the same download as another manifest, meaning that they
+    /// have the same title, version, encryption state, and contents. Files from a download
+    /// described by one can be reused by a download described by the other.
+    pub fn is_same_download(&self, other: &TitleManifest) -> bool {
+        self.title_id.eq_ignore_ascii_case(&other.title_id) && self.title_version == other.title_version
+            && self.encrypted == other.encrypted && self.contents == other.contents
+    }
+
+    /// Gets whether the file for a content listed in the manifest has already been written to the
+    /// title's directory and matches the manifest. Decrypted contents must match the recorded
+    /// hash. Encrypted contents must be the padded size of the content, and must also decrypt to
+    /// the recorded hash when the manifest includes a Title Key.
+    pub fn is_content_complete(&self, path: &Path, content: &ManifestContent) -> Result<bool, ManifestError> {
+        let content_path = path.join(&content.file);
+        if !content_path.exists() {
+            return Ok(false);
+        }
+        let data = fs::read(content_path)?;
+        if !self.encrypted {
+            return Ok(hex::encode(Sha1::digest(&data)) == content.hash.to_ascii_lowercase());
+        }
+        if data.len() as u64 != content::align_to(content.size, 16) {
+            return Ok(false);
+        }
+        match self.title_key()? {
+            Some(title_key) => {
+                let mut content_dec = crypto::decrypt_content(&data, title_key, content.index);
+                content_dec.truncate(content.size as usize);
+                Ok(hex::encode(Sha1::digest(&content_dec)) == content.hash.to_ascii_lowercase())
+            },
+            None => Ok(true),
+        }
+    }
+
+    /// Creates a new Title instance from the files listed in the manifest, which are read from the
+    /// title's directory. A certificate chain can be provided to use instead of the one listed in
+    /// the manifest.
+    pub fn load_title(&self, path: &Path, cert_chain: Option<cert::CertificateChain>) -> Result<Title, ManifestError> {
+        self.load_title_with_ticket(path, cert_chain, None)
+    }
+
+    /// Creates a new Title instance from the files listed in the manifest, like
+    /// TitleManifest::load_title(), but using the provided Ticket if the manifest doesn't list one.
+    /// This allows titles downloaded without a Ticket to be loaded using a Ticket from elsewhere.
+    pub fn load_title_with_ticket(&self, path: &Path, cert_chain: Option<cert::CertificateChain>, fallback_ticket: Option<ticket::Ticket>) -> Result<Title, ManifestError> {
+        let tmd = tmd::TMD::from_bytes(&fs::read(path.join(&self.tmd))?)?;
+        if tmd.title_id() != self.title_id()? {
+            return Err(ManifestError::TitleIdMismatch { manifest: self.title_id()?, tmd: tmd.title_id() });
+        }
+        let ticket = match (&self.ticket, fallback_ticket) {
+            (Some(ticket_file), _) => ticket::Ticket::from_bytes(&fs::read(path.join(ticket_file))?)?,
+            (None, Some(ticket)) => ticket,
+            (None, None) => return Err(ManifestError::MissingTicket),
+        };
+        let cert_chain = match cert_chain {
+            Some(cert_chain) => cert_chain,
+            None => cert::CertificateChain::from_bytes(&fs::read(path.join(&self.cert_chain))?)?,
+        };
+        let form = if self.encrypted { content::ContentForm::Encrypted } else { content::ContentForm::Decrypted };
+        let mut content_region = content::ContentRegion::new(tmd.content_records.clone())?;
+        let content_ids: Vec<u32> = tmd.content_records.read().iter().map(|record| record.content_id).collect();
+        for (i, cid) in content_ids.into_iter().enumerate() {
+            let content = self.contents.iter().find(|content| content.content_id == cid).ok_or(ManifestError::MissingContent(cid))?;
+            content_region.probe_content(&fs::read(path.join(&content.file))?, i, ticket.dec_title_key(), Some(form))?;
+        }
+        Ok(Title::from_parts(cert_chain, None, ticket, tmd, content_region, None)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::title::test_utils;
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let title = test_utils::build_title(&[vec![0x61; 100], vec![0x62; 300]]);
+        let manifest = TitleManifest::new(&title.tmd, Some(&title.ticket));
+        let reloaded = TitleManifest::from_json(&manifest.to_json().unwrap()).unwrap();
+        assert_eq!(reloaded, manifest);
+        assert_eq!(reloaded.title_id().unwrap(), title.tmd.title_id());
+        assert_eq!(reloaded.title_key().unwrap(), Some(test_utils::TEST_TITLE_KEY));
+        assert_eq!(reloaded.contents[1].file, "00000011.app");
+        assert!(TitleManifest::from_json("{}").is_err());
+    }
+
+    #[test]
+    fn test_manifest_load_title() {
+        let contents = vec![vec![0x63; 100], vec![0x64; 300]];
+        let title = test_utils::build_title(&contents);
+        let expected = title.to_wad_bytes().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = test_utils::write_nus_download(&title, dir.path(), false);
+        // Downloads normally have a Ticket when their contents are decrypted.
+        fs::write(dir.path().join(manifest.ticket.as_ref().unwrap()), title.ticket.to_bytes().unwrap()).unwrap();
+        let loaded = TitleManifest::read(dir.path()).unwrap().unwrap().load_title(dir.path(), None).unwrap();
+        assert_eq!(loaded.to_wad_bytes().unwrap(), expected);
+        // Encrypted downloads don't have a Ticket, and so can't be packed.
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = test_utils::write_nus_download(&title, dir.path(), true);
+        assert!(matches!(manifest.load_title(dir.path(), None), Err(ManifestError::MissingTicket)));
+    }
+
+    #[test]
+    fn test_manifest_resume() {
+        let contents = vec![vec![0x65; 100], vec![0x66; 300], vec![0x67; 50]];
+        let title = test_utils::build_title(&contents);
+        for encrypted in [false, true] {
+            let dir = tempfile::tempdir().unwrap();
+            let previous = test_utils::write_nus_download(&title, dir.path(), encrypted);
+            // Simulate an interrupted download by removing one content and truncating another.
+            fs::remove_file(dir.path().join(&previous.contents[1].file)).unwrap();
+            fs::write(dir.path().join(&previous.contents[2].file), [0x67; 16]).unwrap();
+            // Re-running the download should only need to fetch the contents that didn't finish.
+            let manifest = TitleManifest::new(&title.tmd, if encrypted { None } else { Some(&title.ticket) });
+            let previous = TitleManifest::read(dir.path()).unwrap().unwrap();
+            assert!(manifest.is_same_download(&previous));
+            let remaining: Vec<u32> = manifest.contents.iter()
+                .filter(|content| !manifest.is_content_complete(dir.path(), content).unwrap())
+                .map(|content| content.content_id)
+                .collect();
+            assert_eq!(remaining, [0x11, 0x12]);
+        }
+        // A manifest for a different version of the title describes a different download.
+        let manifest = TitleManifest::new(&title.tmd, None);
+        let mut other = manifest.clone();
+        other.title_version += 1;
+        assert!(!manifest.is_same_download(&other));
+    }
+}
@@ -0,0 +1,199 @@
+// title/meta.rs from rustii (c) 2025 NinjaCheetah & Contributors
+// https://github.com/NinjaCheetah/rustii
+//
+// Implements parsing for the meta/footer data stored at the end of a WAD file.
+
+use std::fmt;
+use crate::archive::imet;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The layouts that a meta/footer can be stored in.
+pub enum MetaLayout {
+    /// A copy of the banner's IMET header, starting at 0x40.
+    Imet,
+    /// A copy of the banner's IMET header, starting at 0x80 after a build tag describing when and
+    /// by whom the title was built.
+    TaggedImet,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The build information stored in the build tag of a meta/footer.
+pub struct BuildInfo {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    /// The user and host that built the title, formatted as USER@HOST.
+    pub builder: String,
+}
+
+impl BuildInfo {
+    // Parses the build tag at the start of a tagged meta/footer. The tag is 0x40 bytes long, and
+    // contains a timestamp formatted as "YYYY/MM/DD HH:MM:SS" followed by the builder at 0x20, each
+    // padded with null characters.
+    fn from_tag(tag: &[u8]) -> Option<BuildInfo> {
+        let read_string = |data: &[u8]| -> Option<String> {
+            let len = data.iter().position(|&c| c == 0).unwrap_or(data.len());
+            let text = std::str::from_utf8(&data[..len]).ok()?;
+            Some(text.trim().to_string())
+        };
+        let timestamp = BuildTimestamp::parse(&read_string(tag.get(0x00..0x20)?)?)?;
+        let builder = read_string(tag.get(0x20..0x40)?)?;
+        Some(BuildInfo {
+            year: timestamp.year,
+            month: timestamp.month,
+            day: timestamp.day,
+            hour: timestamp.hour,
+            minute: timestamp.minute,
+            second: timestamp.second,
+            builder,
+        })
+    }
+}
+
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02} {:02}:{:02}", self.year, self.month, self.day, self.hour, self.minute)?;
+        if !self.builder.is_empty() {
+            write!(f, " by {}", self.builder)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The timestamp stored in the build tag of a meta/footer, which records when a title was built.
+pub struct BuildTimestamp {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl BuildTimestamp {
+    /// Parses a timestamp in the format used by build tags, which is "YYYY/MM/DD HH:MM:SS".
+    /// Returns None if the timestamp isn't in that format or isn't a valid date and time.
+    pub fn parse(text: &str) -> Option<BuildTimestamp> {
+        let (date, time) = text.trim().split_once(' ')?;
+        let date: Vec<&str> = date.split('/').collect();
+        let time: Vec<&str> = time.split(':').collect();
+        if date.len() != 3 || time.len() != 3 {
+            return None;
+        }
+        let timestamp = BuildTimestamp {
+            year: date[0].parse().ok()?,
+            month: date[1].parse().ok()?,
+            day: date[2].parse().ok()?,
+            hour: time[0].parse().ok()?,
+            minute: time[1].parse().ok()?,
+            second: time[2].parse().ok()?,
+        };
+        if !(1..=12).contains(&timestamp.month) || !(1..=31).contains(&timestamp.day) || timestamp.hour > 23 ||
+            timestamp.minute > 59 || timestamp.second > 59 {
+            return None;
+        }
+        Some(timestamp)
+    }
+}
+
+impl fmt::Display for BuildTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}/{:02}/{:02} {:02}:{:02}:{:02}", self.year, self.month, self.day, self.hour, self.minute, self.second)
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A structure that represents the meta/footer data stored at the end of a WAD file, which is
+/// installed to the title's title.met file.
+pub struct Meta {
+    pub layout: MetaLayout,
+    /// The build information from the build tag, if the footer has one and it could be read.
+    pub build_info: Option<BuildInfo>,
+    pub imet_header: imet::IMETHeader,
+}
+
+impl Meta {
+    /// Creates a new Meta instance from the binary data of a meta/footer. Footers are written by
+    /// several different tools, so this returns None rather than an error for footers that are
+    /// empty or don't match a known layout.
+    pub fn from_bytes(data: &[u8]) -> Option<Meta> {
+        let layout = match imet::find_imet_offset(data)? {
+            0x40 => MetaLayout::Imet,
+            _ => MetaLayout::TaggedImet,
+        };
+        let imet_header = imet::IMETHeader::from_bytes(data).ok()?;
+        let build_info = match layout {
+            MetaLayout::Imet => None,
+            MetaLayout::TaggedImet => BuildInfo::from_tag(&data[..0x40]),
+        };
+        Some(Meta { layout, build_info, imet_header })
+    }
+}
+
+/// Replaces the timestamp in the build tag of a meta/footer, leaving the builder and the rest of the
+/// footer untouched. Returns None if the footer doesn't have a build tag.
+pub fn with_build_timestamp(data: &[u8], timestamp: &BuildTimestamp) -> Option<Vec<u8>> {
+    if Meta::from_bytes(data)?.layout != MetaLayout::TaggedImet {
+        return None;
+    }
+    let mut buf = data.to_vec();
+    let mut tag_timestamp = timestamp.to_string().into_bytes();
+    tag_timestamp.resize(0x20, 0);
+    buf[..0x20].copy_from_slice(&tag_timestamp);
+    Some(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::title::test_utils::{build_imet_footer, build_tagged_footer};
+
+    #[test]
+    fn test_imet_footer() {
+        let meta = Meta::from_bytes(&build_imet_footer()).unwrap();
+        assert_eq!(meta.layout, MetaLayout::Imet);
+        assert!(meta.build_info.is_none());
+        assert_eq!(meta.imet_header.channel_name(imet::Language::English), "Footer Channel");
+    }
+
+    #[test]
+    fn test_tagged_footer() {
+        let meta = Meta::from_bytes(&build_tagged_footer("2009/04/03 12:11:05", "BUILDER@HOST")).unwrap();
+        assert_eq!(meta.layout, MetaLayout::TaggedImet);
+        let build_info = meta.build_info.unwrap();
+        assert_eq!(build_info.second, 5);
+        assert_eq!(build_info.to_string(), "2009-04-03 12:11 by BUILDER@HOST");
+        assert_eq!(meta.imet_header.channel_name(imet::Language::Japanese), "Footer Channel");
+        // A build tag that can't be read shouldn't prevent the rest of the footer from being used.
+        let meta = Meta::from_bytes(&build_tagged_footer("not a timestamp", "BUILDER@HOST")).unwrap();
+        assert_eq!(meta.layout, MetaLayout::TaggedImet);
+        assert!(meta.build_info.is_none());
+    }
+
+    #[test]
+    fn test_with_build_timestamp() {
+        let timestamp = BuildTimestamp::parse("2011/12/25 08:30:00").unwrap();
+        assert_eq!(timestamp.to_string(), "2011/12/25 08:30:00");
+        let footer = with_build_timestamp(&build_tagged_footer("2009/04/03 12:11:05", "BUILDER@HOST"), &timestamp).unwrap();
+        assert_eq!(footer.len(), build_tagged_footer("", "").len());
+        let build_info = Meta::from_bytes(&footer).unwrap().build_info.unwrap();
+        assert_eq!(build_info.to_string(), "2011-12-25 08:30 by BUILDER@HOST");
+        // Footers without a build tag don't have a timestamp to replace.
+        assert!(with_build_timestamp(&build_imet_footer(), &timestamp).is_none());
+        assert!(with_build_timestamp(&[0x5A; 0x100], &timestamp).is_none());
+        assert!(BuildTimestamp::parse("2011/13/25 08:30:00").is_none());
+        assert!(BuildTimestamp::parse("2011-12-25 08:30:00").is_none());
+    }
+
+    #[test]
+    fn test_unrecognized_footer() {
+        assert!(Meta::from_bytes(&[]).is_none());
+        assert!(Meta::from_bytes(&[0x5A; 0x100]).is_none());
+        // Footers that are cut off partway through the IMET header can't be used either.
+        assert!(Meta::from_bytes(&build_imet_footer()[..0x100]).is_none());
+    }
+}
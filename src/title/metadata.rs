@@ -0,0 +1,350 @@
+// title/metadata.rs from rustii (c) 2025 NinjaCheetah & Contributors
+// https://github.com/NinjaCheetah/rustii
+//
+// Implements exporting the editable fields of a title's TMD and Ticket to a document, and applying
+// an edited document back to the title.
+
+use std::collections::HashSet;
+use thiserror::Error;
+use crate::title::{ticket, tmd, Title, TitleId};
+
+#[derive(Debug, Error)]
+pub enum MetadataError {
+    #[error("metadata field `{field}` has invalid value `{value}`")]
+    InvalidField { field: String, value: String },
+    #[error("metadata lists content index {0}, but no content record has that index")]
+    UnknownContent(u16),
+    #[error("metadata lists content index {0} more than once")]
+    DuplicateContent(u16),
+    #[error("metadata would give more than one content the Content ID {0:08X}")]
+    DuplicateContentId(u32),
+    #[error("metadata sets Ticket field `{0}`, but the title does not have a Ticket")]
+    MissingTicket(String),
+    #[error("metadata is not valid JSON")]
+    Json(#[from] serde_json::Error),
+    #[error("TMD processing error")]
+    TMD(#[from] tmd::TMDError),
+    #[error("Ticket processing error")]
+    Ticket(#[from] ticket::TicketError),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+/// The access rights that can be edited in a TitleMetadata document.
+pub struct AccessRightsMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ahb: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dvd_video: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+/// A title limit in a TitleMetadata document, using the raw limit type stored in the Ticket.
+pub struct LimitMetadata {
+    pub limit_type: u32,
+    pub limit_max: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+/// The editable fields of a single content record in a TitleMetadata document. The content is
+/// identified by its index, which can't be changed.
+pub struct ContentMetadata {
+    pub index: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_id: Option<u32>,
+    /// The type of the content, which can be "Normal", "Development", "HashTree", "DLC", or "Shared".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+/// A structure that represents the editable subset of a title's TMD and Ticket. Every field is
+/// optional, and fields that are left out of a document are left unchanged when it's applied.
+pub struct TitleMetadata {
+    /// The Title ID, as a hex string.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title_version: Option<u16>,
+    /// The Title ID of the required IOS, as a hex string.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ios_tid: Option<String>,
+    /// The region, which can be "JPN", "USA", "EUR", "KOR", or "None".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access_rights: Option<AccessRightsMetadata>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub common_key_index: Option<u8>,
+    /// The Ticket's title limits. When set, slots that aren't listed are cleared.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limits: Option<Vec<LimitMetadata>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contents: Option<Vec<ContentMetadata>>,
+}
+
+fn content_type_name(content_type: &tmd::ContentType) -> &'static str {
+    match content_type {
+        tmd::ContentType::Normal => "Normal",
+        tmd::ContentType::Development => "Development",
+        tmd::ContentType::HashTree => "HashTree",
+        tmd::ContentType::DLC => "DLC",
+        tmd::ContentType::Shared => "Shared",
+    }
+}
+
+fn parse_content_type(content_type: &str) -> Result<tmd::ContentType, MetadataError> {
+    match content_type.to_ascii_lowercase().as_str() {
+        "normal" => Ok(tmd::ContentType::Normal),
+        "development" => Ok(tmd::ContentType::Development),
+        "hashtree" => Ok(tmd::ContentType::HashTree),
+        "dlc" => Ok(tmd::ContentType::DLC),
+        "shared" => Ok(tmd::ContentType::Shared),
+        _ => Err(MetadataError::InvalidField { field: String::from("content_type"), value: content_type.to_owned() }),
+    }
+}
+
+fn parse_title_id(field: &str, title_id: &str) -> Result<TitleId, MetadataError> {
+    TitleId::from_hex(title_id).map_err(|_| MetadataError::InvalidField { field: field.to_owned(), value: title_id.to_owned() })
+}
+
+impl TitleMetadata {
+    /// Creates a new TitleMetadata document containing the current value of every editable field
+    /// of a Title. Ticket fields are left out for titles without a Ticket.
+    pub fn from_title(title: &Title) -> TitleMetadata {
+        let has_ticket = title.has_ticket();
+        TitleMetadata {
+            title_id: Some(title.tmd.title_id().to_string()),
+            title_version: Some(title.tmd.title_version),
+            ios_tid: Some(hex::encode_upper(title.tmd.ios_tid())),
+            region: Some(title.tmd.region().to_owned()),
+            access_rights: Some(AccessRightsMetadata {
+                ahb: Some(title.tmd.check_access_right(tmd::AccessRight::AHB)),
+                dvd_video: Some(title.tmd.check_access_right(tmd::AccessRight::DVDVideo)),
+            }),
+            group_id: Some(title.tmd.group_id),
+            common_key_index: has_ticket.then_some(title.ticket.common_key_index()),
+            limits: has_ticket.then(|| title.ticket.title_limits.iter()
+                .map(|limit| LimitMetadata { limit_type: limit.limit_type, limit_max: limit.limit_max })
+                .collect()),
+            contents: Some(title.tmd.content_records.read().iter().map(|record| ContentMetadata {
+                index: record.index,
+                content_id: Some(record.content_id),
+                content_type: Some(content_type_name(&record.content_type).to_owned()),
+            }).collect()),
+        }
+    }
+
+    /// Creates a new TitleMetadata instance from its JSON data. Unknown fields are rejected.
+    pub fn from_json(data: &str) -> Result<TitleMetadata, MetadataError> {
+        Ok(serde_json::from_str(data)?)
+    }
+
+    /// Dumps the TitleMetadata to JSON data that can be written to a file.
+    pub fn to_json(&self) -> Result<String, MetadataError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Applies every field set in the TitleMetadata to a Title. Every field is validated before
+    /// the Title is changed, so the Title is left untouched if an error is returned. The Title Key
+    /// is re-encrypted when the Title ID or common key index changes, so that the contents can
+    /// still be decrypted. The Title is not fakesigned.
+    pub fn apply(&self, title: &mut Title) -> Result<(), MetadataError> {
+        let mut new_tmd = title.tmd.clone();
+        let mut new_ticket = title.ticket.clone();
+        if !title.has_ticket() {
+            let ticket_fields = [("common_key_index", self.common_key_index.is_some()), ("limits", self.limits.is_some())];
+            if let Some((field, _)) = ticket_fields.iter().find(|(_, is_set)| *is_set) {
+                return Err(MetadataError::MissingTicket(field.to_string()));
+            }
+        }
+        if let Some(title_id) = &self.title_id {
+            let title_id = parse_title_id("title_id", title_id)?;
+            new_tmd.set_title_id(title_id)?;
+            new_ticket.set_title_id(title_id)?;
+        }
+        if let Some(title_version) = self.title_version {
+            new_tmd.title_version = title_version;
+            new_ticket.title_version = title_version;
+        }
+        if let Some(ios_tid) = &self.ios_tid {
+            new_tmd.set_ios_tid(parse_title_id("ios_tid", ios_tid)?.to_bytes())?;
+        }
+        if let Some(region) = &self.region {
+            new_tmd.set_region(region)?;
+        }
+        if let Some(access_rights) = &self.access_rights {
+            for (right, enabled) in [(tmd::AccessRight::AHB, access_rights.ahb), (tmd::AccessRight::DVDVideo, access_rights.dvd_video)] {
+                if let Some(enabled) = enabled {
+                    new_tmd.set_access_right(right, enabled);
+                }
+            }
+        }
+        if let Some(group_id) = self.group_id {
+            new_tmd.group_id = group_id;
+        }
+        if let Some(common_key_index) = self.common_key_index {
+            new_ticket.set_common_key_index(common_key_index)?;
+        }
+        if let Some(limits) = &self.limits {
+            if limits.len() > new_ticket.title_limits.len() {
+                return Err(MetadataError::InvalidField { field: String::from("limits"), value: format!("{} limits", limits.len()) });
+            }
+            new_ticket.clear_limits();
+            for (slot, limit) in limits.iter().enumerate() {
+                new_ticket.set_limit(slot, ticket::LimitType::from(limit.limit_type), limit.limit_max)?;
+            }
+        }
+        if let Some(contents) = &self.contents {
+            let mut records = new_tmd.content_records.write();
+            let mut seen_indices: HashSet<u16> = HashSet::new();
+            for content in contents {
+                if !seen_indices.insert(content.index) {
+                    return Err(MetadataError::DuplicateContent(content.index));
+                }
+                let record = records.iter_mut().find(|record| record.index == content.index)
+                    .ok_or(MetadataError::UnknownContent(content.index))?;
+                if let Some(content_id) = content.content_id {
+                    record.content_id = content_id;
+                }
+                if let Some(content_type) = &content.content_type {
+                    record.content_type = parse_content_type(content_type)?;
+                }
+            }
+            // Content IDs are checked once every edit has been made, so that two contents can
+            // swap their Content IDs.
+            let mut seen_cids: HashSet<u32> = HashSet::new();
+            if let Some(record) = records.iter().find(|record| !seen_cids.insert(record.content_id)) {
+                return Err(MetadataError::DuplicateContentId(record.content_id));
+            }
+        }
+        let has_ticket = title.has_ticket();
+        title.set_tmd(new_tmd);
+        if has_ticket {
+            title.set_ticket(new_ticket);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::title::test_utils;
+
+    fn build_title() -> Title {
+        test_utils::build_title(&[vec![0x01; 16], vec![0x02; 32]])
+    }
+
+    #[test]
+    fn test_dump_and_apply_unchanged() {
+        let mut title = build_title();
+        let original = title.to_wad_bytes().unwrap();
+        let metadata = TitleMetadata::from_title(&title);
+        assert_eq!(metadata.contents.as_ref().unwrap().len(), 2);
+        assert_eq!(metadata.limits.as_ref().unwrap().len(), 8);
+        let reloaded = TitleMetadata::from_json(&metadata.to_json().unwrap()).unwrap();
+        assert_eq!(reloaded, metadata);
+        reloaded.apply(&mut title).unwrap();
+        assert_eq!(title.to_wad_bytes().unwrap(), original);
+    }
+
+    #[test]
+    fn test_apply_partial_document() {
+        let mut title = build_title();
+        let old_title_key = title.ticket.dec_title_key();
+        let old_content = title.get_content_by_index(0).unwrap();
+        let metadata = TitleMetadata::from_json(r#"{
+            "title_id": "0001000148414445",
+            "region": "kor",
+            "access_rights": { "ahb": true },
+            "common_key_index": 1,
+            "contents": [{ "index": 1, "content_type": "Shared" }]
+        }"#).unwrap();
+        metadata.apply(&mut title).unwrap();
+        assert_eq!(title.tmd.title_id(), TitleId::from_hex("0001000148414445").unwrap());
+        assert_eq!(title.ticket.title_id(), title.tmd.title_id());
+        assert_eq!(title.tmd.region(), "KOR");
+        assert!(title.tmd.check_access_right(tmd::AccessRight::AHB));
+        assert!(!title.tmd.check_access_right(tmd::AccessRight::DVDVideo));
+        assert_eq!(title.ticket.common_key_index(), 1);
+        // The Title Key is re-encrypted, so the contents can still be decrypted.
+        assert_eq!(title.ticket.dec_title_key(), old_title_key);
+        assert_eq!(title.get_content_by_index(0).unwrap(), old_content);
+        let records = title.tmd.content_records.read();
+        assert_eq!(records[1].content_type, tmd::ContentType::Shared);
+        assert_eq!(records[0].content_type, tmd::ContentType::Normal);
+        assert_eq!(records[1].content_id, 0x11);
+        drop(records);
+        // Fields left out of the document aren't changed.
+        assert_eq!(title.tmd.title_version, build_title().tmd.title_version);
+        assert_eq!(title.content.content_records.read()[1].content_type, tmd::ContentType::Shared);
+    }
+
+    #[test]
+    fn test_apply_limits_and_versions() {
+        let mut title = build_title();
+        let metadata = TitleMetadata::from_json(r#"{ "title_version": 513, "limits": [{ "limit_type": 4, "limit_max": 10 }] }"#).unwrap();
+        metadata.apply(&mut title).unwrap();
+        assert_eq!(title.tmd.title_version, 513);
+        assert_eq!(title.ticket.title_version, 513);
+        let limits = title.ticket.limits();
+        assert_eq!(limits[0], (ticket::LimitType::LaunchCount, 10));
+        assert!(limits[1..].iter().all(|limit| limit.0 == ticket::LimitType::None));
+    }
+
+    #[test]
+    fn test_reject_invalid_documents() {
+        // Unknown fields are rejected by name, including in nested objects.
+        let err = TitleMetadata::from_json(r#"{ "title_versoin": 2 }"#).unwrap_err();
+        assert!(err.to_string().contains("not valid JSON"));
+        assert!(matches!(&err, MetadataError::Json(json_err) if json_err.to_string().contains("title_versoin")));
+        let err = TitleMetadata::from_json(r#"{ "contents": [{ "index": 0, "hash": "00" }] }"#).unwrap_err();
+        assert!(matches!(&err, MetadataError::Json(json_err) if json_err.to_string().contains("hash")));
+        // Invalid values are caught when applying, and leave the title unchanged.
+        let mut title = build_title();
+        let original = title.to_wad_bytes().unwrap();
+        let cases = [
+            (r#"{ "region": "AUS" }"#, "region"),
+            (r#"{ "title_id": "HADE", "group_id": 1 }"#, "title_id"),
+            (r#"{ "group_id": 1, "ios_tid": "0001000100000050" }"#, "ios_tid"),
+            (r#"{ "contents": [{ "index": 7 }] }"#, "unknown content"),
+            (r#"{ "contents": [{ "index": 0, "content_type": "Big" }] }"#, "content_type"),
+        ];
+        for (document, description) in cases {
+            let metadata = TitleMetadata::from_json(document).unwrap();
+            assert!(metadata.apply(&mut title).is_err(), "{} should be rejected", description);
+            assert_eq!(title.to_wad_bytes().unwrap(), original);
+        }
+        let metadata = TitleMetadata::from_json(r#"{ "common_key_index": 5 }"#).unwrap();
+        assert!(matches!(metadata.apply(&mut title), Err(MetadataError::Ticket(ticket::TicketError::InvalidCommonKeyIndex(5)))));
+    }
+
+    #[test]
+    fn test_conflicting_edits() {
+        let mut title = build_title();
+        // The same content can't be edited twice in one document.
+        let metadata = TitleMetadata::from_json(r#"{ "contents": [{ "index": 0, "content_id": 5 }, { "index": 0, "content_id": 6 }] }"#).unwrap();
+        assert!(matches!(metadata.apply(&mut title), Err(MetadataError::DuplicateContent(0))));
+        // Two contents can't end up with the same Content ID.
+        let metadata = TitleMetadata::from_json(r#"{ "contents": [{ "index": 0, "content_id": 17 }] }"#).unwrap();
+        assert!(matches!(metadata.apply(&mut title), Err(MetadataError::DuplicateContentId(17))));
+        assert_eq!(title.tmd.content_records.read()[0].content_id, 0x10);
+        // But they can swap Content IDs.
+        let metadata = TitleMetadata::from_json(r#"{ "contents": [{ "index": 0, "content_id": 17 }, { "index": 1, "content_id": 16 }] }"#).unwrap();
+        metadata.apply(&mut title).unwrap();
+        let cids: Vec<u32> = title.content.content_records.read().iter().map(|record| record.content_id).collect();
+        assert_eq!(cids, vec![0x11, 0x10]);
+        // Ticket fields can't be set on a title without a Ticket.
+        let mut title = build_title();
+        title.has_ticket = false;
+        let boot2 = TitleMetadata::from_json(r#"{ "limits": [] }"#).unwrap();
+        assert!(matches!(boot2.apply(&mut title), Err(MetadataError::MissingTicket(field)) if field == "limits"));
+        assert!(TitleMetadata::from_title(&title).common_key_index.is_none());
+    }
+}
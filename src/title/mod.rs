@@ -7,14 +7,36 @@ pub mod cert;
 pub mod commonkeys;
 pub mod content;
 pub mod crypto;
+pub mod diff;
+pub mod dol;
+pub mod lint;
+#[cfg(all(feature = "fs", feature = "serde"))]
+pub mod manifest;
+pub mod meta;
+#[cfg(feature = "serde")]
+pub mod metadata;
+#[cfg(feature = "nus")]
 pub mod nus;
+pub mod signed;
 pub mod ticket;
+pub mod title_id;
 pub mod tmd;
 pub mod versions;
 pub mod wad;
+#[cfg(test)]
+pub(crate) mod test_utils;
 
-use std::rc::Rc;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+#[cfg(feature = "fs")]
+use std::fs;
+#[cfg(feature = "fs")]
+use std::path::{Path, PathBuf};
 use thiserror::Error;
+use crate::archive::{imet, u8};
+pub use crate::title::signed::SignedFile;
+pub use crate::title::title_id::TitleId;
 
 #[derive(Debug, Error)]
 pub enum TitleError {
@@ -22,6 +44,16 @@ pub enum TitleError {
     InvalidData(String),
     #[error("WAD data is not in a valid format")]
     InvalidWAD,
+    #[error("no `{0}` file could be found in the source directory")]
+    MissingComponent(String),
+    #[error("more than one `{0}` file was found in the source directory")]
+    DuplicateComponent(String),
+    #[error("this title does not have a banner")]
+    NoBanner,
+    #[error("the boot index {0} does not match any content record")]
+    BootContentNotFound(u16),
+    #[error("this title already uses {0} encryption")]
+    AlreadyEncrypted(EncryptionTarget),
     #[error("certificate processing error")]
     CertificateError(#[from] cert::CertificateError),
     #[error("TMD processing error")]
@@ -32,16 +64,104 @@ pub enum TitleError {
     Content(#[from] content::ContentError),
     #[error("WAD processing error")]
     WAD(#[from] wad::WADError),
+    #[error("IMET processing error")]
+    IMET(#[from] imet::IMETError),
+    #[error("U8 processing error")]
+    U8(#[from] u8::U8Error),
+    #[cfg(feature = "fs")]
+    #[error("WAD file `{}` could not be read", path.display())]
+    ReadFile { path: PathBuf, source: std::io::Error },
     #[error("WAD data is not in a valid format")]
     IO(#[from] std::io::Error),
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+/// The naming conventions that can be used for content files in an unpacked title's directory.
+pub enum ContentNaming {
+    /// Contents are named after their index, e.g. 00000000.app.
+    #[default]
+    Index,
+    /// Contents are named after their Content ID, matching the NUS, e.g. 00000010.app.
+    ContentID,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Whether shared content should be counted when calculating the installed size of a title. Shared
+/// content is often already installed, so a title's real installed size is usually somewhere
+/// between the two.
+pub enum SharedContentAccounting {
+    Include,
+    Exclude,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The encryption that a title can use, which determines the common key used to encrypt its Title
+/// Key and the certificates that its TMD and Ticket are signed with.
+pub enum EncryptionTarget {
+    /// The retail common key, used by titles for retail consoles and Dolphin.
+    Retail,
+    /// The development common key, used by titles for development consoles.
+    Dev,
+    /// The vWii common key, used by titles installed from Wii U mode.
+    Vwii,
+    /// The Korean common key, used by titles for Korean consoles.
+    Korean,
+}
+
+impl fmt::Display for EncryptionTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EncryptionTarget::Retail => write!(f, "retail"),
+            EncryptionTarget::Dev => write!(f, "development"),
+            EncryptionTarget::Vwii => write!(f, "vWii"),
+            EncryptionTarget::Korean => write!(f, "Korean"),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+/// Options that control how a Title is packed into a WAD file.
+pub struct PackOptions {
+    /// Whether the WAD should only depend on the components of the title, so that packing the same
+    /// title twice always produces identical files. The build timestamp in a tagged meta/footer is
+    /// replaced with timestamp_override, or the meta/footer is left out if no timestamp is given.
+    pub deterministic: bool,
+    /// The timestamp to record in the build tag of the meta/footer when packing deterministically.
+    pub timestamp_override: Option<meta::BuildTimestamp>,
+}
+
 #[derive(Debug)]
 /// A structure that represents the components of a digital Wii title.
+///
+/// The methods that edit a Title return the Title again on success, so a series of edits can be
+/// chained together. If an edit fails, the Title is left as it was before that edit.
+///
+/// ```
+/// use rustii::prelude::*;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let data = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/test_title.wad"))?;
+/// let mut title = Title::from_bytes(&data)?;
+/// title.set_title_id("00010001-HADE".parse()?)?
+///     .set_title_version(2)?
+///     .set_required_ios(58)?
+///     .set_region("None")?
+///     .clear_limits()?
+///     .fakesign()?;
+/// let edited = Title::from_bytes(&title.to_wad_bytes()?)?;
+/// assert_eq!(edited.tmd.title_id().ascii_low().as_deref(), Some("HADE"));
+/// assert_eq!((edited.tmd.title_version, edited.tmd.ios_tid()[7]), (2, 58));
+/// assert!(edited.is_fakesigned());
+/// // The contents are untouched by editing the TMD and Ticket.
+/// assert_eq!(edited.get_content_by_index(0)?, Title::from_bytes(&data)?.get_content_by_index(0)?);
+/// # Ok(())
+/// # }
+/// ```
 pub struct Title {
     pub cert_chain: cert::CertificateChain,
     crl: Vec<u8>,
     pub ticket: ticket::Ticket,
+    has_ticket: bool,
     pub tmd: tmd::TMD,
     pub content: content::ContentRegion,
     meta: Vec<u8>
@@ -50,23 +170,43 @@ pub struct Title {
 impl Title {
     /// Creates a new Title instance from an existing WAD instance.
     pub fn from_wad(wad: &wad::WAD) -> Result<Title, TitleError> {
-        let cert_chain = cert::CertificateChain::from_bytes(&wad.cert_chain()).map_err(TitleError::CertificateError)?;
-        let ticket = ticket::Ticket::from_bytes(&wad.ticket()).map_err(TitleError::Ticket)?;
-        let tmd = tmd::TMD::from_bytes(&wad.tmd()).map_err(TitleError::TMD)?;
-        let content = content::ContentRegion::from_bytes(&wad.content(), Rc::clone(&tmd.content_records)).map_err(TitleError::Content)?;
+        let cert_chain = wad.parsed_cert_chain()?;
+        let tmd = wad.parsed_tmd()?;
+        // boot2 WADs sometimes don't include a Ticket, so a blank one is used in its place to allow
+        // the rest of the title to be loaded. Its contents can't be decrypted without the real one.
+        let has_ticket = !(matches!(wad.header.wad_type, wad::WADType::ImportBoot) && wad.ticket().is_empty());
+        let ticket = if has_ticket {
+            wad.parsed_ticket()?
+        } else {
+            ticket::Ticket::blank(tmd.title_id())
+        };
+        // DLC WADs often only contain the contents that the Ticket permits access to, so if the
+        // content area is too short to hold every content, fall back on only loading those.
+        let present: Vec<bool> = tmd.content_records.read().iter().map(|record| ticket.is_content_permitted(record.index)).collect();
+        let content = match content::ContentRegion::from_bytes(wad.content(), tmd.content_records.clone()) {
+            Err(content::ContentError::TruncatedContent { .. }) if present.contains(&false) => {
+                content::ContentRegion::from_bytes_with_presence(wad.content(), tmd.content_records.clone(), &present)?
+            },
+            result => result?,
+        };
         Ok(Title {
             cert_chain,
-            crl: wad.crl(),
+            crl: wad.crl().to_vec(),
             ticket,
+            has_ticket,
             tmd,
             content,
-            meta: wad.meta(),
+            meta: wad.meta().to_vec(),
         })
     }
     
-    /// Creates a new Title instance from all of its individual components.
-    pub fn from_parts(cert_chain: cert::CertificateChain, crl: Option<&[u8]>, ticket: ticket::Ticket, tmd: tmd::TMD,
+    /// Creates a new Title instance from all of its individual components. The TMD will be linked to
+    /// the content records of the ContentRegion if they aren't already shared.
+    pub fn from_parts(cert_chain: cert::CertificateChain, crl: Option<&[u8]>, ticket: ticket::Ticket, mut tmd: tmd::TMD,
                       content: content::ContentRegion, meta: Option<&[u8]>) -> Result<Title, TitleError> {
+        if !tmd.content_records.ptr_eq(&content.content_records) {
+            tmd.content_records = content.content_records.clone();
+        }
         // Create empty vecs for the CRL and meta areas if we weren't supplied with any, as they're
         // optional components.
         let crl = match crl {
@@ -81,6 +221,7 @@ impl Title {
             cert_chain,
             crl,
             ticket,
+            has_ticket: true,
             tmd,
             content,
             meta
@@ -88,19 +229,82 @@ impl Title {
     }
     
     /// Converts a Title instance into a WAD, which can be used to export the Title back to a file.
+    /// Titles without a Ticket will have an empty Ticket section.
     pub fn to_wad(&self) -> Result<wad::WAD, TitleError> {
+        self.to_wad_with_options(&PackOptions::default())
+    }
+
+    /// Converts a Title instance into a WAD, like Title::to_wad(), using the provided packing
+    /// options.
+    pub fn to_wad_with_options(&self, options: &PackOptions) -> Result<wad::WAD, TitleError> {
         // Create a new WAD from the data in the Title.
-        let wad = wad::WAD::from_parts(
+        let mut wad = wad::WAD::from_parts(
             &self.cert_chain,
             &self.crl,
             &self.ticket,
             &self.tmd,
             &self.content,
-            &self.meta
+            &self.packed_meta(options)
         ).map_err(TitleError::WAD)?;
+        if !self.has_ticket {
+            wad.set_ticket(&[]);
+        }
         Ok(wad)
     }
+
+    /// Writes a Title instance to the provided writer as a WAD file. This produces the same data as
+    /// Title::to_wad() followed by WAD::write_to(), but the contents are written straight from the
+    /// Title rather than first being copied into a WAD.
+    pub fn write_wad<W: Write>(&self, writer: &mut W) -> Result<(), TitleError> {
+        self.write_wad_with_options(writer, &PackOptions::default())
+    }
+
+    /// Writes a Title instance to the provided writer as a WAD file, like Title::write_wad(), using
+    /// the provided packing options.
+    pub fn write_wad_with_options<W: Write>(&self, writer: &mut W, options: &PackOptions) -> Result<(), TitleError> {
+        let mut wad = wad::WAD::from_parts_with_content(&self.cert_chain, &self.crl, &self.ticket, &self.tmd, Vec::new(), &self.packed_meta(options))?;
+        if !self.has_ticket {
+            wad.set_ticket(&[]);
+        }
+        wad.write_with_content_region(writer, &self.content)?;
+        Ok(())
+    }
+
+    /// Converts a Title instance into the binary data of a WAD file, like Title::to_wad() followed
+    /// by WAD::to_bytes(), but without building an intermediate copy of the contents.
+    pub fn to_wad_bytes(&self) -> Result<Vec<u8>, TitleError> {
+        let mut buf: Vec<u8> = Vec::new();
+        self.write_wad(&mut buf)?;
+        Ok(buf)
+    }
+
+    // Gets the meta/footer that should be written to a WAD packed with the provided options. Only
+    // tagged footers record when they were built, so other footers are always kept as-is.
+    fn packed_meta(&self, options: &PackOptions) -> Vec<u8> {
+        let is_tagged = meta::Meta::from_bytes(&self.meta).is_some_and(|meta| meta.layout == meta::MetaLayout::TaggedImet);
+        if !options.deterministic || !is_tagged {
+            return self.meta.clone();
+        }
+        match options.timestamp_override {
+            Some(timestamp) => meta::with_build_timestamp(&self.meta, &timestamp).unwrap_or_default(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Gets whether the Title has a real Ticket. This is only false for boot2 titles that were
+    /// loaded without one, in which case the Ticket is a blank stand-in.
+    pub fn has_ticket(&self) -> bool {
+        self.has_ticket
+    }
     
+    #[cfg(feature = "fs")]
+    /// Creates a new Title instance from a WAD file, reading the file from the provided path.
+    pub fn open(path: impl AsRef<Path>) -> Result<Title, TitleError> {
+        let path = path.as_ref();
+        let data = fs::read(path).map_err(|source| TitleError::ReadFile { path: path.to_path_buf(), source })?;
+        Title::from_bytes(&data)
+    }
+
     /// Creates a new Title instance from the binary data of a WAD file.
     pub fn from_bytes(bytes: &[u8]) -> Result<Title, TitleError> {
         let wad = wad::WAD::from_bytes(bytes).map_err(|_| TitleError::InvalidWAD)?;
@@ -108,67 +312,434 @@ impl Title {
         Ok(title)
     }
     
+    #[cfg(feature = "fs")]
+    /// Creates a new Title instance from a directory containing its unpacked components. The
+    /// directory must contain exactly one TMD (.tmd), Ticket (.tik), and certificate chain (.cert)
+    /// file, and may optionally contain one meta/footer (.footer) file. boot2 titles may omit the
+    /// Ticket. Contents are read from
+    /// files named after their index (e.g. 00000000.app), falling back on files named after their
+    /// Content ID, and may be either decrypted or encrypted, which is detected by checking the hash
+    /// of the content against its record.
+    pub fn from_directory(path: &Path) -> Result<Title, TitleError> {
+        Title::from_directory_with_cert_chain(path, None)
+    }
+
+    #[cfg(feature = "fs")]
+    /// Creates a new Title instance from a directory containing its unpacked components, like
+    /// Title::from_directory(), but using the provided certificate chain instead of one from the
+    /// directory when one is specified. The directory does not need to contain a certificate chain
+    /// (.cert) file in that case, and any that it does contain will be ignored.
+    pub fn from_directory_with_cert_chain(path: &Path, cert_chain: Option<cert::CertificateChain>) -> Result<Title, TitleError> {
+        Title::from_directory_with_options(path, cert_chain, false, None)
+    }
+
+    #[cfg(feature = "fs")]
+    /// Creates a new Title instance from a directory containing its unpacked components, like
+    /// Title::from_directory_with_cert_chain(), but optionally allowing content files to be
+    /// missing. Missing contents are left as not present rather than causing an error, which is
+    /// needed for DLC titles that don't include every content listed in their TMD. Each content
+    /// file is checked to see whether it's decrypted or encrypted unless a form is specified, in
+    /// which case every content file must be in that form.
+    pub fn from_directory_with_options(path: &Path, cert_chain: Option<cert::CertificateChain>, allow_missing: bool, content_form: Option<content::ContentForm>) -> Result<Title, TitleError> {
+        Title::load_directory(path, cert_chain, None, allow_missing, content_form)
+    }
+
+    #[cfg(feature = "fs")]
+    // Loads a Title from a directory, as described by Title::from_directory_with_options(). If the
+    // directory doesn't contain a Ticket, the fallback Ticket is used instead when one is provided.
+    pub(crate) fn load_directory(path: &Path, cert_chain: Option<cert::CertificateChain>, fallback_ticket: Option<ticket::Ticket>,
+                                 allow_missing: bool, content_form: Option<content::ContentForm>) -> Result<Title, TitleError> {
+        let tmd = tmd::TMD::from_bytes(&fs::read(find_component(path, "tmd", true)?.unwrap())?)?;
+        let is_boot2 = tmd.title_id() == TitleId::BOOT2;
+        let (ticket, has_ticket) = match (find_component(path, "tik", !is_boot2 && fallback_ticket.is_none())?, fallback_ticket) {
+            (Some(ticket_path), _) => (ticket::Ticket::from_bytes(&fs::read(ticket_path)?)?, true),
+            (None, Some(ticket)) => (ticket, true),
+            (None, None) => (ticket::Ticket::blank(tmd.title_id()), false),
+        };
+        let cert_chain = match cert_chain {
+            Some(cert_chain) => cert_chain,
+            None => cert::CertificateChain::from_bytes(&fs::read(find_component(path, "cert", true)?.unwrap())?)?,
+        };
+        let meta = match find_component(path, "footer", false)? {
+            Some(meta_path) => fs::read(meta_path)?,
+            None => Vec::new(),
+        };
+        let title_key = ticket.dec_title_key();
+        let mut content_region = content::ContentRegion::new(tmd.content_records.clone())?;
+        for (i, record) in content_region.iter_by_position() {
+            let (index, content_id) = (record.index, record.content_id);
+            // Content files may be named after either their index or their Content ID. Since one
+            // content's Content ID can match another's index, the content is matched by hash
+            // before falling back on whichever file exists.
+            let candidates: Vec<PathBuf> = [format!("{:08X}.app", index), format!("{:08X}.app", content_id)].iter()
+                .map(|name| path.join(name))
+                .filter(|candidate| candidate.exists())
+                .collect();
+            if candidates.is_empty() {
+                if allow_missing {
+                    continue;
+                }
+                return Err(TitleError::MissingComponent(format!("{:08X}.app", index)));
+            }
+            // Without a Ticket there's no way to check the content, so it's assumed to be encrypted,
+            // since that's how it's written out.
+            if !has_ticket {
+                content_region.load_enc_content(&fs::read(&candidates[0])?, i)?;
+                continue;
+            }
+            // Content that doesn't match its record in either form is reported using the first
+            // candidate, since that's the file named after the content's index.
+            let mut first_error: Option<content::ContentError> = None;
+            for candidate in &candidates {
+                match content_region.probe_content(&fs::read(candidate)?, i, title_key, content_form) {
+                    Ok(_) => {
+                        first_error = None;
+                        break;
+                    },
+                    Err(err) => {
+                        first_error.get_or_insert(err);
+                    }
+                }
+            }
+            if let Some(err) = first_error {
+                return Err(err.into());
+            }
+        }
+        let mut title = Title::from_parts(cert_chain, None, ticket, tmd, content_region, Some(&meta))?;
+        title.has_ticket = has_ticket;
+        Ok(title)
+    }
+
+    #[cfg(feature = "fs")]
+    /// Writes the components of a Title out to a directory, which will be created if it doesn't
+    /// already exist. Components are named after the Title ID, and contents are decrypted and
+    /// named after their index, matching the layout read by Title::from_directory(). Contents that
+    /// aren't present are skipped.
+    pub fn to_directory(&self, path: &Path) -> Result<(), TitleError> {
+        self.to_directory_with_options(path, ContentNaming::Index, false)
+    }
+
+    #[cfg(feature = "fs")]
+    /// Writes the components of a Title out to a directory, like Title::to_directory(), but with
+    /// contents named using the specified convention. Contents can optionally be written without
+    /// being decrypted, in which case their hashes are not checked. Contents are always written
+    /// encrypted for titles without a Ticket, since they can't be decrypted.
+    pub fn to_directory_with_options(&self, path: &Path, naming: ContentNaming, encrypted: bool) -> Result<(), TitleError> {
+        let encrypted = encrypted || !self.has_ticket;
+        fs::create_dir_all(path)?;
+        let tid = hex::encode(self.tmd.title_id());
+        fs::write(path.join(format!("{}.tmd", tid)), self.tmd.to_bytes()?)?;
+        if self.has_ticket {
+            fs::write(path.join(format!("{}.tik", tid)), self.ticket.to_bytes()?)?;
+        }
+        fs::write(path.join(format!("{}.cert", tid)), self.cert_chain.to_bytes()?)?;
+        fs::write(path.join(format!("{}.footer", tid)), &self.meta)?;
+        for (i, record) in self.iter_by_position().filter(|(i, _)| self.content.is_content_present(*i)) {
+            let content_file_name = match naming {
+                ContentNaming::Index => format!("{:08X}.app", record.index),
+                ContentNaming::ContentID => format!("{:08X}.app", record.content_id),
+            };
+            let content = if encrypted {
+                self.content.get_enc_content_by_index(i)?
+            } else {
+                self.get_content_by_index(i)?
+            };
+            fs::write(path.join(content_file_name), content)?;
+        }
+        Ok(())
+    }
+
     /// Gets whether the TMD and Ticket of a Title are both fakesigned.
     pub fn is_fakesigned(&self) -> bool {
         self.tmd.is_fakesigned() && self.ticket.is_fakesigned()
     }
     
     /// Fakesigns the TMD and Ticket of a Title.
-    pub fn fakesign(&mut self) -> Result<(), TitleError> {
-        // Run the fakesign methods on the TMD and Ticket.
-        self.tmd.fakesign().map_err(TitleError::TMD)?;
-        self.ticket.fakesign().map_err(TitleError::Ticket)?;
-        Ok(())
+    pub fn fakesign(&mut self) -> Result<&mut Self, TitleError> {
+        self.edit_signed_parts(|tmd, ticket| {
+            tmd.fakesign()?;
+            ticket.fakesign()?;
+            Ok(())
+        })
+    }
+
+    // Applies an edit to copies of the TMD and Ticket, and only keeps the changes if the whole edit
+    // succeeds, so that a failed edit can't leave the two out of sync with each other. The copy of
+    // the TMD gets its own content records, so they're shared with the content region again once
+    // the edit is kept.
+    fn edit_signed_parts(&mut self, edit: impl FnOnce(&mut tmd::TMD, &mut ticket::Ticket) -> Result<(), TitleError>) -> Result<&mut Self, TitleError> {
+        let mut tmd = self.tmd.clone();
+        let mut ticket = self.ticket.clone();
+        edit(&mut tmd, &mut ticket)?;
+        tmd.content_records = self.content.content_records.clone();
+        self.tmd = tmd;
+        self.ticket = ticket;
+        Ok(self)
     }
     
+    /// Gets the encryption that the Title currently uses, based on its Ticket and TMD.
+    pub fn encryption(&self) -> EncryptionTarget {
+        if self.ticket.is_dev() {
+            EncryptionTarget::Dev
+        } else if self.tmd.is_vwii() {
+            EncryptionTarget::Vwii
+        } else if self.ticket.common_key_index() == 1 {
+            EncryptionTarget::Korean
+        } else {
+            EncryptionTarget::Retail
+        }
+    }
+
+    /// Converts the Title to use a different encryption. This re-encrypts the Title Key with the
+    /// target's common key and updates the signature issuers of the TMD and Ticket to match, which
+    /// invalidates their signatures, so the Title is fakesigned afterward if requested. Otherwise,
+    /// the original signature bytes are left untouched. The contents don't need to be re-encrypted,
+    /// since the decrypted Title Key is unchanged. Returns an error if the Title already uses the
+    /// target encryption, in which case the Title is left unchanged.
+    pub fn convert_encryption(&mut self, target: EncryptionTarget, fakesign: bool) -> Result<&mut Self, TitleError> {
+        if self.encryption() == target {
+            return Err(TitleError::AlreadyEncrypted(target));
+        }
+        let (tmd_issuer, ticket_issuer, is_dev) = match target {
+            EncryptionTarget::Dev => ("Root-CA00000002-CP00000007", "Root-CA00000002-XS00000006", true),
+            _ => ("Root-CA00000001-CP00000004", "Root-CA00000001-XS00000003", false),
+        };
+        let common_key_index = match target {
+            EncryptionTarget::Retail | EncryptionTarget::Dev => 0,
+            EncryptionTarget::Korean => 1,
+            EncryptionTarget::Vwii => 2,
+        };
+        self.edit_signed_parts(|tmd, ticket| {
+            ticket.change_common_key(common_key_index, is_dev)?;
+            tmd.set_signature_issuer(String::from(tmd_issuer))?;
+            ticket.set_signature_issuer(String::from(ticket_issuer))?;
+            tmd.set_is_vwii(target == EncryptionTarget::Vwii);
+            if fakesign {
+                tmd.fakesign()?;
+                ticket.fakesign()?;
+            }
+            Ok(())
+        })
+    }
+    
+    /// Sets a new decrypted Title Key for the Title. Every present content is decrypted with the
+    /// current Title Key and re-encrypted with the new one, so the Title stays valid, and the Title
+    /// is fakesigned afterward. Nothing is changed unless every content can be re-encrypted.
+    pub fn set_title_key(&mut self, title_key: [u8; 16]) -> Result<&mut Self, TitleError> {
+        let old_key = self.ticket.dec_title_key();
+        let mut reencrypted: Vec<Option<Vec<u8>>> = Vec::new();
+        for (position, record) in self.content.content_records.read().iter().enumerate() {
+            if !self.content.is_content_present(position) {
+                reencrypted.push(None);
+                continue;
+            }
+            let content = self.content.get_content_by_index(position, old_key)?;
+            reencrypted.push(Some(crypto::encrypt_content(&content, title_key, record.index, record.content_size)));
+        }
+        for (position, content) in reencrypted.into_iter().enumerate() {
+            if let Some(content) = content {
+                self.content.load_enc_content(&content, position)?;
+            }
+        }
+        self.ticket.set_dec_title_key(title_key);
+        self.fakesign()
+    }
+
     /// Gets the decrypted content file from the Title at the specified index.
     pub fn get_content_by_index(&self, index: usize) -> Result<Vec<u8>, content::ContentError> {
         let content = self.content.get_content_by_index(index, self.ticket.dec_title_key())?;
         Ok(content)
     }
     
+    /// Opens the content at the specified index for reading, decrypting it and verifying its hash
+    /// as it's read rather than decrypting the whole content up front.
+    pub fn open_content_by_index(&self, index: usize) -> Result<content::ContentReader<'_>, content::ContentError> {
+        self.content.open_content_by_index(index, self.ticket.dec_title_key())
+    }
+
     /// Gets the decrypted content file from the Title with the specified Content ID.
     pub fn get_content_by_cid(&self, cid: u32) -> Result<Vec<u8>, content::ContentError> {
         let content = self.content.get_content_by_cid(cid, self.ticket.dec_title_key())?;
         Ok(content)
     }
 
+    /// Gets the position of content in the content list using the index stored in its record.
+    pub fn get_position_for_index(&self, index: u16) -> Result<usize, content::ContentError> {
+        self.content.get_position_for_index(index)
+    }
+
+    /// Iterates over the Title's content records in the order they're stored, yielding each record
+    /// alongside its position in the content list. See ContentRegion::iter_by_position().
+    pub fn iter_by_position(&self) -> impl Iterator<Item = (usize, tmd::ContentRecord)> + use<> {
+        self.content.iter_by_position()
+    }
+
+    /// Iterates over the Title's content records sorted by record index, yielding each record
+    /// alongside its position in the content list. See ContentRegion::iter_by_record_index().
+    pub fn iter_by_record_index(&self) -> impl Iterator<Item = (usize, tmd::ContentRecord)> + use<> {
+        self.content.iter_by_record_index()
+    }
+
+    /// Gets the position of the boot content in the content list by looking up the TMD's boot index
+    /// in the content records. The boot index refers to a record's index, not its position, so the
+    /// two may differ when records are stored out of order.
+    pub fn get_boot_content_index(&self) -> Result<usize, TitleError> {
+        let boot_index = self.tmd.boot_index;
+        self.get_position_for_index(boot_index).map_err(|_| TitleError::BootContentNotFound(boot_index))
+    }
+
+    /// Gets the decrypted boot content of the Title, which is the content whose record index
+    /// matches the boot index set in the TMD.
+    pub fn get_boot_content(&self) -> Result<Vec<u8>, TitleError> {
+        let position = self.get_boot_content_index()?;
+        let content = self.get_content_by_index(position)?;
+        Ok(content)
+    }
+
+    /// Opens the boot content of the Title for reading, like Title::open_content_by_index(), so
+    /// that its header can be checked without decrypting the whole content.
+    pub fn open_boot_content(&self) -> Result<content::ContentReader<'_>, TitleError> {
+        let position = self.get_boot_content_index()?;
+        Ok(self.open_content_by_index(position)?)
+    }
+
     /// Sets the content at the specified index to the provided decrypted content. This content will
     /// have its size and hash saved into the matching record. Optionally, a new Content ID or
     /// content type can be provided, with the existing values being preserved by default.
-    pub fn set_content(&mut self, content: &[u8], index: usize, cid: Option<u32>, content_type: Option<tmd::ContentType>) -> Result<(), TitleError> {
+    pub fn set_content(&mut self, content: &[u8], index: usize, cid: Option<u32>, content_type: Option<tmd::ContentType>) -> Result<&mut Self, TitleError> {
         self.content.set_content(content, index, cid, content_type, self.ticket.dec_title_key())?;
-        Ok(())
+        Ok(self)
     }
 
     /// Adds new decrypted content to the end of the content list and content records. The provided
-    /// Content ID and type will be added to the record alongside a hash of the decrypted data. An
-    /// index will be automatically assigned based on the highest index currently recorded in the
-    /// content records.
-    pub fn add_content(&mut self, content: &[u8], cid: u32, content_type: tmd::ContentType) -> Result<(), TitleError> {
+    /// Content ID and type will be added to the record alongside a hash of the decrypted data. If
+    /// no Content ID is provided, an unused one will be assigned with
+    /// ContentRegion::next_available_cid(). An index will be automatically assigned based on the
+    /// highest index currently recorded in the content records. Returns the Content ID that was
+    /// used.
+    pub fn add_content(&mut self, content: &[u8], cid: Option<u32>, content_type: tmd::ContentType) -> Result<u32, TitleError> {
+        let cid = cid.unwrap_or_else(|| self.content.next_available_cid());
         self.content.add_content(content, cid, content_type, self.ticket.dec_title_key())?;
-        Ok(())
+        Ok(cid)
     }
-    
-    /// Gets the installed size of the title, in bytes. Use the optional parameter "absolute" to set
-    /// whether shared content should be included in this total or not.
-    pub fn title_size(&self, absolute: Option<bool>) -> Result<usize, TitleError> {
-        let mut title_size: usize = 0;
-        // Get the TMD and Ticket size by dumping them and measuring their length for the most
-        // accurate results.
-        title_size += self.tmd.to_bytes().map_err(|x| TitleError::TMD(tmd::TMDError::IO(x)))?.len();
-        title_size += self.ticket.to_bytes().map_err(|x| TitleError::Ticket(ticket::TicketError::IO(x)))?.len();
-        for record in self.tmd.content_records.borrow().iter() {
-            if matches!(record.content_type, tmd::ContentType::Shared) {
-                if absolute == Some(true) {
-                    title_size += record.content_size as usize;
-                }
-            }
-            else {
-                title_size += record.content_size as usize;
+
+    /// Removes the content at the specified index from the content list and content records. The
+    /// TMD shares these records, so it will reflect the removal without any further changes.
+    pub fn remove_content(&mut self, index: usize) -> Result<&mut Self, TitleError> {
+        self.content.remove_content(index)?;
+        Ok(self)
+    }
+
+    /// Renumbers the Title's content indices so that they run from 0 to n-1 in their current order,
+    /// re-encrypting any content whose index changed. The TMD's boot index and the Ticket's content
+    /// access permissions are remapped to follow the contents they referred to.
+    pub fn reindex_contents(&mut self) -> Result<&mut Self, TitleError> {
+        let old_indices: Vec<u16> = self.content.content_records.read().iter().map(|record| record.index).collect();
+        let boot_position = old_indices.iter().position(|index| *index == self.tmd.boot_index);
+        // The Ticket's permissions are moved to the new indices on a copy first, since that can fail
+        // and the contents shouldn't be renumbered if it does.
+        let mut ticket = self.ticket.clone();
+        if !ticket.all_contents_permitted() {
+            let permitted: Vec<bool> = old_indices.iter().map(|index| ticket.is_content_permitted(*index)).collect();
+            ticket.content_access_permission = [0; 64];
+            for (new_index, allowed) in permitted.into_iter().enumerate() {
+                ticket.set_content_permitted(new_index as u16, allowed).map_err(TitleError::Ticket)?;
             }
         }
-        Ok(title_size)
+        self.content.reindex(self.ticket.dec_title_key())?;
+        if let Some(boot_position) = boot_position {
+            self.tmd.boot_index = boot_position as u16;
+        }
+        self.ticket = ticket;
+        Ok(self)
+    }
+
+    /// Removes all contents of the specified type from the content list and content records,
+    /// returning the Content IDs of the removed contents in their original order.
+    pub fn remove_contents_by_type(&mut self, content_type: tmd::ContentType) -> Vec<u32> {
+        let targets: Vec<(usize, u32)> = self.content.content_records.read().iter().enumerate()
+            .filter(|(_, record)| record.content_type == content_type)
+            .map(|(position, record)| (position, record.content_id))
+            .collect();
+        // Remove from the back so that the earlier positions stay valid.
+        for (position, _) in targets.iter().rev() {
+            self.content.remove_content(*position).expect("content position should be in range");
+        }
+        targets.into_iter().map(|(_, cid)| cid).collect()
+    }
+    
+    // Gets the decrypted banner content (the content at index 0) of a Title, provided that it
+    // starts with an IMET header.
+    fn banner_content(&self) -> Result<Vec<u8>, TitleError> {
+        let position = self.content.content_records.read().iter().position(|record| record.index == 0)
+            .ok_or(TitleError::NoBanner)?;
+        let mut banner: Vec<u8> = Vec::new();
+        self.open_content_by_index(position)?.copy_to(&mut banner)?;
+        if imet::find_imet_offset(&banner).is_none() {
+            return Err(TitleError::NoBanner);
+        }
+        Ok(banner)
+    }
+
+    /// Extracts the banner from the Title's content at index 0, returning the U8 archive that
+    /// follows the IMET header. Returns TitleError::NoBanner for titles without a banner, like
+    /// IOSes and most system titles.
+    pub fn extract_banner(&self) -> Result<u8::U8Archive, TitleError> {
+        let banner = self.banner_content()?;
+        let data_offset = imet::imet_data_offset(&banner).ok_or(TitleError::NoBanner)?;
+        let archive = u8::U8Archive::from_bytes(banner.get(data_offset..).ok_or(TitleError::NoBanner)?)?;
+        Ok(archive)
+    }
+
+    /// Gets the localized names of the channel from the IMET header of the Title's banner. Returns
+    /// TitleError::NoBanner for titles without a banner, like IOSes and most system titles.
+    pub fn channel_names(&self) -> Result<HashMap<imet::Language, String>, TitleError> {
+        let banner = self.banner_content()?;
+        let imet_header = imet::IMETHeader::from_bytes(&banner)?;
+        Ok(imet_header.channel_names())
+    }
+
+    /// Compares a Title against another Title, returning a TitleDiff that describes every
+    /// difference found in their TMDs, Tickets, and content records.
+    pub fn diff(&self, other: &Title) -> diff::TitleDiff {
+        diff::diff_titles(self, other)
+    }
+
+    /// Checks the Title for structural problems that would prevent it from installing or working
+    /// correctly, like a boot index that doesn't match any content. Signatures aren't checked.
+    pub fn lint(&self) -> Vec<lint::TitleLintWarning> {
+        lint::lint_title(self)
+    }
+
+    // Gets the size of the TMD and Ticket, by dumping them and measuring their length for the most
+    // accurate results.
+    fn metadata_size(&self) -> Result<u64, TitleError> {
+        let tmd_size = self.tmd.to_bytes().map_err(|x| TitleError::TMD(tmd::TMDError::IO(x)))?.len();
+        let ticket_size = self.ticket.to_bytes().map_err(|x| TitleError::Ticket(ticket::TicketError::IO(x)))?.len();
+        Ok((tmd_size + ticket_size) as u64)
+    }
+
+    // Gets the sizes of the contents that should be counted, based on how shared content is being
+    // accounted for. Contents that aren't present won't be installed, so they're never counted.
+    fn counted_content_sizes(&self, shared: SharedContentAccounting) -> Vec<u64> {
+        self.tmd.content_records.read().iter().enumerate()
+            .filter(|(i, _)| self.content.is_content_present(*i))
+            .filter(|(_, record)| shared == SharedContentAccounting::Include || !matches!(record.content_type, tmd::ContentType::Shared))
+            .map(|(_, record)| record.content_size)
+            .collect()
+    }
+
+    /// Gets the installed size of the title, in bytes, including the TMD and Ticket.
+    pub fn title_size(&self, shared: SharedContentAccounting) -> Result<u64, TitleError> {
+        Ok(self.metadata_size()? + self.counted_content_sizes(shared).iter().sum::<u64>())
+    }
+
+    /// Gets the installed size of the title, in blocks. Like the System Menu, each content is
+    /// rounded up to a whole number of blocks individually, and the TMD and Ticket are counted
+    /// together on top of that.
+    pub fn title_size_blocks(&self, shared: SharedContentAccounting) -> Result<u64, TitleError> {
+        let content_blocks: u64 = self.counted_content_sizes(shared).into_iter().map(bytes_to_blocks).sum();
+        Ok(bytes_to_blocks(self.metadata_size()?) + content_blocks)
     }
     
     /// Verifies entire certificate chain, and then the TMD and Ticket. Returns true if the title
@@ -190,18 +761,63 @@ impl Title {
     
     /// Sets a new Title ID for the Title. This will re-encrypt the Title Key in the Ticket, since 
     /// the Title ID is used as the IV for decrypting the Title Key.
-    pub fn set_title_id(&mut self, title_id: [u8; 8]) -> Result<(), TitleError> {
-        self.tmd.set_title_id(title_id)?;
-        self.ticket.set_title_id(title_id)?;
-        Ok(())
+    pub fn set_title_id(&mut self, title_id: TitleId) -> Result<&mut Self, TitleError> {
+        self.edit_signed_parts(|tmd, ticket| {
+            tmd.set_title_id(title_id)?;
+            ticket.set_title_id(title_id)?;
+            Ok(())
+        })
+    }
+
+    /// Sets a new version for the Title, in both the TMD and the Ticket.
+    pub fn set_title_version(&mut self, title_version: u16) -> Result<&mut Self, TitleError> {
+        self.tmd.title_version = title_version;
+        self.ticket.title_version = title_version;
+        Ok(self)
+    }
+
+    /// Sets the IOS required by the Title, using its version number (e.g. 58 for IOS58). The valid
+    /// range of IOS versions is 3 to 255.
+    pub fn set_required_ios(&mut self, ios_version: u32) -> Result<&mut Self, TitleError> {
+        self.tmd.set_ios_tid(TitleId::from_parts([0, 0, 0, 1], ios_version.to_be_bytes()).to_bytes())?;
+        Ok(self)
+    }
+
+    /// Sets the region the Title was created for, using its 3-letter code (or "None" for region
+    /// free). See TMD::set_region().
+    pub fn set_region(&mut self, region: &str) -> Result<&mut Self, TitleError> {
+        self.tmd.set_region(region)?;
+        Ok(self)
+    }
+
+    /// Enables or disables an access right for the Title.
+    pub fn set_access_right(&mut self, right: tmd::AccessRight, enabled: bool) -> Result<&mut Self, TitleError> {
+        self.tmd.set_access_right(right, enabled);
+        Ok(self)
+    }
+
+    /// Removes every limit from the Title's Ticket, like the play time limits used by demos.
+    pub fn clear_limits(&mut self) -> Result<&mut Self, TitleError> {
+        self.ticket.clear_limits();
+        Ok(self)
+    }
+
+    /// Gets the certificate chain used to verify the Title's TMD and Ticket.
+    pub fn cert_chain(&self) -> &cert::CertificateChain {
+        &self.cert_chain
+    }
+
+    /// Gets the raw binary data of the Title's certificate chain, as it would be written to a WAD.
+    pub fn cert_chain_bytes(&self) -> Result<Vec<u8>, TitleError> {
+        Ok(self.cert_chain.to_bytes()?)
     }
 
     pub fn set_cert_chain(&mut self, cert_chain: cert::CertificateChain) {
         self.cert_chain = cert_chain;
     }
     
-    pub fn crl(&self) -> Vec<u8> {
-        self.crl.clone()
+    pub fn crl(&self) -> &[u8] {
+        &self.crl
     }
     
     pub fn set_crl(&mut self, crl: &[u8]) {
@@ -210,18 +826,31 @@ impl Title {
     
     pub fn set_ticket(&mut self, ticket: ticket::Ticket) {
         self.ticket = ticket;
+        self.has_ticket = true;
     }
     
+    /// Sets a new TMD for the Title. The ContentRegion will be linked to the new TMD's content
+    /// records, so the TMD must describe the existing contents.
     pub fn set_tmd(&mut self, tmd: tmd::TMD) {
+        self.content.content_records = tmd.content_records.clone();
         self.tmd = tmd;
     }
     
+    /// Sets a new ContentRegion for the Title. The TMD will be linked to the new ContentRegion's
+    /// content records.
     pub fn set_content_region(&mut self, content: content::ContentRegion) {
+        self.tmd.content_records = content.content_records.clone();
         self.content = content;
     }
     
-    pub fn meta(&self) -> Vec<u8> {
-        self.meta.clone()
+    pub fn meta(&self) -> &[u8] {
+        &self.meta
+    }
+
+    /// Gets the meta/footer of the Title as a Meta. Returns None if the Title doesn't have a
+    /// footer, or if the footer doesn't use a known layout.
+    pub fn parsed_meta(&self) -> Option<meta::Meta> {
+        meta::Meta::from_bytes(&self.meta)
     }
     
     pub fn set_meta(&mut self, meta: &[u8]) {
@@ -229,7 +858,765 @@ impl Title {
     }
 }
 
-/// Converts bytes to the Wii's storage unit, blocks.
-pub fn bytes_to_blocks(size_bytes: usize) -> usize {
-    (size_bytes as f64 / 131072.0).ceil() as usize
+#[cfg(feature = "fs")]
+// Finds the file with the provided extension in a directory containing unpacked Title components,
+// ensuring that there's only one matching file.
+pub(crate) fn find_component(path: &Path, extension: &str, required: bool) -> Result<Option<PathBuf>, TitleError> {
+    let mut matches: Vec<PathBuf> = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let entry_path = entry?.path();
+        if entry_path.is_file() && entry_path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case(extension)) {
+            matches.push(entry_path);
+        }
+    }
+    if matches.len() > 1 {
+        return Err(TitleError::DuplicateComponent(format!(".{}", extension)));
+    }
+    if matches.is_empty() && required {
+        return Err(TitleError::MissingComponent(format!(".{}", extension)));
+    }
+    Ok(matches.pop())
+}
+
+/// The size of a block, the Wii's storage unit, in bytes.
+pub const BLOCK_SIZE: u64 = 0x20000;
+
+/// Converts bytes to the Wii's storage unit, blocks, rounding up to the nearest whole block.
+pub fn bytes_to_blocks(size_bytes: u64) -> u64 {
+    size_bytes.div_ceil(BLOCK_SIZE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{self, Read};
+    use crate::title::test_utils;
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let wad_path = dir.path().join("title.wad");
+        let title = test_utils::build_title(&[vec![0x11; 100]]);
+        fs::write(&wad_path, title.to_wad_bytes().unwrap()).unwrap();
+        assert_eq!(Title::open(&wad_path).unwrap().get_content_by_index(0).unwrap(), vec![0x11; 100]);
+        let missing = dir.path().join("missing.wad");
+        let err = Title::open(&missing).unwrap_err();
+        assert!(err.to_string().contains("missing.wad"));
+        assert!(matches!(err, TitleError::ReadFile { source, .. } if source.kind() == io::ErrorKind::NotFound));
+    }
+
+    #[cfg(all(feature = "fs", unix))]
+    #[test]
+    fn test_open_permission_denied() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let wad_path = dir.path().join("title.wad");
+        fs::write(&wad_path, test_utils::build_title(&[vec![0x11; 100]]).to_wad_bytes().unwrap()).unwrap();
+        fs::set_permissions(&wad_path, fs::Permissions::from_mode(0o000)).unwrap();
+        // Permissions don't apply to root, so only expect an error if the file really can't be
+        // opened.
+        match fs::File::open(&wad_path) {
+            Err(_) => assert!(matches!(Title::open(&wad_path), Err(TitleError::ReadFile { path, source })
+                if path == wad_path && source.kind() == io::ErrorKind::PermissionDenied)),
+            Ok(_) => assert!(Title::open(&wad_path).is_ok()),
+        }
+    }
+
+    fn build_banner(name: &str) -> Vec<u8> {
+        let mut banner = vec![0u8; 0x40];
+        banner.extend_from_slice(b"IMET");
+        banner.extend_from_slice(&0x600u32.to_be_bytes());
+        banner.extend_from_slice(&3u32.to_be_bytes());
+        banner.resize(0x5C, 0);
+        for _ in 0..10 {
+            let mut encoded: Vec<u8> = name.encode_utf16().flat_map(|c| c.to_be_bytes()).collect();
+            encoded.resize(84, 0);
+            banner.extend(encoded);
+        }
+        banner.resize(0x600, 0);
+        let root = u8::U8Directory::new(String::new());
+        let meta = u8::U8Directory::new(String::from("meta"));
+        u8::U8Directory::add_file(&meta, u8::U8File::new(String::from("icon.bin"), vec![0x11; 32]));
+        u8::U8Directory::add_dir(&root, meta);
+        banner.extend(u8::U8Archive::from_tree(&root).unwrap().to_bytes().unwrap());
+        banner
+    }
+
+    // These only need to compile, to prove that the API surface is the same with and without the
+    // nus feature.
+    #[test]
+    fn test_api_without_nus() {
+        let _: fn(&[u8]) -> Result<Title, TitleError> = Title::from_bytes;
+        let _: fn(&wad::WAD) -> Result<Title, TitleError> = Title::from_wad;
+        let _: fn(&[u8]) -> Result<wad::WAD, wad::WADError> = wad::WAD::from_bytes;
+        let _: fn(&str) -> Option<Vec<versions::SystemUpdateTitle>> = versions::system_update_titles;
+    }
+
+    #[cfg(feature = "nus")]
+    #[test]
+    fn test_api_with_nus() {
+        let _: fn(TitleId, Option<u16>, bool) -> Result<Title, nus::NUSError> = nus::download_title;
+        let _: fn(TitleId, u32, bool) -> Result<Vec<u8>, nus::NUSError> = nus::download_content;
+        let _: fn(bool) -> Result<Vec<u8>, nus::NUSError> = nus::download_cert_chain;
+    }
+
+    #[test]
+    fn test_title_size() {
+        let title = test_utils::build_title(&[vec![0x01; 0x20000], vec![0x02; 0x20001], vec![0x03; 0x1FFFF], vec![0x04; 0x10]]);
+        title.content.content_records.write()[3].content_type = tmd::ContentType::Shared;
+        let metadata_size = (title.tmd.to_bytes().unwrap().len() + title.ticket.to_bytes().unwrap().len()) as u64;
+        assert_eq!(title.title_size(SharedContentAccounting::Exclude).unwrap(), metadata_size + 0x60000);
+        assert_eq!(title.title_size(SharedContentAccounting::Include).unwrap(), metadata_size + 0x60010);
+        // Each content is rounded up on its own, so 0x20001 and 0x1FFFF bytes take 3 blocks rather
+        // than 2, and the TMD and Ticket take another.
+        assert_eq!(title.title_size_blocks(SharedContentAccounting::Exclude).unwrap(), 5);
+        assert_eq!(title.title_size_blocks(SharedContentAccounting::Include).unwrap(), 6);
+        assert_eq!(bytes_to_blocks(0), 0);
+        assert_eq!(bytes_to_blocks(BLOCK_SIZE), 1);
+        assert_eq!(bytes_to_blocks(BLOCK_SIZE + 1), 2);
+    }
+
+    #[test]
+    fn test_extract_banner() {
+        let title = test_utils::build_title(&[build_banner("Test Channel"), vec![0x56; 16]]);
+        let archive = title.extract_banner().unwrap();
+        let meta = u8::U8Directory::get_child_dir(&archive.node_tree, "meta").unwrap();
+        assert_eq!(meta.borrow().files[0].borrow().name, "icon.bin");
+        let names = title.channel_names().unwrap();
+        assert_eq!(names[&imet::Language::English], "Test Channel");
+        assert_eq!(names.len(), 10);
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_parsed_meta() {
+        let mut title = test_utils::build_title(&[vec![0x9C; 16]]);
+        assert!(title.parsed_meta().is_none());
+        // The footer is a copy of the banner's IMET header, optionally after a build tag.
+        let mut footer = b"2009/04/03 12:11:05".to_vec();
+        footer.resize(0x20, 0);
+        footer.extend_from_slice(b"BUILDER@HOST");
+        footer.resize(0x40, 0);
+        footer.extend_from_slice(&build_banner("Test Channel")[..0x640]);
+        title.set_meta(&footer);
+        let build_info = title.parsed_meta().unwrap().build_info.unwrap();
+        assert_eq!(build_info.to_string(), "2009-04-03 12:11 by BUILDER@HOST");
+        // Unrecognized footers should still be written out exactly as they were read.
+        title.set_meta(&[0xE7; 0x30]);
+        assert!(title.parsed_meta().is_none());
+        let dir = tempfile::tempdir().unwrap();
+        title.to_directory(dir.path()).unwrap();
+        assert_eq!(Title::from_directory(dir.path()).unwrap().meta(), [0xE7; 0x30]);
+    }
+
+    #[test]
+    fn test_extract_banner_no_banner() {
+        let title = test_utils::build_title(&[vec![0x78; 0x700]]);
+        assert!(matches!(title.extract_banner(), Err(TitleError::NoBanner)));
+        assert!(matches!(title.channel_names(), Err(TitleError::NoBanner)));
+    }
+
+    #[test]
+    fn test_get_boot_content() {
+        let mut title = test_utils::build_title(&[vec![0x01; 16], vec![0x02; 16]]);
+        assert_eq!(title.get_boot_content().unwrap(), vec![0x01; 16]);
+        title.tmd.boot_index = 1;
+        assert_eq!(title.get_boot_content().unwrap(), vec![0x02; 16]);
+    }
+
+    #[test]
+    fn test_get_boot_content_out_of_order() {
+        let mut title = test_utils::build_title_with_indices(&[(2, vec![0xAA; 32]), (0, vec![0xBB; 32]), (1, vec![0xCC; 32])]);
+        title.tmd.boot_index = 1;
+        assert_eq!(title.get_boot_content_index().unwrap(), 2);
+        assert_eq!(title.get_boot_content().unwrap(), vec![0xCC; 32]);
+        title.tmd.boot_index = 2;
+        assert_eq!(title.get_boot_content_index().unwrap(), 0);
+        assert_eq!(title.get_boot_content().unwrap(), vec![0xAA; 32]);
+        title.tmd.boot_index = 3;
+        assert!(matches!(title.get_boot_content(), Err(TitleError::BootContentNotFound(3))));
+    }
+
+    #[test]
+    fn test_open_boot_content_dol() {
+        let mut dol = vec![0u8; dol::DOL_HEADER_SIZE];
+        dol[0x00..0x04].copy_from_slice(&0x100u32.to_be_bytes());
+        dol[0x48..0x4C].copy_from_slice(&0x80004000u32.to_be_bytes());
+        dol[0x90..0x94].copy_from_slice(&0x20u32.to_be_bytes());
+        dol[0xE0..0xE4].copy_from_slice(&0x80004000u32.to_be_bytes());
+        dol.extend([0x60; 0x20]);
+        let mut title = test_utils::build_title_with_indices(&[(0, vec![0xAA; 64]), (1, dol)]);
+        title.tmd.boot_index = 1;
+        let header = dol::DolHeader::from_reader(&mut title.open_boot_content().unwrap()).unwrap();
+        assert_eq!(header.entry_point, 0x80004000);
+        assert_eq!(header.file_size(), 0x120);
+        title.tmd.boot_index = 0;
+        assert!(dol::DolHeader::from_reader(&mut title.open_boot_content().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_open_content_by_index() {
+        let contents: Vec<Vec<u8>> = vec![(0..0x12345).map(|i| (i % 251) as u8).collect(), vec![], vec![0x33; 40]];
+        let title = test_utils::build_title(&contents);
+        for (i, content) in contents.iter().enumerate() {
+            // Reading in small, odd-sized chunks should produce the same bytes as decrypting the
+            // whole content at once.
+            let mut reader = title.open_content_by_index(i).unwrap();
+            let mut read: Vec<u8> = Vec::new();
+            let mut chunk = [0u8; 7];
+            loop {
+                let len = reader.read(&mut chunk).unwrap();
+                if len == 0 {
+                    break;
+                }
+                read.extend_from_slice(&chunk[..len]);
+            }
+            assert_eq!(&read, content);
+            assert_eq!(read, title.get_content_by_index(i).unwrap());
+        }
+        // Streaming a large content shouldn't need anywhere near a full copy of it in memory.
+        let (copied, peak) = test_utils::measure_peak_allocation(|| title.open_content_by_index(0).unwrap().copy_to(&mut io::sink()).unwrap());
+        assert_eq!(copied, 0x12345);
+        assert!(peak < 0x12345, "peak allocation was {:#X} bytes", peak);
+        assert!(matches!(title.open_content_by_index(3), Err(content::ContentError::IndexOutOfRange { .. })));
+    }
+
+    #[test]
+    fn test_open_content_by_index_bad_hash() {
+        let title = test_utils::build_title(&[vec![0x11; 0x30000]]);
+        let mut records = title.content.content_records.write();
+        records[0].content_hash[0] ^= 0xFF;
+        drop(records);
+        // The bad hash is only detected once the end of the content is reached, and the final
+        // read fails instead of returning data that doesn't match.
+        let mut reader = title.open_content_by_index(0).unwrap();
+        let mut buf = vec![0u8; 0x10000];
+        assert_eq!(reader.read(&mut buf).unwrap(), 0x10000);
+        let mut rest: Vec<u8> = Vec::new();
+        let err = reader.read_to_end(&mut rest).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(reader.read(&mut buf).is_err());
+        assert!(matches!(title.open_content_by_index(0).unwrap().copy_to(&mut io::sink()), Err(content::ContentError::BadHash { .. })));
+        assert!(matches!(title.extract_banner(), Err(TitleError::Content(content::ContentError::BadHash { .. }))));
+    }
+
+    #[test]
+    fn test_content_iteration_order() {
+        let title = test_utils::build_title_with_indices(&[(2, vec![0xAA; 32]), (0, vec![0xBB; 32]), (1, vec![0xCC; 32])]);
+        let by_position: Vec<(usize, u16)> = title.iter_by_position().map(|(i, record)| (i, record.index)).collect();
+        assert_eq!(by_position, vec![(0, 2), (1, 0), (2, 1)]);
+        let by_index: Vec<(usize, u16)> = title.iter_by_record_index().map(|(i, record)| (i, record.index)).collect();
+        assert_eq!(by_index, vec![(1, 0), (2, 1), (0, 2)]);
+        assert_eq!(title.get_position_for_index(0).unwrap(), 1);
+        assert_eq!(title.get_position_for_index(2).unwrap(), 0);
+        assert!(matches!(title.get_position_for_index(3), Err(content::ContentError::RecordIndexNotFound(3))));
+        for (i, record) in title.iter_by_record_index() {
+            let expected = [vec![0xBB; 32], vec![0xCC; 32], vec![0xAA; 32]][record.index as usize].clone();
+            assert_eq!(title.get_content_by_index(i).unwrap(), expected);
+        }
+    }
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_title_types_are_send_sync() {
+        assert_send_sync::<tmd::TMD>();
+        assert_send_sync::<ticket::Ticket>();
+        assert_send_sync::<content::ContentRegion>();
+        assert_send_sync::<Title>();
+    }
+
+    #[test]
+    fn test_title_can_move_between_threads() {
+        let title = test_utils::build_title(&[vec![0x42; 32]]);
+        let handle = std::thread::spawn(move || title.get_content_by_index(0).unwrap());
+        assert_eq!(handle.join().unwrap(), vec![0x42; 32]);
+    }
+
+    #[test]
+    fn test_add_content_updates_tmd() {
+        let mut title = test_utils::build_title(&[vec![0x9A; 48]]);
+        assert_eq!(title.add_content(&[0xBC; 80], Some(0x20), tmd::ContentType::Normal).unwrap(), 0x20);
+        assert_eq!(title.add_content(&[0xDE; 16], None, tmd::ContentType::Normal).unwrap(), 0x21);
+        title.remove_content(2).unwrap();
+        assert_eq!(title.tmd.num_contents(), 2);
+        let tmd = tmd::TMD::from_bytes(&title.tmd.to_bytes().unwrap()).unwrap();
+        assert_eq!(tmd.num_contents(), 2);
+        assert_eq!(tmd.content_records.read()[1].content_id, 0x20);
+        assert_eq!(tmd.content_records.read()[1].content_size, 80);
+    }
+
+    #[test]
+    fn test_chained_edits() {
+        let mut title = test_utils::build_title(&[vec![0x3C; 64]]);
+        title.set_title_id(TitleId::from_hex("0001000148414445").unwrap()).unwrap()
+            .set_title_version(513).unwrap()
+            .set_required_ios(58).unwrap()
+            .set_access_right(tmd::AccessRight::AHB, true).unwrap()
+            .fakesign().unwrap();
+        assert_eq!(title.ticket.title_id(), title.tmd.title_id());
+        assert_eq!((title.tmd.title_version, title.ticket.title_version), (513, 513));
+        assert!(title.is_fakesigned());
+        // Edits to the TMD and Ticket don't stop the TMD from sharing the content records.
+        title.add_content(&[0x3D; 32], None, tmd::ContentType::Normal).unwrap();
+        assert_eq!(title.tmd.num_contents(), 2);
+        // Failed edits leave the Title as it was.
+        let before = title.to_wad_bytes().unwrap();
+        assert!(matches!(title.set_required_ios(2), Err(TitleError::TMD(tmd::TMDError::InvalidIOSVersion(2)))));
+        assert!(matches!(title.set_region("AUS"), Err(TitleError::TMD(tmd::TMDError::InvalidRegion(_)))));
+        assert!(matches!(title.convert_encryption(EncryptionTarget::Retail, true), Err(TitleError::AlreadyEncrypted(_))));
+        let used_cid = title.content.content_records.read()[1].content_id;
+        assert!(matches!(title.set_content(&[0x3E; 48], 0, Some(used_cid), None), Err(TitleError::Content(content::ContentError::CIDAlreadyExists(_)))));
+        assert_eq!(title.get_content_by_index(0).unwrap(), [0x3C; 64]);
+        assert_eq!(title.to_wad_bytes().unwrap(), before);
+    }
+
+    #[test]
+    fn test_failed_reindex_leaves_title() {
+        // A Ticket can only hold permissions for 512 contents, so renumbering 513 contents that
+        // don't all have permission has to fail, and shouldn't renumber anything when it does.
+        let mut title = test_utils::build_title(&vec![vec![0x5A; 16]; 514]);
+        title.remove_content(0).unwrap();
+        title.ticket.permit_all_contents();
+        title.ticket.set_content_permitted(1, false).unwrap();
+        let before = title.to_wad_bytes().unwrap();
+        assert!(matches!(title.reindex_contents(), Err(TitleError::Ticket(ticket::TicketError::InvalidContentIndex(512)))));
+        assert_eq!(title.content.content_records.read()[0].index, 1);
+        assert_eq!(title.to_wad_bytes().unwrap(), before);
+    }
+
+    #[test]
+    fn test_content_counts_stay_in_sync() {
+        let mut title = test_utils::build_title(&[vec![0x01; 16]]);
+        let assert_count = |title: &Title, count: usize| {
+            assert_eq!(title.content.len(), count);
+            assert_eq!(title.tmd.num_contents() as usize, count);
+            let tmd = tmd::TMD::from_bytes(&title.tmd.to_bytes().unwrap()).unwrap();
+            assert_eq!(tmd.num_contents() as usize, count);
+        };
+        title.add_content(&[0x02; 32], None, tmd::ContentType::Normal).unwrap();
+        assert_count(&title, 2);
+        title.add_content(&[0x03; 48], None, tmd::ContentType::Normal).unwrap();
+        assert_count(&title, 3);
+        title.set_content(&[0x04; 64], 1, None, None).unwrap();
+        assert_count(&title, 3);
+        title.remove_content(0).unwrap();
+        assert_count(&title, 2);
+        title.remove_content(0).unwrap();
+        title.remove_content(0).unwrap();
+        assert_count(&title, 0);
+        assert!(title.content.is_empty());
+    }
+
+    #[test]
+    fn test_remove_content_updates_tmd() {
+        let mut title = test_utils::build_title(&[vec![0x01; 16], vec![0x02; 16], vec![0x03; 16]]);
+        title.remove_content(1).unwrap();
+        let tmd = tmd::TMD::from_bytes(&title.tmd.to_bytes().unwrap()).unwrap();
+        assert_eq!(tmd.num_contents(), 2);
+        let cids: Vec<u32> = tmd.content_records.read().iter().map(|record| record.content_id).collect();
+        assert_eq!(cids, vec![0x10, 0x12]);
+        let reloaded = Title::from_bytes(&title.to_wad().unwrap().to_bytes().unwrap()).unwrap();
+        assert_eq!(reloaded.get_content_by_index(1).unwrap(), vec![0x03; 16]);
+    }
+
+    #[test]
+    fn test_set_title_key() {
+        let mut title = test_utils::build_title(&[vec![0x01; 16], vec![0x02; 100]]);
+        let old_enc = title.content.get_enc_content_by_index(1).unwrap();
+        let new_key = ticket::parse_title_key("00112233445566778899aabbccddeeff").unwrap();
+        title.set_title_key(new_key).unwrap();
+        assert_ne!(title.content.get_enc_content_by_index(1).unwrap(), old_enc);
+        let reloaded = Title::from_bytes(&title.to_wad().unwrap().to_bytes().unwrap()).unwrap();
+        assert_eq!(reloaded.ticket.dec_title_key(), new_key);
+        assert_eq!(reloaded.get_content_by_index(0).unwrap(), vec![0x01; 16]);
+        assert_eq!(reloaded.get_content_by_index(1).unwrap(), vec![0x02; 100]);
+        assert!(reloaded.is_fakesigned());
+    }
+
+    #[test]
+    fn test_reindex_contents() {
+        let contents = vec![(0, vec![0x01; 16]), (3, vec![0x02; 40]), (7, vec![0x03; 16])];
+        let mut title = test_utils::build_title_with_indices(&contents);
+        title.tmd.boot_index = 7;
+        title.ticket.set_content_permitted(0, true).unwrap();
+        title.ticket.set_content_permitted(7, true).unwrap();
+        title.reindex_contents().unwrap();
+        let indices: Vec<u16> = title.tmd.content_records.read().iter().map(|record| record.index).collect();
+        assert_eq!(indices, vec![0, 1, 2]);
+        // The boot index has to follow the content it pointed at, not stay at its old value.
+        assert_eq!(title.tmd.boot_index, 2);
+        assert_eq!(title.get_boot_content().unwrap(), vec![0x03; 16]);
+        assert_eq!(title.ticket.permitted_contents(), vec![0, 2]);
+        title.fakesign().unwrap();
+        let reloaded = Title::from_bytes(&title.to_wad().unwrap().to_bytes().unwrap()).unwrap();
+        for (position, (_, data)) in contents.iter().enumerate() {
+            assert_eq!(&reloaded.get_content_by_index(position).unwrap(), data);
+        }
+        // Reindexing contiguous contents is a no-op.
+        let before = title.to_wad().unwrap().to_bytes().unwrap();
+        title.reindex_contents().unwrap();
+        assert_eq!(title.to_wad().unwrap().to_bytes().unwrap(), before);
+    }
+
+    #[test]
+    fn test_remove_contents_by_type() {
+        let mut title = test_utils::build_title(&[vec![0x01; 16], vec![0x02; 16]]);
+        title.add_content(&[0x03; 16], None, tmd::ContentType::DLC).unwrap();
+        title.add_content(&[0x04; 16], None, tmd::ContentType::Shared).unwrap();
+        title.add_content(&[0x05; 16], None, tmd::ContentType::DLC).unwrap();
+        assert_eq!(title.remove_contents_by_type(tmd::ContentType::DLC), vec![0x12, 0x14]);
+        assert!(title.remove_contents_by_type(tmd::ContentType::DLC).is_empty());
+        title.fakesign().unwrap();
+        let reloaded = Title::from_bytes(&title.to_wad().unwrap().to_bytes().unwrap()).unwrap();
+        assert_eq!(reloaded.tmd.num_contents(), 3);
+        let cids: Vec<u32> = reloaded.tmd.content_records.read().iter().map(|record| record.content_id).collect();
+        assert_eq!(cids, vec![0x10, 0x11, 0x13]);
+        assert_eq!(reloaded.get_content_by_cid(0x13).unwrap(), vec![0x04; 16]);
+        assert!(reloaded.is_fakesigned());
+    }
+
+    #[test]
+    fn test_set_content_region_shares_records() {
+        let mut title = test_utils::build_title(&[vec![0x01; 16]]);
+        let other = test_utils::build_title(&[vec![0x02; 16], vec![0x03; 16]]);
+        title.set_content_region(other.content);
+        assert!(title.tmd.content_records.ptr_eq(&title.content.content_records));
+        assert_eq!(title.tmd.num_contents(), 2);
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_directory_round_trip() {
+        let contents = vec![vec![0xAB; 100], vec![0xCD; 4096]];
+        let title = test_utils::build_title(&contents);
+        let dir = tempfile::tempdir().unwrap();
+        title.to_directory(dir.path()).unwrap();
+        let loaded = Title::from_directory(dir.path()).unwrap();
+        assert_eq!(loaded.to_wad().unwrap().to_bytes().unwrap(), title.to_wad().unwrap().to_bytes().unwrap());
+        assert_eq!(loaded.get_content_by_index(1).unwrap(), contents[1]);
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_reproducible_pack() {
+        let mut title = test_utils::build_title(&[vec![0x5E; 300], vec![0x7A; 0x1000]]);
+        title.set_meta(&test_utils::build_tagged_footer("2009/04/03 12:11:05", "BUILDER@HOST"));
+        let dir = tempfile::tempdir().unwrap();
+        title.to_directory(dir.path()).unwrap();
+        let options = PackOptions { deterministic: true, timestamp_override: None };
+        let pack = || {
+            let mut title = Title::from_directory(dir.path()).unwrap();
+            title.fakesign().unwrap();
+            let mut buf: Vec<u8> = Vec::new();
+            title.write_wad_with_options(&mut buf, &options).unwrap();
+            buf
+        };
+        let first = pack();
+        assert_eq!(pack(), first);
+        // The modification times of the files being packed don't leak into the WAD.
+        let content_path = dir.path().join("00000001.app");
+        let modified = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1234567890);
+        fs::File::options().write(true).open(&content_path).unwrap().set_modified(modified).unwrap();
+        assert_eq!(pack(), first);
+        // Without a timestamp to pin the build tag to, the footer is left out entirely.
+        let wad = wad::WAD::from_bytes(&first).unwrap();
+        assert!(wad.meta().is_empty());
+        assert!(Title::from_wad(&wad).unwrap().is_fakesigned());
+        assert_eq!(title.to_wad_with_options(&options).unwrap().meta(), wad.meta());
+    }
+
+    #[test]
+    fn test_pack_timestamp_override() {
+        let footer = test_utils::build_tagged_footer("2009/04/03 12:11:05", "BUILDER@HOST");
+        let mut title = test_utils::build_title(&[vec![0x5F; 64]]);
+        title.set_meta(&footer);
+        let timestamp = meta::BuildTimestamp::parse("2011/12/25 08:30:00").unwrap();
+        let options = PackOptions { deterministic: true, timestamp_override: Some(timestamp) };
+        let wad = title.to_wad_with_options(&options).unwrap();
+        let build_info = meta::Meta::from_bytes(wad.meta()).unwrap().build_info.unwrap();
+        assert_eq!(build_info.to_string(), "2011-12-25 08:30 by BUILDER@HOST");
+        assert_eq!(title.to_wad_bytes().unwrap().len(), wad.to_bytes().unwrap().len());
+        // The timestamp is only applied when packing deterministically.
+        let options = PackOptions { deterministic: false, timestamp_override: Some(timestamp) };
+        assert_eq!(title.to_wad_with_options(&options).unwrap().meta(), footer);
+        // Footers without a build tag don't record when they were built, so they're kept as-is.
+        title.set_meta(&test_utils::build_imet_footer());
+        let options = PackOptions { deterministic: true, timestamp_override: None };
+        assert_eq!(title.to_wad_with_options(&options).unwrap().meta(), test_utils::build_imet_footer());
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_directory_round_trip_options() {
+        // Content IDs are assigned starting from 0x10, so give the second content a Content ID that
+        // collides with the first content's index to make sure files are matched correctly.
+        let contents = vec![vec![0x21; 300], vec![0x43; 64], vec![0x65; 5000]];
+        let title = test_utils::build_title(&contents);
+        title.content.content_records.write()[1].content_id = 0;
+        let expected = title.to_wad().unwrap().to_bytes().unwrap();
+        for naming in [ContentNaming::Index, ContentNaming::ContentID] {
+            for encrypted in [false, true] {
+                let dir = tempfile::tempdir().unwrap();
+                title.to_directory_with_options(dir.path(), naming, encrypted).unwrap();
+                let loaded = Title::from_directory(dir.path()).unwrap();
+                assert_eq!(loaded.to_wad().unwrap().to_bytes().unwrap(), expected);
+                for (i, content) in contents.iter().enumerate() {
+                    assert_eq!(&loaded.get_content_by_index(i).unwrap(), content);
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_directory_round_trip_shuffled_indices() {
+        // Records stored out of order must keep their data when unpacked and packed again, whether
+        // files are named after the record index or the Content ID.
+        let contents = vec![(2, vec![0x11; 48]), (0, vec![0x22; 700]), (1, vec![0x33; 16])];
+        let title = test_utils::build_title_with_indices(&contents);
+        let expected = title.to_wad().unwrap().to_bytes().unwrap();
+        for naming in [ContentNaming::Index, ContentNaming::ContentID] {
+            let dir = tempfile::tempdir().unwrap();
+            title.to_directory_with_options(dir.path(), naming, false).unwrap();
+            if naming == ContentNaming::Index {
+                assert_eq!(fs::read(dir.path().join("00000000.app")).unwrap(), contents[1].1);
+            }
+            let loaded = Title::from_directory(dir.path()).unwrap();
+            assert_eq!(loaded.to_wad().unwrap().to_bytes().unwrap(), expected);
+            for (index, data) in &contents {
+                let position = loaded.get_position_for_index(*index).unwrap();
+                assert_eq!(&loaded.get_content_by_index(position).unwrap(), data);
+            }
+        }
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_from_directory_without_cert() {
+        let contents = vec![vec![0x77; 200]];
+        let title = test_utils::build_title(&contents);
+        let dir = tempfile::tempdir().unwrap();
+        title.to_directory(dir.path()).unwrap();
+        let cert_name = format!("{}.cert", hex::encode(title.tmd.title_id()));
+        fs::remove_file(dir.path().join(cert_name)).unwrap();
+        assert!(matches!(Title::from_directory(dir.path()), Err(TitleError::MissingComponent(ext)) if ext == ".cert"));
+        let cert_chain = cert::CertificateChain::from_bytes(&test_utils::build_cert_chain()).unwrap();
+        let loaded = Title::from_directory_with_cert_chain(dir.path(), Some(cert_chain)).unwrap();
+        assert_eq!(loaded.to_wad().unwrap().to_bytes().unwrap(), title.to_wad().unwrap().to_bytes().unwrap());
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_from_directory_encrypted_content() {
+        let contents = vec![vec![0x12; 64]];
+        let title = test_utils::build_title(&contents);
+        let dir = tempfile::tempdir().unwrap();
+        title.to_directory(dir.path()).unwrap();
+        fs::write(dir.path().join("00000000.app"), title.content.get_enc_content_by_index(0).unwrap()).unwrap();
+        let loaded = Title::from_directory(dir.path()).unwrap();
+        assert_eq!(loaded.get_content_by_index(0).unwrap(), contents[0]);
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_from_directory_content_forms() {
+        let contents = vec![vec![0x56; 100], vec![0x78; 2000]];
+        let title = test_utils::build_title(&contents);
+        let expected = title.to_wad().unwrap().to_bytes().unwrap();
+        for encrypted in [false, true] {
+            let dir = tempfile::tempdir().unwrap();
+            title.to_directory_with_options(dir.path(), ContentNaming::Index, encrypted).unwrap();
+            let loaded = Title::from_directory(dir.path()).unwrap();
+            assert_eq!(loaded.to_wad().unwrap().to_bytes().unwrap(), expected);
+            let forced = Title::from_directory_with_options(dir.path(), None, false, Some(content::ContentForm::Encrypted));
+            if encrypted {
+                assert_eq!(forced.unwrap().to_wad().unwrap().to_bytes().unwrap(), expected);
+            } else {
+                assert!(matches!(forced, Err(TitleError::Content(content::ContentError::BadEncryptedContent { index: 0, .. }))));
+            }
+        }
+        // Content that matches its record in neither form should be rejected.
+        let dir = tempfile::tempdir().unwrap();
+        title.to_directory(dir.path()).unwrap();
+        fs::write(dir.path().join("00000001.app"), vec![0x9A; 2000]).unwrap();
+        assert!(matches!(Title::from_directory(dir.path()), Err(TitleError::Content(content::ContentError::UnrecognizedContent { index: 1, .. }))));
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_from_directory_component_errors() {
+        let title = test_utils::build_title(&[vec![0x34; 32]]);
+        let dir = tempfile::tempdir().unwrap();
+        title.to_directory(dir.path()).unwrap();
+        fs::write(dir.path().join("extra.tik"), title.ticket.to_bytes().unwrap()).unwrap();
+        assert!(matches!(Title::from_directory(dir.path()), Err(TitleError::DuplicateComponent(_))));
+        fs::remove_file(dir.path().join("extra.tik")).unwrap();
+        fs::remove_file(dir.path().join("00000000.app")).unwrap();
+        assert!(matches!(Title::from_directory(dir.path()), Err(TitleError::MissingComponent(_))));
+    }
+
+    #[test]
+    fn test_convert_encryption() {
+        let contents = vec![vec![0x5A; 300], vec![0xA5; 40]];
+        let mut title = test_utils::build_title(&contents);
+        let title_key = title.ticket.dec_title_key();
+        assert_eq!(title.encryption(), EncryptionTarget::Retail);
+        assert!(matches!(title.convert_encryption(EncryptionTarget::Retail, true), Err(TitleError::AlreadyEncrypted(EncryptionTarget::Retail))));
+        let targets = [EncryptionTarget::Dev, EncryptionTarget::Vwii, EncryptionTarget::Korean, EncryptionTarget::Dev,
+            EncryptionTarget::Korean, EncryptionTarget::Vwii, EncryptionTarget::Retail];
+        for target in targets {
+            title.convert_encryption(target, true).unwrap();
+            // Reload the converted title to make sure that everything was written out correctly.
+            let converted = Title::from_bytes(&title.to_wad().unwrap().to_bytes().unwrap()).unwrap();
+            assert_eq!(converted.encryption(), target);
+            assert_eq!(converted.ticket.dec_title_key(), title_key);
+            assert!(converted.is_fakesigned());
+            for (i, content) in contents.iter().enumerate() {
+                assert_eq!(&converted.get_content_by_index(i).unwrap(), content);
+            }
+            assert!(matches!(title.convert_encryption(target, true), Err(TitleError::AlreadyEncrypted(already)) if already == target));
+        }
+    }
+
+    #[test]
+    fn test_convert_encryption_signatures() {
+        let build = || {
+            let mut title = test_utils::build_title(&[vec![0x5A; 300]]);
+            title.tmd.signature.fill(0xA5);
+            title.ticket.signature.fill(0xA5);
+            title
+        };
+        // Without fakesigning, the original signatures are left exactly as they were.
+        let mut title = build();
+        title.convert_encryption(EncryptionTarget::Vwii, false).unwrap();
+        let converted = Title::from_bytes(&title.to_wad_bytes().unwrap()).unwrap();
+        assert_eq!(converted.encryption(), EncryptionTarget::Vwii);
+        assert_eq!(converted.tmd.signature, vec![0xA5; 256]);
+        assert_eq!(converted.ticket.signature, vec![0xA5; 256]);
+        assert!(!converted.is_fakesigned());
+        // With fakesigning, both signatures are replaced.
+        let mut title = build();
+        title.convert_encryption(EncryptionTarget::Vwii, true).unwrap();
+        let converted = Title::from_bytes(&title.to_wad_bytes().unwrap()).unwrap();
+        assert!(converted.tmd.signature.iter().all(|byte| *byte == 0));
+        assert!(converted.ticket.signature.iter().all(|byte| *byte == 0));
+        assert!(converted.is_fakesigned());
+    }
+
+    #[test]
+    fn test_from_parts_optional_components() {
+        let title = test_utils::build_title(&[vec![0x21; 100]]);
+        let wad = title.to_wad().unwrap();
+        let parts = |crl: Option<&[u8]>, meta: Option<&[u8]>| {
+            let content = content::ContentRegion::from_bytes(wad.content(), tmd::TMD::from_bytes(wad.tmd()).unwrap().content_records).unwrap();
+            Title::from_parts(wad.parsed_cert_chain().unwrap(), crl, wad.parsed_ticket().unwrap(), wad.parsed_tmd().unwrap(), content, meta).unwrap()
+        };
+        let bare = parts(None, None);
+        assert!(bare.crl().is_empty());
+        assert!(bare.meta().is_empty());
+        assert_eq!(bare.cert_chain_bytes().unwrap(), wad.cert_chain());
+        assert_eq!(bare.cert_chain().to_bytes().unwrap(), title.cert_chain.to_bytes().unwrap());
+        assert_eq!(bare.to_wad().unwrap().to_bytes().unwrap(), wad.to_bytes().unwrap());
+        let full = parts(Some(&[0xC0; 0x40]), Some(&[0x3E; 0x20]));
+        assert_eq!(full.crl(), [0xC0; 0x40]);
+        assert_eq!(full.meta(), [0x3E; 0x20]);
+        let data = full.to_wad().unwrap().to_bytes().unwrap();
+        let reloaded = Title::from_bytes(&data).unwrap();
+        assert_eq!(reloaded.crl(), full.crl());
+        assert_eq!(reloaded.meta(), full.meta());
+        assert_eq!(reloaded.to_wad().unwrap().to_bytes().unwrap(), data);
+    }
+
+    // Builds a boot2 title, which is packed into an "ib" WAD without a Ticket.
+    fn build_boot2_wad(contents: &[Vec<u8>]) -> wad::WAD {
+        let mut title = test_utils::build_title(contents);
+        title.tmd.set_title_id(TitleId::BOOT2).unwrap();
+        let mut wad = title.to_wad().unwrap();
+        wad.set_ticket(&[]);
+        wad
+    }
+
+    #[test]
+    fn test_boot2_without_ticket() {
+        let wad = build_boot2_wad(&[vec![0x42; 0x300]]);
+        let data = wad.to_bytes().unwrap();
+        assert_eq!(&data[4..6], b"ib");
+        let title = Title::from_bytes(&data).unwrap();
+        assert!(!title.has_ticket());
+        assert_eq!(title.ticket.title_id(), TitleId::BOOT2);
+        assert_eq!(title.content.get_enc_content_by_index(0).unwrap(), wad.content());
+        // Repacking should produce the same WAD, still without a Ticket.
+        assert_eq!(title.to_wad().unwrap().to_bytes().unwrap(), data);
+        // A boot2 WAD with a Ticket should still have it loaded.
+        let title = test_utils::build_title(&[vec![0x42; 0x300]]);
+        let mut with_ticket = build_boot2_wad(&[vec![0x42; 0x300]]);
+        with_ticket.set_ticket(&title.ticket.to_bytes().unwrap());
+        assert!(Title::from_wad(&with_ticket).unwrap().has_ticket());
+        // Setting a Ticket on a boot2 title that didn't have one means it gets packed from then on.
+        let mut boot2 = Title::from_bytes(&data).unwrap();
+        boot2.set_ticket(title.ticket.clone());
+        assert!(boot2.has_ticket());
+        assert_eq!(boot2.to_wad().unwrap().ticket(), title.ticket.to_bytes().unwrap());
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_boot2_directory_round_trip() {
+        let data = build_boot2_wad(&[vec![0x42; 0x300]]).to_bytes().unwrap();
+        let title = Title::from_bytes(&data).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        title.to_directory(dir.path()).unwrap();
+        assert!(find_component(dir.path(), "tik", false).unwrap().is_none());
+        assert_eq!(fs::read(dir.path().join("00000000.app")).unwrap(), title.content.get_enc_content_by_index(0).unwrap());
+        let loaded = Title::from_directory(dir.path()).unwrap();
+        assert!(!loaded.has_ticket());
+        assert_eq!(loaded.to_wad().unwrap().to_bytes().unwrap(), data);
+        // Other titles still require a Ticket.
+        let dir = tempfile::tempdir().unwrap();
+        test_utils::build_title(&[vec![0x42; 0x300]]).to_directory(dir.path()).unwrap();
+        fs::remove_file(find_component(dir.path(), "tik", true).unwrap().unwrap()).unwrap();
+        assert!(matches!(Title::from_directory(dir.path()), Err(TitleError::MissingComponent(ext)) if ext == ".tik"));
+    }
+
+    // Builds a DLC-style title where the content at index 1 isn't present, and isn't permitted by
+    // the Ticket.
+    #[test]
+    fn test_dlc_wad_with_missing_contents() {
+        let contents = vec![vec![0x01; 100], vec![0x02; 300], vec![0x03; 50]];
+        let title = test_utils::build_dlc_title(&contents);
+        let wad = title.to_wad().unwrap();
+        // Only the present contents should be packed, each padded to 64 bytes.
+        assert_eq!(wad.content_size(), 128 + 64);
+        assert_eq!(wad.validate(), []);
+        let loaded = Title::from_bytes(&wad.to_bytes().unwrap()).unwrap();
+        assert!(loaded.content.is_content_present(0));
+        assert!(!loaded.content.is_content_present(1));
+        assert!(loaded.content.is_content_present(2));
+        assert_eq!(loaded.get_content_by_index(0).unwrap(), contents[0]);
+        assert_eq!(loaded.get_content_by_index(2).unwrap(), contents[2]);
+        assert!(matches!(loaded.get_content_by_index(1), Err(content::ContentError::ContentNotPresent(1))));
+        assert_eq!(loaded.title_size(SharedContentAccounting::Include).unwrap(), title.metadata_size().unwrap() + 150);
+        assert_eq!(loaded.to_wad().unwrap().to_bytes().unwrap(), wad.to_bytes().unwrap());
+        // Without the Ticket explaining which contents are missing, the WAD can't be parsed.
+        let mut full_ticket = title.ticket.clone();
+        full_ticket.set_content_permitted(1, true).unwrap();
+        let mismatched = wad::WAD::from_parts(&title.cert_chain, &[], &full_ticket, &title.tmd, &title.content, &[]).unwrap();
+        assert!(matches!(Title::from_wad(&mismatched), Err(TitleError::Content(content::ContentError::TruncatedContent { index: 1, .. }))));
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_directory_allow_missing() {
+        let contents = vec![vec![0x01; 100], vec![0x02; 300], vec![0x03; 50]];
+        let title = test_utils::build_dlc_title(&contents);
+        let dir = tempfile::tempdir().unwrap();
+        title.to_directory(dir.path()).unwrap();
+        assert!(!dir.path().join("00000001.app").exists());
+        assert!(matches!(Title::from_directory(dir.path()), Err(TitleError::MissingComponent(name)) if name == "00000001.app"));
+        let loaded = Title::from_directory_with_options(dir.path(), None, true, None).unwrap();
+        assert!(!loaded.content.is_content_present(1));
+        assert_eq!(loaded.to_wad().unwrap().to_bytes().unwrap(), title.to_wad().unwrap().to_bytes().unwrap());
+    }
 }
@@ -7,8 +7,9 @@ use std::str;
 use std::io::Write;
 use reqwest;
 use thiserror::Error;
-use crate::title::{cert, tmd, ticket, content};
+use crate::title::{cert, tmd, ticket, content, TitleId};
 use crate::title;
+use crate::PROGRESS_LOG_TARGET;
 
 const WII_NUS_ENDPOINT: &str = "http://nus.cdn.shop.wii.com/ccs/download/";
 const WII_U_NUS_ENDPOINT: &str = "http://ccs.cdn.wup.shop.nintendo.net/ccs/download/";
@@ -19,6 +20,8 @@ pub enum NUSError {
     InvalidData,
     #[error("the requested Title ID or version could not be found on the NUS")]
     NotFound,
+    #[error("the NUS returned an unexpected response (HTTP status {0})")]
+    HttpStatus(u16),
     #[error("Certificate processing error")]
     Certificate(#[from] cert::CertificateError),
     #[error("TMD processing error")]
@@ -35,6 +38,52 @@ pub enum NUSError {
     IO(#[from] std::io::Error),
 }
 
+#[derive(Debug)]
+/// A TMD downloaded from the NUS. The NUS appends the certificates needed to verify a TMD to the
+/// end of it, so the response is split into the TMD itself and those certificates.
+pub struct NusTmd {
+    /// The TMD, without any appended certificates.
+    pub tmd: Vec<u8>,
+    /// The certificates that were appended to the TMD, in the order that they were appended. This
+    /// is empty if the response only contained a TMD.
+    pub certs: Vec<cert::Certificate>,
+    raw: Vec<u8>,
+}
+
+impl NusTmd {
+    /// Creates a new NusTmd instance from the binary data of a TMD as it's served by the NUS,
+    /// with or without certificates appended to it. Any data following the TMD has to be made up
+    /// of valid certificates.
+    pub fn from_bytes(data: &[u8]) -> Result<NusTmd, NUSError> {
+        // TMDs are always exactly the size of their header and content records, so the TMD ends
+        // where writing it back out does.
+        let tmd_len = tmd::TMD::from_bytes(data).and_then(|tmd| Ok(tmd.to_bytes()?.len())).map_err(|_| NUSError::InvalidData)?;
+        let tmd = data.get(..tmd_len).ok_or(NUSError::InvalidData)?.to_vec();
+        let mut certs: Vec<cert::Certificate> = Vec::new();
+        let mut offset = tmd_len;
+        while offset < data.len() {
+            let cert = cert::Certificate::from_bytes(&data[offset..]).map_err(|_| NUSError::InvalidData)?;
+            offset += cert.to_bytes()?.len();
+            certs.push(cert);
+        }
+        if offset != data.len() {
+            return Err(NUSError::InvalidData);
+        }
+        Ok(NusTmd { tmd, certs, raw: data.to_vec() })
+    }
+
+    /// Gets the certificates that were appended to the TMD, exactly as they were served by the
+    /// NUS.
+    pub fn cert_data(&self) -> &[u8] {
+        &self.raw[self.tmd.len()..]
+    }
+
+    /// Gets the TMD exactly as it was served by the NUS, including any appended certificates.
+    pub fn raw(&self) -> &[u8] {
+        &self.raw
+    }
+}
+
 /// Downloads the retail certificate chain from the NUS.
 pub fn download_cert_chain(wiiu_endpoint: bool) -> Result<Vec<u8>, NUSError> {
     // To build the certificate chain, we need to download both the TMD and Ticket of a title. For
@@ -47,9 +96,8 @@ pub fn download_cert_chain(wiiu_endpoint: bool) -> Result<Vec<u8>, NUSError> {
     };
     let tmd_url = format!("{}0000000100000002/tmd.513", endpoint_url);
     let tik_url = format!("{}0000000100000002/cetk", endpoint_url);
-    let client = reqwest::blocking::Client::new();
-    let tmd = client.get(tmd_url).header(reqwest::header::USER_AGENT, "wii libnup/1.0").send()?.bytes()?;
-    let tik = client.get(tik_url).header(reqwest::header::USER_AGENT, "wii libnup/1.0").send()?.bytes()?;
+    let tmd = nus_get(&tmd_url)?;
+    let tik = nus_get(&tik_url)?;
     // Assemble the certificate chain.
     let mut cert_chain: Vec<u8> = Vec::new();
     // Certificate Authority data.
@@ -62,7 +110,7 @@ pub fn download_cert_chain(wiiu_endpoint: bool) -> Result<Vec<u8>, NUSError> {
 }
 
 /// Downloads a specified content file from the specified title from the NUS.
-pub fn download_content(title_id: [u8; 8], content_id: u32, wiiu_endpoint: bool) -> Result<Vec<u8>, NUSError> {
+pub fn download_content(title_id: TitleId, content_id: u32, wiiu_endpoint: bool) -> Result<Vec<u8>, NUSError> {
     // Build the download URL. The structure is download/<TID>/<CID>
     let endpoint_url = if wiiu_endpoint {
         WII_U_NUS_ENDPOINT.to_owned()
@@ -70,72 +118,221 @@ pub fn download_content(title_id: [u8; 8], content_id: u32, wiiu_endpoint: bool)
         WII_NUS_ENDPOINT.to_owned()
     };
     let content_url = format!("{}{}/{:08X}", endpoint_url, &hex::encode(title_id), content_id);
-    let client = reqwest::blocking::Client::new();
-    let response = client.get(content_url).header(reqwest::header::USER_AGENT, "wii libnup/1.0").send()?;
-    if !response.status().is_success() {
-        return Err(NUSError::NotFound);
-    }
-    Ok(response.bytes()?.to_vec())
+    nus_get(&content_url)
 }
 
 /// Downloads all contents from the specified title from the NUS.
 pub fn download_contents(tmd: &tmd::TMD, wiiu_endpoint: bool) -> Result<Vec<Vec<u8>>, NUSError> {
-    let content_ids: Vec<u32> = tmd.content_records.borrow().iter().map(|record| { record.content_id }).collect();
+    let content_ids: Vec<u32> = tmd.content_records.read().iter().map(|record| { record.content_id }).collect();
     let mut contents: Vec<Vec<u8>> = Vec::new();
-    for id in content_ids {
-        contents.push(download_content(tmd.title_id(), id, wiiu_endpoint)?);
+    for (i, id) in content_ids.iter().enumerate() {
+        log::info!(target: PROGRESS_LOG_TARGET, "downloading content {} of {} (Content ID: {:08X})", i + 1, content_ids.len(), id);
+        contents.push(download_content(tmd.title_id(), *id, wiiu_endpoint)?);
     }
     Ok(contents)
 }
 
-/// Downloads the Ticket for a specified Title ID from the NUS, if it's available.
-pub fn download_ticket(title_id: [u8; 8], wiiu_endpoint: bool) -> Result<Vec<u8>, NUSError> {
-    // Build the download URL. The structure is download/<TID>/cetk.
+/// Downloads the Ticket for a specified Title ID from the NUS, if it's available. The certificates
+/// that the NUS appends to the Ticket are removed. Most titles don't have a common Ticket available,
+/// in which case NUSError::NotFound is returned.
+pub fn download_ticket(title_id: TitleId, wiiu_endpoint: bool) -> Result<Vec<u8>, NUSError> {
+    ticket_from_cetk(&download_cetk(title_id, wiiu_endpoint)?)
+}
+
+/// Downloads the cetk for a specified Title ID from the NUS, if it's available. This is the Ticket
+/// exactly as it's served by the NUS, with the certificates needed to verify it appended.
+pub fn download_cetk(title_id: TitleId, wiiu_endpoint: bool) -> Result<Vec<u8>, NUSError> {
     let endpoint_url = if wiiu_endpoint {
-        WII_U_NUS_ENDPOINT.to_owned()
+        WII_U_NUS_ENDPOINT
     } else {
-        WII_NUS_ENDPOINT.to_owned()
+        WII_NUS_ENDPOINT
     };
+    download_cetk_from(endpoint_url, title_id)
+}
+
+// Downloads the cetk for a specified Title ID from the provided NUS endpoint.
+fn download_cetk_from(endpoint_url: &str, title_id: TitleId) -> Result<Vec<u8>, NUSError> {
+    // Build the download URL. The structure is download/<TID>/cetk.
     let tik_url = format!("{}{}/cetk", endpoint_url, &hex::encode(title_id));
-    let client = reqwest::blocking::Client::new();
-    let response = client.get(tik_url).header(reqwest::header::USER_AGENT, "wii libnup/1.0").send()?;
-    if !response.status().is_success() {
-        return Err(NUSError::NotFound);
+    nus_get(&tik_url)
+}
+
+/// Gets the Ticket stored at the start of a cetk downloaded from the NUS, without the certificates
+/// that follow it. Both v0 Tickets and the v1 Tickets used by the Wii U are supported.
+pub fn ticket_from_cetk(cetk: &[u8]) -> Result<Vec<u8>, NUSError> {
+    let signature_type = cetk.get(0..4).map(|data| u32::from_be_bytes(data.try_into().unwrap())).ok_or(NUSError::InvalidData)?;
+    let key_type = cert::CertificateKeyType::from_signature_type(signature_type).ok_or(NUSError::InvalidData)?;
+    // The Ticket's body starts with its issuer and ECDH data, followed by its version.
+    let body_offset = 4 + key_type.signature_len() + key_type.signature_padding_len();
+    let ticket_version = *cetk.get(body_offset + 0x7C).ok_or(NUSError::InvalidData)?;
+    let v0_len = body_offset + 0x164;
+    let ticket_len = match ticket_version {
+        0 => v0_len,
+        // v1 Tickets are followed by an extra section, with its total size stored after its own
+        // version and header size.
+        1 => {
+            let v1_size = cetk.get(v0_len + 4..v0_len + 8).map(|data| u32::from_be_bytes(data.try_into().unwrap())).ok_or(NUSError::InvalidData)?;
+            v0_len + v1_size as usize
+        },
+        _ => return Err(NUSError::InvalidData),
+    };
+    let tik = cetk.get(..ticket_len).ok_or(NUSError::InvalidData)?;
+    // Make sure that v0 Tickets can actually be parsed, since they're the only ones that rustii
+    // can use.
+    if ticket_version == 0 {
+        ticket::Ticket::from_bytes(tik).map_err(|_| NUSError::InvalidData)?;
     }
-    let tik = ticket::Ticket::from_bytes(&response.bytes()?).map_err(|_| NUSError::InvalidData)?;
-    tik.to_bytes().map_err(|_| NUSError::InvalidData)
+    Ok(tik.to_vec())
 }
 
 /// Downloads an entire title with all of its content from the NUS and returns a Title instance.
-pub fn download_title(title_id: [u8; 8], title_version: Option<u16>, wiiu_endpoint: bool) -> Result<title::Title, NUSError> {
+pub fn download_title(title_id: TitleId, title_version: Option<u16>, wiiu_endpoint: bool) -> Result<title::Title, NUSError> {
     // Download the individual components of a title and then build a title from them.
+    log::info!(target: PROGRESS_LOG_TARGET, "downloading TMD and Ticket for title {}", title_id);
     let cert_chain = cert::CertificateChain::from_bytes(&download_cert_chain(wiiu_endpoint)?)?;
-    let tmd = tmd::TMD::from_bytes(&download_tmd(title_id, title_version, wiiu_endpoint)?)?;
+    let tmd = tmd::TMD::from_bytes(&download_tmd(title_id, title_version, wiiu_endpoint)?.tmd)?;
     let tik = ticket::Ticket::from_bytes(&download_ticket(title_id, wiiu_endpoint)?)?;
     let content_region = content::ContentRegion::from_contents(download_contents(&tmd, wiiu_endpoint)?, tmd.content_records.clone())?;
     let title = title::Title::from_parts(cert_chain, None, tik, tmd, content_region, None)?;
     Ok(title)
 }
 
-/// Downloads the TMD for a specified Title ID from the NUS.
-pub fn download_tmd(title_id: [u8; 8], title_version: Option<u16>, wiiu_endpoint: bool) -> Result<Vec<u8>, NUSError> {
-    // Build the download URL. The structure is download/<TID>/tmd for latest and 
-    // download/<TID>/tmd.<version> for when a specific version is requested.
+/// Downloads the TMD for a specified Title ID from the NUS, split into the TMD itself and the
+/// certificates that the NUS appends to it.
+pub fn download_tmd(title_id: TitleId, title_version: Option<u16>, wiiu_endpoint: bool) -> Result<NusTmd, NUSError> {
     let endpoint_url = if wiiu_endpoint {
-        WII_U_NUS_ENDPOINT.to_owned()
+        WII_U_NUS_ENDPOINT
     } else {
-        WII_NUS_ENDPOINT.to_owned()
+        WII_NUS_ENDPOINT
     };
-    let tmd_url = if title_version.is_some() {
-        format!("{}{}/tmd.{}", endpoint_url, &hex::encode(title_id), title_version.unwrap())
+    download_tmd_from(endpoint_url, title_id, title_version)
+}
+
+// Downloads the TMD for a specified Title ID from the provided NUS endpoint.
+fn download_tmd_from(endpoint_url: &str, title_id: TitleId, title_version: Option<u16>) -> Result<NusTmd, NUSError> {
+    // Build the download URL. The structure is download/<TID>/tmd for latest and
+    // download/<TID>/tmd.<version> for when a specific version is requested.
+    let tmd_url = if let Some(title_version) = title_version {
+        format!("{}{}/tmd.{}", endpoint_url, &hex::encode(title_id), title_version)
     } else {
         format!("{}{}/tmd", endpoint_url, &hex::encode(title_id))
     };
+    NusTmd::from_bytes(&nus_get(&tmd_url)?)
+}
+
+// Downloads the data at the provided NUS URL. The NUS responds with a 404 for titles, versions, and
+// Tickets that aren't available, which is reported as NUSError::NotFound so that it can be told
+// apart from other failed requests.
+fn nus_get(url: &str) -> Result<Vec<u8>, NUSError> {
     let client = reqwest::blocking::Client::new();
-    let response = client.get(tmd_url).header(reqwest::header::USER_AGENT, "wii libnup/1.0").send()?;
-    if !response.status().is_success() {
-        return Err(NUSError::NotFound);
+    let response = client.get(url).header(reqwest::header::USER_AGENT, "wii libnup/1.0").send()?;
+    match response.status() {
+        reqwest::StatusCode::NOT_FOUND => Err(NUSError::NotFound),
+        status if !status.is_success() => Err(NUSError::HttpStatus(status.as_u16())),
+        _ => Ok(response.bytes()?.to_vec()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use crate::title::test_utils;
+
+    // Starts a local HTTP server that stands in for the NUS, returning its endpoint URL. Requests
+    // are answered with the status and body registered for the end of their path, or with a 404.
+    fn serve_fixture(responses: Vec<(String, u16, Vec<u8>)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/ccs/download/", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                // Skip the rest of the request's headers.
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+                let (status, body) = responses.iter()
+                    .find(|(suffix, _, _)| path.ends_with(suffix.as_str()))
+                    .map(|(_, status, body)| (*status, body.clone()))
+                    .unwrap_or((404, Vec::new()));
+                write!(stream, "HTTP/1.1 {} Fixture\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, body.len()).unwrap();
+                stream.write_all(&body).unwrap();
+            }
+        });
+        endpoint
+    }
+
+    #[test]
+    fn test_download_cetk() {
+        let tik = test_utils::build_ticket(test_utils::TEST_TITLE_ID, test_utils::TEST_TITLE_KEY);
+        let mut cetk = tik.clone();
+        cetk.extend(test_utils::build_cert_chain());
+        let title_id = TitleId::new(test_utils::TEST_TITLE_ID);
+        let endpoint = serve_fixture(vec![
+            (format!("{}/cetk", hex::encode(title_id)), 200, cetk.clone()),
+            (String::from("0000000100000002/cetk"), 500, Vec::new()),
+        ]);
+        let downloaded = download_cetk_from(&endpoint, title_id).unwrap();
+        assert_eq!(downloaded, cetk);
+        assert_eq!(ticket_from_cetk(&downloaded).unwrap(), tik);
+        // Titles without a common Ticket should be told apart from other failures.
+        assert!(matches!(download_cetk_from(&endpoint, TitleId::new([0x00, 0x01, 0x00, 0x01, 0x48, 0x41, 0x44, 0x45])), Err(NUSError::NotFound)));
+        assert!(matches!(download_cetk_from(&endpoint, TitleId::new([0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02])), Err(NUSError::HttpStatus(500))));
+        // Nothing is listening once the listener has been dropped, so the request itself fails.
+        let closed = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/ccs/download/", closed.local_addr().unwrap());
+        drop(closed);
+        assert!(matches!(download_cetk_from(&endpoint, title_id), Err(NUSError::Request(_))));
+    }
+
+    #[test]
+    fn test_download_tmd() {
+        let tmd = test_utils::build_tmd(test_utils::TEST_TITLE_ID);
+        let cert_chain = test_utils::build_cert_chain();
+        // The NUS appends the TMD's certificate followed by the CA certificate.
+        let mut with_certs = tmd.clone();
+        with_certs.extend_from_slice(&cert_chain[0x300..0x600]);
+        with_certs.extend_from_slice(&cert_chain[..0x300]);
+        let title_id = TitleId::new(test_utils::TEST_TITLE_ID);
+        let endpoint = serve_fixture(vec![
+            (format!("{}/tmd", hex::encode(title_id)), 200, with_certs.clone()),
+            (format!("{}/tmd.1", hex::encode(title_id)), 200, tmd.clone()),
+        ]);
+        let downloaded = download_tmd_from(&endpoint, title_id, None).unwrap();
+        assert_eq!(downloaded.tmd, tmd);
+        assert_eq!(downloaded.raw(), with_certs);
+        assert_eq!(downloaded.cert_data(), &with_certs[tmd.len()..]);
+        let identities: Vec<String> = downloaded.certs.iter().map(|cert| cert.child_cert_identity()).collect();
+        assert_eq!(identities, ["CP00000004", "CA00000001"]);
+        // A TMD without appended certificates is returned as-is.
+        let downloaded = download_tmd_from(&endpoint, title_id, Some(1)).unwrap();
+        assert_eq!(downloaded.tmd, tmd);
+        assert!(downloaded.certs.is_empty());
+        assert!(downloaded.cert_data().is_empty());
+        assert!(matches!(download_tmd_from(&endpoint, title_id, Some(2)), Err(NUSError::NotFound)));
+        // Anything after the TMD that isn't a whole certificate is rejected.
+        assert!(matches!(NusTmd::from_bytes(&with_certs[..with_certs.len() - 0x10]), Err(NUSError::InvalidData)));
+        let mut trailing = tmd.clone();
+        trailing.extend_from_slice(&[0x5A; 0x40]);
+        assert!(matches!(NusTmd::from_bytes(&trailing), Err(NUSError::InvalidData)));
+    }
+
+    #[test]
+    fn test_ticket_from_cetk() {
+        let mut tik = test_utils::build_ticket(test_utils::TEST_TITLE_ID, test_utils::TEST_TITLE_KEY);
+        // Make a v1 Ticket, which has an extra section after the usual Ticket data.
+        tik[0x1BC] = 1;
+        tik.extend_from_slice(&[0x00, 0x01, 0x00, 0x14, 0x00, 0x00, 0x00, 0x14]);
+        tik.resize(0x2A4 + 0x14, 0);
+        let mut cetk = tik.clone();
+        cetk.extend(test_utils::build_cert_chain());
+        assert_eq!(ticket_from_cetk(&cetk).unwrap(), tik);
+        assert!(matches!(ticket_from_cetk(&cetk[..0x2A8]), Err(NUSError::InvalidData)));
+        assert!(matches!(ticket_from_cetk(&[0x00; 0x10]), Err(NUSError::InvalidData)));
     }
-    let tmd = tmd::TMD::from_bytes(&response.bytes()?).map_err(|_| NUSError::InvalidData)?;
-    tmd.to_bytes().map_err(|_| NUSError::InvalidData)
 }
@@ -0,0 +1,233 @@
+// title/signed.rs from rustii (c) 2025 NinjaCheetah & Contributors
+// https://github.com/NinjaCheetah/rustii
+//
+// Implements identifying and loading any signed Wii file (WADs, TMDs, and Tickets) from its data.
+
+use std::{fmt, str};
+use thiserror::Error;
+use crate::title::{cert, ticket, tmd, wad};
+
+// Sizes and offsets in TMDs and Tickets, relative to the signature issuer so that they apply
+// regardless of the type of signature.
+const TMD_NUM_CONTENTS_OFFSET: usize = 0x9E;
+const TMD_HEADER_SIZE: usize = 0xA4;
+const TMD_RECORD_SIZE: usize = 36;
+const TICKET_SIZE: usize = 0x164;
+
+#[derive(Debug, Error)]
+pub enum SignedFileError {
+    #[error("the data is not a WAD, TMD, or Ticket")]
+    UnknownFormat,
+    #[error("TMD processing error")]
+    TMD(#[from] tmd::TMDError),
+    #[error("Ticket processing error")]
+    Ticket(#[from] ticket::TicketError),
+    #[error("WAD processing error")]
+    WAD(#[from] wad::WADError),
+    #[error("signed file data is not in a valid format")]
+    IO(#[from] std::io::Error),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The types of file that a SignedFile can hold.
+pub enum SignedFileType {
+    Wad,
+    Tmd,
+    Ticket,
+}
+
+impl fmt::Display for SignedFileType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SignedFileType::Wad => write!(f, "WAD"),
+            SignedFileType::Tmd => write!(f, "TMD"),
+            SignedFileType::Ticket => write!(f, "Ticket"),
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Any signed Wii file, identified by its structure rather than by its name. WADs are boxed, since
+/// they're much larger than TMDs and Tickets.
+pub enum SignedFile {
+    Wad(Box<wad::WAD>),
+    Tmd(tmd::TMD),
+    Ticket(ticket::Ticket),
+}
+
+impl SignedFile {
+    /// Identifies the type of signed file that the provided data contains, without fully parsing
+    /// it. Returns None if the data doesn't look like a WAD, TMD, or Ticket.
+    pub fn identify(data: &[u8]) -> Option<SignedFileType> {
+        // WADs don't have a proper magic number, but they do start with a small header size and one
+        // of two types. TMDs and Tickets start with a signature type instead, which is much larger.
+        let header_size = u32::from_be_bytes(data.get(0..4)?.try_into().ok()?);
+        if (0x20..0x10000).contains(&header_size) && matches!(data.get(4..6), Some(b"Is") | Some(b"ib")) {
+            return Some(SignedFileType::Wad);
+        }
+        // TMDs and Tickets both start with a signature, and the type of certificate that the
+        // signature was issued by is the most reliable way to tell them apart.
+        let key_type = cert::CertificateKeyType::from_signature_type(header_size)?;
+        let issuer_offset = 4 + key_type.signature_len() + key_type.signature_padding_len();
+        let issuer = data.get(issuer_offset..issuer_offset + 64)?;
+        let issuer = str::from_utf8(&issuer[..issuer.iter().position(|&c| c == 0).unwrap_or(64)]).unwrap_or_default();
+        if issuer.contains("-CP") {
+            return Some(SignedFileType::Tmd);
+        } else if issuer.contains("-XS") {
+            return Some(SignedFileType::Ticket);
+        }
+        // Files with an unknown issuer can still be identified by their size, since a TMD is
+        // always sized to fit its content records exactly, and a Ticket never is.
+        let num_contents_offset = issuer_offset + TMD_NUM_CONTENTS_OFFSET;
+        let num_contents = u16::from_be_bytes(data.get(num_contents_offset..num_contents_offset + 2)?.try_into().ok()?) as usize;
+        if data.len() == issuer_offset + TMD_HEADER_SIZE + num_contents * TMD_RECORD_SIZE {
+            Some(SignedFileType::Tmd)
+        } else if data.len() >= issuer_offset + TICKET_SIZE {
+            Some(SignedFileType::Ticket)
+        } else {
+            None
+        }
+    }
+
+    /// Creates a new SignedFile instance from the binary data of a WAD, TMD, or Ticket. For WADs,
+    /// the TMD and Ticket inside the WAD are also checked to make sure that they can be parsed.
+    pub fn from_bytes(data: &[u8]) -> Result<SignedFile, SignedFileError> {
+        match SignedFile::identify(data) {
+            Some(SignedFileType::Wad) => {
+                let wad = wad::WAD::from_bytes(data)?;
+                wad.parsed_tmd()?;
+                // boot2 WADs sometimes don't include a Ticket, so only check it if it's there.
+                if !wad.ticket().is_empty() {
+                    wad.parsed_ticket()?;
+                }
+                Ok(SignedFile::Wad(Box::new(wad)))
+            },
+            Some(SignedFileType::Tmd) => Ok(SignedFile::Tmd(tmd::TMD::from_bytes(data)?)),
+            Some(SignedFileType::Ticket) => Ok(SignedFile::Ticket(ticket::Ticket::from_bytes(data)?)),
+            None => Err(SignedFileError::UnknownFormat),
+        }
+    }
+
+    /// Dumps the data in a SignedFile back into binary data that can be written to a file.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SignedFileError> {
+        match self {
+            SignedFile::Wad(wad) => Ok(wad.to_bytes()?),
+            SignedFile::Tmd(tmd) => Ok(tmd.to_bytes()?),
+            SignedFile::Ticket(ticket) => Ok(ticket.to_bytes()?),
+        }
+    }
+
+    /// Gets the type of file that a SignedFile holds.
+    pub fn file_type(&self) -> SignedFileType {
+        match self {
+            SignedFile::Wad(_) => SignedFileType::Wad,
+            SignedFile::Tmd(_) => SignedFileType::Tmd,
+            SignedFile::Ticket(_) => SignedFileType::Ticket,
+        }
+    }
+
+    /// Gets whether a SignedFile is fakesigned using the strncmp (trucha) bug or not. A WAD is
+    /// only fakesigned if both its TMD and Ticket are.
+    pub fn is_fakesigned(&self) -> bool {
+        match self {
+            SignedFile::Wad(wad) => {
+                wad.parsed_tmd().is_ok_and(|tmd| tmd.is_fakesigned()) &&
+                    (wad.ticket().is_empty() || wad.parsed_ticket().is_ok_and(|ticket| ticket.is_fakesigned()))
+            },
+            SignedFile::Tmd(tmd) => tmd.is_fakesigned(),
+            SignedFile::Ticket(ticket) => ticket.is_fakesigned(),
+        }
+    }
+
+    /// Fakesigns a SignedFile for use with the strncmp (trucha) bug. For WADs, the TMD and Ticket
+    /// are fakesigned in place, leaving the rest of the WAD untouched.
+    pub fn fakesign(&mut self) -> Result<(), SignedFileError> {
+        match self {
+            SignedFile::Wad(wad) => {
+                let mut tmd = wad.parsed_tmd()?;
+                tmd.fakesign()?;
+                wad.set_tmd(&tmd.to_bytes()?);
+                if !wad.ticket().is_empty() {
+                    let mut ticket = wad.parsed_ticket()?;
+                    ticket.fakesign()?;
+                    wad.set_ticket(&ticket.to_bytes()?);
+                }
+            },
+            SignedFile::Tmd(tmd) => tmd.fakesign()?,
+            SignedFile::Ticket(ticket) => ticket.fakesign()?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::title::test_utils::*;
+
+    fn build_wad() -> Vec<u8> {
+        build_title(&[vec![0x11; 0x40]]).to_wad_bytes().unwrap()
+    }
+
+    #[test]
+    fn test_identify() {
+        assert_eq!(SignedFile::identify(&build_wad()), Some(SignedFileType::Wad));
+        assert_eq!(SignedFile::identify(&build_tmd(TEST_TITLE_ID)), Some(SignedFileType::Tmd));
+        assert_eq!(SignedFile::identify(&build_ticket(TEST_TITLE_ID, TEST_TITLE_KEY)), Some(SignedFileType::Ticket));
+        // Other signature types move the issuer, but shouldn't change how the file is identified.
+        for signature_type in [0x00010000, 0x00010002] {
+            assert_eq!(SignedFile::identify(&with_signature_type(&build_tmd(TEST_TITLE_ID), signature_type)), Some(SignedFileType::Tmd));
+            assert_eq!(SignedFile::identify(&with_signature_type(&build_ticket(TEST_TITLE_ID, TEST_TITLE_KEY), signature_type)), Some(SignedFileType::Ticket));
+        }
+        // Without a recognizable issuer, the file's size is used instead.
+        let mut tmd = build_tmd(TEST_TITLE_ID);
+        tmd[0x140..0x180].fill(0);
+        assert_eq!(SignedFile::identify(&tmd), Some(SignedFileType::Tmd));
+        let mut ticket = build_ticket(TEST_TITLE_ID, TEST_TITLE_KEY);
+        ticket[0x140..0x180].fill(0);
+        assert_eq!(SignedFile::identify(&ticket), Some(SignedFileType::Ticket));
+        assert_eq!(SignedFile::identify(&[]), None);
+        assert_eq!(SignedFile::identify(b"\x55\xAA\x38\x2D"), None);
+        assert_eq!(SignedFile::identify(&[0u8; 0x400]), None);
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        for (data, file_type) in [
+            (build_wad(), SignedFileType::Wad),
+            (build_tmd(TEST_TITLE_ID), SignedFileType::Tmd),
+            (build_ticket(TEST_TITLE_ID, TEST_TITLE_KEY), SignedFileType::Ticket),
+        ] {
+            let file = SignedFile::from_bytes(&data).unwrap();
+            assert_eq!(file.file_type(), file_type);
+            assert_eq!(file.to_bytes().unwrap(), data);
+        }
+        assert!(matches!(SignedFile::from_bytes(&[0x5A; 0x100]), Err(SignedFileError::UnknownFormat)));
+        // A WAD with a TMD that can't be parsed isn't loaded.
+        let mut wad = build_wad();
+        wad[0xC00..0xC04].copy_from_slice(&0xFFFFFFFFu32.to_be_bytes());
+        assert!(matches!(SignedFile::from_bytes(&wad), Err(SignedFileError::WAD(_))));
+    }
+
+    #[test]
+    fn test_fakesign() {
+        for data in [build_wad(), build_tmd(TEST_TITLE_ID), build_ticket(TEST_TITLE_ID, TEST_TITLE_KEY)] {
+            let mut file = SignedFile::from_bytes(&data).unwrap();
+            assert!(!file.is_fakesigned());
+            file.fakesign().unwrap();
+            assert!(file.is_fakesigned());
+            // Fakesigning has to survive being written out and read back in.
+            let file = SignedFile::from_bytes(&file.to_bytes().unwrap()).unwrap();
+            assert!(file.is_fakesigned());
+        }
+        // Fakesigning a WAD only changes its TMD and Ticket.
+        let data = build_wad();
+        let mut file = SignedFile::from_bytes(&data).unwrap();
+        file.fakesign().unwrap();
+        let SignedFile::Wad(fakesigned) = file else { panic!("expected a WAD") };
+        let original = wad::WAD::from_bytes(&data).unwrap();
+        assert_eq!(fakesigned.cert_chain(), original.cert_chain());
+        assert_eq!(fakesigned.content(), original.content());
+        assert_ne!(fakesigned.tmd(), original.tmd());
+    }
+}
@@ -0,0 +1,215 @@
+// title/test_utils.rs from rustii (c) 2025 NinjaCheetah & Contributors
+// https://github.com/NinjaCheetah/rustii
+//
+// Helpers for building synthetic title components for use in tests, since real Wii files can't be
+// distributed alongside the library.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use sha1::{Digest, Sha1};
+use crate::title::{cert, content, crypto, ticket, tmd, Title, TitleId};
+
+pub const TEST_TITLE_ID: [u8; 8] = [0x00, 0x01, 0x00, 0x01, 0x52, 0x53, 0x54, 0x49];
+pub const TEST_TITLE_KEY: [u8; 16] = [0x5A; 16];
+
+fn signing_string(value: &str) -> [u8; 64] {
+    let mut bytes = value.as_bytes().to_vec();
+    bytes.resize(64, 0);
+    bytes.try_into().unwrap()
+}
+
+/// Builds the binary data for an RSA-2048 signed certificate containing an RSA-2048 key.
+pub fn build_cert(issuer: &str, identity: &str) -> Vec<u8> {
+    let mut buf: Vec<u8> = Vec::new();
+    buf.extend_from_slice(&0x00010001u32.to_be_bytes());
+    buf.resize(0x140, 0);
+    buf.extend_from_slice(&signing_string(issuer));
+    buf.extend_from_slice(&0x00000001u32.to_be_bytes());
+    buf.extend_from_slice(&signing_string(identity));
+    buf.extend_from_slice(&[0u8; 4]);
+    buf.extend_from_slice(&[0xFFu8; 256]);
+    buf.extend_from_slice(&0x00010001u32.to_be_bytes());
+    buf.resize((buf.len() + 63) & !63, 0);
+    buf
+}
+
+/// Builds the binary data for a certificate chain containing a CA, TMD, and Ticket certificate.
+pub fn build_cert_chain() -> Vec<u8> {
+    let mut buf = build_cert("Root", "CA00000001");
+    buf.extend(build_cert("Root-CA00000001", "CP00000004"));
+    buf.extend(build_cert("Root-CA00000001", "XS00000003"));
+    buf
+}
+
+/// Builds the binary data for a TMD with no content records.
+pub fn build_tmd(title_id: [u8; 8]) -> Vec<u8> {
+    let mut buf = vec![0u8; 0x1E4];
+    buf[0..4].copy_from_slice(&0x00010001u32.to_be_bytes());
+    buf[0x140..0x180].copy_from_slice(&signing_string("Root-CA00000001-CP00000004"));
+    buf[0x184..0x18C].copy_from_slice(&[0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x38]);
+    buf[0x18C..0x194].copy_from_slice(&title_id);
+    buf[0x194..0x198].copy_from_slice(&0x00000001u32.to_be_bytes());
+    buf
+}
+
+/// Builds the binary data for a v0 Ticket containing the provided decrypted Title Key.
+pub fn build_ticket(title_id: [u8; 8], title_key: [u8; 16]) -> Vec<u8> {
+    let mut buf = vec![0u8; 0x2A4];
+    buf[0..4].copy_from_slice(&0x00010001u32.to_be_bytes());
+    buf[0x140..0x180].copy_from_slice(&signing_string("Root-CA00000001-XS00000003"));
+    buf[0x1BF..0x1CF].copy_from_slice(&crypto::encrypt_title_key(title_key, 0, title_id, false));
+    buf[0x1DC..0x1E4].copy_from_slice(&title_id);
+    buf
+}
+
+/// Overwrites the common key index stored in the binary data of an RSA-2048 signed Ticket without
+/// re-encrypting the Title Key, which is how Tickets with junk indices end up in the wild.
+pub fn with_common_key_index(data: &[u8], common_key_index: u8) -> Vec<u8> {
+    let mut buf = data.to_vec();
+    buf[0x1F1] = common_key_index;
+    buf
+}
+
+/// Converts the binary data of an RSA-2048 signed TMD or Ticket to use a different signature type,
+/// resizing the signature and its padding to match.
+pub fn with_signature_type(data: &[u8], signature_type: u32) -> Vec<u8> {
+    let key_type = cert::CertificateKeyType::from_signature_type(signature_type).unwrap();
+    let mut buf = signature_type.to_be_bytes().to_vec();
+    buf.extend(vec![0xA5u8; key_type.signature_len()]);
+    buf.extend(vec![0u8; key_type.signature_padding_len()]);
+    buf.extend_from_slice(&data[0x140..]);
+    buf
+}
+
+/// Builds a meta/footer containing only an IMET header, with the channel name "Footer Channel".
+pub fn build_imet_footer() -> Vec<u8> {
+    let mut footer = vec![0u8; 0x40];
+    footer.extend_from_slice(b"IMET");
+    footer.extend_from_slice(&0x600u32.to_be_bytes());
+    footer.extend_from_slice(&3u32.to_be_bytes());
+    footer.resize(0x5C, 0);
+    for _ in 0..10 {
+        let mut encoded: Vec<u8> = "Footer Channel".encode_utf16().flat_map(|c| c.to_be_bytes()).collect();
+        encoded.resize(84, 0);
+        footer.extend(encoded);
+    }
+    footer.resize(0x600, 0);
+    footer
+}
+
+/// Builds a meta/footer with a build tag recording the provided timestamp and builder, followed by
+/// an IMET header.
+pub fn build_tagged_footer(timestamp: &str, builder: &str) -> Vec<u8> {
+    let mut footer = timestamp.as_bytes().to_vec();
+    footer.resize(0x20, 0);
+    footer.extend_from_slice(builder.as_bytes());
+    footer.resize(0x40, 0);
+    footer.extend(build_imet_footer());
+    footer
+}
+
+/// Builds a complete Title with the provided decrypted contents, assigned indices starting from 0.
+pub fn build_title(contents: &[Vec<u8>]) -> Title {
+    let indexed: Vec<(u16, Vec<u8>)> = contents.iter().enumerate().map(|(i, data)| (i as u16, data.clone())).collect();
+    build_title_with_indices(&indexed)
+}
+
+/// Builds a complete Title with the provided decrypted contents, each stored with the index it's
+/// paired with. Content IDs are assigned in order starting from 0x10.
+pub fn build_title_with_indices(contents: &[(u16, Vec<u8>)]) -> Title {
+    let cert_chain = cert::CertificateChain::from_bytes(&build_cert_chain()).unwrap();
+    let ticket = ticket::Ticket::from_bytes(&build_ticket(TEST_TITLE_ID, TEST_TITLE_KEY)).unwrap();
+    let tmd = tmd::TMD::from_bytes(&build_tmd(TEST_TITLE_ID)).unwrap();
+    let mut content_region = content::ContentRegion::new(tmd.content_records.clone()).unwrap();
+    for (i, (index, data)) in contents.iter().enumerate() {
+        let mut hasher = Sha1::new();
+        hasher.update(data);
+        let hash: [u8; 20] = hasher.finalize().into();
+        let enc = crypto::encrypt_content(data, TEST_TITLE_KEY, *index, data.len() as u64);
+        content_region.add_enc_content(&enc, *index, 0x10 + i as u32, tmd::ContentType::Normal, data.len() as u64, hash).unwrap();
+    }
+    Title::from_parts(cert_chain, None, ticket, tmd, content_region, None).unwrap()
+}
+
+/// Builds a DLC title with the provided decrypted contents, where the content at index 1 hasn't
+/// been purchased. Its Ticket doesn't permit that content, and the content isn't present.
+pub fn build_dlc_title(contents: &[Vec<u8>]) -> Title {
+    let mut title = build_title(contents);
+    title.set_title_id(TitleId::from_parts([0x00, 0x01, 0x00, 0x05], *b"RSTD")).unwrap();
+    title.content.contents[1] = None;
+    title.ticket.permit_all_contents();
+    title.ticket.set_content_permitted(1, false).unwrap();
+    title
+}
+
+#[cfg(all(feature = "fs", feature = "serde"))]
+/// Writes a title out to a directory in the same way that it would be downloaded from the NUS,
+/// returning the manifest that describes it. The Ticket is never written, so that tests can choose
+/// whether to add it.
+pub fn write_nus_download(title: &Title, path: &std::path::Path, encrypted: bool) -> crate::title::manifest::TitleManifest {
+    let manifest = crate::title::manifest::TitleManifest::new(&title.tmd, if encrypted { None } else { Some(&title.ticket) });
+    manifest.write(path).unwrap();
+    std::fs::write(path.join(&manifest.tmd), title.tmd.to_bytes().unwrap()).unwrap();
+    std::fs::write(path.join(&manifest.cert_chain), title.cert_chain.to_bytes().unwrap()).unwrap();
+    for (i, content) in manifest.contents.iter().enumerate() {
+        let data = if encrypted {
+            title.content.get_enc_content_by_index(i).unwrap()
+        } else {
+            title.get_content_by_index(i).unwrap()
+        };
+        std::fs::write(path.join(&content.file), data).unwrap();
+    }
+    manifest
+}
+
+thread_local! {
+    static ALLOCATED: Cell<isize> = const { Cell::new(0) };
+    static PEAK_ALLOCATED: Cell<isize> = const { Cell::new(0) };
+}
+
+// An allocator that keeps track of how much memory each thread has allocated, so that tests can
+// check the peak memory usage of an operation without being affected by tests on other threads.
+struct TrackingAllocator;
+
+fn track_allocation(change: isize) {
+    let _ = ALLOCATED.try_with(|allocated| {
+        allocated.set(allocated.get() + change);
+        let _ = PEAK_ALLOCATED.try_with(|peak| peak.set(peak.get().max(allocated.get())));
+    });
+}
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            track_allocation(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        track_allocation(-(layout.size() as isize));
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            track_allocation(new_size as isize - layout.size() as isize);
+        }
+        new_ptr
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: TrackingAllocator = TrackingAllocator;
+
+/// Runs the provided function and returns its result alongside the most memory that was allocated
+/// by the current thread at any point while it ran, relative to when it started.
+pub fn measure_peak_allocation<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let start = ALLOCATED.with(|allocated| allocated.get());
+    PEAK_ALLOCATED.with(|peak| peak.set(start));
+    let result = f();
+    let peak = PEAK_ALLOCATED.with(|peak| peak.get());
+    (result, (peak - start) as usize)
+}
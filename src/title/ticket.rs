@@ -3,11 +3,16 @@
 //
 // Implements the structures and methods required for Ticket parsing and editing.
 
+use std::fmt;
+#[cfg(feature = "fs")]
+use std::fs;
 use std::io::{Cursor, Read, Write};
+#[cfg(feature = "fs")]
+use std::path::{Path, PathBuf};
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use sha1::{Sha1, Digest};
 use thiserror::Error;
-use crate::title::crypto;
+use crate::title::{cert, crypto, diff, TitleId};
 use crate::title::crypto::decrypt_title_key;
 
 #[derive(Debug, Error)]
@@ -16,12 +21,74 @@ pub enum TicketError {
     UnsupportedVersion(u8),
     #[error("Ticket data could not be fakesigned")]
     CannotFakesign,
+    #[error("Ticket is signed with unknown signature type `{0:#010X}`")]
+    InvalidSignatureType(u32),
     #[error("signature issuer string must not exceed 64 characters (was {0})")]
     IssuerTooLong(usize),
+    #[error("content index `{0}` is out of range, Tickets can only store permissions for indices 0-511")]
+    InvalidContentIndex(u16),
+    #[error("title limit slot `{0}` is out of range, Tickets only have 8 title limit slots")]
+    InvalidLimitSlot(usize),
+    #[error("the Title Key in this Ticket is encrypted with a console-specific key, so the decrypted Title Key must be supplied to unpersonalize it")]
+    ConsoleSpecificTitleKey,
+    #[error("invalid common key index `{0}`, common key index must be in the range 0-2")]
+    InvalidCommonKeyIndex(u8),
+    #[error("`{0}` is not a valid Title Key, Title Keys must be 32 hex characters long")]
+    InvalidTitleKey(String),
+    #[cfg(feature = "fs")]
+    #[error("Ticket file `{}` could not be read", path.display())]
+    ReadFile { path: PathBuf, source: std::io::Error },
     #[error("Ticket data is not in a valid format")]
     IO(#[from] std::io::Error),
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The type of limit that a title limit places on a title. Limits are used by demo Tickets to
+/// restrict how long or how many times a title can be played.
+pub enum LimitType {
+    /// No limit is applied. Both type 0 and type 3 are treated as disabled by ES.
+    None,
+    /// A limit on the total play time, in minutes.
+    Time,
+    /// A limit on the number of times the title can be launched.
+    LaunchCount,
+    /// A limit type that isn't known, storing the raw type value.
+    Unknown(u32),
+}
+
+impl From<u32> for LimitType {
+    fn from(limit_type: u32) -> Self {
+        match limit_type {
+            0 | 3 => LimitType::None,
+            1 => LimitType::Time,
+            4 => LimitType::LaunchCount,
+            _ => LimitType::Unknown(limit_type),
+        }
+    }
+}
+
+impl From<LimitType> for u32 {
+    fn from(limit_type: LimitType) -> Self {
+        match limit_type {
+            LimitType::None => 0,
+            LimitType::Time => 1,
+            LimitType::LaunchCount => 4,
+            LimitType::Unknown(limit_type) => limit_type,
+        }
+    }
+}
+
+impl fmt::Display for LimitType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LimitType::None => write!(f, "None"),
+            LimitType::Time => write!(f, "Time"),
+            LimitType::LaunchCount => write!(f, "Launch Count"),
+            LimitType::Unknown(limit_type) => write!(f, "Unknown ({})", limit_type),
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct TitleLimit {
     // The type of limit being applied (time, launch count, etc.)
@@ -30,12 +97,12 @@ pub struct TitleLimit {
     pub limit_max: u32,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 /// A structure that represents a Wii Ticket file.
 pub struct Ticket {
     pub signature_type: u32,
-    pub signature: [u8; 256],
-    padding1: [u8; 60],
+    pub signature: Vec<u8>,
+    padding1: Vec<u8>,
     pub signature_issuer: [u8; 64],
     pub ecdh_data: [u8; 60],
     pub ticket_version: u8,
@@ -49,8 +116,8 @@ pub struct Ticket {
     pub title_version: u16,
     pub permitted_titles_mask: [u8; 4],
     pub permit_mask: [u8; 4],
-    pub title_export_allowed: u8,
-    pub common_key_index: u8,
+    title_export_allowed: u8,
+    common_key_index: u8,
     unknown3: [u8; 48],
     pub content_access_permission: [u8; 64],
     padding2: [u8; 2],
@@ -58,14 +125,59 @@ pub struct Ticket {
 }
 
 impl Ticket {
+    /// Creates a new blank Ticket for the specified Title ID. The Ticket is unsigned, has an empty
+    /// Title Key, and permits access to every content. This is used as a stand-in for titles that
+    /// don't come with a Ticket, like some boot2 WADs.
+    pub fn blank(title_id: TitleId) -> Self {
+        let key_type = cert::CertificateKeyType::Rsa2048;
+        let mut signature_issuer = [0u8; 64];
+        let issuer = b"Root-CA00000001-XS00000003";
+        signature_issuer[..issuer.len()].copy_from_slice(issuer);
+        Ticket {
+            signature_type: 0x00010001,
+            signature: vec![0u8; key_type.signature_len()],
+            padding1: vec![0u8; key_type.signature_padding_len()],
+            signature_issuer,
+            ecdh_data: [0; 60],
+            ticket_version: 0,
+            reserved1: [0; 2],
+            title_key: [0; 16],
+            unknown1: [0; 1],
+            ticket_id: [0; 8],
+            console_id: [0; 4],
+            title_id: title_id.to_bytes(),
+            unknown2: [0; 2],
+            title_version: 0,
+            permitted_titles_mask: [0; 4],
+            permit_mask: [0; 4],
+            title_export_allowed: 0,
+            common_key_index: 0,
+            unknown3: [0; 48],
+            content_access_permission: [0xFF; 64],
+            padding2: [0; 2],
+            title_limits: [TitleLimit { limit_type: 0, limit_max: 0 }; 8],
+        }
+    }
+
+    #[cfg(feature = "fs")]
+    /// Creates a new Ticket instance from a Ticket file, reading the file from the provided path.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, TicketError> {
+        let path = path.as_ref();
+        let data = fs::read(path).map_err(|source| TicketError::ReadFile { path: path.to_path_buf(), source })?;
+        Ticket::from_bytes(&data)
+    }
+
     /// Creates a new Ticket instance from the binary data of a Ticket file.
     pub fn from_bytes(data: &[u8]) -> Result<Self, TicketError> {
         let mut buf = Cursor::new(data);
         let signature_type = buf.read_u32::<BigEndian>().map_err(TicketError::IO)?;
-        let mut signature = [0u8; 256];
+        // The size of the signature and its padding depend on the type of key used to sign the
+        // Ticket.
+        let key_type = cert::CertificateKeyType::from_signature_type(signature_type)
+            .ok_or(TicketError::InvalidSignatureType(signature_type))?;
+        let mut signature = vec![0u8; key_type.signature_len()];
         buf.read_exact(&mut signature).map_err(TicketError::IO)?;
-        // Maybe this can be read differently?
-        let mut padding1 = [0u8; 60];
+        let mut padding1 = vec![0u8; key_type.signature_padding_len()];
         buf.read_exact(&mut padding1).map_err(TicketError::IO)?;
         let mut signature_issuer = [0u8; 64];
         buf.read_exact(&mut signature_issuer).map_err(TicketError::IO)?;
@@ -169,30 +281,99 @@ impl Ticket {
         Ok(buf)
     }
 
-    /// Gets the decrypted version of the Title Key stored in a Ticket.
+    /// Gets the decrypted version of the Title Key stored in a Ticket. A common key index outside of
+    /// the range 0-2 is treated as index 0, the same way that the console treats it. Use
+    /// dec_title_key_strict() to reject those Tickets instead.
     pub fn dec_title_key(&self) -> [u8; 16] {
         // Get the dev status of this Ticket so decrypt_title_key knows the right common key.
         let is_dev = self.is_dev();
-        decrypt_title_key(self.title_key, self.common_key_index, self.title_id, is_dev)
+        decrypt_title_key(self.title_key, self.effective_common_key_index(), self.title_id, is_dev)
+    }
+
+    /// Gets the decrypted version of the Title Key stored in a Ticket, returning an error if the
+    /// Ticket's common key index is outside of the range 0-2.
+    pub fn dec_title_key_strict(&self) -> Result<[u8; 16], TicketError> {
+        if !self.has_valid_common_key_index() {
+            return Err(TicketError::InvalidCommonKeyIndex(self.common_key_index));
+        }
+        Ok(self.dec_title_key())
+    }
+
+    /// Gets whether a Ticket's common key index refers to one of the known common keys.
+    pub fn has_valid_common_key_index(&self) -> bool {
+        self.common_key_index <= 2
+    }
+
+    // Gets the common key index that the Title Key is actually encrypted with, since the console
+    // falls back on the common key when the index is out of range.
+    fn effective_common_key_index(&self) -> u8 {
+        if self.has_valid_common_key_index() { self.common_key_index } else { 0 }
     }
     
+    /// Sets a new decrypted Title Key for a Ticket, encrypting it with the common key for the
+    /// Ticket's current common key index. Because the resulting key is no longer encrypted with a
+    /// console-specific key, this also clears the Ticket's ECDH data.
+    pub fn set_dec_title_key(&mut self, title_key: [u8; 16]) {
+        self.title_key = crypto::encrypt_title_key(title_key, self.effective_common_key_index(), self.title_id, self.is_dev());
+        self.ecdh_data = [0; 60];
+    }
+
+    /// Gets whether the title that a Ticket is for is allowed to be exported.
+    pub fn export_allowed(&self) -> bool {
+        self.title_export_allowed == 1
+    }
+
+    /// Sets whether the title that a Ticket is for is allowed to be exported.
+    pub fn set_export_allowed(&mut self, export_allowed: bool) {
+        self.title_export_allowed = export_allowed as u8;
+    }
+
+    /// Gets the index of the common key that the Title Key is encrypted with. This can only be
+    /// changed with Ticket::change_common_key() or Ticket::set_common_key_index(), which keep the
+    /// Title Key in sync with it.
+    pub fn common_key_index(&self) -> u8 {
+        self.common_key_index
+    }
+
+    /// Sets a new common key index for a Ticket. This will re-encrypt the Title Key with the new
+    /// common key, so that the decrypted Title Key doesn't change.
+    pub fn set_common_key_index(&mut self, common_key_index: u8) -> Result<(), TicketError> {
+        self.change_common_key(common_key_index, self.is_dev())
+    }
+
+    /// Moves a Ticket to a different common key, which can also be the development common key. The
+    /// Title Key is decrypted with the current common key and re-encrypted with the new one, so the
+    /// decrypted Title Key doesn't change. Because whether a Ticket is for development is decided by
+    /// its signature issuer, the issuer is changed to the standard retail or development Ticket
+    /// certificate when switching between them.
+    pub fn change_common_key(&mut self, new_index: u8, is_dev: bool) -> Result<(), TicketError> {
+        if new_index > 2 {
+            return Err(TicketError::InvalidCommonKeyIndex(new_index));
+        }
+        let dec_title_key = self.dec_title_key();
+        if is_dev != self.is_dev() {
+            let issuer = if is_dev { "Root-CA00000002-XS00000006" } else { "Root-CA00000001-XS00000003" };
+            self.set_signature_issuer(String::from(issuer))?;
+        }
+        self.title_key = crypto::encrypt_title_key(dec_title_key, new_index, self.title_id, is_dev);
+        self.common_key_index = new_index;
+        Ok(())
+    }
+
     /// Gets whether a Ticket was signed for development (true) or retail (false).
     pub fn is_dev(&self) -> bool {
-        // Parse the signature issuer to determine if this is a dev Ticket or not.
-        let issuer_str = String::from_utf8(Vec::from(&self.signature_issuer)).unwrap_or_default();
-        issuer_str.contains("Root-CA00000002-XS00000004") || issuer_str.contains("Root-CA00000002-XS00000006")
+        self.signing_certificate().is_dev()
     }
     
     /// Gets whether a Ticket is fakesigned using the strncmp (trucha) bug or not.
     pub fn is_fakesigned(&self) -> bool {
         // Can't be fakesigned without a null signature.
-        if self.signature != [0; 256] {
+        if self.signature.iter().any(|byte| *byte != 0) {
             return false;
         }
         // Test the hash of the Ticket body to make sure it starts with 00.
         let mut hasher = Sha1::new();
-        let ticket_body = self.to_bytes().unwrap();
-        hasher.update(&ticket_body[320..]);
+        hasher.update(self.signed_body().unwrap());
         let result = hasher.finalize();
         if result[0] != 0 {
             return false;
@@ -203,24 +384,37 @@ impl Ticket {
     /// Fakesigns a Ticket for use with the strncmp (trucha) bug.
     pub fn fakesign(&mut self) -> Result<(), TicketError> {
         // Erase the signature.
-        self.signature = [0; 256];
-        let mut current_int: u16 = 0;
-        let mut test_hash: [u8; 20] = [255; 20];
-        while test_hash[0] != 0 {
-            if current_int == 65535 { return Err(TicketError::CannotFakesign); }
-            current_int += 1;
-            self.unknown2 = current_int.to_be_bytes();
-            let mut hasher = Sha1::new();
-            let ticket_body = self.to_bytes()?;
-            hasher.update(&ticket_body[320..]);
-            test_hash = <[u8; 20]>::from(hasher.finalize());
-        }
+        self.signature.fill(0);
+        // Serialize the Ticket once and brute-force the unused field in place.
+        let mut ticket_body = self.signed_body()?;
+        let current_int = crypto::fakesign_body(&mut ticket_body, 0, 0xA4).ok_or(TicketError::CannotFakesign)?;
+        self.unknown2 = current_int.to_be_bytes();
         Ok(())
     }
 
+    /// Gets the offset of the signed data in a Ticket, which follows the signature type, the
+    /// signature, and the padding after the signature. This depends on the signature type.
+    pub fn signed_body_offset(&self) -> usize {
+        4 + self.signature.len() + self.padding1.len()
+    }
+
+    /// Gets the signed data of a Ticket, which is the data that its signature is created from. This
+    /// is everything after the signature and its padding, so it can be hashed and checked against
+    /// the signature by external tools.
+    pub fn signed_body(&self) -> Result<Vec<u8>, TicketError> {
+        let mut body = self.to_bytes()?;
+        body.drain(..self.signed_body_offset());
+        Ok(body)
+    }
+
     /// Gets the name of the certificate used to sign a Ticket as a string.
     pub fn signature_issuer(&self) -> String {
-        String::from_utf8_lossy(&self.signature_issuer).trim_end_matches('\0').to_owned()
+        cert::issuer_to_string(&self.signature_issuer)
+    }
+
+    /// Identifies the certificate used to sign a Ticket from its signature issuer.
+    pub fn signing_certificate(&self) -> cert::CertIdentity {
+        cert::CertIdentity::from_issuer(&self.signature_issuer())
     }
 
     /// Sets a new name for the certificate used to sign a Ticket.
@@ -235,16 +429,370 @@ impl Ticket {
     }
     
     /// Gets the Title ID of the Ticket.
-    pub fn title_id(&self) -> [u8; 8] {
-        self.title_id
+    pub fn title_id(&self) -> TitleId {
+        TitleId::new(self.title_id)
     }
     
     /// Sets a new Title ID for the Ticket. This will re-encrypt the Title Key, since the Title ID
     /// is used as the IV for decrypting the Title Key.
-    pub fn set_title_id(&mut self, title_id: [u8; 8]) -> Result<(), TicketError> {
-        let new_enc_title_key = crypto::encrypt_title_key(self.dec_title_key(), self.common_key_index, title_id, self.is_dev());
+    pub fn set_title_id(&mut self, title_id: TitleId) -> Result<(), TicketError> {
+        let new_enc_title_key = crypto::encrypt_title_key(self.dec_title_key(), self.effective_common_key_index(), title_id.to_bytes(), self.is_dev());
         self.title_key = new_enc_title_key;
-        self.title_id = title_id;
+        self.title_id = title_id.to_bytes();
         Ok(())
     }
+
+    /// Gets whether a Ticket permits access to the content with the specified index. The content
+    /// access permissions are stored as a bitmap with one bit per content index, where the lowest
+    /// bit of each byte corresponds to the lowest index in that byte.
+    pub fn is_content_permitted(&self, index: u16) -> bool {
+        match self.content_access_permission.get(index as usize / 8) {
+            Some(byte) => (byte >> (index % 8)) & 1 == 1,
+            None => false,
+        }
+    }
+
+    /// Sets whether a Ticket permits access to the content with the specified index.
+    pub fn set_content_permitted(&mut self, index: u16, allowed: bool) -> Result<(), TicketError> {
+        let byte = self.content_access_permission.get_mut(index as usize / 8).ok_or(TicketError::InvalidContentIndex(index))?;
+        if allowed {
+            *byte |= 1 << (index % 8);
+        } else {
+            *byte &= !(1 << (index % 8));
+        }
+        Ok(())
+    }
+
+    /// Gets whether a Ticket permits access to every possible content index.
+    pub fn all_contents_permitted(&self) -> bool {
+        self.content_access_permission.iter().all(|byte| *byte == 0xFF)
+    }
+
+    /// Gets the indices of all contents that a Ticket permits access to.
+    pub fn permitted_contents(&self) -> Vec<u16> {
+        (0..(self.content_access_permission.len() * 8) as u16).filter(|index| self.is_content_permitted(*index)).collect()
+    }
+
+    /// Permits access to every possible content index, which unlocks all contents of a title such
+    /// as DLC that would otherwise only be partially authorized by its Ticket.
+    pub fn permit_all_contents(&mut self) {
+        self.content_access_permission = [0xFF; 64];
+    }
+
+    /// Gets whether a Ticket is personalized, meaning that it was issued to a specific console and
+    /// can't be installed on any other console.
+    pub fn is_personalized(&self) -> bool {
+        self.console_id != [0; 4] || self.ecdh_data != [0; 60]
+    }
+
+    /// Removes the console-specific data from a personalized Ticket so that it can be installed on
+    /// any console. If the Title Key was encrypted with a console-specific key (which is the case
+    /// when the Ticket contains ECDH data), an error is returned, and the decrypted Title Key must
+    /// be supplied with set_dec_title_key() first.
+    pub fn unpersonalize(&mut self) -> Result<(), TicketError> {
+        if self.ecdh_data != [0; 60] {
+            return Err(TicketError::ConsoleSpecificTitleKey);
+        }
+        self.console_id = [0; 4];
+        self.ecdh_data = [0; 60];
+        Ok(())
+    }
+
+    /// Gets the type and maximum value of each of the 8 title limit slots in a Ticket.
+    pub fn limits(&self) -> Vec<(LimitType, u32)> {
+        self.title_limits.iter().map(|limit| (LimitType::from(limit.limit_type), limit.limit_max)).collect()
+    }
+
+    /// Sets the type and maximum value of the title limit in the specified slot.
+    pub fn set_limit(&mut self, slot: usize, limit_type: LimitType, limit_max: u32) -> Result<(), TicketError> {
+        let limit = self.title_limits.get_mut(slot).ok_or(TicketError::InvalidLimitSlot(slot))?;
+        limit.limit_type = limit_type.into();
+        limit.limit_max = limit_max;
+        Ok(())
+    }
+
+    /// Removes all title limits from a Ticket, allowing the title to be played without any
+    /// restrictions.
+    pub fn clear_limits(&mut self) {
+        self.title_limits = [TitleLimit { limit_type: 0, limit_max: 0 }; 8];
+    }
+
+    /// Compares this Ticket against another Ticket, returning a TicketDiff that describes every
+    /// changed field.
+    pub fn diff(&self, other: &Ticket) -> diff::TicketDiff {
+        diff::diff_tickets(self, other)
+    }
+}
+
+/// Parses a Title Key from a string of 32 hex characters, like the decrypted Title Keys printed by
+/// most Wii tools. Surrounding whitespace is ignored.
+pub fn parse_title_key(title_key: &str) -> Result<[u8; 16], TicketError> {
+    let trimmed = title_key.trim();
+    let bytes = hex::decode(trimmed).map_err(|_| TicketError::InvalidTitleKey(title_key.to_owned()))?;
+    bytes.try_into().map_err(|_| TicketError::InvalidTitleKey(title_key.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::title::test_utils;
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let ticket_path = dir.path().join("title.tik");
+        fs::write(&ticket_path, test_utils::build_ticket(test_utils::TEST_TITLE_ID, test_utils::TEST_TITLE_KEY)).unwrap();
+        assert_eq!(Ticket::open(&ticket_path).unwrap().dec_title_key(), test_utils::TEST_TITLE_KEY);
+        let missing = dir.path().join("missing.tik");
+        assert!(matches!(Ticket::open(&missing), Err(TicketError::ReadFile { path, .. }) if path == missing));
+    }
+
+    #[test]
+    fn test_fakesign() {
+        let mut ticket = Ticket::from_bytes(&test_utils::build_ticket(test_utils::TEST_TITLE_ID, test_utils::TEST_TITLE_KEY)).unwrap();
+        ticket.signature.fill(0xFF);
+        assert!(!ticket.is_fakesigned());
+        ticket.fakesign().unwrap();
+        assert!(ticket.is_fakesigned());
+        // The brute-forced field should round trip through the serialized Ticket.
+        let reparsed = Ticket::from_bytes(&ticket.to_bytes().unwrap()).unwrap();
+        assert!(reparsed.is_fakesigned());
+        assert_eq!(reparsed.to_bytes().unwrap(), ticket.to_bytes().unwrap());
+    }
+
+    #[test]
+    fn test_blank() {
+        let ticket = Ticket::blank(TitleId::BOOT2);
+        let data = ticket.to_bytes().unwrap();
+        assert_eq!(data.len(), 0x2A4);
+        let reparsed = Ticket::from_bytes(&data).unwrap();
+        assert_eq!(reparsed.title_id(), TitleId::BOOT2);
+        assert_eq!(reparsed.signature_issuer(), "Root-CA00000001-XS00000003");
+        assert!(reparsed.all_contents_permitted());
+    }
+
+    #[test]
+    fn test_set_export_allowed() {
+        let mut ticket = Ticket::from_bytes(&test_utils::build_ticket(test_utils::TEST_TITLE_ID, test_utils::TEST_TITLE_KEY)).unwrap();
+        assert!(!ticket.export_allowed());
+        ticket.set_export_allowed(true);
+        assert!(Ticket::from_bytes(&ticket.to_bytes().unwrap()).unwrap().export_allowed());
+        ticket.set_export_allowed(false);
+        assert_eq!(ticket.to_bytes().unwrap()[0x1F0], 0);
+        // Unexpected values aren't treated as allowing export, but are still written back out exactly.
+        let mut data = test_utils::build_ticket(test_utils::TEST_TITLE_ID, test_utils::TEST_TITLE_KEY);
+        data[0x1F0] = 0x80;
+        let ticket = Ticket::from_bytes(&data).unwrap();
+        assert!(!ticket.export_allowed());
+        assert_eq!(ticket.to_bytes().unwrap(), data);
+    }
+
+    #[test]
+    fn test_content_permissions() {
+        let mut ticket = Ticket::from_bytes(&test_utils::build_ticket(test_utils::TEST_TITLE_ID, test_utils::TEST_TITLE_KEY)).unwrap();
+        assert!(ticket.permitted_contents().is_empty());
+        for index in [0, 7, 8, 511] {
+            ticket.set_content_permitted(index, true).unwrap();
+        }
+        assert_eq!(ticket.content_access_permission[0], 0x81);
+        assert_eq!(ticket.content_access_permission[1], 0x01);
+        assert_eq!(ticket.content_access_permission[63], 0x80);
+        assert!(ticket.is_content_permitted(7));
+        assert!(!ticket.is_content_permitted(6));
+        assert!(!ticket.is_content_permitted(512));
+        assert!(matches!(ticket.set_content_permitted(512, true), Err(TicketError::InvalidContentIndex(512))));
+        ticket.set_content_permitted(0, false).unwrap();
+        // Permissions should survive a round trip through the serialized Ticket.
+        let reparsed = Ticket::from_bytes(&ticket.to_bytes().unwrap()).unwrap();
+        assert_eq!(reparsed.permitted_contents(), vec![7, 8, 511]);
+        assert!(!reparsed.all_contents_permitted());
+        ticket.permit_all_contents();
+        let reparsed = Ticket::from_bytes(&ticket.to_bytes().unwrap()).unwrap();
+        assert!(reparsed.all_contents_permitted());
+        assert_eq!(reparsed.permitted_contents().len(), 512);
+    }
+
+    #[test]
+    fn test_title_limits() {
+        let mut ticket = Ticket::from_bytes(&test_utils::build_ticket(test_utils::TEST_TITLE_ID, test_utils::TEST_TITLE_KEY)).unwrap();
+        assert!(ticket.limits().iter().all(|(limit_type, _)| *limit_type == LimitType::None));
+        ticket.set_limit(0, LimitType::Time, 60).unwrap();
+        ticket.set_limit(7, LimitType::LaunchCount, 10).unwrap();
+        assert!(matches!(ticket.set_limit(8, LimitType::Time, 1), Err(TicketError::InvalidLimitSlot(8))));
+        // Edited limits should survive a round trip through the serialized Ticket.
+        let reparsed = Ticket::from_bytes(&ticket.to_bytes().unwrap()).unwrap();
+        let limits = reparsed.limits();
+        assert_eq!(limits.len(), 8);
+        assert_eq!(limits[0], (LimitType::Time, 60));
+        assert_eq!(limits[7], (LimitType::LaunchCount, 10));
+        assert_eq!(LimitType::from(3), LimitType::None);
+        assert_eq!(u32::from(LimitType::from(9)), 9);
+        ticket.clear_limits();
+        let reparsed = Ticket::from_bytes(&ticket.to_bytes().unwrap()).unwrap();
+        assert!(reparsed.limits().iter().all(|limit| *limit == (LimitType::None, 0)));
+    }
+
+    #[test]
+    fn test_unpersonalize() {
+        let mut ticket = Ticket::from_bytes(&test_utils::build_ticket(test_utils::TEST_TITLE_ID, test_utils::TEST_TITLE_KEY)).unwrap();
+        assert!(!ticket.is_personalized());
+        // A Ticket with only a Console ID set can be unpersonalized directly.
+        ticket.console_id = [0x0A, 0x0B, 0x0C, 0x0D];
+        assert!(ticket.is_personalized());
+        ticket.unpersonalize().unwrap();
+        assert!(!ticket.is_personalized());
+        assert_eq!(ticket.dec_title_key(), test_utils::TEST_TITLE_KEY);
+    }
+
+    #[test]
+    fn test_unpersonalize_rekey() {
+        let mut ticket = Ticket::from_bytes(&test_utils::build_ticket(test_utils::TEST_TITLE_ID, test_utils::TEST_TITLE_KEY)).unwrap();
+        // Simulate a Title Key encrypted with a console-specific key.
+        ticket.console_id = [0x0A, 0x0B, 0x0C, 0x0D];
+        ticket.ecdh_data = [0x42; 60];
+        ticket.title_key = [0x99; 16];
+        assert!(matches!(ticket.unpersonalize(), Err(TicketError::ConsoleSpecificTitleKey)));
+        assert!(ticket.is_personalized());
+        ticket.set_dec_title_key(test_utils::TEST_TITLE_KEY);
+        ticket.unpersonalize().unwrap();
+        let reparsed = Ticket::from_bytes(&ticket.to_bytes().unwrap()).unwrap();
+        assert!(!reparsed.is_personalized());
+        assert_eq!(reparsed.console_id, [0; 4]);
+        assert_eq!(reparsed.dec_title_key(), test_utils::TEST_TITLE_KEY);
+    }
+
+    #[test]
+    fn test_set_dec_title_key() {
+        let mut ticket = Ticket::from_bytes(&test_utils::build_ticket(test_utils::TEST_TITLE_ID, test_utils::TEST_TITLE_KEY)).unwrap();
+        let new_key = parse_title_key("00112233445566778899aabbccddeeff").unwrap();
+        ticket.set_dec_title_key(new_key);
+        assert_ne!(ticket.title_key, new_key);
+        let reparsed = Ticket::from_bytes(&ticket.to_bytes().unwrap()).unwrap();
+        assert_eq!(reparsed.dec_title_key(), new_key);
+        // The key should still decrypt correctly after moving to a different common key.
+        ticket.set_common_key_index(2).unwrap();
+        assert_eq!(ticket.dec_title_key(), new_key);
+    }
+
+    #[test]
+    fn test_parse_title_key() {
+        assert_eq!(parse_title_key(" 00112233445566778899AABBCCDDEEFF\n").unwrap()[15], 0xFF);
+        assert!(matches!(parse_title_key("00112233"), Err(TicketError::InvalidTitleKey(_))));
+        assert!(matches!(parse_title_key("00112233445566778899aabbccddeeff00"), Err(TicketError::InvalidTitleKey(_))));
+        assert!(matches!(parse_title_key("zz112233445566778899aabbccddeeff"), Err(TicketError::InvalidTitleKey(_))));
+    }
+
+    #[test]
+    fn test_set_common_key_index() {
+        let mut ticket = Ticket::from_bytes(&test_utils::build_ticket(test_utils::TEST_TITLE_ID, test_utils::TEST_TITLE_KEY)).unwrap();
+        let original_key = ticket.title_key;
+        ticket.set_common_key_index(1).unwrap();
+        assert_ne!(ticket.title_key, original_key);
+        let reparsed = Ticket::from_bytes(&ticket.to_bytes().unwrap()).unwrap();
+        assert_eq!(reparsed.common_key_index, 1);
+        assert_eq!(reparsed.dec_title_key(), test_utils::TEST_TITLE_KEY);
+        assert!(matches!(ticket.set_common_key_index(3), Err(TicketError::InvalidCommonKeyIndex(3))));
+    }
+
+    #[test]
+    fn test_change_common_key() {
+        let mut ticket = Ticket::from_bytes(&test_utils::build_ticket(test_utils::TEST_TITLE_ID, test_utils::TEST_TITLE_KEY)).unwrap();
+        // Retail -> vWii -> Korean -> retail, with the decrypted key surviving every step and a trip
+        // through the Ticket's binary data.
+        for index in [2, 1, 0] {
+            let previous_key = ticket.title_key;
+            ticket.change_common_key(index, false).unwrap();
+            assert_ne!(ticket.title_key, previous_key);
+            ticket = Ticket::from_bytes(&ticket.to_bytes().unwrap()).unwrap();
+            assert_eq!(ticket.common_key_index(), index);
+            assert_eq!(ticket.dec_title_key(), test_utils::TEST_TITLE_KEY);
+        }
+        assert_eq!(ticket.to_bytes().unwrap(), test_utils::build_ticket(test_utils::TEST_TITLE_ID, test_utils::TEST_TITLE_KEY));
+        // Switching to the development common key also switches the issuer, since that's what
+        // decides which common key is used.
+        ticket.change_common_key(0, true).unwrap();
+        assert!(ticket.is_dev());
+        assert_eq!(ticket.signature_issuer(), "Root-CA00000002-XS00000006");
+        assert_eq!(ticket.dec_title_key(), test_utils::TEST_TITLE_KEY);
+        ticket.change_common_key(0, false).unwrap();
+        assert!(!ticket.is_dev());
+        assert_eq!(ticket.dec_title_key(), test_utils::TEST_TITLE_KEY);
+        // Invalid indices leave the Ticket untouched.
+        let before = ticket.to_bytes().unwrap();
+        assert!(matches!(ticket.change_common_key(3, false), Err(TicketError::InvalidCommonKeyIndex(3))));
+        assert_eq!(ticket.to_bytes().unwrap(), before);
+    }
+
+    #[test]
+    fn test_out_of_range_common_key_index() {
+        for index in [0, 1, 2] {
+            let mut ticket = Ticket::from_bytes(&test_utils::build_ticket(test_utils::TEST_TITLE_ID, test_utils::TEST_TITLE_KEY)).unwrap();
+            ticket.set_common_key_index(index).unwrap();
+            assert!(ticket.has_valid_common_key_index());
+            assert_eq!(ticket.dec_title_key_strict().unwrap(), test_utils::TEST_TITLE_KEY);
+        }
+        // A junk index falls back on the common key, which is what the key is encrypted with here.
+        for index in [3, 0xFF] {
+            let data = test_utils::build_ticket(test_utils::TEST_TITLE_ID, test_utils::TEST_TITLE_KEY);
+            let ticket = Ticket::from_bytes(&test_utils::with_common_key_index(&data, index)).unwrap();
+            assert!(!ticket.has_valid_common_key_index());
+            assert_eq!(ticket.dec_title_key(), test_utils::TEST_TITLE_KEY);
+            assert!(matches!(ticket.dec_title_key_strict(), Err(TicketError::InvalidCommonKeyIndex(i)) if i == index));
+            let reparsed = Ticket::from_bytes(&ticket.to_bytes().unwrap()).unwrap();
+            assert_eq!(reparsed.common_key_index, index);
+            assert_eq!(reparsed.dec_title_key(), test_utils::TEST_TITLE_KEY);
+        }
+    }
+
+    #[test]
+    fn test_is_dev_with_padded_issuer() {
+        let mut ticket = Ticket::from_bytes(&test_utils::build_ticket(test_utils::TEST_TITLE_ID, test_utils::TEST_TITLE_KEY)).unwrap();
+        let issuer = b"Root-CA00000002-XS00000006";
+        let paddings: [Vec<u8>; 4] = [
+            vec![0x00; 64 - issuer.len()],
+            vec![0xFF; 64 - issuer.len()],
+            [vec![0x00], vec![0xC3, 0x28, 0x9A, 0x41, 0x7E, 0x01]].concat(),
+            vec![0xA7, 0x3B, 0xFE, 0x10, 0x55],
+        ];
+        for padding in paddings {
+            let mut raw = issuer.to_vec();
+            raw.extend(padding);
+            raw.resize(64, 0);
+            ticket.signature_issuer = raw.try_into().unwrap();
+            assert_eq!(ticket.signature_issuer(), "Root-CA00000002-XS00000006");
+            assert!(ticket.is_dev());
+        }
+        // The development common key should be used for the Title Key of a dev Ticket, regardless
+        // of the padding used.
+        ticket.set_dec_title_key(test_utils::TEST_TITLE_KEY);
+        ticket.signature_issuer[issuer.len()..].fill(0x00);
+        assert_eq!(ticket.dec_title_key(), test_utils::TEST_TITLE_KEY);
+        ticket.signature_issuer = [0xFF; 64];
+        ticket.signature_issuer[..26].copy_from_slice(b"Root-CA00000001-XS00000003");
+        assert_eq!(ticket.signature_issuer(), "Root-CA00000001-XS00000003");
+        assert!(!ticket.is_dev());
+    }
+
+    #[test]
+    fn test_rsa4096_signature() {
+        let rsa2048 = test_utils::build_ticket(test_utils::TEST_TITLE_ID, test_utils::TEST_TITLE_KEY);
+        let data = test_utils::with_signature_type(&rsa2048, 0x00010000);
+        let mut ticket = Ticket::from_bytes(&data).unwrap();
+        assert_eq!(ticket.signature.len(), 512);
+        assert_eq!(ticket.signed_body_offset(), 0x240);
+        assert_eq!(ticket.signed_body().unwrap(), data[0x240..]);
+        assert_eq!(ticket.title_id().to_bytes(), test_utils::TEST_TITLE_ID);
+        assert_eq!(ticket.dec_title_key(), test_utils::TEST_TITLE_KEY);
+        assert_eq!(ticket.to_bytes().unwrap(), data);
+        ticket.fakesign().unwrap();
+        assert!(Ticket::from_bytes(&ticket.to_bytes().unwrap()).unwrap().is_fakesigned());
+    }
+
+    #[test]
+    fn test_signed_body() {
+        let data = test_utils::build_ticket(test_utils::TEST_TITLE_ID, test_utils::TEST_TITLE_KEY);
+        let ticket = Ticket::from_bytes(&data).unwrap();
+        assert_eq!(ticket.signed_body().unwrap(), data[320..]);
+    }
 }
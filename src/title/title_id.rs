@@ -0,0 +1,219 @@
+// title/title_id.rs from rustii (c) 2025 NinjaCheetah & Contributors
+// https://github.com/NinjaCheetah/rustii
+//
+// Implements a typed Title ID that handles parsing and formatting in one place.
+
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+use crate::title::tmd::TitleType;
+
+#[derive(Debug, Error)]
+pub enum TitleIdError {
+    #[error("Title ID `{0}` is invalid, Title IDs must be 16 hex characters long")]
+    InvalidHex(String),
+    #[error("Title ID high `{0}` is invalid, the high half of a Title ID must be 8 hex characters long")]
+    InvalidHigh(String),
+    #[error("Title ID low `{0}` is invalid, the ASCII low half of a Title ID must be 4 ASCII characters long")]
+    InvalidAsciiLow(String),
+    #[error("Title ID `{0}` is invalid, Title IDs must be 16 hex characters (e.g. 0001000148414445), a hex high and ASCII low (e.g. 00010001-HADE), or 4 ASCII characters (e.g. HADE)")]
+    InvalidFormat(String),
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// A Title ID, which uniquely identifies a title. The first half (the high) identifies the type of
+/// the title, and the second half (the low) identifies the title itself, and is usually 4 ASCII
+/// characters for non-system titles.
+pub struct TitleId([u8; 8]);
+
+impl TitleId {
+    /// The Title ID of boot2, which is installed from "ib" (ImportBoot) WADs.
+    pub const BOOT2: TitleId = TitleId([0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01]);
+
+    /// Creates a new TitleId from its binary form.
+    pub fn new(title_id: [u8; 8]) -> Self {
+        TitleId(title_id)
+    }
+
+    /// Parses a TitleId from its 16 character hex form, e.g. 0001000148414445.
+    pub fn from_hex(title_id: &str) -> Result<Self, TitleIdError> {
+        if title_id.len() != 16 {
+            return Err(TitleIdError::InvalidHex(title_id.to_owned()));
+        }
+        let bytes: [u8; 8] = hex::decode(title_id)
+            .map_err(|_| TitleIdError::InvalidHex(title_id.to_owned()))?
+            .try_into()
+            .map_err(|_| TitleIdError::InvalidHex(title_id.to_owned()))?;
+        Ok(TitleId(bytes))
+    }
+
+    /// Creates a TitleId from the hex form of its high half and the ASCII form of its low half,
+    /// e.g. "00010001" and "HADE".
+    pub fn from_ascii_low(high: &str, low: &str) -> Result<Self, TitleIdError> {
+        if high.len() != 8 {
+            return Err(TitleIdError::InvalidHigh(high.to_owned()));
+        }
+        let high_bytes = hex::decode(high).map_err(|_| TitleIdError::InvalidHigh(high.to_owned()))?;
+        if low.len() != 4 || !low.chars().all(|c| c.is_ascii_graphic()) {
+            return Err(TitleIdError::InvalidAsciiLow(low.to_owned()));
+        }
+        let mut title_id = [0u8; 8];
+        title_id[0..4].copy_from_slice(&high_bytes);
+        title_id[4..8].copy_from_slice(low.as_bytes());
+        Ok(TitleId(title_id))
+    }
+
+    /// Creates a TitleId from its high and low halves.
+    pub fn from_parts(high: [u8; 4], low: [u8; 4]) -> Self {
+        let mut title_id = [0u8; 8];
+        title_id[0..4].copy_from_slice(&high);
+        title_id[4..8].copy_from_slice(&low);
+        TitleId(title_id)
+    }
+
+    /// Gets the binary form of a TitleId.
+    pub fn to_bytes(&self) -> [u8; 8] {
+        self.0
+    }
+
+    /// Gets the high half of a TitleId, which identifies the type of the title.
+    pub fn high(&self) -> [u8; 4] {
+        self.0[0..4].try_into().unwrap()
+    }
+
+    /// Gets the low half of a TitleId, which identifies the title itself.
+    pub fn low(&self) -> [u8; 4] {
+        self.0[4..8].try_into().unwrap()
+    }
+
+    /// Gets the low half of a TitleId as 4 ASCII characters, e.g. "HADE". Returns None if the low
+    /// half isn't made up of printable ASCII characters, which is the case for system titles.
+    pub fn ascii_low(&self) -> Option<String> {
+        let low = self.low();
+        if low.iter().all(|c| c.is_ascii_graphic()) {
+            Some(String::from_utf8_lossy(&low).into_owned())
+        } else {
+            None
+        }
+    }
+
+    /// Gets the type of title that a TitleId belongs to, based on its high half. Returns None if
+    /// the high half doesn't match any known title type.
+    pub fn title_type(&self) -> Option<TitleType> {
+        match u32::from_be_bytes(self.high()) {
+            0x00000001 => Some(TitleType::System),
+            0x00010000 => Some(TitleType::Game),
+            0x00010001 => Some(TitleType::Channel),
+            0x00010002 => Some(TitleType::SystemChannel),
+            0x00010004 => Some(TitleType::GameChannel),
+            0x00010005 => Some(TitleType::DLC),
+            0x00010008 => Some(TitleType::HiddenChannel),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TitleId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", hex::encode_upper(self.0))
+    }
+}
+
+impl FromStr for TitleId {
+    type Err = TitleIdError;
+
+    /// Parses a TitleId from any of the forms that it's commonly written in: 16 hex characters
+    /// (0001000148414445), a hex high and low separated by a dash (00010001-48414445), a hex high
+    /// and ASCII low separated by a dash (00010001-HADE), or just an ASCII low (HADE), which is
+    /// assumed to be a channel with the high 00010001.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some((high, low)) = s.split_once('-') {
+            return match low.len() {
+                8 => TitleId::from_hex(&format!("{}{}", high, low)).map_err(|_| TitleIdError::InvalidFormat(s.to_owned())),
+                _ => TitleId::from_ascii_low(high, low),
+            };
+        }
+        match s.len() {
+            16 => TitleId::from_hex(s),
+            4 => TitleId::from_ascii_low("00010001", s),
+            _ => Err(TitleIdError::InvalidFormat(s.to_owned())),
+        }
+    }
+}
+
+impl From<[u8; 8]> for TitleId {
+    fn from(title_id: [u8; 8]) -> Self {
+        TitleId(title_id)
+    }
+}
+
+impl From<TitleId> for [u8; 8] {
+    fn from(title_id: TitleId) -> Self {
+        title_id.0
+    }
+}
+
+impl AsRef<[u8]> for TitleId {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_hex() {
+        let tid = TitleId::from_hex("0001000148414445").unwrap();
+        assert_eq!(tid.to_bytes(), [0x00, 0x01, 0x00, 0x01, 0x48, 0x41, 0x44, 0x45]);
+        assert_eq!(tid, "0001000148414445".parse().unwrap());
+        assert_eq!(TitleId::from_hex("000100014841444a").unwrap().to_string(), "000100014841444A");
+        assert!(matches!(TitleId::from_hex("00010001484144"), Err(TitleIdError::InvalidHex(_))));
+        assert!(matches!(TitleId::from_hex("000100014841444G"), Err(TitleIdError::InvalidHex(_))));
+        assert!(matches!(TitleId::from_hex("00010001HADE"), Err(TitleIdError::InvalidHex(_))));
+    }
+
+    #[test]
+    fn test_from_ascii_low() {
+        let tid = TitleId::from_ascii_low("00010001", "HADE").unwrap();
+        assert_eq!(tid.to_string(), "0001000148414445");
+        assert_eq!(tid.high(), [0x00, 0x01, 0x00, 0x01]);
+        assert_eq!(tid.low(), *b"HADE");
+        assert!(matches!(TitleId::from_ascii_low("0001001", "HADE"), Err(TitleIdError::InvalidHigh(_))));
+        assert!(matches!(TitleId::from_ascii_low("0001000Z", "HADE"), Err(TitleIdError::InvalidHigh(_))));
+        assert!(matches!(TitleId::from_ascii_low("00010001", "HAD"), Err(TitleIdError::InvalidAsciiLow(_))));
+        assert!(matches!(TitleId::from_ascii_low("00010001", "HADÉ"), Err(TitleIdError::InvalidAsciiLow(_))));
+    }
+
+    #[test]
+    fn test_from_str() {
+        let expected = TitleId::from_hex("0001000148414445").unwrap();
+        for input in ["0001000148414445", "00010001-HADE", "00010001-48414445", "HADE", " HADE\n"] {
+            assert_eq!(input.parse::<TitleId>().unwrap(), expected, "failed to parse {:?}", input);
+        }
+        assert_eq!("00010002-HAAA".parse::<TitleId>().unwrap().to_string(), "0001000248414141");
+        assert_eq!("0000000100000002".parse::<TitleId>().unwrap().ascii_low(), None);
+        // Each form should give an error describing what's wrong with it.
+        assert!(matches!("000100014841444G".parse::<TitleId>(), Err(TitleIdError::InvalidHex(_))));
+        assert!(matches!("0001000Z-HADE".parse::<TitleId>(), Err(TitleIdError::InvalidHigh(_))));
+        assert!(matches!("00010001-HAD".parse::<TitleId>(), Err(TitleIdError::InvalidAsciiLow(_))));
+        assert!(matches!("00010001-4841444Z".parse::<TitleId>(), Err(TitleIdError::InvalidFormat(_))));
+        assert!(matches!("HA E".parse::<TitleId>(), Err(TitleIdError::InvalidAsciiLow(_))));
+        assert!(matches!("HAD".parse::<TitleId>(), Err(TitleIdError::InvalidFormat(_))));
+        assert!(matches!("00010001HADE".parse::<TitleId>(), Err(TitleIdError::InvalidFormat(_))));
+        assert!(matches!("".parse::<TitleId>(), Err(TitleIdError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn test_ascii_low_and_type() {
+        let channel = TitleId::from_hex("0001000148414445").unwrap();
+        assert_eq!(channel.ascii_low(), Some(String::from("HADE")));
+        assert!(matches!(channel.title_type(), Some(TitleType::Channel)));
+        let system = TitleId::from_hex("0000000100000002").unwrap();
+        assert_eq!(system.ascii_low(), None);
+        assert!(matches!(system.title_type(), Some(TitleType::System)));
+        assert!(TitleId::from_hex("1234567800000000").unwrap().title_type().is_none());
+    }
+}
@@ -1,83 +1,307 @@
 // title/versions.rs from rustii (c) 2025 NinjaCheetah & Contributors
 // https://github.com/NinjaCheetah/rustii
 //
-// Handles converting Title version formats, and provides Wii Menu version constants.
-
-use std::collections::HashMap;
-
-fn wii_menu_versions_map(vwii: Option<bool>) -> HashMap<u16, String> {
-    let mut menu_versions: HashMap<u16, String> = HashMap::new();
-    if vwii == Some(true) {
-        menu_versions.insert(512, "vWii-1.0.0J".to_string());
-        menu_versions.insert(513, "vWii-1.0.0U".to_string());
-        menu_versions.insert(514, "vWii-1.0.0E".to_string());
-        menu_versions.insert(544, "vWii-4.0.0J".to_string());
-        menu_versions.insert(545, "vWii-4.0.0U".to_string());
-        menu_versions.insert(546, "vWii-4.0.0E".to_string());
-        menu_versions.insert(608, "vWii-5.2.0J".to_string());
-        menu_versions.insert(609, "vWii-5.2.0U".to_string());
-        menu_versions.insert(610, "vWii-5.2.0E".to_string());
+// Handles converting Title version formats, and provides Wii Menu, IOS, and system update version
+// constants.
+
+use crate::title::TitleId;
+
+const SYSTEM_MENU_TID: &str = "0000000100000002";
+
+// The decimal and user-friendly versions of every Wii Menu release.
+const WII_MENU_VERSIONS: &[(u16, &str)] = &[
+    (0, "Prelaunch"),
+    (1, "Prelaunch"),
+    (2, "Prelaunch"),
+    (64, "1.0J"),
+    (33, "1.0U"),
+    (34, "1.0E"),
+    (128, "2.0J"),
+    (97, "2.0U"),
+    (130, "2.0E"),
+    (162, "2.1E"),
+    (192, "2.2J"),
+    (193, "2.2U"),
+    (194, "2.2E"),
+    (224, "3.0J"),
+    (225, "3.0U"),
+    (226, "3.0E"),
+    (256, "3.1J"),
+    (257, "3.1U"),
+    (258, "3.1E"),
+    (288, "3.2J"),
+    (289, "3.2U"),
+    (290, "3.2E"),
+    (352, "3.3J"),
+    (353, "3.3U"),
+    (354, "3.3E"),
+    (326, "3.3K"),
+    (384, "3.4J"),
+    (385, "3.4U"),
+    (386, "3.4E"),
+    (390, "3.5K"),
+    (416, "4.0J"),
+    (417, "4.0U"),
+    (418, "4.0E"),
+    (448, "4.1J"),
+    (449, "4.1U"),
+    (450, "4.1E"),
+    (454, "4.1K"),
+    (480, "4.2J"),
+    (481, "4.2U"),
+    (482, "4.2E"),
+    (486, "4.2K"),
+    (512, "4.3J"),
+    (513, "4.3U"),
+    (514, "4.3E"),
+    (518, "4.3K"),
+    (4609, "4.3U-Mini"),
+    (4610, "4.3E-Mini"),
+];
+
+// The decimal and user-friendly versions of every vWii Menu release.
+const VWII_MENU_VERSIONS: &[(u16, &str)] = &[
+    (512, "vWii-1.0.0J"),
+    (513, "vWii-1.0.0U"),
+    (514, "vWii-1.0.0E"),
+    (544, "vWii-4.0.0J"),
+    (545, "vWii-4.0.0U"),
+    (546, "vWii-4.0.0E"),
+    (608, "vWii-5.2.0J"),
+    (609, "vWii-5.2.0U"),
+    (610, "vWii-5.2.0E"),
+];
+
+// The latest version of each IOS available on the NUS for the Wii. Stubbed IOSes are not included.
+const WII_LATEST_IOS_VERSIONS: &[(u8, u16)] = &[
+    (9, 1034),
+    (12, 526),
+    (13, 1032),
+    (14, 1032),
+    (15, 1032),
+    (17, 1032),
+    (21, 1039),
+    (22, 1294),
+    (28, 1807),
+    (31, 3608),
+    (33, 3608),
+    (34, 3608),
+    (35, 3608),
+    (36, 3608),
+    (37, 5663),
+    (38, 4124),
+    (41, 3607),
+    (43, 3607),
+    (45, 3607),
+    (46, 3607),
+    (48, 4124),
+    (53, 5663),
+    (55, 5663),
+    (56, 5662),
+    (57, 5919),
+    (58, 6176),
+    (59, 9249),
+    (61, 5662),
+    (62, 6430),
+    (80, 6944),
+];
+
+// The latest version of each IOS available on the NUS for the vWii. vWii IOSes are versioned one
+// major version higher than their Wii counterparts.
+const VWII_LATEST_IOS_VERSIONS: &[(u8, u16)] = &[
+    (9, 1290),
+    (12, 782),
+    (13, 1288),
+    (14, 1288),
+    (15, 1288),
+    (17, 1288),
+    (21, 1295),
+    (22, 1550),
+    (28, 2063),
+    (31, 3864),
+    (33, 3864),
+    (34, 3864),
+    (35, 3864),
+    (36, 3864),
+    (37, 5919),
+    (38, 4380),
+    (41, 3863),
+    (43, 3863),
+    (45, 3863),
+    (46, 3863),
+    (48, 4380),
+    (53, 5919),
+    (55, 5919),
+    (56, 5918),
+    (57, 6175),
+    (58, 6432),
+    (59, 9505),
+    (61, 5918),
+    (62, 6686),
+    (80, 7200),
+];
+
+// The titles installed by every region of the final (4.3) system update, excluding the Wii Menu.
+// IOSes are taken from WII_LATEST_IOS_VERSIONS, minus IOS59, which was never part of an update.
+const SYSTEM_UPDATE_COMMON_TITLES: &[(u64, u16)] = &[
+    (0x0000000100000100, 6), // BC
+    (0x0000000100000101, 10), // MIOS
+    (0x0001000248414241, 21), // Wii Shop Channel
+    (0x0001000248414341, 6), // Mii Channel
+    (0x0001000248415A41, 3), // Photo Channel 1.1
+];
+
+// The region-specific titles installed by each of the final (4.3) system updates, keyed by the
+// decimal version of the Wii Menu that they install.
+const SYSTEM_UPDATE_REGION_TITLES: &[(u16, &[(u64, u16)])] = &[
+    (512, &[
+        (0x000100024841464A, 7), // Forecast Channel
+        (0x000100024841474A, 7), // News Channel
+        (0x0001000848414B4A, 3), // EULA
+        (0x0001000848414C4A, 2), // Region Select
+    ]),
+    (513, &[
+        (0x0001000248414645, 7), // Forecast Channel
+        (0x0001000248414745, 7), // News Channel
+        (0x0001000848414B45, 3), // EULA
+        (0x0001000848414C45, 2), // Region Select
+    ]),
+    (514, &[
+        (0x0001000248414650, 7), // Forecast Channel
+        (0x0001000248414750, 7), // News Channel
+        (0x0001000848414B50, 3), // EULA
+        (0x0001000848414C50, 2), // Region Select
+    ]),
+    (518, &[
+        (0x0001000848414B4B, 3), // EULA
+        (0x0001000848414C4B, 2), // Region Select
+    ]),
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// A title installed by a system update, along with the version of it that the update installs.
+pub struct SystemUpdateTitle {
+    pub title_id: TitleId,
+    pub version: u16,
+}
+
+/// Gets the decimal and user-friendly versions of every Wii Menu release, or every vWii Menu
+/// release if vwii is true.
+pub fn system_menu_versions(vwii: bool) -> &'static [(u16, &'static str)] {
+    if vwii {
+        VWII_MENU_VERSIONS
     } else {
-        menu_versions.insert( 0, "Prelaunch".to_string());
-        menu_versions.insert( 1, "Prelaunch".to_string());
-        menu_versions.insert( 2, "Prelaunch".to_string());
-        menu_versions.insert( 64, "1.0J".to_string());
-        menu_versions.insert( 33, "1.0U".to_string());
-        menu_versions.insert( 34, "1.0E".to_string());
-        menu_versions.insert( 128, "2.0J".to_string());
-        menu_versions.insert( 97, "2.0U".to_string());
-        menu_versions.insert( 130, "2.0E".to_string());
-        menu_versions.insert( 162, "2.1E".to_string());
-        menu_versions.insert( 192, "2.2J".to_string());
-        menu_versions.insert( 193, "2.2U".to_string());
-        menu_versions.insert( 194, "2.2E".to_string());
-        menu_versions.insert( 224, "3.0J".to_string());
-        menu_versions.insert( 225, "3.0U".to_string());
-        menu_versions.insert( 226, "3.0E".to_string());
-        menu_versions.insert( 256, "3.1J".to_string());
-        menu_versions.insert( 257, "3.1U".to_string());
-        menu_versions.insert( 258, "3.1E".to_string());
-        menu_versions.insert( 288, "3.2J".to_string());
-        menu_versions.insert( 289, "3.2U".to_string());
-        menu_versions.insert( 290, "3.2E".to_string());
-        menu_versions.insert( 352, "3.3J".to_string());
-        menu_versions.insert( 353, "3.3U".to_string());
-        menu_versions.insert( 354, "3.3E".to_string());
-        menu_versions.insert( 326, "3.3K".to_string());
-        menu_versions.insert( 384, "3.4J".to_string());
-        menu_versions.insert( 385, "3.4U".to_string());
-        menu_versions.insert( 386, "3.4E".to_string());
-        menu_versions.insert( 390, "3.5K".to_string());
-        menu_versions.insert( 416, "4.0J".to_string());
-        menu_versions.insert( 417, "4.0U".to_string());
-        menu_versions.insert( 418, "4.0E".to_string());
-        menu_versions.insert( 448, "4.1J".to_string());
-        menu_versions.insert( 449, "4.1U".to_string());
-        menu_versions.insert( 450, "4.1E".to_string());
-        menu_versions.insert( 454, "4.1K".to_string());
-        menu_versions.insert( 480, "4.2J".to_string());
-        menu_versions.insert( 481, "4.2U".to_string());
-        menu_versions.insert( 482, "4.2E".to_string());
-        menu_versions.insert( 486, "4.2K".to_string());
-        menu_versions.insert( 512, "4.3J".to_string());
-        menu_versions.insert( 513, "4.3U".to_string());
-        menu_versions.insert( 514, "4.3E".to_string());
-        menu_versions.insert( 518, "4.3K".to_string());
-        menu_versions.insert( 4609, "4.3U-Mini".to_string());
-        menu_versions.insert( 4610, "4.3E-Mini".to_string());
+        WII_MENU_VERSIONS
     }
-    menu_versions
+}
+
+/// Gets the latest version of the IOS in the specified slot that is available on the NUS, for
+/// either the Wii or the vWii. Returns None if the IOS isn't available, or is only available as a
+/// stub.
+pub fn latest_ios_version(slot: u8, vwii: bool) -> Option<u16> {
+    let versions = if vwii { VWII_LATEST_IOS_VERSIONS } else { WII_LATEST_IOS_VERSIONS };
+    versions.iter().find(|(ios, _)| *ios == slot).map(|(_, version)| *version)
+}
+
+/// Gets the list of titles that make up the system update that installs the specified Wii Menu
+/// version (e.g. "4.3U"). The Wii Menu is always the last title in the list, matching the order
+/// that a console installs them in. Returns None if the version isn't known, or if there is no
+/// description of the update that installs it. Currently only the final (4.3) updates are
+/// described.
+pub fn system_update_titles(menu_version: &str) -> Option<Vec<SystemUpdateTitle>> {
+    let menu = WII_MENU_VERSIONS.iter().find(|(_, standard)| standard.eq_ignore_ascii_case(menu_version))?.0;
+    let (_, region_titles) = SYSTEM_UPDATE_REGION_TITLES.iter().find(|(version, _)| *version == menu)?;
+    let ios_titles = WII_LATEST_IOS_VERSIONS.iter()
+        .filter(|(slot, _)| *slot != 59)
+        .map(|(slot, version)| (0x0000000100000000 | *slot as u64, *version));
+    let mut titles: Vec<SystemUpdateTitle> = ios_titles
+        .chain(SYSTEM_UPDATE_COMMON_TITLES.iter().copied())
+        .chain(region_titles.iter().copied())
+        .map(|(tid, version)| SystemUpdateTitle { title_id: TitleId::new(tid.to_be_bytes()), version })
+        .collect();
+    titles.push(SystemUpdateTitle { title_id: TitleId::from_hex(SYSTEM_MENU_TID).unwrap(), version: menu });
+    Some(titles)
 }
 
 /// Converts the decimal version of a title (vXXX) into a more standard format for applicable
-/// titles. For the Wii Menu, this uses the optional vwii argument and a hash table to determine
+/// titles. For the Wii Menu, this uses the optional vwii argument and a lookup table to determine
 /// the user-friendly version number, as there is no way to directly derive it from the decimal
 /// format.
 pub fn dec_to_standard(version: u16, title_id: &str, vwii: Option<bool>) -> Option<String> {
-    if title_id == "0000000100000002" {
-        let map = wii_menu_versions_map(vwii);
-        map.get(&version).cloned()
+    if title_id == SYSTEM_MENU_TID {
+        system_menu_versions(vwii == Some(true)).iter()
+            .find(|(dec, _)| *dec == version)
+            .map(|(_, standard)| standard.to_string())
     } else {
-        Some(format!("{}.{}", version >> 8, version & 0xF))
+        Some(format!("{}.{}", version >> 8, version & 0xFF))
+    }
+}
+
+/// Converts the standard format of a title's version back into its decimal version (vXXX). For the
+/// Wii Menu, this accepts any version from either the Wii or vWii Menu tables (e.g. "4.3U" or
+/// "vWii-5.2.0U"), and for other titles it accepts the "major.minor" format. Returns None if the
+/// version can't be converted.
+pub fn standard_to_dec(version: &str, title_id: &str) -> Option<u16> {
+    if title_id == SYSTEM_MENU_TID {
+        WII_MENU_VERSIONS.iter().chain(VWII_MENU_VERSIONS)
+            .find(|(_, standard)| standard.eq_ignore_ascii_case(version))
+            .map(|(dec, _)| *dec)
+    } else {
+        let (major, minor) = version.split_once('.')?;
+        let major: u8 = major.parse().ok()?;
+        let minor: u8 = minor.parse().ok()?;
+        Some(((major as u16) << 8) | minor as u16)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_system_menu_round_trip() {
+        for vwii in [false, true] {
+            for (dec, standard) in system_menu_versions(vwii) {
+                assert_eq!(dec_to_standard(*dec, SYSTEM_MENU_TID, Some(vwii)).as_deref(), Some(*standard));
+                let converted = standard_to_dec(standard, SYSTEM_MENU_TID).unwrap();
+                // Prelaunch has multiple decimal versions, so it can only round trip as a string.
+                assert_eq!(dec_to_standard(converted, SYSTEM_MENU_TID, Some(vwii)).as_deref(), Some(*standard));
+            }
+        }
+        assert_eq!(standard_to_dec("4.3u", SYSTEM_MENU_TID), Some(513));
+        assert_eq!(standard_to_dec("9.9U", SYSTEM_MENU_TID), None);
+    }
+
+    #[test]
+    fn test_ios_round_trip() {
+        for vwii in [false, true] {
+            let table = if vwii { VWII_LATEST_IOS_VERSIONS } else { WII_LATEST_IOS_VERSIONS };
+            for (slot, version) in table {
+                let tid = format!("00000001{:08X}", slot);
+                assert_eq!(latest_ios_version(*slot, vwii), Some(*version));
+                let standard = dec_to_standard(*version, &tid, None).unwrap();
+                assert_eq!(standard_to_dec(&standard, &tid), Some(*version));
+            }
+        }
+        assert_eq!(dec_to_standard(6176, "000000010000003A", None).as_deref(), Some("24.32"));
+        assert_eq!(latest_ios_version(254, false), None);
+        assert_eq!(standard_to_dec("24", "000000010000003A"), None);
+    }
+
+    #[test]
+    fn test_system_update_titles() {
+        let titles = system_update_titles("4.3u").unwrap();
+        let menu = titles.last().unwrap();
+        assert_eq!(menu.title_id.to_string(), SYSTEM_MENU_TID);
+        assert_eq!(menu.version, 513);
+        let ios80 = titles.iter().find(|title| title.title_id.to_string() == "0000000100000050").unwrap();
+        assert_eq!(ios80.version, 6944);
+        assert!(!titles.iter().any(|title| title.title_id.to_string() == "000000010000003B"));
+        assert!(titles.iter().any(|title| title.title_id.ascii_low().as_deref() == Some("HAKE")));
+        assert!(!titles.iter().any(|title| title.title_id.ascii_low().as_deref() == Some("HAKP")));
+        let korean = system_update_titles("4.3K").unwrap();
+        assert!(korean.iter().any(|title| title.title_id.ascii_low().as_deref() == Some("HALK")));
+        assert!(system_update_titles("4.2U").is_none());
+        assert!(system_update_titles("vWii-5.2.0U").is_none());
     }
 }
@@ -3,11 +3,16 @@
 //
 // Implements the structures and methods required for WAD parsing and editing.
 
-use std::str;
-use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use std::{fmt, str};
+#[cfg(feature = "fs")]
+use std::fs;
+use std::sync::OnceLock;
+use std::io::{self, Cursor, Read, Write};
+#[cfg(feature = "fs")]
+use std::path::{Path, PathBuf};
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use thiserror::Error;
-use crate::title::{cert, tmd, ticket, content};
+use crate::title::{cert, tmd, ticket, content, TitleId};
 use crate::title::ticket::TicketError;
 use crate::title::tmd::TMDError;
 
@@ -15,10 +20,19 @@ use crate::title::tmd::TMDError;
 pub enum WADError {
     #[error("WAD is invalid type `{0}`")]
     BadType(String),
+    #[error("WAD section `{section}` at offset {offset} with size {size} extends past the end of the data ({available} bytes available)")]
+    SectionOutOfBounds { section: String, offset: u64, size: u64, available: u64 },
+    #[error("certificate processing error")]
+    CertificateError(#[from] cert::CertificateError),
     #[error("TMD processing error")]
     TMD(#[from] TMDError),
     #[error("Ticket processing error")]
     Ticket(#[from] TicketError),
+    #[error("content region of {0} bytes is too large to fit in a WAD (the maximum is 4294967295 bytes)")]
+    ContentTooLarge(u64),
+    #[cfg(feature = "fs")]
+    #[error("WAD file `{}` could not be read", path.display())]
+    ReadFile { path: PathBuf, source: std::io::Error },
     #[error("WAD data is not in a valid format")]
     IO(#[from] std::io::Error),
 }
@@ -29,25 +43,160 @@ pub enum WADType {
     ImportBoot
 }
 
+#[derive(Debug, Clone, PartialEq)]
+/// A problem with the structure of a WAD that doesn't prevent it from being parsed, but that may
+/// cause it to be rejected by a console.
+pub enum WadAnomaly {
+    /// The header size field is not 32.
+    HeaderSize { expected: u32, actual: u32 },
+    /// The WAD version field is not 0.
+    WadVersion { expected: u16, actual: u16 },
+    /// The declared content size doesn't match the size of the contents listed in the TMD.
+    ContentSize { expected: u64, actual: u64 },
+    /// The declared size of a section doesn't match the size of its parsed data.
+    SectionSize { section: String, expected: u64, actual: u64 },
+    /// A section's data could not be parsed at all.
+    InvalidSection { section: String },
+    /// The padding after a section contains non-zero data.
+    NonZeroPadding { section: String, offset: u64 },
+    /// The total length of the WAD isn't aligned to 64 bytes.
+    UnalignedLength { length: u64 },
+    /// There is data past the end of the last section of the WAD and its padding.
+    TrailingData { offset: u64, size: u64 },
+}
+
+impl fmt::Display for WadAnomaly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WadAnomaly::HeaderSize { expected, actual } =>
+                write!(f, "header size is {} (expected {})", actual, expected),
+            WadAnomaly::WadVersion { expected, actual } =>
+                write!(f, "WAD version is {} (expected {})", actual, expected),
+            WadAnomaly::ContentSize { expected, actual } =>
+                write!(f, "declared content size is {} bytes, but the TMD describes {} bytes of content", actual, expected),
+            WadAnomaly::SectionSize { section, expected, actual } =>
+                write!(f, "declared {} size is {} bytes, but the parsed {} is {} bytes", section, actual, section, expected),
+            WadAnomaly::InvalidSection { section } =>
+                write!(f, "{} data could not be parsed", section),
+            WadAnomaly::NonZeroPadding { section, offset } =>
+                write!(f, "padding after {} contains non-zero data at offset {:#X}", section, offset),
+            WadAnomaly::UnalignedLength { length } =>
+                write!(f, "WAD length of {} bytes is not aligned to 64 bytes", length),
+            WadAnomaly::TrailingData { offset, size } =>
+                write!(f, "{} bytes of unknown data follow the last section at offset {:#X}", size, offset),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The sections stored in a WAD file, in the order that they appear.
+pub enum WadSectionType {
+    Header,
+    CertChain,
+    Crl,
+    Ticket,
+    Tmd,
+    Content,
+    Meta,
+}
+
+impl WadSectionType {
+    /// All sections of a WAD file, in the order that they appear.
+    pub const ALL: [WadSectionType; 7] = [
+        WadSectionType::Header,
+        WadSectionType::CertChain,
+        WadSectionType::Crl,
+        WadSectionType::Ticket,
+        WadSectionType::Tmd,
+        WadSectionType::Content,
+        WadSectionType::Meta,
+    ];
+}
+
+impl fmt::Display for WadSectionType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WadSectionType::Header => write!(f, "header"),
+            WadSectionType::CertChain => write!(f, "certificate chain"),
+            WadSectionType::Crl => write!(f, "CRL"),
+            WadSectionType::Ticket => write!(f, "Ticket"),
+            WadSectionType::Tmd => write!(f, "TMD"),
+            WadSectionType::Content => write!(f, "content"),
+            WadSectionType::Meta => write!(f, "meta"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The padding that follows a section of a WAD file to align the next section to 64 bytes.
+pub struct WadPadding {
+    pub offset: u64,
+    pub size: u64,
+    /// The offset of the first non-zero byte in the padding, if there is one. Non-zero padding is
+    /// only kept for WADs parsed from binary data, and is zeroed once the section before it is
+    /// replaced.
+    pub non_zero_offset: Option<u64>,
+}
+
+impl WadPadding {
+    /// Gets whether the padding only contains zeros.
+    pub fn is_zero_filled(&self) -> bool {
+        self.non_zero_offset.is_none()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The location of a section of a WAD file, and of the padding that follows it.
+pub struct WadSection {
+    pub section: WadSectionType,
+    pub offset: u64,
+    /// The size of the section's data. For the content section, this includes the padding of the
+    /// last content to 16 bytes when it's present, which may not be included in the header's
+    /// content size.
+    pub size: u64,
+    pub padding: WadPadding,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The layout of a WAD file, describing where each of its sections are stored.
+pub struct WadLayout {
+    pub sections: Vec<WadSection>,
+    /// The total size of the WAD file, including the padding after the last section.
+    pub total_size: u64,
+}
+
+impl WadLayout {
+    /// Gets the location of the specified section.
+    pub fn section(&self, section: WadSectionType) -> Option<&WadSection> {
+        self.sections.iter().find(|entry| entry.section == section)
+    }
+}
+
 #[derive(Debug)]
 /// A structure that represents an entire WAD file as a separate header and body.
 pub struct WAD {
     pub header: WADHeader,
     pub body: WADBody,
+    // Parsed copies of the components stored in the body, which are only filled in when first
+    // requested and are cleared whenever the matching raw data is replaced.
+    parsed_cert_chain: OnceLock<cert::CertificateChain>,
+    parsed_ticket: OnceLock<ticket::Ticket>,
+    parsed_tmd: OnceLock<tmd::TMD>,
+    // The padding after each section in the data that the WAD was parsed from, which is only kept
+    // when it contains non-zero data so that it can be written back out unchanged. For the header,
+    // this is everything past its first 64 bytes, since those are stored in the header itself.
+    section_padding: [Vec<u8>; 7],
 }
 
 #[derive(Debug)]
-/// A structure that represents the header of a WAD file.
+/// A structure that represents the header of a WAD file. The sizes of most sections aren't stored
+/// here, since they're always taken from the sections themselves when the WAD is written. Only the
+/// content size is kept, because the header may leave out the padding at the end of the content.
 pub struct WADHeader {
     pub header_size: u32,
     pub wad_type: WADType,
     pub wad_version: u16,
-    cert_chain_size: u32,
-    crl_size: u32,
-    ticket_size: u32,
-    tmd_size: u32,
     content_size: u32,
-    meta_size: u32,
     padding: [u8; 32],
 }
 
@@ -66,45 +215,51 @@ impl WADHeader {
     /// Creates a new WADHeader instance from the binary data of a WAD file's header.
     pub fn from_body(body: &WADBody) -> Result<WADHeader, WADError> {
         // Generates a new WADHeader from a populated WADBody object.
-        // Parse the TMD and use that to determine if this is a standard WAD or a boot2 WAD.
         let tmd = tmd::TMD::from_bytes(&body.tmd).map_err(WADError::TMD)?;
-        let wad_type = match hex::encode(tmd.title_id()).as_str() {
-            "0000000100000001" => WADType::ImportBoot,
+        WADHeader::from_body_and_tmd(body, &tmd)
+    }
+
+    // Generates a new WADHeader from a populated WADBody object and its already parsed TMD.
+    fn from_body_and_tmd(body: &WADBody, tmd: &tmd::TMD) -> Result<WADHeader, WADError> {
+        // Use the TMD to determine if this is a standard WAD or a boot2 WAD.
+        let wad_type = match tmd.title_id() {
+            TitleId::BOOT2 => WADType::ImportBoot,
             _ => WADType::Installable,
         };
-        // Find the sizes of all components of the Title.
-        let cert_chain_size = body.cert_chain.len() as u32;
-        let crl_size = body.crl.len() as u32;
-        let ticket_size = body.ticket.len() as u32;
-        let tmd_size = body.tmd.len() as u32;
-        let content_size = body.content.len() as u32;
-        let meta_size = body.meta.len() as u32;
-        let header = WADHeader {
+        let content_size = checked_content_size(body.content.len() as u64)?;
+        Ok(WADHeader {
             header_size: 32,
             wad_type,
             wad_version: 0, // This is always officially a zero.
-            cert_chain_size,
-            crl_size,
-            ticket_size,
-            tmd_size,
             content_size,
-            meta_size,
             padding: [0; 32],
-        };
-        Ok(header)
+        })
     }
 }
 
+// Gets the size of a content region as stored in a WAD's header. The header can only hold a 32-bit
+// size, so larger content regions can't be stored in a WAD at all.
+fn checked_content_size(size: u64) -> Result<u32, WADError> {
+    u32::try_from(size).map_err(|_| WADError::ContentTooLarge(size))
+}
+
 impl WADBody {
     /// Creates a new WADBody instance from instances of the components stored in a WAD file.
     pub fn from_parts(cert_chain: &cert::CertificateChain, crl: &[u8], ticket: &ticket::Ticket, tmd: &tmd::TMD, 
                       content: &content::ContentRegion, meta: &[u8]) -> Result<WADBody, WADError> {
+        WADBody::from_parts_with_content(cert_chain, crl, ticket, tmd, content.to_bytes().map_err(WADError::IO)?, meta)
+    }
+
+    // Creates a new WADBody instance from the components stored in a WAD file, taking ownership of
+    // the already serialized content region.
+    fn from_parts_with_content(cert_chain: &cert::CertificateChain, crl: &[u8], ticket: &ticket::Ticket, tmd: &tmd::TMD,
+                               content: Vec<u8>, meta: &[u8]) -> Result<WADBody, WADError> {
         let body = WADBody {
             cert_chain: cert_chain.to_bytes().map_err(WADError::IO)?,
             crl: crl.to_vec(),
             ticket: ticket.to_bytes().map_err(WADError::IO)?,
             tmd: tmd.to_bytes().map_err(WADError::IO)?,
-            content: content.to_bytes().map_err(WADError::IO)?,
+            content,
             meta: meta.to_vec(),
         };
         Ok(body)
@@ -112,8 +267,19 @@ impl WADBody {
 }
 
 impl WAD {
+    #[cfg(feature = "fs")]
+    /// Creates a new WAD instance from a WAD file, reading the file from the provided path.
+    pub fn open(path: impl AsRef<Path>) -> Result<WAD, WADError> {
+        let path = path.as_ref();
+        let data = fs::read(path).map_err(|source| WADError::ReadFile { path: path.to_path_buf(), source })?;
+        WAD::from_bytes(&data)
+    }
+
     /// Creates a new WAD instance from the binary data of a WAD file.
     pub fn from_bytes(data: &[u8]) -> Result<WAD, WADError> {
+        // The header (including its padding) is always 64 bytes, so make sure that we have at
+        // least that much data before trying to read it.
+        check_section_bounds("header", 0, 64, data.len())?;
         let mut buf = Cursor::new(data);
         let header_size = buf.read_u32::<BigEndian>().map_err(WADError::IO)?;
         let mut wad_type = [0u8; 2];
@@ -131,50 +297,49 @@ impl WAD {
         let crl_size = buf.read_u32::<BigEndian>().map_err(WADError::IO)?;
         let ticket_size = buf.read_u32::<BigEndian>().map_err(WADError::IO)?;
         let tmd_size = buf.read_u32::<BigEndian>().map_err(WADError::IO)?;
-        // Round the content size to the nearest 16.
-        let content_size = (buf.read_u32::<BigEndian>().map_err(WADError::IO)? + 15) & !15;
+        let content_size = buf.read_u32::<BigEndian>().map_err(WADError::IO)?;
         let meta_size = buf.read_u32::<BigEndian>().map_err(WADError::IO)?;
         let mut padding = [0u8; 32];
         buf.read_exact(&mut padding).map_err(WADError::IO)?;
+        // Find rounded offsets for each region, and make sure that each one actually fits in the
+        // provided data before allocating anything for it. This is all done in u64 so that huge
+        // size values can't overflow.
+        let align = |value: u64| (value + 63) & !63;
+        let available = data.len();
+        check_section_bounds("header", 0, header_size as u64, available)?;
+        let cert_chain_offset = align(header_size as u64);
+        check_section_bounds("cert_chain", cert_chain_offset, cert_chain_size as u64, available)?;
+        let crl_offset = align(cert_chain_offset + cert_chain_size as u64);
+        check_section_bounds("crl", crl_offset, crl_size as u64, available)?;
+        let ticket_offset = align(crl_offset + crl_size as u64);
+        check_section_bounds("ticket", ticket_offset, ticket_size as u64, available)?;
+        let tmd_offset = align(ticket_offset + ticket_size as u64);
+        check_section_bounds("tmd", tmd_offset, tmd_size as u64, available)?;
+        let content_offset = align(tmd_offset + tmd_size as u64);
+        check_section_bounds("content", content_offset, content_size as u64, available)?;
+        let meta_offset = align(content_offset + content_size as u64);
+        check_section_bounds("meta", meta_offset, meta_size as u64, available)?;
+        // Round the content size up to the nearest 16 when reading the content, since content is
+        // encrypted in 16-byte blocks. This padding is only taken from the data if it's actually
+        // there, and the header keeps the original size so that it's written back out unchanged.
+        let content_read_size = ((content_size as u64 + 15) & !15).min((available as u64) - content_offset) as u32;
         // Build header so we can use that data to read the WAD data.
         let header = WADHeader {
             header_size,
             wad_type,
             wad_version,
-            cert_chain_size,
-            crl_size,
-            ticket_size,
-            tmd_size,
             content_size,
-            meta_size,
             padding,
         };
-        // Find rounded offsets for each region.
-        let cert_chain_offset = (header.header_size + 63) & !63;
-        let crl_offset = (cert_chain_offset + header.cert_chain_size + 63) & !63;
-        let ticket_offset = (crl_offset + header.crl_size + 63) & !63;
-        let tmd_offset = (ticket_offset + header.ticket_size + 63) & !63;
-        let content_offset = (tmd_offset + header.tmd_size + 63) & !63;
-        let meta_offset = (content_offset + header.content_size + 63) & !63;
-        // Read cert chain data.
-        buf.seek(SeekFrom::Start(cert_chain_offset as u64)).map_err(WADError::IO)?;
-        let mut cert_chain = vec![0u8; header.cert_chain_size as usize];
-        buf.read_exact(&mut cert_chain).map_err(WADError::IO)?;
-        buf.seek(SeekFrom::Start(crl_offset as u64)).map_err(WADError::IO)?;
-        let mut crl = vec![0u8; header.crl_size as usize];
-        buf.read_exact(&mut crl).map_err(WADError::IO)?;
-        buf.seek(SeekFrom::Start(ticket_offset as u64)).map_err(WADError::IO)?;
-        let mut ticket = vec![0u8; header.ticket_size as usize];
-        buf.read_exact(&mut ticket).map_err(WADError::IO)?;
-        buf.seek(SeekFrom::Start(tmd_offset as u64)).map_err(WADError::IO)?;
-        let mut tmd = vec![0u8; header.tmd_size as usize];
-        buf.read_exact(&mut tmd).map_err(WADError::IO)?;
-        buf.seek(SeekFrom::Start(content_offset as u64)).map_err(WADError::IO)?;
-        let mut content = vec![0u8; header.content_size as usize];
-        buf.read_exact(&mut content).map_err(WADError::IO)?;
-        buf.seek(SeekFrom::Start(meta_offset as u64)).map_err(WADError::IO)?;
-        let mut meta = vec![0u8; header.meta_size as usize];
-        buf.read_exact(&mut meta).map_err(WADError::IO)?;
+        let read_section = |offset: u64, size: u32| -> Vec<u8> {
+            data[offset as usize..offset as usize + size as usize].to_vec()
+        };
+        let cert_chain = read_section(cert_chain_offset, cert_chain_size);
+        let crl = read_section(crl_offset, crl_size);
+        let ticket = read_section(ticket_offset, ticket_size);
+        let tmd = read_section(tmd_offset, tmd_size);
+        let content = read_section(content_offset, content_read_size);
+        let meta = read_section(meta_offset, meta_size);
         let body = WADBody {
             cert_chain,
             crl,
@@ -184,10 +349,25 @@ impl WAD {
             meta,
         };
         // Assemble full WAD object.
-        let wad = WAD {
+        let mut wad = WAD {
             header,
             body,
+            parsed_cert_chain: OnceLock::new(),
+            parsed_ticket: OnceLock::new(),
+            parsed_tmd: OnceLock::new(),
+            section_padding: Default::default(),
         };
+        // Keep any padding that isn't zeroed so that the WAD can be written back out exactly as it
+        // was read. The padding after the last section may be missing entirely if the file was
+        // truncated.
+        for (i, (_, _, end, next)) in wad.section_spans().into_iter().enumerate() {
+            let end = if i == 0 { 64 } else { end };
+            let end = end.min(data.len() as u64);
+            let next = next.min(data.len() as u64);
+            if end < next && data[end as usize..next as usize].iter().any(|&b| b != 0) {
+                wad.section_padding[i] = data[end as usize..next as usize].to_vec();
+            }
+        }
         Ok(wad)
     }
     
@@ -196,110 +376,713 @@ impl WAD {
     pub fn from_parts(cert_chain: &cert::CertificateChain, crl: &[u8], ticket: &ticket::Ticket, tmd: &tmd::TMD,
                       content: &content::ContentRegion, meta: &[u8]) -> Result<WAD, WADError> {
         let body = WADBody::from_parts(cert_chain, crl, ticket, tmd, content, meta)?;
-        let header = WADHeader::from_body(&body)?;
-        let wad = WAD {
+        WAD::from_body_and_tmd(body, tmd)
+    }
+
+    // Creates a new WAD instance from instances of the components stored in a WAD file, taking
+    // ownership of the already serialized content region.
+    pub(crate) fn from_parts_with_content(cert_chain: &cert::CertificateChain, crl: &[u8], ticket: &ticket::Ticket, tmd: &tmd::TMD,
+                                          content: Vec<u8>, meta: &[u8]) -> Result<WAD, WADError> {
+        let body = WADBody::from_parts_with_content(cert_chain, crl, ticket, tmd, content, meta)?;
+        WAD::from_body_and_tmd(body, tmd)
+    }
+
+    // Creates a new WAD instance from a populated WADBody and its already parsed TMD, generating a
+    // matching WADHeader.
+    fn from_body_and_tmd(body: WADBody, tmd: &tmd::TMD) -> Result<WAD, WADError> {
+        let header = WADHeader::from_body_and_tmd(&body, tmd)?;
+        Ok(WAD {
             header,
             body,
-        };
-        Ok(wad)
+            parsed_cert_chain: OnceLock::new(),
+            parsed_ticket: OnceLock::new(),
+            parsed_tmd: OnceLock::new(),
+            section_padding: Default::default(),
+        })
     }
     
     /// Dumps the data in a WAD instance back into binary data that can be written to a file.
     pub fn to_bytes(&self) -> Result<Vec<u8>, WADError> {
-        let mut buf = Vec::new();
-        buf.write_u32::<BigEndian>(self.header.header_size).map_err(WADError::IO)?;
+        let mut buf = Vec::with_capacity(self.data_size() as usize);
+        self.write_to(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Writes the data in a WAD instance to the provided writer, in the same format as to_bytes().
+    /// Writing straight to a file this way avoids building a second copy of the WAD in memory.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), WADError> {
+        self.write_sections(writer, self.header.content_size, self.body.content.len() as u64, |writer| writer.write_all(&self.body.content))
+    }
+
+    // Writes the data in a WAD instance to the provided writer, using the provided ContentRegion in
+    // place of the WAD's own content. This lets a Title be written out without first copying all
+    // of its contents into a WAD.
+    pub(crate) fn write_with_content_region<W: Write>(&self, writer: &mut W, content: &content::ContentRegion) -> Result<(), WADError> {
+        let content_size = content.data_size();
+        self.write_sections(writer, checked_content_size(content_size)?, content_size, |writer| content.write_to(writer))
+    }
+
+    // Writes the header and each section of the WAD to the provided writer, with the content
+    // section (and its size in the header) supplied separately.
+    fn write_sections<W: Write>(&self, writer: &mut W, content_size: u32, content_len: u64,
+                                write_content: impl FnOnce(&mut W) -> io::Result<()>) -> Result<(), WADError> {
+        // Pad up to nearest multiple of 64 after each section of data, using the original padding
+        // when the WAD was parsed from data that had non-zero padding.
+        let write_padding = |writer: &mut W, i: usize, len: u64| -> io::Result<()> {
+            if self.section_padding[i].len() as u64 == len {
+                writer.write_all(&self.section_padding[i])
+            } else {
+                io::copy(&mut io::repeat(0).take(len), writer).map(|_| ())
+            }
+        };
+        // The header is always written as 64 bytes, which may need to be followed by more padding
+        // if the header size is larger than that.
+        writer.write_all(&self.header_bytes(content_size)?)?;
+        write_padding(writer, 0, align_64(self.header.header_size as u64).saturating_sub(64))?;
+        let sections = [&self.body.cert_chain, &self.body.crl, &self.body.ticket, &self.body.tmd];
+        for (i, section) in sections.into_iter().enumerate() {
+            writer.write_all(section)?;
+            write_padding(writer, i + 1, align_64(section.len() as u64) - section.len() as u64)?;
+        }
+        write_content(writer)?;
+        write_padding(writer, 5, align_64(content_len) - content_len)?;
+        writer.write_all(&self.body.meta)?;
+        write_padding(writer, 6, align_64(self.body.meta.len() as u64) - self.body.meta.len() as u64)?;
+        Ok(())
+    }
+
+    // Builds the 64 bytes of the WAD's header, including the padding stored in the header. The size
+    // of each section always comes from the section's current data, so the header can't describe
+    // stale data no matter how the sections were changed.
+    fn header_bytes(&self, content_size: u32) -> io::Result<Vec<u8>> {
+        let mut header: Vec<u8> = Vec::with_capacity(64);
+        header.write_u32::<BigEndian>(self.header.header_size)?;
         match self.header.wad_type {
-            WADType::Installable => { buf.write("Is".as_bytes()).map_err(WADError::IO)?; },
-            WADType::ImportBoot => { buf.write("ib".as_bytes()).map_err(WADError::IO)?; },
+            WADType::Installable => header.write_all("Is".as_bytes())?,
+            WADType::ImportBoot => header.write_all("ib".as_bytes())?,
         }
-        buf.write_u16::<BigEndian>(self.header.wad_version).map_err(WADError::IO)?;
-        buf.write_u32::<BigEndian>(self.header.cert_chain_size).map_err(WADError::IO)?;
-        buf.write_u32::<BigEndian>(self.header.crl_size).map_err(WADError::IO)?;
-        buf.write_u32::<BigEndian>(self.header.ticket_size).map_err(WADError::IO)?;
-        buf.write_u32::<BigEndian>(self.header.tmd_size).map_err(WADError::IO)?;
-        buf.write_u32::<BigEndian>(self.header.content_size).map_err(WADError::IO)?;
-        buf.write_u32::<BigEndian>(self.header.meta_size).map_err(WADError::IO)?;
-        buf.write_all(&self.header.padding).map_err(WADError::IO)?;
-        // Pad up to nearest multiple of 64. This also needs to happen after each section of data.
-        buf.resize((buf.len() + 63) & !63, 0);
-        buf.write_all(&self.body.cert_chain).map_err(WADError::IO)?;
-        buf.resize((buf.len() + 63) & !63, 0);
-        buf.write_all(&self.body.crl).map_err(WADError::IO)?;
-        buf.resize((buf.len() + 63) & !63, 0);
-        buf.write_all(&self.body.ticket).map_err(WADError::IO)?;
-        buf.resize((buf.len() + 63) & !63, 0);
-        buf.write_all(&self.body.tmd).map_err(WADError::IO)?;
-        buf.resize((buf.len() + 63) & !63, 0);
-        buf.write_all(&self.body.content).map_err(WADError::IO)?;
-        buf.resize((buf.len() + 63) & !63, 0);
-        buf.write_all(&self.body.meta).map_err(WADError::IO)?;
-        buf.resize((buf.len() + 63) & !63, 0);
-        Ok(buf)
+        header.write_u16::<BigEndian>(self.header.wad_version)?;
+        header.write_u32::<BigEndian>(self.cert_chain_size())?;
+        header.write_u32::<BigEndian>(self.crl_size())?;
+        header.write_u32::<BigEndian>(self.ticket_size())?;
+        header.write_u32::<BigEndian>(self.tmd_size())?;
+        header.write_u32::<BigEndian>(content_size)?;
+        header.write_u32::<BigEndian>(self.meta_size())?;
+        header.write_all(&self.header.padding)?;
+        Ok(header)
+    }
+
+    // Gets the total size of the WAD's binary data, as returned by to_bytes().
+    fn data_size(&self) -> u64 {
+        // The header is always at least 64 bytes once padded.
+        let sections = [&self.body.cert_chain, &self.body.crl, &self.body.ticket, &self.body.tmd, &self.body.content, &self.body.meta];
+        align_64(self.header.header_size as u64).max(64) + sections.iter().map(|section| align_64(section.len() as u64)).sum::<u64>()
+    }
+
+    /// Checks whether writing the WAD back out with to_bytes() produces exactly the provided data,
+    /// which should be the data that the WAD was parsed from. Most WADs can be reproduced exactly,
+    /// including any non-zero padding, but data past the end of the last section and padding cut
+    /// off by a truncated file are not kept.
+    pub fn is_roundtrip_exact(&self, original_bytes: &[u8]) -> bool {
+        self.data_size() == original_bytes.len() as u64 && self.to_bytes().is_ok_and(|data| data == original_bytes)
+    }
+    
+    /// Checks the WAD for structural problems that don't prevent it from being parsed, but that
+    /// may cause it to be rejected by a console. Returns an empty list if no problems were found.
+    pub fn validate(&self) -> Vec<WadAnomaly> {
+        let mut anomalies: Vec<WadAnomaly> = Vec::new();
+        if self.header.header_size != 32 {
+            anomalies.push(WadAnomaly::HeaderSize { expected: 32, actual: self.header.header_size });
+        }
+        if self.header.wad_version != 0 {
+            anomalies.push(WadAnomaly::WadVersion { expected: 0, actual: self.header.wad_version });
+        }
+        // boot2 WADs don't always include a Ticket.
+        let missing_boot2_ticket = matches!(self.header.wad_type, WADType::ImportBoot) && self.body.ticket.is_empty();
+        match self.parsed_ticket().and_then(|ticket| Ok(ticket.to_bytes()?)) {
+            _ if missing_boot2_ticket => (),
+            Ok(ticket) => if ticket.len() != self.body.ticket.len() {
+                anomalies.push(WadAnomaly::SectionSize { section: String::from("Ticket"), expected: ticket.len() as u64, actual: self.body.ticket.len() as u64 });
+            },
+            Err(_) => anomalies.push(WadAnomaly::InvalidSection { section: String::from("Ticket") }),
+        }
+        match self.parsed_tmd() {
+            Ok(tmd) => {
+                let tmd_len = tmd.to_bytes().map(|data| data.len()).unwrap_or_default();
+                if tmd_len != self.body.tmd.len() {
+                    anomalies.push(WadAnomaly::SectionSize { section: String::from("TMD"), expected: tmd_len as u64, actual: self.body.tmd.len() as u64 });
+                }
+                // Each content is usually padded to 64 bytes (but sometimes 16 or 32), except for
+                // the last one which may only be padded to 16 bytes, so accept any of those totals.
+                let totals = |sizes: &[u64], alignment: u64| {
+                    let expected: u64 = sizes.iter().map(|size| content::align_to(*size, alignment)).sum();
+                    let expected_min = match sizes.last() {
+                        Some(last) => expected - content::align_to(*last, alignment) + content::align_to(*last, 16),
+                        None => 0,
+                    };
+                    (expected, expected_min)
+                };
+                let sizes: Vec<u64> = tmd.content_records.read().iter().map(|record| record.content_size).collect();
+                // DLC WADs may only contain the contents that their Ticket permits access to.
+                let permitted_sizes: Vec<u64> = match self.parsed_ticket() {
+                    Ok(ticket) => tmd.content_records.read().iter()
+                        .filter(|record| ticket.is_content_permitted(record.index))
+                        .map(|record| record.content_size)
+                        .collect(),
+                    Err(_) => sizes.clone(),
+                };
+                // The header may not include the padding of the last content, since it's only
+                // needed for decryption.
+                let actual = self.header.content_size as u64;
+                let actual_padded = content::align_to(actual, 16);
+                let valid = content::CONTENT_ALIGNMENTS.iter()
+                    .flat_map(|alignment| [totals(&sizes, *alignment), totals(&permitted_sizes, *alignment)])
+                    .any(|(expected, expected_min)| actual == expected || actual_padded == expected_min);
+                if !valid {
+                    let expected = totals(&sizes, content::DEFAULT_CONTENT_ALIGNMENT).0;
+                    anomalies.push(WadAnomaly::ContentSize { expected, actual });
+                }
+            },
+            Err(_) => anomalies.push(WadAnomaly::InvalidSection { section: String::from("TMD") }),
+        }
+        anomalies
+    }
+
+    /// Checks the raw data of a WAD file for layout problems that aren't preserved once it's been
+    /// parsed, like non-zero padding between sections. This should be used alongside
+    /// WAD::validate().
+    pub fn validate_layout(data: &[u8]) -> Result<Vec<WadAnomaly>, WADError> {
+        let wad = WAD::from_bytes(data)?;
+        // Every section is followed by padding up to the next multiple of 64 bytes, which should
+        // always be zeroed.
+        let layout = wad.layout();
+        let mut anomalies: Vec<WadAnomaly> = layout.sections.iter()
+            .filter_map(|entry| entry.padding.non_zero_offset.map(|offset| WadAnomaly::NonZeroPadding { section: entry.section.to_string(), offset }))
+            .collect();
+        if data.len() as u64 > layout.total_size {
+            anomalies.push(WadAnomaly::TrailingData { offset: layout.total_size, size: data.len() as u64 - layout.total_size });
+        }
+        if !(data.len() as u64).is_multiple_of(64) {
+            anomalies.push(WadAnomaly::UnalignedLength { length: data.len() as u64 });
+        }
+        Ok(anomalies)
+    }
+
+    /// Gets the layout of the WAD's binary data, with the offset and size of each section and of
+    /// the padding that follows it. For WADs parsed from binary data, this describes the data that
+    /// they were parsed from, including whether each padding region was actually zero-filled.
+    pub fn layout(&self) -> WadLayout {
+        let sections: Vec<WadSection> = self.section_spans().into_iter().enumerate()
+            .map(|(i, (section, offset, end, next))| {
+                // The first 64 bytes of the header are all stored in the header itself, so its
+                // padding needs to start there, skipping any part that's actually header data.
+                let non_zero_offset = if i == 0 {
+                    let start = end.max(0x20);
+                    self.header.padding.iter().chain(self.section_padding[0].iter())
+                        .skip(start as usize - 0x20)
+                        .position(|&b| b != 0)
+                        .map(|pos| start + pos as u64)
+                } else {
+                    self.section_padding[i].iter().position(|&b| b != 0).map(|pos| end + pos as u64)
+                };
+                WadSection {
+                    section,
+                    offset,
+                    size: end - offset,
+                    padding: WadPadding { offset: end, size: next - end, non_zero_offset },
+                }
+            })
+            .collect();
+        let total_size = sections.last().map(|entry| entry.padding.offset + entry.padding.size).unwrap_or(0);
+        WadLayout { sections, total_size }
+    }
+
+    // Gets the offset of each section of the WAD, along with the end of its data and the end of
+    // the padding that follows it. Offsets are found using the sizes declared in the header, the
+    // same way that they are when parsing.
+    fn section_spans(&self) -> [(WadSectionType, u64, u64, u64); 7] {
+        let declared = [
+            self.header.header_size,
+            self.cert_chain_size(),
+            self.crl_size(),
+            self.ticket_size(),
+            self.tmd_size(),
+            self.header.content_size,
+            self.meta_size(),
+        ];
+        // The end of the last content is padded to 16 bytes for decryption, and that padding isn't
+        // always included in the header's size, so use the size of the data that was read.
+        let stored = [
+            self.header.header_size as u64,
+            self.body.cert_chain.len() as u64,
+            self.body.crl.len() as u64,
+            self.body.ticket.len() as u64,
+            self.body.tmd.len() as u64,
+            self.body.content.len() as u64,
+            self.body.meta.len() as u64,
+        ];
+        let mut offset: u64 = 0;
+        WadSectionType::ALL.map(|section| {
+            let i = section as usize;
+            let start = offset;
+            offset = align_64(start + declared[i] as u64);
+            (section, start, start + stored[i], offset.max(align_64(start + stored[i])))
+        })
     }
     
-    pub fn cert_chain_size(&self) -> u32 { self.header.cert_chain_size }
+    pub fn cert_chain_size(&self) -> u32 { self.body.cert_chain.len() as u32 }
 
-    pub fn cert_chain(&self) -> Vec<u8> {
-        self.body.cert_chain.clone()
+    pub fn cert_chain(&self) -> &[u8] {
+        &self.body.cert_chain
     }
     
     pub fn set_cert_chain(&mut self, cert_chain: &[u8]) {
         self.body.cert_chain = cert_chain.to_vec();
-        self.header.cert_chain_size = cert_chain.len() as u32;
+        self.parsed_cert_chain = OnceLock::new();
+        self.section_padding[1] = Vec::new();
+    }
+
+    /// Gets the certificate chain stored in the WAD as a CertificateChain. The chain is only parsed
+    /// the first time it's requested, and a copy of that result is returned afterward.
+    pub fn parsed_cert_chain(&self) -> Result<cert::CertificateChain, WADError> {
+        if let Some(cert_chain) = self.parsed_cert_chain.get() {
+            return Ok(cert_chain.clone());
+        }
+        let cert_chain = cert::CertificateChain::from_bytes(&self.body.cert_chain)?;
+        Ok(self.parsed_cert_chain.get_or_init(|| cert_chain).clone())
     }
     
-    pub fn crl_size(&self) -> u32 { self.header.crl_size }
+    pub fn crl_size(&self) -> u32 { self.body.crl.len() as u32 }
 
-    pub fn crl(&self) -> Vec<u8> {
-        self.body.crl.clone()
+    pub fn crl(&self) -> &[u8] {
+        &self.body.crl
     }
     
     pub fn set_crl(&mut self, crl: &[u8]) {
         self.body.crl = crl.to_vec();
-        self.header.crl_size = crl.len() as u32;
+        self.section_padding[2] = Vec::new();
     }
     
-    pub fn ticket_size(&self) -> u32 { self.header.ticket_size }
+    pub fn ticket_size(&self) -> u32 { self.body.ticket.len() as u32 }
 
-    pub fn ticket(&self) -> Vec<u8> {
-        self.body.ticket.clone()
+    pub fn ticket(&self) -> &[u8] {
+        &self.body.ticket
     }
     
     pub fn set_ticket(&mut self, ticket: &[u8]) {
         self.body.ticket = ticket.to_vec();
-        self.header.ticket_size = ticket.len() as u32;
+        self.parsed_ticket = OnceLock::new();
+        self.section_padding[3] = Vec::new();
+    }
+
+    /// Gets the Ticket stored in the WAD as a Ticket. The Ticket is only parsed the first time it's
+    /// requested, and a copy of that result is returned afterward.
+    pub fn parsed_ticket(&self) -> Result<ticket::Ticket, WADError> {
+        if let Some(ticket) = self.parsed_ticket.get() {
+            return Ok(ticket.clone());
+        }
+        let ticket = ticket::Ticket::from_bytes(&self.body.ticket)?;
+        Ok(self.parsed_ticket.get_or_init(|| ticket).clone())
     }
     
-    pub fn tmd_size(&self) -> u32 { self.header.tmd_size }
+    pub fn tmd_size(&self) -> u32 { self.body.tmd.len() as u32 }
 
-    pub fn tmd(&self) -> Vec<u8> {
-        self.body.tmd.clone()
+    pub fn tmd(&self) -> &[u8] {
+        &self.body.tmd
     }
     
     pub fn set_tmd(&mut self, tmd: &[u8]) {
         self.body.tmd = tmd.to_vec();
-        self.header.tmd_size = tmd.len() as u32;
+        self.parsed_tmd = OnceLock::new();
+        self.section_padding[4] = Vec::new();
+    }
+
+    /// Gets the TMD stored in the WAD as a TMD. The TMD is only parsed the first time it's
+    /// requested, and a copy of that result is returned afterward. Each copy has its own content
+    /// records, so changes made to one won't affect the WAD or other copies.
+    pub fn parsed_tmd(&self) -> Result<tmd::TMD, WADError> {
+        if let Some(tmd) = self.parsed_tmd.get() {
+            return Ok(tmd.clone());
+        }
+        let tmd = tmd::TMD::from_bytes(&self.body.tmd)?;
+        Ok(self.parsed_tmd.get_or_init(|| tmd).clone())
     }
     
     pub fn content_size(&self) -> u32 { self.header.content_size }
 
-    pub fn content(&self) -> Vec<u8> {
-        self.body.content.clone()
+    pub fn content(&self) -> &[u8] {
+        &self.body.content
     }
     
-    pub fn set_content(&mut self, content: &[u8]) {
+    /// Sets the WAD's content region to the provided encrypted content. Fails without changing the
+    /// WAD if the content is too large for its size to be stored in the WAD's header.
+    pub fn set_content(&mut self, content: &[u8]) -> Result<(), WADError> {
+        self.header.content_size = checked_content_size(content.len() as u64)?;
         self.body.content = content.to_vec();
-        self.header.content_size = content.len() as u32;
+        self.section_padding[5] = Vec::new();
+        Ok(())
     }
     
-    pub fn meta_size(&self) -> u32 { self.header.meta_size }
+    pub fn meta_size(&self) -> u32 { self.body.meta.len() as u32 }
 
-    pub fn meta(&self) -> Vec<u8> {
-        self.body.meta.clone()
+    pub fn meta(&self) -> &[u8] {
+        &self.body.meta
     }
     
     pub fn set_meta(&mut self, meta: &[u8]) {
         self.body.meta = meta.to_vec();
-        self.header.meta_size = meta.len() as u32;
+        self.section_padding[6] = Vec::new();
+    }
+}
+
+// Rounds a size up to the nearest multiple of 64, which every section of a WAD is padded to.
+fn align_64(size: u64) -> u64 {
+    (size + 63) & !63
+}
+
+// Checks that a section of a WAD starting at the specified offset with the specified size fits in
+// the available data.
+fn check_section_bounds(section: &str, offset: u64, size: u64, available: usize) -> Result<(), WADError> {
+    if offset.checked_add(size).is_none_or(|end| end > available as u64) {
+        return Err(WADError::SectionOutOfBounds { section: section.to_string(), offset, size, available: available as u64 });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::title::{signed, test_utils, Title};
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let wad_path = dir.path().join("title.wad");
+        let data = test_utils::build_title(&[vec![0x11; 100]]).to_wad_bytes().unwrap();
+        fs::write(&wad_path, &data).unwrap();
+        assert_eq!(WAD::open(&wad_path).unwrap().to_bytes().unwrap(), data);
+        // Directories can't be read as files either.
+        assert!(matches!(WAD::open(dir.path()), Err(WADError::ReadFile { path, .. }) if path == dir.path()));
+    }
+
+    #[test]
+    fn test_wad_round_trip() {
+        let title = test_utils::build_title(&[vec![0x11; 100], vec![0x22; 200]]);
+        let data = title.to_wad().unwrap().to_bytes().unwrap();
+        let wad = WAD::from_bytes(&data).unwrap();
+        assert_eq!(wad.to_bytes().unwrap(), data);
+        assert_eq!(wad.tmd(), title.tmd.to_bytes().unwrap());
+    }
+
+    #[test]
+    fn test_wad_round_trip_16_aligned() {
+        let mut title = test_utils::build_title(&[vec![0x11; 100], vec![0x22; 200], vec![0x33; 40]]);
+        title.content.set_alignment(16).unwrap();
+        let data = title.to_wad().unwrap().to_bytes().unwrap();
+        let wad = WAD::from_bytes(&data).unwrap();
+        assert_eq!(wad.content_size(), 112 + 208 + 48);
+        assert_eq!(wad.validate(), []);
+        let reloaded = Title::from_wad(&wad).unwrap();
+        assert_eq!(reloaded.content.alignment(), 16);
+        assert_eq!(reloaded.get_content_by_index(2).unwrap(), [0x33; 40]);
+        assert_eq!(reloaded.to_wad().unwrap().to_bytes().unwrap(), data);
+    }
+
+    // Checks that the section sizes in the header of the WAD's binary data match its sections,
+    // and that parsing that data gives back the same sections.
+    fn assert_header_sizes_match(wad: &WAD) {
+        let data = wad.to_bytes().unwrap();
+        let header_sizes: Vec<u32> = data[8..32].chunks(4).map(|size| u32::from_be_bytes(size.try_into().unwrap())).collect();
+        let sections = [wad.cert_chain(), wad.crl(), wad.ticket(), wad.tmd(), wad.content(), wad.meta()];
+        assert_eq!(header_sizes, sections.iter().map(|section| section.len() as u32).collect::<Vec<u32>>());
+        let reparsed = WAD::from_bytes(&data).unwrap();
+        assert_eq!([reparsed.cert_chain(), reparsed.crl(), reparsed.ticket(), reparsed.tmd(), reparsed.content(), reparsed.meta()], sections);
+    }
+
+    #[test]
+    fn test_header_sizes_follow_sections() {
+        let mut title = test_utils::build_title(&[vec![0x11; 100], vec![0x22; 200]]);
+        let mut wad = title.to_wad().unwrap();
+        assert_header_sizes_match(&wad);
+        // Every setter on a WAD changes the size written to the header.
+        wad.set_cert_chain(&[0x01; 0x30]);
+        wad.set_crl(&[0x02; 0x50]);
+        wad.set_ticket(&[]);
+        wad.set_tmd(&test_utils::build_tmd(test_utils::TEST_TITLE_ID));
+        wad.set_content(&[0x03; 0x70]).unwrap();
+        wad.set_meta(&[0x04; 0x90]);
+        assert_header_sizes_match(&wad);
+        assert_eq!(wad.meta_size(), 0x90);
+        // Sections changed on a Title are picked up when it's converted, whichever way the WAD is
+        // built.
+        title.set_meta(&[0x05; 0x24]);
+        title.set_crl(&[0x06; 0x18]);
+        title.set_content(&[0x07; 0x500], 1, None, None).unwrap();
+        let wad = title.to_wad().unwrap();
+        assert_header_sizes_match(&wad);
+        assert_eq!(title.to_wad_bytes().unwrap(), wad.to_bytes().unwrap());
+        // Changes made to a parsed WAD have to replace the sizes that it was parsed with.
+        let mut wad = WAD::from_bytes(&wad.to_bytes().unwrap()).unwrap();
+        wad.set_meta(&[]);
+        wad.set_crl(&[0x08; 0x41]);
+        assert_header_sizes_match(&wad);
+        let mut signed = signed::SignedFile::Wad(Box::new(wad));
+        signed.fakesign().unwrap();
+        let signed::SignedFile::Wad(wad) = signed else { panic!("expected a WAD") };
+        assert_header_sizes_match(&wad);
+    }
+
+    #[test]
+    fn test_content_too_large() {
+        // Two contents that are each just over 2 GiB make a content region that's too large for the
+        // 32-bit content size in a WAD's header.
+        let content_size = 2 * content::align_to(0x90000000, 64);
+        assert!(matches!(checked_content_size(content_size), Err(WADError::ContentTooLarge(0x120000000))));
+        assert_eq!(checked_content_size(u32::MAX as u64).unwrap(), u32::MAX);
+    }
+
+    #[test]
+    fn test_wad_unpadded_content_size() {
+        // Some WADs store the content size without the padding of the last content, which should be
+        // preserved rather than rounded up.
+        let mut data = build_wad_data();
+        let size = u32::from_be_bytes(data[24..28].try_into().unwrap());
+        data[24..28].copy_from_slice(&(size - 8).to_be_bytes());
+        let wad = WAD::from_bytes(&data).unwrap();
+        assert_eq!(wad.content_size(), size - 8);
+        assert_eq!(wad.content().len(), size as usize);
+        assert_eq!(wad.to_bytes().unwrap(), data);
+        assert_eq!(WAD::validate_layout(&data).unwrap(), []);
+    }
+
+    #[test]
+    fn test_wad_peak_allocation() {
+        let contents: Vec<Vec<u8>> = (0..8u8).map(|i| vec![i; 256 * 1024]).collect();
+        let content_size: usize = contents.iter().map(|content| content.len()).sum();
+        let title = test_utils::build_title(&contents);
+        let expected = title.to_wad().unwrap().to_bytes().unwrap();
+        // Serializing the content region should only ever make a single copy of it.
+        let (region, peak) = test_utils::measure_peak_allocation(|| title.content.to_bytes().unwrap());
+        assert!(peak <= region.len(), "content region used {} bytes to build {} bytes", peak, region.len());
+        drop(region);
+        // Streaming a WAD out shouldn't need to copy its content again.
+        let wad = title.to_wad().unwrap();
+        let (_, peak) = test_utils::measure_peak_allocation(|| wad.write_to(&mut io::sink()).unwrap());
+        assert!(peak < content_size / 8, "writing the WAD allocated {} bytes", peak);
+        drop(wad);
+        // Writing a Title as a WAD shouldn't copy its contents at all, and building the WAD's data
+        // directly should only need the space for the output.
+        let (_, peak) = test_utils::measure_peak_allocation(|| title.write_wad(&mut io::sink()).unwrap());
+        assert!(peak < content_size / 8, "writing the Title as a WAD allocated {} bytes", peak);
+        let (data, peak) = test_utils::measure_peak_allocation(|| title.to_wad_bytes().unwrap());
+        assert!(peak < data.len() * 2, "building the WAD's data allocated {} bytes", peak);
+        assert_eq!(data, expected);
+    }
+
+    // Builds the data for a WAD with a single content.
+    fn build_wad_data() -> Vec<u8> {
+        let title = test_utils::build_title(&[vec![0x44; 256]]);
+        title.to_wad().unwrap().to_bytes().unwrap()
+    }
+
+    #[test]
+    fn test_wad_huge_section_size() {
+        let mut data = build_wad_data();
+        // Set the content size to 0xFFFFFFFF.
+        data[0x18..0x1C].copy_from_slice(&[0xFF; 4]);
+        let result = WAD::from_bytes(&data);
+        assert!(matches!(result, Err(WADError::SectionOutOfBounds { ref section, .. }) if section == "content"));
+        // Set the header size to 0xFFFFFFFF.
+        let mut data = build_wad_data();
+        data[0..4].copy_from_slice(&[0xFF; 4]);
+        let result = WAD::from_bytes(&data);
+        assert!(matches!(result, Err(WADError::SectionOutOfBounds { ref section, .. }) if section == "header"));
+    }
+
+    #[test]
+    fn test_wad_truncated() {
+        let data = build_wad_data();
+        // Every truncation of the WAD should fail gracefully, rather than panicking.
+        for len in (0..data.len()).step_by(61) {
+            assert!(WAD::from_bytes(&data[..len]).is_err());
+        }
+        assert!(matches!(WAD::from_bytes(&data[..40]), Err(WADError::SectionOutOfBounds { ref section, .. }) if section == "header"));
+    }
+
+    #[test]
+    fn test_wad_fuzzed_sizes() {
+        let data = build_wad_data();
+        // Overwrite each size field with a range of values, none of which should cause a panic.
+        for field in 0..6 {
+            for value in [0u32, 1, 63, 64, 0x1000, 0x7FFFFFFF, 0xFFFFFFC0, 0xFFFFFFFF] {
+                let mut fuzzed = data.clone();
+                let offset = 8 + field * 4;
+                fuzzed[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+                let _ = WAD::from_bytes(&fuzzed);
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_clean_wad() {
+        let data = build_wad_data();
+        let wad = WAD::from_bytes(&data).unwrap();
+        assert!(wad.validate().is_empty());
+        assert!(WAD::validate_layout(&data).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_validate_anomalies() {
+        let mut data = build_wad_data();
+        // Set the WAD version to 1, and put garbage in the padding after the header.
+        data[6..8].copy_from_slice(&1u16.to_be_bytes());
+        data[0x30] = 0xAA;
+        let mut wad = WAD::from_bytes(&data).unwrap();
+        assert!(wad.validate().contains(&WadAnomaly::WadVersion { expected: 0, actual: 1 }));
+        assert!(WAD::validate_layout(&data).unwrap().contains(&WadAnomaly::NonZeroPadding { section: String::from("header"), offset: 0x30 }));
+        // Extend the TMD with junk data so that its declared size no longer matches.
+        let mut tmd = wad.tmd().to_vec();
+        tmd.extend_from_slice(&[0u8; 4]);
+        wad.set_tmd(&tmd);
+        assert!(wad.validate().iter().any(|anomaly| matches!(anomaly, WadAnomaly::SectionSize { section, .. } if section == "TMD")));
+        // Drop a content's worth of data so the content size no longer matches the TMD.
+        wad.set_content(&[0u8; 64]).unwrap();
+        assert!(wad.validate().iter().any(|anomaly| matches!(anomaly, WadAnomaly::ContentSize { expected: 256, actual: 64 })));
+    }
+
+    #[test]
+    fn test_layout() {
+        let mut data = build_wad_data();
+        // The certificate chain is 0x900 bytes, the Ticket is 0x2A4 bytes, the TMD is 0x208 bytes,
+        // and the single content is 0x100 bytes, with no CRL or meta.
+        let expected = [
+            (WadSectionType::Header, 0x0, 0x20, 0x20),
+            (WadSectionType::CertChain, 0x40, 0x900, 0x0),
+            (WadSectionType::Crl, 0x940, 0x0, 0x0),
+            (WadSectionType::Ticket, 0x940, 0x2A4, 0x1C),
+            (WadSectionType::Tmd, 0xC00, 0x208, 0x38),
+            (WadSectionType::Content, 0xE40, 0x100, 0x0),
+            (WadSectionType::Meta, 0xF40, 0x0, 0x0),
+        ];
+        let layout = WAD::from_bytes(&data).unwrap().layout();
+        let actual: Vec<(WadSectionType, u64, u64, u64)> = layout.sections.iter()
+            .map(|entry| (entry.section, entry.offset, entry.size, entry.padding.size))
+            .collect();
+        assert_eq!(actual, expected);
+        assert!(layout.sections.iter().all(|entry| entry.padding.offset == entry.offset + entry.size && entry.padding.is_zero_filled()));
+        assert_eq!(layout.total_size, data.len() as u64);
+        // Garbage in the padding after the Ticket should be found, and kept until the Ticket is
+        // replaced.
+        data[0xBF0] = 0xAA;
+        let mut wad = WAD::from_bytes(&data).unwrap();
+        assert_eq!(wad.layout().section(WadSectionType::Ticket).unwrap().padding.non_zero_offset, Some(0xBF0));
+        assert_eq!(wad.layout().section(WadSectionType::Tmd).unwrap().padding.non_zero_offset, None);
+        wad.set_meta(&[0x5A; 0x10]);
+        let layout = wad.layout();
+        assert_eq!(layout.section(WadSectionType::Ticket).unwrap().padding.non_zero_offset, Some(0xBF0));
+        assert_eq!(layout.section(WadSectionType::Meta).unwrap().padding.size, 0x30);
+        assert_eq!(layout.total_size, wad.to_bytes().unwrap().len() as u64);
+        let ticket = wad.ticket().to_vec();
+        wad.set_ticket(&ticket);
+        assert!(wad.layout().sections.iter().all(|entry| entry.padding.is_zero_filled()));
+    }
+
+    #[test]
+    fn test_round_trip_exact() {
+        let clean = build_wad_data();
+        let mut title = test_utils::build_title(&[vec![0x11; 100], vec![0x22; 200], vec![0x33; 40]]);
+        title.content.set_alignment(16).unwrap();
+        let aligned_16 = title.to_wad().unwrap().to_bytes().unwrap();
+        // The content size is stored without the padding of the last content.
+        let mut unpadded = clean.clone();
+        unpadded[0x18..0x1C].copy_from_slice(&0xF8u32.to_be_bytes());
+        // An unofficial WAD version, and data stashed in the header's padding.
+        let mut header_quirks = clean.clone();
+        header_quirks[6..8].copy_from_slice(&1u16.to_be_bytes());
+        header_quirks[0x20..0x40].copy_from_slice(&[0xA5; 0x20]);
+        // A larger header, with its extra data past the first 64 bytes.
+        let mut large_header = clean[..0x40].to_vec();
+        large_header[0..4].copy_from_slice(&0x60u32.to_be_bytes());
+        large_header.extend_from_slice(&[0x77; 0x40]);
+        large_header.extend_from_slice(&clean[0x40..]);
+        // Non-zero padding after the Ticket, the TMD, and the meta.
+        let mut wad = WAD::from_bytes(&clean).unwrap();
+        wad.set_meta(&[0x5A; 0x10]);
+        let mut section_padding = wad.to_bytes().unwrap();
+        section_padding[0xBF0] = 0xAA;
+        section_padding[0xE3F] = 0xBB;
+        section_padding[0xF60] = 0xCC;
+        for data in [&clean, &aligned_16, &unpadded, &header_quirks, &large_header, &section_padding] {
+            let wad = WAD::from_bytes(data).unwrap();
+            assert!(wad.is_roundtrip_exact(data));
+            assert_eq!(wad.to_bytes().unwrap(), *data);
+            assert_eq!(wad.layout().total_size, data.len() as u64);
+            assert!(Title::from_wad(&wad).is_ok());
+        }
+        assert_eq!(WAD::from_bytes(&large_header).unwrap().layout().section(WadSectionType::CertChain).unwrap().offset, 0x80);
+        // Building a new WAD from the parsed components shouldn't carry over any of the padding.
+        let rebuilt = Title::from_wad(&WAD::from_bytes(&header_quirks).unwrap()).unwrap().to_wad().unwrap();
+        assert!(rebuilt.layout().sections.iter().all(|entry| entry.padding.is_zero_filled()));
+        assert_eq!(rebuilt.header.wad_version, 0);
+        // Data past the end of the last section, and padding cut off by truncation, can't be kept.
+        let mut trailing = clean.clone();
+        trailing.extend_from_slice(&[0xEE; 0x20]);
+        assert!(!WAD::from_bytes(&trailing).unwrap().is_roundtrip_exact(&trailing));
+        assert!(WAD::validate_layout(&trailing).unwrap().contains(&WadAnomaly::TrailingData { offset: 0xF40, size: 0x20 }));
+        let truncated = &section_padding[..0xF60];
+        assert!(!WAD::from_bytes(truncated).unwrap().is_roundtrip_exact(truncated));
+    }
+
+    #[test]
+    fn test_accessors_borrow_data() {
+        // Use a large content so that an accidental copy would be obvious, and make sure that the
+        // accessors hand out the WAD's own buffers rather than copies of them.
+        let title = test_utils::build_title(&[vec![0x33; 8 * 1024 * 1024]]);
+        let wad = WAD::from_bytes(&title.to_wad().unwrap().to_bytes().unwrap()).unwrap();
+        assert_eq!(wad.content().as_ptr(), wad.body.content.as_ptr());
+        assert_eq!(wad.content().len(), wad.content_size() as usize);
+        assert_eq!(wad.tmd().as_ptr(), wad.body.tmd.as_ptr());
+        assert_eq!(wad.ticket().as_ptr(), wad.body.ticket.as_ptr());
+        assert_eq!(wad.cert_chain().as_ptr(), wad.body.cert_chain.as_ptr());
+    }
+
+    #[test]
+    fn test_parsed_accessors() {
+        let title = test_utils::build_title(&[vec![0x55; 128]]);
+        let wad = WAD::from_bytes(&title.to_wad().unwrap().to_bytes().unwrap()).unwrap();
+        let tmd = wad.parsed_tmd().unwrap();
+        assert_eq!(tmd.to_bytes().unwrap(), wad.tmd());
+        assert_eq!(wad.parsed_ticket().unwrap().to_bytes().unwrap(), wad.ticket());
+        assert_eq!(wad.parsed_cert_chain().unwrap().to_bytes().unwrap(), wad.cert_chain());
+        // Changes to a returned TMD shouldn't leak back into the cached copy.
+        tmd.content_records.write().clear();
+        assert_eq!(wad.parsed_tmd().unwrap().num_contents(), 1);
+    }
+
+    #[test]
+    fn test_parsed_cache_invalidation() {
+        let title = test_utils::build_title(&[vec![0x66; 128]]);
+        let mut wad = title.to_wad().unwrap();
+        assert_eq!(wad.parsed_tmd().unwrap().title_version, 0);
+        assert_eq!(wad.parsed_ticket().unwrap().common_key_index(), 0);
+        // Replace the TMD and Ticket with modified copies, and make sure the new data is returned.
+        let mut tmd = wad.parsed_tmd().unwrap();
+        tmd.title_version = 513;
+        wad.set_tmd(&tmd.to_bytes().unwrap());
+        let mut ticket = wad.parsed_ticket().unwrap();
+        ticket.set_common_key_index(2).unwrap();
+        wad.set_ticket(&ticket.to_bytes().unwrap());
+        assert_eq!(wad.parsed_tmd().unwrap().title_version, 513);
+        assert_eq!(wad.parsed_ticket().unwrap().common_key_index(), 2);
+        // Invalid data should produce an error rather than the stale cached value.
+        wad.set_cert_chain(&[0u8; 16]);
+        assert!(wad.parsed_cert_chain().is_err());
+        let reloaded = Title::from_wad(&WAD::from_bytes(&wad.to_bytes().unwrap()).unwrap());
+        assert!(reloaded.is_err());
+        wad.set_cert_chain(&test_utils::build_cert_chain());
+        let reloaded = Title::from_wad(&WAD::from_bytes(&wad.to_bytes().unwrap()).unwrap()).unwrap();
+        assert_eq!(reloaded.tmd.title_version, 513);
+        assert_eq!(reloaded.ticket.common_key_index(), 2);
     }
 }